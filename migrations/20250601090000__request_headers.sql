@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS requestheader(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0,
+    request_id TEXT NOT NULL REFERENCES requestitem(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS requestheader_request_id ON requestheader(request_id);
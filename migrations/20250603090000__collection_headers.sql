@@ -0,0 +1,4 @@
+ALTER TABLE collectionheader ADD COLUMN enabled INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE collectionheader ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS collectionheader_collection_id ON collectionheader(collection_id);
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS requestbody(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    mode TEXT NOT NULL DEFAULT 'none',
+    content_type TEXT NOT NULL DEFAULT '',
+    payload TEXT NOT NULL DEFAULT '',
+    request_id TEXT NOT NULL UNIQUE REFERENCES requestitem(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS requestbodypart(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    is_file INTEGER NOT NULL DEFAULT 0,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0,
+    request_id TEXT NOT NULL REFERENCES requestitem(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS requestbodypart_request_id ON requestbodypart(request_id);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS environment(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    is_active INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS environmentvariable(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0,
+    environment_id TEXT NOT NULL REFERENCES environment(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS environmentvariable_environment_id ON environmentvariable(environment_id);
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS collectionvariable(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0,
+    collection_id TEXT NOT NULL REFERENCES collectionitem(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS collectionvariable_collection_id ON collectionvariable(collection_id);
+
+CREATE TABLE IF NOT EXISTS requestvariable(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0,
+    request_id TEXT NOT NULL REFERENCES requestitem(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS requestvariable_request_id ON requestvariable(request_id);
+
+CREATE TABLE IF NOT EXISTS globalvariable(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    value TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    position INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS authconfig(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    auth_type TEXT NOT NULL DEFAULT 'none',
+    username TEXT NOT NULL DEFAULT '',
+    key_name TEXT NOT NULL DEFAULT '',
+    placement TEXT NOT NULL DEFAULT 'header',
+    request_id TEXT UNIQUE REFERENCES requestitem(id) ON DELETE CASCADE,
+    collection_id TEXT UNIQUE REFERENCES collectionitem(id) ON DELETE CASCADE,
+    CHECK ((request_id IS NULL) <> (collection_id IS NULL))
+);
+
+-- Passwords, tokens and key values live apart from the plain auth settings
+-- so they can be masked and left out of exports.
+CREATE TABLE IF NOT EXISTS authsecret(
+    auth_id TEXT NOT NULL PRIMARY KEY REFERENCES authconfig(id) ON DELETE CASCADE,
+    value TEXT NOT NULL DEFAULT ''
+);
@@ -0,0 +1,30 @@
+-- OAuth 2.0 settings of an auth configuration with auth_type 'oauth2'. The
+-- password of the password grant is the auth secret, the client secret is
+-- kept next to it.
+CREATE TABLE IF NOT EXISTS oauth2config(
+    auth_id TEXT NOT NULL PRIMARY KEY REFERENCES authconfig(id) ON DELETE CASCADE,
+    grant_type TEXT NOT NULL DEFAULT 'client_credentials',
+    auth_url TEXT NOT NULL DEFAULT '',
+    token_url TEXT NOT NULL DEFAULT '',
+    client_id TEXT NOT NULL DEFAULT '',
+    scope TEXT NOT NULL DEFAULT '',
+    redirect_uri TEXT NOT NULL DEFAULT ''
+);
+
+ALTER TABLE authsecret ADD COLUMN client_secret TEXT NOT NULL DEFAULT '';
+
+-- Tokens obtained for an auth configuration, one per environment. Tokens
+-- obtained without an active environment have no environment_id.
+CREATE TABLE IF NOT EXISTS oauth2token(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    auth_id TEXT NOT NULL REFERENCES authconfig(id) ON DELETE CASCADE,
+    environment_id TEXT REFERENCES environment(id) ON DELETE CASCADE,
+    access_token TEXT NOT NULL,
+    refresh_token TEXT NOT NULL DEFAULT '',
+    token_type TEXT NOT NULL DEFAULT 'Bearer',
+    -- Unix timestamp in seconds, NULL when the server gave no lifetime.
+    expires_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS oauth2token_auth_id ON oauth2token(auth_id, environment_id);
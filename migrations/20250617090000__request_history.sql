@@ -0,0 +1,42 @@
+-- Every execution of a request, with what was sent after variables were
+-- applied but before auth was, so no credentials are stored, and what came
+-- back or why it failed. The request, collection and environment may be
+-- deleted later, so their names are copied and the references are cleared
+-- instead of removing the history.
+CREATE TABLE IF NOT EXISTS requesthistory(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    request_id TEXT REFERENCES requestitem(id) ON DELETE SET NULL,
+    request_name TEXT NOT NULL DEFAULT '',
+    collection_id TEXT REFERENCES collectionitem(id) ON DELETE SET NULL,
+    environment_id TEXT REFERENCES environment(id) ON DELETE SET NULL,
+    environment_name TEXT NOT NULL DEFAULT '',
+    http_method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    -- Headers are stored as a JSON array of [name, value] pairs.
+    request_headers TEXT NOT NULL DEFAULT '[]',
+    request_body BLOB NOT NULL DEFAULT x'',
+    request_body_truncated INTEGER NOT NULL DEFAULT 0,
+    -- NULL when no response was received, see error.
+    status INTEGER,
+    status_text TEXT NOT NULL DEFAULT '',
+    response_headers TEXT NOT NULL DEFAULT '[]',
+    response_body BLOB NOT NULL DEFAULT x'',
+    response_body_truncated INTEGER NOT NULL DEFAULT 0,
+    response_size INTEGER NOT NULL DEFAULT 0,
+    error TEXT NOT NULL DEFAULT '',
+    headers_received_ms INTEGER NOT NULL DEFAULT 0,
+    total_ms INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX IF NOT EXISTS requesthistory_created_at ON requesthistory(created_at);
+CREATE INDEX IF NOT EXISTS requesthistory_request_id ON requesthistory(request_id);
+
+-- How much history is kept. Zero means no limit.
+CREATE TABLE IF NOT EXISTS historysettings(
+    id INTEGER NOT NULL PRIMARY KEY CHECK (id = 1),
+    max_entries INTEGER NOT NULL DEFAULT 1000,
+    max_age_days INTEGER NOT NULL DEFAULT 30
+);
+
+INSERT OR IGNORE INTO historysettings (id) VALUES (1);
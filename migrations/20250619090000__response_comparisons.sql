@@ -0,0 +1,10 @@
+-- Two recorded executions compared side by side. A comparison goes away
+-- with either of its history entries, and the history retention limits
+-- leave compared entries alone.
+CREATE TABLE IF NOT EXISTS responsecomparison(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    left_id TEXT NOT NULL REFERENCES requesthistory(id) ON DELETE CASCADE,
+    right_id TEXT NOT NULL REFERENCES requesthistory(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+-- Folder a request was imported into. Nested folders are separated by " / ",
+-- requests outside of any folder have an empty folder.
+ALTER TABLE requestitem ADD COLUMN folder TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,19 @@
+-- Connection settings of a WebSocket request. Handshake headers are the
+-- request's own headers; subprotocols are offered comma separated.
+CREATE TABLE IF NOT EXISTS websocketconfig(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    subprotocols TEXT NOT NULL DEFAULT ''
+);
+
+-- Messages saved with a WebSocket request to be sent again later. The
+-- format is text, json or binary, with binary payloads written as hex.
+CREATE TABLE IF NOT EXISTS websockettemplate(
+    id TEXT NOT NULL PRIMARY KEY,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    name TEXT NOT NULL DEFAULT '',
+    format TEXT NOT NULL DEFAULT 'text',
+    payload TEXT NOT NULL DEFAULT '',
+    request_id TEXT NOT NULL REFERENCES requestitem(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS websockettemplate_request_id ON websockettemplate(request_id);
@@ -0,0 +1,16 @@
+-- The operation a GraphQL request sends. It takes the place of the body;
+-- variables are JSON, kept as typed so they may use {{variables}}.
+CREATE TABLE IF NOT EXISTS graphqlquery(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    query TEXT NOT NULL DEFAULT '',
+    variables TEXT NOT NULL DEFAULT '',
+    operation_name TEXT NOT NULL DEFAULT ''
+);
+
+-- The last schema introspected from the endpoint of a GraphQL request, as
+-- the __schema object of the introspection result.
+CREATE TABLE IF NOT EXISTS graphqlschema(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    schema TEXT NOT NULL,
+    fetched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,20 @@
+-- The call a gRPC request makes. Services are described by .proto files or
+-- asked from the server by reflection; metadata is the request's own
+-- headers. Paths are one per line, the method reads like
+-- package.Service/Method and the message is JSON.
+CREATE TABLE IF NOT EXISTS grpccall(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    source TEXT NOT NULL DEFAULT 'reflection',
+    proto_files TEXT NOT NULL DEFAULT '',
+    import_paths TEXT NOT NULL DEFAULT '',
+    method TEXT NOT NULL DEFAULT '',
+    message TEXT NOT NULL DEFAULT ''
+);
+
+-- The services last loaded for a gRPC request, as an encoded
+-- FileDescriptorSet.
+CREATE TABLE IF NOT EXISTS grpcdescriptors(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    descriptors BLOB NOT NULL,
+    loaded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,9 @@
+-- Stream settings of an SSE request. The method, headers and body are the
+-- request's own. The last event id is sent as Last-Event-ID so the next
+-- connection picks up where the last one stopped; reconnect is whether a
+-- stream the server ends is opened again.
+CREATE TABLE IF NOT EXISTS sseconfig(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    last_event_id TEXT NOT NULL DEFAULT '',
+    reconnect INTEGER NOT NULL DEFAULT 1
+);
@@ -0,0 +1,29 @@
+-- How a Socket.IO request talks to its server. The namespace is the path of
+-- the request URL, the path here is where the server mounts Socket.IO.
+-- Listeners are event names, one per line, all events when empty. The
+-- event, payload and ack are what the next emit sends; the payload is a
+-- single JSON argument.
+CREATE TABLE IF NOT EXISTS socketioconfig(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    path TEXT NOT NULL DEFAULT '/socket.io/',
+    listeners TEXT NOT NULL DEFAULT '',
+    event TEXT NOT NULL DEFAULT '',
+    payload TEXT NOT NULL DEFAULT '',
+    ack INTEGER NOT NULL DEFAULT 0
+);
+
+-- How an MQTT request talks to its broker. Credentials are the Basic auth
+-- of the request. Topic filters are one per line, subscribed to with the
+-- same QoS; topic, payload, QoS and retain are what the next publish sends.
+CREATE TABLE IF NOT EXISTS mqttconfig(
+    request_id TEXT NOT NULL PRIMARY KEY REFERENCES requestitem(id) ON DELETE CASCADE,
+    client_id TEXT NOT NULL DEFAULT '',
+    clean_session INTEGER NOT NULL DEFAULT 1,
+    keep_alive INTEGER NOT NULL DEFAULT 60,
+    filters TEXT NOT NULL DEFAULT '',
+    subscribe_qos INTEGER NOT NULL DEFAULT 0,
+    topic TEXT NOT NULL DEFAULT '',
+    payload TEXT NOT NULL DEFAULT '',
+    publish_qos INTEGER NOT NULL DEFAULT 0,
+    retain INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,594 @@
+use std::error::Error;
+
+use slint::ComponentHandle;
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::environments::refresh_unresolved_variables,
+    utils::{
+        crud::{
+            auth::{
+                get_auth_secret, get_collection_auth, get_request_auth, update_auth_secret,
+                update_collection_auth, update_request_auth, ApiKeyPlacement, AuthData, AuthTypes,
+            },
+            environments::get_active_environment,
+            oauth2::{
+                delete_oauth2_tokens, get_oauth2_client_secret, get_oauth2_config,
+                get_oauth2_token, update_oauth2_client_secret, update_oauth2_config,
+                OAuth2ConfigData, OAuth2GrantTypes,
+            },
+            requests::get_single_request,
+        },
+        executor::{
+            auth::PreparedAuth,
+            oauth2::{is_token_valid, new_access_token, unix_time, OAuth2Settings},
+        },
+        variables::{UnresolvedVariables, VariableResolver},
+    },
+    AppConfig, AppWindow,
+};
+
+/// Describe the token cached for an auth configuration in the active environment.
+async fn token_status(auth_id: &str, db: &SqlitePool) -> Result<String, Box<dyn Error>> {
+    let environment_id = get_active_environment(db).await?.map(|data| data.id);
+    let status = match get_oauth2_token(auth_id, environment_id.as_deref(), db).await? {
+        None => "No token yet, one is requested when a request is sent.".to_string(),
+        Some(token) if !is_token_valid(&token, unix_time()) => {
+            "Token expired, it is renewed when a request is sent.".to_string()
+        }
+        Some(token) => match token.expires_at {
+            Some(expires_at) => format!(
+                "Token valid for {} more minutes.",
+                (expires_at - unix_time()) / 60
+            ),
+            None => "Token cached without expiry.".to_string(),
+        },
+    };
+
+    Ok(status)
+}
+
+/// OAuth 2.0 settings and token status of an auth configuration, with defaults when there are none.
+async fn oauth2_details(
+    auth_id: Option<&str>,
+    db: &SqlitePool,
+) -> Result<(OAuth2ConfigData, String, String), Box<dyn Error>> {
+    let auth_id = match auth_id {
+        Some(auth_id) => auth_id,
+        None => return Ok((OAuth2ConfigData::default(), String::new(), String::new())),
+    };
+    let config = get_oauth2_config(auth_id, db).await?.unwrap_or_default();
+    let client_secret = get_oauth2_client_secret(auth_id, db).await?;
+
+    Ok((config, client_secret, token_status(auth_id, db).await?))
+}
+
+fn grant_type_or_default(grant_type: &str) -> String {
+    OAuth2GrantTypes::from_string(grant_type)
+        .unwrap_or(OAuth2GrantTypes::ClientCredentials)
+        .to_string()
+}
+
+fn show_request_oauth2(cfg: &AppConfig, details: (OAuth2ConfigData, String, String)) {
+    let (config, client_secret, status) = details;
+    cfg.set_active_request_oauth2_grant_type(grant_type_or_default(&config.grant_type).into());
+    cfg.set_active_request_oauth2_auth_url(config.auth_url.into());
+    cfg.set_active_request_oauth2_token_url(config.token_url.into());
+    cfg.set_active_request_oauth2_client_id(config.client_id.into());
+    cfg.set_active_request_oauth2_client_secret(client_secret.into());
+    cfg.set_active_request_oauth2_scope(config.scope.into());
+    cfg.set_active_request_oauth2_redirect_uri(config.redirect_uri.into());
+    cfg.set_active_request_oauth2_token_status(status.into());
+}
+
+fn show_collection_oauth2(cfg: &AppConfig, details: (OAuth2ConfigData, String, String)) {
+    let (config, client_secret, status) = details;
+    cfg.set_active_collection_oauth2_grant_type(grant_type_or_default(&config.grant_type).into());
+    cfg.set_active_collection_oauth2_auth_url(config.auth_url.into());
+    cfg.set_active_collection_oauth2_token_url(config.token_url.into());
+    cfg.set_active_collection_oauth2_client_id(config.client_id.into());
+    cfg.set_active_collection_oauth2_client_secret(client_secret.into());
+    cfg.set_active_collection_oauth2_scope(config.scope.into());
+    cfg.set_active_collection_oauth2_redirect_uri(config.redirect_uri.into());
+    cfg.set_active_collection_oauth2_token_status(status.into());
+}
+
+/// Save OAuth 2.0 settings and forget the tokens obtained with the previous ones.
+#[allow(clippy::too_many_arguments)]
+async fn save_oauth2_settings(
+    auth: &AuthData,
+    grant_type: &str,
+    auth_url: &str,
+    token_url: &str,
+    client_id: &str,
+    client_secret: &str,
+    scope: &str,
+    redirect_uri: &str,
+    db: &SqlitePool,
+) -> Result<String, Box<dyn Error>> {
+    update_oauth2_config(
+        &auth.id,
+        OAuth2GrantTypes::from_string(grant_type).unwrap_or(OAuth2GrantTypes::ClientCredentials),
+        auth_url,
+        token_url,
+        client_id,
+        scope,
+        redirect_uri,
+        db,
+    )
+    .await?;
+    update_oauth2_client_secret(&auth.id, client_secret, db).await?;
+    delete_oauth2_tokens(&auth.id, db).await?;
+
+    token_status(&auth.id, db).await
+}
+
+/// Request a new token with variables substituted in the settings, and describe it.
+async fn request_new_token(
+    settings: OAuth2Settings,
+    variables: VariableResolver,
+    db: &SqlitePool,
+) -> Result<String, Box<dyn Error>> {
+    let mut unresolved = Vec::new();
+    let settings = settings.substitute_variables(&variables, &mut unresolved);
+    if !unresolved.is_empty() {
+        return Err(UnresolvedVariables(unresolved).into());
+    }
+
+    let token = new_access_token(&settings, db).await?;
+    token_status(&token.auth_id, db).await
+}
+
+/// Show the auth settings of a request in the request pane.
+///
+/// A request without saved settings inherits the auth of its collection.
+pub async fn load_request_auth(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    match get_request_auth(request_id, db).await? {
+        Some(auth) => {
+            let secret = get_auth_secret(&auth.id, db).await?;
+            cfg.set_active_request_auth_type(auth.auth_type.into());
+            cfg.set_active_request_auth_username(auth.username.into());
+            cfg.set_active_request_auth_key_name(auth.key_name.into());
+            cfg.set_active_request_auth_placement(auth.placement.into());
+            cfg.set_active_request_auth_secret(secret.into());
+            show_request_oauth2(cfg, oauth2_details(Some(&auth.id), db).await?);
+        }
+        None => {
+            cfg.set_active_request_auth_type(AuthTypes::Inherit.to_string().into());
+            cfg.set_active_request_auth_username("".into());
+            cfg.set_active_request_auth_key_name("".into());
+            cfg.set_active_request_auth_placement(ApiKeyPlacement::Header.to_string().into());
+            cfg.set_active_request_auth_secret("".into());
+            show_request_oauth2(cfg, oauth2_details(None, db).await?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Save the auth settings of a request edited in its Auth tab.
+pub async fn process_update_request_auth(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_auth(
+        move |request_id, auth_type, username, key_name, placement, secret| {
+            let weak_app_for_task = weak_app.clone();
+            let db_copy_for_task = db_copy.clone();
+
+            let _ = slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let auth = match update_request_auth(
+                    &request_id,
+                    AuthTypes::from_string(&auth_type).unwrap_or(AuthTypes::Inherit),
+                    &username,
+                    &key_name,
+                    ApiKeyPlacement::from_string(&placement).unwrap_or(ApiKeyPlacement::Header),
+                    &db_copy_for_task,
+                )
+                .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating auth  - {}", error);
+                        return;
+                    }
+                };
+
+                if let Err(error) = update_auth_secret(&auth.id, &secret, &db_copy_for_task).await {
+                    eprintln!("Error updating auth secret  - {}", error);
+                    return;
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+        },
+    );
+
+    Ok(())
+}
+
+/// Show the auth settings of a collection in the collection settings.
+pub async fn process_get_collection_auth(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_collection_auth(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let auth = match get_collection_auth(&collection_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting auth  - {}", error);
+                    return;
+                }
+            };
+
+            match auth {
+                Some(auth) => {
+                    let secret = match get_auth_secret(&auth.id, &db_copy_for_task).await {
+                        Ok(data) => data,
+                        Err(error) => {
+                            eprintln!("Error getting auth secret  - {}", error);
+                            return;
+                        }
+                    };
+                    cfg.set_active_collection_auth_type(auth.auth_type.into());
+                    cfg.set_active_collection_auth_username(auth.username.into());
+                    cfg.set_active_collection_auth_key_name(auth.key_name.into());
+                    cfg.set_active_collection_auth_placement(auth.placement.into());
+                    cfg.set_active_collection_auth_secret(secret.into());
+                    match oauth2_details(Some(&auth.id), &db_copy_for_task).await {
+                        Ok(details) => show_collection_oauth2(&cfg, details),
+                        Err(error) => eprintln!("Error getting OAuth 2.0 settings  - {}", error),
+                    }
+                }
+                None => {
+                    cfg.set_active_collection_auth_type(AuthTypes::None.to_string().into());
+                    cfg.set_active_collection_auth_username("".into());
+                    cfg.set_active_collection_auth_key_name("".into());
+                    cfg.set_active_collection_auth_placement(
+                        ApiKeyPlacement::Header.to_string().into(),
+                    );
+                    cfg.set_active_collection_auth_secret("".into());
+                    show_collection_oauth2(
+                        &cfg,
+                        (OAuth2ConfigData::default(), String::new(), String::new()),
+                    );
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Save the auth settings shared by the requests of a collection.
+pub async fn process_update_collection_auth(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_collection_auth(
+        move |collection_id, auth_type, username, key_name, placement, secret| {
+            let weak_app_for_task = weak_app.clone();
+            let db_copy_for_task = db_copy.clone();
+
+            let _ = slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let auth = match update_collection_auth(
+                    &collection_id,
+                    AuthTypes::from_string(&auth_type).unwrap_or(AuthTypes::None),
+                    &username,
+                    &key_name,
+                    ApiKeyPlacement::from_string(&placement).unwrap_or(ApiKeyPlacement::Header),
+                    &db_copy_for_task,
+                )
+                .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating auth  - {}", error);
+                        return;
+                    }
+                };
+
+                if let Err(error) = update_auth_secret(&auth.id, &secret, &db_copy_for_task).await {
+                    eprintln!("Error updating auth secret  - {}", error);
+                    return;
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+        },
+    );
+
+    Ok(())
+}
+
+/// Save the OAuth 2.0 settings of a request edited in its Auth tab.
+pub async fn process_update_request_oauth2(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_oauth2(
+        move |request_id,
+              grant_type,
+              auth_url,
+              token_url,
+              client_id,
+              client_secret,
+              scope,
+              redirect_uri| {
+            let weak_app_for_task = weak_app.clone();
+            let db_copy_for_task = db_copy.clone();
+
+            let _ = slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let auth = match get_request_auth(&request_id, &db_copy_for_task).await {
+                    Ok(Some(data)) => data,
+                    Ok(None) => match update_request_auth(
+                        &request_id,
+                        AuthTypes::OAuth2,
+                        "",
+                        "",
+                        ApiKeyPlacement::Header,
+                        &db_copy_for_task,
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(error) => {
+                            eprintln!("Error updating auth  - {}", error);
+                            return;
+                        }
+                    },
+                    Err(error) => {
+                        eprintln!("Error getting auth  - {}", error);
+                        return;
+                    }
+                };
+
+                match save_oauth2_settings(
+                    &auth,
+                    &grant_type,
+                    &auth_url,
+                    &token_url,
+                    &client_id,
+                    &client_secret,
+                    &scope,
+                    &redirect_uri,
+                    &db_copy_for_task,
+                )
+                .await
+                {
+                    Ok(status) => cfg.set_active_request_oauth2_token_status(status.into()),
+                    Err(error) => {
+                        eprintln!("Error updating OAuth 2.0 settings  - {}", error);
+                        return;
+                    }
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+        },
+    );
+
+    Ok(())
+}
+
+/// Request a new OAuth 2.0 token for a request, replacing the cached one.
+pub async fn process_get_request_oauth2_token(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_request_oauth2_token(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+            let settings = match PreparedAuth::for_request(&request, &db_copy_for_task).await {
+                Ok(PreparedAuth::OAuth2(settings)) => settings,
+                Ok(_) => return,
+                Err(error) => {
+                    eprintln!("Error getting auth  - {}", error);
+                    return;
+                }
+            };
+            let variables = match VariableResolver::for_request(&request, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting variables  - {}", error);
+                    return;
+                }
+            };
+
+            cfg.set_active_request_oauth2_token_status("Requesting a token...".into());
+            let status = match request_new_token(settings, variables, &db_copy_for_task).await {
+                Ok(status) => status,
+                Err(error) => error.to_string(),
+            };
+            cfg.set_active_request_oauth2_token_status(status.into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save the OAuth 2.0 settings shared by the requests of a collection.
+pub async fn process_update_collection_oauth2(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_collection_oauth2(
+        move |collection_id,
+              grant_type,
+              auth_url,
+              token_url,
+              client_id,
+              client_secret,
+              scope,
+              redirect_uri| {
+            let weak_app_for_task = weak_app.clone();
+            let db_copy_for_task = db_copy.clone();
+
+            let _ = slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let auth = match get_collection_auth(&collection_id, &db_copy_for_task).await {
+                    Ok(Some(data)) => data,
+                    Ok(None) => match update_collection_auth(
+                        &collection_id,
+                        AuthTypes::OAuth2,
+                        "",
+                        "",
+                        ApiKeyPlacement::Header,
+                        &db_copy_for_task,
+                    )
+                    .await
+                    {
+                        Ok(data) => data,
+                        Err(error) => {
+                            eprintln!("Error updating auth  - {}", error);
+                            return;
+                        }
+                    },
+                    Err(error) => {
+                        eprintln!("Error getting auth  - {}", error);
+                        return;
+                    }
+                };
+
+                match save_oauth2_settings(
+                    &auth,
+                    &grant_type,
+                    &auth_url,
+                    &token_url,
+                    &client_id,
+                    &client_secret,
+                    &scope,
+                    &redirect_uri,
+                    &db_copy_for_task,
+                )
+                .await
+                {
+                    Ok(status) => cfg.set_active_collection_oauth2_token_status(status.into()),
+                    Err(error) => {
+                        eprintln!("Error updating OAuth 2.0 settings  - {}", error);
+                        return;
+                    }
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+        },
+    );
+
+    Ok(())
+}
+
+/// Request a new OAuth 2.0 token for a collection, replacing the cached one.
+pub async fn process_get_collection_oauth2_token(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_collection_oauth2_token(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let auth = match get_collection_auth(&collection_id, &db_copy_for_task).await {
+                Ok(Some(data)) if data.auth_type == AuthTypes::OAuth2.to_string() => data,
+                Ok(_) => return,
+                Err(error) => {
+                    eprintln!("Error getting auth  - {}", error);
+                    return;
+                }
+            };
+            let password = match get_auth_secret(&auth.id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting auth secret  - {}", error);
+                    return;
+                }
+            };
+            let settings = match OAuth2Settings::for_auth(&auth, password, &db_copy_for_task).await
+            {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting OAuth 2.0 settings  - {}", error);
+                    return;
+                }
+            };
+            let variables =
+                match VariableResolver::for_collection(&collection_id, &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error getting variables  - {}", error);
+                        return;
+                    }
+                };
+
+            cfg.set_active_collection_oauth2_token_status("Requesting a token...".into());
+            let status = match request_new_token(settings, variables, &db_copy_for_task).await {
+                Ok(status) => status,
+                Err(error) => error.to_string(),
+            };
+            cfg.set_active_collection_oauth2_token_status(status.into());
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::environments::refresh_unresolved_variables,
+    utils::crud::bodies::{
+        create_body_part, delete_body_part, get_body_parts, get_request_body, update_body_part,
+        update_request_body, BodyModes, BodyPartData,
+    },
+    AppConfig, AppWindow, BodyPartItem,
+};
+
+pub fn to_body_part_item(part: BodyPartData) -> BodyPartItem {
+    BodyPartItem {
+        id: part.id.into(),
+        name: part.name.into(),
+        value: part.value.into(),
+        is_file: part.is_file,
+        enabled: part.enabled,
+    }
+}
+
+/// Show the body of a request in the request pane.
+pub async fn load_request_body(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    match get_request_body(request_id, db).await? {
+        Some(body) => {
+            cfg.set_active_request_body_mode(body.mode.into());
+            cfg.set_active_request_body_content_type(body.content_type.into());
+            cfg.set_active_request_body_payload(body.payload.into());
+        }
+        None => {
+            cfg.set_active_request_body_mode(BodyModes::None.to_string().into());
+            cfg.set_active_request_body_content_type("".into());
+            cfg.set_active_request_body_payload("".into());
+        }
+    }
+
+    let parts: Vec<BodyPartItem> = get_body_parts(request_id, db)
+        .await?
+        .into_iter()
+        .map(to_body_part_item)
+        .collect();
+    cfg.set_active_request_body_parts(Rc::new(VecModel::from(parts)).into());
+
+    Ok(())
+}
+
+/// Save the body edited in the body editor.
+pub async fn process_update_request_body(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_body(move |request_id, mode, content_type, payload| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let mode = BodyModes::from_string(&mode).unwrap_or(BodyModes::None);
+            if let Err(error) = update_request_body(
+                &request_id,
+                mode,
+                &content_type,
+                &payload,
+                &db_copy_for_task,
+            )
+            .await
+            {
+                eprintln!("Error updating body  - {}", error);
+                return;
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty field to a form body.
+pub async fn process_create_body_part(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_body_part(move |request_id, is_file| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let part = match create_body_part(&request_id, "", "", is_file, &db_copy_for_task).await
+            {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error creating body field  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<BodyPartItem> = cfg.get_active_request_body_parts().iter().collect();
+            items.push(to_body_part_item(part));
+            cfg.set_active_request_body_parts(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a form field edited in the body editor.
+pub async fn process_update_body_part(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_body_part(move |id, name, value, is_file, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let part =
+                match update_body_part(&id, &name, &value, is_file, enabled, &db_copy_for_task)
+                    .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating body field  - {}", error);
+                        return;
+                    }
+                };
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_active_request_body_parts();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, to_body_part_item(part));
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a field from a form body.
+pub async fn process_remove_body_part(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_body_part(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            match delete_body_part(&id, &db_copy_for_task).await {
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("Error deleting body field  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<BodyPartItem> = cfg.get_active_request_body_parts().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_request_body_parts(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Open a native file picker.
+pub fn process_pick_file(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_pick_file(|| {
+        rfd::FileDialog::new()
+            .pick_file()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default()
+            .into()
+    });
+
+    Ok(())
+}
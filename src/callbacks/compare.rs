@@ -0,0 +1,382 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    utils::{
+        compare::{comparison_name, prepare_comparison, Comparison, ComparisonSide},
+        crud::{
+            comparisons::{
+                create_comparison, delete_comparison, get_comparison, get_comparisons,
+                ComparisonData,
+            },
+            history::{
+                get_history_entry, get_request_history, HistoryEntryData, HistorySummaryData,
+            },
+            requests::get_single_request,
+        },
+        executor::send_request_to,
+        response_view::format_size,
+    },
+    AppConfig, AppWindow, CompareChangeItem, CompareRowItem, CompareSideItem, ComparisonItem,
+    ComparisonTabItem,
+};
+
+/// How many past responses of a request the compare dialog offers.
+const COMPARE_HISTORY_LIMIT: i64 = 50;
+
+fn to_side_item(side: ComparisonSide) -> CompareSideItem {
+    CompareSideItem {
+        label: side.label.into(),
+        status: side.status.unwrap_or(0) as i32,
+        status_text: side.status_text.into(),
+        error: side.error.into(),
+        elapsed_ms: side.total_ms as i32,
+        size_text: format_size(side.size).into(),
+        truncated: side.body_truncated,
+    }
+}
+
+fn timing_text(difference: i64) -> String {
+    match difference {
+        0 => "Both took as long".to_string(),
+        difference if difference > 0 => format!("Right took {} ms longer", difference),
+        difference => format!("Right took {} ms less", -difference),
+    }
+}
+
+fn to_comparison_item(comparison_data: &ComparisonData, comparison: Comparison) -> ComparisonItem {
+    let timing_difference = timing_text(comparison.timing_difference());
+    let body_rows: Vec<CompareRowItem> = comparison
+        .body_rows
+        .into_iter()
+        .map(|row| {
+            let (left_number, left_text) = row
+                .left
+                .map(|(number, text)| (number.to_string(), text))
+                .unwrap_or_default();
+            let (right_number, right_text) = row
+                .right
+                .map(|(number, text)| (number.to_string(), text))
+                .unwrap_or_default();
+            CompareRowItem {
+                kind: row.kind.to_string().into(),
+                left_number: left_number.into(),
+                left_text: left_text.into(),
+                right_number: right_number.into(),
+                right_text: right_text.into(),
+            }
+        })
+        .collect();
+    let changes: Vec<CompareChangeItem> = comparison
+        .json_changes
+        .into_iter()
+        .map(|change| CompareChangeItem {
+            path: change.path.into(),
+            kind: change.kind.to_string().into(),
+            left: change.left.unwrap_or_default().into(),
+            right: change.right.unwrap_or_default().into(),
+        })
+        .collect();
+    let headers: Vec<CompareChangeItem> = comparison
+        .headers
+        .into_iter()
+        .map(|header| CompareChangeItem {
+            path: header.name.into(),
+            kind: header.kind.to_string().into(),
+            left: header.left.unwrap_or_default().into(),
+            right: header.right.unwrap_or_default().into(),
+        })
+        .collect();
+
+    ComparisonItem {
+        id: comparison_data.id.clone().into(),
+        name: comparison_data.name.clone().into(),
+        left: to_side_item(comparison.left),
+        right: to_side_item(comparison.right),
+        json: comparison.json,
+        identical_bodies: comparison.identical_bodies,
+        timing_difference: timing_difference.into(),
+        body_rows: Rc::new(VecModel::from(body_rows)).into(),
+        changes: Rc::new(VecModel::from(changes)).into(),
+        headers: Rc::new(VecModel::from(headers)).into(),
+    }
+}
+
+fn history_label(entry: &HistorySummaryData) -> String {
+    let status = match entry.status {
+        Some(status) => status.to_string(),
+        None => "Failed".to_string(),
+    };
+    format!(
+        "{} · {} · {} ms · {}",
+        entry.created_at, status, entry.total_ms, entry.url
+    )
+}
+
+/// Refresh the comparison tabs, leaving a comparison that no longer exists.
+pub async fn show_comparison_tabs(
+    cfg: &AppConfig<'_>,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let tabs: Vec<ComparisonTabItem> = get_comparisons(db)
+        .await?
+        .into_iter()
+        .map(|comparison| ComparisonTabItem {
+            id: comparison.id.into(),
+            name: comparison.name.into(),
+        })
+        .collect();
+
+    let active_id = cfg.get_active_comparison_id();
+    if !tabs.iter().any(|tab| tab.id == active_id) {
+        cfg.set_active_comparison_id("".into());
+    }
+    cfg.set_comparison_tabs(Rc::new(VecModel::from(tabs)).into());
+    Ok(())
+}
+
+async fn show_comparison(
+    cfg: &AppConfig<'_>,
+    comparison_data: &ComparisonData,
+    left: HistoryEntryData,
+    right: HistoryEntryData,
+) -> Result<(), Box<dyn Error>> {
+    let comparison = prepare_comparison(left, right).await?;
+
+    cfg.set_active_comparison(to_comparison_item(comparison_data, comparison));
+    cfg.set_active_comparison_id(comparison_data.id.clone().into());
+    Ok(())
+}
+
+async fn open_comparison(
+    cfg: &AppConfig<'_>,
+    id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let comparison_data = get_comparison(id, db).await?;
+    let left = get_history_entry(&comparison_data.left_id, db).await?;
+    let right = get_history_entry(&comparison_data.right_id, db).await?;
+
+    show_comparison(cfg, &comparison_data, left, right).await
+}
+
+/// Save a comparison of two history entries and open it in a new tab.
+async fn compare_entries(
+    cfg: &AppConfig<'_>,
+    left_id: &str,
+    right_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let left = get_history_entry(left_id, db).await?;
+    let right = get_history_entry(right_id, db).await?;
+    let name = comparison_name(&left.request_name, &left.url, &right.url);
+    let comparison_data = create_comparison(&name, left_id, right_id, db).await?;
+
+    show_comparison_tabs(cfg, db).await?;
+    show_comparison(cfg, &comparison_data, left, right).await?;
+    cfg.set_show_compare_dialog(false);
+    Ok(())
+}
+
+/// Send a request to two URLs, one after the other, and compare the responses.
+async fn compare_urls(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    left_url: &str,
+    right_url: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+
+    let mut history_ids = Vec::new();
+    for url in [left_url, right_url] {
+        match send_request_to(&request, url, db).await?.history_id {
+            Some(history_id) => history_ids.push(history_id),
+            None => return Err("The response to compare could not be saved.".into()),
+        }
+    }
+    cfg.invoke_get_history(cfg.get_history_search());
+
+    compare_entries(cfg, &history_ids[0], &history_ids[1], db).await
+}
+
+pub async fn load_comparisons(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    show_comparison_tabs(&config, db).await
+}
+
+/// Fill the compare dialog for a request and show it.
+pub async fn process_open_compare_dialog(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_open_compare_dialog(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let entries =
+                match get_request_history(&request_id, COMPARE_HISTORY_LIMIT, &db_copy_for_task)
+                    .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error getting request history  - {}", error);
+                        return;
+                    }
+                };
+            let labels: Vec<SharedString> = entries
+                .iter()
+                .map(|entry| history_label(entry).into())
+                .collect();
+            let ids: Vec<SharedString> = entries.into_iter().map(|entry| entry.id.into()).collect();
+
+            // Start from the URL being edited, the one most likely to be compared.
+            if cfg.get_compare_request_id() != request_id || cfg.get_compare_left_url() == "" {
+                cfg.set_compare_left_url(cfg.get_active_request_url());
+                cfg.set_compare_right_url("".into());
+            }
+            cfg.set_compare_request_id(request_id);
+            cfg.set_compare_history_labels(Rc::new(VecModel::from(labels)).into());
+            cfg.set_compare_history_ids(Rc::new(VecModel::from(ids)).into());
+            cfg.set_compare_error("".into());
+            cfg.set_show_compare_dialog(true);
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_compare_urls(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_compare_urls(move |request_id, left_url, right_url| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            cfg.set_comparing(true);
+            cfg.set_compare_error("".into());
+            if let Err(error) = compare_urls(
+                &cfg,
+                &request_id,
+                left_url.trim(),
+                right_url.trim(),
+                &db_copy_for_task,
+            )
+            .await
+            {
+                eprintln!("Error comparing URLs  - {}", error);
+                cfg.set_compare_error(error.to_string().into());
+            }
+            cfg.set_comparing(false);
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_compare_history_entries(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_compare_history_entries(move |left_id, right_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            cfg.set_comparing(true);
+            cfg.set_compare_error("".into());
+            if let Err(error) = compare_entries(&cfg, &left_id, &right_id, &db_copy_for_task).await
+            {
+                eprintln!("Error comparing responses  - {}", error);
+                cfg.set_compare_error(error.to_string().into());
+            }
+            cfg.set_comparing(false);
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_select_comparison(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_select_comparison(move |id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = open_comparison(&cfg, &id, &db_copy_for_task).await {
+                eprintln!("Error opening comparison  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Close a comparison tab. Comparisons only live in their tab, so this deletes it.
+pub async fn process_close_comparison(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_close_comparison(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_comparison(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting comparison  - {}", error);
+                return;
+            }
+
+            let mut tabs: Vec<ComparisonTabItem> = cfg.get_comparison_tabs().iter().collect();
+            if tabs.get(index as usize).is_some() {
+                tabs.remove(index as usize);
+            }
+            cfg.set_comparison_tabs(Rc::new(VecModel::from(tabs)).into());
+            if cfg.get_active_comparison_id() == id {
+                cfg.set_active_comparison_id("".into());
+            }
+        });
+    });
+
+    Ok(())
+}
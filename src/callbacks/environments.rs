@@ -0,0 +1,398 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    utils::{
+        crud::{
+            environments::{
+                create_environment, create_environment_variable, delete_environment,
+                delete_environment_variable, get_environment_variables, get_environments,
+                set_active_environment, update_environment, update_environment_variable,
+                EnvironmentData, EnvironmentVariableData,
+            },
+            requests::get_single_request,
+        },
+        executor::unresolved_request_variables,
+        variables::UnresolvedVariables,
+    },
+    AppConfig, AppWindow, EnvironmentItem, KeyValueItem,
+};
+
+pub fn to_environment_item(environment: &EnvironmentData) -> EnvironmentItem {
+    EnvironmentItem {
+        id: environment.id.clone().into(),
+        name: environment.name.clone().into(),
+    }
+}
+
+pub fn variable_to_key_value_item(variable: EnvironmentVariableData) -> KeyValueItem {
+    KeyValueItem {
+        id: variable.id.into(),
+        name: variable.name.into(),
+        value: variable.value.into(),
+        enabled: variable.enabled,
+    }
+}
+
+/// Show the stored environments and select the active one.
+async fn show_environments(cfg: &AppConfig<'_>, db: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    let environments = get_environments(db).await?;
+
+    let mut names: Vec<SharedString> = vec!["No environment".into()];
+    names.extend(environments.iter().map(|env| SharedString::from(&env.name)));
+    let active_index = environments
+        .iter()
+        .position(|env| env.is_active)
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    let items: Vec<EnvironmentItem> = environments.iter().map(to_environment_item).collect();
+
+    cfg.set_environments(Rc::new(VecModel::from(items)).into());
+    cfg.set_environment_names(Rc::new(VecModel::from(names)).into());
+    cfg.set_active_environment_index(active_index as i32);
+    Ok(())
+}
+
+pub async fn load_environments(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    show_environments(&config, db).await
+}
+
+/// Point out the variables the active request references but nothing defines.
+///
+/// Errors are only logged, the check never gets in the way of editing.
+pub async fn refresh_unresolved_variables(cfg: &AppConfig<'_>, db: &SqlitePool) {
+    let request_id = cfg.get_active_request().id;
+    if request_id.is_empty() {
+        cfg.set_active_request_unresolved("".into());
+        return;
+    }
+
+    let request = match get_single_request(&request_id, db).await {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("Error getting request  - {}", error);
+            return;
+        }
+    };
+    let unresolved = match unresolved_request_variables(&request, db).await {
+        Ok(data) => data,
+        Err(error) => {
+            eprintln!("Error checking variables  - {}", error);
+            return;
+        }
+    };
+
+    // The active request may have changed while the check ran.
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+    if unresolved.is_empty() {
+        cfg.set_active_request_unresolved("".into());
+    } else {
+        cfg.set_active_request_unresolved(UnresolvedVariables(unresolved).to_string().into());
+    }
+}
+
+/// Switch the environment requests are resolved against.
+pub async fn process_select_environment(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_select_environment(move |index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            // The first entry is "No environment".
+            let environment_id = if index > 0 {
+                cfg.get_environments()
+                    .row_data(index as usize - 1)
+                    .map(|env| env.id.to_string())
+            } else {
+                None
+            };
+
+            if let Err(error) =
+                set_active_environment(environment_id.as_deref(), &db_copy_for_task).await
+            {
+                eprintln!("Error selecting environment  - {}", error);
+                return;
+            }
+            cfg.set_active_environment_index(index);
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Create an environment and open it for editing.
+pub async fn process_create_environment(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_environment(move || {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let environment = match create_environment("New environment", &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error creating environment  - {}", error);
+                    return;
+                }
+            };
+
+            if let Err(error) = show_environments(&cfg, &db_copy_for_task).await {
+                eprintln!("Error getting environments  - {}", error);
+            }
+            cfg.set_editing_globals(false);
+            cfg.set_editing_environment_index(cfg.get_environments().row_count() as i32 - 1);
+            cfg.set_editing_environment_id(environment.id.into());
+            cfg.set_editing_environment_name(environment.name.into());
+            cfg.set_editing_environment_variables(Rc::new(VecModel::default()).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Rename an environment.
+pub async fn process_update_environment(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_environment(move |id, name, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let environment = match update_environment(&id, &name, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error updating environment  - {}", error);
+                    return;
+                }
+            };
+
+            let items = cfg.get_environments();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, to_environment_item(&environment));
+            }
+            let names = cfg.get_environment_names();
+            if (index as usize + 1) < names.row_count() {
+                names.set_row_data(index as usize + 1, environment.name.into());
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Delete an environment together with its variables.
+pub async fn process_remove_environment(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_environment(move |id, _index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_environment(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting environment  - {}", error);
+                return;
+            }
+
+            if cfg.get_editing_environment_id() == id {
+                cfg.set_editing_environment_id("".into());
+                cfg.set_editing_environment_name("".into());
+                cfg.set_editing_environment_index(-1);
+                cfg.set_editing_environment_variables(Rc::new(VecModel::default()).into());
+            }
+            if let Err(error) = show_environments(&cfg, &db_copy_for_task).await {
+                eprintln!("Error getting environments  - {}", error);
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Show the variables of the environment being edited.
+pub async fn process_load_environment_variables(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_load_environment_variables(move |environment_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variables: Vec<KeyValueItem> =
+                match get_environment_variables(&environment_id, &db_copy_for_task).await {
+                    Ok(data) => data.into_iter().map(variable_to_key_value_item).collect(),
+                    Err(error) => {
+                        eprintln!("Error getting variables  - {}", error);
+                        return;
+                    }
+                };
+
+            cfg.set_editing_environment_variables(Rc::new(VecModel::from(variables)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty variable to an environment.
+pub async fn process_create_environment_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_environment_variable(move |environment_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match create_environment_variable(&environment_id, "", "", &db_copy_for_task).await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error creating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            let mut items: Vec<KeyValueItem> =
+                cfg.get_editing_environment_variables().iter().collect();
+            items.push(variable_to_key_value_item(variable));
+            cfg.set_editing_environment_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a variable edited in the environment grid.
+pub async fn process_update_environment_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_environment_variable(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match update_environment_variable(&id, &name, &value, enabled, &db_copy_for_task)
+                    .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_editing_environment_variables();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, variable_to_key_value_item(variable));
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a variable from an environment.
+pub async fn process_remove_environment_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_environment_variable(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_environment_variable(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting variable  - {}", error);
+                return;
+            }
+
+            let mut items: Vec<KeyValueItem> =
+                cfg.get_editing_environment_variables().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_editing_environment_variables(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        requests::{sync_request_item, to_request_item},
+        response_view::{hex_model, lines_model, to_slint_image},
+    },
+    utils::{
+        crud::requests::{get_single_request, parse_method, update_request_item, ProtocolTypes},
+        executor::{send_request, ResponseData},
+        response_view::{format_size, prepare_response_view, BodyKind, ResponseView},
+    },
+    AppConfig, AppWindow, ResponseHeaderItem, ResponseItem,
+};
+
+/// Convert an executed response into its UI representation.
+///
+/// The body is handed to lazy models, so only the rows on screen are ever
+/// formatted and highlighted.
+pub fn to_response_item(
+    request_id: &str,
+    response: ResponseData,
+    view: ResponseView,
+) -> ResponseItem {
+    let headers: Vec<ResponseHeaderItem> = response
+        .headers
+        .iter()
+        .map(|(name, value)| ResponseHeaderItem {
+            name: name.into(),
+            value: value.into(),
+        })
+        .collect();
+    let highlight = match view.kind {
+        BodyKind::Json | BodyKind::Xml | BodyKind::Html => Some(view.kind),
+        _ => None,
+    };
+
+    ResponseItem {
+        request_id: request_id.into(),
+        status: response.status as i32,
+        status_text: response.status_text.clone().into(),
+        elapsed_ms: response.timings.total.as_millis() as i32,
+        size: response.size as i32,
+        size_text: format_size(response.size).into(),
+        content_type: view.content_type.into(),
+        body_kind: view.kind.to_string().into(),
+        headers: Rc::new(VecModel::from(headers)).into(),
+        has_pretty: view.pretty.is_some(),
+        pretty_lines: view
+            .pretty
+            .map(|pretty| lines_model(pretty, highlight))
+            .unwrap_or_default(),
+        raw_lines: lines_model(view.raw, None),
+        hex_lines: hex_model(response.body),
+        has_image: view.image.is_some(),
+        image: view.image.map(to_slint_image).unwrap_or_default(),
+        error: "".into(),
+    }
+}
+
+/// Show the outcome of sending a request in the response viewer.
+pub async fn show_response(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    response: Result<ResponseData, Box<dyn Error>>,
+) {
+    let response = match response {
+        Ok(response) => prepare_response_view(response).await,
+        Err(error) => Err(error),
+    };
+    let response_item = match response {
+        Ok((response, view)) => to_response_item(request_id, response, view),
+        Err(error) => ResponseItem {
+            request_id: request_id.into(),
+            error: error.to_string().into(),
+            ..Default::default()
+        },
+    };
+    cfg.set_request_in_flight(false);
+    cfg.set_active_response(response_item);
+}
+
+/// Send a request and show the response.
+pub async fn process_send_request(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_send_request(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let mut request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            // Persist whatever is currently typed in the request pane before sending.
+            let active_request = cfg.get_active_request();
+            if active_request.id == request_id {
+                let updated = match parse_method(&active_request.http_method) {
+                    Ok(method) => {
+                        update_request_item(
+                            &request.id,
+                            &request.name,
+                            ProtocolTypes::from_string(&request.protocol)
+                                .unwrap_or(ProtocolTypes::Http),
+                            method,
+                            &cfg.get_active_request_url(),
+                            &db_copy_for_task,
+                        )
+                        .await
+                    }
+                    Err(error) => Err(error),
+                };
+                request = match updated {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating request  - {}", error);
+                        return;
+                    }
+                };
+                sync_request_item(&cfg, to_request_item(request.clone()));
+            }
+
+            cfg.set_request_in_flight(true);
+            let response = send_request(&request, &db_copy_for_task).await;
+            show_response(&cfg, &request_id, response).await;
+            cfg.invoke_get_history(cfg.get_history_search());
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,665 @@
+use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc};
+
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        environments::refresh_unresolved_variables,
+        requests::{sync_request_item, to_request_item},
+        sessions::SessionRegistry,
+        websocket::to_message_item,
+    },
+    utils::{
+        crud::{
+            graphql::{
+                get_graphql_query, get_graphql_schema, save_graphql_schema, update_graphql_query,
+                GraphQLQueryData, GraphQLSchemaData,
+            },
+            requests::{
+                get_single_request, parse_method, update_request_item, ProtocolTypes, RequestData,
+            },
+        },
+        graphql::{
+            completion::{complete_query, Completions},
+            fetch_schema,
+            schema::{Schema, SchemaType, TypeKinds},
+            subscriptions::{
+                complete, connection_init, pong, prepare_subscription, subscribe, ServerMessages,
+            },
+            validation::{operation_kind, validate_query},
+            variables_problem,
+        },
+        websocket::{connect, Directions, FrameKinds, LogEntry, WebSocketSession},
+    },
+    AppConfig, AppWindow, GraphQLCompletionItem, GraphQLFieldItem, GraphQLTypeDetailItem,
+    GraphQLTypeItem, WebSocketMessageItem,
+};
+
+/// Each connection carries a single subscription, always under this id.
+const SUBSCRIPTION_ID: &str = "1";
+
+/// The introspected schema of a request, parsed once.
+struct CachedSchema {
+    schema: Schema,
+    fetched_at: String,
+}
+
+/// Suggestions on screen, with where they were asked for.
+struct PendingCompletions {
+    request_id: String,
+    offset: usize,
+    completions: Completions,
+}
+
+thread_local! {
+    // Slint callbacks all run on the UI thread.
+    static SCHEMAS: RefCell<HashMap<String, CachedSchema>> = RefCell::new(HashMap::new());
+    static COMPLETIONS: RefCell<Option<PendingCompletions>> = const { RefCell::new(None) };
+    /// Subscriptions stay connecting until the server accepts the connection.
+    static SUBSCRIPTIONS: SessionRegistry<WebSocketSession, LogEntry> = SessionRegistry::default();
+}
+
+fn to_type_detail(schema_type: &SchemaType) -> GraphQLTypeDetailItem {
+    let fields: Vec<GraphQLFieldItem> = match schema_type.kind {
+        TypeKinds::Object | TypeKinds::Interface => schema_type
+            .fields
+            .iter()
+            .map(|field| GraphQLFieldItem {
+                signature: match field.deprecated {
+                    true => format!("{} (deprecated)", field.signature()),
+                    false => field.signature(),
+                }
+                .into(),
+                description: field.description.clone().into(),
+                type_name: field.field_type.named().into(),
+            })
+            .collect(),
+        TypeKinds::InputObject => schema_type
+            .input_fields
+            .iter()
+            .map(|input| GraphQLFieldItem {
+                signature: match &input.default_value {
+                    Some(default) => format!("{}: {} = {}", input.name, input.value_type, default),
+                    None => format!("{}: {}", input.name, input.value_type),
+                }
+                .into(),
+                description: input.description.clone().into(),
+                type_name: input.value_type.named().into(),
+            })
+            .collect(),
+        TypeKinds::Enum => schema_type
+            .enum_values
+            .iter()
+            .map(|value| GraphQLFieldItem {
+                signature: value.into(),
+                ..Default::default()
+            })
+            .collect(),
+        TypeKinds::Union => schema_type
+            .possible_types
+            .iter()
+            .map(|name| GraphQLFieldItem {
+                signature: name.into(),
+                type_name: name.into(),
+                ..Default::default()
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    GraphQLTypeDetailItem {
+        name: schema_type.name.clone().into(),
+        kind: schema_type.kind.to_string().into(),
+        description: schema_type.description.clone().into(),
+        fields: Rc::new(VecModel::from(fields)).into(),
+    }
+}
+
+/// Check the operation in the request pane against the schema of its request.
+fn check_query(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let query = cfg.get_active_graphql_query();
+    let operation_name = cfg.get_active_graphql_operation_name();
+    let problems: Vec<SharedString> = SCHEMAS.with_borrow(|schemas| {
+        validate_query(
+            &query,
+            &operation_name,
+            schemas.get(request_id).map(|cached| &cached.schema),
+        )
+        .iter()
+        .map(|problem| problem.to_string().into())
+        .collect()
+    });
+    cfg.set_active_graphql_problems(Rc::new(VecModel::from(problems)).into());
+    cfg.set_active_graphql_variables_error(
+        variables_problem(&cfg.get_active_graphql_variables())
+            .unwrap_or_default()
+            .into(),
+    );
+    // A query being typed rarely parses; it keeps its kind until it does.
+    if let Some(kind) = operation_kind(&query, &operation_name) {
+        cfg.set_active_graphql_operation_kind(kind.to_string().into());
+    }
+}
+
+fn clear_completions(cfg: &AppConfig) {
+    COMPLETIONS.with_borrow_mut(|pending| *pending = None);
+    cfg.set_active_graphql_completions(Rc::new(VecModel::default()).into());
+    cfg.set_active_graphql_completion_index(0);
+}
+
+/// Show the schema of a request in the explorer, if it is the one in the
+/// request pane.
+fn show_schema(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let filter = cfg.get_graphql_schema_filter().trim().to_lowercase();
+    let (status, types) = SCHEMAS.with_borrow(|schemas| {
+        let Some(cached) = schemas.get(request_id) else {
+            return (
+                "Fetch the schema to check queries against it and get suggestions.".to_string(),
+                vec![],
+            );
+        };
+        let roots = [
+            &cached.schema.query_type,
+            &cached.schema.mutation_type,
+            &cached.schema.subscription_type,
+        ];
+        let mut types: Vec<&SchemaType> = cached
+            .schema
+            .user_types()
+            .filter(|schema_type| schema_type.name.to_lowercase().contains(&filter))
+            .collect();
+        // The operation types come first, the rest by name.
+        types.sort_by_key(|schema_type| {
+            let root = roots
+                .iter()
+                .position(|root| root.as_deref() == Some(schema_type.name.as_str()));
+            (root.unwrap_or(roots.len()), schema_type.name.clone())
+        });
+        let types: Vec<GraphQLTypeItem> = types
+            .into_iter()
+            .map(|schema_type| GraphQLTypeItem {
+                name: schema_type.name.clone().into(),
+                kind: schema_type.kind.to_string().into(),
+            })
+            .collect();
+        (format!("Schema fetched {} UTC.", cached.fetched_at), types)
+    });
+
+    cfg.set_active_graphql_schema_status(status.into());
+    cfg.set_graphql_schema_types(Rc::new(VecModel::from(types)).into());
+}
+
+fn cache_schema(request_id: &str, saved: GraphQLSchemaData) -> Result<(), Box<dyn Error>> {
+    let schema = Schema::from_introspection(&serde_json::from_str(&saved.schema)?)?;
+    SCHEMAS.with_borrow_mut(|schemas| {
+        schemas.insert(
+            request_id.to_string(),
+            CachedSchema {
+                schema,
+                fetched_at: saved.fetched_at,
+            },
+        )
+    });
+
+    Ok(())
+}
+
+/// Show the subscription of a request, if it is the one in the request pane.
+fn show_subscription(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let (state, events) = SUBSCRIPTIONS.with(|subscriptions| {
+        subscriptions.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("stopped", vec![]);
+            };
+            let status = match (&state.session, state.connecting) {
+                (_, true) => "subscribing",
+                (Some(_), false) => "running",
+                (None, false) => "stopped",
+            };
+            let events: Vec<WebSocketMessageItem> = state.log.iter().map(to_message_item).collect();
+            (status, events)
+        })
+    });
+
+    cfg.set_active_graphql_subscription_state(state.into());
+    cfg.set_active_graphql_events(Rc::new(VecModel::from(events)).into());
+}
+
+fn send_to_subscription(request_id: &str, message: tokio_tungstenite::tungstenite::Message) {
+    let sent = SUBSCRIPTIONS.with(|subscriptions| {
+        subscriptions.with_session(request_id, |session| session.send(message))
+    });
+    if let Some(Err(error)) = sent {
+        eprintln!("Error sending to subscription  - {}", error);
+    }
+}
+
+/// Load the operation, schema and subscription of a GraphQL request.
+pub async fn load_graphql_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let operation = get_graphql_query(request_id, db).await?;
+    cfg.set_active_graphql_query(operation.query.into());
+    cfg.set_active_graphql_variables(operation.variables.into());
+    cfg.set_active_graphql_operation_name(operation.operation_name.into());
+    cfg.set_active_graphql_operation_kind("query".into());
+    clear_completions(cfg);
+
+    let cached = SCHEMAS.with_borrow(|schemas| schemas.contains_key(request_id));
+    if !cached {
+        if let Some(saved) = get_graphql_schema(request_id, db).await? {
+            cache_schema(request_id, saved)?;
+        }
+    }
+    cfg.set_active_graphql_type(GraphQLTypeDetailItem::default());
+    show_schema(cfg, request_id);
+    check_query(cfg, request_id);
+    show_subscription(cfg, request_id);
+
+    Ok(())
+}
+
+/// Stop the subscription of a request, if it has one. Its log is kept.
+pub fn close_graphql_subscription(request_id: &str) {
+    SUBSCRIPTIONS.with(|subscriptions| {
+        subscriptions.with_session(request_id, |session| {
+            let _ = session.send(complete(SUBSCRIPTION_ID));
+            session.close();
+        })
+    });
+}
+
+/// Forget the schema and subscription of a removed request.
+pub fn remove_graphql_request(request_id: &str) {
+    SCHEMAS.with_borrow_mut(|schemas| schemas.remove(request_id));
+    SUBSCRIPTIONS.with(|subscriptions| subscriptions.remove(request_id));
+}
+
+/// The request, with the URL currently typed in the request pane saved first.
+async fn current_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let active_request = cfg.get_active_request();
+    if active_request.id != request_id {
+        return Ok(request);
+    }
+
+    let request = update_request_item(
+        &request.id,
+        &request.name,
+        ProtocolTypes::GraphQL,
+        parse_method(&active_request.http_method)?,
+        &cfg.get_active_request_url(),
+        db,
+    )
+    .await?;
+    sync_request_item(cfg, to_request_item(request.clone()));
+
+    Ok(request)
+}
+
+pub async fn process_update_graphql_query(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_graphql_query(move |request_id, query, variables, operation_name| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            check_query(&cfg, &request_id);
+            let operation = GraphQLQueryData {
+                query: query.to_string(),
+                variables: variables.to_string(),
+                operation_name: operation_name.trim().to_string(),
+            };
+            if let Err(error) =
+                update_graphql_query(&request_id, &operation, &db_copy_for_task).await
+            {
+                eprintln!("Error updating GraphQL query  - {}", error);
+                return;
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_complete_graphql_query(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_complete_graphql_query(move |request_id, offset, asked| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let query = cfg.get_active_graphql_query();
+        let offset = offset.max(0) as usize;
+        let completions = SCHEMAS.with_borrow(|schemas| {
+            schemas
+                .get(request_id.as_str())
+                .map(|cached| complete_query(&query, offset, &cached.schema))
+        });
+        let Some(completions) = completions else {
+            clear_completions(&cfg);
+            if asked {
+                cfg.set_show_graphql_schema(true);
+            }
+            return;
+        };
+
+        // While typing, suggestions only show up once a word is started and
+        // go away once it is complete.
+        let word = &query[completions.start.min(query.len())..offset.min(query.len())];
+        if !asked
+            && (word.is_empty()
+                || completions
+                    .items
+                    .iter()
+                    .all(|completion| completion.label == word))
+        {
+            clear_completions(&cfg);
+            return;
+        }
+
+        let items: Vec<GraphQLCompletionItem> = completions
+            .items
+            .iter()
+            .map(|completion| GraphQLCompletionItem {
+                label: completion.label.clone().into(),
+                detail: completion.detail.clone().into(),
+            })
+            .collect();
+        cfg.set_active_graphql_completions(Rc::new(VecModel::from(items)).into());
+        cfg.set_active_graphql_completion_index(0);
+        COMPLETIONS.with_borrow_mut(|pending| {
+            *pending = Some(PendingCompletions {
+                request_id: request_id.to_string(),
+                offset,
+                completions,
+            })
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_apply_graphql_completion(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_apply_graphql_completion(move |index| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let Some(pending) = COMPLETIONS.with_borrow_mut(|pending| pending.take()) else {
+            return 0;
+        };
+        clear_completions(&cfg);
+        let Some(completion) = pending.completions.items.get(index.max(0) as usize) else {
+            return pending.offset as i32;
+        };
+
+        let query = cfg.get_active_graphql_query();
+        let (query, cursor) = pending
+            .completions
+            .apply(&query, pending.offset, &completion.label);
+        cfg.set_active_graphql_query(query.into());
+        check_query(&cfg, &pending.request_id);
+        cursor as i32
+    });
+
+    Ok(())
+}
+
+pub async fn process_fetch_graphql_schema(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_fetch_graphql_schema(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let request = match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            cfg.set_graphql_schema_fetching(true);
+            let fetched = match fetch_schema(&request, &db_copy_for_task).await {
+                Ok(schema) => {
+                    save_graphql_schema(&request_id, &schema.to_string(), &db_copy_for_task)
+                        .await
+                        .and_then(|saved| cache_schema(&request_id, saved))
+                }
+                Err(error) => Err(error),
+            };
+            cfg.set_graphql_schema_fetching(false);
+
+            match fetched {
+                Ok(()) => {
+                    if cfg.get_active_request().id == request_id {
+                        cfg.set_active_graphql_type(GraphQLTypeDetailItem::default());
+                    }
+                    show_schema(&cfg, &request_id);
+                    check_query(&cfg, &request_id);
+                }
+                Err(error) => {
+                    if cfg.get_active_request().id == request_id {
+                        cfg.set_active_graphql_schema_status(error.to_string().into());
+                    }
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_filter_graphql_schema(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_filter_graphql_schema(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        cfg.set_active_graphql_type(GraphQLTypeDetailItem::default());
+        show_schema(&cfg, &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_select_graphql_type(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_select_graphql_type(move |request_id, name| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let detail = SCHEMAS.with_borrow(|schemas| {
+            schemas
+                .get(request_id.as_str())
+                .and_then(|cached| cached.schema.get_type(&name))
+                .map(to_type_detail)
+        });
+        cfg.set_active_graphql_type(detail.unwrap_or_default());
+    });
+
+    Ok(())
+}
+
+pub async fn process_subscribe_graphql(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_subscribe_graphql(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            let request = match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            close_graphql_subscription(&request_id);
+            let generation = SUBSCRIPTIONS.with(|subscriptions| subscriptions.begin(&request_id));
+            show_subscription(&cfg, &request_id);
+
+            let connection = match prepare_subscription(&request, &db_copy_for_task).await {
+                Ok((handshake, body)) => connect(handshake)
+                    .await
+                    .map(|connection| (connection, body)),
+                Err(error) => Err(error),
+            };
+            let (mut log, body) = match connection {
+                Ok((connection, body)) => {
+                    let _ = connection.session.send(connection_init());
+                    SUBSCRIPTIONS.with(|subscriptions| {
+                        subscriptions.update(&request_id, |state| {
+                            state.session = Some(connection.session)
+                        })
+                    });
+                    (connection.log, body)
+                }
+                Err(error) => {
+                    SUBSCRIPTIONS.with(|subscriptions| {
+                        subscriptions.failed(
+                            &request_id,
+                            LogEntry::new(Directions::Info, FrameKinds::Error, &error.to_string()),
+                        )
+                    });
+                    show_subscription(&cfg, &request_id);
+                    return;
+                }
+            };
+            show_subscription(&cfg, &request_id);
+            // The window may be closed while the subscription runs.
+            drop(app);
+
+            while let Some(entry) = log.recv().await {
+                if entry.direction == Directions::Received && entry.kind == FrameKinds::Text {
+                    match ServerMessages::parse(&entry.data) {
+                        ServerMessages::ConnectionAck => {
+                            let subscribed = subscribe(SUBSCRIPTION_ID, &body);
+                            match subscribed {
+                                Ok(message) => send_to_subscription(&request_id, message),
+                                Err(error) => {
+                                    SUBSCRIPTIONS.with(|subscriptions| {
+                                        subscriptions.push(
+                                            &request_id,
+                                            LogEntry::new(
+                                                Directions::Info,
+                                                FrameKinds::Error,
+                                                &error.to_string(),
+                                            ),
+                                        )
+                                    });
+                                    close_graphql_subscription(&request_id);
+                                }
+                            }
+                            SUBSCRIPTIONS.with(|subscriptions| {
+                                subscriptions.update(&request_id, |state| state.connecting = false)
+                            });
+                        }
+                        ServerMessages::Ping => send_to_subscription(&request_id, pong()),
+                        // The subscription is over, and so is the connection.
+                        ServerMessages::Complete { .. } | ServerMessages::Error { .. } => {
+                            SUBSCRIPTIONS.with(|subscriptions| {
+                                subscriptions.with_session(&request_id, WebSocketSession::close)
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+                SUBSCRIPTIONS.with(|subscriptions| subscriptions.push(&request_id, entry));
+                let Some(app) = weak_app_for_task.upgrade() else {
+                    return;
+                };
+                show_subscription(&app.global::<AppConfig>(), &request_id);
+            }
+
+            SUBSCRIPTIONS.with(|subscriptions| subscriptions.end(&request_id, generation, |_| ()));
+            if let Some(app) = weak_app_for_task.upgrade() {
+                show_subscription(&app.global::<AppConfig>(), &request_id);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_unsubscribe_graphql(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_unsubscribe_graphql(move |request_id| {
+        close_graphql_subscription(&request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_graphql_events(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_graphql_events(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        SUBSCRIPTIONS.with(|subscriptions| subscriptions.clear_log(&request_id));
+        show_subscription(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
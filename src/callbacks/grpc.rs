@@ -0,0 +1,550 @@
+use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc};
+
+use prost_reflect::DescriptorPool;
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        environments::refresh_unresolved_variables,
+        requests::{sync_request_item, to_request_item},
+        sessions::SessionRegistry,
+        websocket::to_message_item,
+    },
+    utils::{
+        crud::{
+            grpc::{
+                get_grpc_call, get_grpc_descriptors, save_grpc_descriptors, update_grpc_call,
+                GrpcCallData, GrpcDescriptorsData,
+            },
+            requests::{
+                get_single_request, parse_method, update_request_item, ProtocolTypes, RequestData,
+            },
+        },
+        grpc::{
+            call::{start_call, CallEntry, CallSession, EntryKinds},
+            find_method, list_methods, load_services, message_template, prepare_call, CallKinds,
+        },
+        websocket::Directions,
+    },
+    AppConfig, AppWindow, WebSocketMessageItem,
+};
+
+/// The services of a request, decoded once.
+struct LoadedServices {
+    services: DescriptorPool,
+    loaded_at: String,
+}
+
+thread_local! {
+    // Slint callbacks all run on the UI thread.
+    static SERVICES: RefCell<HashMap<String, LoadedServices>> = RefCell::new(HashMap::new());
+    /// Calls along with how the last one ended.
+    static CALLS: SessionRegistry<CallSession, CallEntry, String> = SessionRegistry::default();
+}
+
+fn cache_services(request_id: &str, saved: GrpcDescriptorsData) -> Result<(), Box<dyn Error>> {
+    let services = DescriptorPool::decode(saved.descriptors.as_slice())?;
+    SERVICES.with_borrow_mut(|cached| {
+        cached.insert(
+            request_id.to_string(),
+            LoadedServices {
+                services,
+                loaded_at: saved.loaded_at,
+            },
+        )
+    });
+    Ok(())
+}
+
+/// Show the methods of a request and the kind of the one picked, if it is
+/// the request in the request pane.
+fn show_services(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let method = cfg.get_active_grpc_method();
+    let (status, methods, kind) = SERVICES.with_borrow(|cached| {
+        let Some(loaded) = cached.get(request_id) else {
+            return (
+                "Load the services to pick a method and get message templates.".to_string(),
+                vec![],
+                String::new(),
+            );
+        };
+        let methods: Vec<SharedString> = list_methods(&loaded.services)
+            .into_iter()
+            .map(SharedString::from)
+            .collect();
+        let kind = find_method(&loaded.services, &method)
+            .map(|method| CallKinds::of(&method).to_string())
+            .unwrap_or_default();
+        (
+            format!("Services loaded {} UTC.", loaded.loaded_at),
+            methods,
+            kind,
+        )
+    });
+    cfg.set_active_grpc_services_status(status.into());
+    cfg.set_active_grpc_methods(Rc::new(VecModel::from(methods)).into());
+    cfg.set_active_grpc_call_kind(kind.into());
+}
+
+/// Show the call of a request, if it is the one in the request pane.
+fn show_call(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let (state, status, log) = CALLS.with(|calls| {
+        calls.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("idle", String::new(), vec![]);
+            };
+            let call_state = match (&state.session, state.connecting) {
+                (_, true) => "starting",
+                (Some(session), false) if session.is_sending() => "sending",
+                (Some(_), false) => "waiting",
+                (None, false) => "idle",
+            };
+            let log: Vec<WebSocketMessageItem> = state.log.iter().map(to_message_item).collect();
+            (call_state, state.extra.clone(), log)
+        })
+    });
+    cfg.set_active_grpc_call_state(state.into());
+    cfg.set_active_grpc_status(status.into());
+    cfg.set_active_grpc_log(Rc::new(VecModel::from(log)).into());
+}
+
+fn set_error(cfg: &AppConfig, request_id: &str, error: &str) {
+    if cfg.get_active_request().id == request_id {
+        cfg.set_active_grpc_error(error.into());
+    }
+}
+
+/// The call as set up in the request pane.
+fn call_data(cfg: &AppConfig) -> GrpcCallData {
+    GrpcCallData {
+        source: cfg.get_active_grpc_source().to_string(),
+        proto_files: cfg.get_active_grpc_proto_files().to_string(),
+        import_paths: cfg.get_active_grpc_import_paths().to_string(),
+        method: cfg.get_active_grpc_method().to_string(),
+        message: cfg.get_active_grpc_message().to_string(),
+    }
+}
+
+/// Load the call, services and call log of a gRPC request.
+pub async fn load_grpc_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let call = get_grpc_call(request_id, db).await?;
+    cfg.set_active_grpc_source(call.source.into());
+    cfg.set_active_grpc_proto_files(call.proto_files.into());
+    cfg.set_active_grpc_import_paths(call.import_paths.into());
+    cfg.set_active_grpc_method(call.method.into());
+    cfg.set_active_grpc_message(call.message.into());
+    cfg.set_active_grpc_error(SharedString::new());
+    let cached = SERVICES.with_borrow(|cached| cached.contains_key(request_id));
+    if !cached {
+        if let Some(saved) = get_grpc_descriptors(request_id, db).await? {
+            cache_services(request_id, saved)?;
+        }
+    }
+    show_services(cfg, request_id);
+    show_call(cfg, request_id);
+    Ok(())
+}
+
+/// Cancel the call of a request, if one is in progress. Its log is kept.
+pub fn close_grpc_call(request_id: &str) {
+    CALLS.with(|calls| calls.with_session_mut(request_id, CallSession::cancel));
+}
+
+/// Forget the services and call of a removed request.
+pub fn remove_grpc_request(request_id: &str) {
+    SERVICES.with_borrow_mut(|cached| cached.remove(request_id));
+    CALLS.with(|calls| calls.remove(request_id));
+}
+
+/// The request, with the address currently typed in the request pane saved
+/// first.
+async fn current_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let active_request = cfg.get_active_request();
+    if active_request.id != request_id {
+        return Ok(request);
+    }
+
+    let request = update_request_item(
+        &request.id,
+        &request.name,
+        ProtocolTypes::Grpc,
+        parse_method(&active_request.http_method)?,
+        &cfg.get_active_request_url(),
+        db,
+    )
+    .await?;
+    sync_request_item(cfg, to_request_item(request.clone()));
+    Ok(request)
+}
+
+/// Load the services of a request and keep them for the next time.
+async fn refresh_services(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let request = current_request(cfg, request_id, db).await?;
+    let call = get_grpc_call(request_id, db).await?;
+    let services = load_services(&request, &call, db).await?;
+    let saved = save_grpc_descriptors(request_id, &services.encode_to_vec(), db).await?;
+    cache_services(request_id, saved)
+}
+
+pub async fn process_update_grpc_call(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_grpc_call(
+        move |request_id, source, proto_files, import_paths, method, message| {
+            let weak_app_for_task = weak_app.clone();
+            let db_copy_for_task = db_copy.clone();
+
+            let _ = slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let call = GrpcCallData {
+                    source: source.to_string(),
+                    proto_files: proto_files.to_string(),
+                    import_paths: import_paths.to_string(),
+                    method: method.to_string(),
+                    message: message.to_string(),
+                };
+                if let Err(error) = update_grpc_call(&request_id, &call, &db_copy_for_task).await {
+                    eprintln!("Error updating gRPC call  - {}", error);
+                    return;
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+        },
+    );
+
+    Ok(())
+}
+
+pub async fn process_load_grpc_services(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_load_grpc_services(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            cfg.set_grpc_services_loading(true);
+            let loaded = refresh_services(&cfg, &request_id, &db_copy_for_task).await;
+            cfg.set_grpc_services_loading(false);
+            match loaded {
+                Ok(()) => show_services(&cfg, &request_id),
+                Err(error) => {
+                    if cfg.get_active_request().id == request_id {
+                        cfg.set_active_grpc_services_status(error.to_string().into());
+                    }
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_select_grpc_method(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_select_grpc_method(move |request_id, method| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            cfg.set_active_grpc_method(method.clone());
+            // A new method starts from its template, unless a message was
+            // already written.
+            if cfg.get_active_grpc_message().trim().is_empty() {
+                let template = SERVICES.with_borrow(|cached| {
+                    cached
+                        .get(request_id.as_str())
+                        .and_then(|loaded| find_method(&loaded.services, &method).ok())
+                        .map(|method| message_template(&method.input()))
+                });
+                if let Some(template) = template {
+                    cfg.set_active_grpc_message(template.into());
+                }
+            }
+            show_services(&cfg, &request_id);
+
+            if let Err(error) =
+                update_grpc_call(&request_id, &call_data(&cfg), &db_copy_for_task).await
+            {
+                eprintln!("Error updating gRPC call  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_fill_grpc_template(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_fill_grpc_template(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let method = cfg.get_active_grpc_method();
+            let template = SERVICES.with_borrow(|cached| {
+                cached
+                    .get(request_id.as_str())
+                    .and_then(|loaded| find_method(&loaded.services, &method).ok())
+                    .map(|method| message_template(&method.input()))
+            });
+            let Some(template) = template else {
+                return;
+            };
+            cfg.set_active_grpc_message(template.into());
+
+            if let Err(error) =
+                update_grpc_call(&request_id, &call_data(&cfg), &db_copy_for_task).await
+            {
+                eprintln!("Error updating gRPC call  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_invoke_grpc(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_invoke_grpc(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            close_grpc_call(&request_id);
+            cfg.set_active_grpc_error(SharedString::new());
+            let generation = CALLS.with(|calls| {
+                let generation = calls.begin(&request_id);
+                calls.update(&request_id, |state| state.extra.clear());
+                generation
+            });
+            show_call(&cfg, &request_id);
+
+            // Services are loaded on the first call when they weren't yet.
+            let cached = SERVICES.with_borrow(|cached| cached.contains_key(&request_id));
+            let started = match cached {
+                true => Ok(()),
+                false => refresh_services(&cfg, &request_id, &db_copy_for_task).await,
+            };
+            let started = match started {
+                Ok(()) => {
+                    show_services(&cfg, &request_id);
+                    let method = cfg.get_active_grpc_method();
+                    let method = SERVICES.with_borrow(|cached| {
+                        cached
+                            .get(&request_id)
+                            .ok_or_else(|| "Load the services first".into())
+                            .and_then(|loaded| find_method(&loaded.services, &method))
+                    });
+                    match method {
+                        Ok(method) => {
+                            match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                                Ok(request) => {
+                                    match prepare_call(&request, &db_copy_for_task).await {
+                                        Ok(prepared) => start_call(method, prepared).await,
+                                        Err(error) => Err(error),
+                                    }
+                                }
+                                Err(error) => Err(error),
+                            }
+                        }
+                        Err(error) => Err(error),
+                    }
+                }
+                Err(error) => Err(error),
+            };
+
+            let mut log = match started {
+                Ok(call) => {
+                    CALLS.with(|calls| calls.connected(&request_id, call.session));
+                    call.log
+                }
+                Err(error) => {
+                    CALLS.with(|calls| calls.update(&request_id, |state| state.connecting = false));
+                    set_error(&cfg, &request_id, &error.to_string());
+                    show_call(&cfg, &request_id);
+                    return;
+                }
+            };
+            show_call(&cfg, &request_id);
+            // The window may be closed while the call runs.
+            drop(app);
+
+            while let Some(entry) = log.recv().await {
+                let ends_call = entry.ends_call();
+                CALLS.with(|calls| {
+                    if ends_call {
+                        calls.end(&request_id, generation, |state| {
+                            state.extra = entry.data.clone()
+                        });
+                    }
+                    calls.push(&request_id, entry);
+                });
+                let Some(app) = weak_app_for_task.upgrade() else {
+                    return;
+                };
+                show_call(&app.global::<AppConfig>(), &request_id);
+                if ends_call {
+                    break;
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_send_grpc_message(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_send_grpc_message(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) =
+                update_grpc_call(&request_id, &call_data(&cfg), &db_copy_for_task).await
+            {
+                eprintln!("Error updating gRPC call  - {}", error);
+                return;
+            }
+            // The message goes out with its variables substituted, like the
+            // first one of a call.
+            let prepared = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => prepare_call(&request, &db_copy_for_task).await,
+                Err(error) => Err(error),
+            };
+            let sent = prepared.and_then(|prepared| {
+                CALLS
+                    .with(|calls| {
+                        calls.with_session(&request_id, |session| session.send(&prepared.message))
+                    })
+                    .unwrap_or_else(|| Err("Invoke the method to open the stream first.".into()))
+            });
+            match sent {
+                Ok(()) => cfg.set_active_grpc_error(SharedString::new()),
+                Err(error) => set_error(&cfg, &request_id, &error.to_string()),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_finish_grpc_stream(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_finish_grpc_stream(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        CALLS.with(|calls| {
+            calls.with_session_mut(&request_id, CallSession::finish);
+            calls.push(
+                &request_id,
+                CallEntry::new(Directions::Sent, EntryKinds::Message, "end of stream"),
+            );
+        });
+        show_call(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_cancel_grpc_call(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_cancel_grpc_call(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        close_grpc_call(&request_id);
+        show_call(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_grpc_log(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_grpc_log(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        CALLS.with(|calls| calls.clear_log(&request_id));
+        show_call(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
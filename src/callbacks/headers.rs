@@ -0,0 +1,307 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::environments::refresh_unresolved_variables,
+    utils::crud::{
+        collections::{
+            create_collection_header, delete_collection_header, get_collection_headers,
+            update_collection_header, CollectionHeaderData,
+        },
+        requests::{
+            create_request_header, delete_request_header, get_request_headers,
+            update_request_header, RequestHeaderData,
+        },
+    },
+    AppConfig, AppWindow, KeyValueItem,
+};
+
+pub fn to_key_value_item(header: RequestHeaderData) -> KeyValueItem {
+    KeyValueItem {
+        id: header.id.into(),
+        name: header.name.into(),
+        value: header.value.into(),
+        enabled: header.enabled,
+    }
+}
+
+pub fn collection_header_to_key_value_item(header: CollectionHeaderData) -> KeyValueItem {
+    KeyValueItem {
+        id: header.id.into(),
+        name: header.name.into(),
+        value: header.value.into(),
+        enabled: header.enabled,
+    }
+}
+
+/// Show the headers of a request in the request pane.
+pub async fn load_request_headers(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let headers: Vec<KeyValueItem> = get_request_headers(request_id, db)
+        .await?
+        .into_iter()
+        .map(to_key_value_item)
+        .collect();
+
+    cfg.set_active_request_headers(Rc::new(VecModel::from(headers)).into());
+    Ok(())
+}
+
+/// Add an empty header to a request.
+pub async fn process_create_request_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_request_header(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let header = match create_request_header(&request_id, "", "", &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error creating header  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_headers().iter().collect();
+            items.push(to_key_value_item(header));
+            cfg.set_active_request_headers(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a header edited in the headers grid.
+pub async fn process_update_request_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_header(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let header =
+                match update_request_header(&id, &name, &value, enabled, &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating header  - {}", error);
+                        return;
+                    }
+                };
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_active_request_headers();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, to_key_value_item(header));
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a header from a request.
+pub async fn process_remove_request_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_request_header(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            match delete_request_header(&id, &db_copy_for_task).await {
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("Error deleting header  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_headers().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_request_headers(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Get the headers of a collection for the collection settings.
+pub async fn process_get_collection_headers(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_collection_headers(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let headers = match get_collection_headers(&collection_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(_) => [].to_vec(),
+            };
+            let items: Vec<KeyValueItem> = headers
+                .into_iter()
+                .map(collection_header_to_key_value_item)
+                .collect();
+            cfg.set_active_collection_headers(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty header to a collection.
+pub async fn process_create_collection_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_collection_header(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let header =
+                match create_collection_header(&collection_id, "", "", &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error creating collection header  - {}", error);
+                        return;
+                    }
+                };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_collection_headers().iter().collect();
+            items.push(collection_header_to_key_value_item(header));
+            cfg.set_active_collection_headers(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a header edited in the collection settings.
+pub async fn process_update_collection_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_collection_header(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ =
+            slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let header =
+                    match update_collection_header(&id, &name, &value, enabled, &db_copy_for_task)
+                        .await
+                    {
+                        Ok(data) => data,
+                        Err(error) => {
+                            eprintln!("Error updating collection header  - {}", error);
+                            return;
+                        }
+                    };
+
+                let items = cfg.get_active_collection_headers();
+                if (index as usize) < items.row_count() {
+                    items.set_row_data(index as usize, collection_header_to_key_value_item(header));
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+    });
+
+    Ok(())
+}
+
+/// Remove a header from a collection.
+pub async fn process_remove_collection_header(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_collection_header(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            match delete_collection_header(&id, &db_copy_for_task).await {
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("Error deleting collection header  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_collection_headers().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_collection_headers(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
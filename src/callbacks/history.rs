@@ -0,0 +1,437 @@
+use std::{error::Error, fs, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        compare::show_comparison_tabs,
+        executor::show_response,
+        images::load_image_item,
+        requests::{open_request_tab, to_request_item},
+    },
+    utils::{
+        crud::{
+            collections::get_single_collection,
+            history::{
+                clear_history, delete_history_entry, get_history_entry, get_history_settings,
+                restore_history_entry, search_history, update_history_settings, HistoryEntryData,
+                HistorySettingsData, HistorySummaryData,
+            },
+            requests::{get_single_request, RequestData},
+        },
+        executor::{execute_and_record, resolve_auth, HistoryOrigin, PreparedRequest},
+        interchange::har::write_har,
+    },
+    AppConfig, AppWindow, HistoryItem,
+};
+
+/// How many entries the history sidebar lists at most.
+const HISTORY_LIST_LIMIT: i64 = 200;
+
+pub fn to_history_item(entry: HistorySummaryData) -> HistoryItem {
+    HistoryItem {
+        id: entry.id.into(),
+        created_at: entry.created_at.into(),
+        request_name: entry.request_name.into(),
+        environment_name: entry.environment_name.into(),
+        http_method: entry.http_method.into(),
+        url: entry.url.into(),
+        status: entry.status.unwrap_or(0) as i32,
+        status_text: entry.status_text.into(),
+        error: entry.error.into(),
+        elapsed_ms: entry.total_ms as i32,
+        selected: false,
+    }
+}
+
+/// Show a retention limit, leaving the field empty when there is none.
+fn limit_text(limit: i64) -> String {
+    if limit > 0 {
+        limit.to_string()
+    } else {
+        "".to_string()
+    }
+}
+
+/// Read a retention limit typed in the sidebar. Empty means no limit.
+fn parse_limit(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0);
+    }
+    text.parse::<i64>().ok().filter(|limit| *limit >= 0)
+}
+
+fn show_history_items(cfg: &AppConfig, items: Vec<HistoryItem>) {
+    let selected = items.iter().filter(|item| item.selected).count();
+    cfg.set_history_items(Rc::new(VecModel::from(items)).into());
+    cfg.set_history_selected_count(selected as i32);
+}
+
+fn show_history_settings(cfg: &AppConfig, settings: HistorySettingsData) {
+    cfg.set_history_max_entries(limit_text(settings.max_entries).into());
+    cfg.set_history_max_age_days(limit_text(settings.max_age_days).into());
+}
+
+async fn show_history(
+    cfg: &AppConfig<'_>,
+    search_term: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let items: Vec<HistoryItem> = search_history(search_term, HISTORY_LIST_LIMIT, db)
+        .await?
+        .into_iter()
+        .map(to_history_item)
+        .collect();
+
+    show_history_items(cfg, items);
+    Ok(())
+}
+
+/// The request an entry was sent from, restored into a new one when it was deleted.
+async fn request_for_entry(
+    entry: &HistoryEntryData,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    if let Some(request_id) = &entry.request_id {
+        if let Ok(request) = get_single_request(request_id, db).await {
+            return Ok(request);
+        }
+    }
+
+    restore_history_entry(entry, db).await
+}
+
+/// Open a request in a tab and bring the sidebar up to date with it.
+async fn open_request(
+    cfg: &AppConfig<'_>,
+    request: RequestData,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let collection = get_single_collection(&request.collection_id, db).await?;
+    let collection_icon = load_image_item(&collection.icon).unwrap_or_default();
+
+    // Restoring may have added a request or even a collection.
+    cfg.invoke_get_collections();
+    if cfg.get_active_collection_item().id == request.collection_id {
+        cfg.invoke_get_requests(request.collection_id.clone().into());
+    }
+
+    open_request_tab(cfg, to_request_item(request), collection_icon);
+    Ok(())
+}
+
+pub async fn load_history_settings(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    show_history_settings(&config, get_history_settings(db).await?);
+
+    Ok(())
+}
+
+/// List past executions matching a search term.
+pub async fn process_get_history(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_history(move |search_term| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = show_history(&cfg, &search_term, &db_copy_for_task).await {
+                eprintln!("Error getting history  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Send a history entry again, as it was sent but with the current auth of
+/// its request, and show the response in the tab of the request it came from.
+pub async fn process_rerun_history_entry(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_rerun_history_entry(move |id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let entry = match get_history_entry(&id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting history entry  - {}", error);
+                    return;
+                }
+            };
+            let mut prepared = match PreparedRequest::from_history(&entry) {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error re-running history entry  - {}", error);
+                    return;
+                }
+            };
+            let request = match request_for_entry(&entry, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error restoring history entry  - {}", error);
+                    return;
+                }
+            };
+            // Credentials are never recorded, so the request's current auth
+            // is sent instead.
+            prepared.auth = match resolve_auth(&request, &db_copy_for_task).await {
+                Ok(auth) => auth,
+                Err(error) => {
+                    eprintln!("Error re-running history entry  - {}", error);
+                    return;
+                }
+            };
+            let request_id = request.id.clone();
+            let origin = HistoryOrigin::for_request(&request);
+            if let Err(error) = open_request(&cfg, request, &db_copy_for_task).await {
+                eprintln!("Error opening request  - {}", error);
+                return;
+            }
+
+            cfg.set_request_in_flight(true);
+            let response = match execute_and_record(prepared, origin, &db_copy_for_task).await {
+                Ok(execution) => execution.response,
+                Err(error) => Err(error),
+            };
+            show_response(&cfg, &request_id, response).await;
+            cfg.invoke_get_history(cfg.get_history_search());
+        });
+    });
+
+    Ok(())
+}
+
+/// Copy a history entry into a new request and open it.
+pub async fn process_restore_history_entry(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_restore_history_entry(move |id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let entry = match get_history_entry(&id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting history entry  - {}", error);
+                    return;
+                }
+            };
+            let request = match restore_history_entry(&entry, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error restoring history entry  - {}", error);
+                    return;
+                }
+            };
+            if let Err(error) = open_request(&cfg, request, &db_copy_for_task).await {
+                eprintln!("Error opening request  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_remove_history_entry(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_history_entry(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_history_entry(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting history entry  - {}", error);
+                return;
+            }
+
+            let mut items: Vec<HistoryItem> = cfg.get_history_items().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            show_history_items(&cfg, items);
+
+            // Comparisons of the entry are gone with it.
+            if let Err(error) = show_comparison_tabs(&cfg, &db_copy_for_task).await {
+                eprintln!("Error getting comparisons  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_history(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_clear_history(move || {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = clear_history(&db_copy_for_task).await {
+                eprintln!("Error clearing history  - {}", error);
+                return;
+            }
+            show_history_items(&cfg, Vec::new());
+        });
+    });
+
+    Ok(())
+}
+
+/// Pick an entry for exporting, or drop it again.
+pub async fn process_select_history_entry(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_select_history_entry(move |index, selected| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let items = cfg.get_history_items();
+        if let Some(mut item) = items.row_data(index as usize) {
+            item.selected = selected;
+            items.set_row_data(index as usize, item);
+        }
+        let count = items.iter().filter(|item| item.selected).count();
+        cfg.set_history_selected_count(count as i32);
+    });
+
+    Ok(())
+}
+
+/// Write the selected entries to a HAR file picked by the user. Credentials
+/// are masked unless secrets are included.
+pub async fn process_export_history_har(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_export_history_har(move |include_secrets| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            // Oldest first, the order HAR entries are recorded in.
+            let mut entries = Vec::new();
+            for item in cfg.get_history_items().iter().filter(|item| item.selected) {
+                match get_history_entry(&item.id, &db_copy_for_task).await {
+                    Ok(entry) => entries.insert(0, entry),
+                    Err(error) => {
+                        eprintln!("Error getting history entry  - {}", error);
+                        return;
+                    }
+                }
+            }
+            let contents = match write_har(&entries, include_secrets) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    eprintln!("Error exporting history  - {}", error);
+                    return;
+                }
+            };
+
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("HAR", &["har"])
+                .set_file_name("history.har")
+                .save_file()
+            else {
+                return;
+            };
+            if let Err(error) = fs::write(path, contents) {
+                eprintln!("Error writing HAR file  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Save the retention limits. Fields that aren't a number yet are ignored.
+pub async fn process_update_history_settings(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_history_settings(move |max_entries, max_age_days| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let (max_entries, max_age_days) =
+            match (parse_limit(&max_entries), parse_limit(&max_age_days)) {
+                (Some(max_entries), Some(max_age_days)) => (max_entries, max_age_days),
+                _ => return,
+            };
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) =
+                update_history_settings(max_entries, max_age_days, &db_copy_for_task).await
+            {
+                eprintln!("Error updating history settings  - {}", error);
+                return;
+            }
+            // Lower limits may have removed entries.
+            if let Err(error) =
+                show_history(&cfg, &cfg.get_history_search(), &db_copy_for_task).await
+            {
+                eprintln!("Error getting history  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
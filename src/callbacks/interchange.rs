@@ -0,0 +1,447 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use slint::{ComponentHandle, Model, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        environments::load_environments,
+        requests::{open_request_tab, to_request_item},
+    },
+    utils::{
+        crud::requests::get_single_request,
+        interchange::{
+            curl_command, export_collections, find_spec_collection,
+            har::{self, HarEntry, HarFilter},
+            import_curl, import_file, import_har, load_all_collections, load_collection,
+            update_from_spec, ExportFormats, ImportReport,
+        },
+    },
+    AppConfig, AppWindow, CollectionItem, HarEntryItem, RequestItem,
+};
+
+/// Show what an import created and left out, or why it failed.
+async fn show_import_result(
+    imported: Result<Vec<ImportReport>, Box<dyn Error>>,
+    file_name: &str,
+    db: &SqlitePool,
+    app: &AppWindow,
+) {
+    let cfg = app.global::<AppConfig>();
+
+    let unmapped: Vec<SharedString> = match imported {
+        Ok(reports) => {
+            let title = match reports.as_slice() {
+                [report] if report.updated > 0 => format!(
+                    "Updated {}: {} requests updated, {} added",
+                    report.collection.name, report.updated, report.requests
+                ),
+                [report] => format!(
+                    "Imported {} with {} requests",
+                    report.collection.name, report.requests
+                ),
+                _ => format!("Imported {} collections", reports.len()),
+            };
+            cfg.set_import_report_title(title.into());
+            cfg.set_import_error("".into());
+            cfg.set_page(2);
+            cfg.invoke_get_collections();
+            // Imports may bring environments along.
+            if let Err(error) = load_environments(db, app).await {
+                eprintln!("Error loading environments  - {}", error);
+            }
+            reports
+                .into_iter()
+                .flat_map(|report| report.unmapped)
+                .map(|item| item.into())
+                .collect()
+        }
+        Err(error) => {
+            eprintln!("Error importing collection  - {}", error);
+            cfg.set_import_report_title(format!("Could not import {}", file_name).into());
+            cfg.set_import_error(error.to_string().into());
+            Vec::new()
+        }
+    };
+    cfg.set_import_report_items(Rc::new(VecModel::from(unmapped)).into());
+    cfg.set_show_import_report(true);
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn to_har_entry_item(entry: &HarEntry) -> HarEntryItem {
+    HarEntryItem {
+        http_method: entry.request.method.to_string().into(),
+        url: entry.request.url.clone().into(),
+        status: entry.status as i32,
+        content_type: entry.content_type.clone().into(),
+    }
+}
+
+fn filter_choices(all: &str, values: Vec<String>) -> Rc<VecModel<SharedString>> {
+    let choices: Vec<SharedString> = std::iter::once(all.to_string())
+        .chain(values)
+        .map(|value| value.into())
+        .collect();
+    Rc::new(VecModel::from(choices))
+}
+
+/// Show the entries of a HAR file that pass the filters.
+fn show_har_entries(cfg: &AppConfig, entries: &[HarEntry], filter: &HarFilter) {
+    let items: Vec<HarEntryItem> = entries
+        .iter()
+        .filter(|entry| filter.matches(entry))
+        .map(to_har_entry_item)
+        .collect();
+    cfg.set_har_entries(Rc::new(VecModel::from(items)).into());
+}
+
+/// Preview a HAR file, letting the user pick the entries to import.
+fn show_har_dialog(cfg: &AppConfig, path: &Path, entries: &[HarEntry]) {
+    let values = har::filter_values(entries);
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    cfg.set_har_path(path.to_string_lossy().to_string().into());
+    cfg.set_har_collection_name(name.into());
+    cfg.set_har_domains(filter_choices("All domains", values.domains).into());
+    cfg.set_har_methods(filter_choices("All methods", values.methods).into());
+    cfg.set_har_content_types(filter_choices("All content types", values.content_types).into());
+    show_har_entries(cfg, entries, &HarFilter::default());
+    cfg.set_har_error("".into());
+    cfg.set_show_har_dialog(true);
+}
+
+/// Import a collection file or OpenAPI spec picked by the user, then show
+/// what was left out. Specs imported before ask whether to update instead,
+/// and HAR files are previewed first.
+pub async fn process_import_collection(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_import_collection(move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Collection or spec", &["json", "yaml", "yml", "har"])
+            .pick_file()
+        else {
+            return;
+        };
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    show_import_result(
+                        Err(error.into()),
+                        &file_name(&path),
+                        &db_copy_for_task,
+                        &app,
+                    )
+                    .await;
+                    return;
+                }
+            };
+            if let Ok(entries) = har::parse_entries(&contents) {
+                show_har_dialog(&cfg, &path, &entries);
+                return;
+            }
+            if let Ok(Some(collection)) = find_spec_collection(&contents, &db_copy_for_task).await {
+                cfg.set_reimport_path(path.to_string_lossy().to_string().into());
+                cfg.set_reimport_collection_id(collection.id.into());
+                cfg.set_reimport_collection_name(collection.name.into());
+                cfg.set_show_reimport_dialog(true);
+                return;
+            }
+
+            let imported = import_file(&contents, &db_copy_for_task).await;
+            show_import_result(imported, &file_name(&path), &db_copy_for_task, &app).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Update the collection of a spec imported before, or import it again as a copy.
+pub async fn process_reimport_spec(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_reimport_spec(move |update| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let path = PathBuf::from(cfg.get_reimport_path().as_str());
+            let imported = match fs::read_to_string(&path) {
+                Ok(contents) if update => update_from_spec(
+                    &contents,
+                    &cfg.get_reimport_collection_id(),
+                    &db_copy_for_task,
+                )
+                .await
+                .map(|report| vec![report]),
+                Ok(contents) => import_file(&contents, &db_copy_for_task).await,
+                Err(error) => Err(error.into()),
+            };
+            show_import_result(imported, &file_name(&path), &db_copy_for_task, &app).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Narrow down the entries of the previewed HAR file.
+pub async fn process_filter_har(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_filter_har(move |domain, method, content_type| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let filter = HarFilter {
+            domain: domain.to_string(),
+            method: method.to_string(),
+            content_type: content_type.to_string(),
+        };
+        let entries = fs::read_to_string(cfg.get_har_path().as_str())
+            .map_err(|error| error.into())
+            .and_then(|contents| har::parse_entries(&contents));
+        match entries {
+            Ok(entries) => show_har_entries(&cfg, &entries, &filter),
+            Err(error) => {
+                eprintln!("Error reading HAR file  - {}", error);
+                cfg.set_har_error(error.to_string().into());
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Import the entries of the previewed HAR file that pass the filters.
+pub async fn process_import_har(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_import_har(move |name, domain, method, content_type| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let filter = HarFilter {
+                domain: domain.to_string(),
+                method: method.to_string(),
+                content_type: content_type.to_string(),
+            };
+            let path = PathBuf::from(cfg.get_har_path().as_str());
+            let imported = match fs::read_to_string(&path) {
+                Ok(contents) => import_har(&contents, &name, &filter, &db_copy_for_task)
+                    .await
+                    .map(|report| vec![report]),
+                Err(error) => Err(error.into()),
+            };
+            cfg.set_show_har_dialog(false);
+            show_import_result(imported, &file_name(&path), &db_copy_for_task, &app).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Export one or all collections to a file picked by the user.
+pub async fn process_export_collections(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_export_collections(move |collection_id, format, all, include_secrets| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let Some(format) = ExportFormats::from_string(&format) else {
+                return;
+            };
+            let specs = match all {
+                true => load_all_collections(include_secrets, &db_copy_for_task).await,
+                false => load_collection(&collection_id, include_secrets, &db_copy_for_task)
+                    .await
+                    .map(|spec| vec![spec]),
+            };
+            let files = match specs.and_then(|specs| export_collections(&specs, format)) {
+                Ok(files) => files,
+                Err(error) => {
+                    eprintln!("Error exporting collections  - {}", error);
+                    cfg.set_export_error(error.to_string().into());
+                    return;
+                }
+            };
+
+            // Postman files hold a single collection, so several go into a folder.
+            let written = match files.as_slice() {
+                [file] => rfd::FileDialog::new()
+                    .add_filter("JSON", &["json"])
+                    .set_file_name(&file.file_name)
+                    .save_file()
+                    .map(|path| fs::write(path, &file.contents)),
+                _ => rfd::FileDialog::new().pick_folder().map(|folder| {
+                    files.iter().try_for_each(|file| {
+                        fs::write(folder.join(&file.file_name), &file.contents)
+                    })
+                }),
+            };
+            match written {
+                None => return,
+                Some(Err(error)) => {
+                    eprintln!("Error writing export  - {}", error);
+                    cfg.set_export_error(error.to_string().into());
+                    return;
+                }
+                Some(Ok(())) => {}
+            }
+
+            let unmapped: Vec<SharedString> = files
+                .into_iter()
+                .flat_map(|file| file.unmapped)
+                .map(|item| item.into())
+                .collect();
+            cfg.set_export_error("".into());
+            if unmapped.is_empty() {
+                cfg.set_show_export_dialog(false);
+            }
+            cfg.set_export_report_items(Rc::new(VecModel::from(unmapped)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Add the request a pasted cURL command describes to the open collection.
+pub async fn process_import_curl(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_import_curl(move |collection_id, collection_index, command| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if collection_id.is_empty() {
+                cfg.set_curl_error("Open a collection to add the request to.".into());
+                return;
+            }
+            let (request, unmapped) =
+                match import_curl(&command, &collection_id, &db_copy_for_task).await {
+                    Ok(imported) => imported,
+                    Err(error) => {
+                        eprintln!("Error importing cURL command  - {}", error);
+                        cfg.set_curl_error(error.to_string().into());
+                        return;
+                    }
+                };
+            let request_item = to_request_item(request);
+
+            let mut items: Vec<RequestItem> = cfg.get_active_collection_requests().iter().collect();
+            items.insert(0, request_item.clone());
+            cfg.set_active_collection_requests(Rc::new(VecModel::from(items)).into());
+
+            let mut collections: Vec<CollectionItem> = cfg.get_collection_items().iter().collect();
+            let collection_icon = match collections.get_mut(collection_index as usize) {
+                Some(collection) => {
+                    collection.request_count += 1;
+                    collection.icon.clone()
+                }
+                None => Default::default(),
+            };
+            cfg.set_collection_items(Rc::new(VecModel::from(collections)).into());
+            open_request_tab(&cfg, request_item, collection_icon);
+
+            let unmapped: Vec<SharedString> =
+                unmapped.into_iter().map(|item| item.into()).collect();
+            cfg.set_curl_error("".into());
+            if unmapped.is_empty() {
+                cfg.set_show_curl_dialog(false);
+            }
+            cfg.set_curl_report_items(Rc::new(VecModel::from(unmapped)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Show the cURL command for a request, ready to be copied.
+pub async fn process_copy_as_curl(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_copy_as_curl(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let command = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => curl_command(&request, &db_copy_for_task).await,
+                Err(error) => Err(error),
+            };
+            match command {
+                Ok(command) => {
+                    cfg.set_curl_command(command.into());
+                    cfg.set_curl_error("".into());
+                }
+                Err(error) => {
+                    eprintln!("Error building cURL command  - {}", error);
+                    cfg.set_curl_command("".into());
+                    cfg.set_curl_error(error.to_string().into());
+                }
+            }
+            cfg.set_curl_report_items(Rc::new(VecModel::from(Vec::<SharedString>::new())).into());
+            cfg.set_curl_import_mode(false);
+            cfg.set_show_curl_dialog(true);
+        });
+    });
+
+    Ok(())
+}
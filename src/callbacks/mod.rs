@@ -1,3 +1,23 @@
+pub mod auth;
+pub mod bodies;
 pub mod collections;
+pub mod compare;
+pub mod environments;
+pub mod executor;
+pub mod graphql;
+pub mod grpc;
+pub mod headers;
+pub mod history;
 pub mod images;
+pub mod interchange;
+pub mod mqtt;
+pub mod params;
+pub mod request_details;
 pub mod requests;
+pub mod response_view;
+pub mod sessions;
+pub mod snippets;
+pub mod socketio;
+pub mod sse;
+pub mod variables;
+pub mod websocket;
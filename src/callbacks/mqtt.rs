@@ -0,0 +1,398 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        requests::{sync_request_item, to_request_item},
+        sessions::{follow_log, SessionRegistry},
+        websocket::to_message_item,
+    },
+    utils::{
+        crud::{
+            mqtt::{get_mqtt_config, update_mqtt_config, MqttConfigData},
+            requests::{
+                get_single_request, parse_method, update_request_item, ProtocolTypes, RequestData,
+            },
+        },
+        mqtt::{
+            connect_mqtt, parse_filters, prepare_mqtt, prepare_publish, EntryKinds, MqttEntry,
+            MqttSession,
+        },
+        websocket::Directions,
+    },
+    AppConfig, AppWindow, WebSocketMessageItem,
+};
+
+thread_local! {
+    static CLIENTS: SessionRegistry<MqttSession, MqttEntry> = SessionRegistry::default();
+}
+
+/// Show the client of a request, if it is the one in the request pane.
+fn show_client(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let (state, log) = CLIENTS.with(|clients| {
+        clients.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("disconnected", vec![]);
+            };
+            let status = match (&state.session, state.connecting) {
+                (_, true) => "connecting",
+                (Some(_), false) => "connected",
+                (None, false) => "disconnected",
+            };
+            let log: Vec<WebSocketMessageItem> = state.log.iter().map(to_message_item).collect();
+            (status, log)
+        })
+    });
+
+    cfg.set_active_mqtt_state(state.into());
+    cfg.set_active_mqtt_log(Rc::new(VecModel::from(log)).into());
+}
+
+fn set_error(cfg: &AppConfig, request_id: &str, error: &str) {
+    if cfg.get_active_request().id == request_id {
+        cfg.set_active_mqtt_error(error.into());
+    }
+}
+
+/// The settings as shown in the request pane. Numbers that don't parse
+/// fall back to the defaults.
+fn config_data(cfg: &AppConfig) -> MqttConfigData {
+    let defaults = MqttConfigData::default();
+    MqttConfigData {
+        client_id: cfg.get_active_mqtt_client_id().trim().to_string(),
+        clean_session: cfg.get_active_mqtt_clean_session(),
+        keep_alive: cfg
+            .get_active_mqtt_keep_alive()
+            .trim()
+            .parse()
+            .unwrap_or(defaults.keep_alive),
+        filters: cfg.get_active_mqtt_filters().to_string(),
+        subscribe_qos: cfg
+            .get_active_mqtt_subscribe_qos()
+            .parse()
+            .unwrap_or(defaults.subscribe_qos),
+        topic: cfg.get_active_mqtt_topic().to_string(),
+        payload: cfg.get_active_mqtt_payload().to_string(),
+        publish_qos: cfg
+            .get_active_mqtt_publish_qos()
+            .parse()
+            .unwrap_or(defaults.publish_qos),
+        retain: cfg.get_active_mqtt_retain(),
+    }
+}
+
+/// Load the settings and client of an MQTT request.
+pub async fn load_mqtt_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let config = get_mqtt_config(request_id, db).await?;
+    cfg.set_active_mqtt_client_id(config.client_id.into());
+    cfg.set_active_mqtt_clean_session(config.clean_session);
+    cfg.set_active_mqtt_keep_alive(config.keep_alive.to_string().into());
+    cfg.set_active_mqtt_filters(config.filters.into());
+    cfg.set_active_mqtt_subscribe_qos(config.subscribe_qos.to_string().into());
+    cfg.set_active_mqtt_topic(config.topic.into());
+    cfg.set_active_mqtt_payload(config.payload.into());
+    cfg.set_active_mqtt_publish_qos(config.publish_qos.to_string().into());
+    cfg.set_active_mqtt_retain(config.retain);
+    cfg.set_active_mqtt_error(SharedString::new());
+    show_client(cfg, request_id);
+
+    Ok(())
+}
+
+/// Disconnect the client of a request, if it has one. Its log is kept.
+pub fn close_mqtt_client(request_id: &str) {
+    CLIENTS.with(|clients| clients.with_session(request_id, MqttSession::disconnect));
+}
+
+/// Forget everything about the client of a request, disconnecting it.
+pub fn remove_mqtt_request(request_id: &str) {
+    CLIENTS.with(|clients| clients.remove(request_id));
+}
+
+/// The request, with the URL currently in the request pane saved first.
+async fn current_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let active_request = cfg.get_active_request();
+    if active_request.id != request_id {
+        return Ok(request);
+    }
+
+    let request = update_request_item(
+        &request.id,
+        &request.name,
+        ProtocolTypes::Mqtt,
+        parse_method(&active_request.http_method)?,
+        &cfg.get_active_request_url(),
+        db,
+    )
+    .await?;
+    sync_request_item(cfg, to_request_item(request.clone()));
+
+    Ok(request)
+}
+
+/// Run `action` with the client of a request, if it is connected.
+fn with_session(
+    request_id: &str,
+    action: impl FnOnce(&MqttSession) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    CLIENTS
+        .with(|clients| clients.with_session(request_id, action))
+        .unwrap_or_else(|| Err("The client is disconnected.".into()))
+}
+
+pub async fn process_update_mqtt_config(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_mqtt_config(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let config = config_data(&cfg);
+            if let Err(error) = update_mqtt_config(&request_id, &config, &db_copy_for_task).await {
+                eprintln!("Error updating MQTT settings  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_connect_mqtt(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_connect_mqtt(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            let request = match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                Ok(request) => request,
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
+                    return;
+                }
+            };
+
+            set_error(&cfg, &request_id, "");
+            let prepared = match get_mqtt_config(&request_id, &db_copy_for_task).await {
+                Ok(config) => prepare_mqtt(&request, &config, &db_copy_for_task).await,
+                Err(error) => Err(error),
+            };
+            let options = match prepared {
+                Ok(options) => options,
+                Err(error) => {
+                    set_error(&cfg, &request_id, &error.to_string());
+                    return;
+                }
+            };
+
+            let generation = CLIENTS.with(|clients| clients.begin(&request_id));
+            show_client(&cfg, &request_id);
+
+            let log = match connect_mqtt(options).await {
+                Ok(connection) => {
+                    CLIENTS.with(|clients| clients.connected(&request_id, connection.session));
+                    connection.log
+                }
+                Err(error) => {
+                    CLIENTS.with(|clients| {
+                        clients.failed(
+                            &request_id,
+                            MqttEntry::new(Directions::Info, EntryKinds::Error, &error.to_string()),
+                        )
+                    });
+                    show_client(&cfg, &request_id);
+                    return;
+                }
+            };
+            show_client(&cfg, &request_id);
+            // The window may be closed while the client is connected.
+            drop(app);
+
+            follow_log(
+                &CLIENTS,
+                &request_id,
+                generation,
+                log,
+                &weak_app_for_task,
+                show_client,
+                |_| (),
+            )
+            .await;
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_disconnect_mqtt(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_disconnect_mqtt(move |request_id| {
+        close_mqtt_client(&request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_publish_mqtt(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_publish_mqtt(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let config = config_data(&cfg);
+            if let Err(error) = update_mqtt_config(&request_id, &config, &db_copy_for_task).await {
+                eprintln!("Error updating MQTT settings  - {}", error);
+                return;
+            }
+            let request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => request,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            let published = prepare_publish(&request, &config, &db_copy_for_task)
+                .await
+                .and_then(|(topic, payload)| {
+                    with_session(&request_id, |session| {
+                        session.publish(&topic, &payload, config.publish_qos as u8, config.retain)
+                    })
+                });
+
+            match published {
+                Ok(()) => set_error(&cfg, &request_id, ""),
+                Err(error) => set_error(&cfg, &request_id, &error.to_string()),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_subscribe_mqtt(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_subscribe_mqtt(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let config = config_data(&cfg);
+            if let Err(error) = update_mqtt_config(&request_id, &config, &db_copy_for_task).await {
+                eprintln!("Error updating MQTT settings  - {}", error);
+                return;
+            }
+
+            let subscribed = parse_filters(&config.filters).and_then(|filters| {
+                with_session(&request_id, |session| {
+                    session.subscribe(&filters, config.subscribe_qos as u8)
+                })
+            });
+
+            match subscribed {
+                Ok(()) => set_error(&cfg, &request_id, ""),
+                Err(error) => set_error(&cfg, &request_id, &error.to_string()),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_unsubscribe_mqtt(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_unsubscribe_mqtt(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let config = config_data(&cfg);
+            if let Err(error) = update_mqtt_config(&request_id, &config, &db_copy_for_task).await {
+                eprintln!("Error updating MQTT settings  - {}", error);
+                return;
+            }
+
+            let unsubscribed = parse_filters(&config.filters).and_then(|filters| {
+                with_session(&request_id, |session| session.unsubscribe(&filters))
+            });
+
+            match unsubscribed {
+                Ok(()) => set_error(&cfg, &request_id, ""),
+                Err(error) => set_error(&cfg, &request_id, &error.to_string()),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_mqtt_log(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_mqtt_log(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        CLIENTS.with(|clients| clients.clear_log(&request_id));
+        show_client(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
@@ -0,0 +1,242 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        environments::refresh_unresolved_variables,
+        requests::{sync_request_item, to_request_item},
+    },
+    utils::{
+        crud::{
+            params::{
+                create_request_param, delete_request_param, get_request_params,
+                replace_request_params, update_request_param, RequestParamData,
+            },
+            requests::{get_single_request, update_request_url},
+        },
+        query_params::{merge_url_params, parse_query, url_from_params, ParamRow},
+    },
+    AppConfig, AppWindow, KeyValueItem,
+};
+
+pub fn param_to_key_value_item(param: RequestParamData) -> KeyValueItem {
+    KeyValueItem {
+        id: param.id.into(),
+        name: param.name.into(),
+        value: param.value.into(),
+        enabled: param.enabled,
+    }
+}
+
+/// Show the query params of a request in the request pane.
+pub async fn load_request_params(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let params: Vec<KeyValueItem> = get_request_params(request_id, db)
+        .await?
+        .into_iter()
+        .map(param_to_key_value_item)
+        .collect();
+
+    cfg.set_active_request_params(Rc::new(VecModel::from(params)).into());
+    Ok(())
+}
+
+/// Rewrite the URL of a request from its params table.
+async fn rebuild_url_from_params(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let rows: Vec<ParamRow> = get_request_params(request_id, db)
+        .await?
+        .iter()
+        .map(|param| param.to_row())
+        .collect();
+
+    let url = url_from_params(&request.url.unwrap_or_default(), &rows);
+    let request = update_request_url(request_id, &url, db).await?;
+
+    if cfg.get_active_request().id == request_id {
+        cfg.set_active_request_url(url.into());
+    }
+    sync_request_item(cfg, to_request_item(request));
+    refresh_unresolved_variables(cfg, db).await;
+    Ok(())
+}
+
+/// Update the params table after the URL was edited.
+pub async fn process_sync_params_from_url(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_sync_params_from_url(move |request_id, url| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let existing: Vec<ParamRow> =
+                match get_request_params(&request_id, &db_copy_for_task).await {
+                    Ok(data) => data.iter().map(|param| param.to_row()).collect(),
+                    Err(error) => {
+                        eprintln!("Error getting params  - {}", error);
+                        return;
+                    }
+                };
+            let rows = merge_url_params(&existing, parse_query(&url));
+
+            let params = match replace_request_params(&request_id, &rows, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error saving params  - {}", error);
+                    return;
+                }
+            };
+            let request = match update_request_url(&request_id, &url, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
+                    return;
+                }
+            };
+
+            let items: Vec<KeyValueItem> =
+                params.into_iter().map(param_to_key_value_item).collect();
+            cfg.set_active_request_params(Rc::new(VecModel::from(items)).into());
+            sync_request_item(&cfg, to_request_item(request));
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty query param to a request.
+pub async fn process_create_request_param(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_request_param(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let param = match create_request_param(&request_id, "", "", &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error creating param  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_params().iter().collect();
+            items.push(param_to_key_value_item(param));
+            cfg.set_active_request_params(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a query param edited in the params grid and rewrite the URL.
+pub async fn process_update_request_param(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_param(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let param =
+                match update_request_param(&id, &name, &value, enabled, &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating param  - {}", error);
+                        return;
+                    }
+                };
+            let request_id = param.request_id.clone();
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_active_request_params();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, param_to_key_value_item(param));
+            }
+
+            if let Err(error) = rebuild_url_from_params(&cfg, &request_id, &db_copy_for_task).await
+            {
+                eprintln!("Error updating URL  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a query param from a request and rewrite the URL.
+pub async fn process_remove_request_param(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_request_param(move |request_id, id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            match delete_request_param(&id, &db_copy_for_task).await {
+                Ok(_) => {}
+                Err(error) => {
+                    eprintln!("Error deleting param  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_params().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_request_params(Rc::new(VecModel::from(items)).into());
+
+            if let Err(error) = rebuild_url_from_params(&cfg, &request_id, &db_copy_for_task).await
+            {
+                eprintln!("Error updating URL  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+
+use slint::ComponentHandle;
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        auth::load_request_auth, bodies::load_request_body,
+        environments::refresh_unresolved_variables, graphql::load_graphql_request,
+        grpc::load_grpc_request, headers::load_request_headers, mqtt::load_mqtt_request,
+        params::load_request_params, socketio::load_socketio_request, sse::load_sse_request,
+        variables::load_request_variables, websocket::load_websocket_session,
+    },
+    AppConfig, AppWindow,
+};
+
+/// Load everything the request pane shows for the active request.
+pub async fn process_load_request_details(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_load_request_details(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = load_request_params(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading params  - {}", error);
+            }
+            if let Err(error) = load_request_headers(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading headers  - {}", error);
+            }
+            if let Err(error) = load_request_body(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading body  - {}", error);
+            }
+            if let Err(error) = load_request_auth(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading auth  - {}", error);
+            }
+            if let Err(error) = load_request_variables(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading variables  - {}", error);
+            }
+            if let Err(error) = load_websocket_session(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading WebSocket session  - {}", error);
+            }
+            if let Err(error) = load_graphql_request(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading GraphQL query  - {}", error);
+            }
+            if let Err(error) = load_grpc_request(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading gRPC call  - {}", error);
+            }
+            if let Err(error) = load_sse_request(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading SSE settings  - {}", error);
+            }
+            if let Err(error) = load_socketio_request(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading Socket.IO settings  - {}", error);
+            }
+            if let Err(error) = load_mqtt_request(&cfg, &request_id, &db_copy_for_task).await {
+                eprintln!("Error loading MQTT settings  - {}", error);
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
@@ -1,16 +1,85 @@
 use std::{error::Error, rc::Rc};
 
-use slint::{ComponentHandle, Model, VecModel};
+use slint::{ComponentHandle, Image, Model, VecModel};
 use sqlx::SqlitePool;
 
 use crate::{
+    callbacks::{
+        graphql::{close_graphql_subscription, remove_graphql_request},
+        grpc::{close_grpc_call, remove_grpc_request},
+        mqtt::{close_mqtt_client, remove_mqtt_request},
+        socketio::{close_socketio_socket, remove_socketio_request},
+        sse::{close_sse_stream, remove_sse_request},
+        websocket::{close_websocket_session, remove_websocket_session},
+    },
     utils::crud::requests::{
-        create_request, delete_request, get_collection_requests, update_request_item, HTTPMethods,
-        ProtocolTypes,
+        create_request, delete_request, get_collection_requests, get_single_request, parse_method,
+        stored_method, update_request_item, ProtocolTypes, RequestData,
     },
     AppConfig, AppWindow, CollectionItem, RequestItem, SelectedRequestItem,
 };
 
+/// Convert a stored request into its UI representation.
+pub fn to_request_item(request: RequestData) -> RequestItem {
+    RequestItem {
+        id: request.id.into(),
+        name: request.name.into(),
+        url: request.url.unwrap_or("".to_string()).into(),
+        protocol: request.protocol.into(),
+        http_method: request.http_method.unwrap_or("get".to_string()).into(),
+        folder: request.folder.into(),
+    }
+}
+
+/// Replace every copy of a request shown in the UI with its latest version.
+pub fn sync_request_item(cfg: &AppConfig, request_item: RequestItem) {
+    if cfg.get_active_request().id == request_item.id {
+        cfg.set_active_request(request_item.clone());
+    }
+
+    let mut selected_requests: Vec<SelectedRequestItem> =
+        cfg.get_selected_requests().iter().collect();
+    for selected_request in selected_requests.iter_mut() {
+        if selected_request.item.id == request_item.id {
+            selected_request.item = request_item.clone();
+        }
+    }
+    cfg.set_selected_requests(Rc::new(VecModel::from(selected_requests)).into());
+
+    let mut collection_requests: Vec<RequestItem> =
+        cfg.get_active_collection_requests().iter().collect();
+    for collection_request in collection_requests.iter_mut() {
+        if collection_request.id == request_item.id {
+            *collection_request = request_item.clone();
+        }
+    }
+    cfg.set_active_collection_requests(Rc::new(VecModel::from(collection_requests)).into());
+}
+
+/// Add a request to the open tabs, unless it is already open, and show it.
+pub fn open_request_tab(cfg: &AppConfig, request_item: RequestItem, collection_icon: Image) {
+    let mut selected_requests: Vec<SelectedRequestItem> =
+        cfg.get_selected_requests().iter().collect();
+
+    let request_already_selected = selected_requests
+        .iter()
+        .any(|selected_request| selected_request.item.id == request_item.id);
+
+    if !request_already_selected {
+        selected_requests.push(SelectedRequestItem {
+            item: request_item.clone(),
+            collection_icon,
+        });
+        cfg.set_selected_requests(Rc::new(VecModel::from(selected_requests)).into());
+    }
+
+    cfg.set_active_comparison_id("".into());
+    cfg.set_active_request_id(request_item.id.clone());
+    cfg.set_active_request(request_item.clone());
+    cfg.set_active_request_url(request_item.url.clone());
+    cfg.invoke_load_request_details(request_item.id);
+}
+
 /// Get requests
 pub async fn process_get_requests(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
     let config = app.global::<AppConfig>();
@@ -39,6 +108,7 @@ pub async fn process_get_requests(db: &SqlitePool, app: &AppWindow) -> Result<()
                     url: item.url.unwrap_or("".to_string()).into(),
                     protocol: item.protocol.into(),
                     http_method: item.http_method.unwrap_or("get".to_string()).into(),
+                    folder: item.folder.into(),
                 })
                 .collect();
 
@@ -82,6 +152,7 @@ pub async fn process_create_requests(
                     url: request_item.url.unwrap_or("".to_string()).into(),
                     protocol: request_item.protocol.into(),
                     http_method: request_item.http_method.unwrap_or("get".to_string()).into(),
+                    folder: request_item.folder.into(),
                 };
 
                 let mut items: Vec<RequestItem> =
@@ -118,36 +189,39 @@ pub async fn process_update_request(
             let app = weak_app_for_task.upgrade().unwrap();
             let cfg = app.global::<AppConfig>();
 
-            let request_item = match update_request_item(
-                &request_id,
-                &name,
-                ProtocolTypes::from_string(&protocol).unwrap_or(ProtocolTypes::Http),
-                HTTPMethods::from_string(&http_method).unwrap_or(HTTPMethods::Get),
-                &url,
-                &db_copy_for_task,
-            )
-            .await
-            {
+            let updated = match parse_method(&http_method) {
+                Ok(method) => {
+                    update_request_item(
+                        &request_id,
+                        &name,
+                        ProtocolTypes::from_string(&protocol).unwrap_or(ProtocolTypes::Http),
+                        method,
+                        &url,
+                        &db_copy_for_task,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            let request_item = match updated {
                 Ok(data) => data,
-                Err(_) => {
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
                     return;
                 }
             };
 
-            let request_data: RequestItem = RequestItem {
-                id: request_item.id.into(),
-                name: request_item.name.into(),
-                url: request_item.url.unwrap_or("".to_string()).into(),
-                protocol: request_item.protocol.into(),
-                http_method: request_item.http_method.unwrap_or("get".to_string()).into(),
-            };
+            let request_data = to_request_item(request_item);
 
             let mut items: Vec<RequestItem> = cfg.get_active_collection_requests().iter().collect();
 
             if let Some(item_ref) = items.get_mut(index as usize) {
-                *item_ref = request_data;
+                *item_ref = request_data.clone();
             }
             cfg.set_active_collection_requests(Rc::new(VecModel::from(items)).into());
+
+            // Keep open tabs and the request pane in step with the sidebar.
+            sync_request_item(&cfg, request_data);
         });
     });
 
@@ -171,7 +245,14 @@ pub async fn process_delete_request(
             let cfg = app.global::<AppConfig>();
 
             match delete_request(&request_id, &db_copy_for_task).await {
-                Ok(_) => {}
+                Ok(_) => {
+                    remove_websocket_session(&request_id);
+                    remove_graphql_request(&request_id);
+                    remove_grpc_request(&request_id);
+                    remove_sse_request(&request_id);
+                    remove_socketio_request(&request_id);
+                    remove_mqtt_request(&request_id);
+                }
                 Err(error) => {
                     eprintln!("Error deleting request  - {}", error);
                     return;
@@ -225,24 +306,11 @@ pub async fn process_request_selection(app: &AppWindow) -> Result<(), Box<dyn Er
             return;
         };
 
-        // Add request to selected requests.
-        let mut selected_requests: Vec<SelectedRequestItem> =
-            cfg.get_selected_requests().iter().collect();
-
-        let mut request_already_selected = false;
-        for request_item in &selected_requests {
-            if request_item.item.id == selected_request.id {
-                request_already_selected = true;
-            }
-        }
-
-        if !request_already_selected {
-            selected_requests.push(SelectedRequestItem {
-                item: selected_request.clone(),
-                collection_icon: active_collection.icon.clone(),
-            });
-            cfg.set_selected_requests(Rc::new(VecModel::from(selected_requests)).into());
-        }
+        open_request_tab(
+            &cfg,
+            selected_request.clone(),
+            active_collection.icon.clone(),
+        );
     });
 
     Ok(())
@@ -261,14 +329,164 @@ pub async fn process_request_remove(app: &AppWindow) -> Result<(), Box<dyn Error
         let mut selected_requests: Vec<SelectedRequestItem> =
             cfg.get_selected_requests().iter().collect();
 
-        if selected_requests.get(request_index as usize).is_some() {
+        let removed_request = if selected_requests.get(request_index as usize).is_some() {
             selected_requests.remove(request_index as usize)
         } else {
             return;
         };
 
+        if removed_request.item.id == cfg.get_active_request_id() {
+            cfg.set_active_request_id("".into());
+            cfg.set_active_request(RequestItem::default());
+            cfg.set_active_request_url("".into());
+            cfg.set_active_request_unresolved("".into());
+        }
+
         cfg.set_selected_requests(Rc::new(VecModel::from(selected_requests)).into());
     });
 
     Ok(())
 }
+
+/// Save edits made to the request open in the request pane.
+pub async fn process_update_active_request(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_active_request(move |request_id, http_method, url| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            let updated = match parse_method(&http_method) {
+                Ok(method) => {
+                    update_request_item(
+                        &request.id,
+                        &request.name,
+                        ProtocolTypes::from_string(&request.protocol)
+                            .unwrap_or(ProtocolTypes::Http),
+                        method,
+                        &url,
+                        &db_copy_for_task,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            let request_item = match updated {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
+                    return;
+                }
+            };
+
+            sync_request_item(&cfg, to_request_item(request_item));
+        });
+    });
+
+    Ok(())
+}
+
+/// Let the method picker reject custom verbs that aren't HTTP tokens.
+pub async fn process_is_http_method(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_is_http_method(|http_method| parse_method(&http_method).is_ok());
+
+    Ok(())
+}
+
+/// Switch the request open in the request pane to another protocol.
+pub async fn process_update_active_request_protocol(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_active_request_protocol(move |request_id, protocol| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let protocol = match ProtocolTypes::from_string(&protocol) {
+                Some(protocol) => protocol,
+                None => return,
+            };
+
+            let request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            // The method is kept for when the request goes back to HTTP.
+            let updated = match stored_method(request.http_method.as_deref()) {
+                Ok(method) => {
+                    update_request_item(
+                        &request.id,
+                        &request.name,
+                        protocol.clone(),
+                        method,
+                        &cfg.get_active_request_url(),
+                        &db_copy_for_task,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            let request_item = match updated {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
+                    return;
+                }
+            };
+
+            if protocol != ProtocolTypes::Websocket {
+                close_websocket_session(&request_id);
+            }
+            if protocol != ProtocolTypes::GraphQL {
+                close_graphql_subscription(&request_id);
+            }
+            if protocol != ProtocolTypes::Grpc {
+                close_grpc_call(&request_id);
+            }
+            if protocol != ProtocolTypes::Sse {
+                close_sse_stream(&request_id);
+            }
+            if protocol != ProtocolTypes::SocketIo {
+                close_socketio_socket(&request_id);
+            }
+            if protocol != ProtocolTypes::Mqtt {
+                close_mqtt_client(&request_id);
+            }
+            sync_request_item(&cfg, to_request_item(request_item));
+            cfg.invoke_load_request_details(request_id);
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::rc::Rc;
+
+use slint::{
+    Image, Model, ModelNotify, ModelRc, ModelTracker, Rgba8Pixel, SharedPixelBuffer, VecModel,
+};
+
+use crate::{
+    utils::response_view::{
+        hex_row, hex_row_count, highlight_line, BodyKind, DecodedImage, TextLines, TokenKind,
+    },
+    ResponseLineItem, ResponseTokenItem,
+};
+
+fn to_line_item(number: String, tokens: Vec<(TokenKind, String)>) -> ResponseLineItem {
+    let tokens: Vec<ResponseTokenItem> = tokens
+        .into_iter()
+        .map(|(kind, text)| ResponseTokenItem {
+            text: text.into(),
+            kind: kind.to_string().into(),
+        })
+        .collect();
+
+    ResponseLineItem {
+        number: number.into(),
+        tokens: Rc::new(VecModel::from(tokens)).into(),
+    }
+}
+
+/// Lines of a body, highlighted only when the list view asks for them.
+pub struct ResponseLinesModel {
+    lines: TextLines,
+    /// `None` shows the lines without highlighting.
+    kind: Option<BodyKind>,
+    notify: ModelNotify,
+}
+
+impl ResponseLinesModel {
+    pub fn new(lines: TextLines, kind: Option<BodyKind>) -> ResponseLinesModel {
+        ResponseLinesModel {
+            lines,
+            kind,
+            notify: ModelNotify::default(),
+        }
+    }
+}
+
+impl Model for ResponseLinesModel {
+    type Data = ResponseLineItem;
+
+    fn row_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn row_data(&self, row: usize) -> Option<ResponseLineItem> {
+        let (text, number) = self.lines.row(row)?;
+        let tokens = match self.kind {
+            Some(kind) => highlight_line(kind, text),
+            None => vec![(TokenKind::Plain, text.to_string())],
+        };
+        Some(to_line_item(
+            number.map(|number| number.to_string()).unwrap_or_default(),
+            tokens,
+        ))
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+}
+
+/// Hex dump of a body, formatted only when the list view asks for a row.
+pub struct HexLinesModel {
+    bytes: Vec<u8>,
+    notify: ModelNotify,
+}
+
+impl HexLinesModel {
+    pub fn new(bytes: Vec<u8>) -> HexLinesModel {
+        HexLinesModel {
+            bytes,
+            notify: ModelNotify::default(),
+        }
+    }
+}
+
+impl Model for HexLinesModel {
+    type Data = ResponseLineItem;
+
+    fn row_count(&self) -> usize {
+        hex_row_count(self.bytes.len())
+    }
+
+    fn row_data(&self, row: usize) -> Option<ResponseLineItem> {
+        let (offset, hex, ascii) = hex_row(&self.bytes, row)?;
+        Some(to_line_item(
+            offset,
+            vec![
+                (TokenKind::Plain, hex),
+                (TokenKind::Plain, "  ".to_string()),
+                (TokenKind::String, ascii),
+            ],
+        ))
+    }
+
+    fn model_tracker(&self) -> &dyn ModelTracker {
+        &self.notify
+    }
+}
+
+pub fn lines_model(lines: TextLines, kind: Option<BodyKind>) -> ModelRc<ResponseLineItem> {
+    ModelRc::new(ResponseLinesModel::new(lines, kind))
+}
+
+pub fn hex_model(bytes: Vec<u8>) -> ModelRc<ResponseLineItem> {
+    ModelRc::new(HexLinesModel::new(bytes))
+}
+
+pub fn to_slint_image(image: DecodedImage) -> Image {
+    Image::from_rgba8(SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
+        &image.rgba,
+        image.width,
+        image.height,
+    ))
+}
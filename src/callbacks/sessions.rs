@@ -0,0 +1,195 @@
+use std::{cell::RefCell, collections::HashMap, thread::LocalKey};
+
+use slint::{ComponentHandle, Weak};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{AppConfig, AppWindow};
+
+/// Log entries kept per request. The oldest go first.
+const LOG_LIMIT: usize = 1000;
+
+/// A request's connection and what it logged, kept while other requests are
+/// shown. `X` holds whatever else a protocol tracks about it.
+pub struct SessionState<S, E, X> {
+    pub session: Option<S>,
+    /// Set while connecting, until the protocol says the session is ready.
+    pub connecting: bool,
+    pub log: Vec<E>,
+    pub extra: X,
+    /// Bumped on every connect, so a connection that ends late doesn't
+    /// clear the one that replaced it.
+    generation: u64,
+}
+
+impl<S, E, X: Default> Default for SessionState<S, E, X> {
+    fn default() -> Self {
+        SessionState {
+            session: None,
+            connecting: false,
+            log: Vec::new(),
+            extra: X::default(),
+            generation: 0,
+        }
+    }
+}
+
+/// Updates the state of a request from an entry it logs.
+pub type Observer<S, E, X> = fn(&mut SessionState<S, E, X>, &E);
+
+/// The connections of every request of one protocol, by request id.
+///
+/// Slint callbacks all run on the UI thread, so each protocol keeps its
+/// registry in a `thread_local!`.
+pub struct SessionRegistry<S, E, X = ()> {
+    states: RefCell<HashMap<String, SessionState<S, E, X>>>,
+    /// Sees every entry before it is logged.
+    observe: Option<Observer<S, E, X>>,
+}
+
+impl<S, E, X: Default> Default for SessionRegistry<S, E, X> {
+    fn default() -> Self {
+        SessionRegistry {
+            states: RefCell::new(HashMap::new()),
+            observe: None,
+        }
+    }
+}
+
+impl<S, E, X: Default> SessionRegistry<S, E, X> {
+    /// A registry that updates the state of a request from what it logs.
+    pub fn observing(observe: Observer<S, E, X>) -> Self {
+        SessionRegistry {
+            states: RefCell::new(HashMap::new()),
+            observe: Some(observe),
+        }
+    }
+
+    /// Read the state of a request, `None` when it never connected.
+    pub fn read<R>(
+        &self,
+        request_id: &str,
+        read: impl FnOnce(Option<&SessionState<S, E, X>>) -> R,
+    ) -> R {
+        read(self.states.borrow().get(request_id))
+    }
+
+    /// Change the state of a request, if it has one.
+    pub fn update<R>(
+        &self,
+        request_id: &str,
+        update: impl FnOnce(&mut SessionState<S, E, X>) -> R,
+    ) -> Option<R> {
+        self.states.borrow_mut().get_mut(request_id).map(update)
+    }
+
+    /// Run `action` with the session of a request, if it is connected.
+    pub fn with_session<R>(&self, request_id: &str, action: impl FnOnce(&S) -> R) -> Option<R> {
+        self.read(request_id, |state| {
+            state.and_then(|state| state.session.as_ref()).map(action)
+        })
+    }
+
+    pub fn with_session_mut<R>(
+        &self,
+        request_id: &str,
+        action: impl FnOnce(&mut S) -> R,
+    ) -> Option<R> {
+        self.update(request_id, |state| state.session.as_mut().map(action))
+            .flatten()
+    }
+
+    /// Start connecting a request, returning the generation of the new
+    /// connection.
+    pub fn begin(&self, request_id: &str) -> u64 {
+        let mut states = self.states.borrow_mut();
+        let state = states.entry(request_id.to_string()).or_default();
+        state.connecting = true;
+        state.generation += 1;
+        state.generation
+    }
+
+    pub fn connected(&self, request_id: &str, session: S) {
+        self.update(request_id, |state| {
+            state.session = Some(session);
+            state.connecting = false;
+        });
+    }
+
+    /// Log why connecting failed, even for a request that never connected.
+    pub fn failed(&self, request_id: &str, entry: E) {
+        self.states
+            .borrow_mut()
+            .entry(request_id.to_string())
+            .or_default()
+            .connecting = false;
+        self.push(request_id, entry);
+    }
+
+    pub fn push(&self, request_id: &str, entry: E) {
+        // Connections of removed requests log nothing more.
+        self.update(request_id, |state| {
+            if let Some(observe) = self.observe {
+                observe(state, &entry);
+            }
+            state.log.push(entry);
+            if state.log.len() > LOG_LIMIT {
+                state.log.drain(..state.log.len() - LOG_LIMIT);
+            }
+        });
+    }
+
+    /// Drop the session of a connection that ended, unless another one
+    /// replaced it already, then run `end` on the state.
+    pub fn end<R>(
+        &self,
+        request_id: &str,
+        generation: u64,
+        end: impl FnOnce(&mut SessionState<S, E, X>) -> R,
+    ) -> Option<R> {
+        let mut states = self.states.borrow_mut();
+        let state = states
+            .get_mut(request_id)
+            .filter(|state| state.generation == generation)?;
+        state.session = None;
+        state.connecting = false;
+        Some(end(state))
+    }
+
+    pub fn clear_log(&self, request_id: &str) {
+        self.update(request_id, |state| state.log.clear());
+    }
+
+    /// Forget everything about a request, closing its connection.
+    pub fn remove(&self, request_id: &str) {
+        self.states.borrow_mut().remove(request_id);
+    }
+}
+
+/// Log what a connection reports until it ends, showing every entry, then
+/// [`SessionRegistry::end`] it.
+///
+/// Returns what `end` did, or `None` when the window was closed or another
+/// connection replaced this one.
+pub async fn follow_log<S, E, X, R>(
+    registry: &'static LocalKey<SessionRegistry<S, E, X>>,
+    request_id: &str,
+    generation: u64,
+    mut log: UnboundedReceiver<E>,
+    weak_app: &Weak<AppWindow>,
+    show: fn(&AppConfig, &str),
+    end: impl FnOnce(&mut SessionState<S, E, X>) -> R,
+) -> Option<R>
+where
+    X: Default,
+{
+    while let Some(entry) = log.recv().await {
+        registry.with(|registry| registry.push(request_id, entry));
+        show(&weak_app.upgrade()?.global::<AppConfig>(), request_id);
+    }
+
+    let ended = registry.with(|registry| registry.end(request_id, generation, end));
+    if let Some(app) = weak_app.upgrade() {
+        show(&app.global::<AppConfig>(), request_id);
+    }
+    ended
+}
@@ -0,0 +1,59 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    utils::{
+        crud::requests::get_single_request,
+        snippets::{find_target, resolved_request, targets},
+    },
+    AppConfig, AppWindow,
+};
+
+/// Offer the snippet targets and write the picked one for a request.
+pub async fn process_generate_snippet(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let targets = targets();
+    let labels: Vec<SharedString> = targets.iter().map(|target| target.label().into()).collect();
+    let ids: Vec<SharedString> = targets.iter().map(|target| target.id().into()).collect();
+    config.set_snippet_target_labels(Rc::new(VecModel::from(labels)).into());
+    config.set_snippet_target_ids(Rc::new(VecModel::from(ids)).into());
+
+    let db_copy = db.clone();
+    config.on_generate_snippet(move |request_id, target_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let Some(target) = find_target(&target_id) else {
+                return;
+            };
+            let prepared = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => resolved_request(&request, &db_copy_for_task).await,
+                Err(error) => Err(error),
+            };
+            match prepared {
+                Ok(prepared) => {
+                    cfg.set_snippet_code(target.generate(&prepared).into());
+                    cfg.set_snippet_error("".into());
+                }
+                Err(error) => {
+                    eprintln!("Error generating code  - {}", error);
+                    cfg.set_snippet_code("".into());
+                    cfg.set_snippet_error(error.to_string().into());
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
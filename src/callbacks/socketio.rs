@@ -0,0 +1,319 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        requests::{sync_request_item, to_request_item},
+        sessions::{follow_log, SessionRegistry},
+        websocket::to_message_item,
+    },
+    utils::{
+        crud::{
+            requests::{
+                get_single_request, parse_method, update_request_item, ProtocolTypes, RequestData,
+            },
+            socketio::{get_socketio_config, update_socketio_config, SocketIoConfigData},
+        },
+        socketio::{
+            connect_socketio, parse_listeners, prepare_emit, prepare_socketio, EntryKinds,
+            SocketIoEntry, SocketIoSession,
+        },
+        websocket::Directions,
+    },
+    AppConfig, AppWindow, WebSocketMessageItem,
+};
+
+thread_local! {
+    static SOCKETS: SessionRegistry<SocketIoSession, SocketIoEntry> = SessionRegistry::default();
+}
+
+/// Show the socket of a request, if it is the one in the request pane.
+fn show_socket(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let (state, log) = SOCKETS.with(|sockets| {
+        sockets.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("disconnected", vec![]);
+            };
+            let status = match (&state.session, state.connecting) {
+                (_, true) => "connecting",
+                (Some(_), false) => "connected",
+                (None, false) => "disconnected",
+            };
+            let log: Vec<WebSocketMessageItem> = state.log.iter().map(to_message_item).collect();
+            (status, log)
+        })
+    });
+
+    cfg.set_active_socketio_state(state.into());
+    cfg.set_active_socketio_log(Rc::new(VecModel::from(log)).into());
+}
+
+fn set_error(cfg: &AppConfig, request_id: &str, error: &str) {
+    if cfg.get_active_request().id == request_id {
+        cfg.set_active_socketio_error(error.into());
+    }
+}
+
+/// The settings as shown in the request pane.
+fn config_data(cfg: &AppConfig) -> SocketIoConfigData {
+    SocketIoConfigData {
+        path: cfg.get_active_socketio_path().trim().to_string(),
+        listeners: cfg.get_active_socketio_listeners().to_string(),
+        event: cfg.get_active_socketio_event().to_string(),
+        payload: cfg.get_active_socketio_payload().to_string(),
+        ack: cfg.get_active_socketio_ack(),
+    }
+}
+
+/// Load the settings and socket of a Socket.IO request.
+pub async fn load_socketio_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let config = get_socketio_config(request_id, db).await?;
+    cfg.set_active_socketio_path(config.path.into());
+    cfg.set_active_socketio_listeners(config.listeners.into());
+    cfg.set_active_socketio_event(config.event.into());
+    cfg.set_active_socketio_payload(config.payload.into());
+    cfg.set_active_socketio_ack(config.ack);
+    cfg.set_active_socketio_error(SharedString::new());
+    show_socket(cfg, request_id);
+
+    Ok(())
+}
+
+/// Disconnect the socket of a request, if it has one. Its log is kept.
+pub fn close_socketio_socket(request_id: &str) {
+    SOCKETS.with(|sockets| sockets.with_session(request_id, SocketIoSession::disconnect));
+}
+
+/// Forget everything about the socket of a request, disconnecting it.
+pub fn remove_socketio_request(request_id: &str) {
+    SOCKETS.with(|sockets| sockets.remove(request_id));
+}
+
+/// The request, with the URL currently in the request pane saved first.
+async fn current_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let active_request = cfg.get_active_request();
+    if active_request.id != request_id {
+        return Ok(request);
+    }
+
+    let request = update_request_item(
+        &request.id,
+        &request.name,
+        ProtocolTypes::SocketIo,
+        parse_method(&active_request.http_method)?,
+        &cfg.get_active_request_url(),
+        db,
+    )
+    .await?;
+    sync_request_item(cfg, to_request_item(request.clone()));
+
+    Ok(request)
+}
+
+pub async fn process_update_socketio_config(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    let db_copy = db.clone();
+    config.on_update_socketio_config(move |request_id, path, listeners, event, payload, ack| {
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let config = SocketIoConfigData {
+                path: path.trim().to_string(),
+                listeners: listeners.to_string(),
+                event: event.to_string(),
+                payload: payload.to_string(),
+                ack,
+            };
+            // A connected socket picks up the new listeners right away.
+            SOCKETS.with(|sockets| {
+                sockets.with_session(&request_id, |session| {
+                    let _ = session.listen(parse_listeners(&config.listeners));
+                })
+            });
+            if let Err(error) =
+                update_socketio_config(&request_id, &config, &db_copy_for_task).await
+            {
+                eprintln!("Error updating Socket.IO settings  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_connect_socketio(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_connect_socketio(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            let request = match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                Ok(request) => request,
+                Err(error) => {
+                    eprintln!("Error updating request  - {}", error);
+                    return;
+                }
+            };
+
+            set_error(&cfg, &request_id, "");
+            let prepared = match get_socketio_config(&request_id, &db_copy_for_task).await {
+                Ok(config) => prepare_socketio(&request, &config, &db_copy_for_task).await,
+                Err(error) => Err(error),
+            };
+            let options = match prepared {
+                Ok(options) => options,
+                Err(error) => {
+                    set_error(&cfg, &request_id, &error.to_string());
+                    return;
+                }
+            };
+
+            let generation = SOCKETS.with(|sockets| sockets.begin(&request_id));
+            show_socket(&cfg, &request_id);
+
+            let log = match connect_socketio(options).await {
+                Ok(connection) => {
+                    SOCKETS.with(|sockets| sockets.connected(&request_id, connection.session));
+                    connection.log
+                }
+                Err(error) => {
+                    SOCKETS.with(|sockets| {
+                        sockets.failed(
+                            &request_id,
+                            SocketIoEntry::new(
+                                Directions::Info,
+                                EntryKinds::Error,
+                                &error.to_string(),
+                            ),
+                        )
+                    });
+                    show_socket(&cfg, &request_id);
+                    return;
+                }
+            };
+            show_socket(&cfg, &request_id);
+            // The window may be closed while the socket is connected.
+            drop(app);
+
+            follow_log(
+                &SOCKETS,
+                &request_id,
+                generation,
+                log,
+                &weak_app_for_task,
+                show_socket,
+                |_| (),
+            )
+            .await;
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_disconnect_socketio(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_disconnect_socketio(move |request_id| {
+        close_socketio_socket(&request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_emit_socketio_event(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_emit_socketio_event(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let config = config_data(&cfg);
+            if let Err(error) =
+                update_socketio_config(&request_id, &config, &db_copy_for_task).await
+            {
+                eprintln!("Error updating Socket.IO settings  - {}", error);
+                return;
+            }
+            let request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => request,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            let emitted = prepare_emit(&request, &config, &db_copy_for_task)
+                .await
+                .and_then(|(event, payload)| {
+                    SOCKETS
+                        .with(|sockets| {
+                            sockets.with_session(&request_id, |session| {
+                                session.emit(&event, &payload, config.ack)
+                            })
+                        })
+                        .unwrap_or_else(|| Err("The socket is disconnected.".into()))
+                });
+
+            match emitted {
+                Ok(()) => set_error(&cfg, &request_id, ""),
+                Err(error) => set_error(&cfg, &request_id, &error.to_string()),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_socketio_log(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_socketio_log(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        SOCKETS.with(|sockets| sockets.clear_log(&request_id));
+        show_socket(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use std::{error::Error, rc::Rc, time::Instant};
+
+use slint::{ComponentHandle, SharedString, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::{
+        requests::{sync_request_item, to_request_item},
+        sessions::{follow_log, SessionRegistry, SessionState},
+    },
+    utils::{
+        crud::{
+            requests::{
+                get_single_request, parse_method, update_request_item, ProtocolTypes, RequestData,
+            },
+            sse::{get_sse_config, update_sse_config, SseConfigData},
+        },
+        executor::{authenticate, HistoryOrigin, PreparedRequest},
+        sse::{open_stream, prepare_stream, record_stream, EntryKinds, SseSession, StreamEntry},
+    },
+    AppConfig, AppWindow, SseEventItem,
+};
+
+/// What a request's stream tracks besides its session and log.
+#[derive(Default)]
+struct StreamState {
+    /// Whether the stream is open right now, rather than being opened.
+    open: bool,
+    /// Whether the stream was open before, so it is being opened again.
+    reopening: bool,
+    /// The request the stream was opened with, before its auth was applied.
+    /// Recorded in the history along with the log.
+    request: Option<PreparedRequest>,
+    started: Option<Instant>,
+    last_event_id: String,
+}
+
+thread_local! {
+    static STREAMS: SessionRegistry<SseSession, StreamEntry, StreamState> =
+        SessionRegistry::observing(observe_entry);
+}
+
+fn to_event_item(entry: &StreamEntry) -> SseEventItem {
+    match &entry.event {
+        Some(event) => SseEventItem {
+            time: entry.time.clone().into(),
+            kind: entry.kind.to_string().into(),
+            id: event.id.clone().into(),
+            event: event.event.clone().into(),
+            data: event.data.clone().into(),
+        },
+        None => SseEventItem {
+            time: entry.time.clone().into(),
+            kind: entry.kind.to_string().into(),
+            data: entry.data.clone().into(),
+            ..Default::default()
+        },
+    }
+}
+
+fn observe_entry(
+    state: &mut SessionState<SseSession, StreamEntry, StreamState>,
+    entry: &StreamEntry,
+) {
+    let stream = &mut state.extra;
+    match entry.kind {
+        EntryKinds::Connected => {
+            stream.open = true;
+            stream.reopening = true;
+        }
+        EntryKinds::Event => {}
+        _ => stream.open = false,
+    }
+    if let Some(event) = &entry.event {
+        stream.last_event_id = event.id.clone();
+    }
+}
+
+/// Show the stream of a request, if it is the one in the request pane.
+fn show_stream(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let (state, last_event_id, events) = STREAMS.with(|streams| {
+        streams.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("stopped", None, vec![]);
+            };
+            let stream = &state.extra;
+            let status = match (&state.session, stream.open, stream.reopening) {
+                (None, _, _) => "stopped",
+                (Some(_), true, _) => "open",
+                (Some(_), false, true) => "reconnecting",
+                (Some(_), false, false) => "connecting",
+            };
+            // While the stream runs, it tells where the next one resumes from.
+            let last_event_id = state.session.as_ref().map(|_| stream.last_event_id.clone());
+            let events: Vec<SseEventItem> = state.log.iter().map(to_event_item).collect();
+            (status, last_event_id, events)
+        })
+    });
+
+    cfg.set_active_sse_state(state.into());
+    if let Some(last_event_id) = last_event_id {
+        cfg.set_active_sse_last_event_id(last_event_id.into());
+    }
+    cfg.set_active_sse_events(Rc::new(VecModel::from(events)).into());
+}
+
+/// Load the settings and stream of an SSE request.
+pub async fn load_sse_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let config = get_sse_config(request_id, db).await?;
+    cfg.set_active_sse_last_event_id(config.last_event_id.into());
+    cfg.set_active_sse_reconnect(config.reconnect);
+    cfg.set_active_sse_status(SharedString::new());
+    show_stream(cfg, request_id);
+
+    Ok(())
+}
+
+/// Stop the stream of a request, if it has one. Its log is kept.
+pub fn close_sse_stream(request_id: &str) {
+    STREAMS.with(|streams| streams.with_session_mut(request_id, SseSession::stop));
+}
+
+/// Forget everything about the stream of a request, stopping it.
+pub fn remove_sse_request(request_id: &str) {
+    STREAMS.with(|streams| streams.remove(request_id));
+}
+
+/// The request, with the URL and method currently in the request pane saved
+/// first.
+async fn current_request(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let request = get_single_request(request_id, db).await?;
+    let active_request = cfg.get_active_request();
+    if active_request.id != request_id {
+        return Ok(request);
+    }
+
+    let request = update_request_item(
+        &request.id,
+        &request.name,
+        ProtocolTypes::Sse,
+        parse_method(&active_request.http_method)?,
+        &cfg.get_active_request_url(),
+        db,
+    )
+    .await?;
+    sync_request_item(cfg, to_request_item(request.clone()));
+
+    Ok(request)
+}
+
+pub async fn process_update_sse_config(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    let db_copy = db.clone();
+    config.on_update_sse_config(move |request_id, last_event_id, reconnect| {
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let config = SseConfigData {
+                last_event_id: last_event_id.trim().to_string(),
+                reconnect,
+            };
+            if let Err(error) = update_sse_config(&request_id, &config, &db_copy_for_task).await {
+                eprintln!("Error updating SSE settings  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_start_sse(db: &SqlitePool, app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_start_sse(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            let request = match current_request(&cfg, &request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+            let settings = match get_sse_config(&request_id, &db_copy_for_task).await {
+                Ok(settings) => settings,
+                Err(error) => {
+                    eprintln!("Error getting SSE settings  - {}", error);
+                    return;
+                }
+            };
+
+            close_sse_stream(&request_id);
+            cfg.set_active_sse_status(SharedString::new());
+            // Sent with its auth, but recorded without it.
+            let prepared = match prepare_stream(&request, &db_copy_for_task).await {
+                Ok(prepared) => authenticate(prepared.clone(), &db_copy_for_task)
+                    .await
+                    .map(|sent| (prepared, sent)),
+                Err(error) => Err(error),
+            };
+            let (prepared, sent) = match prepared {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    STREAMS.with(|streams| {
+                        streams.failed(
+                            &request_id,
+                            StreamEntry::new(EntryKinds::Error, &error.to_string()),
+                        )
+                    });
+                    show_stream(&cfg, &request_id);
+                    return;
+                }
+            };
+
+            let stream = open_stream(sent, &settings.last_event_id, settings.reconnect);
+            let generation = STREAMS.with(|streams| {
+                let generation = streams.begin(&request_id);
+                streams.update(&request_id, |state| {
+                    state.extra = StreamState {
+                        request: Some(prepared),
+                        started: Some(Instant::now()),
+                        last_event_id: settings.last_event_id.clone(),
+                        ..Default::default()
+                    };
+                });
+                streams.connected(&request_id, stream.session);
+                generation
+            });
+            show_stream(&cfg, &request_id);
+            // The window may be closed while the stream is open.
+            drop(app);
+
+            let last_event_id = follow_log(
+                &STREAMS,
+                &request_id,
+                generation,
+                stream.log,
+                &weak_app_for_task,
+                show_stream,
+                |state| {
+                    state.extra.open = false;
+                    state.extra.last_event_id.clone()
+                },
+            )
+            .await;
+            // The next stream picks up where this one stopped.
+            if let Some(last_event_id) = last_event_id {
+                let settings = SseConfigData {
+                    last_event_id,
+                    ..settings
+                };
+                if let Err(error) =
+                    update_sse_config(&request_id, &settings, &db_copy_for_task).await
+                {
+                    eprintln!("Error updating SSE settings  - {}", error);
+                }
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_stop_sse(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_stop_sse(move |request_id| {
+        close_sse_stream(&request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_sse_events(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_sse_events(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        STREAMS.with(|streams| streams.clear_log(&request_id));
+        cfg.set_active_sse_status(SharedString::new());
+        show_stream(&cfg, &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_save_sse_events(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_save_sse_events(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let recorded = STREAMS.with(|streams| {
+                streams.read(&request_id, |state| {
+                    let state = state?;
+                    let total_ms = state
+                        .extra
+                        .started
+                        .map(|started| started.elapsed().as_millis() as i64)
+                        .unwrap_or_default();
+                    Some((state.extra.request.clone()?, state.log.clone(), total_ms))
+                })
+            });
+            let Some((prepared, log, total_ms)) = recorded else {
+                cfg.set_active_sse_status("Connect first, there is nothing to save yet.".into());
+                return;
+            };
+            let origin = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(request) => HistoryOrigin::for_request(&request),
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            let events = log.iter().filter(|entry| entry.event.is_some()).count();
+            match record_stream(&prepared, origin, &log, total_ms, &db_copy_for_task).await {
+                Ok(_) => {
+                    cfg.set_active_sse_status(
+                        format!("Saved {} events to the history.", events).into(),
+                    );
+                    cfg.invoke_get_history(cfg.get_history_search());
+                }
+                Err(error) => cfg.set_active_sse_status(
+                    format!("Couldn't save to the history: {}", error).into(),
+                ),
+            }
+        });
+    });
+
+    Ok(())
+}
@@ -0,0 +1,466 @@
+use std::{error::Error, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+
+use crate::{
+    callbacks::environments::refresh_unresolved_variables,
+    utils::crud::{
+        collections::{
+            create_collection_variable, delete_collection_variable, get_collection_variables,
+            update_collection_variable, CollectionVariableData,
+        },
+        environments::{
+            create_global_variable, delete_global_variable, get_global_variables,
+            update_global_variable, GlobalVariableData,
+        },
+        requests::{
+            create_request_variable, delete_request_variable, get_request_variables,
+            update_request_variable, RequestVariableData,
+        },
+    },
+    AppConfig, AppWindow, KeyValueItem,
+};
+
+pub fn request_variable_to_key_value_item(variable: RequestVariableData) -> KeyValueItem {
+    KeyValueItem {
+        id: variable.id.into(),
+        name: variable.name.into(),
+        value: variable.value.into(),
+        enabled: variable.enabled,
+    }
+}
+
+pub fn collection_variable_to_key_value_item(variable: CollectionVariableData) -> KeyValueItem {
+    KeyValueItem {
+        id: variable.id.into(),
+        name: variable.name.into(),
+        value: variable.value.into(),
+        enabled: variable.enabled,
+    }
+}
+
+pub fn global_variable_to_key_value_item(variable: GlobalVariableData) -> KeyValueItem {
+    KeyValueItem {
+        id: variable.id.into(),
+        name: variable.name.into(),
+        value: variable.value.into(),
+        enabled: variable.enabled,
+    }
+}
+
+/// Show the variables of a request in the request pane.
+pub async fn load_request_variables(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let variables: Vec<KeyValueItem> = get_request_variables(request_id, db)
+        .await?
+        .into_iter()
+        .map(request_variable_to_key_value_item)
+        .collect();
+
+    cfg.set_active_request_variables(Rc::new(VecModel::from(variables)).into());
+    Ok(())
+}
+
+/// Add an empty request variable.
+pub async fn process_create_request_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_request_variable(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match create_request_variable(&request_id, "", "", &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error creating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_variables().iter().collect();
+            items.push(request_variable_to_key_value_item(variable));
+            cfg.set_active_request_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a request variable edited in its grid.
+pub async fn process_update_request_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_request_variable(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match update_request_variable(&id, &name, &value, enabled, &db_copy_for_task).await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_active_request_variables();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(index as usize, request_variable_to_key_value_item(variable));
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a request variable.
+pub async fn process_remove_request_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_request_variable(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_request_variable(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting variable  - {}", error);
+                return;
+            }
+
+            let mut items: Vec<KeyValueItem> = cfg.get_active_request_variables().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_request_variables(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Show the variables of a collection in the collection settings.
+pub async fn process_get_collection_variables(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_collection_variables(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let items: Vec<KeyValueItem> =
+                match get_collection_variables(&collection_id, &db_copy_for_task).await {
+                    Ok(data) => data
+                        .into_iter()
+                        .map(collection_variable_to_key_value_item)
+                        .collect(),
+                    Err(error) => {
+                        eprintln!("Error getting variables  - {}", error);
+                        return;
+                    }
+                };
+            cfg.set_active_collection_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty collection variable.
+pub async fn process_create_collection_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_collection_variable(move |collection_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match create_collection_variable(&collection_id, "", "", &db_copy_for_task).await {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error creating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            let mut items: Vec<KeyValueItem> =
+                cfg.get_active_collection_variables().iter().collect();
+            items.push(collection_variable_to_key_value_item(variable));
+            cfg.set_active_collection_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a collection variable edited in its grid.
+pub async fn process_update_collection_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_collection_variable(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable =
+                match update_collection_variable(&id, &name, &value, enabled, &db_copy_for_task)
+                    .await
+                {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating variable  - {}", error);
+                        return;
+                    }
+                };
+
+            // Update the row in place so the line being edited keeps its focus.
+            let items = cfg.get_active_collection_variables();
+            if (index as usize) < items.row_count() {
+                items.set_row_data(
+                    index as usize,
+                    collection_variable_to_key_value_item(variable),
+                );
+            }
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Remove a collection variable.
+pub async fn process_remove_collection_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_collection_variable(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_collection_variable(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting variable  - {}", error);
+                return;
+            }
+
+            let mut items: Vec<KeyValueItem> =
+                cfg.get_active_collection_variables().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_active_collection_variables(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
+
+/// Show the global variables in the environment settings.
+pub async fn process_get_global_variables(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_get_global_variables(move || {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let items: Vec<KeyValueItem> = match get_global_variables(&db_copy_for_task).await {
+                Ok(data) => data
+                    .into_iter()
+                    .map(global_variable_to_key_value_item)
+                    .collect(),
+                Err(error) => {
+                    eprintln!("Error getting variables  - {}", error);
+                    return;
+                }
+            };
+            cfg.set_global_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Add an empty global variable.
+pub async fn process_create_global_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_create_global_variable(move || {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let variable = match create_global_variable("", "", &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error creating variable  - {}", error);
+                    return;
+                }
+            };
+
+            let mut items: Vec<KeyValueItem> = cfg.get_global_variables().iter().collect();
+            items.push(global_variable_to_key_value_item(variable));
+            cfg.set_global_variables(Rc::new(VecModel::from(items)).into());
+        });
+    });
+
+    Ok(())
+}
+
+/// Save a global variable edited in its grid.
+pub async fn process_update_global_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_update_global_variable(move |id, name, value, enabled, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ =
+            slint::spawn_local(async move {
+                let app = weak_app_for_task.upgrade().unwrap();
+                let cfg = app.global::<AppConfig>();
+
+                let variable =
+                    match update_global_variable(&id, &name, &value, enabled, &db_copy_for_task)
+                        .await
+                    {
+                        Ok(data) => data,
+                        Err(error) => {
+                            eprintln!("Error updating variable  - {}", error);
+                            return;
+                        }
+                    };
+
+                // Update the row in place so the line being edited keeps its focus.
+                let items = cfg.get_global_variables();
+                if (index as usize) < items.row_count() {
+                    items.set_row_data(index as usize, global_variable_to_key_value_item(variable));
+                }
+                refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+            });
+    });
+
+    Ok(())
+}
+
+/// Remove a global variable.
+pub async fn process_remove_global_variable(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_global_variable(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_global_variable(&id, &db_copy_for_task).await {
+                eprintln!("Error deleting variable  - {}", error);
+                return;
+            }
+
+            let mut items: Vec<KeyValueItem> = cfg.get_global_variables().iter().collect();
+            if items.get(index as usize).is_some() {
+                items.remove(index as usize);
+            }
+            cfg.set_global_variables(Rc::new(VecModel::from(items)).into());
+            refresh_unresolved_variables(&cfg, &db_copy_for_task).await;
+        });
+    });
+
+    Ok(())
+}
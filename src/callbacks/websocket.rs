@@ -0,0 +1,413 @@
+use std::{error::Error, fmt, rc::Rc};
+
+use slint::{ComponentHandle, Model, VecModel};
+use sqlx::SqlitePool;
+use tokio_tungstenite::tungstenite::{Bytes, Message};
+
+use crate::{
+    callbacks::{
+        requests::{sync_request_item, to_request_item},
+        sessions::{follow_log, SessionRegistry},
+    },
+    utils::{
+        crud::{
+            requests::{get_single_request, parse_method, update_request_item, ProtocolTypes},
+            websocket::{
+                create_websocket_template, delete_websocket_template, get_websocket_subprotocols,
+                get_websocket_templates, update_websocket_subprotocols, MessageFormats,
+                WebSocketTemplateData,
+            },
+        },
+        websocket::{
+            connect, encode_message, prepare_handshake, Directions, FrameKinds, LogEntry,
+            LogFilter, WebSocketSession,
+        },
+    },
+    AppConfig, AppWindow, WebSocketMessageItem, WebSocketTemplateItem,
+};
+
+thread_local! {
+    /// Connections along with the subprotocol the server picked.
+    static SESSIONS: SessionRegistry<WebSocketSession, LogEntry, String> =
+        SessionRegistry::default();
+}
+
+/// A log line as the UI shows it, for WebSocket and the protocols carried
+/// over it alike.
+pub fn to_message_item<K: fmt::Display>(entry: &LogEntry<K>) -> WebSocketMessageItem {
+    WebSocketMessageItem {
+        time: entry.time.clone().into(),
+        direction: entry.direction.to_string().into(),
+        kind: entry.kind.to_string().into(),
+        data: entry.data.clone().into(),
+    }
+}
+
+fn to_template_item(template: WebSocketTemplateData) -> WebSocketTemplateItem {
+    WebSocketTemplateItem {
+        id: template.id.into(),
+        name: template.name.into(),
+        format: template.format.into(),
+        payload: template.payload.into(),
+    }
+}
+
+fn log_filter(cfg: &AppConfig) -> LogFilter {
+    LogFilter {
+        text: cfg.get_websocket_filter_text().to_string(),
+        direction: Directions::from_string(&cfg.get_websocket_filter_direction()),
+    }
+}
+
+/// Show the session of a request, if it is the one in the request pane.
+fn show_session(cfg: &AppConfig, request_id: &str) {
+    if cfg.get_active_request().id != request_id {
+        return;
+    }
+
+    let filter = log_filter(cfg);
+    let (state, protocol, messages) = SESSIONS.with(|sessions| {
+        sessions.read(request_id, |state| {
+            let Some(state) = state else {
+                return ("disconnected", String::new(), vec![]);
+            };
+            let status = match (&state.session, state.connecting) {
+                (_, true) => "connecting",
+                (Some(_), false) => "connected",
+                (None, false) => "disconnected",
+            };
+            let messages: Vec<WebSocketMessageItem> = state
+                .log
+                .iter()
+                .filter(|entry| filter.matches(entry))
+                .map(to_message_item)
+                .collect();
+            (status, state.extra.clone(), messages)
+        })
+    });
+
+    cfg.set_active_websocket_state(state.into());
+    cfg.set_active_websocket_protocol(protocol.into());
+    cfg.set_active_websocket_messages(Rc::new(VecModel::from(messages)).into());
+}
+
+async fn show_templates(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let templates: Vec<WebSocketTemplateItem> = get_websocket_templates(request_id, db)
+        .await?
+        .into_iter()
+        .map(to_template_item)
+        .collect();
+    cfg.set_active_websocket_templates(Rc::new(VecModel::from(templates)).into());
+
+    Ok(())
+}
+
+/// Load the settings, templates and session of a WebSocket request.
+pub async fn load_websocket_session(
+    cfg: &AppConfig<'_>,
+    request_id: &str,
+    db: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    cfg.set_active_websocket_error("".into());
+    show_session(cfg, request_id);
+    cfg.set_active_websocket_subprotocols(get_websocket_subprotocols(request_id, db).await?.into());
+    show_templates(cfg, request_id, db).await
+}
+
+/// Close the connection of a request, if it has one. Its log is kept.
+pub fn close_websocket_session(request_id: &str) {
+    SESSIONS.with(|sessions| sessions.with_session(request_id, WebSocketSession::close));
+}
+
+/// Forget everything about the session of a request, closing it.
+pub fn remove_websocket_session(request_id: &str) {
+    SESSIONS.with(|sessions| sessions.remove(request_id));
+}
+
+pub async fn process_connect_websocket(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_connect_websocket(move |request_id| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+            let request_id = request_id.to_string();
+
+            let mut request = match get_single_request(&request_id, &db_copy_for_task).await {
+                Ok(data) => data,
+                Err(error) => {
+                    eprintln!("Error getting request  - {}", error);
+                    return;
+                }
+            };
+
+            // Persist whatever is currently typed in the request pane before connecting.
+            let active_request = cfg.get_active_request();
+            if active_request.id == request_id {
+                let updated = match parse_method(&active_request.http_method) {
+                    Ok(method) => {
+                        update_request_item(
+                            &request.id,
+                            &request.name,
+                            ProtocolTypes::Websocket,
+                            method,
+                            &cfg.get_active_request_url(),
+                            &db_copy_for_task,
+                        )
+                        .await
+                    }
+                    Err(error) => Err(error),
+                };
+                request = match updated {
+                    Ok(data) => data,
+                    Err(error) => {
+                        eprintln!("Error updating request  - {}", error);
+                        return;
+                    }
+                };
+                sync_request_item(&cfg, to_request_item(request.clone()));
+            }
+
+            cfg.set_active_websocket_error("".into());
+            let handshake = match prepare_handshake(&request, &db_copy_for_task).await {
+                Ok(handshake) => handshake,
+                Err(error) => {
+                    cfg.set_active_websocket_error(error.to_string().into());
+                    return;
+                }
+            };
+
+            let generation = SESSIONS.with(|sessions| sessions.begin(&request_id));
+            show_session(&cfg, &request_id);
+
+            let log = match connect(handshake).await {
+                Ok(connection) => {
+                    SESSIONS.with(|sessions| {
+                        sessions.connected(&request_id, connection.session);
+                        sessions.update(&request_id, |state| state.extra = connection.protocol);
+                    });
+                    connection.log
+                }
+                Err(error) => {
+                    SESSIONS.with(|sessions| {
+                        sessions.failed(
+                            &request_id,
+                            LogEntry::new(Directions::Info, FrameKinds::Error, &error.to_string()),
+                        )
+                    });
+                    show_session(&cfg, &request_id);
+                    return;
+                }
+            };
+            show_session(&cfg, &request_id);
+            // The window may be closed while the connection is open.
+            drop(app);
+
+            follow_log(
+                &SESSIONS,
+                &request_id,
+                generation,
+                log,
+                &weak_app_for_task,
+                show_session,
+                |state| state.extra.clear(),
+            )
+            .await;
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_disconnect_websocket(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_disconnect_websocket(move |request_id| {
+        close_websocket_session(&request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_send_websocket_message(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_send_websocket_message(move |request_id, format, payload| {
+        let app = weak_app.upgrade().unwrap();
+        let cfg = app.global::<AppConfig>();
+
+        let format = MessageFormats::from_string(&format).unwrap_or(MessageFormats::Text);
+        let sent = encode_message(&format, &payload).and_then(|message| {
+            SESSIONS
+                .with(|sessions| {
+                    sessions.with_session(&request_id, |session| session.send(message))
+                })
+                .unwrap_or_else(|| Err("The connection is closed.".into()))
+        });
+
+        match sent {
+            Ok(()) => cfg.set_active_websocket_error("".into()),
+            Err(error) => cfg.set_active_websocket_error(error.to_string().into()),
+        }
+    });
+
+    Ok(())
+}
+
+pub async fn process_ping_websocket(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    config.on_ping_websocket(move |request_id| {
+        let sent = SESSIONS.with(|sessions| {
+            sessions.with_session(&request_id, |session| {
+                session.send(Message::Ping(Bytes::new()))
+            })
+        });
+        if let Some(Err(error)) = sent {
+            eprintln!("Error sending ping  - {}", error);
+        }
+    });
+
+    Ok(())
+}
+
+pub async fn process_filter_websocket_messages(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_filter_websocket_messages(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+        show_session(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_clear_websocket_messages(app: &AppWindow) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    config.on_clear_websocket_messages(move |request_id| {
+        let app = weak_app.upgrade().unwrap();
+
+        SESSIONS.with(|sessions| sessions.clear_log(&request_id));
+        show_session(&app.global::<AppConfig>(), &request_id);
+    });
+
+    Ok(())
+}
+
+pub async fn process_update_websocket_subprotocols(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+
+    let db_copy = db.clone();
+    config.on_update_websocket_subprotocols(move |request_id, subprotocols| {
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            if let Err(error) =
+                update_websocket_subprotocols(&request_id, &subprotocols, &db_copy_for_task).await
+            {
+                eprintln!("Error updating subprotocols  - {}", error);
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_save_websocket_template(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_save_websocket_template(move |request_id, name, format, payload| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            let format = MessageFormats::from_string(&format).unwrap_or(MessageFormats::Text);
+            // Catch payloads that could never be sent before they are saved.
+            if let Err(error) = encode_message(&format, &payload) {
+                cfg.set_active_websocket_error(error.to_string().into());
+                return;
+            }
+
+            match create_websocket_template(
+                &request_id,
+                name.trim(),
+                format,
+                &payload,
+                &db_copy_for_task,
+            )
+            .await
+            {
+                Ok(template) => {
+                    let mut templates: Vec<WebSocketTemplateItem> =
+                        cfg.get_active_websocket_templates().iter().collect();
+                    templates.push(to_template_item(template));
+                    cfg.set_active_websocket_templates(Rc::new(VecModel::from(templates)).into());
+                    cfg.set_active_websocket_error("".into());
+                }
+                Err(error) => eprintln!("Error saving template  - {}", error),
+            }
+        });
+    });
+
+    Ok(())
+}
+
+pub async fn process_remove_websocket_template(
+    db: &SqlitePool,
+    app: &AppWindow,
+) -> Result<(), Box<dyn Error>> {
+    let config = app.global::<AppConfig>();
+    let weak_app = app.as_weak();
+
+    let db_copy = db.clone();
+    config.on_remove_websocket_template(move |id, index| {
+        let weak_app_for_task = weak_app.clone();
+        let db_copy_for_task = db_copy.clone();
+
+        let _ = slint::spawn_local(async move {
+            let app = weak_app_for_task.upgrade().unwrap();
+            let cfg = app.global::<AppConfig>();
+
+            if let Err(error) = delete_websocket_template(&id, &db_copy_for_task).await {
+                eprintln!("Error removing template  - {}", error);
+                return;
+            }
+
+            let mut templates: Vec<WebSocketTemplateItem> =
+                cfg.get_active_websocket_templates().iter().collect();
+            if templates.get(index as usize).is_some() {
+                templates.remove(index as usize);
+            }
+            cfg.set_active_websocket_templates(Rc::new(VecModel::from(templates)).into());
+        });
+    });
+
+    Ok(())
+}
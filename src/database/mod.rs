@@ -26,9 +26,34 @@ pub async fn setup_test_db() -> Result<SqlitePool, Box<dyn Error>> {
     // Drop all tables to start fresh
     sqlx::query(
         r#"
+        DROP TABLE IF EXISTS mqttconfig;
+        DROP TABLE IF EXISTS socketioconfig;
+        DROP TABLE IF EXISTS sseconfig;
+        DROP TABLE IF EXISTS grpcdescriptors;
+        DROP TABLE IF EXISTS grpccall;
+        DROP TABLE IF EXISTS graphqlschema;
+        DROP TABLE IF EXISTS graphqlquery;
+        DROP TABLE IF EXISTS websockettemplate;
+        DROP TABLE IF EXISTS websocketconfig;
+        DROP TABLE IF EXISTS responsecomparison;
+        DROP TABLE IF EXISTS requesthistory;
+        DROP TABLE IF EXISTS historysettings;
         DROP TABLE IF EXISTS collectionitem;
         DROP TABLE IF EXISTS collectionheader;
+        DROP TABLE IF EXISTS requestheader;
+        DROP TABLE IF EXISTS requestbody;
+        DROP TABLE IF EXISTS requestbodypart;
+        DROP TABLE IF EXISTS requestparam;
+        DROP TABLE IF EXISTS oauth2token;
+        DROP TABLE IF EXISTS oauth2config;
+        DROP TABLE IF EXISTS authsecret;
+        DROP TABLE IF EXISTS authconfig;
+        DROP TABLE IF EXISTS requestvariable;
+        DROP TABLE IF EXISTS collectionvariable;
+        DROP TABLE IF EXISTS globalvariable;
         DROP TABLE IF EXISTS requestitem;
+        DROP TABLE IF EXISTS environmentvariable;
+        DROP TABLE IF EXISTS environment;
         DROP TABLE IF EXISTS _sqlx_migrations;  
         "#,
     )
@@ -5,15 +5,100 @@ use slint::{ComponentHandle, PhysicalSize, PlatformError};
 
 use lib::{
     callbacks::{
+        auth::{
+            process_get_collection_auth, process_get_collection_oauth2_token,
+            process_get_request_oauth2_token, process_update_collection_auth,
+            process_update_collection_oauth2, process_update_request_auth,
+            process_update_request_oauth2,
+        },
+        bodies::{
+            process_create_body_part, process_pick_file, process_remove_body_part,
+            process_update_body_part, process_update_request_body,
+        },
         collections::{
             check_startup_page, load_collections, process_create_collection,
             process_get_collections, process_page_change, process_remove_collection,
             process_search_collections, process_update_collection,
         },
+        compare::{
+            load_comparisons, process_close_comparison, process_compare_history_entries,
+            process_compare_urls, process_open_compare_dialog, process_select_comparison,
+        },
+        environments::{
+            load_environments, process_create_environment, process_create_environment_variable,
+            process_load_environment_variables, process_remove_environment,
+            process_remove_environment_variable, process_select_environment,
+            process_update_environment, process_update_environment_variable,
+        },
+        executor::process_send_request,
+        graphql::{
+            process_apply_graphql_completion, process_clear_graphql_events,
+            process_complete_graphql_query, process_fetch_graphql_schema,
+            process_filter_graphql_schema, process_select_graphql_type, process_subscribe_graphql,
+            process_unsubscribe_graphql, process_update_graphql_query,
+        },
+        grpc::{
+            process_cancel_grpc_call, process_clear_grpc_log, process_fill_grpc_template,
+            process_finish_grpc_stream, process_invoke_grpc, process_load_grpc_services,
+            process_select_grpc_method, process_send_grpc_message, process_update_grpc_call,
+        },
+        headers::{
+            process_create_collection_header, process_create_request_header,
+            process_get_collection_headers, process_remove_collection_header,
+            process_remove_request_header, process_update_collection_header,
+            process_update_request_header,
+        },
+        history::{
+            load_history_settings, process_clear_history, process_export_history_har,
+            process_get_history, process_remove_history_entry, process_rerun_history_entry,
+            process_restore_history_entry, process_select_history_entry,
+            process_update_history_settings,
+        },
         images::process_get_images,
+        interchange::{
+            process_copy_as_curl, process_export_collections, process_filter_har,
+            process_import_collection, process_import_curl, process_import_har,
+            process_reimport_spec,
+        },
+        mqtt::{
+            process_clear_mqtt_log, process_connect_mqtt, process_disconnect_mqtt,
+            process_publish_mqtt, process_subscribe_mqtt, process_unsubscribe_mqtt,
+            process_update_mqtt_config,
+        },
+        params::{
+            process_create_request_param, process_remove_request_param,
+            process_sync_params_from_url, process_update_request_param,
+        },
+        request_details::process_load_request_details,
         requests::{
             process_create_requests, process_delete_request, process_get_requests,
-            process_request_remove, process_request_selection, process_update_request,
+            process_is_http_method, process_request_remove, process_request_selection,
+            process_update_active_request, process_update_active_request_protocol,
+            process_update_request,
+        },
+        snippets::process_generate_snippet,
+        socketio::{
+            process_clear_socketio_log, process_connect_socketio, process_disconnect_socketio,
+            process_emit_socketio_event, process_update_socketio_config,
+        },
+        sse::{
+            process_clear_sse_events, process_save_sse_events, process_start_sse, process_stop_sse,
+            process_update_sse_config,
+        },
+        variables::{
+            process_create_collection_variable, process_create_global_variable,
+            process_create_request_variable, process_get_collection_variables,
+            process_get_global_variables, process_remove_collection_variable,
+            process_remove_global_variable, process_remove_request_variable,
+            process_update_collection_variable, process_update_global_variable,
+            process_update_request_variable,
+        },
+        websocket::{
+            process_clear_websocket_messages, process_connect_websocket,
+            process_disconnect_websocket, process_filter_websocket_messages,
+            process_ping_websocket, process_remove_websocket_template,
+            process_save_websocket_template, process_send_websocket_message,
+            process_update_websocket_subprotocols,
         },
     },
     database::get_database,
@@ -28,6 +113,9 @@ async fn main() -> Result<(), PlatformError> {
 
     check_startup_page(&db, &app).await.unwrap();
     load_collections(&db, &app).await.unwrap();
+    load_environments(&db, &app).await.unwrap();
+    load_history_settings(&db, &app).await.unwrap();
+    load_comparisons(&db, &app).await.unwrap();
     process_page_change(&app).await.unwrap();
     process_get_collections(&db, &app).await.unwrap();
     process_create_collection(&db, &app).await.unwrap();
@@ -41,6 +129,130 @@ async fn main() -> Result<(), PlatformError> {
     process_delete_request(&db, &app).await.unwrap();
     process_request_selection(&app).await.unwrap();
     process_request_remove(&app).await.unwrap();
+    process_update_active_request(&db, &app).await.unwrap();
+    process_is_http_method(&app).await.unwrap();
+    process_update_active_request_protocol(&db, &app)
+        .await
+        .unwrap();
+    process_send_request(&db, &app).await.unwrap();
+    process_load_request_details(&db, &app).await.unwrap();
+    process_create_request_header(&db, &app).await.unwrap();
+    process_update_request_header(&db, &app).await.unwrap();
+    process_remove_request_header(&db, &app).await.unwrap();
+    process_get_collection_headers(&db, &app).await.unwrap();
+    process_create_collection_header(&db, &app).await.unwrap();
+    process_update_collection_header(&db, &app).await.unwrap();
+    process_remove_collection_header(&db, &app).await.unwrap();
+    process_update_request_body(&db, &app).await.unwrap();
+    process_create_body_part(&db, &app).await.unwrap();
+    process_update_body_part(&db, &app).await.unwrap();
+    process_remove_body_part(&db, &app).await.unwrap();
+    process_pick_file(&app).unwrap();
+    process_sync_params_from_url(&db, &app).await.unwrap();
+    process_create_request_param(&db, &app).await.unwrap();
+    process_update_request_param(&db, &app).await.unwrap();
+    process_remove_request_param(&db, &app).await.unwrap();
+    process_select_environment(&db, &app).await.unwrap();
+    process_create_environment(&db, &app).await.unwrap();
+    process_update_environment(&db, &app).await.unwrap();
+    process_remove_environment(&db, &app).await.unwrap();
+    process_load_environment_variables(&db, &app).await.unwrap();
+    process_create_environment_variable(&db, &app)
+        .await
+        .unwrap();
+    process_update_environment_variable(&db, &app)
+        .await
+        .unwrap();
+    process_remove_environment_variable(&db, &app)
+        .await
+        .unwrap();
+    process_create_request_variable(&db, &app).await.unwrap();
+    process_update_request_variable(&db, &app).await.unwrap();
+    process_remove_request_variable(&db, &app).await.unwrap();
+    process_get_collection_variables(&db, &app).await.unwrap();
+    process_create_collection_variable(&db, &app).await.unwrap();
+    process_update_collection_variable(&db, &app).await.unwrap();
+    process_remove_collection_variable(&db, &app).await.unwrap();
+    process_get_global_variables(&db, &app).await.unwrap();
+    process_create_global_variable(&db, &app).await.unwrap();
+    process_update_global_variable(&db, &app).await.unwrap();
+    process_remove_global_variable(&db, &app).await.unwrap();
+    process_update_request_auth(&db, &app).await.unwrap();
+    process_get_collection_auth(&db, &app).await.unwrap();
+    process_update_collection_auth(&db, &app).await.unwrap();
+    process_update_request_oauth2(&db, &app).await.unwrap();
+    process_get_request_oauth2_token(&db, &app).await.unwrap();
+    process_update_collection_oauth2(&db, &app).await.unwrap();
+    process_get_collection_oauth2_token(&db, &app)
+        .await
+        .unwrap();
+    process_get_history(&db, &app).await.unwrap();
+    process_rerun_history_entry(&db, &app).await.unwrap();
+    process_restore_history_entry(&db, &app).await.unwrap();
+    process_remove_history_entry(&db, &app).await.unwrap();
+    process_clear_history(&db, &app).await.unwrap();
+    process_update_history_settings(&db, &app).await.unwrap();
+    process_select_history_entry(&app).await.unwrap();
+    process_export_history_har(&db, &app).await.unwrap();
+    process_open_compare_dialog(&db, &app).await.unwrap();
+    process_compare_urls(&db, &app).await.unwrap();
+    process_compare_history_entries(&db, &app).await.unwrap();
+    process_select_comparison(&db, &app).await.unwrap();
+    process_close_comparison(&db, &app).await.unwrap();
+    process_import_collection(&db, &app).await.unwrap();
+    process_reimport_spec(&db, &app).await.unwrap();
+    process_filter_har(&app).await.unwrap();
+    process_import_har(&db, &app).await.unwrap();
+    process_export_collections(&db, &app).await.unwrap();
+    process_import_curl(&db, &app).await.unwrap();
+    process_copy_as_curl(&db, &app).await.unwrap();
+    process_generate_snippet(&db, &app).await.unwrap();
+    process_connect_websocket(&db, &app).await.unwrap();
+    process_disconnect_websocket(&app).await.unwrap();
+    process_send_websocket_message(&app).await.unwrap();
+    process_ping_websocket(&app).await.unwrap();
+    process_filter_websocket_messages(&app).await.unwrap();
+    process_clear_websocket_messages(&app).await.unwrap();
+    process_update_websocket_subprotocols(&db, &app)
+        .await
+        .unwrap();
+    process_save_websocket_template(&db, &app).await.unwrap();
+    process_remove_websocket_template(&db, &app).await.unwrap();
+    process_update_graphql_query(&db, &app).await.unwrap();
+    process_complete_graphql_query(&app).await.unwrap();
+    process_apply_graphql_completion(&app).await.unwrap();
+    process_fetch_graphql_schema(&db, &app).await.unwrap();
+    process_filter_graphql_schema(&app).await.unwrap();
+    process_select_graphql_type(&app).await.unwrap();
+    process_subscribe_graphql(&db, &app).await.unwrap();
+    process_unsubscribe_graphql(&app).await.unwrap();
+    process_clear_graphql_events(&app).await.unwrap();
+    process_update_grpc_call(&db, &app).await.unwrap();
+    process_load_grpc_services(&db, &app).await.unwrap();
+    process_select_grpc_method(&db, &app).await.unwrap();
+    process_fill_grpc_template(&db, &app).await.unwrap();
+    process_invoke_grpc(&db, &app).await.unwrap();
+    process_send_grpc_message(&db, &app).await.unwrap();
+    process_finish_grpc_stream(&app).await.unwrap();
+    process_cancel_grpc_call(&app).await.unwrap();
+    process_clear_grpc_log(&app).await.unwrap();
+    process_update_sse_config(&db, &app).await.unwrap();
+    process_start_sse(&db, &app).await.unwrap();
+    process_stop_sse(&app).await.unwrap();
+    process_clear_sse_events(&app).await.unwrap();
+    process_save_sse_events(&db, &app).await.unwrap();
+    process_update_socketio_config(&db, &app).await.unwrap();
+    process_connect_socketio(&db, &app).await.unwrap();
+    process_disconnect_socketio(&app).await.unwrap();
+    process_emit_socketio_event(&db, &app).await.unwrap();
+    process_clear_socketio_log(&app).await.unwrap();
+    process_update_mqtt_config(&db, &app).await.unwrap();
+    process_connect_mqtt(&db, &app).await.unwrap();
+    process_disconnect_mqtt(&app).await.unwrap();
+    process_publish_mqtt(&db, &app).await.unwrap();
+    process_subscribe_mqtt(&db, &app).await.unwrap();
+    process_unsubscribe_mqtt(&db, &app).await.unwrap();
+    process_clear_mqtt_log(&app).await.unwrap();
 
     let size: PhysicalSize = PhysicalSize::new(1920, 1080);
     app.set_window_height(size.height as f32);
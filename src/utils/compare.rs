@@ -0,0 +1,633 @@
+use std::{collections::BTreeMap, error::Error, fmt};
+
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::utils::{crud::history::HistoryEntryData, tokio_runtime::runtime};
+
+/// Line diffs of bodies longer than this, in lines multiplied together, fall
+/// back to showing the differing middle as removed and added as a whole.
+const LINE_DIFF_LIMIT: usize = 4_000_000;
+
+/// Values shown in the list of JSON changes are cut off after this many characters.
+const CHANGE_VALUE_LENGTH: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChangeKind {
+    Same,
+    Added,
+    Removed,
+    Changed,
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChangeKind::Same => write!(f, "same"),
+            ChangeKind::Added => write!(f, "added"),
+            ChangeKind::Removed => write!(f, "removed"),
+            ChangeKind::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+/// A value that differs between two JSON documents, addressed by its path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// A row of a side-by-side diff. Line numbers start at 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffRow {
+    pub kind: ChangeKind,
+    pub left: Option<(usize, String)>,
+    pub right: Option<(usize, String)>,
+}
+
+/// A header present on either side, with all its values joined.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeaderDiff {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// One of the two responses being compared.
+#[derive(Clone, Debug, Default)]
+pub struct ComparisonSide {
+    pub label: String,
+    /// `None` when no response was received.
+    pub status: Option<u16>,
+    pub status_text: String,
+    pub error: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub body_truncated: bool,
+    pub size: usize,
+    pub total_ms: i64,
+}
+
+impl From<&HistoryEntryData> for ComparisonSide {
+    fn from(entry: &HistoryEntryData) -> Self {
+        ComparisonSide {
+            label: format!("{} {} · {}", entry.http_method, entry.url, entry.created_at),
+            status: entry.status.map(|status| status as u16),
+            status_text: entry.status_text.clone(),
+            error: entry.error.clone(),
+            headers: entry.response_headers.clone(),
+            body: entry.response_body.clone(),
+            body_truncated: entry.response_body_truncated,
+            size: entry.response_size as usize,
+            total_ms: entry.total_ms,
+        }
+    }
+}
+
+/// Two responses and how they differ.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub left: ComparisonSide,
+    pub right: ComparisonSide,
+    /// Both bodies are JSON, so they were compared structurally.
+    pub json: bool,
+    pub identical_bodies: bool,
+    pub json_changes: Vec<JsonChange>,
+    pub body_rows: Vec<DiffRow>,
+    pub headers: Vec<HeaderDiff>,
+}
+
+impl Comparison {
+    pub fn new(left: ComparisonSide, right: ComparisonSide) -> Comparison {
+        let left_json = parse_json(&left.body);
+        let right_json = parse_json(&right.body);
+
+        let (json, changes, left_text, right_text) = match (left_json, right_json) {
+            (Some(left_value), Some(right_value)) => (
+                true,
+                json_changes(&left_value, &right_value),
+                canonical_json(&left_value),
+                canonical_json(&right_value),
+            ),
+            _ => (
+                false,
+                Vec::new(),
+                String::from_utf8_lossy(&left.body).to_string(),
+                String::from_utf8_lossy(&right.body).to_string(),
+            ),
+        };
+        let identical_bodies = if json {
+            changes.is_empty()
+        } else {
+            left.body == right.body
+        };
+        let body_rows = diff_lines(&left_text, &right_text);
+        let headers = header_diff(&left.headers, &right.headers);
+
+        Comparison {
+            left,
+            right,
+            json,
+            identical_bodies,
+            json_changes: changes,
+            body_rows,
+            headers,
+        }
+    }
+
+    /// Time the right side took compared to the left, in milliseconds.
+    pub fn timing_difference(&self) -> i64 {
+        self.right.total_ms - self.left.total_ms
+    }
+}
+
+fn parse_json(body: &[u8]) -> Option<Value> {
+    if body.iter().all(|byte| byte.is_ascii_whitespace()) {
+        return None;
+    }
+    serde_json::from_slice(body).ok()
+}
+
+/// Copy of a JSON value with the keys of every object sorted.
+fn sorted_json(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), sorted_json(&map[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(sorted_json).collect()),
+        _ => value.clone(),
+    }
+}
+
+/// Pretty printed JSON with sorted keys, so that key order doesn't show up in a line diff.
+pub fn canonical_json(value: &Value) -> String {
+    serde_json::to_string_pretty(&sorted_json(value)).unwrap_or_default()
+}
+
+fn path_for_key(path: &str, key: &str) -> String {
+    let plain = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if plain {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{}]", path, Value::String(key.to_string()))
+    }
+}
+
+fn change_value(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(CHANGE_VALUE_LENGTH) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text,
+    }
+}
+
+fn collect_json_changes(path: &str, left: &Value, right: &Value, changes: &mut Vec<JsonChange>) {
+    match (left, right) {
+        (Value::Object(left_map), Value::Object(right_map)) => {
+            let mut keys: Vec<&String> = left_map.keys().chain(right_map.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let key_path = path_for_key(path, key);
+                match (left_map.get(key), right_map.get(key)) {
+                    (Some(left_value), Some(right_value)) => {
+                        collect_json_changes(&key_path, left_value, right_value, changes)
+                    }
+                    (Some(left_value), None) => changes.push(JsonChange {
+                        path: key_path,
+                        kind: ChangeKind::Removed,
+                        left: Some(change_value(left_value)),
+                        right: None,
+                    }),
+                    (None, Some(right_value)) => changes.push(JsonChange {
+                        path: key_path,
+                        kind: ChangeKind::Added,
+                        left: None,
+                        right: Some(change_value(right_value)),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(left_items), Value::Array(right_items)) => {
+            for index in 0..left_items.len().max(right_items.len()) {
+                let item_path = format!("{}[{}]", path, index);
+                match (left_items.get(index), right_items.get(index)) {
+                    (Some(left_value), Some(right_value)) => {
+                        collect_json_changes(&item_path, left_value, right_value, changes)
+                    }
+                    (Some(left_value), None) => changes.push(JsonChange {
+                        path: item_path,
+                        kind: ChangeKind::Removed,
+                        left: Some(change_value(left_value)),
+                        right: None,
+                    }),
+                    (None, Some(right_value)) => changes.push(JsonChange {
+                        path: item_path,
+                        kind: ChangeKind::Added,
+                        left: None,
+                        right: Some(change_value(right_value)),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if left != right => changes.push(JsonChange {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            left: Some(change_value(left)),
+            right: Some(change_value(right)),
+        }),
+        _ => {}
+    }
+}
+
+/// Every value that was added, removed or changed between two JSON documents.
+///
+/// Object keys are matched by name, so their order doesn't matter. Array
+/// items are matched by position.
+pub fn json_changes(left: &Value, right: &Value) -> Vec<JsonChange> {
+    let mut changes = Vec::new();
+    collect_json_changes("$", left, right, &mut changes);
+    changes
+}
+
+/// Turn runs of removed and added lines into rows showing both sides next to each other.
+fn pair_changes(
+    rows: &mut Vec<DiffRow>,
+    removed: &mut Vec<(usize, String)>,
+    added: &mut Vec<(usize, String)>,
+) {
+    let mut removed_lines = removed.drain(..);
+    let mut added_lines = added.drain(..);
+    loop {
+        let row = match (removed_lines.next(), added_lines.next()) {
+            (Some(left), Some(right)) => DiffRow {
+                kind: ChangeKind::Changed,
+                left: Some(left),
+                right: Some(right),
+            },
+            (Some(left), None) => DiffRow {
+                kind: ChangeKind::Removed,
+                left: Some(left),
+                right: None,
+            },
+            (None, Some(right)) => DiffRow {
+                kind: ChangeKind::Added,
+                left: None,
+                right: Some(right),
+            },
+            (None, None) => break,
+        };
+        rows.push(row);
+    }
+}
+
+/// Side-by-side diff of two texts, line by line.
+pub fn diff_lines(left: &str, right: &str) -> Vec<DiffRow> {
+    let left_lines: Vec<&str> = left.lines().collect();
+    let right_lines: Vec<&str> = right.lines().collect();
+
+    let prefix = left_lines
+        .iter()
+        .zip(&right_lines)
+        .take_while(|(left_line, right_line)| left_line == right_line)
+        .count();
+    let suffix = left_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(right_lines[prefix..].iter().rev())
+        .take_while(|(left_line, right_line)| left_line == right_line)
+        .count();
+    let left_middle = &left_lines[prefix..left_lines.len() - suffix];
+    let right_middle = &right_lines[prefix..right_lines.len() - suffix];
+
+    // Pairs of matching line indexes in the middle, from a longest common subsequence.
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    if left_middle.len() * right_middle.len() <= LINE_DIFF_LIMIT {
+        let width = right_middle.len() + 1;
+        let mut lengths = vec![0u32; (left_middle.len() + 1) * width];
+        for i in (0..left_middle.len()).rev() {
+            for j in (0..right_middle.len()).rev() {
+                lengths[i * width + j] = if left_middle[i] == right_middle[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < left_middle.len() && j < right_middle.len() {
+            if left_middle[i] == right_middle[j] {
+                matches.push((i, j));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    matches.push((left_middle.len(), right_middle.len()));
+
+    let mut rows: Vec<DiffRow> = Vec::new();
+    let same = |left_index: usize, right_index: usize, line: &str| DiffRow {
+        kind: ChangeKind::Same,
+        left: Some((left_index + 1, line.to_string())),
+        right: Some((right_index + 1, line.to_string())),
+    };
+    for (index, line) in left_lines.iter().enumerate().take(prefix) {
+        rows.push(same(index, index, line));
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    for (match_i, match_j) in matches {
+        while i < match_i {
+            removed.push((prefix + i + 1, left_middle[i].to_string()));
+            i += 1;
+        }
+        while j < match_j {
+            added.push((prefix + j + 1, right_middle[j].to_string()));
+            j += 1;
+        }
+        pair_changes(&mut rows, &mut removed, &mut added);
+        if match_i < left_middle.len() {
+            rows.push(same(prefix + i, prefix + j, left_middle[i]));
+            i += 1;
+            j += 1;
+        }
+    }
+
+    for offset in 0..suffix {
+        let left_index = left_lines.len() - suffix + offset;
+        let right_index = right_lines.len() - suffix + offset;
+        rows.push(same(left_index, right_index, left_lines[left_index]));
+    }
+    rows
+}
+
+/// Compare headers by name, case-insensitively, sorted by name.
+pub fn header_diff(left: &[(String, String)], right: &[(String, String)]) -> Vec<HeaderDiff> {
+    fn grouped(headers: &[(String, String)]) -> BTreeMap<String, Vec<String>> {
+        let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, value) in headers {
+            groups
+                .entry(name.to_lowercase())
+                .or_default()
+                .push(value.clone());
+        }
+        groups
+    }
+    let left = grouped(left);
+    let right = grouped(right);
+
+    let mut names: Vec<&String> = left.keys().chain(right.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| {
+            let left_value = left.get(name).map(|values| values.join(", "));
+            let right_value = right.get(name).map(|values| values.join(", "));
+            let kind = match (&left_value, &right_value) {
+                (Some(left_value), Some(right_value)) if left_value == right_value => {
+                    ChangeKind::Same
+                }
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (Some(_), None) => ChangeKind::Removed,
+                _ => ChangeKind::Added,
+            };
+            HeaderDiff {
+                name: name.clone(),
+                kind,
+                left: left_value,
+                right: right_value,
+            }
+        })
+        .collect()
+}
+
+/// Compare two history entries on the shared runtime, away from the UI thread.
+pub async fn prepare_comparison(
+    left: HistoryEntryData,
+    right: HistoryEntryData,
+) -> Result<Comparison, Box<dyn Error>> {
+    let comparison = runtime()
+        .spawn_blocking(move || Comparison::new((&left).into(), (&right).into()))
+        .await?;
+
+    Ok(comparison)
+}
+
+/// Name a comparison after what tells its sides apart: the hosts, or the
+/// whole URLs when they share one. Two responses from the same URL are named
+/// after their request.
+pub fn comparison_name(request_name: &str, left_url: &str, right_url: &str) -> String {
+    if left_url == right_url {
+        return format!("{} · compared", request_name);
+    }
+
+    let host = |url: &str| {
+        Url::parse(url).ok().and_then(|url| {
+            url.host_str().map(|host| match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            })
+        })
+    };
+    match (host(left_url), host(right_url)) {
+        (Some(left_host), Some(right_host)) if left_host != right_host => {
+            format!("{} vs {}", left_host, right_host)
+        }
+        _ => format!("{} vs {}", left_url, right_url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_changes_ignore_key_order() {
+        let left = json!({"a": 1, "b": {"c": [1, 2], "d": "x"}});
+        let right = json!({"b": {"d": "x", "c": [1, 2]}, "a": 1});
+
+        assert!(json_changes(&left, &right).is_empty());
+        assert!(canonical_json(&left) == canonical_json(&right));
+    }
+
+    #[test]
+    fn test_json_changes() {
+        let left = json!({"id": 1, "tags": ["a", "b"], "name": "old", "gone": true});
+        let right = json!({"id": "1", "tags": ["a"], "name": "old", "first name": "x"});
+
+        let changes = json_changes(&left, &right);
+        assert!(
+            changes
+                == vec![
+                    JsonChange {
+                        path: "$[\"first name\"]".to_string(),
+                        kind: ChangeKind::Added,
+                        left: None,
+                        right: Some("\"x\"".to_string()),
+                    },
+                    JsonChange {
+                        path: "$.gone".to_string(),
+                        kind: ChangeKind::Removed,
+                        left: Some("true".to_string()),
+                        right: None,
+                    },
+                    JsonChange {
+                        path: "$.id".to_string(),
+                        kind: ChangeKind::Changed,
+                        left: Some("1".to_string()),
+                        right: Some("\"1\"".to_string()),
+                    },
+                    JsonChange {
+                        path: "$.tags[1]".to_string(),
+                        kind: ChangeKind::Removed,
+                        left: Some("\"b\"".to_string()),
+                        right: None,
+                    },
+                ]
+        );
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let rows = diff_lines("a\nb\nc\nd", "a\nx\nc\nd\ne");
+        let kinds: Vec<ChangeKind> = rows.iter().map(|row| row.kind).collect();
+        assert!(
+            kinds
+                == vec![
+                    ChangeKind::Same,
+                    ChangeKind::Changed,
+                    ChangeKind::Same,
+                    ChangeKind::Same,
+                    ChangeKind::Added,
+                ]
+        );
+        assert!(rows[1].left == Some((2, "b".to_string())));
+        assert!(rows[1].right == Some((2, "x".to_string())));
+        assert!(rows[4].left.is_none());
+        assert!(rows[4].right == Some((5, "e".to_string())));
+
+        let rows = diff_lines("a\nb", "b");
+        assert!(rows[0].kind == ChangeKind::Removed);
+        assert!(rows[1].kind == ChangeKind::Same);
+        assert!(rows[1].left == Some((2, "b".to_string())));
+        assert!(rows[1].right == Some((1, "b".to_string())));
+
+        assert!(diff_lines("", "").is_empty());
+    }
+
+    #[test]
+    fn test_header_diff() {
+        let left = vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            ("Set-Cookie".to_string(), "a=1".to_string()),
+            ("Set-Cookie".to_string(), "b=2".to_string()),
+            ("X-Old".to_string(), "1".to_string()),
+        ];
+        let right = vec![
+            ("content-type".to_string(), "application/json".to_string()),
+            ("set-cookie".to_string(), "a=1".to_string()),
+            ("x-new".to_string(), "1".to_string()),
+        ];
+
+        let headers = header_diff(&left, &right);
+        let summary: Vec<(&str, ChangeKind)> = headers
+            .iter()
+            .map(|header| (header.name.as_str(), header.kind))
+            .collect();
+        assert!(
+            summary
+                == vec![
+                    ("content-type", ChangeKind::Same),
+                    ("set-cookie", ChangeKind::Changed),
+                    ("x-new", ChangeKind::Added),
+                    ("x-old", ChangeKind::Removed),
+                ]
+        );
+        assert!(headers[1].left == Some("a=1, b=2".to_string()));
+    }
+
+    #[test]
+    fn test_comparison() {
+        let left = ComparisonSide {
+            status: Some(200),
+            body: br#"{"b": 2, "a": 1}"#.to_vec(),
+            total_ms: 120,
+            ..Default::default()
+        };
+        let right = ComparisonSide {
+            status: Some(200),
+            body: br#"{"a": 1, "b": 2}"#.to_vec(),
+            total_ms: 80,
+            ..Default::default()
+        };
+
+        let comparison = Comparison::new(left, right);
+        assert!(comparison.json);
+        assert!(comparison.identical_bodies);
+        assert!(comparison
+            .body_rows
+            .iter()
+            .all(|row| row.kind == ChangeKind::Same));
+        assert!(comparison.timing_difference() == -40);
+
+        let left = ComparisonSide {
+            body: b"plain".to_vec(),
+            ..Default::default()
+        };
+        let right = ComparisonSide {
+            body: b"{}".to_vec(),
+            ..Default::default()
+        };
+        let comparison = Comparison::new(left, right);
+        assert!(!comparison.json);
+        assert!(!comparison.identical_bodies);
+    }
+
+    #[test]
+    fn test_comparison_name() {
+        assert!(
+            comparison_name(
+                "Users",
+                "https://staging.example.com/users",
+                "https://example.com/users"
+            ) == "staging.example.com vs example.com"
+        );
+        assert!(
+            comparison_name(
+                "Users",
+                "http://localhost:8080/users",
+                "http://localhost:8081/users"
+            ) == "localhost:8080 vs localhost:8081"
+        );
+        assert!(
+            comparison_name("Users", "https://example.com/v1", "https://example.com/v2")
+                == "https://example.com/v1 vs https://example.com/v2"
+        );
+        assert!(
+            comparison_name("Users", "https://example.com", "https://example.com")
+                == "Users · compared"
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::{error::Error, fmt};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum AuthTypes {
+    /// Use the auth of the request's collection. Only valid on requests.
+    Inherit,
+    None,
+    Basic,
+    Bearer,
+    ApiKey,
+    Digest,
+    OAuth2,
+}
+
+impl fmt::Display for AuthTypes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthTypes::Inherit => write!(f, "inherit"),
+            AuthTypes::None => write!(f, "none"),
+            AuthTypes::Basic => write!(f, "basic"),
+            AuthTypes::Bearer => write!(f, "bearer"),
+            AuthTypes::ApiKey => write!(f, "apikey"),
+            AuthTypes::Digest => write!(f, "digest"),
+            AuthTypes::OAuth2 => write!(f, "oauth2"),
+        }
+    }
+}
+
+impl AuthTypes {
+    pub fn from_string(s: &str) -> Option<AuthTypes> {
+        match s {
+            "inherit" => Some(AuthTypes::Inherit),
+            "none" => Some(AuthTypes::None),
+            "basic" => Some(AuthTypes::Basic),
+            "bearer" => Some(AuthTypes::Bearer),
+            "apikey" => Some(AuthTypes::ApiKey),
+            "digest" => Some(AuthTypes::Digest),
+            "oauth2" => Some(AuthTypes::OAuth2),
+            _ => None,
+        }
+    }
+}
+
+/// Where an API key is sent.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ApiKeyPlacement {
+    Header,
+    Query,
+}
+
+impl fmt::Display for ApiKeyPlacement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiKeyPlacement::Header => write!(f, "header"),
+            ApiKeyPlacement::Query => write!(f, "query"),
+        }
+    }
+}
+
+impl ApiKeyPlacement {
+    pub fn from_string(s: &str) -> Option<ApiKeyPlacement> {
+        match s {
+            "header" => Some(ApiKeyPlacement::Header),
+            "query" => Some(ApiKeyPlacement::Query),
+            _ => None,
+        }
+    }
+}
+
+/// Auth settings of a request or a collection, without the secret.
+///
+/// `username` is the user for Basic and Digest auth, `key_name` and
+/// `placement` describe an API key. The password, token or key value is
+/// kept in `authsecret` and read with [`get_auth_secret`].
+#[derive(Clone, Debug, FromRow)]
+pub struct AuthData {
+    pub id: String,
+    pub auth_type: String,
+    pub username: String,
+    pub key_name: String,
+    pub placement: String,
+    pub request_id: Option<String>,
+    pub collection_id: Option<String>,
+}
+
+pub async fn get_request_auth<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Option<AuthData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let auth = query_as(
+        "SELECT id, auth_type, username, key_name, placement, request_id, collection_id FROM authconfig WHERE request_id=$1",
+    )
+    .bind(request_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(auth)
+}
+
+pub async fn get_collection_auth<'a, A>(
+    collection_id: &str,
+    conn: A,
+) -> Result<Option<AuthData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let auth = query_as(
+        "SELECT id, auth_type, username, key_name, placement, request_id, collection_id FROM authconfig WHERE collection_id=$1",
+    )
+    .bind(collection_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(auth)
+}
+
+/// Create or replace the auth settings of a request.
+pub async fn update_request_auth<'a, A>(
+    request_id: &str,
+    auth_type: AuthTypes,
+    username: &str,
+    key_name: &str,
+    placement: ApiKeyPlacement,
+    conn: A,
+) -> Result<AuthData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "INSERT INTO authconfig (id, auth_type, username, key_name, placement, request_id) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(request_id) DO UPDATE SET auth_type=excluded.auth_type, username=excluded.username, key_name=excluded.key_name, placement=excluded.placement RETURNING id, auth_type, username, key_name, placement, request_id, collection_id";
+    let auth = query_as(command)
+        .bind(Uuid::new_v4().to_string())
+        .bind(auth_type.to_string())
+        .bind(username)
+        .bind(key_name)
+        .bind(placement.to_string())
+        .bind(request_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(auth)
+}
+
+/// Create or replace the auth settings shared by the requests of a collection.
+pub async fn update_collection_auth<'a, A>(
+    collection_id: &str,
+    auth_type: AuthTypes,
+    username: &str,
+    key_name: &str,
+    placement: ApiKeyPlacement,
+    conn: A,
+) -> Result<AuthData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "INSERT INTO authconfig (id, auth_type, username, key_name, placement, collection_id) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(collection_id) DO UPDATE SET auth_type=excluded.auth_type, username=excluded.username, key_name=excluded.key_name, placement=excluded.placement RETURNING id, auth_type, username, key_name, placement, request_id, collection_id";
+    let auth = query_as(command)
+        .bind(Uuid::new_v4().to_string())
+        .bind(auth_type.to_string())
+        .bind(username)
+        .bind(key_name)
+        .bind(placement.to_string())
+        .bind(collection_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(auth)
+}
+
+/// Password, token or key value of an auth configuration. Empty when none was saved.
+pub async fn get_auth_secret(auth_id: &str, pool: &SqlitePool) -> Result<String, Box<dyn Error>> {
+    let secret: Option<(String,)> = query_as("SELECT value FROM authsecret WHERE auth_id=$1")
+        .bind(auth_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(secret.map(|(value,)| value).unwrap_or_default())
+}
+
+pub async fn update_auth_secret<'a, A>(
+    auth_id: &str,
+    value: &str,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO authsecret (auth_id, value) VALUES ($1, $2) ON CONFLICT(auth_id) DO UPDATE SET value=excluded.value")
+        .bind(auth_id)
+        .bind(value)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_request_auth() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+        assert!(get_request_auth(&request.id, &db).await.unwrap().is_none());
+
+        let auth = update_request_auth(
+            &request.id,
+            AuthTypes::Basic,
+            "admin",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&auth.id, "hunter2", &db).await.unwrap();
+
+        let updated = update_request_auth(
+            &request.id,
+            AuthTypes::ApiKey,
+            "",
+            "api_key",
+            ApiKeyPlacement::Query,
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(updated.id == auth.id);
+        assert!(updated.auth_type == "apikey");
+        assert!(updated.placement == "query");
+        assert!(get_auth_secret(&auth.id, &db).await.unwrap() == "hunter2");
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_auth_secret(&auth.id, &db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collection_auth() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+
+        let auth = update_collection_auth(
+            &collection.id,
+            AuthTypes::Bearer,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&auth.id, "token", &db).await.unwrap();
+        update_auth_secret(&auth.id, "new-token", &db)
+            .await
+            .unwrap();
+
+        let stored = get_collection_auth(&collection.id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(stored.auth_type == "bearer");
+        assert!(stored.request_id.is_none());
+        assert!(get_auth_secret(&stored.id, &db).await.unwrap() == "new-token");
+    }
+}
@@ -0,0 +1,299 @@
+use std::{error::Error, fmt};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum BodyModes {
+    None,
+    Raw,
+    Json,
+    Xml,
+    FormUrlEncoded,
+    Multipart,
+    Binary,
+}
+
+impl fmt::Display for BodyModes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyModes::None => write!(f, "none"),
+            BodyModes::Raw => write!(f, "raw"),
+            BodyModes::Json => write!(f, "json"),
+            BodyModes::Xml => write!(f, "xml"),
+            BodyModes::FormUrlEncoded => write!(f, "x-www-form-urlencoded"),
+            BodyModes::Multipart => write!(f, "form-data"),
+            BodyModes::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl BodyModes {
+    pub fn from_string(s: &str) -> Option<BodyModes> {
+        match s {
+            "none" => Some(BodyModes::None),
+            "raw" => Some(BodyModes::Raw),
+            "json" => Some(BodyModes::Json),
+            "xml" => Some(BodyModes::Xml),
+            "x-www-form-urlencoded" => Some(BodyModes::FormUrlEncoded),
+            "form-data" => Some(BodyModes::Multipart),
+            "binary" => Some(BodyModes::Binary),
+            _ => None,
+        }
+    }
+
+    /// Content type sent for this mode when neither the body nor the
+    /// request headers specify one.
+    pub fn default_content_type(&self) -> Option<&'static str> {
+        match self {
+            BodyModes::None => None,
+            BodyModes::Raw => Some("text/plain"),
+            BodyModes::Json => Some("application/json"),
+            BodyModes::Xml => Some("application/xml"),
+            BodyModes::FormUrlEncoded => Some("application/x-www-form-urlencoded"),
+            BodyModes::Multipart => Some("multipart/form-data"),
+            BodyModes::Binary => Some("application/octet-stream"),
+        }
+    }
+}
+
+/// Body of a request. For binary mode the payload holds the file path.
+#[derive(Clone, Debug, FromRow)]
+pub struct RequestBodyData {
+    pub id: String,
+    pub mode: String,
+    pub content_type: String,
+    pub payload: String,
+    pub request_id: String,
+}
+
+/// A field of a form-urlencoded or multipart body. For file parts the
+/// value holds the file path.
+#[derive(Clone, Debug, FromRow)]
+pub struct BodyPartData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub is_file: bool,
+    pub enabled: bool,
+    pub position: i32,
+    pub request_id: String,
+}
+
+/// Get the body of a request, if one was ever saved.
+pub async fn get_request_body<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Option<RequestBodyData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let body = query_as(
+        "SELECT id, mode, content_type, payload, request_id FROM requestbody WHERE request_id=$1",
+    )
+    .bind(request_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(body)
+}
+
+/// Create or replace the body of a request.
+pub async fn update_request_body<'a, A>(
+    request_id: &str,
+    mode: BodyModes,
+    content_type: &str,
+    payload: &str,
+    conn: A,
+) -> Result<RequestBodyData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "INSERT INTO requestbody (id, mode, content_type, payload, request_id) VALUES ($1, $2, $3, $4, $5) ON CONFLICT(request_id) DO UPDATE SET mode=excluded.mode, content_type=excluded.content_type, payload=excluded.payload RETURNING id, mode, content_type, payload, request_id";
+    let body = query_as(command)
+        .bind(Uuid::new_v4().to_string())
+        .bind(mode.to_string())
+        .bind(content_type)
+        .bind(payload)
+        .bind(request_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(body)
+}
+
+/// Get all form fields of a request body, in display order.
+pub async fn get_body_parts<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Vec<BodyPartData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let parts = query_as(
+        "SELECT id, name, value, is_file, enabled, position, request_id FROM requestbodypart WHERE request_id=$1 ORDER BY position, created_at",
+    )
+    .bind(request_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(parts)
+}
+
+/// Add a form field at the end of a request body.
+pub async fn create_body_part<'a, A>(
+    request_id: &str,
+    name: &str,
+    value: &str,
+    is_file: bool,
+    conn: A,
+) -> Result<BodyPartData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let part = query_as(
+        "INSERT INTO requestbodypart (id, name, value, is_file, enabled, position, request_id) VALUES ($1, $2, $3, $4, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM requestbodypart WHERE request_id=$5), $5) RETURNING id, name, value, is_file, enabled, position, request_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(is_file)
+    .bind(request_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(part)
+}
+
+/// Update a form field of a request body.
+pub async fn update_body_part<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    is_file: bool,
+    enabled: bool,
+    conn: A,
+) -> Result<BodyPartData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE requestbodypart SET name=$1, value=$2, is_file=$3, enabled=$4 WHERE id = $5 RETURNING id, name, value, is_file, enabled, position, request_id";
+    let part = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(is_file)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(part)
+}
+
+pub async fn delete_body_part<'a, A>(id: &str, conn: A) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("DELETE FROM requestbodypart WHERE id=$1")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[test]
+    fn test_body_modes_round_trip() {
+        let modes = [
+            BodyModes::None,
+            BodyModes::Raw,
+            BodyModes::Json,
+            BodyModes::Xml,
+            BodyModes::FormUrlEncoded,
+            BodyModes::Multipart,
+            BodyModes::Binary,
+        ];
+        for mode in modes {
+            assert!(BodyModes::from_string(&mode.to_string()) == Some(mode));
+        }
+        assert!(BodyModes::from_string("yaml").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_request_body() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(get_request_body(&request.id, &db).await.unwrap().is_none());
+
+        let body = update_request_body(&request.id, BodyModes::Json, "", "{}", &db)
+            .await
+            .unwrap();
+        assert!(body.mode == "json");
+
+        let updated = update_request_body(&request.id, BodyModes::Raw, "text/csv", "a,b", &db)
+            .await
+            .unwrap();
+        assert!(updated.id == body.id);
+
+        let fetched = get_request_body(&request.id, &db).await.unwrap().unwrap();
+        assert!(fetched.mode == "raw");
+        assert!(fetched.content_type == "text/csv");
+        assert!(fetched.payload == "a,b");
+    }
+
+    #[tokio::test]
+    async fn test_body_parts() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+
+        let field = create_body_part(&request.id, "name", "querry", false, &db)
+            .await
+            .unwrap();
+        let file = create_body_part(&request.id, "upload", "/tmp/a.txt", true, &db)
+            .await
+            .unwrap();
+        assert!(file.is_file);
+        assert!(file.position == field.position + 1);
+
+        let updated = update_body_part(&field.id, "name", "querry", false, false, &db)
+            .await
+            .unwrap();
+        assert!(!updated.enabled);
+
+        delete_body_part(&file.id, &db).await.unwrap();
+        let parts = get_body_parts(&request.id, &db).await.unwrap();
+        assert!(parts.len() == 1);
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_body_parts(&request.id, &db).await.unwrap().is_empty());
+        assert!(get_request_body(&request.id, &db).await.unwrap().is_none());
+    }
+}
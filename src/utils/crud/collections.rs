@@ -2,7 +2,7 @@ use std::error::Error;
 
 use crate::utils::get_icon_pack_names;
 use rand::{rng, seq::IndexedRandom};
-use sqlx::{query, query_as, FromRow, SqlitePool};
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
 use uuid::Uuid;
 
 #[derive(Clone, Debug, FromRow)]
@@ -23,7 +23,7 @@ pub async fn get_all_collections(pool: &SqlitePool) -> Result<Vec<CollectionData
         FROM 
             collectionitem
         ORDER BY 
-            created_at DESC",
+            created_at DESC, rowid DESC",
     )
     .fetch_all(pool)
     .await?;
@@ -46,7 +46,7 @@ pub async fn search_collections(
         WHERE 
             LOWER(name) LIKE LOWER($1)
         ORDER BY 
-            created_at DESC",
+            created_at DESC, rowid DESC",
     )
     .bind(format!("%{}%", search_term))
     .fetch_all(pool)
@@ -56,13 +56,17 @@ pub async fn search_collections(
 }
 
 /// Update a collection item.
-pub async fn update_collection_item(
+pub async fn update_collection_item<'a, A>(
     id: &str,
     name: &str,
     icon: &str,
     requests_count: i32,
-    pool: &SqlitePool,
-) -> Result<CollectionData, Box<dyn Error>> {
+    conn: A,
+) -> Result<CollectionData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
     // Update collection
     let command = "UPDATE collectionitem SET name=$1, icon=$2, requests_count=$3 WHERE id = $4 RETURNING id, name, icon, requests_count";
     let collection: CollectionData = query_as(command)
@@ -70,15 +74,19 @@ pub async fn update_collection_item(
         .bind(icon)
         .bind(requests_count)
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
     Ok(collection)
 }
 
-pub async fn create_collection(
+pub async fn create_collection<'a, A>(
     name: String,
-    pool: &SqlitePool,
-) -> Result<CollectionData, Box<dyn Error>> {
+    conn: A,
+) -> Result<CollectionData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
     let icon_items = get_icon_pack_names()?;
     let mut rng = rng();
     let random_icon = icon_items
@@ -91,7 +99,7 @@ pub async fn create_collection(
         .bind(Uuid::new_v4().to_string())
         .bind(name)
         .bind(random_icon)
-        .fetch_one(pool).await?;
+        .fetch_one(&mut *conn).await?;
 
     Ok(collection)
 }
@@ -108,18 +116,190 @@ pub async fn delete_collection(
     Ok(())
 }
 
-pub async fn get_single_collection(
+pub async fn get_single_collection<'a, A>(
     id: &str,
-    pool: &SqlitePool,
-) -> Result<CollectionData, Box<dyn Error>> {
+    conn: A,
+) -> Result<CollectionData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
     let collection: CollectionData =
         query_as("SELECT id, name, icon, requests_count FROM collectionitem WHERE id = $1")
             .bind(id)
-            .fetch_one(pool)
+            .fetch_one(&mut *conn)
             .await?;
     Ok(collection)
 }
 
+#[derive(Clone, Debug, FromRow)]
+pub struct CollectionHeaderData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub collection_id: String,
+}
+
+/// Get all headers shared by the requests of a collection, in display order.
+pub async fn get_collection_headers(
+    collection_id: &str,
+    pool: &SqlitePool,
+) -> Result<Vec<CollectionHeaderData>, Box<dyn Error>> {
+    let headers = query_as(
+        "SELECT id, COALESCE(name, '') AS name, COALESCE(value, '') AS value, enabled, position, collection_id FROM collectionheader WHERE collection_id=$1 ORDER BY position, created_at",
+    )
+    .bind(collection_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(headers)
+}
+
+/// Add a header at the end of a collection's header list.
+pub async fn create_collection_header<'a, A>(
+    collection_id: &str,
+    name: &str,
+    value: &str,
+    conn: A,
+) -> Result<CollectionHeaderData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let header = query_as(
+        "INSERT INTO collectionheader (id, name, value, enabled, position, collection_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM collectionheader WHERE collection_id=$4), $4) RETURNING id, COALESCE(name, '') AS name, COALESCE(value, '') AS value, enabled, position, collection_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(collection_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(header)
+}
+
+/// Update a collection header.
+pub async fn update_collection_header<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    conn: A,
+) -> Result<CollectionHeaderData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE collectionheader SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, COALESCE(name, '') AS name, COALESCE(value, '') AS value, enabled, position, collection_id";
+    let header = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(header)
+}
+
+pub async fn delete_collection_header(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM collectionheader WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct CollectionVariableData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub collection_id: String,
+}
+
+/// Get all variables of a collection, including disabled ones, in display order.
+pub async fn get_collection_variables<'a, A>(
+    collection_id: &str,
+    conn: A,
+) -> Result<Vec<CollectionVariableData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let variables = query_as(
+        "SELECT id, name, value, enabled, position, collection_id FROM collectionvariable WHERE collection_id=$1 ORDER BY position, created_at",
+    )
+    .bind(collection_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(variables)
+}
+
+/// Add a variable at the end of a collection's variables table.
+pub async fn create_collection_variable<'a, A>(
+    collection_id: &str,
+    name: &str,
+    value: &str,
+    conn: A,
+) -> Result<CollectionVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let variable = query_as(
+        "INSERT INTO collectionvariable (id, name, value, enabled, position, collection_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM collectionvariable WHERE collection_id=$4), $4) RETURNING id, name, value, enabled, position, collection_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(collection_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(variable)
+}
+
+/// Update a collection variable.
+pub async fn update_collection_variable<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    conn: A,
+) -> Result<CollectionVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE collectionvariable SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position, collection_id";
+    let variable = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(variable)
+}
+
+pub async fn delete_collection_variable(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM collectionvariable WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,4 +457,74 @@ mod tests {
 
         assert!(collections.len() == 0);
     }
+
+    #[tokio::test]
+    async fn test_collection_headers() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+
+        let first = create_collection_header(&collection.id, "X-Tenant", "acme", &db)
+            .await
+            .unwrap();
+        let second = create_collection_header(&collection.id, "Authorization", "Bearer 1", &db)
+            .await
+            .unwrap();
+        assert!(first.enabled);
+        assert!(second.position == first.position + 1);
+
+        let updated = update_collection_header(&second.id, "Authorization", "Bearer 2", false, &db)
+            .await
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.value == "Bearer 2");
+
+        let headers = get_collection_headers(&collection.id, &db).await.unwrap();
+        assert!(headers.len() == 2);
+        assert!(headers[0].name == "X-Tenant");
+
+        delete_collection_header(&first.id, &db).await.unwrap();
+        let headers = get_collection_headers(&collection.id, &db).await.unwrap();
+        assert!(headers.len() == 1);
+
+        delete_collection(&collection.id, &db).await.unwrap();
+        let headers = get_collection_headers(&collection.id, &db).await.unwrap();
+        assert!(headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collection_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .expect("Cant get collections");
+
+        let base_url = create_collection_variable(&collection.id, "baseUrl", "https://api", &db)
+            .await
+            .unwrap();
+        let token = create_collection_variable(&collection.id, "token", "abc", &db)
+            .await
+            .unwrap();
+        assert!(token.position == base_url.position + 1);
+
+        let updated = update_collection_variable(&token.id, "token", "xyz", false, &db)
+            .await
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.value == "xyz");
+
+        delete_collection_variable(&base_url.id, &db).await.unwrap();
+        let variables = get_collection_variables(&collection.id, &db).await.unwrap();
+        assert!(variables.len() == 1);
+        assert!(variables[0].name == "token");
+
+        delete_collection(&collection.id, &db)
+            .await
+            .expect("Can't delete");
+        assert!(get_collection_variables(&collection.id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
 }
@@ -0,0 +1,127 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, FromRow, SqlitePool};
+use uuid::Uuid;
+
+/// Two history entries kept for comparing side by side.
+#[derive(Clone, Debug, FromRow)]
+pub struct ComparisonData {
+    pub id: String,
+    pub name: String,
+    pub left_id: String,
+    pub right_id: String,
+}
+
+/// All saved comparisons, oldest first, in the order their tabs were opened.
+pub async fn get_comparisons(pool: &SqlitePool) -> Result<Vec<ComparisonData>, Box<dyn Error>> {
+    let comparisons = query_as(
+        "SELECT id, name, left_id, right_id FROM responsecomparison ORDER BY created_at, rowid",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(comparisons)
+}
+
+pub async fn get_comparison(id: &str, pool: &SqlitePool) -> Result<ComparisonData, Box<dyn Error>> {
+    let comparison =
+        query_as("SELECT id, name, left_id, right_id FROM responsecomparison WHERE id=$1")
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(comparison)
+}
+
+pub async fn create_comparison(
+    name: &str,
+    left_id: &str,
+    right_id: &str,
+    pool: &SqlitePool,
+) -> Result<ComparisonData, Box<dyn Error>> {
+    let comparison = query_as(
+        "INSERT INTO responsecomparison (id, name, left_id, right_id) VALUES ($1, $2, $3, $4) RETURNING id, name, left_id, right_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(left_id)
+    .bind(right_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(comparison)
+}
+
+pub async fn delete_comparison(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM responsecomparison WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::history::{
+            clear_history, create_history_entry, delete_history_entry, search_history,
+            update_history_settings, NewHistoryEntry,
+        },
+    };
+
+    async fn history_entry(url: &str, pool: &SqlitePool) -> String {
+        let entry = NewHistoryEntry {
+            http_method: "GET".to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        create_history_entry(&entry, pool).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn test_comparisons() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let left_id = history_entry("https://staging.example.com", &db).await;
+        let right_id = history_entry("https://example.com", &db).await;
+
+        let comparison = create_comparison("Staging vs production", &left_id, &right_id, &db)
+            .await
+            .unwrap();
+        let saved = get_comparison(&comparison.id, &db).await.unwrap();
+        assert!(saved.name == "Staging vs production");
+        assert!(saved.left_id == left_id && saved.right_id == right_id);
+        assert!(get_comparisons(&db).await.unwrap().len() == 1);
+
+        delete_comparison(&comparison.id, &db).await.unwrap();
+        assert!(get_comparisons(&db).await.unwrap().is_empty());
+
+        // Deleting either side deletes the comparison.
+        create_comparison("Again", &left_id, &right_id, &db)
+            .await
+            .unwrap();
+        delete_history_entry(&right_id, &db).await.unwrap();
+        assert!(get_comparisons(&db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compared_entries_are_kept() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let left_id = history_entry("https://example.com/1", &db).await;
+        let right_id = history_entry("https://example.com/2", &db).await;
+        history_entry("https://example.com/3", &db).await;
+        create_comparison("Kept", &left_id, &right_id, &db)
+            .await
+            .unwrap();
+
+        update_history_settings(1, 0, &db).await.unwrap();
+        assert!(search_history("", 100, &db).await.unwrap().len() == 3);
+
+        clear_history(&db).await.unwrap();
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 2);
+        assert!(get_comparisons(&db).await.unwrap().len() == 1);
+    }
+}
@@ -0,0 +1,356 @@
+use std::{collections::HashMap, error::Error};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct EnvironmentData {
+    pub id: String,
+    pub name: String,
+    pub is_active: bool,
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct EnvironmentVariableData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub environment_id: String,
+}
+
+pub async fn get_environments(pool: &SqlitePool) -> Result<Vec<EnvironmentData>, Box<dyn Error>> {
+    let environments = query_as("SELECT id, name, is_active FROM environment ORDER BY created_at")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(environments)
+}
+
+pub async fn create_environment<'a, A>(
+    name: &str,
+    conn: A,
+) -> Result<EnvironmentData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let environment = query_as(
+        "INSERT INTO environment (id, name) VALUES ($1, $2) RETURNING id, name, is_active",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(environment)
+}
+
+pub async fn update_environment(
+    id: &str,
+    name: &str,
+    pool: &SqlitePool,
+) -> Result<EnvironmentData, Box<dyn Error>> {
+    let environment =
+        query_as("UPDATE environment SET name=$1 WHERE id = $2 RETURNING id, name, is_active")
+            .bind(name)
+            .bind(id)
+            .fetch_one(pool)
+            .await?;
+
+    Ok(environment)
+}
+
+pub async fn delete_environment(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM environment WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Make an environment the active one, or deactivate all of them when `id` is `None`.
+pub async fn set_active_environment(
+    id: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    let mut transaction = pool.begin().await?;
+
+    query("UPDATE environment SET is_active=0")
+        .execute(&mut *transaction)
+        .await?;
+    if let Some(id) = id {
+        query("UPDATE environment SET is_active=1 WHERE id=$1")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+pub async fn get_active_environment(
+    pool: &SqlitePool,
+) -> Result<Option<EnvironmentData>, Box<dyn Error>> {
+    let environment =
+        query_as("SELECT id, name, is_active FROM environment WHERE is_active=1 LIMIT 1")
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(environment)
+}
+
+/// Get all variables of an environment, including disabled ones, in display order.
+pub async fn get_environment_variables(
+    environment_id: &str,
+    pool: &SqlitePool,
+) -> Result<Vec<EnvironmentVariableData>, Box<dyn Error>> {
+    let variables = query_as(
+        "SELECT id, name, value, enabled, position, environment_id FROM environmentvariable WHERE environment_id=$1 ORDER BY position, created_at",
+    )
+    .bind(environment_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(variables)
+}
+
+/// Add a variable at the end of an environment's variables table.
+pub async fn create_environment_variable<'a, A>(
+    environment_id: &str,
+    name: &str,
+    value: &str,
+    conn: A,
+) -> Result<EnvironmentVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let variable = query_as(
+        "INSERT INTO environmentvariable (id, name, value, enabled, position, environment_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM environmentvariable WHERE environment_id=$4), $4) RETURNING id, name, value, enabled, position, environment_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(environment_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(variable)
+}
+
+pub async fn update_environment_variable<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    conn: A,
+) -> Result<EnvironmentVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE environmentvariable SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position, environment_id";
+    let variable = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(variable)
+}
+
+pub async fn delete_environment_variable(
+    id: &str,
+    pool: &SqlitePool,
+) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM environmentvariable WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Enabled variables of the active environment by name.
+///
+/// Empty when no environment is active. Later rows win over earlier rows
+/// with the same name.
+pub async fn get_active_environment_variables(
+    pool: &SqlitePool,
+) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let environment = match get_active_environment(pool).await? {
+        Some(data) => data,
+        None => return Ok(HashMap::new()),
+    };
+
+    let variables = get_environment_variables(&environment.id, pool)
+        .await?
+        .into_iter()
+        .filter(|variable| variable.enabled && !variable.name.trim().is_empty())
+        .map(|variable| (variable.name.trim().to_string(), variable.value))
+        .collect();
+
+    Ok(variables)
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct GlobalVariableData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+}
+
+/// Get all global variables, including disabled ones, in display order.
+pub async fn get_global_variables(
+    pool: &SqlitePool,
+) -> Result<Vec<GlobalVariableData>, Box<dyn Error>> {
+    let variables = query_as(
+        "SELECT id, name, value, enabled, position FROM globalvariable ORDER BY position, created_at",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(variables)
+}
+
+/// Add a variable at the end of the global variables table.
+pub async fn create_global_variable(
+    name: &str,
+    value: &str,
+    pool: &SqlitePool,
+) -> Result<GlobalVariableData, Box<dyn Error>> {
+    let variable = query_as(
+        "INSERT INTO globalvariable (id, name, value, enabled, position) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM globalvariable)) RETURNING id, name, value, enabled, position",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(variable)
+}
+
+pub async fn update_global_variable(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    pool: &SqlitePool,
+) -> Result<GlobalVariableData, Box<dyn Error>> {
+    let command = "UPDATE globalvariable SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position";
+    let variable = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(variable)
+}
+
+pub async fn delete_global_variable(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM globalvariable WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::setup_test_db;
+
+    #[tokio::test]
+    async fn test_environments() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let local = create_environment("Local", &db).await.unwrap();
+        let staging = create_environment("Staging", &db).await.unwrap();
+        assert!(!local.is_active);
+
+        let renamed = update_environment(&staging.id, "Stage", &db).await.unwrap();
+        assert!(renamed.name == "Stage");
+
+        set_active_environment(Some(&local.id), &db).await.unwrap();
+        set_active_environment(Some(&staging.id), &db)
+            .await
+            .unwrap();
+        let active = get_active_environment(&db).await.unwrap().unwrap();
+        assert!(active.id == staging.id);
+        let environments = get_environments(&db).await.unwrap();
+        assert!(environments.iter().filter(|env| env.is_active).count() == 1);
+
+        set_active_environment(None, &db).await.unwrap();
+        assert!(get_active_environment(&db).await.unwrap().is_none());
+
+        delete_environment(&local.id, &db).await.unwrap();
+        assert!(get_environments(&db).await.unwrap().len() == 1);
+    }
+
+    #[tokio::test]
+    async fn test_active_environment_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let environment = create_environment("Local", &db).await.unwrap();
+
+        create_environment_variable(&environment.id, "host", "localhost:8080", &db)
+            .await
+            .unwrap();
+        let token = create_environment_variable(&environment.id, "token", "abc", &db)
+            .await
+            .unwrap();
+        update_environment_variable(&token.id, "token", "abc", false, &db)
+            .await
+            .unwrap();
+
+        assert!(get_active_environment_variables(&db)
+            .await
+            .unwrap()
+            .is_empty());
+
+        set_active_environment(Some(&environment.id), &db)
+            .await
+            .unwrap();
+        let variables = get_active_environment_variables(&db).await.unwrap();
+        assert!(variables.len() == 1);
+        assert!(variables.get("host") == Some(&"localhost:8080".to_string()));
+
+        delete_environment(&environment.id, &db).await.unwrap();
+        assert!(get_environment_variables(&environment.id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_global_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let host = create_global_variable("host", "localhost", &db)
+            .await
+            .unwrap();
+        let port = create_global_variable("port", "80", &db).await.unwrap();
+        assert!(port.position == host.position + 1);
+
+        let updated = update_global_variable(&port.id, "port", "8080", true, &db)
+            .await
+            .unwrap();
+        assert!(updated.value == "8080");
+
+        delete_global_variable(&host.id, &db).await.unwrap();
+        let variables = get_global_variables(&db).await.unwrap();
+        assert!(variables.len() == 1);
+        assert!(variables[0].name == "port");
+    }
+}
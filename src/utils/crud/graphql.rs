@@ -0,0 +1,149 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+
+/// The operation of a GraphQL request.
+#[derive(Clone, Debug, Default, FromRow, PartialEq)]
+pub struct GraphQLQueryData {
+    pub query: String,
+    /// JSON as typed, possibly with `{{variables}}` in it.
+    pub variables: String,
+    pub operation_name: String,
+}
+
+/// The operation of a request, empty when none was written yet.
+pub async fn get_graphql_query<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<GraphQLQueryData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let operation: Option<GraphQLQueryData> =
+        query_as("SELECT query, variables, operation_name FROM graphqlquery WHERE request_id=$1")
+            .bind(request_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    Ok(operation.unwrap_or_default())
+}
+
+pub async fn update_graphql_query<'a, A>(
+    request_id: &str,
+    operation: &GraphQLQueryData,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO graphqlquery (request_id, query, variables, operation_name) VALUES ($1, $2, $3, $4) ON CONFLICT(request_id) DO UPDATE SET query=excluded.query, variables=excluded.variables, operation_name=excluded.operation_name")
+        .bind(request_id)
+        .bind(&operation.query)
+        .bind(&operation.variables)
+        .bind(&operation.operation_name)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// A schema introspected for a request.
+#[derive(Clone, Debug, FromRow)]
+pub struct GraphQLSchemaData {
+    /// The `__schema` object of the introspection result.
+    pub schema: String,
+    pub fetched_at: String,
+}
+
+pub async fn get_graphql_schema<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Option<GraphQLSchemaData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let schema = query_as("SELECT schema, fetched_at FROM graphqlschema WHERE request_id=$1")
+        .bind(request_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(schema)
+}
+
+/// Cache the schema of a request, replacing the one fetched before.
+pub async fn save_graphql_schema<'a, A>(
+    request_id: &str,
+    schema: &str,
+    conn: A,
+) -> Result<GraphQLSchemaData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let saved = query_as("INSERT INTO graphqlschema (request_id, schema) VALUES ($1, $2) ON CONFLICT(request_id) DO UPDATE SET schema=excluded.schema, fetched_at=CURRENT_TIMESTAMP RETURNING schema, fetched_at")
+        .bind(request_id)
+        .bind(schema)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_graphql_query_and_schema() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Graph".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::GraphQL, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(get_graphql_query(&request.id, &db).await.unwrap() == GraphQLQueryData::default());
+        let operation = GraphQLQueryData {
+            query: "query Me { me { id } }".to_string(),
+            variables: "{\"id\": {{user_id}}}".to_string(),
+            operation_name: "Me".to_string(),
+        };
+        update_graphql_query(&request.id, &operation, &db)
+            .await
+            .unwrap();
+        update_graphql_query(&request.id, &operation, &db)
+            .await
+            .unwrap();
+        assert!(get_graphql_query(&request.id, &db).await.unwrap() == operation);
+
+        assert!(get_graphql_schema(&request.id, &db)
+            .await
+            .unwrap()
+            .is_none());
+        save_graphql_schema(&request.id, "{\"types\":[]}", &db)
+            .await
+            .unwrap();
+        let saved = save_graphql_schema(&request.id, "{\"types\":[{}]}", &db)
+            .await
+            .unwrap();
+        assert!(saved.schema == "{\"types\":[{}]}");
+        let cached = get_graphql_schema(&request.id, &db).await.unwrap().unwrap();
+        assert!(cached.schema == saved.schema && !cached.fetched_at.is_empty());
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_graphql_schema(&request.id, &db)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(get_graphql_query(&request.id, &db).await.unwrap() == GraphQLQueryData::default());
+    }
+}
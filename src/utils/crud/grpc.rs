@@ -0,0 +1,199 @@
+use std::{error::Error, fmt};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+
+/// Where the services of a gRPC request are described.
+#[derive(Debug, PartialEq, Clone)]
+pub enum DescriptorSources {
+    /// Asked from the server.
+    Reflection,
+    /// Compiled from .proto files.
+    Proto,
+}
+
+impl fmt::Display for DescriptorSources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DescriptorSources::Reflection => write!(f, "reflection"),
+            DescriptorSources::Proto => write!(f, "proto"),
+        }
+    }
+}
+
+impl DescriptorSources {
+    pub fn from_string(s: &str) -> Option<DescriptorSources> {
+        match s {
+            "reflection" => Some(DescriptorSources::Reflection),
+            "proto" => Some(DescriptorSources::Proto),
+            _ => None,
+        }
+    }
+}
+
+/// The call of a gRPC request.
+#[derive(Clone, Debug, FromRow, PartialEq)]
+pub struct GrpcCallData {
+    pub source: String,
+    /// One path per line.
+    pub proto_files: String,
+    /// One path per line.
+    pub import_paths: String,
+    /// Like `package.Service/Method`, empty until one is picked.
+    pub method: String,
+    /// JSON as typed, possibly with `{{variables}}` in it.
+    pub message: String,
+}
+
+impl Default for GrpcCallData {
+    fn default() -> Self {
+        GrpcCallData {
+            source: DescriptorSources::Reflection.to_string(),
+            proto_files: String::new(),
+            import_paths: String::new(),
+            method: String::new(),
+            message: String::new(),
+        }
+    }
+}
+
+/// The call of a request, the default one when none was set up yet.
+pub async fn get_grpc_call<'a, A>(request_id: &str, conn: A) -> Result<GrpcCallData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let call: Option<GrpcCallData> = query_as(
+        "SELECT source, proto_files, import_paths, method, message FROM grpccall WHERE request_id=$1",
+    )
+    .bind(request_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(call.unwrap_or_default())
+}
+
+pub async fn update_grpc_call<'a, A>(
+    request_id: &str,
+    call: &GrpcCallData,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO grpccall (request_id, source, proto_files, import_paths, method, message) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(request_id) DO UPDATE SET source=excluded.source, proto_files=excluded.proto_files, import_paths=excluded.import_paths, method=excluded.method, message=excluded.message")
+        .bind(request_id)
+        .bind(&call.source)
+        .bind(&call.proto_files)
+        .bind(&call.import_paths)
+        .bind(&call.method)
+        .bind(&call.message)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Services loaded for a request.
+#[derive(Clone, Debug, FromRow)]
+pub struct GrpcDescriptorsData {
+    /// An encoded `FileDescriptorSet`.
+    pub descriptors: Vec<u8>,
+    pub loaded_at: String,
+}
+
+pub async fn get_grpc_descriptors<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Option<GrpcDescriptorsData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let descriptors =
+        query_as("SELECT descriptors, loaded_at FROM grpcdescriptors WHERE request_id=$1")
+            .bind(request_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    Ok(descriptors)
+}
+
+/// Keep the services of a request, replacing the ones loaded before.
+pub async fn save_grpc_descriptors<'a, A>(
+    request_id: &str,
+    descriptors: &[u8],
+    conn: A,
+) -> Result<GrpcDescriptorsData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let saved = query_as("INSERT INTO grpcdescriptors (request_id, descriptors) VALUES ($1, $2) ON CONFLICT(request_id) DO UPDATE SET descriptors=excluded.descriptors, loaded_at=CURRENT_TIMESTAMP RETURNING descriptors, loaded_at")
+        .bind(request_id)
+        .bind(descriptors)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_grpc_call_and_descriptors() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Rpc".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Grpc, &collection.id, &db)
+            .await
+            .unwrap();
+
+        let call = get_grpc_call(&request.id, &db).await.unwrap();
+        assert!(call == GrpcCallData::default());
+        assert!(
+            DescriptorSources::from_string(&call.source) == Some(DescriptorSources::Reflection)
+        );
+
+        let call = GrpcCallData {
+            source: DescriptorSources::Proto.to_string(),
+            proto_files: "/protos/echo.proto".to_string(),
+            import_paths: "/include".to_string(),
+            method: "echo.Echo/Say".to_string(),
+            message: "{\"text\": \"{{name}}\"}".to_string(),
+        };
+        update_grpc_call(&request.id, &call, &db).await.unwrap();
+        update_grpc_call(&request.id, &call, &db).await.unwrap();
+        assert!(get_grpc_call(&request.id, &db).await.unwrap() == call);
+
+        assert!(get_grpc_descriptors(&request.id, &db)
+            .await
+            .unwrap()
+            .is_none());
+        save_grpc_descriptors(&request.id, &[1, 2], &db)
+            .await
+            .unwrap();
+        let saved = save_grpc_descriptors(&request.id, &[3], &db).await.unwrap();
+        assert!(saved.descriptors == vec![3]);
+        let loaded = get_grpc_descriptors(&request.id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(loaded.descriptors == vec![3] && !loaded.loaded_at.is_empty());
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_grpc_descriptors(&request.id, &db)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(get_grpc_call(&request.id, &db).await.unwrap() == GrpcCallData::default());
+    }
+}
@@ -0,0 +1,672 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, FromRow, SqlitePool};
+use uuid::Uuid;
+
+use crate::utils::{
+    crud::{
+        bodies::{update_request_body, BodyModes},
+        collections::{create_collection, get_all_collections},
+        params::replace_request_params,
+        requests::{
+            create_request, create_request_header, parse_method, update_request_item,
+            ProtocolTypes, RequestData,
+        },
+    },
+    query_params::{merge_url_params, parse_query},
+};
+
+/// Bodies larger than this are cut off before they are stored in the history.
+pub const HISTORY_BODY_LIMIT: usize = 1024 * 1024;
+
+/// Condition matching executions that no comparison refers to.
+const NOT_COMPARED: &str = "id NOT IN (SELECT left_id FROM responsecomparison UNION SELECT right_id FROM responsecomparison)";
+
+/// Collection that entries are restored into once their own collection is deleted.
+pub const RESTORED_COLLECTION_NAME: &str = "Restored from history";
+
+/// An execution to record, with the request as it went on the wire.
+#[derive(Clone, Debug, Default)]
+pub struct NewHistoryEntry {
+    pub request_id: Option<String>,
+    pub request_name: String,
+    pub collection_id: Option<String>,
+    pub environment_id: Option<String>,
+    pub environment_name: String,
+    pub http_method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Vec<u8>,
+    /// `None` when no response was received.
+    pub status: Option<u16>,
+    pub status_text: String,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Vec<u8>,
+    pub response_size: usize,
+    pub error: String,
+    pub headers_received_ms: i64,
+    pub total_ms: i64,
+}
+
+/// What the history list shows for an execution.
+#[derive(Clone, Debug, FromRow)]
+pub struct HistorySummaryData {
+    pub id: String,
+    pub created_at: String,
+    pub request_id: Option<String>,
+    pub request_name: String,
+    pub environment_name: String,
+    pub http_method: String,
+    pub url: String,
+    pub status: Option<i64>,
+    pub status_text: String,
+    pub error: String,
+    pub total_ms: i64,
+}
+
+#[derive(FromRow)]
+struct HistoryRow {
+    id: String,
+    created_at: String,
+    request_id: Option<String>,
+    request_name: String,
+    collection_id: Option<String>,
+    environment_id: Option<String>,
+    environment_name: String,
+    http_method: String,
+    url: String,
+    request_headers: String,
+    request_body: Vec<u8>,
+    request_body_truncated: bool,
+    status: Option<i64>,
+    status_text: String,
+    response_headers: String,
+    response_body: Vec<u8>,
+    response_body_truncated: bool,
+    response_size: i64,
+    error: String,
+    headers_received_ms: i64,
+    total_ms: i64,
+}
+
+/// Everything recorded for an execution.
+#[derive(Clone, Debug)]
+pub struct HistoryEntryData {
+    pub id: String,
+    pub created_at: String,
+    pub request_id: Option<String>,
+    pub request_name: String,
+    pub collection_id: Option<String>,
+    pub environment_id: Option<String>,
+    pub environment_name: String,
+    pub http_method: String,
+    pub url: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Vec<u8>,
+    pub request_body_truncated: bool,
+    pub status: Option<i64>,
+    pub status_text: String,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Vec<u8>,
+    pub response_body_truncated: bool,
+    pub response_size: i64,
+    pub error: String,
+    pub headers_received_ms: i64,
+    pub total_ms: i64,
+}
+
+impl From<HistoryRow> for HistoryEntryData {
+    fn from(row: HistoryRow) -> Self {
+        HistoryEntryData {
+            id: row.id,
+            created_at: row.created_at,
+            request_id: row.request_id,
+            request_name: row.request_name,
+            collection_id: row.collection_id,
+            environment_id: row.environment_id,
+            environment_name: row.environment_name,
+            http_method: row.http_method,
+            url: row.url,
+            request_headers: decode_headers(&row.request_headers),
+            request_body: row.request_body,
+            request_body_truncated: row.request_body_truncated,
+            status: row.status,
+            status_text: row.status_text,
+            response_headers: decode_headers(&row.response_headers),
+            response_body: row.response_body,
+            response_body_truncated: row.response_body_truncated,
+            response_size: row.response_size,
+            error: row.error,
+            headers_received_ms: row.headers_received_ms,
+            total_ms: row.total_ms,
+        }
+    }
+}
+
+/// How much history is kept. Zero means no limit.
+#[derive(Clone, Debug, FromRow)]
+pub struct HistorySettingsData {
+    pub max_entries: i64,
+    pub max_age_days: i64,
+}
+
+fn encode_headers(headers: &[(String, String)]) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string(headers)?)
+}
+
+fn decode_headers(headers: &str) -> Vec<(String, String)> {
+    serde_json::from_str(headers).unwrap_or_default()
+}
+
+/// Cut a body down to what is stored, and whether anything was cut.
+fn truncate_body(body: &[u8]) -> (&[u8], bool) {
+    if body.len() > HISTORY_BODY_LIMIT {
+        (&body[..HISTORY_BODY_LIMIT], true)
+    } else {
+        (body, false)
+    }
+}
+
+/// Record an execution and drop whatever falls outside the retention limits.
+pub async fn create_history_entry(
+    entry: &NewHistoryEntry,
+    pool: &SqlitePool,
+) -> Result<HistorySummaryData, Box<dyn Error>> {
+    let (request_body, request_body_truncated) = truncate_body(&entry.request_body);
+    let (response_body, response_body_truncated) = truncate_body(&entry.response_body);
+
+    let summary = query_as(
+        "INSERT INTO requesthistory (id, request_id, request_name, collection_id, environment_id, environment_name, http_method, url, request_headers, request_body, request_body_truncated, status, status_text, response_headers, response_body, response_body_truncated, response_size, error, headers_received_ms, total_ms) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20) RETURNING id, created_at, request_id, request_name, environment_name, http_method, url, status, status_text, error, total_ms",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(&entry.request_id)
+    .bind(&entry.request_name)
+    .bind(&entry.collection_id)
+    .bind(&entry.environment_id)
+    .bind(&entry.environment_name)
+    .bind(&entry.http_method)
+    .bind(&entry.url)
+    .bind(encode_headers(&entry.request_headers)?)
+    .bind(request_body)
+    .bind(request_body_truncated)
+    .bind(entry.status.map(i64::from))
+    .bind(&entry.status_text)
+    .bind(encode_headers(&entry.response_headers)?)
+    .bind(response_body)
+    .bind(response_body_truncated)
+    .bind(entry.response_size as i64)
+    .bind(&entry.error)
+    .bind(entry.headers_received_ms)
+    .bind(entry.total_ms)
+    .fetch_one(pool)
+    .await?;
+
+    prune_history(pool).await?;
+
+    Ok(summary)
+}
+
+/// Most recent executions first, optionally filtered by URL, method, status,
+/// request name or environment.
+pub async fn search_history(
+    search_term: &str,
+    limit: i64,
+    pool: &SqlitePool,
+) -> Result<Vec<HistorySummaryData>, Box<dyn Error>> {
+    let entries = query_as(
+        "SELECT
+            id,
+            created_at,
+            request_id,
+            request_name,
+            environment_name,
+            http_method,
+            url,
+            status,
+            status_text,
+            error,
+            total_ms
+        FROM
+            requesthistory
+        WHERE
+            LOWER(url) LIKE LOWER($1)
+            OR LOWER(http_method) LIKE LOWER($1)
+            OR LOWER(request_name) LIKE LOWER($1)
+            OR LOWER(environment_name) LIKE LOWER($1)
+            OR CAST(status AS TEXT) LIKE $1
+        ORDER BY
+            created_at DESC, rowid DESC
+        LIMIT $2",
+    )
+    .bind(format!("%{}%", search_term.trim()))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Most recent executions of a request first.
+pub async fn get_request_history(
+    request_id: &str,
+    limit: i64,
+    pool: &SqlitePool,
+) -> Result<Vec<HistorySummaryData>, Box<dyn Error>> {
+    let entries = query_as(
+        "SELECT id, created_at, request_id, request_name, environment_name, http_method, url, status, status_text, error, total_ms FROM requesthistory WHERE request_id=$1 ORDER BY created_at DESC, rowid DESC LIMIT $2",
+    )
+    .bind(request_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
+
+pub async fn get_history_entry(
+    id: &str,
+    pool: &SqlitePool,
+) -> Result<HistoryEntryData, Box<dyn Error>> {
+    let row: HistoryRow = query_as(
+        "SELECT id, created_at, request_id, request_name, collection_id, environment_id, environment_name, http_method, url, request_headers, request_body, request_body_truncated, status, status_text, response_headers, response_body, response_body_truncated, response_size, error, headers_received_ms, total_ms FROM requesthistory WHERE id=$1",
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.into())
+}
+
+pub async fn delete_history_entry(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM requesthistory WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove all executions except the ones a comparison is kept for.
+pub async fn clear_history(pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query(&format!(
+        "DELETE FROM requesthistory WHERE {}",
+        NOT_COMPARED
+    ))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_history_settings(
+    pool: &SqlitePool,
+) -> Result<HistorySettingsData, Box<dyn Error>> {
+    let settings = query_as("SELECT max_entries, max_age_days FROM historysettings WHERE id=1")
+        .fetch_one(pool)
+        .await?;
+
+    Ok(settings)
+}
+
+/// Change the retention limits and apply them right away.
+pub async fn update_history_settings(
+    max_entries: i64,
+    max_age_days: i64,
+    pool: &SqlitePool,
+) -> Result<HistorySettingsData, Box<dyn Error>> {
+    let settings = query_as(
+        "INSERT INTO historysettings (id, max_entries, max_age_days) VALUES (1, $1, $2) ON CONFLICT(id) DO UPDATE SET max_entries=excluded.max_entries, max_age_days=excluded.max_age_days RETURNING max_entries, max_age_days",
+    )
+    .bind(max_entries.max(0))
+    .bind(max_age_days.max(0))
+    .fetch_one(pool)
+    .await?;
+
+    prune_history(pool).await?;
+
+    Ok(settings)
+}
+
+/// Remove executions older than the age limit or beyond the entry limit.
+///
+/// Executions that are part of a comparison are kept and don't count
+/// towards the entry limit, so comparisons never push out new executions.
+pub async fn prune_history(pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    let settings = get_history_settings(pool).await?;
+
+    if settings.max_age_days > 0 {
+        query(&format!(
+            "DELETE FROM requesthistory WHERE created_at < datetime('now', $1) AND {}",
+            NOT_COMPARED
+        ))
+        .bind(format!("-{} days", settings.max_age_days))
+        .execute(pool)
+        .await?;
+    }
+    if settings.max_entries > 0 {
+        query(&format!(
+            "DELETE FROM requesthistory WHERE id NOT IN (SELECT id FROM requesthistory WHERE {} ORDER BY created_at DESC, rowid DESC LIMIT $1) AND {}",
+            NOT_COMPARED, NOT_COMPARED
+        ))
+        .bind(settings.max_entries)
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Create a new request from what was sent for a history entry.
+///
+/// The request goes into the collection it was sent from, or into
+/// [`RESTORED_COLLECTION_NAME`] when that collection no longer exists.
+/// Headers are restored as request headers and text bodies as raw, JSON or
+/// XML bodies; binary and cut off bodies are left out.
+pub async fn restore_history_entry(
+    entry: &HistoryEntryData,
+    pool: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let collection_id = match &entry.collection_id {
+        Some(collection_id) => collection_id.clone(),
+        None => {
+            let existing = get_all_collections(pool)
+                .await?
+                .into_iter()
+                .find(|collection| collection.name == RESTORED_COLLECTION_NAME);
+            match existing {
+                Some(collection) => collection.id,
+                None => {
+                    create_collection(RESTORED_COLLECTION_NAME.to_string(), pool)
+                        .await?
+                        .id
+                }
+            }
+        }
+    };
+
+    let request = create_request(ProtocolTypes::Http, &collection_id, pool).await?;
+    let name = if entry.request_name.trim().is_empty() {
+        request.name.clone()
+    } else {
+        entry.request_name.clone()
+    };
+    let request = update_request_item(
+        &request.id,
+        &name,
+        ProtocolTypes::Http,
+        parse_method(&entry.http_method)?,
+        &entry.url,
+        pool,
+    )
+    .await?;
+    replace_request_params(
+        &request.id,
+        &merge_url_params(&[], parse_query(&entry.url)),
+        pool,
+    )
+    .await?;
+
+    let mut content_type = String::new();
+    for (name, value) in &entry.request_headers {
+        if name.eq_ignore_ascii_case("content-type") {
+            content_type = value.to_lowercase();
+        }
+        create_request_header(&request.id, name, value, pool).await?;
+    }
+
+    if !entry.request_body_truncated {
+        if let Ok(payload) = std::str::from_utf8(&entry.request_body) {
+            let mode = if payload.is_empty() {
+                BodyModes::None
+            } else if content_type.contains("json") {
+                BodyModes::Json
+            } else if content_type.contains("xml") {
+                BodyModes::Xml
+            } else {
+                BodyModes::Raw
+            };
+            // The content type is already one of the restored headers.
+            update_request_body(&request.id, mode, "", payload, pool).await?;
+        }
+    }
+
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            bodies::get_request_body,
+            collections::delete_collection,
+            comparisons::create_comparison,
+            params::get_request_params,
+            requests::{delete_request, get_request_headers},
+        },
+    };
+
+    fn entry_for(url: &str, request_id: Option<String>) -> NewHistoryEntry {
+        NewHistoryEntry {
+            request_id,
+            request_name: "New Request".to_string(),
+            http_method: "POST".to_string(),
+            url: url.to_string(),
+            request_headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+            request_body: b"ping".to_vec(),
+            status: Some(200),
+            status_text: "OK".to_string(),
+            response_headers: vec![("x-test".to_string(), "yes".to_string())],
+            response_body: b"pong".to_vec(),
+            response_size: 4,
+            total_ms: 12,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_history_entry() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let summary = create_history_entry(&entry_for("https://example.com/a", None), &db)
+            .await
+            .unwrap();
+        assert!(summary.status == Some(200));
+
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+        assert!(entry.http_method == "POST");
+        assert!(
+            entry.request_headers == vec![("Content-Type".to_string(), "text/plain".to_string())]
+        );
+        assert!(entry.request_body == b"ping");
+        assert!(entry.response_headers == vec![("x-test".to_string(), "yes".to_string())]);
+        assert!(entry.response_body == b"pong");
+        assert!(!entry.response_body_truncated);
+
+        let mut large = entry_for("https://example.com/b", None);
+        large.response_body = vec![b'a'; HISTORY_BODY_LIMIT + 10];
+        large.response_size = HISTORY_BODY_LIMIT + 10;
+        let summary = create_history_entry(&large, &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+        assert!(entry.response_body.len() == HISTORY_BODY_LIMIT);
+        assert!(entry.response_body_truncated);
+        assert!(entry.response_size == (HISTORY_BODY_LIMIT + 10) as i64);
+    }
+
+    #[tokio::test]
+    async fn test_history_survives_request_deletion() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+
+        let summary = create_history_entry(
+            &entry_for("https://example.com", Some(request.id.clone())),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(summary.request_id == Some(request.id.clone()));
+
+        create_history_entry(&entry_for("https://example.com", None), &db)
+            .await
+            .unwrap();
+        let entries = get_request_history(&request.id, 10, &db).await.unwrap();
+        assert!(entries.len() == 1);
+        assert!(entries[0].id == summary.id);
+
+        delete_request(&request.id, &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+        assert!(entry.request_id.is_none());
+        assert!(entry.request_name == "New Request");
+    }
+
+    #[tokio::test]
+    async fn test_search_history() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        create_history_entry(&entry_for("https://example.com/users", None), &db)
+            .await
+            .unwrap();
+        let mut failed = entry_for("https://example.com/orders", None);
+        failed.status = Some(404);
+        create_history_entry(&failed, &db).await.unwrap();
+
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 2);
+        // Most recent first.
+        assert!(entries[0].url == "https://example.com/orders");
+
+        let entries = search_history("USERS", 100, &db).await.unwrap();
+        assert!(entries.len() == 1);
+        let entries = search_history("404", 100, &db).await.unwrap();
+        assert!(entries.len() == 1);
+        assert!(entries[0].url == "https://example.com/orders");
+        assert!(search_history("", 1, &db).await.unwrap().len() == 1);
+
+        delete_history_entry(&entries[0].id, &db).await.unwrap();
+        assert!(search_history("", 100, &db).await.unwrap().len() == 1);
+        clear_history(&db).await.unwrap();
+        assert!(search_history("", 100, &db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_retention() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let settings = get_history_settings(&db).await.unwrap();
+        assert!(settings.max_entries == 1000);
+        assert!(settings.max_age_days == 30);
+
+        for index in 0..5 {
+            create_history_entry(
+                &entry_for(&format!("https://example.com/{}", index), None),
+                &db,
+            )
+            .await
+            .unwrap();
+        }
+        update_history_settings(3, 0, &db).await.unwrap();
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 3);
+        assert!(entries[0].url == "https://example.com/4");
+
+        // Entries older than the age limit go on the next write.
+        query("UPDATE requesthistory SET created_at = datetime('now', '-10 days') WHERE url = $1")
+            .bind("https://example.com/2")
+            .execute(&db)
+            .await
+            .unwrap();
+        let settings = update_history_settings(0, 7, &db).await.unwrap();
+        assert!(settings.max_entries == 0);
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 2);
+        assert!(!entries
+            .iter()
+            .any(|entry| entry.url == "https://example.com/2"));
+    }
+
+    #[tokio::test]
+    async fn test_history_retention_keeps_comparisons() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        update_history_settings(0, 0, &db).await.unwrap();
+        for index in 0..3 {
+            create_history_entry(
+                &entry_for(&format!("https://example.com/{}", index), None),
+                &db,
+            )
+            .await
+            .unwrap();
+        }
+        let left = create_history_entry(&entry_for("https://example.com/left", None), &db)
+            .await
+            .unwrap();
+        let right = create_history_entry(&entry_for("https://example.com/right", None), &db)
+            .await
+            .unwrap();
+        create_comparison("Before and after", &left.id, &right.id, &db)
+            .await
+            .unwrap();
+
+        // The compared entries don't take up room under the limit.
+        update_history_settings(3, 0, &db).await.unwrap();
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 5);
+
+        create_history_entry(&entry_for("https://example.com/3", None), &db)
+            .await
+            .unwrap();
+        let entries = search_history("", 100, &db).await.unwrap();
+        assert!(entries.len() == 5);
+        assert!(entries[0].url == "https://example.com/3");
+        assert!(!entries
+            .iter()
+            .any(|entry| entry.url == "https://example.com/0"));
+        assert!(entries.iter().any(|entry| entry.id == left.id));
+        assert!(entries.iter().any(|entry| entry.id == right.id));
+    }
+
+    #[tokio::test]
+    async fn test_restore_history_entry() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let mut entry = entry_for("https://example.com/users?page=2", None);
+        entry.collection_id = Some(collection.id.clone());
+        entry.request_headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        entry.request_body = b"{\"a\":1}".to_vec();
+        let summary = create_history_entry(&entry, &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+
+        let request = restore_history_entry(&entry, &db).await.unwrap();
+        assert!(request.collection_id == collection.id);
+        assert!(request.name == "New Request");
+        assert!(request.http_method == Some("POST".to_string()));
+        assert!(request.url == Some("https://example.com/users?page=2".to_string()));
+
+        let params = get_request_params(&request.id, &db).await.unwrap();
+        assert!(params.len() == 1);
+        assert!(params[0].name == "page" && params[0].value == "2");
+        let headers = get_request_headers(&request.id, &db).await.unwrap();
+        assert!(headers.len() == 1);
+        assert!(headers[0].name == "Content-Type");
+        let body = get_request_body(&request.id, &db).await.unwrap().unwrap();
+        assert!(body.mode == "json");
+        assert!(body.payload == "{\"a\":1}");
+
+        // Without its collection the entry is restored into a shared one.
+        delete_collection(&collection.id, &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+        assert!(entry.collection_id.is_none());
+        let first = restore_history_entry(&entry, &db).await.unwrap();
+        let second = restore_history_entry(&entry, &db).await.unwrap();
+        assert!(first.collection_id == second.collection_id);
+        let collections = get_all_collections(&db).await.unwrap();
+        assert!(collections.len() == 1);
+        assert!(collections[0].name == RESTORED_COLLECTION_NAME);
+        assert!(collections[0].requests_count == 2);
+    }
+}
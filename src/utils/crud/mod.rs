@@ -1,2 +1,15 @@
+pub mod auth;
+pub mod bodies;
 pub mod collections;
+pub mod comparisons;
+pub mod environments;
+pub mod graphql;
+pub mod grpc;
+pub mod history;
+pub mod mqtt;
+pub mod oauth2;
+pub mod params;
 pub mod requests;
+pub mod socketio;
+pub mod sse;
+pub mod websocket;
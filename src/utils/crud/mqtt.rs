@@ -0,0 +1,122 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+
+/// How an MQTT request talks to its broker. The credentials are the Basic
+/// auth of the request.
+#[derive(Clone, Debug, FromRow, PartialEq)]
+pub struct MqttConfigData {
+    /// A generated one is used when empty.
+    pub client_id: String,
+    pub clean_session: bool,
+    /// Seconds between pings, none when 0.
+    pub keep_alive: i64,
+    /// Topic filters to subscribe to, one per line.
+    pub filters: String,
+    pub subscribe_qos: i64,
+    /// The topic the next publish goes to.
+    pub topic: String,
+    pub payload: String,
+    pub publish_qos: i64,
+    pub retain: bool,
+}
+
+impl Default for MqttConfigData {
+    fn default() -> Self {
+        MqttConfigData {
+            client_id: String::new(),
+            clean_session: true,
+            keep_alive: 60,
+            filters: String::new(),
+            subscribe_qos: 0,
+            topic: String::new(),
+            payload: String::new(),
+            publish_qos: 0,
+            retain: false,
+        }
+    }
+}
+
+/// The settings of a request, the default ones when none were set yet.
+pub async fn get_mqtt_config<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<MqttConfigData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let config: Option<MqttConfigData> = query_as("SELECT client_id, clean_session, keep_alive, filters, subscribe_qos, topic, payload, publish_qos, retain FROM mqttconfig WHERE request_id=$1")
+        .bind(request_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    Ok(config.unwrap_or_default())
+}
+
+pub async fn update_mqtt_config<'a, A>(
+    request_id: &str,
+    config: &MqttConfigData,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO mqttconfig (request_id, client_id, clean_session, keep_alive, filters, subscribe_qos, topic, payload, publish_qos, retain) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT(request_id) DO UPDATE SET client_id=excluded.client_id, clean_session=excluded.clean_session, keep_alive=excluded.keep_alive, filters=excluded.filters, subscribe_qos=excluded.subscribe_qos, topic=excluded.topic, payload=excluded.payload, publish_qos=excluded.publish_qos, retain=excluded.retain")
+        .bind(request_id)
+        .bind(&config.client_id)
+        .bind(config.clean_session)
+        .bind(config.keep_alive)
+        .bind(&config.filters)
+        .bind(config.subscribe_qos)
+        .bind(&config.topic)
+        .bind(&config.payload)
+        .bind(config.publish_qos)
+        .bind(config.retain)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_mqtt_config() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Brokers".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Mqtt, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(get_mqtt_config(&request.id, &db).await.unwrap() == MqttConfigData::default());
+
+        let config = MqttConfigData {
+            client_id: "sensor-1".to_string(),
+            clean_session: false,
+            keep_alive: 30,
+            filters: "sensors/+/temperature\nalerts/#".to_string(),
+            subscribe_qos: 1,
+            topic: "sensors/kitchen/temperature".to_string(),
+            payload: "21.5".to_string(),
+            publish_qos: 2,
+            retain: true,
+        };
+        update_mqtt_config(&request.id, &config, &db).await.unwrap();
+        update_mqtt_config(&request.id, &config, &db).await.unwrap();
+        assert!(get_mqtt_config(&request.id, &db).await.unwrap() == config);
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_mqtt_config(&request.id, &db).await.unwrap() == MqttConfigData::default());
+    }
+}
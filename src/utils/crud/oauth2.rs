@@ -0,0 +1,329 @@
+use std::{error::Error, fmt};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum OAuth2GrantTypes {
+    ClientCredentials,
+    Password,
+    /// Authorization code with PKCE, redirected to a local listener.
+    AuthorizationCode,
+}
+
+impl fmt::Display for OAuth2GrantTypes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OAuth2GrantTypes::ClientCredentials => write!(f, "client_credentials"),
+            OAuth2GrantTypes::Password => write!(f, "password"),
+            OAuth2GrantTypes::AuthorizationCode => write!(f, "authorization_code"),
+        }
+    }
+}
+
+impl OAuth2GrantTypes {
+    pub fn from_string(s: &str) -> Option<OAuth2GrantTypes> {
+        match s {
+            "client_credentials" => Some(OAuth2GrantTypes::ClientCredentials),
+            "password" => Some(OAuth2GrantTypes::Password),
+            "authorization_code" => Some(OAuth2GrantTypes::AuthorizationCode),
+            _ => None,
+        }
+    }
+}
+
+/// OAuth 2.0 settings of an auth configuration, without the client secret.
+///
+/// An empty `redirect_uri` lets the authorization code flow listen on a
+/// free loopback port.
+#[derive(Clone, Debug, Default, FromRow)]
+pub struct OAuth2ConfigData {
+    pub auth_id: String,
+    pub grant_type: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub scope: String,
+    pub redirect_uri: String,
+}
+
+/// A token obtained for an auth configuration in an environment.
+#[derive(Clone, Debug, FromRow)]
+pub struct OAuth2TokenData {
+    pub id: String,
+    pub auth_id: String,
+    pub environment_id: Option<String>,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_at: Option<i64>,
+}
+
+pub async fn get_oauth2_config(
+    auth_id: &str,
+    pool: &SqlitePool,
+) -> Result<Option<OAuth2ConfigData>, Box<dyn Error>> {
+    let config = query_as(
+        "SELECT auth_id, grant_type, auth_url, token_url, client_id, scope, redirect_uri FROM oauth2config WHERE auth_id=$1",
+    )
+    .bind(auth_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(config)
+}
+
+/// Create or replace the OAuth 2.0 settings of an auth configuration.
+#[allow(clippy::too_many_arguments)]
+pub async fn update_oauth2_config<'a, A>(
+    auth_id: &str,
+    grant_type: OAuth2GrantTypes,
+    auth_url: &str,
+    token_url: &str,
+    client_id: &str,
+    scope: &str,
+    redirect_uri: &str,
+    conn: A,
+) -> Result<OAuth2ConfigData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "INSERT INTO oauth2config (auth_id, grant_type, auth_url, token_url, client_id, scope, redirect_uri) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT(auth_id) DO UPDATE SET grant_type=excluded.grant_type, auth_url=excluded.auth_url, token_url=excluded.token_url, client_id=excluded.client_id, scope=excluded.scope, redirect_uri=excluded.redirect_uri RETURNING auth_id, grant_type, auth_url, token_url, client_id, scope, redirect_uri";
+    let config = query_as(command)
+        .bind(auth_id)
+        .bind(grant_type.to_string())
+        .bind(auth_url)
+        .bind(token_url)
+        .bind(client_id)
+        .bind(scope)
+        .bind(redirect_uri)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(config)
+}
+
+/// Client secret of an auth configuration. Empty when none was saved.
+pub async fn get_oauth2_client_secret(
+    auth_id: &str,
+    pool: &SqlitePool,
+) -> Result<String, Box<dyn Error>> {
+    let secret: Option<(String,)> =
+        query_as("SELECT client_secret FROM authsecret WHERE auth_id=$1")
+            .bind(auth_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(secret.map(|(value,)| value).unwrap_or_default())
+}
+
+pub async fn update_oauth2_client_secret<'a, A>(
+    auth_id: &str,
+    client_secret: &str,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO authsecret (auth_id, client_secret) VALUES ($1, $2) ON CONFLICT(auth_id) DO UPDATE SET client_secret=excluded.client_secret")
+        .bind(auth_id)
+        .bind(client_secret)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Cached token of an auth configuration for an environment, `None` meaning no active environment.
+pub async fn get_oauth2_token(
+    auth_id: &str,
+    environment_id: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<Option<OAuth2TokenData>, Box<dyn Error>> {
+    let token = query_as(
+        "SELECT id, auth_id, environment_id, access_token, refresh_token, token_type, expires_at FROM oauth2token WHERE auth_id=$1 AND environment_id IS $2",
+    )
+    .bind(auth_id)
+    .bind(environment_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// Cache a token, replacing the one stored for the same auth configuration and environment.
+pub async fn save_oauth2_token(
+    auth_id: &str,
+    environment_id: Option<&str>,
+    access_token: &str,
+    refresh_token: &str,
+    token_type: &str,
+    expires_at: Option<i64>,
+    pool: &SqlitePool,
+) -> Result<OAuth2TokenData, Box<dyn Error>> {
+    let mut transaction = pool.begin().await?;
+
+    query("DELETE FROM oauth2token WHERE auth_id=$1 AND environment_id IS $2")
+        .bind(auth_id)
+        .bind(environment_id)
+        .execute(&mut *transaction)
+        .await?;
+    let token = query_as(
+        "INSERT INTO oauth2token (id, auth_id, environment_id, access_token, refresh_token, token_type, expires_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id, auth_id, environment_id, access_token, refresh_token, token_type, expires_at",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(auth_id)
+    .bind(environment_id)
+    .bind(access_token)
+    .bind(refresh_token)
+    .bind(token_type)
+    .bind(expires_at)
+    .fetch_one(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(token)
+}
+
+/// Forget every token cached for an auth configuration.
+pub async fn delete_oauth2_tokens(auth_id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM oauth2token WHERE auth_id=$1")
+        .bind(auth_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            auth::{
+                get_auth_secret, update_auth_secret, update_collection_auth, ApiKeyPlacement,
+                AuthTypes,
+            },
+            collections::create_collection,
+            environments::{create_environment, delete_environment},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_oauth2_config() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let auth = update_collection_auth(
+            &collection.id,
+            AuthTypes::OAuth2,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(get_oauth2_config(&auth.id, &db).await.unwrap().is_none());
+
+        update_oauth2_config(
+            &auth.id,
+            OAuth2GrantTypes::ClientCredentials,
+            "",
+            "https://example.com/token",
+            "client",
+            "read",
+            "",
+            &db,
+        )
+        .await
+        .unwrap();
+        let config = update_oauth2_config(
+            &auth.id,
+            OAuth2GrantTypes::Password,
+            "",
+            "https://example.com/token",
+            "client",
+            "read write",
+            "",
+            &db,
+        )
+        .await
+        .unwrap();
+        assert!(config.grant_type == "password");
+        assert!(config.scope == "read write");
+
+        // The client secret and the password share a row without overwriting each other.
+        update_oauth2_client_secret(&auth.id, "client-secret", &db)
+            .await
+            .unwrap();
+        update_auth_secret(&auth.id, "password", &db).await.unwrap();
+        assert!(get_oauth2_client_secret(&auth.id, &db).await.unwrap() == "client-secret");
+        assert!(get_auth_secret(&auth.id, &db).await.unwrap() == "password");
+    }
+
+    #[tokio::test]
+    async fn test_oauth2_tokens() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let auth = update_collection_auth(
+            &collection.id,
+            AuthTypes::OAuth2,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        let environment = create_environment("Local", &db).await.unwrap();
+
+        save_oauth2_token(&auth.id, None, "a", "", "Bearer", None, &db)
+            .await
+            .unwrap();
+        save_oauth2_token(&auth.id, None, "b", "r", "Bearer", Some(10), &db)
+            .await
+            .unwrap();
+        save_oauth2_token(
+            &auth.id,
+            Some(&environment.id),
+            "c",
+            "",
+            "Bearer",
+            None,
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let token = get_oauth2_token(&auth.id, None, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.access_token == "b");
+        assert!(token.expires_at == Some(10));
+        let token = get_oauth2_token(&auth.id, Some(&environment.id), &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(token.access_token == "c");
+
+        delete_environment(&environment.id, &db).await.unwrap();
+        assert!(get_oauth2_token(&auth.id, Some(&environment.id), &db)
+            .await
+            .unwrap()
+            .is_none());
+
+        delete_oauth2_tokens(&auth.id, &db).await.unwrap();
+        assert!(get_oauth2_token(&auth.id, None, &db)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,208 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, Acquire, Connection, FromRow, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+use crate::utils::query_params::ParamRow;
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RequestParamData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub request_id: String,
+}
+
+impl RequestParamData {
+    pub fn to_row(&self) -> ParamRow {
+        ParamRow {
+            name: self.name.clone(),
+            value: self.value.clone(),
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// Get all query params of a request, including disabled ones, in display order.
+pub async fn get_request_params<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Vec<RequestParamData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let params = query_as(
+        "SELECT id, name, value, enabled, position, request_id FROM requestparam WHERE request_id=$1 ORDER BY position, created_at",
+    )
+    .bind(request_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(params)
+}
+
+/// Add a query param at the end of a request's params table.
+pub async fn create_request_param(
+    request_id: &str,
+    name: &str,
+    value: &str,
+    pool: &SqlitePool,
+) -> Result<RequestParamData, Box<dyn Error>> {
+    let param = query_as(
+        "INSERT INTO requestparam (id, name, value, enabled, position, request_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM requestparam WHERE request_id=$4), $4) RETURNING id, name, value, enabled, position, request_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(request_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(param)
+}
+
+/// Update a query param.
+pub async fn update_request_param(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    pool: &SqlitePool,
+) -> Result<RequestParamData, Box<dyn Error>> {
+    let command = "UPDATE requestparam SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position, request_id";
+    let param = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(param)
+}
+
+pub async fn delete_request_param(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM requestparam WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Replace the whole params table of a request in one transaction.
+pub async fn replace_request_params<'a, A>(
+    request_id: &str,
+    rows: &[ParamRow],
+    conn: A,
+) -> Result<Vec<RequestParamData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let mut transaction = Connection::begin(&mut *conn).await?;
+
+    query("DELETE FROM requestparam WHERE request_id=$1")
+        .bind(request_id)
+        .execute(&mut *transaction)
+        .await?;
+
+    for (position, row) in rows.iter().enumerate() {
+        query("INSERT INTO requestparam (id, name, value, enabled, position, request_id) VALUES ($1, $2, $3, $4, $5, $6)")
+            .bind(Uuid::new_v4().to_string())
+            .bind(&row.name)
+            .bind(&row.value)
+            .bind(row.enabled)
+            .bind(position as i32)
+            .bind(request_id)
+            .execute(&mut *transaction)
+            .await?;
+    }
+    transaction.commit().await?;
+
+    get_request_params(request_id, &mut *conn).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_request_params() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+
+        let first = create_request_param(&request.id, "q", "rust", &db)
+            .await
+            .unwrap();
+        let second = create_request_param(&request.id, "page", "1", &db)
+            .await
+            .unwrap();
+        assert!(second.position == first.position + 1);
+
+        let updated = update_request_param(&second.id, "page", "2", false, &db)
+            .await
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.value == "2");
+
+        delete_request_param(&first.id, &db).await.unwrap();
+        let params = get_request_params(&request.id, &db).await.unwrap();
+        assert!(params.len() == 1);
+        assert!(params[0].name == "page");
+    }
+
+    #[tokio::test]
+    async fn test_replace_request_params() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db)
+            .await
+            .unwrap();
+        create_request_param(&request.id, "old", "1", &db)
+            .await
+            .unwrap();
+
+        let rows = vec![
+            ParamRow {
+                name: "a".to_string(),
+                value: "1".to_string(),
+                enabled: true,
+            },
+            ParamRow {
+                name: "debug".to_string(),
+                value: "true".to_string(),
+                enabled: false,
+            },
+        ];
+        let params = replace_request_params(&request.id, &rows, &db)
+            .await
+            .unwrap();
+
+        assert!(params.len() == 2);
+        assert!(
+            params
+                .iter()
+                .map(|param| param.to_row())
+                .collect::<Vec<_>>()
+                == rows
+        );
+    }
+}
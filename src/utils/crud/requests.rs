@@ -1,16 +1,19 @@
 use std::{error::Error, fmt};
 
-use sqlx::{query, query_as, FromRow, SqlitePool};
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
 use uuid::Uuid;
 
 use crate::utils::crud::collections::{get_single_collection, update_collection_item};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ProtocolTypes {
     Http,
     Websocket,
     Grpc,
     GraphQL,
+    Sse,
+    SocketIo,
+    Mqtt,
 }
 
 // Implement the Display trait
@@ -21,6 +24,9 @@ impl fmt::Display for ProtocolTypes {
             ProtocolTypes::Websocket => write!(f, "WS"),
             ProtocolTypes::Grpc => write!(f, "GRPC"),
             ProtocolTypes::GraphQL => write!(f, "GQL"),
+            ProtocolTypes::Sse => write!(f, "SSE"),
+            ProtocolTypes::SocketIo => write!(f, "SIO"),
+            ProtocolTypes::Mqtt => write!(f, "MQTT"),
         }
     }
 }
@@ -32,6 +38,9 @@ impl ProtocolTypes {
             "WS" => Some(ProtocolTypes::Websocket),
             "GRPC" => Some(ProtocolTypes::Grpc),
             "GQL" => Some(ProtocolTypes::GraphQL),
+            "SSE" => Some(ProtocolTypes::Sse),
+            "SIO" => Some(ProtocolTypes::SocketIo),
+            "MQTT" => Some(ProtocolTypes::Mqtt),
             _ => None,
         }
     }
@@ -43,6 +52,12 @@ pub enum HTTPMethods {
     Get,
     Put,
     Delete,
+    Patch,
+    Head,
+    Options,
+    Trace,
+    Connect,
+    Custom(String),
 }
 
 impl fmt::Display for HTTPMethods {
@@ -51,21 +66,72 @@ impl fmt::Display for HTTPMethods {
             HTTPMethods::Post => write!(f, "POST"),
             HTTPMethods::Get => write!(f, "GET"),
             HTTPMethods::Put => write!(f, "PUT"),
-            HTTPMethods::Delete => write!(f, "DEL"),
+            HTTPMethods::Delete => write!(f, "DELETE"),
+            HTTPMethods::Patch => write!(f, "PATCH"),
+            HTTPMethods::Head => write!(f, "HEAD"),
+            HTTPMethods::Options => write!(f, "OPTIONS"),
+            HTTPMethods::Trace => write!(f, "TRACE"),
+            HTTPMethods::Connect => write!(f, "CONNECT"),
+            HTTPMethods::Custom(method) => write!(f, "{}", method),
         }
     }
 }
 
 impl HTTPMethods {
+    /// Parse a stored method, ignoring case. Unknown verbs become `Custom`,
+    /// uppercased, as long as they are valid HTTP tokens; anything else is
+    /// rejected.
     pub fn from_string(s: &str) -> Option<HTTPMethods> {
-        match s {
+        let s = s.to_ascii_uppercase();
+        match s.as_str() {
             "POST" => Some(HTTPMethods::Post),
             "GET" => Some(HTTPMethods::Get),
             "PUT" => Some(HTTPMethods::Put),
-            "DEL" => Some(HTTPMethods::Delete),
+            // "DEL" is how older versions stored DELETE.
+            "DELETE" | "DEL" => Some(HTTPMethods::Delete),
+            "PATCH" => Some(HTTPMethods::Patch),
+            "HEAD" => Some(HTTPMethods::Head),
+            "OPTIONS" => Some(HTTPMethods::Options),
+            "TRACE" => Some(HTTPMethods::Trace),
+            "CONNECT" => Some(HTTPMethods::Connect),
+            _ if is_http_token(&s) => Some(HTTPMethods::Custom(s)),
             _ => None,
         }
     }
+
+    /// Methods offered in the method picker.
+    pub fn standard() -> Vec<HTTPMethods> {
+        vec![
+            HTTPMethods::Get,
+            HTTPMethods::Post,
+            HTTPMethods::Put,
+            HTTPMethods::Patch,
+            HTTPMethods::Delete,
+            HTTPMethods::Head,
+            HTTPMethods::Options,
+            HTTPMethods::Trace,
+            HTTPMethods::Connect,
+        ]
+    }
+}
+
+/// Parse a method typed in the UI or read back from storage. Invalid verbs
+/// are an error rather than being sent as something else.
+pub fn parse_method(http_method: &str) -> Result<HTTPMethods, Box<dyn Error>> {
+    HTTPMethods::from_string(http_method)
+        .ok_or_else(|| format!("Invalid HTTP method: {}", http_method).into())
+}
+
+/// The method of a stored request. Requests saved without one are GET.
+pub fn stored_method(http_method: Option<&str>) -> Result<HTTPMethods, Box<dyn Error>> {
+    http_method.map_or(Ok(HTTPMethods::Get), parse_method)
+}
+
+/// Check that a method name only uses characters allowed in an HTTP token.
+fn is_http_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
 }
 
 #[derive(Clone, Debug, FromRow)]
@@ -76,34 +142,40 @@ pub struct RequestData {
     pub protocol: String,
     pub collection_id: String,
     pub http_method: Option<String>,
+    /// Empty when the request is not in a folder.
+    pub folder: String,
 }
 
 pub async fn get_collection_requests(
     pool: &SqlitePool,
     collection_id: &str,
 ) -> Result<Vec<RequestData>, Box<dyn Error>> {
-    let requests = query_as("SELECT id, name, url, protocol, http_method, collection_id FROM requestitem WHERE collection_id=$1 ORDER BY created_at DESC").bind(collection_id).fetch_all(pool).await?;
+    let requests = query_as("SELECT id, name, url, protocol, http_method, collection_id, folder FROM requestitem WHERE collection_id=$1 ORDER BY created_at DESC, rowid DESC").bind(collection_id).fetch_all(pool).await?;
 
     Ok(requests)
 }
 
-pub async fn create_request(
+pub async fn create_request<'a, A>(
     protocol: ProtocolTypes,
     collection_id: &str,
-    pool: &SqlitePool,
-) -> Result<RequestData, Box<dyn Error>> {
+    conn: A,
+) -> Result<RequestData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
     let request = query_as(
-        "INSERT INTO requestitem (id, name, protocol, http_method, collection_id, url) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, name, url, protocol, http_method, collection_id"
-    ).bind(Uuid::new_v4().to_string()).bind("New Request").bind(protocol.to_string()).bind(HTTPMethods::Get.to_string()).bind(collection_id).bind("").fetch_one(pool).await?;
+        "INSERT INTO requestitem (id, name, protocol, http_method, collection_id, url) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id, name, url, protocol, http_method, collection_id, folder"
+    ).bind(Uuid::new_v4().to_string()).bind("New Request").bind(protocol.to_string()).bind(HTTPMethods::Get.to_string()).bind(collection_id).bind("").fetch_one(&mut *conn).await?;
 
-    let collection = get_single_collection(collection_id, pool).await?;
+    let collection = get_single_collection(collection_id, &mut *conn).await?;
     let requests_count = collection.requests_count + 1;
     update_collection_item(
         collection_id,
         &collection.name,
         &collection.icon,
         requests_count,
-        pool,
+        &mut *conn,
     )
     .await?;
     Ok(request)
@@ -131,42 +203,251 @@ pub async fn delete_request(request_id: &str, pool: &SqlitePool) -> Result<(), B
     Ok(())
 }
 
-pub async fn get_single_request(
-    id: &str,
-    pool: &SqlitePool,
-) -> Result<RequestData, Box<dyn Error>> {
+pub async fn get_single_request<'a, A>(id: &str, conn: A) -> Result<RequestData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
     let request = query_as(
-        "SELECT id, name, url, protocol, http_method, collection_id FROM requestitem WHERE id=$1",
+        "SELECT id, name, url, protocol, http_method, collection_id, folder FROM requestitem WHERE id=$1",
     )
     .bind(id)
-    .fetch_one(pool)
+    .fetch_one(&mut *conn)
     .await?;
 
     Ok(request)
 }
 
 /// Update a request item.
-pub async fn update_request_item(
+pub async fn update_request_item<'a, A>(
     id: &str,
     name: &str,
     protocol: ProtocolTypes,
     http_method: HTTPMethods,
     url: &str,
-    pool: &SqlitePool,
-) -> Result<RequestData, Box<dyn Error>> {
-    let command = "UPDATE requestitem SET name=$1, protocol=$2, http_method=$3, url=$4 WHERE id = $5 RETURNING id, name, url, protocol, http_method, collection_id";
+    conn: A,
+) -> Result<RequestData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE requestitem SET name=$1, protocol=$2, http_method=$3, url=$4 WHERE id = $5 RETURNING id, name, url, protocol, http_method, collection_id, folder";
     let request: RequestData = query_as(command)
         .bind(name)
         .bind(protocol.to_string())
         .bind(http_method.to_string())
         .bind(url)
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
 
     Ok(request)
 }
 
+/// Update only the URL of a request item.
+pub async fn update_request_url(
+    id: &str,
+    url: &str,
+    pool: &SqlitePool,
+) -> Result<RequestData, Box<dyn Error>> {
+    let command = "UPDATE requestitem SET url=$1 WHERE id = $2 RETURNING id, name, url, protocol, http_method, collection_id, folder";
+    let request: RequestData = query_as(command).bind(url).bind(id).fetch_one(pool).await?;
+
+    Ok(request)
+}
+
+/// Move a request into a folder, or out of any folder with an empty one.
+pub async fn update_request_folder<'a, A>(
+    id: &str,
+    folder: &str,
+    conn: A,
+) -> Result<RequestData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE requestitem SET folder=$1 WHERE id = $2 RETURNING id, name, url, protocol, http_method, collection_id, folder";
+    let request: RequestData = query_as(command)
+        .bind(folder)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(request)
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RequestHeaderData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub request_id: String,
+}
+
+/// Get all headers of a request, including disabled ones, in display order.
+pub async fn get_request_headers<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Vec<RequestHeaderData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let headers = query_as(
+        "SELECT id, name, value, enabled, position, request_id FROM requestheader WHERE request_id=$1 ORDER BY position, created_at",
+    )
+    .bind(request_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(headers)
+}
+
+/// Add a header at the end of a request's header list.
+pub async fn create_request_header<'a, A>(
+    request_id: &str,
+    name: &str,
+    value: &str,
+    conn: A,
+) -> Result<RequestHeaderData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let header = query_as(
+        "INSERT INTO requestheader (id, name, value, enabled, position, request_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM requestheader WHERE request_id=$4), $4) RETURNING id, name, value, enabled, position, request_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(request_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(header)
+}
+
+/// Update a request header.
+pub async fn update_request_header<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    conn: A,
+) -> Result<RequestHeaderData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE requestheader SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position, request_id";
+    let header = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(header)
+}
+
+pub async fn delete_request_header(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM requestheader WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Clone, Debug, FromRow)]
+pub struct RequestVariableData {
+    pub id: String,
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+    pub position: i32,
+    pub request_id: String,
+}
+
+/// Get all variables of a request, including disabled ones, in display order.
+pub async fn get_request_variables<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Vec<RequestVariableData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let variables = query_as(
+        "SELECT id, name, value, enabled, position, request_id FROM requestvariable WHERE request_id=$1 ORDER BY position, created_at",
+    )
+    .bind(request_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(variables)
+}
+
+/// Add a variable at the end of a request's variables table.
+pub async fn create_request_variable<'a, A>(
+    request_id: &str,
+    name: &str,
+    value: &str,
+    conn: A,
+) -> Result<RequestVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let variable = query_as(
+        "INSERT INTO requestvariable (id, name, value, enabled, position, request_id) VALUES ($1, $2, $3, 1, (SELECT COALESCE(MAX(position), -1) + 1 FROM requestvariable WHERE request_id=$4), $4) RETURNING id, name, value, enabled, position, request_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(value)
+    .bind(request_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(variable)
+}
+
+/// Update a request variable.
+pub async fn update_request_variable<'a, A>(
+    id: &str,
+    name: &str,
+    value: &str,
+    enabled: bool,
+    conn: A,
+) -> Result<RequestVariableData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let command = "UPDATE requestvariable SET name=$1, value=$2, enabled=$3 WHERE id = $4 RETURNING id, name, value, enabled, position, request_id";
+    let variable = query_as(command)
+        .bind(name)
+        .bind(value)
+        .bind(enabled)
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(variable)
+}
+
+pub async fn delete_request_variable(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM requestvariable WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -283,4 +564,153 @@ mod tests {
         assert!(updated_request.name == "Hello Request".to_string());
         assert!(updated_request.url == Some("https://bbc.co.uk".to_string()))
     }
+
+    #[tokio::test]
+    async fn test_update_request_url() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db.clone())
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db.clone())
+            .await
+            .unwrap();
+
+        let updated_request = update_request_url(&request.id, "https://bbc.co.uk?a=1", &db)
+            .await
+            .unwrap();
+
+        assert!(updated_request.name == request.name);
+        assert!(updated_request.http_method == request.http_method);
+        assert!(updated_request.url == Some("https://bbc.co.uk?a=1".to_string()));
+    }
+
+    #[test]
+    fn test_http_methods_round_trip() {
+        let mut methods = HTTPMethods::standard();
+        methods.push(HTTPMethods::Custom("PURGE".to_string()));
+
+        for method in methods {
+            assert!(HTTPMethods::from_string(&method.to_string()) == Some(method));
+        }
+    }
+
+    #[test]
+    fn test_http_methods_from_string() {
+        assert!(HTTPMethods::from_string("DEL") == Some(HTTPMethods::Delete));
+        assert!(
+            HTTPMethods::from_string("PROPFIND")
+                == Some(HTTPMethods::Custom("PROPFIND".to_string()))
+        );
+        assert!(HTTPMethods::from_string("patch") == Some(HTTPMethods::Patch));
+        assert!(
+            HTTPMethods::from_string("purge") == Some(HTTPMethods::Custom("PURGE".to_string()))
+        );
+        assert!(HTTPMethods::from_string("").is_none());
+        assert!(HTTPMethods::from_string("BAD VERB").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_request_custom_method() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db.clone())
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db.clone())
+            .await
+            .expect("Cant get collections");
+
+        let updated_request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::Http,
+            HTTPMethods::Custom("PURGE".to_string()),
+            "https://bbc.co.uk",
+            &db.clone(),
+        )
+        .await
+        .unwrap();
+
+        assert!(updated_request.http_method == Some("PURGE".to_string()));
+        let method = HTTPMethods::from_string(&updated_request.http_method.unwrap());
+        assert!(method == Some(HTTPMethods::Custom("PURGE".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_request_headers() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db.clone())
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db.clone())
+            .await
+            .unwrap();
+
+        let first = create_request_header(&request.id, "Accept", "text/plain", &db)
+            .await
+            .unwrap();
+        let second = create_request_header(&request.id, "Authorization", "Bearer 1", &db)
+            .await
+            .unwrap();
+        assert!(first.enabled);
+        assert!(second.position == first.position + 1);
+
+        let updated = update_request_header(&second.id, "Authorization", "Bearer 2", false, &db)
+            .await
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.value == "Bearer 2");
+
+        let headers = get_request_headers(&request.id, &db).await.unwrap();
+        assert!(headers.len() == 2);
+        assert!(headers[0].id == first.id);
+        assert!(headers[1].id == second.id);
+
+        delete_request_header(&first.id, &db).await.unwrap();
+        let headers = get_request_headers(&request.id, &db).await.unwrap();
+        assert!(headers.len() == 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_headers_deleted_with_request() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db.clone())
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db.clone())
+            .await
+            .unwrap();
+        create_request_header(&request.id, "Accept", "text/plain", &db)
+            .await
+            .unwrap();
+
+        delete_request(&request.id, &db).await.unwrap();
+        let headers = get_request_headers(&request.id, &db).await.unwrap();
+        assert!(headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Test collection".to_string(), &db.clone())
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, &db.clone())
+            .await
+            .unwrap();
+
+        let user = create_request_variable(&request.id, "userId", "1", &db)
+            .await
+            .unwrap();
+        create_request_variable(&request.id, "page", "2", &db)
+            .await
+            .unwrap();
+        update_request_variable(&user.id, "userId", "42", true, &db)
+            .await
+            .unwrap();
+        delete_request_variable(&user.id, &db).await.unwrap();
+
+        let variables = get_request_variables(&request.id, &db).await.unwrap();
+        assert!(variables.len() == 1);
+        assert!(variables[0].name == "page");
+    }
 }
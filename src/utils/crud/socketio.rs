@@ -0,0 +1,116 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+
+/// How a Socket.IO request talks to its server.
+#[derive(Clone, Debug, FromRow, PartialEq)]
+pub struct SocketIoConfigData {
+    /// Where the server mounts Socket.IO, the namespace is the URL path.
+    pub path: String,
+    /// Event names to log, one per line. All events are logged when empty.
+    pub listeners: String,
+    /// The event the next emit sends.
+    pub event: String,
+    /// The JSON argument of the event, none when empty.
+    pub payload: String,
+    /// Whether the emit asks the server for an acknowledgement.
+    pub ack: bool,
+}
+
+impl Default for SocketIoConfigData {
+    fn default() -> Self {
+        SocketIoConfigData {
+            path: "/socket.io/".to_string(),
+            listeners: String::new(),
+            event: String::new(),
+            payload: String::new(),
+            ack: false,
+        }
+    }
+}
+
+/// The settings of a request, the default ones when none were set yet.
+pub async fn get_socketio_config<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<SocketIoConfigData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let config: Option<SocketIoConfigData> = query_as(
+        "SELECT path, listeners, event, payload, ack FROM socketioconfig WHERE request_id=$1",
+    )
+    .bind(request_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(config.unwrap_or_default())
+}
+
+pub async fn update_socketio_config<'a, A>(
+    request_id: &str,
+    config: &SocketIoConfigData,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO socketioconfig (request_id, path, listeners, event, payload, ack) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT(request_id) DO UPDATE SET path=excluded.path, listeners=excluded.listeners, event=excluded.event, payload=excluded.payload, ack=excluded.ack")
+        .bind(request_id)
+        .bind(&config.path)
+        .bind(&config.listeners)
+        .bind(&config.event)
+        .bind(&config.payload)
+        .bind(config.ack)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_socketio_config() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Sockets".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::SocketIo, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(
+            get_socketio_config(&request.id, &db).await.unwrap() == SocketIoConfigData::default()
+        );
+
+        let config = SocketIoConfigData {
+            path: "/realtime/".to_string(),
+            listeners: "chat\nnotice".to_string(),
+            event: "chat".to_string(),
+            payload: r#"{"text":"hi"}"#.to_string(),
+            ack: true,
+        };
+        update_socketio_config(&request.id, &config, &db)
+            .await
+            .unwrap();
+        update_socketio_config(&request.id, &config, &db)
+            .await
+            .unwrap();
+        assert!(get_socketio_config(&request.id, &db).await.unwrap() == config);
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(
+            get_socketio_config(&request.id, &db).await.unwrap() == SocketIoConfigData::default()
+        );
+    }
+}
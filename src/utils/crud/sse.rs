@@ -0,0 +1,92 @@
+use std::error::Error;
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite};
+
+/// How an SSE request streams.
+#[derive(Clone, Debug, FromRow, PartialEq)]
+pub struct SseConfigData {
+    /// Sent as `Last-Event-ID`, empty to start from the beginning.
+    pub last_event_id: String,
+    /// Whether a stream the server ends is opened again.
+    pub reconnect: bool,
+}
+
+impl Default for SseConfigData {
+    fn default() -> Self {
+        SseConfigData {
+            last_event_id: String::new(),
+            reconnect: true,
+        }
+    }
+}
+
+/// The settings of a request, the default ones when none were set yet.
+pub async fn get_sse_config<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<SseConfigData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let config: Option<SseConfigData> =
+        query_as("SELECT last_event_id, reconnect FROM sseconfig WHERE request_id=$1")
+            .bind(request_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    Ok(config.unwrap_or_default())
+}
+
+pub async fn update_sse_config<'a, A>(
+    request_id: &str,
+    config: &SseConfigData,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO sseconfig (request_id, last_event_id, reconnect) VALUES ($1, $2, $3) ON CONFLICT(request_id) DO UPDATE SET last_event_id=excluded.last_event_id, reconnect=excluded.reconnect")
+        .bind(request_id)
+        .bind(&config.last_event_id)
+        .bind(config.reconnect)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_sse_config() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Streams".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Sse, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(get_sse_config(&request.id, &db).await.unwrap() == SseConfigData::default());
+
+        let config = SseConfigData {
+            last_event_id: "42".to_string(),
+            reconnect: false,
+        };
+        update_sse_config(&request.id, &config, &db).await.unwrap();
+        update_sse_config(&request.id, &config, &db).await.unwrap();
+        assert!(get_sse_config(&request.id, &db).await.unwrap() == config);
+
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_sse_config(&request.id, &db).await.unwrap() == SseConfigData::default());
+    }
+}
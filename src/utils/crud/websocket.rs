@@ -0,0 +1,207 @@
+use std::{error::Error, fmt};
+
+use sqlx::{query, query_as, Acquire, FromRow, Sqlite, SqlitePool};
+use uuid::Uuid;
+
+/// How the payload of an outgoing WebSocket message is written.
+#[derive(Debug, PartialEq, Clone)]
+pub enum MessageFormats {
+    Text,
+    /// Text that has to be valid JSON.
+    Json,
+    /// Bytes written as hex.
+    Binary,
+}
+
+impl fmt::Display for MessageFormats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MessageFormats::Text => write!(f, "text"),
+            MessageFormats::Json => write!(f, "json"),
+            MessageFormats::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl MessageFormats {
+    pub fn from_string(s: &str) -> Option<MessageFormats> {
+        match s {
+            "text" => Some(MessageFormats::Text),
+            "json" => Some(MessageFormats::Json),
+            "binary" => Some(MessageFormats::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// Subprotocols a WebSocket request offers, comma separated as typed.
+pub async fn get_websocket_subprotocols<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<String, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let subprotocols: Option<(String,)> =
+        query_as("SELECT subprotocols FROM websocketconfig WHERE request_id=$1")
+            .bind(request_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    Ok(subprotocols
+        .map(|(subprotocols,)| subprotocols)
+        .unwrap_or_default())
+}
+
+pub async fn update_websocket_subprotocols<'a, A>(
+    request_id: &str,
+    subprotocols: &str,
+    conn: A,
+) -> Result<(), Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    query("INSERT INTO websocketconfig (request_id, subprotocols) VALUES ($1, $2) ON CONFLICT(request_id) DO UPDATE SET subprotocols=excluded.subprotocols")
+        .bind(request_id)
+        .bind(subprotocols)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// A message saved with a WebSocket request.
+#[derive(Clone, Debug, FromRow)]
+pub struct WebSocketTemplateData {
+    pub id: String,
+    pub name: String,
+    pub format: String,
+    pub payload: String,
+    pub request_id: String,
+}
+
+/// Templates of a request, in the order they were saved.
+pub async fn get_websocket_templates<'a, A>(
+    request_id: &str,
+    conn: A,
+) -> Result<Vec<WebSocketTemplateData>, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let templates = query_as(
+        "SELECT id, name, format, payload, request_id FROM websockettemplate WHERE request_id=$1 ORDER BY created_at, rowid",
+    )
+    .bind(request_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(templates)
+}
+
+pub async fn create_websocket_template<'a, A>(
+    request_id: &str,
+    name: &str,
+    format: MessageFormats,
+    payload: &str,
+    conn: A,
+) -> Result<WebSocketTemplateData, Box<dyn Error>>
+where
+    A: Acquire<'a, Database = Sqlite>,
+{
+    let mut conn = conn.acquire().await?;
+    let template = query_as(
+        "INSERT INTO websockettemplate (id, name, format, payload, request_id) VALUES ($1, $2, $3, $4, $5) RETURNING id, name, format, payload, request_id",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(format.to_string())
+    .bind(payload)
+    .bind(request_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(template)
+}
+
+pub async fn delete_websocket_template(id: &str, pool: &SqlitePool) -> Result<(), Box<dyn Error>> {
+    query("DELETE FROM websockettemplate WHERE id=$1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            collections::create_collection,
+            requests::{create_request, delete_request, ProtocolTypes},
+        },
+    };
+
+    #[tokio::test]
+    async fn test_websocket_settings() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Sockets".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Websocket, &collection.id, &db)
+            .await
+            .unwrap();
+
+        assert!(get_websocket_subprotocols(&request.id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+        update_websocket_subprotocols(&request.id, "graphql-ws", &db)
+            .await
+            .unwrap();
+        update_websocket_subprotocols(&request.id, "graphql-ws, mqtt", &db)
+            .await
+            .unwrap();
+        assert!(get_websocket_subprotocols(&request.id, &db).await.unwrap() == "graphql-ws, mqtt");
+
+        let hello =
+            create_websocket_template(&request.id, "Hello", MessageFormats::Text, "hi", &db)
+                .await
+                .unwrap();
+        create_websocket_template(
+            &request.id,
+            "Subscribe",
+            MessageFormats::Json,
+            "{\"type\":\"subscribe\"}",
+            &db,
+        )
+        .await
+        .unwrap();
+        let templates = get_websocket_templates(&request.id, &db).await.unwrap();
+        assert!(templates.len() == 2);
+        assert!(templates[0].name == "Hello" && templates[0].payload == "hi");
+        assert!(templates[1].format == "json");
+
+        delete_websocket_template(&hello.id, &db).await.unwrap();
+        assert!(
+            get_websocket_templates(&request.id, &db)
+                .await
+                .unwrap()
+                .len()
+                == 1
+        );
+
+        // Settings and templates go away with the request.
+        delete_request(&request.id, &db).await.unwrap();
+        assert!(get_websocket_templates(&request.id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(get_websocket_subprotocols(&request.id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
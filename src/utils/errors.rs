@@ -0,0 +1,48 @@
+use std::error::Error;
+
+/// A message about an error and what caused it, as transport errors alone
+/// say little.
+pub fn error_chain(error: &(dyn Error + 'static)) -> String {
+    let mut text = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_text = cause.to_string();
+        if !text.contains(&cause_text) {
+            text.push_str(": ");
+            text.push_str(&cause_text);
+        }
+        source = cause.source();
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fmt, io};
+
+    #[derive(Debug)]
+    struct Wrapper(io::Error);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "transport error")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let refused = io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused");
+        assert!(error_chain(&Wrapper(refused)) == "transport error: connection refused");
+
+        // Causes already in the message aren't repeated.
+        let error = io::Error::other("connection refused");
+        assert!(error_chain(&error) == "connection refused");
+    }
+}
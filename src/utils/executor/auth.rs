@@ -0,0 +1,318 @@
+use std::error::Error;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use md5::Md5;
+use sha2::{Digest as _, Sha256};
+use sqlx::SqlitePool;
+
+use crate::utils::{
+    crud::{
+        auth::{
+            get_auth_secret, get_collection_auth, get_request_auth, ApiKeyPlacement, AuthData,
+            AuthTypes,
+        },
+        requests::RequestData,
+    },
+    variables::VariableResolver,
+};
+
+use super::oauth2::OAuth2Settings;
+
+/// Auth resolved for a request, with its secret.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreparedAuth {
+    None,
+    Basic {
+        username: String,
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+    ApiKey {
+        name: String,
+        value: String,
+        placement: ApiKeyPlacement,
+    },
+    /// Answered by the executor once the server sent its challenge.
+    Digest {
+        username: String,
+        password: String,
+    },
+    /// Sent with a token obtained, or taken from the cache, right before the request.
+    OAuth2(OAuth2Settings),
+}
+
+impl PreparedAuth {
+    /// Auth described by stored settings. OAuth 2.0 settings need more than
+    /// the auth row and are loaded by [`PreparedAuth::for_request`].
+    pub fn from_stored(auth: &AuthData, secret: String) -> PreparedAuth {
+        match AuthTypes::from_string(&auth.auth_type).unwrap_or(AuthTypes::None) {
+            AuthTypes::Inherit | AuthTypes::None | AuthTypes::OAuth2 => PreparedAuth::None,
+            AuthTypes::Basic => PreparedAuth::Basic {
+                username: auth.username.clone(),
+                password: secret,
+            },
+            AuthTypes::Bearer => PreparedAuth::Bearer { token: secret },
+            AuthTypes::ApiKey if auth.key_name.trim().is_empty() => PreparedAuth::None,
+            AuthTypes::ApiKey => PreparedAuth::ApiKey {
+                name: auth.key_name.trim().to_string(),
+                value: secret,
+                placement: ApiKeyPlacement::from_string(&auth.placement)
+                    .unwrap_or(ApiKeyPlacement::Header),
+            },
+            AuthTypes::Digest => PreparedAuth::Digest {
+                username: auth.username.clone(),
+                password: secret,
+            },
+        }
+    }
+
+    /// Auth of a request, or of its collection when the request inherits it.
+    ///
+    /// A request without saved auth settings inherits.
+    pub async fn for_request(
+        request: &RequestData,
+        pool: &SqlitePool,
+    ) -> Result<PreparedAuth, Box<dyn Error>> {
+        let auth = match get_request_auth(&request.id, pool).await? {
+            Some(auth) if auth.auth_type != AuthTypes::Inherit.to_string() => Some(auth),
+            _ => get_collection_auth(&request.collection_id, pool).await?,
+        };
+
+        match auth {
+            Some(auth) if auth.auth_type == AuthTypes::OAuth2.to_string() => {
+                let password = get_auth_secret(&auth.id, pool).await?;
+                Ok(PreparedAuth::OAuth2(
+                    OAuth2Settings::for_auth(&auth, password, pool).await?,
+                ))
+            }
+            Some(auth) => {
+                let secret = get_auth_secret(&auth.id, pool).await?;
+                Ok(PreparedAuth::from_stored(&auth, secret))
+            }
+            None => Ok(PreparedAuth::None),
+        }
+    }
+
+    /// Substitute `{{variable}}` placeholders in every field.
+    pub fn substitute_variables(
+        self,
+        variables: &VariableResolver,
+        unresolved: &mut Vec<String>,
+    ) -> PreparedAuth {
+        let mut substitute = |text: &str| variables.substitute(text, unresolved);
+
+        match self {
+            PreparedAuth::None => PreparedAuth::None,
+            PreparedAuth::Basic { username, password } => PreparedAuth::Basic {
+                username: substitute(&username),
+                password: substitute(&password),
+            },
+            PreparedAuth::Bearer { token } => PreparedAuth::Bearer {
+                token: substitute(&token),
+            },
+            PreparedAuth::ApiKey {
+                name,
+                value,
+                placement,
+            } => PreparedAuth::ApiKey {
+                name: substitute(&name),
+                value: substitute(&value),
+                placement,
+            },
+            PreparedAuth::Digest { username, password } => PreparedAuth::Digest {
+                username: substitute(&username),
+                password: substitute(&password),
+            },
+            PreparedAuth::OAuth2(settings) => {
+                PreparedAuth::OAuth2(settings.substitute_variables(variables, unresolved))
+            }
+        }
+    }
+}
+
+pub fn basic_authorization(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{}", username, password))
+    )
+}
+
+/// Parameters of a `WWW-Authenticate: Digest` challenge.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: Option<String>,
+    /// Quality of protection options offered by the server.
+    pub qop: Vec<String>,
+}
+
+/// Parse a Digest challenge. Returns `None` for other auth schemes.
+pub fn parse_digest_challenge(header: &str) -> Option<DigestChallenge> {
+    let header = header.trim();
+    if header.len() < 7 || !header[..7].eq_ignore_ascii_case("digest ") {
+        return None;
+    }
+
+    let mut challenge = DigestChallenge::default();
+    let mut rest = header[7..].trim_start();
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=')?;
+        let name = name.trim().to_ascii_lowercase();
+        let after_name = after_name.trim_start();
+
+        let (value, after_value) = if let Some(quoted) = after_name.strip_prefix('"') {
+            let end = quoted.find('"')?;
+            (&quoted[..end], &quoted[end + 1..])
+        } else {
+            match after_name.find(',') {
+                Some(end) => (&after_name[..end], &after_name[end..]),
+                None => (after_name, ""),
+            }
+        };
+
+        match name.as_str() {
+            "realm" => challenge.realm = value.to_string(),
+            "nonce" => challenge.nonce = value.to_string(),
+            "opaque" => challenge.opaque = Some(value.to_string()),
+            "algorithm" => challenge.algorithm = Some(value.trim().to_string()),
+            "qop" => {
+                challenge.qop = value
+                    .split(',')
+                    .map(|option| option.trim().to_string())
+                    .filter(|option| !option.is_empty())
+                    .collect()
+            }
+            _ => {}
+        }
+        rest = after_value
+            .trim_start()
+            .trim_start_matches(',')
+            .trim_start();
+    }
+
+    Some(challenge)
+}
+
+fn digest_hash(algorithm: &str, data: &str) -> String {
+    let bytes = if algorithm.to_ascii_uppercase().starts_with("SHA-256") {
+        Sha256::digest(data.as_bytes()).to_vec()
+    } else {
+        Md5::digest(data.as_bytes()).to_vec()
+    };
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Build the `Authorization` header answering a Digest challenge (RFC 7616).
+///
+/// `uri` is the path and query of the request, `nc` the number of requests
+/// sent with this nonce so far, including this one.
+pub fn digest_authorization(
+    username: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    challenge: &DigestChallenge,
+    cnonce: &str,
+    nc: u32,
+) -> String {
+    let algorithm = challenge
+        .algorithm
+        .clone()
+        .unwrap_or_else(|| "MD5".to_string());
+    let qop = challenge
+        .qop
+        .iter()
+        .find(|option| option.eq_ignore_ascii_case("auth"))
+        .cloned();
+    let nc = format!("{:08x}", nc);
+
+    let mut ha1 = digest_hash(
+        &algorithm,
+        &format!("{}:{}:{}", username, challenge.realm, password),
+    );
+    if algorithm.to_ascii_lowercase().ends_with("-sess") {
+        ha1 = digest_hash(
+            &algorithm,
+            &format!("{}:{}:{}", ha1, challenge.nonce, cnonce),
+        );
+    }
+    let ha2 = digest_hash(&algorithm, &format!("{}:{}", method, uri));
+    let response = match &qop {
+        Some(qop) => digest_hash(
+            &algorithm,
+            &format!(
+                "{}:{}:{}:{}:{}:{}",
+                ha1, challenge.nonce, nc, cnonce, qop, ha2
+            ),
+        ),
+        None => digest_hash(&algorithm, &format!("{}:{}:{}", ha1, challenge.nonce, ha2)),
+    };
+
+    let mut header = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+        username, challenge.realm, challenge.nonce, uri, algorithm, response
+    );
+    if let Some(qop) = qop {
+        header.push_str(&format!(", qop={}, nc={}, cnonce=\"{}\"", qop, nc, cnonce));
+    }
+    if let Some(opaque) = &challenge.opaque {
+        header.push_str(&format!(", opaque=\"{}\"", opaque));
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_authorization() {
+        assert!(
+            basic_authorization("Aladdin", "open sesame") == "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[test]
+    fn test_parse_digest_challenge() {
+        let challenge = parse_digest_challenge(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        )
+        .unwrap();
+
+        assert!(challenge.realm == "testrealm@host.com");
+        assert!(challenge.nonce == "dcd98b7102dd2f0e8b11d0f600bfb0c093");
+        assert!(challenge.qop == vec!["auth".to_string(), "auth-int".to_string()]);
+        assert!(challenge.opaque == Some("5ccc069c403ebaf9f0171e9517f40e41".to_string()));
+        assert!(challenge.algorithm.is_none());
+        assert!(parse_digest_challenge("Basic realm=\"x\"").is_none());
+    }
+
+    #[test]
+    fn test_digest_authorization() {
+        // Example from RFC 2617, section 3.5.
+        let challenge = DigestChallenge {
+            realm: "testrealm@host.com".to_string(),
+            nonce: "dcd98b7102dd2f0e8b11d0f600bfb0c093".to_string(),
+            opaque: Some("5ccc069c403ebaf9f0171e9517f40e41".to_string()),
+            algorithm: None,
+            qop: vec!["auth".to_string(), "auth-int".to_string()],
+        };
+        let header = digest_authorization(
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            &challenge,
+            "0a4f113b",
+            1,
+        );
+
+        assert!(header.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+        assert!(header.contains("nc=00000001"));
+        assert!(header.contains("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+    }
+}
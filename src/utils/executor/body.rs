@@ -0,0 +1,335 @@
+use std::{error::Error, path::Path};
+
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use crate::utils::{
+    crud::bodies::{BodyModes, BodyPartData, RequestBodyData},
+    variables::VariableResolver,
+};
+
+/// A field of a multipart body. For file fields the value is the file path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultipartField {
+    pub name: String,
+    pub value: String,
+    pub is_file: bool,
+}
+
+/// Request body resolved from the stored body and its form fields.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PreparedBody {
+    Empty,
+    Text(String),
+    Form(Vec<(String, String)>),
+    Multipart {
+        boundary: String,
+        fields: Vec<MultipartField>,
+    },
+    /// Path of a file sent as-is.
+    File(String),
+    /// Bytes recorded in the history, sent as they are.
+    Bytes(Vec<u8>),
+}
+
+impl PreparedBody {
+    /// Build the body from what is stored for a request.
+    ///
+    /// Returns the body together with the content type it should be sent
+    /// with, unless the request headers already set one.
+    pub fn from_stored(
+        body: Option<RequestBodyData>,
+        parts: Vec<BodyPartData>,
+    ) -> (PreparedBody, Option<String>) {
+        let body = match body {
+            Some(data) => data,
+            None => return (PreparedBody::Empty, None),
+        };
+        let mode = BodyModes::from_string(&body.mode).unwrap_or(BodyModes::None);
+        let content_type = if body.content_type.trim().is_empty() {
+            mode.default_content_type().map(|value| value.to_string())
+        } else {
+            Some(body.content_type.trim().to_string())
+        };
+        let parts: Vec<BodyPartData> = parts
+            .into_iter()
+            .filter(|part| part.enabled && !part.name.is_empty())
+            .collect();
+
+        match mode {
+            BodyModes::None => (PreparedBody::Empty, None),
+            BodyModes::Raw | BodyModes::Json | BodyModes::Xml => {
+                (PreparedBody::Text(body.payload), content_type)
+            }
+            BodyModes::FormUrlEncoded => {
+                let fields = parts
+                    .into_iter()
+                    .filter(|part| !part.is_file)
+                    .map(|part| (part.name, part.value))
+                    .collect();
+                (PreparedBody::Form(fields), content_type)
+            }
+            BodyModes::Multipart => {
+                let boundary = format!("----QuerryBoundary{}", Uuid::new_v4().simple());
+                let fields = parts
+                    .into_iter()
+                    .map(|part| MultipartField {
+                        name: part.name,
+                        value: part.value,
+                        is_file: part.is_file,
+                    })
+                    .collect();
+                let content_type =
+                    multipart_content_type(content_type.as_deref().unwrap_or_default(), &boundary);
+                (
+                    PreparedBody::Multipart { boundary, fields },
+                    Some(content_type),
+                )
+            }
+            BodyModes::Binary if body.payload.trim().is_empty() => (PreparedBody::Empty, None),
+            BodyModes::Binary => (
+                PreparedBody::File(body.payload.trim().to_string()),
+                content_type,
+            ),
+        }
+    }
+
+    /// Substitute `{{variable}}` placeholders in the text, fields and file paths.
+    pub fn substitute_variables(
+        self,
+        variables: &VariableResolver,
+        unresolved: &mut Vec<String>,
+    ) -> PreparedBody {
+        let mut substitute = |text: &str| variables.substitute(text, unresolved);
+
+        match self {
+            PreparedBody::Empty => PreparedBody::Empty,
+            PreparedBody::Text(text) => PreparedBody::Text(substitute(&text)),
+            PreparedBody::Form(fields) => PreparedBody::Form(
+                fields
+                    .iter()
+                    .map(|(name, value)| (substitute(name), substitute(value)))
+                    .collect(),
+            ),
+            PreparedBody::Multipart { boundary, fields } => PreparedBody::Multipart {
+                boundary,
+                fields: fields
+                    .iter()
+                    .map(|field| MultipartField {
+                        name: substitute(&field.name),
+                        value: substitute(&field.value),
+                        is_file: field.is_file,
+                    })
+                    .collect(),
+            },
+            PreparedBody::File(path) => PreparedBody::File(substitute(&path)),
+            PreparedBody::Bytes(bytes) => PreparedBody::Bytes(bytes),
+        }
+    }
+
+    /// Encode the body into the bytes sent on the wire, reading any files.
+    pub async fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        let bytes = match self {
+            PreparedBody::Empty => Vec::new(),
+            PreparedBody::Text(text) => text.as_bytes().to_vec(),
+            PreparedBody::Form(fields) => form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(fields)
+                .finish()
+                .into_bytes(),
+            PreparedBody::Multipart { boundary, fields } => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for field in fields {
+                    bytes.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+                    if field.is_file {
+                        let file_name = Path::new(&field.value)
+                            .file_name()
+                            .map(|name| name.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        bytes.extend_from_slice(
+                            format!(
+                                "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+                                escape_field_name(&field.name),
+                                escape_field_name(&file_name)
+                            )
+                            .as_bytes(),
+                        );
+                        bytes.extend_from_slice(&tokio::fs::read(&field.value).await?);
+                    } else {
+                        bytes.extend_from_slice(
+                            format!(
+                                "Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                                escape_field_name(&field.name)
+                            )
+                            .as_bytes(),
+                        );
+                        bytes.extend_from_slice(field.value.as_bytes());
+                    }
+                    bytes.extend_from_slice(b"\r\n");
+                }
+                bytes.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+                bytes
+            }
+            PreparedBody::File(path) => tokio::fs::read(path).await?,
+            PreparedBody::Bytes(bytes) => bytes.clone(),
+        };
+
+        Ok(bytes)
+    }
+}
+
+/// The content type a multipart body is sent with, carrying the boundary it
+/// was encoded with.
+///
+/// The boundary is generated when the body is built, so any boundary in
+/// `content_type` is replaced. Types that aren't multipart are ignored.
+pub fn multipart_content_type(content_type: &str, boundary: &str) -> String {
+    let mut params = content_type.split(';').map(str::trim);
+    let media_type = params.next().unwrap_or_default();
+    if !media_type.to_ascii_lowercase().starts_with("multipart/") {
+        return format!("multipart/form-data; boundary={}", boundary);
+    }
+
+    let mut parts = vec![media_type.to_string()];
+    parts.extend(
+        params
+            .filter(|param| {
+                let name = param.split('=').next().unwrap_or_default();
+                !param.is_empty() && !name.trim().eq_ignore_ascii_case("boundary")
+            })
+            .map(str::to_string),
+    );
+    parts.push(format!("boundary={}", boundary));
+    parts.join("; ")
+}
+
+/// Escape a multipart field or file name the way browsers do.
+fn escape_field_name(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored_body(mode: BodyModes, content_type: &str, payload: &str) -> Option<RequestBodyData> {
+        Some(RequestBodyData {
+            id: "body".to_string(),
+            mode: mode.to_string(),
+            content_type: content_type.to_string(),
+            payload: payload.to_string(),
+            request_id: "request".to_string(),
+        })
+    }
+
+    fn part(name: &str, value: &str, is_file: bool, enabled: bool) -> BodyPartData {
+        BodyPartData {
+            id: name.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            is_file,
+            enabled,
+            position: 0,
+            request_id: "request".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_body() {
+        let (body, content_type) =
+            PreparedBody::from_stored(stored_body(BodyModes::Json, "", "{\"a\": 1}"), Vec::new());
+
+        assert!(content_type == Some("application/json".to_string()));
+        assert!(body.to_bytes().await.unwrap() == b"{\"a\": 1}".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_raw_body_content_type() {
+        let (_, content_type) =
+            PreparedBody::from_stored(stored_body(BodyModes::Raw, "text/csv", "a,b"), Vec::new());
+        assert!(content_type == Some("text/csv".to_string()));
+
+        let (body, content_type) = PreparedBody::from_stored(None, Vec::new());
+        assert!(body == PreparedBody::Empty);
+        assert!(content_type.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_form_body() {
+        let parts = vec![
+            part("q", "rust lang", false, true),
+            part("skip", "me", false, false),
+            part("page", "2&3", false, true),
+        ];
+        let (body, content_type) =
+            PreparedBody::from_stored(stored_body(BodyModes::FormUrlEncoded, "", ""), parts);
+
+        assert!(content_type == Some("application/x-www-form-urlencoded".to_string()));
+        assert!(body.to_bytes().await.unwrap() == b"q=rust+lang&page=2%263".to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_multipart_body() {
+        let file_path = std::env::temp_dir().join("querry_multipart_test.txt");
+        tokio::fs::write(&file_path, "file contents").await.unwrap();
+
+        let parts = vec![
+            part("name", "querry", false, true),
+            part("upload", file_path.to_str().unwrap(), true, true),
+        ];
+        let (body, content_type) =
+            PreparedBody::from_stored(stored_body(BodyModes::Multipart, "", ""), parts);
+
+        let boundary = match &body {
+            PreparedBody::Multipart { boundary, .. } => boundary.clone(),
+            _ => panic!("Expected a multipart body"),
+        };
+        assert!(content_type == Some(format!("multipart/form-data; boundary={}", boundary)));
+
+        let encoded = String::from_utf8(body.to_bytes().await.unwrap()).unwrap();
+        assert!(encoded.contains("Content-Disposition: form-data; name=\"name\"\r\n\r\nquerry\r\n"));
+        assert!(encoded.contains("name=\"upload\"; filename=\"querry_multipart_test.txt\""));
+        assert!(encoded.contains("file contents"));
+        assert!(encoded.ends_with(&format!("--{}--\r\n", boundary)));
+    }
+
+    #[test]
+    fn test_multipart_content_type() {
+        assert!(
+            multipart_content_type("multipart/form-data", "b1")
+                == "multipart/form-data; boundary=b1"
+        );
+        assert!(
+            multipart_content_type("multipart/related; type=\"text/xml\"; boundary=x", "b1")
+                == "multipart/related; type=\"text/xml\"; boundary=b1"
+        );
+        assert!(
+            multipart_content_type("application/json", "b1") == "multipart/form-data; boundary=b1"
+        );
+
+        let (body, content_type) = PreparedBody::from_stored(
+            stored_body(BodyModes::Multipart, "multipart/mixed", ""),
+            Vec::new(),
+        );
+        let boundary = match &body {
+            PreparedBody::Multipart { boundary, .. } => boundary.clone(),
+            _ => panic!("Expected a multipart body"),
+        };
+        assert!(content_type == Some(format!("multipart/mixed; boundary={}", boundary)));
+    }
+
+    #[tokio::test]
+    async fn test_binary_body() {
+        let (body, _) =
+            PreparedBody::from_stored(stored_body(BodyModes::Binary, "", " "), Vec::new());
+        assert!(body == PreparedBody::Empty);
+
+        let (body, content_type) = PreparedBody::from_stored(
+            stored_body(BodyModes::Binary, "", "/tmp/image.png"),
+            Vec::new(),
+        );
+        assert!(body == PreparedBody::File("/tmp/image.png".to_string()));
+        assert!(content_type == Some("application/octet-stream".to_string()));
+    }
+}
@@ -0,0 +1,1377 @@
+use std::{
+    error::Error,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    Client, Method, Response, StatusCode,
+};
+use sqlx::SqlitePool;
+use url::Url;
+use uuid::Uuid;
+
+use crate::utils::{
+    crud::{
+        auth::ApiKeyPlacement,
+        bodies::{get_body_parts, get_request_body},
+        collections::get_collection_headers,
+        environments::get_active_environment,
+        graphql::get_graphql_query,
+        grpc::get_grpc_call,
+        history::{create_history_entry, HistoryEntryData, NewHistoryEntry},
+        requests::{get_request_headers, stored_method, HTTPMethods, ProtocolTypes, RequestData},
+    },
+    graphql::graphql_body,
+    query_params::append_query_param,
+    tokio_runtime::runtime,
+    variables::{UnresolvedVariables, VariableResolver},
+};
+
+pub mod auth;
+pub mod body;
+pub mod oauth2;
+
+use auth::{basic_authorization, digest_authorization, parse_digest_challenge, PreparedAuth};
+use body::{multipart_content_type, PreparedBody};
+use oauth2::{access_token, token_authorization};
+
+/// A request with everything stored for it gathered and ready to go on the wire.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedRequest {
+    pub method: HTTPMethods,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: PreparedBody,
+    pub auth: PreparedAuth,
+}
+
+impl PreparedRequest {
+    /// Value of the first header with the given name, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Add a header unless one with the same name is already set.
+    pub fn set_default_header(&mut self, name: &str, value: String) {
+        if self.header(name).is_none() {
+            self.headers.push((name.to_string(), value));
+        }
+    }
+
+    /// Substitute `{{variable}}` placeholders in the URL, headers, body and auth.
+    pub fn resolve_variables(
+        self,
+        variables: &VariableResolver,
+    ) -> Result<PreparedRequest, UnresolvedVariables> {
+        let mut unresolved = Vec::new();
+
+        let url = variables.substitute(&self.url, &mut unresolved);
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                (
+                    variables.substitute(name, &mut unresolved),
+                    variables.substitute(value, &mut unresolved),
+                )
+            })
+            .collect();
+        let body = self.body.substitute_variables(variables, &mut unresolved);
+        let auth = self.auth.substitute_variables(variables, &mut unresolved);
+
+        if !unresolved.is_empty() {
+            return Err(UnresolvedVariables(unresolved));
+        }
+        Ok(PreparedRequest {
+            method: self.method,
+            url,
+            headers,
+            body,
+            auth,
+        })
+    }
+
+    /// Turn the auth into the headers or query parameter it is sent as.
+    ///
+    /// Headers set explicitly on the request or collection win. Digest auth
+    /// is kept, as it can only be answered once the server sent a challenge,
+    /// and so is OAuth 2.0, as its token may have to be obtained first.
+    pub fn apply_auth(mut self) -> PreparedRequest {
+        match std::mem::replace(&mut self.auth, PreparedAuth::None) {
+            PreparedAuth::None => {}
+            PreparedAuth::Basic { username, password } => {
+                self.set_default_header("Authorization", basic_authorization(&username, &password))
+            }
+            PreparedAuth::Bearer { token } => {
+                self.set_default_header("Authorization", format!("Bearer {}", token))
+            }
+            PreparedAuth::ApiKey {
+                name,
+                value,
+                placement: ApiKeyPlacement::Header,
+            } => self.set_default_header(&name, value),
+            PreparedAuth::ApiKey {
+                name,
+                value,
+                placement: ApiKeyPlacement::Query,
+            } => self.url = append_query_param(&self.url, &name, &value),
+            auth @ (PreparedAuth::Digest { .. } | PreparedAuth::OAuth2(_)) => {
+                if self.header("authorization").is_none() {
+                    self.auth = auth;
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Time spent on the different stages of a request.
+#[derive(Clone, Debug, Default)]
+pub struct ResponseTimings {
+    /// Time until the status line and headers were received.
+    pub headers_received: Duration,
+    /// Time until the whole body was read.
+    pub total: Duration,
+}
+
+/// Everything a server sent back for a single request.
+#[derive(Clone, Debug)]
+pub struct ResponseData {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub size: usize,
+    pub timings: ResponseTimings,
+}
+
+impl ResponseData {
+    /// Value of the first header with the given name, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Body decoded as UTF-8, with invalid sequences replaced.
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+fn http_client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(Client::new)
+}
+
+fn to_reqwest_method(method: &HTTPMethods) -> Result<Method, Box<dyn Error + Send + Sync>> {
+    let method = match method {
+        HTTPMethods::Post => Method::POST,
+        HTTPMethods::Get => Method::GET,
+        HTTPMethods::Put => Method::PUT,
+        HTTPMethods::Delete => Method::DELETE,
+        HTTPMethods::Patch => Method::PATCH,
+        HTTPMethods::Head => Method::HEAD,
+        HTTPMethods::Options => Method::OPTIONS,
+        HTTPMethods::Trace => Method::TRACE,
+        HTTPMethods::Connect => Method::CONNECT,
+        HTTPMethods::Custom(method) => Method::from_bytes(method.as_bytes())?,
+    };
+    Ok(method)
+}
+
+/// Combine collection-level and request-level headers.
+///
+/// A request header replaces every collection header with the same name,
+/// compared case-insensitively.
+pub fn merge_headers(
+    collection_headers: Vec<(String, String)>,
+    request_headers: Vec<(String, String)>,
+) -> Vec<(String, String)> {
+    let mut headers: Vec<(String, String)> = collection_headers
+        .into_iter()
+        .filter(|(name, _)| {
+            !request_headers
+                .iter()
+                .any(|(request_name, _)| request_name.eq_ignore_ascii_case(name))
+        })
+        .collect();
+    headers.extend(request_headers);
+    headers
+}
+
+/// Gather everything stored for a request, with `{{variable}}` placeholders left as they are.
+///
+/// Disabled and nameless headers are left out, and headers set on the
+/// collection are inherited unless the request overrides them. The body's
+/// content type is added unless one of those headers already sets it. Auth
+/// is inherited from the collection the same way. GraphQL requests post
+/// their operation as the body, and gRPC requests carry their message in it.
+async fn gather_request(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    let url = request.url.clone().unwrap_or_default();
+    let protocol = ProtocolTypes::from_string(&request.protocol);
+    let is_graphql = protocol == Some(ProtocolTypes::GraphQL);
+    // GraphQL operations are always posted, whatever method the request had before.
+    let method = match is_graphql {
+        true => HTTPMethods::Post,
+        false => stored_method(request.http_method.as_deref())?,
+    };
+
+    let collection_headers = get_collection_headers(&request.collection_id, pool)
+        .await?
+        .into_iter()
+        .filter(|header| header.enabled && !header.name.trim().is_empty())
+        .map(|header| (header.name.trim().to_string(), header.value))
+        .collect();
+    let request_headers = get_request_headers(&request.id, pool)
+        .await?
+        .into_iter()
+        .filter(|header| header.enabled && !header.name.trim().is_empty())
+        .map(|header| (header.name.trim().to_string(), header.value))
+        .collect();
+    let headers = merge_headers(collection_headers, request_headers);
+
+    let (body, content_type) = match protocol {
+        Some(ProtocolTypes::GraphQL) => {
+            let operation = get_graphql_query(&request.id, pool).await?;
+            (
+                PreparedBody::Text(graphql_body(
+                    &operation.query,
+                    &operation.variables,
+                    &operation.operation_name,
+                )),
+                Some("application/json".to_string()),
+            )
+        }
+        // The call sets its own content type.
+        Some(ProtocolTypes::Grpc) => (
+            PreparedBody::Text(get_grpc_call(&request.id, pool).await?.message),
+            None,
+        ),
+        _ => PreparedBody::from_stored(
+            get_request_body(&request.id, pool).await?,
+            get_body_parts(&request.id, pool).await?,
+        ),
+    };
+
+    let mut prepared = PreparedRequest {
+        method,
+        url: url.trim().to_string(),
+        headers,
+        body,
+        auth: PreparedAuth::for_request(request, pool).await?,
+    };
+    if let Some(content_type) = content_type {
+        prepared.set_default_header("Content-Type", content_type);
+    }
+    // A Content-Type set by the user still has to carry the generated boundary.
+    if let PreparedBody::Multipart { boundary, .. } = &prepared.body {
+        for (name, value) in prepared.headers.iter_mut() {
+            if name.eq_ignore_ascii_case("Content-Type") {
+                *value = multipart_content_type(value, boundary);
+            }
+        }
+    }
+
+    Ok(prepared)
+}
+
+/// Gather everything stored for a request, with its variables substituted
+/// but its auth not applied yet.
+///
+/// Variables are substituted as resolved by [`VariableResolver`], and a
+/// request that still references unknown variables is rejected instead of
+/// being sent with the placeholders in it.
+pub async fn resolve_request(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    if request.url.as_deref().unwrap_or_default().trim().is_empty() {
+        return Err("Request has no URL".into());
+    }
+
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let resolved = gather_request(request, pool)
+        .await?
+        .resolve_variables(&variables)?;
+
+    Ok(resolved)
+}
+
+/// Gather everything stored for a request into what will be sent.
+///
+/// See [`resolve_request`]. Auth is applied as far as it can be before
+/// sending, see [`PreparedRequest::apply_auth`].
+pub async fn prepare_request(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    Ok(resolve_request(request, pool).await?.apply_auth())
+}
+
+/// The current auth of a stored request, with its variables substituted.
+pub async fn resolve_auth(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedAuth, Box<dyn Error>> {
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let mut unresolved = Vec::new();
+    let auth = PreparedAuth::for_request(request, pool)
+        .await?
+        .substitute_variables(&variables, &mut unresolved);
+    if !unresolved.is_empty() {
+        return Err(UnresolvedVariables(unresolved).into());
+    }
+
+    Ok(auth)
+}
+
+/// Names of the variables a request references that have no value.
+pub async fn unresolved_request_variables(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let unresolved = match gather_request(request, pool)
+        .await?
+        .resolve_variables(&variables)
+    {
+        Ok(_) => Vec::new(),
+        Err(UnresolvedVariables(names)) => names,
+    };
+
+    Ok(unresolved)
+}
+
+async fn send_once(
+    request: &PreparedRequest,
+    method: &Method,
+    body: Option<Vec<u8>>,
+    authorization: Option<String>,
+) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    let mut builder = http_client().request(method.clone(), request.url.as_str());
+    for (name, value) in &request.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    if let Some(authorization) = authorization {
+        builder = builder.header(AUTHORIZATION, authorization);
+    }
+    if let Some(body) = body {
+        builder = builder.body(body);
+    }
+
+    Ok(builder.send().await?)
+}
+
+/// Header value answering the Digest challenge of a `401` response, if it has one.
+fn answer_digest_challenge(
+    request: &PreparedRequest,
+    method: &Method,
+    response: &Response,
+) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+    let (username, password) = match &request.auth {
+        PreparedAuth::Digest { username, password } => (username, password),
+        _ => return Ok(None),
+    };
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(None);
+    }
+    let challenge = match response
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(parse_digest_challenge)
+    {
+        Some(challenge) => challenge,
+        None => return Ok(None),
+    };
+
+    let url = Url::parse(&request.url)?;
+    let uri = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    Ok(Some(digest_authorization(
+        username,
+        password,
+        method.as_str(),
+        &uri,
+        &challenge,
+        &Uuid::new_v4().simple().to_string(),
+        1,
+    )))
+}
+
+/// Send a prepared request and collect the response.
+///
+/// With Digest auth the request is sent again with the answer to the
+/// server's challenge, and the timings cover both round trips.
+pub async fn execute_request(
+    request: &PreparedRequest,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let body = request.body.to_bytes().await?;
+    execute_with_body(request, body).await
+}
+
+/// Send a prepared request with its body already encoded, up to the point
+/// where the status and headers are in. The body is left to be read.
+pub async fn open_response(
+    request: &PreparedRequest,
+    body: Vec<u8>,
+) -> Result<Response, Box<dyn Error + Send + Sync>> {
+    let method = to_reqwest_method(&request.method)?;
+    let body = if request.body != PreparedBody::Empty {
+        Some(body)
+    } else {
+        None
+    };
+
+    let mut response = send_once(request, &method, body.clone(), None).await?;
+    if let Some(authorization) = answer_digest_challenge(request, &method, &response)? {
+        response = send_once(request, &method, body, Some(authorization)).await?;
+    }
+
+    Ok(response)
+}
+
+/// Headers of a response, with values that aren't UTF-8 decoded lossily.
+pub fn response_headers(response: &Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Send a prepared request with its body already encoded.
+async fn execute_with_body(
+    request: &PreparedRequest,
+    body: Vec<u8>,
+) -> Result<ResponseData, Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let response = open_response(request, body).await?;
+    let headers_received = started.elapsed();
+
+    let status = response.status();
+    let headers = response_headers(&response);
+    let body = response.bytes().await?.to_vec();
+    let total = started.elapsed();
+
+    Ok(ResponseData {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        headers,
+        size: body.len(),
+        body,
+        timings: ResponseTimings {
+            headers_received,
+            total,
+        },
+    })
+}
+
+/// Send an OAuth 2.0 token with the request, obtaining or refreshing it when needed.
+pub async fn apply_oauth2_token(
+    mut request: PreparedRequest,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    if let PreparedAuth::OAuth2(settings) = &request.auth {
+        let token = access_token(settings, pool).await?;
+        request.auth = PreparedAuth::None;
+        request.set_default_header("Authorization", token_authorization(&token));
+    }
+
+    Ok(request)
+}
+
+/// Apply the auth of a request, obtaining an OAuth 2.0 token when needed.
+pub async fn authenticate(
+    request: PreparedRequest,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    apply_oauth2_token(request.apply_auth(), pool).await
+}
+
+/// The stored request an execution is recorded under in the history.
+#[derive(Clone, Debug, Default)]
+pub struct HistoryOrigin {
+    pub request_id: Option<String>,
+    pub request_name: String,
+    pub collection_id: Option<String>,
+}
+
+impl HistoryOrigin {
+    pub fn for_request(request: &RequestData) -> HistoryOrigin {
+        HistoryOrigin {
+            request_id: Some(request.id.clone()),
+            request_name: request.name.clone(),
+            collection_id: Some(request.collection_id.clone()),
+        }
+    }
+}
+
+impl PreparedRequest {
+    /// Rebuild the request as it was sent for a history entry.
+    ///
+    /// Variables were substituted before the entry was recorded, but auth
+    /// was not recorded, so it has to be set again before sending. Entries
+    /// whose body was cut off can't be sent again.
+    pub fn from_history(entry: &HistoryEntryData) -> Result<PreparedRequest, Box<dyn Error>> {
+        if entry.request_body_truncated {
+            return Err("The request body was too large to be kept in the history".into());
+        }
+        let method = HTTPMethods::from_string(&entry.http_method)
+            .ok_or_else(|| format!("Invalid HTTP method: {}", entry.http_method))?;
+        let body = if entry.request_body.is_empty() {
+            PreparedBody::Empty
+        } else {
+            PreparedBody::Bytes(entry.request_body.clone())
+        };
+
+        Ok(PreparedRequest {
+            method,
+            url: entry.url.clone(),
+            headers: entry.request_headers.clone(),
+            body,
+            auth: PreparedAuth::None,
+        })
+    }
+}
+
+/// The outcome of an execution and the history entry it was recorded as.
+pub struct RecordedExecution {
+    /// `None` when the history couldn't be written.
+    pub history_id: Option<String>,
+    pub response: Result<ResponseData, Box<dyn Error>>,
+}
+
+/// Execute a resolved request on the shared tokio runtime and record it in the history.
+///
+/// The request is recorded before its auth is applied, so credentials never
+/// end up in the history. Slint callbacks run on the UI thread, so the
+/// network work is handed to the runtime and only the result is awaited
+/// here. Requests that fail before or while reaching the server are
+/// recorded too; failing to write the history is reported without failing
+/// the request.
+pub async fn execute_and_record(
+    request: PreparedRequest,
+    origin: HistoryOrigin,
+    pool: &SqlitePool,
+) -> Result<RecordedExecution, Box<dyn Error>> {
+    let environment = get_active_environment(pool).await?;
+    let http_method = request.method.to_string();
+    let url = request.url.clone();
+    let headers = request.headers.clone();
+    // Failing to get a token or to build the body still counts as an execution.
+    let sent = match authenticate(request, pool).await {
+        Ok(request) => runtime()
+            .spawn(async move {
+                let body = request.body.to_bytes().await?;
+                let response = execute_with_body(&request, body.clone()).await;
+                Ok::<_, Box<dyn Error + Send + Sync>>((body, response))
+            })
+            .await
+            .map_err(|error| error.to_string())
+            .and_then(|sent| sent.map_err(|error| error.to_string())),
+        Err(error) => Err(error.to_string()),
+    };
+    let (body, response) = match sent {
+        Ok((body, response)) => (body, response.map_err(|error| error.to_string())),
+        Err(error) => (Vec::new(), Err(error)),
+    };
+
+    let mut entry = NewHistoryEntry {
+        request_id: origin.request_id,
+        request_name: origin.request_name,
+        collection_id: origin.collection_id,
+        environment_id: environment
+            .as_ref()
+            .map(|environment| environment.id.clone()),
+        environment_name: environment
+            .map(|environment| environment.name)
+            .unwrap_or_default(),
+        http_method,
+        url,
+        request_headers: headers,
+        request_body: body,
+        ..Default::default()
+    };
+    match &response {
+        Ok(response) => {
+            entry.status = Some(response.status);
+            entry.status_text = response.status_text.clone();
+            entry.response_headers = response.headers.clone();
+            entry.response_body = response.body.clone();
+            entry.response_size = response.size;
+            entry.headers_received_ms = response.timings.headers_received.as_millis() as i64;
+            entry.total_ms = response.timings.total.as_millis() as i64;
+        }
+        Err(error) => entry.error = error.clone(),
+    }
+    let history_id = match create_history_entry(&entry, pool).await {
+        Ok(summary) => Some(summary.id),
+        Err(error) => {
+            eprintln!("Error saving history  - {}", error);
+            None
+        }
+    };
+
+    Ok(RecordedExecution {
+        history_id,
+        response: response.map_err(Into::into),
+    })
+}
+
+/// Prepare and execute a stored request, recording it in the history.
+pub async fn send_request(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<ResponseData, Box<dyn Error>> {
+    let resolved = resolve_request(request, pool).await?;
+
+    execute_and_record(resolved, HistoryOrigin::for_request(request), pool)
+        .await?
+        .response
+}
+
+/// Send a stored request to another URL, recording it in the history like
+/// any other execution. The URL may use variables just like the stored one.
+pub async fn send_request_to(
+    request: &RequestData,
+    url: &str,
+    pool: &SqlitePool,
+) -> Result<RecordedExecution, Box<dyn Error>> {
+    let mut target = request.clone();
+    target.url = Some(url.to_string());
+    let resolved = resolve_request(&target, pool).await?;
+
+    execute_and_record(resolved, HistoryOrigin::for_request(request), pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                auth::{
+                    update_auth_secret, update_collection_auth, update_request_auth, AuthTypes,
+                },
+                bodies::{create_body_part, update_request_body, BodyModes},
+                collections::{
+                    create_collection, create_collection_header, create_collection_variable,
+                },
+                environments::{
+                    create_environment, create_environment_variable, create_global_variable,
+                    set_active_environment,
+                },
+                history::{get_history_entry, search_history},
+                oauth2::{update_oauth2_client_secret, update_oauth2_config, OAuth2GrantTypes},
+                requests::{
+                    create_request, create_request_header, create_request_variable,
+                    update_request_header, update_request_item, ProtocolTypes,
+                },
+            },
+            test_server::{spawn_test_server, spawn_test_server_sequence},
+        },
+    };
+
+    fn request_for(url: &str, method: HTTPMethods) -> PreparedRequest {
+        PreparedRequest {
+            method,
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: PreparedBody::Empty,
+            auth: PreparedAuth::None,
+        }
+    }
+
+    async fn stored_request(url: &str, pool: &SqlitePool) -> RequestData {
+        let collection = create_collection("Test collection".to_string(), pool)
+            .await
+            .unwrap();
+        let request = create_request(ProtocolTypes::Http, &collection.id, pool)
+            .await
+            .unwrap();
+        update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::Http,
+            HTTPMethods::Post,
+            url,
+            pool,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_execute_request() {
+        let (url, received) = spawn_test_server(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nX-Test: yes\r\nContent-Length: 5\r\n\r\nhello",
+        )
+        .await;
+
+        let response = execute_request(&request_for(&url, HTTPMethods::Get))
+            .await
+            .unwrap();
+        let raw_request = received.await.unwrap();
+
+        assert!(raw_request.starts_with("GET / HTTP/1.1"));
+        assert!(response.status == 200);
+        assert!(response.status_text == "OK");
+        assert!(response.body_text() == "hello");
+        assert!(response.size == 5);
+        assert!(response
+            .headers
+            .iter()
+            .any(|(name, value)| name == "x-test" && value == "yes"));
+        assert!(response.timings.total >= response.timings.headers_received);
+    }
+
+    #[tokio::test]
+    async fn test_execute_request_method() {
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").await;
+
+        let response = execute_request(&request_for(&url, HTTPMethods::Post))
+            .await
+            .unwrap();
+        let raw_request = received.await.unwrap();
+
+        assert!(raw_request.starts_with("POST / HTTP/1.1"));
+        assert!(response.status == 404);
+        assert!(response.status_text == "Not Found");
+        assert!(response.body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_request_custom_method() {
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+
+        let method = HTTPMethods::Custom("PURGE".to_string());
+        let response = execute_request(&request_for(&url, method)).await.unwrap();
+        let raw_request = received.await.unwrap();
+
+        assert!(raw_request.starts_with("PURGE / HTTP/1.1"));
+        assert!(response.status == 200);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_without_url() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("", &db).await;
+
+        let prepared = prepare_request(&request, &db).await;
+        assert!(prepared.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_skips_disabled_headers() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("https://example.com/", &db).await;
+
+        create_request_header(&request.id, "Accept", "text/plain", &db)
+            .await
+            .unwrap();
+        let auth = create_request_header(&request.id, "Authorization", "Bearer 1", &db)
+            .await
+            .unwrap();
+        update_request_header(&auth.id, "Authorization", "Bearer 1", false, &db)
+            .await
+            .unwrap();
+        create_request_header(&request.id, "", "ignored", &db)
+            .await
+            .unwrap();
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.method == HTTPMethods::Post);
+        assert!(prepared.url == "https://example.com/");
+        assert!(prepared.headers == vec![("Accept".to_string(), "text/plain".to_string())]);
+    }
+
+    #[test]
+    fn test_merge_headers() {
+        let merged = merge_headers(
+            vec![
+                ("Authorization".to_string(), "Bearer collection".to_string()),
+                ("X-Tenant".to_string(), "acme".to_string()),
+            ],
+            vec![("authorization".to_string(), "Bearer request".to_string())],
+        );
+
+        assert!(
+            merged
+                == vec![
+                    ("X-Tenant".to_string(), "acme".to_string()),
+                    ("authorization".to_string(), "Bearer request".to_string()),
+                ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_inherits_collection_headers() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("https://example.com/", &db).await;
+
+        create_collection_header(&request.collection_id, "X-Tenant", "acme", &db)
+            .await
+            .unwrap();
+        create_collection_header(&request.collection_id, "Accept", "text/html", &db)
+            .await
+            .unwrap();
+        create_request_header(&request.id, "Accept", "application/json", &db)
+            .await
+            .unwrap();
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(
+            prepared.headers
+                == vec![
+                    ("X-Tenant".to_string(), "acme".to_string()),
+                    ("Accept".to_string(), "application/json".to_string()),
+                ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_body_content_type() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("https://example.com/", &db).await;
+        update_request_body(&request.id, BodyModes::Json, "", "{}", &db)
+            .await
+            .unwrap();
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.header("content-type") == Some("application/json"));
+        assert!(prepared.body == PreparedBody::Text("{}".to_string()));
+
+        // A Content-Type set by the user wins over the body mode.
+        create_request_header(&request.id, "content-type", "application/vnd.api+json", &db)
+            .await
+            .unwrap();
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.headers.len() == 1);
+        assert!(prepared.header("Content-Type") == Some("application/vnd.api+json"));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_invalid_method() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut request = stored_request("https://example.com/", &db).await;
+
+        // A method that doesn't parse is never sent as GET.
+        request.http_method = Some("GE T".to_string());
+        assert!(prepare_request(&request, &db).await.is_err());
+        request.http_method = None;
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.method == HTTPMethods::Get);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_multipart_content_type() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("https://example.com/", &db).await;
+        update_request_body(&request.id, BodyModes::Multipart, "", "", &db)
+            .await
+            .unwrap();
+        create_body_part(&request.id, "name", "querry", false, &db)
+            .await
+            .unwrap();
+        // Without the boundary the server couldn't split the body.
+        create_request_header(&request.id, "Content-Type", "multipart/form-data", &db)
+            .await
+            .unwrap();
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        let boundary = match &prepared.body {
+            PreparedBody::Multipart { boundary, .. } => boundary.clone(),
+            _ => panic!("Expected a multipart body"),
+        };
+        assert!(prepared.headers.len() == 1);
+        assert!(
+            prepared.header("Content-Type")
+                == Some(format!("multipart/form-data; boundary={}", boundary).as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_request_body() {
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+
+        let mut request = request_for(&url, HTTPMethods::Post);
+        request.body = PreparedBody::Text("{\"name\":\"querry\"}".to_string());
+        execute_request(&request).await.unwrap();
+
+        let raw_request = received.await.unwrap();
+        assert!(raw_request.ends_with("\r\n\r\n{\"name\":\"querry\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_send_request() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 201 Created\r\nContent-Length: 2\r\n\r\nok").await;
+        let request = stored_request(&url, &db).await;
+        create_request_header(&request.id, "X-Debug", "on", &db)
+            .await
+            .unwrap();
+
+        let response = send_request(&request, &db).await.unwrap();
+        let raw_request = received.await.unwrap().to_lowercase();
+
+        assert!(raw_request.contains("x-debug: on"));
+        assert!(response.status == 201);
+        assert!(response.body_text() == "ok");
+    }
+
+    #[tokio::test]
+    async fn test_send_request_records_history() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, _received) =
+            spawn_test_server("HTTP/1.1 201 Created\r\nX-Test: yes\r\nContent-Length: 2\r\n\r\nok")
+                .await;
+        let request = stored_request(&format!("{}{{{{path}}}}", url), &db).await;
+        create_request_variable(&request.id, "path", "users", &db)
+            .await
+            .unwrap();
+        update_request_body(&request.id, BodyModes::Json, "", "{\"a\":1}", &db)
+            .await
+            .unwrap();
+        let environment = create_environment("Local", &db).await.unwrap();
+        set_active_environment(Some(&environment.id), &db)
+            .await
+            .unwrap();
+
+        send_request(&request, &db).await.unwrap();
+
+        let entries = search_history("", 10, &db).await.unwrap();
+        assert!(entries.len() == 1);
+        let entry = get_history_entry(&entries[0].id, &db).await.unwrap();
+        // The resolved request is recorded, not the stored one.
+        assert!(entry.url == format!("{}users", url));
+        assert!(entry.http_method == "POST");
+        assert!(entry.request_id == Some(request.id.clone()));
+        assert!(entry.environment_name == "Local");
+        assert!(entry.request_body == b"{\"a\":1}");
+        assert!(entry
+            .request_headers
+            .contains(&("Content-Type".to_string(), "application/json".to_string())));
+        assert!(entry.status == Some(201));
+        assert!(entry.response_body == b"ok");
+        assert!(entry
+            .response_headers
+            .contains(&("x-test".to_string(), "yes".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_send_request_keeps_auth_out_of_history() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+        let request = stored_request(&url, &db).await;
+        let auth = update_request_auth(
+            &request.id,
+            AuthTypes::ApiKey,
+            "",
+            "key",
+            ApiKeyPlacement::Query,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&auth.id, "s3cret", &db).await.unwrap();
+
+        send_request(&request, &db).await.unwrap();
+        assert!(received.await.unwrap().contains("key=s3cret"));
+
+        let entries = search_history("", 10, &db).await.unwrap();
+        let entry = get_history_entry(&entries[0].id, &db).await.unwrap();
+        assert!(entry.url == url);
+        assert!(entry.request_headers.is_empty());
+
+        // Re-running sends the current auth of the request again.
+        let mut prepared = PreparedRequest::from_history(&entry).unwrap();
+        prepared.auth = resolve_auth(&request, &db).await.unwrap();
+        assert!(prepared.apply_auth().url == format!("{}?key=s3cret", url));
+    }
+
+    #[tokio::test]
+    async fn test_send_request_records_failures() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        // Nothing listens on port 9 of the loopback address.
+        let request = stored_request("http://127.0.0.1:9/", &db).await;
+
+        assert!(send_request(&request, &db).await.is_err());
+
+        let entries = search_history("", 10, &db).await.unwrap();
+        assert!(entries.len() == 1);
+        assert!(entries[0].status.is_none());
+        assert!(!entries[0].error.is_empty());
+
+        // So are requests whose body can't be built.
+        update_request_body(&request.id, BodyModes::Multipart, "", "", &db)
+            .await
+            .unwrap();
+        create_body_part(&request.id, "file", "/nonexistent/querry.txt", true, &db)
+            .await
+            .unwrap();
+
+        assert!(send_request(&request, &db).await.is_err());
+
+        let entries = search_history("", 10, &db).await.unwrap();
+        assert!(entries.len() == 2);
+        assert!(entries[0].status.is_none());
+        assert!(!entries[0].error.is_empty());
+
+        // And requests whose token can't be fetched.
+        update_request_body(&request.id, BodyModes::None, "", "", &db)
+            .await
+            .unwrap();
+        let auth = update_collection_auth(
+            &request.collection_id,
+            AuthTypes::OAuth2,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_oauth2_config(
+            &auth.id,
+            OAuth2GrantTypes::ClientCredentials,
+            "",
+            "http://127.0.0.1:9/token",
+            "querry",
+            "",
+            "",
+            &db,
+        )
+        .await
+        .unwrap();
+
+        assert!(send_request(&request, &db).await.is_err());
+
+        let entries = search_history("", 10, &db).await.unwrap();
+        assert!(entries.len() == 3);
+        assert!(entries[0].status.is_none());
+        assert!(!entries[0].error.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_request_to() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("http://127.0.0.1:9/", &db).await;
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+        create_request_variable(&request.id, "base", &url, &db)
+            .await
+            .unwrap();
+
+        let execution = send_request_to(&request, "{{base}}other", &db)
+            .await
+            .unwrap();
+        let raw_request = received.await.unwrap();
+
+        assert!(raw_request.starts_with("POST /other HTTP/1.1"));
+        assert!(execution.response.unwrap().body_text() == "ok");
+        let entry = get_history_entry(&execution.history_id.unwrap(), &db)
+            .await
+            .unwrap();
+        assert!(entry.url == format!("{}other", url));
+        assert!(entry.request_id == Some(request.id.clone()));
+    }
+
+    #[tokio::test]
+    async fn test_execute_history_entry() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, _received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let request = stored_request(&url, &db).await;
+        create_request_header(&request.id, "X-Debug", "on", &db)
+            .await
+            .unwrap();
+        update_request_body(&request.id, BodyModes::Raw, "text/plain", "ping", &db)
+            .await
+            .unwrap();
+        send_request(&request, &db).await.unwrap();
+        let entries = search_history("", 10, &db).await.unwrap();
+        let entry = get_history_entry(&entries[0].id, &db).await.unwrap();
+
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n").await;
+        let mut prepared = PreparedRequest::from_history(&entry).unwrap();
+        prepared.url = url;
+        let execution = execute_and_record(prepared, HistoryOrigin::default(), &db)
+            .await
+            .unwrap();
+        let response = execution.response.unwrap();
+        let raw_request = received.await.unwrap();
+
+        assert!(response.status == 204);
+        assert!(raw_request.starts_with("POST / HTTP/1.1"));
+        assert!(raw_request.to_lowercase().contains("x-debug: on"));
+        assert!(raw_request.ends_with("ping"));
+        let entries = search_history("", 10, &db).await.unwrap();
+        assert!(entries.len() == 2);
+        assert!(execution.history_id == Some(entries[0].id.clone()));
+
+        let mut truncated = entry.clone();
+        truncated.request_body_truncated = true;
+        assert!(PreparedRequest::from_history(&truncated).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_substitutes_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("http://{{host}}/users?page={{page}}", &db).await;
+        create_request_header(&request.id, "Authorization", "Bearer {{token}}", &db)
+            .await
+            .unwrap();
+        update_request_body(
+            &request.id,
+            BodyModes::Json,
+            "",
+            "{\"page\": {{page}}}",
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let environment = create_environment("Local", &db).await.unwrap();
+        set_active_environment(Some(&environment.id), &db)
+            .await
+            .unwrap();
+        for (name, value) in [("host", "localhost:8080"), ("page", "2"), ("token", "abc")] {
+            create_environment_variable(&environment.id, name, value, &db)
+                .await
+                .unwrap();
+        }
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.url == "http://localhost:8080/users?page=2");
+        assert!(prepared.header("authorization") == Some("Bearer abc"));
+        assert!(prepared.body == PreparedBody::Text("{\"page\": 2}".to_string()));
+        assert!(unresolved_request_variables(&request, &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_rejects_unresolved_variables() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("http://{{host}}/", &db).await;
+        create_request_header(&request.id, "X-Api-Key", "{{api_key}}", &db)
+            .await
+            .unwrap();
+
+        let error = prepare_request(&request, &db).await.unwrap_err();
+        assert!(error.to_string() == "Unresolved variables: {{host}}, {{api_key}}");
+        assert!(
+            unresolved_request_variables(&request, &db).await.unwrap()
+                == vec!["host".to_string(), "api_key".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_variable_scopes() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("{{baseUrl}}/users/{{userId}}?v={{version}}", &db).await;
+
+        create_global_variable("baseUrl", "http://global", &db)
+            .await
+            .unwrap();
+        create_global_variable("version", "1", &db).await.unwrap();
+        let environment = create_environment("Local", &db).await.unwrap();
+        set_active_environment(Some(&environment.id), &db)
+            .await
+            .unwrap();
+        create_environment_variable(&environment.id, "baseUrl", "http://environment", &db)
+            .await
+            .unwrap();
+        create_environment_variable(&environment.id, "userId", "1", &db)
+            .await
+            .unwrap();
+        create_collection_variable(&request.collection_id, "baseUrl", "http://collection", &db)
+            .await
+            .unwrap();
+        create_request_variable(&request.id, "userId", "42", &db)
+            .await
+            .unwrap();
+
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.url == "http://collection/users/42?v=1");
+    }
+
+    #[tokio::test]
+    async fn test_prepare_request_auth() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let request = stored_request("https://example.com/items?page=1", &db).await;
+
+        let collection_auth = update_collection_auth(
+            &request.collection_id,
+            AuthTypes::Basic,
+            "{{user}}",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&collection_auth.id, "secret", &db)
+            .await
+            .unwrap();
+        create_collection_variable(&request.collection_id, "user", "admin", &db)
+            .await
+            .unwrap();
+
+        // Requests without auth settings inherit the collection's.
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.header("authorization") == Some("Basic YWRtaW46c2VjcmV0"));
+
+        let request_auth = update_request_auth(
+            &request.id,
+            AuthTypes::ApiKey,
+            "",
+            "api key",
+            ApiKeyPlacement::Query,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&request_auth.id, "k&1", &db)
+            .await
+            .unwrap();
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.url == "https://example.com/items?page=1&api%20key=k%261");
+        assert!(prepared.header("authorization").is_none());
+
+        update_request_auth(
+            &request.id,
+            AuthTypes::Bearer,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&request_auth.id, "abc", &db)
+            .await
+            .unwrap();
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.header("authorization") == Some("Bearer abc"));
+
+        // An explicit Authorization header wins over the auth helper.
+        create_request_header(&request.id, "Authorization", "Token xyz", &db)
+            .await
+            .unwrap();
+        let prepared = prepare_request(&request, &db).await.unwrap();
+        assert!(prepared.headers.len() == 1);
+        assert!(prepared.header("authorization") == Some("Token xyz"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_request_digest_auth() {
+        let (url, received) = spawn_test_server_sequence(vec![
+            "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: Digest realm=\"test\", qop=\"auth\", nonce=\"abc123\", opaque=\"xyz\"\r\nConnection: close\r\nContent-Length: 0\r\n\r\n".to_string(),
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok".to_string(),
+        ])
+        .await;
+
+        let mut request = request_for(&format!("{}dir/index.html?a=1", url), HTTPMethods::Get);
+        request.auth = PreparedAuth::Digest {
+            username: "Mufasa".to_string(),
+            password: "Circle Of Life".to_string(),
+        };
+        let response = execute_request(&request).await.unwrap();
+        let requests = received.await.unwrap();
+
+        assert!(response.status == 200);
+        assert!(requests.len() == 2);
+        assert!(!requests[0].to_lowercase().contains("authorization:"));
+        let authorization = requests[1]
+            .lines()
+            .find(|line| line.to_lowercase().starts_with("authorization:"))
+            .unwrap();
+        assert!(authorization.contains("Digest username=\"Mufasa\""));
+        assert!(authorization.contains("uri=\"/dir/index.html?a=1\""));
+        assert!(authorization.contains("opaque=\"xyz\""));
+    }
+
+    #[tokio::test]
+    async fn test_send_request_oauth2() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let token_body = "{\"access_token\":\"abc\",\"token_type\":\"bearer\",\"expires_in\":3600}";
+        let (token_url, token_request) = spawn_test_server(&format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            token_body.len(),
+            token_body
+        ))
+        .await;
+        let (url, received) =
+            spawn_test_server("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").await;
+        let request = stored_request(&url, &db).await;
+
+        let auth = update_collection_auth(
+            &request.collection_id,
+            AuthTypes::OAuth2,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_oauth2_config(
+            &auth.id,
+            OAuth2GrantTypes::ClientCredentials,
+            "",
+            "{{tokenUrl}}",
+            "querry",
+            "",
+            "",
+            &db,
+        )
+        .await
+        .unwrap();
+        update_oauth2_client_secret(&auth.id, "s3cret", &db)
+            .await
+            .unwrap();
+        create_collection_variable(&request.collection_id, "tokenUrl", &token_url, &db)
+            .await
+            .unwrap();
+
+        let response = send_request(&request, &db).await.unwrap();
+        assert!(response.status == 200);
+        assert!(token_request
+            .await
+            .unwrap()
+            .contains("grant_type=client_credentials"));
+        assert!(received
+            .await
+            .unwrap()
+            .to_lowercase()
+            .contains("authorization: bearer abc"));
+    }
+}
@@ -0,0 +1,756 @@
+//! OAuth 2.0 grants, the loopback redirect listener and the token cache.
+
+use std::{
+    error::Error,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{rng, Rng};
+use reqwest::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+use url::{form_urlencoded, Url};
+use uuid::Uuid;
+
+use super::{auth::basic_authorization, http_client};
+use crate::utils::{
+    crud::{
+        auth::AuthData,
+        environments::get_active_environment,
+        oauth2::{
+            get_oauth2_client_secret, get_oauth2_config, get_oauth2_token, save_oauth2_token,
+            OAuth2GrantTypes, OAuth2TokenData,
+        },
+    },
+    query_params::{append_query_param, parse_query},
+    tokio_runtime::runtime,
+    variables::VariableResolver,
+};
+
+/// Tokens expiring within this many seconds are refreshed before use.
+const EXPIRY_MARGIN_SECONDS: i64 = 30;
+
+/// How long the authorization code flow waits for the browser to redirect back.
+const AUTHORIZATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+const CALLBACK_PAGE: &str = "<html><body><p>Authorization finished, you can close this window and return to Querry.</p></body></html>";
+
+/// OAuth 2.0 settings of an auth configuration, with its secrets.
+///
+/// `username` and `password` are only used by the password grant.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OAuth2Settings {
+    /// Auth configuration the obtained tokens are cached for.
+    pub auth_id: String,
+    pub grant_type: OAuth2GrantTypes,
+    pub auth_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    pub redirect_uri: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl OAuth2Settings {
+    /// Load the OAuth 2.0 settings saved for an auth configuration.
+    pub async fn for_auth(
+        auth: &AuthData,
+        password: String,
+        pool: &SqlitePool,
+    ) -> Result<OAuth2Settings, Box<dyn Error>> {
+        let config = get_oauth2_config(&auth.id, pool).await?.unwrap_or_default();
+
+        Ok(OAuth2Settings {
+            auth_id: auth.id.clone(),
+            grant_type: OAuth2GrantTypes::from_string(&config.grant_type)
+                .unwrap_or(OAuth2GrantTypes::ClientCredentials),
+            auth_url: config.auth_url.trim().to_string(),
+            token_url: config.token_url.trim().to_string(),
+            client_id: config.client_id,
+            client_secret: get_oauth2_client_secret(&auth.id, pool).await?,
+            scope: config.scope,
+            redirect_uri: config.redirect_uri.trim().to_string(),
+            username: auth.username.clone(),
+            password,
+        })
+    }
+
+    /// Substitute `{{variable}}` placeholders in every field but the auth id.
+    pub fn substitute_variables(
+        self,
+        variables: &VariableResolver,
+        unresolved: &mut Vec<String>,
+    ) -> OAuth2Settings {
+        let mut substitute = |text: &str| variables.substitute(text, unresolved);
+
+        OAuth2Settings {
+            auth_url: substitute(&self.auth_url),
+            token_url: substitute(&self.token_url),
+            client_id: substitute(&self.client_id),
+            client_secret: substitute(&self.client_secret),
+            scope: substitute(&self.scope),
+            redirect_uri: substitute(&self.redirect_uri),
+            username: substitute(&self.username),
+            password: substitute(&self.password),
+            ..self
+        }
+    }
+}
+
+/// Successful answer of a token endpoint.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub token_type: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Lifetime in seconds. Some servers send it as a string.
+    #[serde(default, deserialize_with = "deserialize_expires_in")]
+    pub expires_in: Option<i64>,
+}
+
+fn deserialize_expires_in<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_json::Value::Number(number)) => number.as_i64(),
+        Some(serde_json::Value::String(text)) => text.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+#[derive(Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Parse the body of a token endpoint response, turning OAuth errors into readable ones.
+pub fn parse_token_response(
+    status: u16,
+    body: &[u8],
+) -> Result<TokenResponse, Box<dyn Error + Send + Sync>> {
+    if let Ok(error) = serde_json::from_slice::<TokenError>(body) {
+        return Err(match error.error_description {
+            Some(description) => format!("Token request failed: {} ({})", error.error, description),
+            None => format!("Token request failed: {}", error.error),
+        }
+        .into());
+    }
+    if !(200..300).contains(&status) {
+        return Err(format!("Token request failed with status {}", status).into());
+    }
+
+    serde_json::from_slice(body)
+        .map_err(|error| format!("Invalid token response: {}", error).into())
+}
+
+/// Current Unix time in seconds, as stored in `expires_at`.
+pub fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Whether a cached token can still be sent.
+pub fn is_token_valid(token: &OAuth2TokenData, now: i64) -> bool {
+    match token.expires_at {
+        Some(expires_at) => expires_at > now + EXPIRY_MARGIN_SECONDS,
+        None => true,
+    }
+}
+
+/// `Authorization` header value for a token.
+pub fn token_authorization(token: &OAuth2TokenData) -> String {
+    let token_type =
+        if token.token_type.is_empty() || token.token_type.eq_ignore_ascii_case("bearer") {
+            "Bearer"
+        } else {
+            token.token_type.as_str()
+        };
+    format!("{} {}", token_type, token.access_token)
+}
+
+/// POST a grant to the token endpoint.
+///
+/// Confidential clients authenticate with HTTP Basic as RFC 6749 requires
+/// servers to support it, public clients send their id in the body.
+async fn request_token(
+    settings: &OAuth2Settings,
+    params: Vec<(&str, &str)>,
+) -> Result<TokenResponse, Box<dyn Error + Send + Sync>> {
+    if settings.token_url.is_empty() {
+        return Err("OAuth 2.0 auth has no token URL".into());
+    }
+
+    // The scope was already granted with the code.
+    let sends_scope = !params.contains(&("grant_type", "authorization_code"));
+    // The serializer isn't Send, so it has to be gone before the request is awaited.
+    let form = {
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.extend_pairs(params);
+        if sends_scope && !settings.scope.trim().is_empty() {
+            form.append_pair("scope", settings.scope.trim());
+        }
+        if settings.client_secret.is_empty() {
+            form.append_pair("client_id", &settings.client_id);
+        }
+        form.finish()
+    };
+    let mut builder = http_client()
+        .post(settings.token_url.as_str())
+        .header(ACCEPT, "application/json")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded");
+    if !settings.client_secret.is_empty() {
+        let encode =
+            |text: &str| form_urlencoded::byte_serialize(text.as_bytes()).collect::<String>();
+        builder = builder.header(
+            AUTHORIZATION,
+            basic_authorization(
+                &encode(&settings.client_id),
+                &encode(&settings.client_secret),
+            ),
+        );
+    }
+
+    let response = builder.body(form).send().await?;
+    let status = response.status().as_u16();
+    let body = response.bytes().await?;
+    parse_token_response(status, &body)
+}
+
+/// Random PKCE code verifier (RFC 7636).
+pub fn pkce_verifier() -> String {
+    const CHARACTERS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+    let mut random = rng();
+    (0..64)
+        .map(|_| CHARACTERS[random.random_range(0..CHARACTERS.len())] as char)
+        .collect()
+}
+
+/// `S256` code challenge of a PKCE code verifier.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// URL the user is sent to in the browser to authorize the client.
+pub fn authorization_url(
+    settings: &OAuth2Settings,
+    redirect_uri: &str,
+    state: &str,
+    code_challenge: &str,
+) -> String {
+    let mut url = settings.auth_url.clone();
+    let mut params = vec![
+        ("response_type", "code"),
+        ("client_id", settings.client_id.as_str()),
+        ("redirect_uri", redirect_uri),
+        ("state", state),
+        ("code_challenge", code_challenge),
+        ("code_challenge_method", "S256"),
+    ];
+    if !settings.scope.trim().is_empty() {
+        params.push(("scope", settings.scope.trim()));
+    }
+    for (name, value) in params {
+        url = append_query_param(&url, name, value);
+    }
+    url
+}
+
+/// Bind the loopback listener the browser is redirected to.
+///
+/// Without a configured redirect URI a free port is picked. A configured one
+/// has to point at the local machine.
+async fn bind_redirect_listener(
+    redirect_uri: &str,
+) -> Result<(TcpListener, String), Box<dyn Error + Send + Sync>> {
+    if redirect_uri.is_empty() {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        return Ok((listener, format!("http://127.0.0.1:{}/callback", port)));
+    }
+
+    let url = Url::parse(redirect_uri)?;
+    let host = match url.host_str() {
+        Some(host @ ("127.0.0.1" | "localhost" | "[::1]")) => host.trim_matches(['[', ']']),
+        _ => return Err("The redirect URI has to point at 127.0.0.1 or localhost".into()),
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    let listener = TcpListener::bind((host, port)).await?;
+    Ok((listener, redirect_uri.to_string()))
+}
+
+/// Read the request line of a redirect, skipping the headers.
+async fn read_request_target(
+    stream: &mut TcpStream,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buffer.windows(4).any(|window| window == b"\r\n\r\n") && buffer.len() < 64 * 1024 {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+
+    let text = String::from_utf8_lossy(&buffer);
+    let target = text
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/");
+    Ok(target.to_string())
+}
+
+/// Wait for the browser to be redirected back and return the authorization code.
+async fn receive_authorization_code(
+    listener: TcpListener,
+    redirect_path: &str,
+    state: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let target = read_request_target(&mut stream).await?;
+        let path = target.split('?').next().unwrap_or("/");
+        if path != redirect_path {
+            // Browsers also ask for a favicon.
+            let _ = stream
+                .write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await;
+            continue;
+        }
+
+        let _ = stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    CALLBACK_PAGE.len(),
+                    CALLBACK_PAGE
+                )
+                .as_bytes(),
+            )
+            .await;
+        let _ = stream.shutdown().await;
+
+        let params = parse_query(&target);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(param_name, _)| param_name == name)
+                .map(|(_, value)| value.clone())
+        };
+        if let Some(error) = param("error") {
+            return Err(match param("error_description") {
+                Some(description) => format!("Authorization failed: {} ({})", error, description),
+                None => format!("Authorization failed: {}", error),
+            }
+            .into());
+        }
+        if param("state").as_deref() != Some(state) {
+            return Err("Authorization failed: the state sent back does not match".into());
+        }
+        return param("code").ok_or_else(|| "Authorization failed: no code was sent back".into());
+    }
+}
+
+/// Run the authorization code flow with PKCE.
+///
+/// `open_browser` is handed the authorization URL. The code is received on a
+/// loopback listener and exchanged for a token.
+pub async fn authorize_with_code<F>(
+    settings: &OAuth2Settings,
+    open_browser: F,
+) -> Result<TokenResponse, Box<dyn Error + Send + Sync>>
+where
+    F: FnOnce(&str) -> Result<(), Box<dyn Error + Send + Sync>>,
+{
+    if settings.auth_url.is_empty() {
+        return Err("OAuth 2.0 auth has no authorization URL".into());
+    }
+
+    let (listener, redirect_uri) = bind_redirect_listener(&settings.redirect_uri).await?;
+    let redirect_path = Url::parse(&redirect_uri)?.path().to_string();
+    let state = Uuid::new_v4().simple().to_string();
+    let verifier = pkce_verifier();
+
+    open_browser(&authorization_url(
+        settings,
+        &redirect_uri,
+        &state,
+        &pkce_challenge(&verifier),
+    ))?;
+    let code = timeout(
+        AUTHORIZATION_TIMEOUT,
+        receive_authorization_code(listener, &redirect_path, &state),
+    )
+    .await
+    .map_err(|_| "Authorization timed out")??;
+
+    request_token(
+        settings,
+        vec![
+            ("grant_type", "authorization_code"),
+            ("code", &code),
+            ("redirect_uri", &redirect_uri),
+            ("code_verifier", &verifier),
+        ],
+    )
+    .await
+}
+
+/// Obtain a new token with the configured grant.
+pub async fn fetch_token(
+    settings: &OAuth2Settings,
+) -> Result<TokenResponse, Box<dyn Error + Send + Sync>> {
+    match settings.grant_type {
+        OAuth2GrantTypes::ClientCredentials => {
+            request_token(settings, vec![("grant_type", "client_credentials")]).await
+        }
+        OAuth2GrantTypes::Password => {
+            request_token(
+                settings,
+                vec![
+                    ("grant_type", "password"),
+                    ("username", &settings.username),
+                    ("password", &settings.password),
+                ],
+            )
+            .await
+        }
+        OAuth2GrantTypes::AuthorizationCode => {
+            authorize_with_code(settings, |url| Ok(open::that(url)?)).await
+        }
+    }
+}
+
+pub async fn refresh_token(
+    settings: &OAuth2Settings,
+    refresh_token: &str,
+) -> Result<TokenResponse, Box<dyn Error + Send + Sync>> {
+    request_token(
+        settings,
+        vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ],
+    )
+    .await
+}
+
+/// Obtain a token on the shared runtime and cache it for the active environment.
+///
+/// With a refresh token the token is refreshed first, and the configured
+/// grant is only used when that fails.
+async fn renew_token(
+    settings: &OAuth2Settings,
+    cached: Option<OAuth2TokenData>,
+    environment_id: Option<&str>,
+    pool: &SqlitePool,
+) -> Result<OAuth2TokenData, Box<dyn Error>> {
+    let previous_refresh_token = cached
+        .map(|token| token.refresh_token)
+        .filter(|token| !token.is_empty());
+
+    let task_settings = settings.clone();
+    let task_refresh_token = previous_refresh_token.clone();
+    let response = runtime()
+        .spawn(async move {
+            if let Some(token) = task_refresh_token {
+                if let Ok(response) = refresh_token(&task_settings, &token).await {
+                    return Ok(response);
+                }
+            }
+            fetch_token(&task_settings).await
+        })
+        .await?
+        .map_err(|error| error.to_string())?;
+
+    // Servers may keep the refresh token unchanged without sending it again.
+    let new_refresh_token = response
+        .refresh_token
+        .or(previous_refresh_token)
+        .unwrap_or_default();
+    let token = save_oauth2_token(
+        &settings.auth_id,
+        environment_id,
+        &response.access_token,
+        &new_refresh_token,
+        response.token_type.as_deref().unwrap_or("Bearer"),
+        response.expires_in.map(|seconds| unix_time() + seconds),
+        pool,
+    )
+    .await?;
+
+    Ok(token)
+}
+
+/// Token to send for the active environment, from the cache while it is valid.
+pub async fn access_token(
+    settings: &OAuth2Settings,
+    pool: &SqlitePool,
+) -> Result<OAuth2TokenData, Box<dyn Error>> {
+    let environment_id = get_active_environment(pool).await?.map(|data| data.id);
+    let cached = get_oauth2_token(&settings.auth_id, environment_id.as_deref(), pool).await?;
+    if let Some(token) = &cached {
+        if is_token_valid(token, unix_time()) {
+            return Ok(token.clone());
+        }
+    }
+
+    renew_token(settings, cached, environment_id.as_deref(), pool).await
+}
+
+/// Obtain a fresh token for the active environment, ignoring the cache.
+pub async fn new_access_token(
+    settings: &OAuth2Settings,
+    pool: &SqlitePool,
+) -> Result<OAuth2TokenData, Box<dyn Error>> {
+    let environment_id = get_active_environment(pool).await?.map(|data| data.id);
+    renew_token(settings, None, environment_id.as_deref(), pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                auth::{update_collection_auth, ApiKeyPlacement, AuthTypes},
+                collections::create_collection,
+            },
+            test_server::{spawn_test_server, spawn_test_server_sequence},
+        },
+    };
+
+    fn token_json(access_token: &str, refresh_token: Option<&str>) -> String {
+        let body = match refresh_token {
+            Some(refresh_token) => format!(
+                "{{\"access_token\":\"{}\",\"token_type\":\"bearer\",\"expires_in\":3600,\"refresh_token\":\"{}\"}}",
+                access_token, refresh_token
+            ),
+            None => format!(
+                "{{\"access_token\":\"{}\",\"token_type\":\"bearer\",\"expires_in\":\"3600\"}}",
+                access_token
+            ),
+        };
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    async fn settings_for(
+        grant_type: OAuth2GrantTypes,
+        token_url: &str,
+        pool: &SqlitePool,
+    ) -> OAuth2Settings {
+        let collection = create_collection("Test collection".to_string(), pool)
+            .await
+            .unwrap();
+        let auth = update_collection_auth(
+            &collection.id,
+            AuthTypes::OAuth2,
+            "",
+            "",
+            ApiKeyPlacement::Header,
+            pool,
+        )
+        .await
+        .unwrap();
+
+        OAuth2Settings {
+            auth_id: auth.id,
+            grant_type,
+            auth_url: String::new(),
+            token_url: token_url.to_string(),
+            client_id: "querry".to_string(),
+            client_secret: "s3cret".to_string(),
+            scope: "read write".to_string(),
+            redirect_uri: String::new(),
+            username: "admin".to_string(),
+            password: "p&ss".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_token_response() {
+        let response =
+            parse_token_response(200, b"{\"access_token\":\"abc\",\"expires_in\":60}").unwrap();
+        assert!(response.access_token == "abc");
+        assert!(response.expires_in == Some(60));
+        assert!(response.refresh_token.is_none());
+
+        let error = parse_token_response(
+            400,
+            b"{\"error\":\"invalid_client\",\"error_description\":\"Unknown client\"}",
+        )
+        .unwrap_err();
+        assert!(error.to_string() == "Token request failed: invalid_client (Unknown client)");
+        assert!(parse_token_response(500, b"oops").is_err());
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // Example from RFC 7636, appendix B.
+        assert!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk")
+                == "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+        let verifier = pkce_verifier();
+        assert!(verifier.len() == 64);
+        assert!(verifier != pkce_verifier());
+    }
+
+    #[test]
+    fn test_token_authorization() {
+        let mut token = OAuth2TokenData {
+            id: String::new(),
+            auth_id: String::new(),
+            environment_id: None,
+            access_token: "abc".to_string(),
+            refresh_token: String::new(),
+            token_type: "bearer".to_string(),
+            expires_at: Some(100),
+        };
+        assert!(token_authorization(&token) == "Bearer abc");
+        assert!(is_token_valid(&token, 0));
+        assert!(!is_token_valid(&token, 80));
+
+        token.token_type = "MAC".to_string();
+        token.expires_at = None;
+        assert!(token_authorization(&token) == "MAC abc");
+        assert!(is_token_valid(&token, i64::MAX / 2));
+    }
+
+    #[tokio::test]
+    async fn test_client_credentials_token_is_cached() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) = spawn_test_server(&token_json("first", None)).await;
+        let settings = settings_for(OAuth2GrantTypes::ClientCredentials, &url, &db).await;
+
+        let token = access_token(&settings, &db).await.unwrap();
+        let raw_request = received.await.unwrap();
+        assert!(token.access_token == "first");
+        assert!(token.expires_at.unwrap() > unix_time() + 3500);
+        assert!(raw_request.starts_with("POST / HTTP/1.1"));
+        assert!(raw_request.contains("grant_type=client_credentials&scope=read+write"));
+        assert!(raw_request.contains("Basic cXVlcnJ5OnMzY3JldA=="));
+
+        // The server is gone, so the token has to come from the cache.
+        let cached = access_token(&settings, &db).await.unwrap();
+        assert!(cached.id == token.id);
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) = spawn_test_server(&token_json("second", None)).await;
+        let settings = settings_for(OAuth2GrantTypes::Password, &url, &db).await;
+        save_oauth2_token(
+            &settings.auth_id,
+            None,
+            "first",
+            "refresh-1",
+            "Bearer",
+            Some(unix_time() - 10),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let token = access_token(&settings, &db).await.unwrap();
+        let raw_request = received.await.unwrap();
+        assert!(raw_request.contains("grant_type=refresh_token&refresh_token=refresh-1"));
+        assert!(token.access_token == "second");
+        // The refresh token is kept when the server does not send a new one.
+        assert!(token.refresh_token == "refresh-1");
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_falls_back_to_grant() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) = spawn_test_server_sequence(vec![
+            "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: 25\r\n\r\n{\"error\":\"invalid_grant\"}".to_string(),
+            token_json("third", Some("refresh-2")),
+        ])
+        .await;
+        let settings = settings_for(OAuth2GrantTypes::Password, &url, &db).await;
+        save_oauth2_token(
+            &settings.auth_id,
+            None,
+            "first",
+            "refresh-1",
+            "Bearer",
+            Some(unix_time() - 10),
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let token = access_token(&settings, &db).await.unwrap();
+        let requests = received.await.unwrap();
+        assert!(requests[1].contains("grant_type=password&username=admin&password=p%26ss"));
+        assert!(token.access_token == "third");
+        assert!(token.refresh_token == "refresh-2");
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_with_pkce() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, received) = spawn_test_server(&token_json("code-token", None)).await;
+        let mut settings = settings_for(OAuth2GrantTypes::AuthorizationCode, &url, &db).await;
+        settings.auth_url = "https://auth.example.com/authorize".to_string();
+        settings.client_secret = String::new();
+
+        // Stand in for the browser: follow the redirect with a code.
+        let response = authorize_with_code(&settings, |authorization_url| {
+            let params = parse_query(authorization_url);
+            let param = |name: &str| {
+                params
+                    .iter()
+                    .find(|(param_name, _)| param_name == name)
+                    .map(|(_, value)| value.clone())
+                    .unwrap()
+            };
+            assert!(param("code_challenge_method") == "S256");
+            assert!(param("client_id") == "querry");
+            let redirect_uri = param("redirect_uri");
+            let favicon = redirect_uri.replace("/callback", "/favicon.ico");
+            let redirect = format!("{}?code=abc&state={}", redirect_uri, param("state"));
+            tokio::spawn(async move {
+                reqwest::get(favicon).await.unwrap();
+                reqwest::get(redirect).await.unwrap();
+            });
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let raw_request = received.await.unwrap();
+        assert!(response.access_token == "code-token");
+        assert!(raw_request.contains("grant_type=authorization_code&code=abc&redirect_uri="));
+        assert!(raw_request.contains("code_verifier="));
+        assert!(raw_request.contains("client_id=querry"));
+        assert!(!raw_request.to_lowercase().contains("authorization:"));
+    }
+}
@@ -0,0 +1,429 @@
+//! Suggestions for the word being typed in a query.
+//!
+//! Queries being typed rarely parse, so instead of the parser a small
+//! scanner follows the braces up to the cursor to tell which type the
+//! selection there belongs to.
+
+use super::schema::{Schema, TypeKinds};
+
+/// Most suggestions shown at once.
+const MAX_COMPLETIONS: usize = 50;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    /// The type of a field or argument, or the kind of a type.
+    pub detail: String,
+}
+
+/// Suggestions for the word that starts at `start` and ends at the cursor.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Completions {
+    pub start: usize,
+    pub items: Vec<Completion>,
+}
+
+impl Completions {
+    /// The query with a suggestion in place of the word, and the offset
+    /// right after it.
+    pub fn apply(&self, query: &str, offset: usize, label: &str) -> (String, usize) {
+        let offset = offset.clamp(self.start, query.len());
+        let text = format!("{}{}{}", &query[..self.start], label, &query[offset..]);
+        (text, self.start + label.len())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Punctuator(char),
+    Spread,
+    /// A string; its content never matters here.
+    Text,
+}
+
+/// Split the text before the cursor into tokens. `None` when the cursor is
+/// inside a string or a comment.
+fn tokenize(text: &str) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        if first.is_whitespace() || first == ',' {
+            rest = &rest[first.len_utf8()..];
+        } else if first == '#' {
+            let end = rest.find('\n')?;
+            rest = &rest[end..];
+        } else if let Some(after) = rest.strip_prefix("\"\"\"") {
+            let end = after.find("\"\"\"")?;
+            rest = &after[end + 3..];
+            tokens.push(Token::Text);
+        } else if first == '"' {
+            let mut escaped = false;
+            let end = rest[1..].char_indices().find_map(|(index, c)| {
+                let closes = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                closes.then_some(index + 1)
+            })?;
+            rest = &rest[end + 1..];
+            tokens.push(Token::Text);
+        } else if let Some(after) = rest.strip_prefix("...") {
+            rest = after;
+            tokens.push(Token::Spread);
+        } else if first == '_' || first.is_ascii_alphanumeric() || first == '-' {
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| !(*c == '_' || c.is_ascii_alphanumeric() || *c == '.'))
+                .map(|(index, _)| index)
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(&rest[..end]));
+            rest = &rest[end..];
+        } else {
+            tokens.push(Token::Punctuator(first));
+            rest = &rest[first.len_utf8()..];
+        }
+    }
+    Some(tokens)
+}
+
+/// Where a token sits, innermost last.
+#[derive(Clone, Debug, PartialEq)]
+enum Frame {
+    /// A selection set on a type, `None` when the type isn't known.
+    Selection {
+        type_name: Option<String>,
+        field: Option<String>,
+        condition: Option<String>,
+    },
+    /// Arguments of a field, or variable definitions when there is no field.
+    Arguments {
+        type_name: Option<String>,
+        field: Option<String>,
+        argument: Option<String>,
+    },
+    /// An object or list value.
+    Value,
+}
+
+/// What the cursor is in.
+struct Context<'a> {
+    frames: Vec<Frame>,
+    previous: Option<Token<'a>>,
+    before_previous: Option<Token<'a>>,
+}
+
+fn scan<'a>(tokens: &[Token<'a>], schema: &Schema) -> Context<'a> {
+    let mut frames: Vec<Frame> = Vec::new();
+    // The type the next selection set at the top level is on.
+    let mut root = schema.query_type.clone();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| tokens[index]);
+        let next = tokens.get(index + 1).copied();
+        match frames.last_mut() {
+            None => match token {
+                Token::Name("query") => root = schema.query_type.clone(),
+                Token::Name("mutation") => root = schema.mutation_type.clone(),
+                Token::Name("subscription") => root = schema.subscription_type.clone(),
+                Token::Name("fragment") => root = None,
+                Token::Name(name) if previous == Some(Token::Name("on")) => {
+                    root = Some(name.to_string())
+                }
+                Token::Punctuator('(') => frames.push(Frame::Arguments {
+                    type_name: None,
+                    field: None,
+                    argument: None,
+                }),
+                Token::Punctuator('{') => frames.push(Frame::Selection {
+                    type_name: root.take(),
+                    field: None,
+                    condition: None,
+                }),
+                Token::Punctuator('}') => root = schema.query_type.clone(),
+                _ => {}
+            },
+            Some(Frame::Selection {
+                type_name,
+                field,
+                condition,
+            }) => match token {
+                Token::Name(name) => match previous {
+                    Some(Token::Spread) => {}
+                    Some(Token::Name("on"))
+                        if index.checked_sub(2).map(|index| tokens[index])
+                            == Some(Token::Spread) =>
+                    {
+                        *condition = Some(name.to_string())
+                    }
+                    Some(Token::Punctuator('@')) => {}
+                    _ if next == Some(Token::Punctuator(':')) => {}
+                    _ => {
+                        *field = Some(name.to_string());
+                        *condition = None;
+                    }
+                },
+                Token::Punctuator('(') => {
+                    let frame = Frame::Arguments {
+                        type_name: type_name.clone(),
+                        field: field.clone(),
+                        argument: None,
+                    };
+                    frames.push(frame);
+                }
+                Token::Punctuator('{') => {
+                    let child = match (condition.take(), previous) {
+                        (Some(condition), _) => Some(condition),
+                        (None, Some(Token::Spread)) => type_name.clone(),
+                        (None, _) => type_name
+                            .as_deref()
+                            .zip(field.as_deref())
+                            .and_then(|(type_name, field)| schema.field(type_name, field))
+                            .map(|field| field.field_type.named().to_string()),
+                    };
+                    frames.push(Frame::Selection {
+                        type_name: child,
+                        field: None,
+                        condition: None,
+                    });
+                }
+                Token::Punctuator('}') => {
+                    frames.pop();
+                }
+                _ => {}
+            },
+            Some(Frame::Arguments { argument, .. }) => match token {
+                Token::Name(name) if next == Some(Token::Punctuator(':')) => {
+                    *argument = Some(name.to_string())
+                }
+                Token::Punctuator(')') => {
+                    frames.pop();
+                }
+                Token::Punctuator('{' | '[') => frames.push(Frame::Value),
+                _ => {}
+            },
+            Some(Frame::Value) => match token {
+                Token::Punctuator('{' | '[') => frames.push(Frame::Value),
+                Token::Punctuator('}' | ']') => {
+                    frames.pop();
+                }
+                _ => {}
+            },
+        }
+    }
+
+    Context {
+        frames,
+        previous: tokens.last().copied(),
+        before_previous: tokens.len().checked_sub(2).map(|index| tokens[index]),
+    }
+}
+
+fn type_completions(schema: &Schema, composite_only: bool, input_only: bool) -> Vec<Completion> {
+    schema
+        .user_types()
+        .filter(|schema_type| !composite_only || schema_type.kind.is_composite())
+        .filter(|schema_type| {
+            !input_only
+                || matches!(
+                    schema_type.kind,
+                    TypeKinds::Scalar | TypeKinds::Enum | TypeKinds::InputObject
+                )
+        })
+        .map(|schema_type| Completion {
+            label: schema_type.name.clone(),
+            detail: schema_type.kind.to_string(),
+        })
+        .collect()
+}
+
+fn keyword_completions(keywords: &[&str]) -> Vec<Completion> {
+    keywords
+        .iter()
+        .map(|keyword| Completion {
+            label: keyword.to_string(),
+            detail: "keyword".to_string(),
+        })
+        .collect()
+}
+
+/// Suggestions for the word the cursor is at, `offset` bytes into the query.
+pub fn complete_query(query: &str, offset: usize, schema: &Schema) -> Completions {
+    let mut offset = offset.min(query.len());
+    while !query.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &query[..offset];
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| *c == '_' || c.is_ascii_alphanumeric())
+        .last()
+        .map(|(index, _)| index)
+        .unwrap_or(offset);
+    let word = before[start..].to_lowercase();
+
+    let Some(tokens) = tokenize(&query[..start]) else {
+        return Completions {
+            start,
+            items: vec![],
+        };
+    };
+    let context = scan(&tokens, schema);
+
+    let candidates = match context.frames.last() {
+        None => match context.previous {
+            Some(Token::Name("on")) => type_completions(schema, true, false),
+            None | Some(Token::Punctuator('}')) => {
+                keyword_completions(&["query", "mutation", "subscription", "fragment"])
+            }
+            _ => vec![],
+        },
+        Some(Frame::Selection { type_name, .. }) => match context.previous {
+            Some(Token::Spread) => keyword_completions(&["on"]),
+            Some(Token::Name("on")) if context.before_previous == Some(Token::Spread) => {
+                match type_name.as_deref().and_then(|name| schema.get_type(name)) {
+                    Some(parent) if !parent.possible_types.is_empty() => parent
+                        .possible_types
+                        .iter()
+                        .map(|name| Completion {
+                            label: name.clone(),
+                            detail: schema
+                                .get_type(name)
+                                .map(|schema_type| schema_type.kind.to_string())
+                                .unwrap_or_default(),
+                        })
+                        .collect(),
+                    _ => type_completions(schema, true, false),
+                }
+            }
+            Some(Token::Punctuator('@')) => keyword_completions(&["include", "skip"]),
+            _ => {
+                let mut fields: Vec<Completion> = type_name
+                    .as_deref()
+                    .and_then(|name| schema.get_type(name))
+                    .map(|parent| {
+                        parent
+                            .fields
+                            .iter()
+                            .map(|field| Completion {
+                                label: field.name.clone(),
+                                detail: field.field_type.to_string(),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if type_name.is_some() {
+                    fields.push(Completion {
+                        label: "__typename".to_string(),
+                        detail: "String!".to_string(),
+                    });
+                }
+                fields
+            }
+        },
+        Some(Frame::Arguments {
+            type_name,
+            field,
+            argument,
+        }) => {
+            let definition = type_name
+                .as_deref()
+                .zip(field.as_deref())
+                .and_then(|(type_name, field)| schema.field(type_name, field));
+            match (definition, context.previous) {
+                // Variable definitions, where the type of a variable goes.
+                (None, Some(Token::Punctuator(':'))) if field.is_none() => {
+                    type_completions(schema, false, true)
+                }
+                (Some(definition), Some(Token::Punctuator(':'))) => {
+                    let value_type = argument.as_deref().and_then(|argument| {
+                        definition
+                            .args
+                            .iter()
+                            .find(|arg| arg.name == argument)
+                            .map(|arg| arg.value_type.named().to_string())
+                    });
+                    match value_type.as_deref() {
+                        Some("Boolean") => keyword_completions(&["true", "false"]),
+                        Some(name) => schema
+                            .get_type(name)
+                            .map(|schema_type| {
+                                schema_type
+                                    .enum_values
+                                    .iter()
+                                    .map(|value| Completion {
+                                        label: value.clone(),
+                                        detail: name.to_string(),
+                                    })
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                        None => vec![],
+                    }
+                }
+                (_, Some(Token::Punctuator('$'))) => vec![],
+                (Some(definition), _) => definition
+                    .args
+                    .iter()
+                    .map(|arg| Completion {
+                        label: arg.name.clone(),
+                        detail: arg.value_type.to_string(),
+                    })
+                    .collect(),
+                (None, _) => vec![],
+            }
+        }
+        Some(Frame::Value) => vec![],
+    };
+
+    Completions {
+        start,
+        items: candidates
+            .into_iter()
+            .filter(|completion| completion.label.to_lowercase().starts_with(&word))
+            .filter(|completion| completion.label.to_lowercase() != word)
+            .take(MAX_COMPLETIONS)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::graphql_introspection;
+
+    fn labels(query: &str) -> Vec<String> {
+        let schema = Schema::from_introspection(&graphql_introspection()).unwrap();
+        // The cursor goes where the `|` is.
+        let offset = query.find('|').unwrap();
+        let query = query.replace('|', "");
+        complete_query(&query, offset, &schema)
+            .items
+            .into_iter()
+            .map(|completion| completion.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_query() {
+        assert!(labels("|") == vec!["query", "mutation", "subscription", "fragment"]);
+        assert!(labels("{ u|") == vec!["user", "users"]);
+        assert!(labels("query { users { |") == vec!["id", "name", "role", "friends", "__typename"]);
+        assert!(labels("{ users { friends(first: 2) { na| } }") == vec!["name"]);
+        assert!(labels("mutation { c|") == vec!["createUser"]);
+        assert!(labels("{ me: user(id: 1) { r|") == vec!["role"]);
+        assert!(labels("{ users(|") == vec!["role"]);
+        assert!(labels("{ users(role: |") == vec!["ADMIN", "MEMBER"]);
+        assert!(labels("{ search(text: \"{ x\") { ... on |") == vec!["User", "Post"]);
+        assert!(labels("{ search(text: \"a\") { ... on Post { t|") == vec!["title"]);
+        assert!(labels("fragment F on U|") == vec!["User"]);
+        assert!(labels("fragment F on User { fr|") == vec!["friends"]);
+        assert!(labels("query Q($role: R|") == vec!["Role"]);
+        assert!(labels("{ users { id } }\n# us|").is_empty());
+
+        let schema = Schema::from_introspection(&graphql_introspection()).unwrap();
+        let completions = complete_query("{ us }", 4, &schema);
+        assert!(completions.start == 2);
+        assert!(completions.apply("{ us }", 4, "users") == ("{ users }".to_string(), 7));
+    }
+}
@@ -0,0 +1,199 @@
+//! GraphQL requests: the body they send, and the schema of their endpoint.
+
+use std::{error::Error, fmt};
+
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+use crate::utils::{
+    crud::requests::{HTTPMethods, RequestData},
+    executor::{
+        apply_oauth2_token, body::PreparedBody, execute_request, prepare_request, ResponseData,
+    },
+    tokio_runtime::runtime,
+};
+
+pub mod completion;
+pub mod schema;
+pub mod subscriptions;
+pub mod validation;
+
+use schema::INTROSPECTION_QUERY;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OperationKinds {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+impl fmt::Display for OperationKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OperationKinds::Query => write!(f, "query"),
+            OperationKinds::Mutation => write!(f, "mutation"),
+            OperationKinds::Subscription => write!(f, "subscription"),
+        }
+    }
+}
+
+/// The JSON body of a GraphQL request.
+///
+/// The variables are put in as typed rather than parsed, so they can hold
+/// `{{variables}}` that only make valid JSON once substituted. Empty
+/// variables and operation names are left out.
+pub fn graphql_body(query: &str, variables: &str, operation_name: &str) -> String {
+    let mut body = format!("{{\"query\":{}", Value::String(query.to_string()));
+    if !variables.trim().is_empty() {
+        body.push_str(",\"variables\":");
+        body.push_str(variables.trim());
+    }
+    if !operation_name.trim().is_empty() {
+        body.push_str(",\"operationName\":");
+        body.push_str(&Value::String(operation_name.trim().to_string()).to_string());
+    }
+    body.push('}');
+    body
+}
+
+/// What is wrong with the variables of a request, if anything.
+///
+/// Variables that use `{{variables}}` can't be checked before they are
+/// substituted, so they pass.
+pub fn variables_problem(variables: &str) -> Option<String> {
+    let variables = variables.trim();
+    if variables.is_empty() || variables.contains("{{") {
+        return None;
+    }
+    match serde_json::from_str::<Value>(variables) {
+        Ok(Value::Object(_)) => None,
+        Ok(_) => Some("Variables have to be a JSON object.".to_string()),
+        Err(error) => Some(format!("Variables aren't valid JSON: {}", error)),
+    }
+}
+
+/// The `__schema` object of an introspection response.
+fn introspection_schema(response: &ResponseData) -> Result<Value, Box<dyn Error>> {
+    let result: Value = serde_json::from_slice(&response.body).map_err(|_| {
+        format!(
+            "The endpoint answered {} {} without a GraphQL result",
+            response.status, response.status_text
+        )
+    })?;
+    match &result["data"]["__schema"] {
+        Value::Object(_) => Ok(result["data"]["__schema"].clone()),
+        _ => {
+            let errors: Vec<&str> = result["errors"]
+                .as_array()
+                .map(|errors| {
+                    errors
+                        .iter()
+                        .filter_map(|error| error["message"].as_str())
+                        .collect()
+                })
+                .unwrap_or_default();
+            match errors.is_empty() {
+                true => Err("The endpoint returned no schema".into()),
+                false => Err(format!("Introspection failed: {}", errors.join("; ")).into()),
+            }
+        }
+    }
+}
+
+/// Ask the endpoint of a request for its schema.
+///
+/// The introspection query goes out with the request's headers and auth,
+/// and isn't recorded in the history.
+pub async fn fetch_schema(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<Value, Box<dyn Error>> {
+    let mut prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    prepared.method = HTTPMethods::Post;
+    prepared.body = PreparedBody::Text(graphql_body(INTROSPECTION_QUERY, "", "IntrospectionQuery"));
+
+    let response = runtime()
+        .spawn(async move { execute_request(&prepared).await })
+        .await?
+        .map_err(|error| error.to_string())?;
+    introspection_schema(&response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                collections::create_collection,
+                graphql::{update_graphql_query, GraphQLQueryData},
+                requests::{create_request, update_request_item, ProtocolTypes},
+            },
+            executor::send_request,
+            graphql::schema::Schema,
+            test_server::spawn_graphql_server,
+        },
+    };
+
+    #[test]
+    fn test_graphql_body() {
+        assert!(graphql_body("{ users { id } }", "", "") == "{\"query\":\"{ users { id } }\"}");
+
+        let body = graphql_body(
+            "query Q($id: ID!) {\n  user(id: $id) { id }\n}",
+            " {\"id\": 1} ",
+            "Q",
+        );
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert!(parsed["query"].as_str().unwrap().contains('\n'));
+        assert!(parsed["variables"]["id"] == 1);
+        assert!(parsed["operationName"] == "Q");
+
+        assert!(variables_problem("").is_none());
+        assert!(variables_problem("{\"id\": {{user_id}}}").is_none());
+        assert!(variables_problem("[1]").unwrap().contains("JSON object"));
+        assert!(variables_problem("{\"id\": }")
+            .unwrap()
+            .starts_with("Variables aren't valid JSON"));
+    }
+
+    #[tokio::test]
+    async fn test_send_and_introspect() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let url = spawn_graphql_server().await;
+        let collection = create_collection("Graph".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::GraphQL, &collection.id, &db)
+            .await
+            .unwrap();
+        // The method stays GET as far as the request is concerned.
+        let request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::GraphQL,
+            HTTPMethods::Get,
+            &url,
+            &db,
+        )
+        .await
+        .unwrap();
+        let operation = GraphQLQueryData {
+            query: "query Q($id: ID!) { user(id: $id) { name } }".to_string(),
+            variables: "{\"id\": \"7\"}".to_string(),
+            operation_name: "Q".to_string(),
+        };
+        update_graphql_query(&request.id, &operation, &db)
+            .await
+            .unwrap();
+
+        let response = send_request(&request, &db).await.unwrap();
+        let result: Value = serde_json::from_slice(&response.body).unwrap();
+        assert!(result["data"]["received"]["query"] == operation.query.as_str());
+        assert!(result["data"]["received"]["variables"]["id"] == "7");
+        assert!(result["data"]["received"]["operationName"] == "Q");
+
+        let schema =
+            Schema::from_introspection(&fetch_schema(&request, &db).await.unwrap()).unwrap();
+        assert!(schema.get_type("User").is_some());
+    }
+}
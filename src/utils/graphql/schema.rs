@@ -0,0 +1,454 @@
+//! The schema of a GraphQL endpoint as told by introspection.
+
+use std::{error::Error, fmt};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Asks an endpoint for everything the explorer, validation and
+/// autocomplete need. Type references are followed seven levels deep,
+/// enough for `[[Type!]!]!`.
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types { ...FullType }
+  }
+}
+
+fragment FullType on __Type {
+  kind
+  name
+  description
+  fields(includeDeprecated: true) {
+    name
+    description
+    args { ...InputValue }
+    type { ...TypeRef }
+    isDeprecated
+  }
+  inputFields { ...InputValue }
+  interfaces { ...TypeRef }
+  enumValues(includeDeprecated: true) { name }
+  possibleTypes { ...TypeRef }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType {
+    kind
+    name
+    ofType {
+      kind
+      name
+      ofType {
+        kind
+        name
+        ofType {
+          kind
+          name
+          ofType {
+            kind
+            name
+            ofType {
+              kind
+              name
+              ofType {
+                kind
+                name
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}";
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TypeKinds {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+    List,
+    NonNull,
+}
+
+impl fmt::Display for TypeKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeKinds::Scalar => write!(f, "scalar"),
+            TypeKinds::Object => write!(f, "type"),
+            TypeKinds::Interface => write!(f, "interface"),
+            TypeKinds::Union => write!(f, "union"),
+            TypeKinds::Enum => write!(f, "enum"),
+            TypeKinds::InputObject => write!(f, "input"),
+            TypeKinds::List => write!(f, "list"),
+            TypeKinds::NonNull => write!(f, "non-null"),
+        }
+    }
+}
+
+impl TypeKinds {
+    /// Whether a field of this kind needs a selection of subfields.
+    pub fn is_composite(&self) -> bool {
+        matches!(
+            self,
+            TypeKinds::Object | TypeKinds::Interface | TypeKinds::Union
+        )
+    }
+}
+
+/// A reference to a type, wrapped in lists and non-null markers.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeRef {
+    Named(String),
+    List(Box<TypeRef>),
+    NonNull(Box<TypeRef>),
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeRef::Named(name) => write!(f, "{}", name),
+            TypeRef::List(inner) => write!(f, "[{}]", inner),
+            TypeRef::NonNull(inner) => write!(f, "{}!", inner),
+        }
+    }
+}
+
+impl TypeRef {
+    /// The type inside the wrappers.
+    pub fn named(&self) -> &str {
+        match self {
+            TypeRef::Named(name) => name,
+            TypeRef::List(inner) | TypeRef::NonNull(inner) => inner.named(),
+        }
+    }
+
+    pub fn is_non_null(&self) -> bool {
+        matches!(self, TypeRef::NonNull(_))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InputValue {
+    pub name: String,
+    pub description: String,
+    pub value_type: TypeRef,
+    pub default_value: Option<String>,
+}
+
+impl InputValue {
+    /// Whether leaving the argument out is an error.
+    pub fn is_required(&self) -> bool {
+        self.value_type.is_non_null() && self.default_value.is_none()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaField {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<InputValue>,
+    pub field_type: TypeRef,
+    pub deprecated: bool,
+}
+
+impl SchemaField {
+    /// The field as it reads in SDL, like `user(id: ID!): User`.
+    pub fn signature(&self) -> String {
+        if self.args.is_empty() {
+            return format!("{}: {}", self.name, self.field_type);
+        }
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| format!("{}: {}", arg.name, arg.value_type))
+            .collect();
+        format!("{}({}): {}", self.name, args.join(", "), self.field_type)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaType {
+    pub kind: TypeKinds,
+    pub name: String,
+    pub description: String,
+    pub fields: Vec<SchemaField>,
+    pub input_fields: Vec<InputValue>,
+    pub interfaces: Vec<String>,
+    pub enum_values: Vec<String>,
+    pub possible_types: Vec<String>,
+}
+
+impl SchemaType {
+    pub fn field(&self, name: &str) -> Option<&SchemaField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub query_type: Option<String>,
+    pub mutation_type: Option<String>,
+    pub subscription_type: Option<String>,
+    pub types: Vec<SchemaType>,
+}
+
+// What introspection returns, before it is turned into the types above.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSchema {
+    query_type: Option<RawName>,
+    mutation_type: Option<RawName>,
+    subscription_type: Option<RawName>,
+    types: Vec<RawType>,
+}
+
+#[derive(Deserialize)]
+struct RawName {
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawType {
+    kind: TypeKinds,
+    name: Option<String>,
+    description: Option<String>,
+    fields: Option<Vec<RawField>>,
+    input_fields: Option<Vec<RawInputValue>>,
+    interfaces: Option<Vec<RawTypeRef>>,
+    enum_values: Option<Vec<RawName>>,
+    possible_types: Option<Vec<RawTypeRef>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawField {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    args: Vec<RawInputValue>,
+    #[serde(rename = "type")]
+    field_type: RawTypeRef,
+    #[serde(default)]
+    is_deprecated: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawInputValue {
+    name: String,
+    description: Option<String>,
+    #[serde(rename = "type")]
+    value_type: RawTypeRef,
+    default_value: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTypeRef {
+    kind: TypeKinds,
+    name: Option<String>,
+    of_type: Option<Box<RawTypeRef>>,
+}
+
+impl RawTypeRef {
+    fn into_type_ref(self) -> Result<TypeRef, Box<dyn Error>> {
+        let wrapped = |of_type: Option<Box<RawTypeRef>>| match of_type {
+            Some(inner) => inner.into_type_ref().map(Box::new),
+            None => Err("A wrapping type is missing the type it wraps".into()),
+        };
+        match self.kind {
+            TypeKinds::List => Ok(TypeRef::List(wrapped(self.of_type)?)),
+            TypeKinds::NonNull => Ok(TypeRef::NonNull(wrapped(self.of_type)?)),
+            _ => Ok(TypeRef::Named(
+                self.name.ok_or("A named type is missing its name")?,
+            )),
+        }
+    }
+}
+
+impl RawInputValue {
+    fn into_input_value(self) -> Result<InputValue, Box<dyn Error>> {
+        Ok(InputValue {
+            name: self.name,
+            description: self.description.unwrap_or_default(),
+            value_type: self.value_type.into_type_ref()?,
+            default_value: self.default_value,
+        })
+    }
+}
+
+impl Schema {
+    /// Read the `__schema` object of an introspection result.
+    pub fn from_introspection(schema: &Value) -> Result<Schema, Box<dyn Error>> {
+        let raw = RawSchema::deserialize(schema)?;
+        let mut types = Vec::new();
+        for raw_type in raw.types {
+            let Some(name) = raw_type.name else {
+                continue;
+            };
+            let fields = raw_type
+                .fields
+                .unwrap_or_default()
+                .into_iter()
+                .map(|field| {
+                    Ok(SchemaField {
+                        name: field.name,
+                        description: field.description.unwrap_or_default(),
+                        args: field
+                            .args
+                            .into_iter()
+                            .map(RawInputValue::into_input_value)
+                            .collect::<Result<_, Box<dyn Error>>>()?,
+                        field_type: field.field_type.into_type_ref()?,
+                        deprecated: field.is_deprecated,
+                    })
+                })
+                .collect::<Result<_, Box<dyn Error>>>()?;
+            let names = |refs: Option<Vec<RawTypeRef>>| -> Vec<String> {
+                refs.unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|type_ref| type_ref.name)
+                    .collect()
+            };
+
+            types.push(SchemaType {
+                kind: raw_type.kind,
+                name,
+                description: raw_type.description.unwrap_or_default(),
+                fields,
+                input_fields: raw_type
+                    .input_fields
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(RawInputValue::into_input_value)
+                    .collect::<Result<_, Box<dyn Error>>>()?,
+                interfaces: names(raw_type.interfaces),
+                enum_values: raw_type
+                    .enum_values
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|value| value.name)
+                    .collect(),
+                possible_types: names(raw_type.possible_types),
+            });
+        }
+
+        Ok(Schema {
+            query_type: raw.query_type.map(|name| name.name),
+            mutation_type: raw.mutation_type.map(|name| name.name),
+            subscription_type: raw.subscription_type.map(|name| name.name),
+            types,
+        })
+    }
+
+    pub fn get_type(&self, name: &str) -> Option<&SchemaType> {
+        self.types
+            .iter()
+            .find(|schema_type| schema_type.name == name)
+    }
+
+    /// Types the schema defines itself, leaving out the introspection ones.
+    pub fn user_types(&self) -> impl Iterator<Item = &SchemaType> {
+        self.types
+            .iter()
+            .filter(|schema_type| !schema_type.name.starts_with("__"))
+    }
+
+    /// The field of a type, including the meta fields every selection and
+    /// the query root have.
+    pub fn field(&self, type_name: &str, field_name: &str) -> Option<SchemaField> {
+        let meta_type = match field_name {
+            "__typename" => Some(TypeRef::NonNull(Box::new(TypeRef::Named(
+                "String".to_string(),
+            )))),
+            "__schema" if self.query_type.as_deref() == Some(type_name) => Some(TypeRef::NonNull(
+                Box::new(TypeRef::Named("__Schema".to_string())),
+            )),
+            "__type" if self.query_type.as_deref() == Some(type_name) => {
+                Some(TypeRef::Named("__Type".to_string()))
+            }
+            _ => None,
+        };
+        if let Some(field_type) = meta_type {
+            let args = match field_name {
+                "__type" => vec![InputValue {
+                    name: "name".to_string(),
+                    description: String::new(),
+                    value_type: TypeRef::NonNull(Box::new(TypeRef::Named("String".to_string()))),
+                    default_value: None,
+                }],
+                _ => vec![],
+            };
+            return Some(SchemaField {
+                name: field_name.to_string(),
+                description: String::new(),
+                args,
+                field_type,
+                deprecated: false,
+            });
+        }
+
+        self.get_type(type_name)?.field(field_name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::graphql_introspection;
+
+    #[test]
+    fn test_from_introspection() {
+        let schema = Schema::from_introspection(&graphql_introspection()).unwrap();
+
+        assert!(schema.query_type.as_deref() == Some("Query"));
+        assert!(schema.subscription_type.as_deref() == Some("Subscription"));
+        assert!(schema
+            .user_types()
+            .all(|schema_type| schema_type.name != "__Schema"));
+
+        let users = schema.field("Query", "users").unwrap();
+        assert!(users.field_type.to_string() == "[User!]!");
+        assert!(users.field_type.named() == "User");
+        assert!(users.signature() == "users(role: Role): [User!]!");
+        assert!(schema.field("Query", "user").unwrap().args[0].is_required());
+
+        let user = schema.get_type("User").unwrap();
+        assert!(user.kind == TypeKinds::Object && user.interfaces == vec!["Node".to_string()]);
+        assert!(
+            schema
+                .get_type("SearchResult")
+                .unwrap()
+                .possible_types
+                .len()
+                == 2
+        );
+        assert!(schema.get_type("Role").unwrap().enum_values == vec!["ADMIN", "MEMBER"]);
+        assert!(schema.get_type("UserInput").unwrap().input_fields[0].name == "name");
+
+        // Meta fields aren't listed by introspection but can always be selected.
+        assert!(schema.field("User", "__typename").is_some());
+        assert!(schema.field("Query", "__schema").is_some());
+        assert!(schema.field("User", "__schema").is_none());
+
+        assert!(Schema::from_introspection(&serde_json::json!({"types": 1})).is_err());
+    }
+}
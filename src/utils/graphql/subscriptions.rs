@@ -0,0 +1,153 @@
+//! Subscriptions over WebSocket, spoken in the graphql-transport-ws protocol.
+
+use std::error::Error;
+
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::utils::{
+    crud::requests::RequestData,
+    executor::{apply_oauth2_token, body::PreparedBody, prepare_request},
+    websocket::Handshake,
+};
+
+/// The subprotocol of the graphql-ws library, offered in the handshake.
+pub const GRAPHQL_WS_PROTOCOL: &str = "graphql-transport-ws";
+
+/// What a server sends over a subscription connection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessages {
+    ConnectionAck,
+    /// A result of the subscription with the given id.
+    Next {
+        id: String,
+        payload: Value,
+    },
+    /// The subscription failed; the payload lists the errors.
+    Error {
+        id: String,
+        payload: Value,
+    },
+    Complete {
+        id: String,
+    },
+    Ping,
+    Pong,
+    /// Anything the protocol doesn't define.
+    Unknown(String),
+}
+
+impl ServerMessages {
+    pub fn parse(text: &str) -> ServerMessages {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return ServerMessages::Unknown(text.to_string());
+        };
+        let id = message["id"].as_str().unwrap_or_default().to_string();
+        match message["type"].as_str() {
+            Some("connection_ack") => ServerMessages::ConnectionAck,
+            Some("next") => ServerMessages::Next {
+                id,
+                payload: message["payload"].clone(),
+            },
+            Some("error") => ServerMessages::Error {
+                id,
+                payload: message["payload"].clone(),
+            },
+            Some("complete") => ServerMessages::Complete { id },
+            Some("ping") => ServerMessages::Ping,
+            Some("pong") => ServerMessages::Pong,
+            _ => ServerMessages::Unknown(text.to_string()),
+        }
+    }
+}
+
+/// The first message of a connection. Nothing may be subscribed to before
+/// the server acknowledges it.
+pub fn connection_init() -> Message {
+    Message::text(json!({"type": "connection_init", "payload": {}}).to_string())
+}
+
+/// Start the operation in a GraphQL request body under the given id.
+pub fn subscribe(id: &str, body: &str) -> Result<Message, Box<dyn Error>> {
+    let payload: Value = serde_json::from_str(body)
+        .map_err(|error| format!("The operation isn't valid JSON: {}", error))?;
+    Ok(Message::text(
+        json!({"id": id, "type": "subscribe", "payload": payload}).to_string(),
+    ))
+}
+
+pub fn complete(id: &str) -> Message {
+    Message::text(json!({"id": id, "type": "complete"}).to_string())
+}
+
+pub fn pong() -> Message {
+    Message::text(json!({"type": "pong"}).to_string())
+}
+
+/// The handshake and the body of a subscription, with the request's
+/// headers, auth and variables applied just as for a query.
+pub async fn prepare_subscription(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<(Handshake, String), Box<dyn Error>> {
+    let prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    let handshake = Handshake::for_request(&prepared, vec![GRAPHQL_WS_PROTOCOL.to_string()])?;
+    let body = match prepared.body {
+        PreparedBody::Text(body) => body,
+        _ => return Err("The request has no operation to subscribe to".into()),
+    };
+
+    Ok((handshake, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        graphql::graphql_body,
+        test_server::spawn_graphql_ws_server,
+        websocket::{connect, Directions, FrameKinds},
+    };
+
+    #[tokio::test]
+    async fn test_subscription() {
+        let url = spawn_graphql_ws_server().await;
+        let handshake = Handshake {
+            url,
+            headers: vec![],
+            subprotocols: vec![GRAPHQL_WS_PROTOCOL.to_string()],
+        };
+        let mut connection = connect(handshake).await.unwrap();
+        assert!(connection.protocol == GRAPHQL_WS_PROTOCOL);
+        connection.session.send(connection_init()).unwrap();
+
+        let body = graphql_body("subscription { userAdded { id } }", "", "");
+        let mut counts = Vec::new();
+        while let Some(entry) = connection.log.recv().await {
+            if entry.direction != Directions::Received || entry.kind != FrameKinds::Text {
+                continue;
+            }
+            match ServerMessages::parse(&entry.data) {
+                ServerMessages::ConnectionAck => connection
+                    .session
+                    .send(subscribe("1", &body).unwrap())
+                    .unwrap(),
+                ServerMessages::Next { id, payload } => {
+                    assert!(id == "1");
+                    counts.push(payload["data"]["count"].as_i64().unwrap());
+                }
+                ServerMessages::Complete { id } => {
+                    assert!(id == "1");
+                    connection.session.close();
+                }
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+        assert!(counts == vec![1, 2]);
+
+        assert!(subscribe("1", "{").is_err());
+        assert!(ServerMessages::parse("{\"type\":\"ping\"}") == ServerMessages::Ping);
+        assert!(ServerMessages::parse("hello") == ServerMessages::Unknown("hello".to_string()));
+    }
+}
@@ -0,0 +1,520 @@
+//! Checking a query against the schema of its endpoint before it is sent.
+
+use std::{collections::HashSet, fmt};
+
+use graphql_parser::{
+    parse_query,
+    query::{
+        Definition, Directive, Document, FragmentDefinition, OperationDefinition, Selection,
+        SelectionSet, TypeCondition, Value,
+    },
+    Pos,
+};
+
+use super::{schema::Schema, OperationKinds};
+
+/// Something wrong with a query, and where it is. Lines and columns start at 1.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryProblem {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{} {}", self.line, self.column, self.message)
+    }
+}
+
+impl QueryProblem {
+    fn at(position: Pos, message: String) -> QueryProblem {
+        QueryProblem {
+            line: position.line,
+            column: position.column,
+            message,
+        }
+    }
+}
+
+type Doc<'a> = Document<'a, &'a str>;
+
+/// Turn the error of the parser into a problem at the position it names.
+fn syntax_problem(error: &str) -> QueryProblem {
+    // Reads like "query parse error: Parse error at 2:5\nUnexpected ...".
+    let (position, details) = error
+        .split_once("Parse error at ")
+        .map(|(_, rest)| rest.split_once('\n').unwrap_or((rest, "")))
+        .unwrap_or(("1:1", error));
+    let (line, column) = position.trim().split_once(':').unwrap_or(("1", "1"));
+    let details: Vec<&str> = details
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    QueryProblem {
+        line: line.parse().unwrap_or(1),
+        column: column.parse().unwrap_or(1),
+        message: format!("Syntax error: {}", details.join(", ")),
+    }
+}
+
+/// Every problem with a query, in the order they appear.
+///
+/// Without a schema only the syntax and the choice of operation can be
+/// checked. An empty query has no problems; it just can't be sent.
+pub fn validate_query(
+    query: &str,
+    operation_name: &str,
+    schema: Option<&Schema>,
+) -> Vec<QueryProblem> {
+    if query.trim().is_empty() {
+        return vec![];
+    }
+    let document: Doc = match parse_query(query) {
+        Ok(document) => document,
+        Err(error) => return vec![syntax_problem(&error.to_string())],
+    };
+
+    let mut problems = Vec::new();
+    let start = Pos { line: 1, column: 1 };
+    let operations: Vec<&OperationDefinition<&str>> = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Operation(operation) => Some(operation),
+            Definition::Fragment(_) => None,
+        })
+        .collect();
+    let operation_name = operation_name.trim();
+    if operations.is_empty() {
+        problems.push(QueryProblem::at(
+            start,
+            "The document has no operation to run.".to_string(),
+        ));
+    } else if operation_name.is_empty() && operations.len() > 1 {
+        problems.push(QueryProblem::at(
+            start,
+            "The document has several operations. Pick one with the operation name.".to_string(),
+        ));
+    } else if !operation_name.is_empty()
+        && !operations
+            .iter()
+            .any(|operation| operation_parts(operation).1 == Some(operation_name))
+    {
+        problems.push(QueryProblem::at(
+            start,
+            format!("There is no operation named \"{}\".", operation_name),
+        ));
+    }
+
+    let Some(schema) = schema else {
+        return problems;
+    };
+    let validator = Validator {
+        schema,
+        fragments: document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some(fragment),
+                Definition::Operation(_) => None,
+            })
+            .collect(),
+    };
+
+    for definition in &document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                validator.operation(operation, &mut problems);
+            }
+            Definition::Fragment(fragment) => validator.fragment(fragment, &mut problems),
+        }
+    }
+    problems.sort_by_key(|problem| (problem.line, problem.column));
+    problems
+}
+
+/// The kind, name, position and selection of an operation.
+fn operation_parts<'a>(
+    operation: &'a OperationDefinition<'a, &'a str>,
+) -> (
+    OperationKinds,
+    Option<&'a str>,
+    Pos,
+    &'a SelectionSet<'a, &'a str>,
+) {
+    match operation {
+        OperationDefinition::SelectionSet(selection_set) => (
+            OperationKinds::Query,
+            None,
+            selection_set.span.0,
+            selection_set,
+        ),
+        OperationDefinition::Query(query) => (
+            OperationKinds::Query,
+            query.name,
+            query.position,
+            &query.selection_set,
+        ),
+        OperationDefinition::Mutation(mutation) => (
+            OperationKinds::Mutation,
+            mutation.name,
+            mutation.position,
+            &mutation.selection_set,
+        ),
+        OperationDefinition::Subscription(subscription) => (
+            OperationKinds::Subscription,
+            subscription.name,
+            subscription.position,
+            &subscription.selection_set,
+        ),
+    }
+}
+
+/// The operation to run: the one named, or the only one there is.
+pub fn find_operation<'a>(
+    document: &'a Doc<'a>,
+    operation_name: &str,
+) -> Option<&'a OperationDefinition<'a, &'a str>> {
+    let operation_name = operation_name.trim();
+    let mut operations = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Operation(operation) => Some(operation),
+            Definition::Fragment(_) => None,
+        });
+    match operation_name.is_empty() {
+        true => operations.next(),
+        false => operations.find(|operation| operation_parts(operation).1 == Some(operation_name)),
+    }
+}
+
+/// What kind of operation a query runs, `None` when it can't be told.
+pub fn operation_kind(query: &str, operation_name: &str) -> Option<OperationKinds> {
+    let document: Doc = parse_query(query).ok()?;
+    find_operation(&document, operation_name).map(|operation| operation_parts(operation).0)
+}
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    fragments: Vec<&'a FragmentDefinition<'a, &'a str>>,
+}
+
+impl<'a> Validator<'a> {
+    fn operation(
+        &self,
+        operation: &'a OperationDefinition<'a, &'a str>,
+        problems: &mut Vec<QueryProblem>,
+    ) {
+        let (kind, name, position, selection_set) = operation_parts(operation);
+        let root = match kind {
+            OperationKinds::Query => &self.schema.query_type,
+            OperationKinds::Mutation => &self.schema.mutation_type,
+            OperationKinds::Subscription => &self.schema.subscription_type,
+        };
+        let Some(root) = root else {
+            problems.push(QueryProblem::at(
+                position,
+                format!("The schema doesn't support {} operations.", kind),
+            ));
+            return;
+        };
+
+        let anonymous = Vec::new();
+        let defined: HashSet<&str> = match operation {
+            OperationDefinition::Query(query) => &query.variable_definitions,
+            OperationDefinition::Mutation(mutation) => &mutation.variable_definitions,
+            OperationDefinition::Subscription(subscription) => &subscription.variable_definitions,
+            OperationDefinition::SelectionSet(_) => &anonymous,
+        }
+        .iter()
+        .map(|definition| definition.name)
+        .collect();
+        let scope = VariableScope {
+            defined: Some(&defined),
+            operation: name.unwrap_or("anonymous"),
+        };
+        self.selection_set(selection_set, root, &scope, problems);
+    }
+
+    fn fragment(
+        &self,
+        fragment: &'a FragmentDefinition<'a, &'a str>,
+        problems: &mut Vec<QueryProblem>,
+    ) {
+        let TypeCondition::On(type_name) = fragment.type_condition;
+        if !self.check_condition(type_name, fragment.position, problems) {
+            return;
+        }
+        // Which operation defines the variables a fragment uses depends on
+        // where it is spread, so they aren't checked here.
+        let scope = VariableScope {
+            defined: None,
+            operation: "",
+        };
+        self.selection_set(&fragment.selection_set, type_name, &scope, problems);
+    }
+
+    /// Whether a fragment can be applied to the type it names.
+    fn check_condition(
+        &self,
+        type_name: &str,
+        position: Pos,
+        problems: &mut Vec<QueryProblem>,
+    ) -> bool {
+        match self.schema.get_type(type_name) {
+            Some(schema_type) if schema_type.kind.is_composite() => true,
+            Some(_) => {
+                problems.push(QueryProblem::at(
+                    position,
+                    format!("Fragments can't be applied to \"{}\".", type_name),
+                ));
+                false
+            }
+            None => {
+                problems.push(QueryProblem::at(
+                    position,
+                    format!("Unknown type \"{}\".", type_name),
+                ));
+                false
+            }
+        }
+    }
+
+    fn selection_set(
+        &self,
+        selection_set: &'a SelectionSet<'a, &'a str>,
+        parent: &str,
+        scope: &VariableScope,
+        problems: &mut Vec<QueryProblem>,
+    ) {
+        for selection in &selection_set.items {
+            match selection {
+                Selection::Field(field) => {
+                    scope.check_directives(&field.directives, problems);
+                    let Some(definition) = self.schema.field(parent, field.name) else {
+                        problems.push(QueryProblem::at(
+                            field.position,
+                            format!(
+                                "Cannot query field \"{}\" on type \"{}\".",
+                                field.name, parent
+                            ),
+                        ));
+                        continue;
+                    };
+
+                    for (argument, value) in &field.arguments {
+                        if !definition.args.iter().any(|arg| arg.name == *argument) {
+                            problems.push(QueryProblem::at(
+                                field.position,
+                                format!(
+                                    "Unknown argument \"{}\" on field \"{}.{}\".",
+                                    argument, parent, field.name
+                                ),
+                            ));
+                        }
+                        scope.check_value(value, field.position, problems);
+                    }
+                    for arg in definition.args.iter().filter(|arg| arg.is_required()) {
+                        if !field.arguments.iter().any(|(name, _)| *name == arg.name) {
+                            problems.push(QueryProblem::at(
+                                field.position,
+                                format!(
+                                    "Field \"{}\" is missing the required argument \"{}\".",
+                                    field.name, arg.name
+                                ),
+                            ));
+                        }
+                    }
+
+                    let type_name = definition.field_type.named();
+                    let Some(field_type) = self.schema.get_type(type_name) else {
+                        continue;
+                    };
+                    let has_selection = !field.selection_set.items.is_empty();
+                    if field_type.kind.is_composite() && !has_selection {
+                        problems.push(QueryProblem::at(
+                            field.position,
+                            format!(
+                                "Field \"{}\" of type \"{}\" needs a selection of subfields.",
+                                field.name, definition.field_type
+                            ),
+                        ));
+                    } else if !field_type.kind.is_composite() && has_selection {
+                        problems.push(QueryProblem::at(
+                            field.position,
+                            format!(
+                                "Field \"{}\" of type \"{}\" can't have a selection of subfields.",
+                                field.name, definition.field_type
+                            ),
+                        ));
+                    } else if has_selection {
+                        self.selection_set(&field.selection_set, type_name, scope, problems);
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    scope.check_directives(&spread.directives, problems);
+                    if !self
+                        .fragments
+                        .iter()
+                        .any(|fragment| fragment.name == spread.fragment_name)
+                    {
+                        problems.push(QueryProblem::at(
+                            spread.position,
+                            format!("Unknown fragment \"{}\".", spread.fragment_name),
+                        ));
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    scope.check_directives(&fragment.directives, problems);
+                    let type_name = match fragment.type_condition {
+                        Some(TypeCondition::On(type_name)) => {
+                            if !self.check_condition(type_name, fragment.position, problems) {
+                                continue;
+                            }
+                            type_name
+                        }
+                        None => parent,
+                    };
+                    self.selection_set(&fragment.selection_set, type_name, scope, problems);
+                }
+            }
+        }
+    }
+}
+
+/// The variables an operation defines, for checking the ones it uses.
+struct VariableScope<'a> {
+    /// `None` when it isn't known which operation the selection belongs to.
+    defined: Option<&'a HashSet<&'a str>>,
+    operation: &'a str,
+}
+
+impl VariableScope<'_> {
+    fn check_value<'v>(
+        &self,
+        value: &Value<'v, &'v str>,
+        position: Pos,
+        problems: &mut Vec<QueryProblem>,
+    ) {
+        let Some(defined) = self.defined else {
+            return;
+        };
+        match value {
+            Value::Variable(name) if !defined.contains(name) => {
+                problems.push(QueryProblem::at(
+                    position,
+                    format!(
+                        "Variable \"${}\" is not defined by operation \"{}\".",
+                        name, self.operation
+                    ),
+                ));
+            }
+            Value::List(values) => {
+                for value in values {
+                    self.check_value(value, position, problems);
+                }
+            }
+            Value::Object(fields) => {
+                for value in fields.values() {
+                    self.check_value(value, position, problems);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_directives<'v>(
+        &self,
+        directives: &[Directive<'v, &'v str>],
+        problems: &mut Vec<QueryProblem>,
+    ) {
+        for directive in directives {
+            for (_, value) in &directive.arguments {
+                self.check_value(value, directive.position, problems);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_server::graphql_introspection;
+
+    fn messages(query: &str, operation_name: &str) -> Vec<String> {
+        let schema = Schema::from_introspection(&graphql_introspection()).unwrap();
+        validate_query(query, operation_name, Some(&schema))
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_query() {
+        assert!(messages("", "").is_empty());
+        assert!(messages(
+            "query Users($role: Role) { users(role: $role) { id name ...Bits } }
+             fragment Bits on User { friends(first: 2) { __typename } }",
+            "",
+        )
+        .is_empty());
+        assert!(messages(
+            "{ search(text: \"a\") { ... on User { name } ... on Post { title } } }",
+            ""
+        )
+        .is_empty());
+
+        assert!(messages("{ users { id", "")[0].starts_with("1:13 Syntax error"));
+        assert!(
+            messages("{ users { email } }", "")
+                == vec!["1:11 Cannot query field \"email\" on type \"User\"."]
+        );
+        assert!(
+            messages("{ user { id } }", "")
+                == vec!["1:3 Field \"user\" is missing the required argument \"id\"."]
+        );
+        assert!(
+            messages("{ user(id: 1, limit: 2) { id } }", "")
+                == vec!["1:3 Unknown argument \"limit\" on field \"Query.user\"."]
+        );
+        assert!(
+            messages("{ users }", "")
+                == vec!["1:3 Field \"users\" of type \"[User!]!\" needs a selection of subfields."]
+        );
+        assert!(
+            messages("{ users { name { first } } }", "")
+                == vec![
+                    "1:11 Field \"name\" of type \"String\" can't have a selection of subfields."
+                ]
+        );
+        assert!(
+            messages("query Q { user(id: $id) { id } }", "")
+                == vec!["1:11 Variable \"$id\" is not defined by operation \"Q\"."]
+        );
+        assert!(
+            messages("{ users { ...Missing ... on Robot { id } } }", "")
+                == vec![
+                    "1:14 Unknown fragment \"Missing\".",
+                    "1:26 Unknown type \"Robot\".",
+                ]
+        );
+
+        let two = "query A { users { id } } query B { users { name } }";
+        assert!(messages(two, "")[0].contains("several operations"));
+        assert!(messages(two, "B").is_empty());
+        assert!(messages(two, "C") == vec!["1:1 There is no operation named \"C\"."]);
+
+        // Without a schema only the document itself is checked.
+        assert!(validate_query("{ anything { goes } }", "", None).is_empty());
+        assert!(
+            operation_kind("subscription { userAdded { id } }", "")
+                == Some(OperationKinds::Subscription)
+        );
+        assert!(operation_kind(two, "B") == Some(OperationKinds::Query));
+        assert!(operation_kind("{", "").is_none());
+    }
+}
@@ -0,0 +1,403 @@
+//! Calls in progress: the messages going both ways, the metadata around
+//! them and the status they end with.
+
+use std::{error::Error, fmt};
+
+use prost_reflect::{DynamicMessage, MessageDescriptor, MethodDescriptor};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+use tonic::{
+    client::Grpc, codegen::http::uri::PathAndQuery, metadata::MetadataMap, transport::Channel,
+    Code, Request, Status,
+};
+
+use super::{
+    code_name, codec::DynamicCodec, connect_channel, message_json, metadata_pairs, method_name,
+    parse_message, to_metadata, CallKinds, PreparedCall,
+};
+use crate::utils::{
+    errors::error_chain,
+    tokio_runtime::runtime,
+    websocket::{Directions, LogEntry},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKinds {
+    Message,
+    Headers,
+    Trailers,
+    /// How the call ended, when it went well.
+    Status,
+    /// How the call ended, when it failed.
+    Error,
+}
+
+impl fmt::Display for EntryKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryKinds::Message => write!(f, "message"),
+            EntryKinds::Headers => write!(f, "headers"),
+            EntryKinds::Trailers => write!(f, "trailers"),
+            EntryKinds::Status => write!(f, "status"),
+            EntryKinds::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A line of the call log. Messages are logged as JSON, metadata as a
+/// `name: value` line per entry.
+pub type CallEntry = LogEntry<EntryKinds>;
+
+impl CallEntry {
+    fn for_status(status: &Status) -> CallEntry {
+        let mut data = code_name(status.code()).to_string();
+        if !status.message().is_empty() {
+            data = format!("{}: {}", data, status.message());
+        }
+        let kind = match status.code() {
+            Code::Ok => EntryKinds::Status,
+            _ => EntryKinds::Error,
+        };
+        CallEntry::new(Directions::Info, kind, &data)
+    }
+
+    fn for_metadata(kind: EntryKinds, metadata: &MetadataMap) -> Option<CallEntry> {
+        let lines: Vec<String> = metadata_pairs(metadata)
+            .into_iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        match lines.is_empty() {
+            true => None,
+            false => Some(CallEntry::new(
+                Directions::Received,
+                kind,
+                &lines.join("\n"),
+            )),
+        }
+    }
+
+    /// Whether the entry is the last one of its call.
+    pub fn ends_call(&self) -> bool {
+        matches!(self.kind, EntryKinds::Status | EntryKinds::Error)
+    }
+}
+
+/// A call in progress. Dropping it cancels the call.
+pub struct CallSession {
+    /// First, so it is dropped before the messages close.
+    cancel: Option<oneshot::Sender<()>>,
+    /// Gone once the client is done sending.
+    messages: Option<UnboundedSender<DynamicMessage>>,
+    log: UnboundedSender<CallEntry>,
+    input: MessageDescriptor,
+}
+
+impl CallSession {
+    /// Send a message, written as JSON.
+    pub fn send(&self, json: &str) -> Result<(), Box<dyn Error>> {
+        let message = parse_message(self.input.clone(), json)?;
+        self.send_message(message)
+    }
+
+    fn send_message(&self, message: DynamicMessage) -> Result<(), Box<dyn Error>> {
+        let messages = self
+            .messages
+            .as_ref()
+            .filter(|messages| !messages.is_closed())
+            .ok_or("The call takes no more messages.")?;
+        let _ = self.log.send(CallEntry::new(
+            Directions::Sent,
+            EntryKinds::Message,
+            &message_json(&message),
+        ));
+        messages
+            .send(message)
+            .map_err(|_| "The call takes no more messages.".into())
+    }
+
+    /// Close the client's side of the call. Servers of client streaming
+    /// calls answer once they see it.
+    pub fn finish(&mut self) {
+        self.messages = None;
+    }
+
+    /// Whether messages can still be sent.
+    pub fn is_sending(&self) -> bool {
+        self.messages
+            .as_ref()
+            .is_some_and(|messages| !messages.is_closed())
+    }
+
+    /// Stop the call; it ends with a CANCELLED status.
+    pub fn cancel(&mut self) {
+        // Signalled before the messages close, or the server could end the
+        // call normally first.
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+        self.messages = None;
+    }
+}
+
+/// A session and the log entries it produces, ending with the status of
+/// the call.
+pub struct Call {
+    pub session: CallSession,
+    pub log: UnboundedReceiver<CallEntry>,
+}
+
+/// Start calling a method.
+///
+/// Unary and server streaming calls send the prepared message right away.
+/// Client and bidi streaming calls start without one; their messages are
+/// sent through the session. The call runs on the runtime, so only the log
+/// is awaited on the UI side.
+pub async fn start_call(
+    method: MethodDescriptor,
+    prepared: PreparedCall,
+) -> Result<Call, Box<dyn Error>> {
+    let metadata = to_metadata(&prepared.metadata)?;
+    let first = match CallKinds::of(&method).streams_requests() {
+        true => None,
+        false => Some(parse_message(method.input(), &prepared.message)?),
+    };
+    let path = PathAndQuery::try_from(format!("/{}", method_name(&method)))?;
+    let url = prepared.url;
+    let channel = runtime()
+        .spawn(async move { connect_channel(&url).await })
+        .await?
+        .map_err(|error| error.to_string())?;
+
+    let (messages, queued) = unbounded_channel();
+    let (log, entries) = unbounded_channel();
+    let (cancel, cancelled) = oneshot::channel();
+    let codec = DynamicCodec::new(method.output());
+    let call_log = log.clone();
+    runtime().spawn(async move {
+        let status = tokio::select! {
+            biased;
+            // A dropped session cancels the call too.
+            _ = cancelled => Status::cancelled("Cancelled by the client"),
+            status = run_call(channel, metadata, path, codec, queued, &call_log) => status,
+        };
+        let _ = call_log.send(CallEntry::for_status(&status));
+    });
+
+    let mut session = CallSession {
+        cancel: Some(cancel),
+        messages: Some(messages),
+        log,
+        input: method.input(),
+    };
+    if let Some(first) = first {
+        session.send_message(first)?;
+        session.finish();
+    }
+
+    Ok(Call {
+        session,
+        log: entries,
+    })
+}
+
+async fn run_call(
+    channel: Channel,
+    metadata: MetadataMap,
+    path: PathAndQuery,
+    codec: DynamicCodec,
+    mut queued: UnboundedReceiver<DynamicMessage>,
+    log: &UnboundedSender<CallEntry>,
+) -> Status {
+    let requests = futures_util::stream::poll_fn(move |cx| queued.poll_recv(cx));
+    let mut request = Request::new(requests);
+    *request.metadata_mut() = metadata;
+
+    let mut client = Grpc::new(channel);
+    if let Err(error) = client.ready().await {
+        return Status::unavailable(error_chain(&error));
+    }
+    // Failing calls may be answered with trailers alone.
+    let response = match client.streaming(request, path, codec).await {
+        Ok(response) => response,
+        Err(status) => {
+            if let Some(entry) = CallEntry::for_metadata(EntryKinds::Trailers, status.metadata()) {
+                let _ = log.send(entry);
+            }
+            return status;
+        }
+    };
+    if let Some(entry) = CallEntry::for_metadata(EntryKinds::Headers, response.metadata()) {
+        let _ = log.send(entry);
+    }
+
+    let mut stream = response.into_inner();
+    loop {
+        match stream.message().await {
+            Ok(Some(message)) => {
+                let _ = log.send(CallEntry::new(
+                    Directions::Received,
+                    EntryKinds::Message,
+                    &message_json(&message),
+                ));
+            }
+            Ok(None) => break,
+            Err(status) => {
+                if let Some(entry) =
+                    CallEntry::for_metadata(EntryKinds::Trailers, status.metadata())
+                {
+                    let _ = log.send(entry);
+                }
+                return status;
+            }
+        }
+    }
+    match stream.trailers().await {
+        Ok(trailers) => {
+            if let Some(entry) = trailers
+                .and_then(|trailers| CallEntry::for_metadata(EntryKinds::Trailers, &trailers))
+            {
+                let _ = log.send(entry);
+            }
+            Status::ok("")
+        }
+        Err(status) => status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        grpc::{compile_protos, find_method},
+        test_server::{spawn_grpc_server, write_echo_protos},
+    };
+    use prost_reflect::DescriptorPool;
+    use serde_json::Value;
+
+    fn echo_services() -> DescriptorPool {
+        let (file, include) = write_echo_protos();
+        compile_protos(&[file], &[include]).unwrap()
+    }
+
+    fn prepared(url: &str, message: &str) -> PreparedCall {
+        PreparedCall {
+            url: url.to_string(),
+            metadata: vec![("x-echo".to_string(), "hi".to_string())],
+            message: message.to_string(),
+        }
+    }
+
+    /// Every entry of a call, up to its status.
+    async fn read_log(call: &mut Call) -> Vec<CallEntry> {
+        let mut entries = Vec::new();
+        while let Some(entry) = call.log.recv().await {
+            let ends = entry.ends_call();
+            entries.push(entry);
+            if ends {
+                break;
+            }
+        }
+        entries
+    }
+
+    fn received_texts(entries: &[CallEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .filter(|entry| {
+                entry.direction == Directions::Received && entry.kind == EntryKinds::Message
+            })
+            .map(|entry| {
+                let message: Value = serde_json::from_str(&entry.data).unwrap();
+                message["text"].as_str().unwrap_or_default().to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_unary_and_server_streaming_calls() {
+        let services = echo_services();
+        let url = spawn_grpc_server().await;
+
+        let say = find_method(&services, "echo.Echo/Say").unwrap();
+        let mut call = start_call(say.clone(), prepared(&url, "{\"text\": \"hello\"}"))
+            .await
+            .unwrap();
+        assert!(!call.session.is_sending());
+        let entries = read_log(&mut call).await;
+        assert!(entries[0].direction == Directions::Sent && entries[0].data.contains("hello"));
+        assert!(entries
+            .iter()
+            .any(|entry| entry.kind == EntryKinds::Headers && entry.data.contains("x-echo: hi")));
+        assert!(received_texts(&entries) == vec!["hello"]);
+        assert!(entries.last().unwrap().data == "OK");
+
+        let mut call = start_call(say.clone(), prepared(&url, "{\"text\": \"fail\"}"))
+            .await
+            .unwrap();
+        let entries = read_log(&mut call).await;
+        assert!(entries.iter().any(
+            |entry| entry.kind == EntryKinds::Trailers && entry.data.contains("reason: asked")
+        ));
+        let status = entries.last().unwrap();
+        assert!(status.kind == EntryKinds::Error && status.data.starts_with("INVALID_ARGUMENT"));
+
+        assert!(start_call(say.clone(), prepared(&url, "{\"text\": 1}"))
+            .await
+            .is_err());
+        assert!(start_call(say, prepared("http://127.0.0.1:1", "{}"))
+            .await
+            .is_err());
+
+        let repeat = find_method(&services, "echo.Echo/Repeat").unwrap();
+        let mut call = start_call(
+            repeat,
+            prepared(&url, "{\"text\": \"again\", \"times\": 3}"),
+        )
+        .await
+        .unwrap();
+        let entries = read_log(&mut call).await;
+        assert!(received_texts(&entries) == vec!["again", "again", "again"]);
+        assert!(entries.last().unwrap().kind == EntryKinds::Status);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_calls() {
+        let services = echo_services();
+        let url = spawn_grpc_server().await;
+
+        let collect = find_method(&services, "echo.Echo/Collect").unwrap();
+        let mut call = start_call(collect, prepared(&url, "")).await.unwrap();
+        assert!(call.session.is_sending());
+        call.session.send("{\"text\": \"one\"}").unwrap();
+        call.session.send("{\"text\": \"two\"}").unwrap();
+        assert!(call.session.send("{\"nope\": true}").is_err());
+        call.session.finish();
+        assert!(call.session.send("{}").is_err());
+        let entries = read_log(&mut call).await;
+        assert!(received_texts(&entries) == vec!["one two"]);
+        assert!(entries.last().unwrap().data == "OK");
+
+        let chat = find_method(&services, "echo.Echo/Chat").unwrap();
+        let mut call = start_call(chat.clone(), prepared(&url, "")).await.unwrap();
+        call.session.send("{\"text\": \"ping\"}").unwrap();
+        let mut entries = Vec::new();
+        while received_texts(&entries).is_empty() {
+            entries.push(call.log.recv().await.unwrap());
+        }
+        assert!(received_texts(&entries) == vec!["ping"]);
+        call.session.cancel();
+        let entries = read_log(&mut call).await;
+        assert!(entries.last().unwrap().data.starts_with("CANCELLED"));
+        assert!(call.session.send("{}").is_err());
+
+        let mut call = start_call(chat, prepared(&url, "")).await.unwrap();
+        call.session.send("{\"text\": \"a\"}").unwrap();
+        call.session.send("{\"text\": \"b\"}").unwrap();
+        call.session.finish();
+        let entries = read_log(&mut call).await;
+        assert!(received_texts(&entries) == vec!["a", "b"]);
+        assert!(entries.last().unwrap().kind == EntryKinds::Status);
+    }
+}
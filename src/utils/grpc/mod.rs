@@ -0,0 +1,543 @@
+//! gRPC requests: the services they call, described by .proto files or
+//! asked from the server, and the messages that go back and forth.
+
+use std::{error::Error, fmt, path::PathBuf, time::Duration};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, Kind, MessageDescriptor, MethodDescriptor, SerializeOptions,
+};
+use serde_json::{json, Map, Value};
+use sqlx::SqlitePool;
+use tonic::{
+    metadata::{
+        AsciiMetadataKey, AsciiMetadataValue, BinaryMetadataKey, BinaryMetadataValue,
+        KeyAndValueRef, MetadataMap,
+    },
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Code,
+};
+use url::Url;
+
+use crate::utils::{
+    crud::{
+        grpc::{DescriptorSources, GrpcCallData},
+        requests::RequestData,
+    },
+    errors::error_chain,
+    executor::{apply_oauth2_token, body::PreparedBody, prepare_request, PreparedRequest},
+    tokio_runtime::runtime,
+};
+
+pub mod call;
+pub mod codec;
+pub mod reflection;
+
+/// Deepest nesting a template is written out to, so recursive messages end.
+const MAX_TEMPLATE_DEPTH: usize = 4;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CallKinds {
+    Unary,
+    ServerStreaming,
+    ClientStreaming,
+    Bidi,
+}
+
+impl fmt::Display for CallKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallKinds::Unary => write!(f, "unary"),
+            CallKinds::ServerStreaming => write!(f, "server streaming"),
+            CallKinds::ClientStreaming => write!(f, "client streaming"),
+            CallKinds::Bidi => write!(f, "bidi streaming"),
+        }
+    }
+}
+
+impl CallKinds {
+    pub fn of(method: &MethodDescriptor) -> CallKinds {
+        match (method.is_client_streaming(), method.is_server_streaming()) {
+            (false, false) => CallKinds::Unary,
+            (false, true) => CallKinds::ServerStreaming,
+            (true, false) => CallKinds::ClientStreaming,
+            (true, true) => CallKinds::Bidi,
+        }
+    }
+
+    /// Whether the client sends its messages one by one, rather than the
+    /// single one it starts the call with.
+    pub fn streams_requests(&self) -> bool {
+        matches!(self, CallKinds::ClientStreaming | CallKinds::Bidi)
+    }
+}
+
+/// The name a method is picked by, like `package.Service/Method`.
+pub fn method_name(method: &MethodDescriptor) -> String {
+    format!("{}/{}", method.parent_service().full_name(), method.name())
+}
+
+/// Every method of the services in a pool, sorted by name. The reflection
+/// service itself is left out.
+pub fn list_methods(pool: &DescriptorPool) -> Vec<String> {
+    let mut methods: Vec<String> = pool
+        .services()
+        .filter(|service| !service.full_name().starts_with("grpc.reflection."))
+        .flat_map(|service| service.methods().collect::<Vec<_>>())
+        .map(|method| method_name(&method))
+        .collect();
+    methods.sort();
+    methods
+}
+
+/// The method with the given name, with or without a leading slash.
+pub fn find_method(pool: &DescriptorPool, name: &str) -> Result<MethodDescriptor, Box<dyn Error>> {
+    let name = name.trim().trim_start_matches('/');
+    let (service, method) = name
+        .split_once('/')
+        .ok_or("Methods are named like package.Service/Method")?;
+    pool.get_service_by_name(service)
+        .and_then(|service| service.methods().find(|found| found.name() == method))
+        .ok_or_else(|| format!("The services don't have a {} method", name).into())
+}
+
+/// The lines of a text box listing paths, without blank ones.
+pub fn path_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Compile .proto files into the services they describe.
+///
+/// Imports are looked up in the import paths first, then in the folder of
+/// each file, so a file importing its neighbours works without any.
+pub fn compile_protos(
+    files: &[String],
+    import_paths: &[String],
+) -> Result<DescriptorPool, Box<dyn Error>> {
+    if files.is_empty() {
+        return Err("Add the .proto files that describe the services".into());
+    }
+    let mut includes: Vec<PathBuf> = import_paths.iter().map(PathBuf::from).collect();
+    for file in files {
+        if let Some(folder) = PathBuf::from(file).parent() {
+            if !includes.iter().any(|include| include == folder) {
+                includes.push(folder.to_path_buf());
+            }
+        }
+    }
+
+    let pool = protox::Compiler::new(includes)?
+        .include_imports(true)
+        .open_files(files)?
+        .descriptor_pool();
+    Ok(pool)
+}
+
+/// A message of the given type read from JSON. Empty text is the empty
+/// message.
+pub fn parse_message(
+    descriptor: MessageDescriptor,
+    json: &str,
+) -> Result<DynamicMessage, Box<dyn Error>> {
+    if json.trim().is_empty() {
+        return Ok(DynamicMessage::new(descriptor));
+    }
+    let name = descriptor.full_name().to_string();
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let message = DynamicMessage::deserialize(descriptor, &mut deserializer)
+        .and_then(|message| deserializer.end().map(|_| message))
+        .map_err(|error| format!("The message isn't a valid {}: {}", name, error))?;
+    Ok(message)
+}
+
+/// A message as JSON on a single line, fields at their defaults included.
+pub fn message_json(message: &DynamicMessage) -> String {
+    let mut serializer = serde_json::Serializer::new(Vec::new());
+    let options = SerializeOptions::new().skip_default_fields(false);
+    match message.serialize_with_options(&mut serializer, &options) {
+        Ok(()) => String::from_utf8_lossy(&serializer.into_inner()).to_string(),
+        Err(error) => format!("<{}>", error),
+    }
+}
+
+/// JSON with every field of a message at an example value, to be filled in.
+///
+/// Only the first field of each oneof is written, 64-bit numbers are
+/// strings as in the JSON mapping, and lists get a single element.
+pub fn message_template(descriptor: &MessageDescriptor) -> String {
+    serde_json::to_string_pretty(&template_value(descriptor, 0)).unwrap_or_default()
+}
+
+fn template_value(descriptor: &MessageDescriptor, depth: usize) -> Value {
+    match descriptor.full_name() {
+        "google.protobuf.Timestamp" => return json!("1970-01-01T00:00:00Z"),
+        "google.protobuf.Duration" => return json!("0s"),
+        "google.protobuf.FieldMask" => return json!(""),
+        "google.protobuf.Struct" => return json!({}),
+        "google.protobuf.ListValue" => return json!([]),
+        "google.protobuf.Value" => return Value::Null,
+        "google.protobuf.Any" => return json!({"@type": ""}),
+        _ => {}
+    }
+    // Wrappers like StringValue are written as the value they wrap.
+    if descriptor.full_name().starts_with("google.protobuf.")
+        && descriptor.full_name().ends_with("Value")
+    {
+        if let Some(field) = descriptor.get_field_by_name("value") {
+            return kind_template(&field.kind(), depth);
+        }
+    }
+
+    let mut object = Map::new();
+    if depth >= MAX_TEMPLATE_DEPTH {
+        return Value::Object(object);
+    }
+    let mut oneofs = Vec::new();
+    for field in descriptor.fields() {
+        if let Some(oneof) = field
+            .containing_oneof()
+            .filter(|oneof| !oneof.is_synthetic())
+        {
+            if oneofs.contains(&oneof.name().to_string()) {
+                continue;
+            }
+            oneofs.push(oneof.name().to_string());
+        }
+        let value = if field.is_map() {
+            json!({})
+        } else if field.is_list() {
+            json!([kind_template(&field.kind(), depth)])
+        } else {
+            kind_template(&field.kind(), depth)
+        };
+        object.insert(field.json_name().to_string(), value);
+    }
+    Value::Object(object)
+}
+
+fn kind_template(kind: &Kind, depth: usize) -> Value {
+    match kind {
+        Kind::Double | Kind::Float => json!(0.0),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Uint32 | Kind::Fixed32 => json!(0),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 | Kind::Uint64 | Kind::Fixed64 => json!("0"),
+        Kind::Bool => json!(false),
+        Kind::String | Kind::Bytes => json!(""),
+        Kind::Enum(descriptor) => json!(descriptor.default_value().name()),
+        Kind::Message(descriptor) => template_value(descriptor, depth + 1),
+    }
+}
+
+/// The canonical name of a status code, like `INVALID_ARGUMENT`.
+pub fn code_name(code: Code) -> &'static str {
+    match code {
+        Code::Ok => "OK",
+        Code::Cancelled => "CANCELLED",
+        Code::Unknown => "UNKNOWN",
+        Code::InvalidArgument => "INVALID_ARGUMENT",
+        Code::DeadlineExceeded => "DEADLINE_EXCEEDED",
+        Code::NotFound => "NOT_FOUND",
+        Code::AlreadyExists => "ALREADY_EXISTS",
+        Code::PermissionDenied => "PERMISSION_DENIED",
+        Code::ResourceExhausted => "RESOURCE_EXHAUSTED",
+        Code::FailedPrecondition => "FAILED_PRECONDITION",
+        Code::Aborted => "ABORTED",
+        Code::OutOfRange => "OUT_OF_RANGE",
+        Code::Unimplemented => "UNIMPLEMENTED",
+        Code::Internal => "INTERNAL",
+        Code::Unavailable => "UNAVAILABLE",
+        Code::DataLoss => "DATA_LOSS",
+        Code::Unauthenticated => "UNAUTHENTICATED",
+    }
+}
+
+/// Metadata as name/value pairs. Binary values are left base64 encoded,
+/// as they were sent.
+pub fn metadata_pairs(metadata: &MetadataMap) -> Vec<(String, String)> {
+    metadata
+        .iter()
+        .map(|entry| match entry {
+            KeyAndValueRef::Ascii(name, value) => (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            ),
+            KeyAndValueRef::Binary(name, value) => (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_encoded_bytes()).to_string(),
+            ),
+        })
+        .collect()
+}
+
+/// Request metadata from headers. Names ending in `-bin` take base64 values.
+pub fn to_metadata(headers: &[(String, String)]) -> Result<MetadataMap, Box<dyn Error>> {
+    let mut metadata = MetadataMap::new();
+    for (name, value) in headers {
+        let name = name.trim().to_ascii_lowercase();
+        let invalid_name = || format!("{} isn't a valid metadata name", name);
+        if name.ends_with("-bin") {
+            let bytes = STANDARD
+                .decode(value.trim())
+                .map_err(|_| format!("The value of {} has to be base64", name))?;
+            let key = BinaryMetadataKey::from_bytes(name.as_bytes()).map_err(|_| invalid_name())?;
+            metadata.append_bin(key, BinaryMetadataValue::from_bytes(&bytes));
+        } else {
+            let key = AsciiMetadataKey::from_bytes(name.as_bytes()).map_err(|_| invalid_name())?;
+            let value = AsciiMetadataValue::try_from(value.as_str())
+                .map_err(|_| format!("The value of {} can't be sent as metadata", name))?;
+            metadata.append(key, value);
+        }
+    }
+    Ok(metadata)
+}
+
+/// The address of a server, as the channel expects it.
+///
+/// Addresses without a scheme are plain text, and `grpcs://` or
+/// `https://` ones use TLS. Paths are dropped, as calls have their own.
+pub fn grpc_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let url = url.trim();
+    let (scheme, rest) = url.split_once("://").unwrap_or(("http", url));
+    let scheme = match scheme.to_ascii_lowercase().as_str() {
+        "http" | "grpc" => "http",
+        "https" | "grpcs" => "https",
+        _ => return Err("gRPC addresses start with http://, https://, grpc:// or grpcs://".into()),
+    };
+    let parsed = Url::parse(&format!("{}://{}", scheme, rest))
+        .map_err(|error| format!("{} isn't a valid address: {}", url, error))?;
+    if parsed.host_str().unwrap_or_default().is_empty() {
+        return Err(format!("{} has no host", url).into());
+    }
+
+    Ok(parsed.origin().ascii_serialization())
+}
+
+/// Open a channel to a server, over TLS for `https` addresses.
+async fn connect_channel(url: &str) -> Result<Channel, Box<dyn Error + Send + Sync>> {
+    let mut endpoint =
+        Endpoint::from_shared(url.to_string())?.connect_timeout(Duration::from_secs(10));
+    if url.starts_with("https://") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_webpki_roots())?;
+    }
+    endpoint
+        .connect()
+        .await
+        .map_err(|error| error_chain(&error).into())
+}
+
+/// What the call of a request is made of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedCall {
+    pub url: String,
+    /// The request's headers, sent as metadata.
+    pub metadata: Vec<(String, String)>,
+    /// The message as JSON, with its variables substituted.
+    pub message: String,
+}
+
+impl PreparedCall {
+    pub fn for_request(request: &PreparedRequest) -> Result<PreparedCall, Box<dyn Error>> {
+        Ok(PreparedCall {
+            url: grpc_url(&request.url)?,
+            // The call sets its own content type.
+            metadata: request
+                .headers
+                .iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+                .cloned()
+                .collect(),
+            message: match &request.body {
+                PreparedBody::Text(message) => message.clone(),
+                _ => String::new(),
+            },
+        })
+    }
+}
+
+/// The call of a stored request, with its variables substituted.
+///
+/// The request's headers and auth become the metadata of the call, just as
+/// they would go into an HTTP request.
+pub async fn prepare_call(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedCall, Box<dyn Error>> {
+    let prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    PreparedCall::for_request(&prepared)
+}
+
+/// The services a request calls, compiled from its .proto files or asked
+/// from its server.
+pub async fn load_services(
+    request: &RequestData,
+    call: &GrpcCallData,
+    pool: &SqlitePool,
+) -> Result<DescriptorPool, Box<dyn Error>> {
+    if DescriptorSources::from_string(&call.source) == Some(DescriptorSources::Proto) {
+        return compile_protos(
+            &path_lines(&call.proto_files),
+            &path_lines(&call.import_paths),
+        );
+    }
+
+    let prepared = prepare_call(request, pool).await?;
+    let metadata = to_metadata(&prepared.metadata)?;
+    let services = runtime()
+        .spawn(async move { reflection::reflect_services(&prepared.url, metadata).await })
+        .await?
+        .map_err(|error| error.to_string())?;
+    Ok(services)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                collections::create_collection,
+                grpc::update_grpc_call,
+                requests::{create_request, update_request_url, ProtocolTypes},
+            },
+            test_server::{spawn_grpc_server, write_echo_protos},
+        },
+    };
+
+    #[test]
+    fn test_grpc_url() {
+        assert!(grpc_url("localhost:50051").unwrap() == "http://localhost:50051");
+        assert!(grpc_url("grpcs://api.example.com/ignored").unwrap() == "https://api.example.com");
+        assert!(grpc_url("HTTP://127.0.0.1:8080").unwrap() == "http://127.0.0.1:8080");
+        assert!(grpc_url("ftp://example.com").is_err());
+        assert!(grpc_url("").is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let headers = vec![
+            ("X-Trace".to_string(), "abc".to_string()),
+            ("blob-bin".to_string(), "AQI=".to_string()),
+        ];
+        let metadata = to_metadata(&headers).unwrap();
+        assert!(metadata.get("x-trace").unwrap() == "abc");
+        assert!(
+            metadata
+                .get_bin("blob-bin")
+                .unwrap()
+                .to_bytes()
+                .unwrap()
+                .as_ref()
+                == [1, 2]
+        );
+        let pairs = metadata_pairs(&metadata);
+        assert!(pairs.contains(&("x-trace".to_string(), "abc".to_string())));
+        assert!(pairs.iter().any(|(name, _)| name == "blob-bin"));
+
+        assert!(to_metadata(&[("blob-bin".to_string(), "%%".to_string())]).is_err());
+        assert!(to_metadata(&[("bad name".to_string(), "x".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_compile_protos_and_templates() {
+        let (file, include) = write_echo_protos();
+        assert!(compile_protos(std::slice::from_ref(&file), &[]).is_err());
+        assert!(compile_protos(&[], std::slice::from_ref(&include)).is_err());
+
+        let pool = compile_protos(&[file], &[include]).unwrap();
+        assert!(
+            list_methods(&pool)
+                == vec![
+                    "echo.Echo/Chat",
+                    "echo.Echo/Collect",
+                    "echo.Echo/Repeat",
+                    "echo.Echo/Say",
+                ]
+        );
+        assert!(find_method(&pool, "/echo.Echo/Say").is_ok());
+        assert!(find_method(&pool, "echo.Echo/Shout").is_err());
+        assert!(find_method(&pool, "Say").is_err());
+
+        let kinds: Vec<CallKinds> = ["Say", "Repeat", "Collect", "Chat"]
+            .iter()
+            .map(|name| CallKinds::of(&find_method(&pool, &format!("echo.Echo/{}", name)).unwrap()))
+            .collect();
+        assert!(
+            kinds
+                == vec![
+                    CallKinds::Unary,
+                    CallKinds::ServerStreaming,
+                    CallKinds::ClientStreaming,
+                    CallKinds::Bidi
+                ]
+        );
+        assert!(!kinds[1].streams_requests() && kinds[3].streams_requests());
+
+        let shout = find_method(&pool, "echo.Echo/Say").unwrap().input();
+        let template: Value = serde_json::from_str(&message_template(&shout)).unwrap();
+        assert!(
+            template
+                == json!({
+                    "text": "",
+                    "times": 0,
+                    "tone": "TONE_CALM",
+                    "tags": [""],
+                    "counts": {},
+                    "at": "1970-01-01T00:00:00Z",
+                    "total": "0",
+                    "person": "",
+                })
+        );
+
+        // The template is a valid message.
+        let message = parse_message(shout.clone(), &template.to_string()).unwrap();
+        assert!(
+            serde_json::from_str::<Value>(&message_json(&message)).unwrap()["tone"] == "TONE_CALM"
+        );
+        let message = parse_message(shout.clone(), "{\"text\": \"hi\", \"total\": \"7\"}").unwrap();
+        let json: Value = serde_json::from_str(&message_json(&message)).unwrap();
+        assert!(json["text"] == "hi" && json["total"] == "7" && json["times"] == 0);
+        assert!(parse_message(shout.clone(), "").is_ok());
+        assert!(parse_message(shout.clone(), "{\"text\": 1}").is_err());
+        assert!(parse_message(shout.clone(), "{\"nope\": 1}").is_err());
+        assert!(parse_message(shout, "{} {}").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_services() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Rpc".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Grpc, &collection.id, &db)
+            .await
+            .unwrap();
+        let (file, include) = write_echo_protos();
+        let url = spawn_grpc_server().await;
+        let request = update_request_url(&request.id, &url, &db).await.unwrap();
+
+        // Asked from the server by default.
+        let call = GrpcCallData::default();
+        let services = load_services(&request, &call, &db).await.unwrap();
+        assert!(list_methods(&services).len() == 4);
+        let shout = find_method(&services, "echo.Echo/Say").unwrap().input();
+        assert!(message_template(&shout).contains("TONE_CALM"));
+
+        let call = GrpcCallData {
+            source: DescriptorSources::Proto.to_string(),
+            proto_files: format!("\n  {}\n", file),
+            import_paths: include,
+            ..Default::default()
+        };
+        update_grpc_call(&request.id, &call, &db).await.unwrap();
+        let services = load_services(&request, &call, &db).await.unwrap();
+        assert!(list_methods(&services).len() == 4);
+
+        let request = update_request_url(&request.id, "127.0.0.1:1", &db)
+            .await
+            .unwrap();
+        assert!(load_services(&request, &GrpcCallData::default(), &db)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Asking a server for its services through the server reflection protocol.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+
+use prost::Message;
+use prost_reflect::DescriptorPool;
+use prost_types::FileDescriptorProto;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tonic::{
+    client::Grpc, codegen::http::uri::PathAndQuery, metadata::MetadataMap, transport::Channel,
+    Code, Request, Status, Streaming,
+};
+use tonic_prost::ProstCodec;
+use tonic_reflection::pb::v1::{
+    server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+    ServerReflectionRequest, ServerReflectionResponse,
+};
+
+use super::{code_name, connect_channel};
+use crate::utils::errors::error_chain;
+
+/// The reflection services, newest first. Both speak the same messages, so
+/// servers that only know the older one are asked in it.
+const REFLECTION_PATHS: [&str; 2] = [
+    "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo",
+    "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo",
+];
+
+/// Ask a server for its services, along with every file they need.
+pub async fn reflect_services(
+    url: &str,
+    metadata: MetadataMap,
+) -> Result<DescriptorPool, Box<dyn Error + Send + Sync>> {
+    let channel = connect_channel(url).await?;
+    let mut last_status = None;
+    for path in REFLECTION_PATHS {
+        match reflect_with(channel.clone(), metadata.clone(), path).await {
+            Ok(pool) => return Ok(pool),
+            Err(status) if status.code() == Code::Unimplemented => last_status = Some(status),
+            Err(status) => return Err(describe_status(&status).into()),
+        }
+    }
+
+    let status = last_status.unwrap_or_else(|| Status::unimplemented(""));
+    Err(format!(
+        "The server doesn't offer reflection ({}). Load its .proto files instead.",
+        describe_status(&status)
+    )
+    .into())
+}
+
+fn describe_status(status: &Status) -> String {
+    match status.message().is_empty() {
+        true => code_name(status.code()).to_string(),
+        false => format!("{}: {}", code_name(status.code()), status.message()),
+    }
+}
+
+/// One reflection stream, where each question gets its answer before the
+/// next one is asked.
+struct Session {
+    questions: UnboundedSender<ServerReflectionRequest>,
+    answers: Streaming<ServerReflectionResponse>,
+}
+
+impl Session {
+    async fn ask(&mut self, request: MessageRequest) -> Result<MessageResponse, Status> {
+        self.questions
+            .send(ServerReflectionRequest {
+                host: String::new(),
+                message_request: Some(request),
+            })
+            .map_err(|_| Status::unavailable("The reflection stream closed"))?;
+        let answer = self
+            .answers
+            .message()
+            .await?
+            .and_then(|answer| answer.message_response)
+            .ok_or_else(|| Status::unavailable("The server stopped answering"))?;
+        match answer {
+            MessageResponse::ErrorResponse(error) => Err(Status::new(
+                Code::from_i32(error.error_code),
+                error.error_message,
+            )),
+            answer => Ok(answer),
+        }
+    }
+
+    /// Ask for files, decoded from the answer.
+    async fn ask_files(
+        &mut self,
+        request: MessageRequest,
+    ) -> Result<Vec<FileDescriptorProto>, Status> {
+        match self.ask(request).await? {
+            MessageResponse::FileDescriptorResponse(response) => response
+                .file_descriptor_proto
+                .iter()
+                .map(|bytes| {
+                    FileDescriptorProto::decode(bytes.as_slice())
+                        .map_err(|error| Status::internal(error.to_string()))
+                })
+                .collect(),
+            _ => Err(Status::internal(
+                "The server answered with something else than files",
+            )),
+        }
+    }
+}
+
+async fn reflect_with(
+    channel: Channel,
+    metadata: MetadataMap,
+    path: &'static str,
+) -> Result<DescriptorPool, Status> {
+    let (questions, mut queued) = unbounded_channel();
+    let requests = futures_util::stream::poll_fn(move |cx| queued.poll_recv(cx));
+    let mut request = Request::new(requests);
+    *request.metadata_mut() = metadata;
+
+    let mut client = Grpc::new(channel);
+    client
+        .ready()
+        .await
+        .map_err(|error| Status::unavailable(error_chain(&error)))?;
+    let codec: ProstCodec<ServerReflectionRequest, ServerReflectionResponse> =
+        ProstCodec::default();
+    let answers = client
+        .streaming(request, PathAndQuery::from_static(path), codec)
+        .await?
+        .into_inner();
+    let mut session = Session { questions, answers };
+
+    let services = match session
+        .ask(MessageRequest::ListServices(String::new()))
+        .await?
+    {
+        MessageResponse::ListServicesResponse(response) => response.service,
+        _ => return Err(Status::internal("The server didn't list its services")),
+    };
+
+    let mut files: HashMap<String, FileDescriptorProto> = HashMap::new();
+    for service in services
+        .iter()
+        .filter(|service| !service.name.starts_with("grpc.reflection."))
+    {
+        let found = session
+            .ask_files(MessageRequest::FileContainingSymbol(service.name.clone()))
+            .await?;
+        for file in found {
+            files.insert(file.name().to_string(), file);
+        }
+    }
+
+    // Servers usually send the dependencies along. Those left out are asked
+    // for by name.
+    let mut asked = HashSet::new();
+    loop {
+        let missing: Vec<String> = files
+            .values()
+            .flat_map(|file| file.dependency.iter())
+            .filter(|dependency| !files.contains_key(*dependency) && !asked.contains(*dependency))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            break;
+        }
+        for name in missing {
+            asked.insert(name.clone());
+            for file in session
+                .ask_files(MessageRequest::FileByFilename(name))
+                .await?
+            {
+                files.insert(file.name().to_string(), file);
+            }
+        }
+    }
+
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_protos(files.into_values())
+        .map_err(|error| Status::internal(format!("The server's files don't fit: {}", error)))?;
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{grpc::list_methods, test_server::spawn_grpc_server};
+
+    #[tokio::test]
+    async fn test_reflect_services() {
+        let url = spawn_grpc_server().await;
+        let pool = reflect_services(&url, MetadataMap::new()).await.unwrap();
+        assert!(list_methods(&pool).contains(&"echo.Echo/Chat".to_string()));
+        // The imported types came along.
+        let shout = pool.get_message_by_name("echo.Shout").unwrap();
+        assert!(shout.get_field_by_name("at").is_some());
+
+        assert!(reflect_services("http://127.0.0.1:1", MetadataMap::new())
+            .await
+            .is_err());
+    }
+}
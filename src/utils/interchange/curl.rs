@@ -0,0 +1,728 @@
+//! cURL commands, as copied from browser devtools or pasted into bug reports.
+//!
+//! Commands are read as a POSIX shell would split them, with backslash line
+//! continuations, single, double and `$'...'` quoting. Only the options that
+//! describe the request are mapped, output options such as `-s` or `-o` are
+//! ignored.
+//!
+//! `-k` is deliberately not supported. Requests always verify certificates,
+//! so an imported `-k` is reported as left out and written commands never
+//! have it.
+
+use std::error::Error;
+
+use url::form_urlencoded;
+
+use crate::utils::{
+    crud::{auth::AuthTypes, bodies::BodyModes, requests::HTTPMethods},
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    interchange::{
+        body_mode_for, request_name, AuthSpec, BodyPartSpec, BodySpec, KeyValueSpec, RequestSpec,
+    },
+    query_params::{decode_component, encode_component},
+};
+
+/// A request read from a cURL command, and the options it could not map.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedCommand {
+    pub request: RequestSpec,
+    pub unmapped: Vec<String>,
+}
+
+/// Options that take no value. Anything else starting with `-` and not
+/// handled below is reported.
+const FLAGS: &[&str] = &[
+    "-s",
+    "--silent",
+    "-S",
+    "--show-error",
+    "-v",
+    "--verbose",
+    "-i",
+    "--include",
+    "-L",
+    "--location",
+    "-f",
+    "--fail",
+    "-#",
+    "--progress-bar",
+    "-N",
+    "--no-buffer",
+    "--http1.1",
+    "--http2",
+    "--compressed",
+    "-g",
+    "--globoff",
+];
+
+/// Options that take a value which has no place in a request.
+const IGNORED_WITH_VALUE: &[&str] = &[
+    "-o",
+    "--output",
+    "-w",
+    "--write-out",
+    "-m",
+    "--max-time",
+    "--connect-timeout",
+    "--retry",
+    "-c",
+    "--cookie-jar",
+    "--max-redirs",
+];
+
+/// Split a command line into words, as a POSIX shell would.
+fn split_words(command: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // A line continuation, also when copied with Windows line endings.
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(escaped) => {
+                    word.push(escaped);
+                    in_word = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(quoted) => word.push(quoted),
+                        None => return Err("A single quote is not closed.".into()),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(escaped @ ('"' | '\\' | '$' | '`')) => word.push(escaped),
+                            Some(other) => {
+                                word.push('\\');
+                                word.push(other);
+                            }
+                            None => return Err("A double quote is not closed.".into()),
+                        },
+                        Some(quoted) => word.push(quoted),
+                        None => return Err("A double quote is not closed.".into()),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                in_word = true;
+                read_ansi_c_quoted(&mut chars, &mut word)?;
+            }
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+/// Read the rest of a `$'...'` string, which devtools use for bodies with
+/// quotes or line breaks in them.
+fn read_ansi_c_quoted(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    word: &mut String,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        match chars.next() {
+            Some('\'') => return Ok(()),
+            Some('\\') => match chars.next() {
+                Some('n') => word.push('\n'),
+                Some('t') => word.push('\t'),
+                Some('r') => word.push('\r'),
+                Some('0') => word.push('\0'),
+                Some(hex @ ('x' | 'u' | 'U')) => {
+                    let length = match hex {
+                        'x' => 2,
+                        'u' => 4,
+                        _ => 8,
+                    };
+                    let mut digits = String::new();
+                    while digits.len() < length && chars.peek().is_some_and(char::is_ascii_hexdigit)
+                    {
+                        digits.extend(chars.next());
+                    }
+                    match u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                    {
+                        Some(decoded) => word.push(decoded),
+                        None => {
+                            word.push('\\');
+                            word.push(hex);
+                            word.push_str(&digits);
+                        }
+                    }
+                }
+                Some(escaped) => word.push(escaped),
+                None => return Err("A $'...' quote is not closed.".into()),
+            },
+            Some(quoted) => word.push(quoted),
+            None => return Err("A $'...' quote is not closed.".into()),
+        }
+    }
+}
+
+/// Short options with a value, which cURL also accepts written as `-XPOST`.
+fn takes_value(option: &str) -> bool {
+    matches!(
+        option,
+        "-X" | "-H" | "-d" | "-F" | "-u" | "-A" | "-e" | "-b" | "-o" | "-w" | "-m" | "-c"
+    )
+}
+
+/// Expand `-XPOST` into `-X POST` and `-sSL` into `-s -S -L`.
+fn expand_short_options(words: Vec<String>) -> Vec<String> {
+    let mut expanded = Vec::new();
+    for word in words {
+        let is_short = word.len() > 2
+            && word.starts_with('-')
+            && !word.starts_with("--")
+            && word.is_char_boundary(2);
+        if !is_short {
+            expanded.push(word);
+            continue;
+        }
+        let option = &word[..2];
+        if takes_value(option) {
+            expanded.push(option.to_string());
+            expanded.push(word[2..].to_string());
+        } else if word[1..].chars().all(|c| {
+            FLAGS.contains(&format!("-{}", c).as_str()) || c == 'k' || c == 'G' || c == 'I'
+        }) {
+            expanded.extend(word[1..].chars().map(|c| format!("-{}", c)));
+        } else {
+            expanded.push(word);
+        }
+    }
+    expanded
+}
+
+/// Read a cURL command into a request.
+pub fn parse_command(command: &str) -> Result<ParsedCommand, Box<dyn Error>> {
+    let mut words = expand_short_options(split_words(command)?).into_iter();
+    match words.next() {
+        Some(first) if first == "curl" || first.ends_with("/curl") || first == "curl.exe" => {}
+        _ => return Err("The command does not start with curl.".into()),
+    }
+
+    let mut unmapped = Vec::new();
+    let mut report = |what: String| {
+        if !unmapped.contains(&what) {
+            unmapped.push(what);
+        }
+    };
+
+    let mut url: Option<String> = None;
+    let mut method: Option<String> = None;
+    let mut headers: Vec<KeyValueSpec> = Vec::new();
+    let mut data: Vec<String> = Vec::new();
+    let mut data_file: Option<String> = None;
+    let mut form: Vec<BodyPartSpec> = Vec::new();
+    let mut user: Option<String> = None;
+    let mut digest = false;
+    let mut data_in_query = false;
+
+    while let Some(word) = words.next() {
+        let mut value = |option: &str| {
+            words
+                .next()
+                .ok_or_else(|| format!("{} needs a value.", option))
+        };
+
+        match word.as_str() {
+            "-X" | "--request" => method = Some(value(&word)?.to_uppercase()),
+            "-I" | "--head" => method = Some("HEAD".to_string()),
+            "-G" | "--get" => data_in_query = true,
+            "-H" | "--header" => {
+                let header = value(&word)?;
+                // `Name;` sends an empty header, `Name:` removes one.
+                match header.split_once(':') {
+                    Some((name, value)) if !value.trim().is_empty() => {
+                        headers.push(KeyValueSpec::new(name.trim(), value.trim(), true))
+                    }
+                    Some(_) => {}
+                    None => {
+                        let name = header.trim_end_matches(';').trim();
+                        headers.push(KeyValueSpec::new(name, "", true));
+                    }
+                }
+            }
+            "-A" | "--user-agent" => {
+                headers.push(KeyValueSpec::new("User-Agent", &value(&word)?, true))
+            }
+            "-e" | "--referer" => headers.push(KeyValueSpec::new("Referer", &value(&word)?, true)),
+            "-b" | "--cookie" => {
+                let cookie = value(&word)?;
+                if cookie.contains('=') {
+                    headers.push(KeyValueSpec::new("Cookie", &cookie, true));
+                } else {
+                    report(format!("{} {}: cookies read from a file", word, cookie));
+                }
+            }
+            "-d" | "--data" | "--data-ascii" => {
+                let text = value(&word)?;
+                match text.strip_prefix('@') {
+                    Some(path) => {
+                        report(format!("{} @{}: sent as a binary file", word, path));
+                        data_file = Some(path.to_string());
+                    }
+                    None => data.push(text),
+                }
+            }
+            "--data-raw" => data.push(value(&word)?),
+            "--data-binary" => {
+                let text = value(&word)?;
+                match text.strip_prefix('@') {
+                    Some(path) => data_file = Some(path.to_string()),
+                    None => data.push(text),
+                }
+            }
+            "--data-urlencode" => {
+                let text = value(&word)?;
+                data.push(match text.split_once('=') {
+                    Some((name, value)) => format!("{}={}", name, encode_component(value)),
+                    None => encode_component(&text),
+                });
+            }
+            "-F" | "--form" | "--form-string" => {
+                let field = value(&word)?;
+                let Some((name, mut field_value)) = field.split_once('=') else {
+                    report(format!("{} {}: field without a value", word, field));
+                    continue;
+                };
+                // `--form-string` values are sent as they are.
+                let is_literal = word == "--form-string";
+                if !is_literal {
+                    if let Some((rest, content_type)) = field_value.split_once(";type=") {
+                        report(format!("{} {}: content type {}", word, name, content_type));
+                        field_value = rest;
+                    }
+                    if field_value.starts_with('<') {
+                        report(format!("{} {}: value read from a file", word, name));
+                    }
+                }
+                let (field_value, is_file) = match field_value.strip_prefix('@') {
+                    Some(path) if !is_literal => (path, true),
+                    _ => (field_value, false),
+                };
+                form.push(BodyPartSpec {
+                    name: name.to_string(),
+                    value: field_value.to_string(),
+                    is_file,
+                    enabled: true,
+                });
+            }
+            "-u" | "--user" => user = Some(value(&word)?),
+            "--digest" => digest = true,
+            "--basic" => digest = false,
+            "-k" | "--insecure" => report(format!(
+                "{}: not supported, certificates are always verified",
+                word
+            )),
+            "--url" => url = Some(value(&word)?),
+            option if FLAGS.contains(&option) => {}
+            option if IGNORED_WITH_VALUE.contains(&option) => {
+                value(option)?;
+            }
+            option if option.starts_with('-') && option.len() > 1 => {
+                report(format!("option {}", option))
+            }
+            _ => match url {
+                None => url = Some(word),
+                Some(_) => report(format!("extra URL {}", word)),
+            },
+        }
+    }
+
+    let Some(mut url) = url else {
+        return Err("The command has no URL.".into());
+    };
+    if data_in_query && !data.is_empty() {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url = format!("{}{}{}", url, separator, data.join("&"));
+        data.clear();
+    }
+
+    let has_body = !data.is_empty() || data_file.is_some() || !form.is_empty();
+    let method_name = method.unwrap_or_else(|| match has_body {
+        true => "POST".to_string(),
+        false => "GET".to_string(),
+    });
+    let method = HTTPMethods::from_string(&method_name).unwrap_or_else(|| {
+        report(format!("method {}, sent as GET", method_name));
+        HTTPMethods::Get
+    });
+
+    // The body keeps the content type, so the header is only left for bodies
+    // that don't have one.
+    let content_type_index = headers
+        .iter()
+        .position(|header| header.name.eq_ignore_ascii_case("content-type"));
+    let content_type = match (has_body, content_type_index) {
+        (true, Some(index)) => headers.remove(index).value,
+        _ => String::new(),
+    };
+
+    let body = if !form.is_empty() {
+        if !data.is_empty() || data_file.is_some() {
+            report("data sent together with form fields".to_string());
+        }
+        BodySpec {
+            mode: BodyModes::Multipart,
+            parts: form,
+            ..Default::default()
+        }
+    } else if let Some(path) = data_file {
+        BodySpec {
+            mode: BodyModes::Binary,
+            content_type,
+            payload: path,
+            parts: Vec::new(),
+        }
+    } else if !data.is_empty() {
+        let payload = data.join("&");
+        let (mode, content_type) = match content_type.is_empty() {
+            true => body_mode_for("application/x-www-form-urlencoded"),
+            false => body_mode_for(&content_type),
+        };
+        match mode {
+            BodyModes::FormUrlEncoded if payload.contains('=') => BodySpec {
+                mode,
+                content_type,
+                parts: payload
+                    .split('&')
+                    .filter(|pair| !pair.is_empty())
+                    .map(|pair| {
+                        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                        BodyPartSpec {
+                            name: decode_component(name),
+                            value: decode_component(value),
+                            is_file: false,
+                            enabled: true,
+                        }
+                    })
+                    .collect(),
+                payload: String::new(),
+            },
+            BodyModes::FormUrlEncoded => BodySpec {
+                mode: BodyModes::Raw,
+                content_type: "application/x-www-form-urlencoded".to_string(),
+                payload,
+                parts: Vec::new(),
+            },
+            mode => BodySpec {
+                mode,
+                content_type,
+                payload,
+                parts: Vec::new(),
+            },
+        }
+    } else {
+        BodySpec::default()
+    };
+
+    let mut request = RequestSpec::new(&request_name(&url), method, &url);
+    request.headers = headers;
+    request.body = body;
+    if let Some(user) = user {
+        let (username, password) = user.split_once(':').unwrap_or((&user, ""));
+        let mut auth = AuthSpec::new(match digest {
+            true => AuthTypes::Digest,
+            false => AuthTypes::Basic,
+        });
+        auth.username = username.to_string();
+        auth.secret = password.to_string();
+        request.auth = Some(auth);
+    }
+
+    Ok(ParsedCommand { request, unmapped })
+}
+
+/// Quote a word for a POSIX shell, leaving plain ones as they are.
+pub fn shell_quote(word: &str) -> String {
+    let is_plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    match is_plain {
+        true => word.to_string(),
+        false => format!("'{}'", word.replace('\'', "'\\''")),
+    }
+}
+
+/// Write a prepared request as a cURL command, one option per line.
+///
+/// The request should have its variables resolved and its auth applied.
+/// Digest auth is written as `--digest -u`, the content type cURL sets
+/// itself for multipart bodies is left out. There is no `-k`, as the app
+/// verifies certificates too.
+pub fn write_command(request: &PreparedRequest) -> String {
+    let mut lines = vec![match request.method {
+        HTTPMethods::Get => format!("curl {}", shell_quote(&request.url)),
+        HTTPMethods::Head => format!("curl --head {}", shell_quote(&request.url)),
+        _ => format!("curl -X {} {}", request.method, shell_quote(&request.url)),
+    }];
+
+    let is_multipart = matches!(request.body, PreparedBody::Multipart { .. });
+    for (name, value) in &request.headers {
+        if is_multipart && name.eq_ignore_ascii_case("content-type") {
+            continue;
+        }
+        match value.is_empty() {
+            true => lines.push(format!("-H {}", shell_quote(&format!("{};", name)))),
+            false => lines.push(format!(
+                "-H {}",
+                shell_quote(&format!("{}: {}", name, value))
+            )),
+        }
+    }
+
+    match &request.auth {
+        PreparedAuth::Digest { username, password } => lines.push(format!(
+            "--digest -u {}",
+            shell_quote(&format!("{}:{}", username, password))
+        )),
+        PreparedAuth::Basic { username, password } => lines.push(format!(
+            "-u {}",
+            shell_quote(&format!("{}:{}", username, password))
+        )),
+        _ => {}
+    }
+
+    match &request.body {
+        PreparedBody::Empty => {}
+        PreparedBody::Text(text) => lines.push(format!("--data-raw {}", shell_quote(text))),
+        PreparedBody::Form(fields) => {
+            let encoded = form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(fields)
+                .finish();
+            lines.push(format!("--data-raw {}", shell_quote(&encoded)));
+        }
+        PreparedBody::Multipart { fields, .. } => {
+            for field in fields {
+                let line = match field.is_file {
+                    true => format!(
+                        "-F {}",
+                        shell_quote(&format!("{}=@{}", field.name, field.value))
+                    ),
+                    // `@` and `<` would make cURL read a file.
+                    false if field.value.starts_with(['@', '<']) => format!(
+                        "--form-string {}",
+                        shell_quote(&format!("{}={}", field.name, field.value))
+                    ),
+                    false => format!(
+                        "-F {}",
+                        shell_quote(&format!("{}={}", field.name, field.value))
+                    ),
+                };
+                lines.push(line);
+            }
+        }
+        PreparedBody::File(path) => lines.push(format!(
+            "--data-binary {}",
+            shell_quote(&format!("@{}", path))
+        )),
+        PreparedBody::Bytes(bytes) => lines.push(format!(
+            "--data-raw {}",
+            shell_quote(&String::from_utf8_lossy(bytes))
+        )),
+    }
+
+    lines.join(" \\\n  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{crud::auth::ApiKeyPlacement, executor::body::MultipartField};
+
+    #[test]
+    fn test_split_words() {
+        let command = "curl 'https://example.com/a b' \\\n  -H \"X-Name: \\\"quoted\\\" \\$HOME\" \\\r\n  --data-raw $'{\"a\":\\n\"it\\'s\"}' plain\\ word";
+        let words = split_words(command).unwrap();
+        assert!(
+            words
+                == vec![
+                    "curl",
+                    "https://example.com/a b",
+                    "-H",
+                    "X-Name: \"quoted\" $HOME",
+                    "--data-raw",
+                    "{\"a\":\n\"it's\"}",
+                    "plain word",
+                ]
+        );
+        assert!(split_words("curl 'unclosed").is_err());
+    }
+
+    #[test]
+    fn test_parse_devtools_command() {
+        let command = r#"curl 'https://api.example.com/v1/pets?limit=10' \
+  -H 'accept: application/json' \
+  -H 'content-type: application/json' \
+  -H 'x-empty;' \
+  --data-raw '{"name":"Rex"}' \
+  --compressed \
+  -k"#;
+        let parsed = parse_command(command).unwrap();
+        let request = parsed.request;
+
+        assert!(request.name == "/v1/pets");
+        assert!(request.method == HTTPMethods::Post);
+        assert!(request.url == "https://api.example.com/v1/pets?limit=10");
+        assert!(
+            request.headers
+                == vec![
+                    KeyValueSpec::new("accept", "application/json", true),
+                    KeyValueSpec::new("x-empty", "", true),
+                ]
+        );
+        assert!(request.body.mode == BodyModes::Json);
+        assert!(request.body.content_type.is_empty());
+        assert!(request.body.payload == r#"{"name":"Rex"}"#);
+        assert!(parsed.unmapped == vec!["-k: not supported, certificates are always verified"]);
+    }
+
+    #[test]
+    fn test_parse_form_and_auth() {
+        let command = "curl -sSL -XPUT -u admin:s3cret --digest https://example.com/upload \
+            -F 'photo=@/tmp/rex.png' -F 'note=hello world'";
+        let request = parse_command(command).unwrap().request;
+
+        assert!(request.method == HTTPMethods::Put);
+        assert!(request.body.mode == BodyModes::Multipart);
+        assert!(request.body.parts[0].is_file && request.body.parts[0].value == "/tmp/rex.png");
+        assert!(request.body.parts[1].value == "hello world");
+        let auth = request.auth.unwrap();
+        assert!(auth.auth_type == AuthTypes::Digest);
+        assert!(auth.username == "admin" && auth.secret == "s3cret");
+
+        let urlencoded = parse_command("curl https://example.com -d 'a=1' -d 'b=two%20words'")
+            .unwrap()
+            .request;
+        assert!(urlencoded.body.mode == BodyModes::FormUrlEncoded);
+        assert!(
+            urlencoded.body.parts[1].name == "b" && urlencoded.body.parts[1].value == "two words"
+        );
+
+        let query = parse_command("curl -G https://example.com/search -d q=rust")
+            .unwrap()
+            .request;
+        assert!(query.method == HTTPMethods::Get);
+        assert!(query.url == "https://example.com/search?q=rust");
+        assert!(query.body.mode == BodyModes::None);
+    }
+
+    #[test]
+    fn test_parse_rejects_other_commands() {
+        assert!(parse_command("wget https://example.com").is_err());
+        assert!(parse_command("curl -H 'a: b'").is_err());
+        assert!(parse_command("curl -X").is_err());
+    }
+
+    #[test]
+    fn test_write_command() {
+        let request = PreparedRequest {
+            method: HTTPMethods::Post,
+            url: "https://example.com/pets?q=a b".to_string(),
+            headers: vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("X-Quote".to_string(), "it's".to_string()),
+            ],
+            body: PreparedBody::Text("{\"name\": \"Rex\"}".to_string()),
+            auth: PreparedAuth::None,
+        };
+        assert!(
+            write_command(&request)
+                == "curl -X POST 'https://example.com/pets?q=a b' \\\n  \
+                    -H 'Content-Type: application/json' \\\n  \
+                    -H 'X-Quote: it'\\''s' \\\n  \
+                    --data-raw '{\"name\": \"Rex\"}'"
+        );
+
+        let get = PreparedRequest {
+            method: HTTPMethods::Get,
+            url: "https://example.com/".to_string(),
+            headers: Vec::new(),
+            body: PreparedBody::Empty,
+            auth: PreparedAuth::ApiKey {
+                name: "key".to_string(),
+                value: "v".to_string(),
+                placement: ApiKeyPlacement::Header,
+            },
+        };
+        assert!(write_command(&get) == "curl https://example.com/");
+    }
+
+    #[test]
+    fn test_written_command_round_trip() {
+        let request = PreparedRequest {
+            method: HTTPMethods::Patch,
+            url: "https://example.com/upload".to_string(),
+            headers: vec![
+                (
+                    "Content-Type".to_string(),
+                    "multipart/form-data; boundary=x".to_string(),
+                ),
+                ("X-Empty".to_string(), String::new()),
+            ],
+            body: PreparedBody::Multipart {
+                boundary: "x".to_string(),
+                fields: vec![
+                    MultipartField {
+                        name: "file".to_string(),
+                        value: "/tmp/a b.png".to_string(),
+                        is_file: true,
+                    },
+                    MultipartField {
+                        name: "handle".to_string(),
+                        value: "@rex".to_string(),
+                        is_file: false,
+                    },
+                ],
+            },
+            auth: PreparedAuth::Digest {
+                username: "user".to_string(),
+                password: "pa'ss".to_string(),
+            },
+        };
+
+        let parsed = parse_command(&write_command(&request)).unwrap();
+        assert!(parsed.unmapped.is_empty());
+        let read = parsed.request;
+        assert!(read.method == HTTPMethods::Patch);
+        assert!(read.headers == vec![KeyValueSpec::new("X-Empty", "", true)]);
+        assert!(read.body.parts[0].is_file && read.body.parts[0].value == "/tmp/a b.png");
+        assert!(!read.body.parts[1].is_file && read.body.parts[1].value == "@rex");
+        let auth = read.auth.unwrap();
+        assert!(auth.auth_type == AuthTypes::Digest && auth.secret == "pa'ss");
+    }
+}
@@ -0,0 +1,750 @@
+//! HAR 1.2 files, as saved by browser devtools.
+//!
+//! Importing turns entries into requests, placed in one folder per domain
+//! when they come from more than one. The responses in the file are not
+//! kept. Exporting writes history entries together with their responses,
+//! so they can be opened in devtools and other HAR viewers. Credentials in
+//! headers, query parameters and bodies are masked unless asked for.
+
+use std::error::Error;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::utils::{
+    crud::{bodies::BodyModes, history::HistoryEntryData, requests::HTTPMethods},
+    interchange::{
+        body_mode_for, item_path, json_text, request_name, BodyPartSpec, BodySpec, CollectionSpec,
+        KeyValueSpec, ParsedCollection, RequestSpec,
+    },
+    query_params::{build_query, decode_component, parse_query, replace_query},
+};
+
+/// Headers the client sets on its own when sending.
+const SKIPPED_HEADERS: [&str; 3] = ["host", "content-length", "connection"];
+
+/// Parts of a name that mark a header, parameter or field as a credential.
+/// Compared without case, dashes and underscores.
+const SECRET_NAME_PARTS: [&str; 8] = [
+    "auth",
+    "cookie",
+    "token",
+    "secret",
+    "password",
+    "apikey",
+    "session",
+    "signature",
+];
+
+/// What masked values are replaced with.
+const REDACTED: &str = "REDACTED";
+
+/// Whether a JSON document is a HAR file.
+pub fn is_har_file(document: &Value) -> bool {
+    document["log"]["entries"].is_array()
+}
+
+/// A request of a HAR file, and what the import preview filters it on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HarEntry {
+    pub request: RequestSpec,
+    pub domain: String,
+    /// Zero when the request got no response.
+    pub status: i64,
+    /// Content type of the response, without parameters.
+    pub content_type: String,
+    pub unmapped: Vec<String>,
+}
+
+/// Which entries to import. Empty fields match every entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HarFilter {
+    pub domain: String,
+    pub method: String,
+    pub content_type: String,
+}
+
+impl HarFilter {
+    pub fn matches(&self, entry: &HarEntry) -> bool {
+        (self.domain.is_empty() || self.domain == entry.domain)
+            && (self.method.is_empty() || self.method == entry.request.method.to_string())
+            && (self.content_type.is_empty() || self.content_type == entry.content_type)
+    }
+}
+
+/// The values entries can be filtered on, each sorted and listed once.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HarFilterValues {
+    pub domains: Vec<String>,
+    pub methods: Vec<String>,
+    pub content_types: Vec<String>,
+}
+
+pub fn filter_values(entries: &[HarEntry]) -> HarFilterValues {
+    let distinct = |values: Vec<String>| {
+        let mut values: Vec<String> = values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect();
+        values.sort();
+        values.dedup();
+        values
+    };
+    HarFilterValues {
+        domains: distinct(entries.iter().map(|entry| entry.domain.clone()).collect()),
+        methods: distinct(
+            entries
+                .iter()
+                .map(|entry| entry.request.method.to_string())
+                .collect(),
+        ),
+        content_types: distinct(
+            entries
+                .iter()
+                .map(|entry| entry.content_type.clone())
+                .collect(),
+        ),
+    }
+}
+
+/// A content type without its parameters.
+fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn parse_body(post_data: &Value, path: &str, unmapped: &mut Vec<String>) -> BodySpec {
+    let mime_type = json_text(&post_data["mimeType"]);
+    let text = json_text(&post_data["text"]);
+    let params = post_data["params"]
+        .as_array()
+        .filter(|params| !params.is_empty());
+    let (mode, content_type) = body_mode_for(&mime_type);
+
+    match (mode, params) {
+        (mode @ (BodyModes::FormUrlEncoded | BodyModes::Multipart), Some(params)) => {
+            let mut parts = Vec::new();
+            for param in params {
+                let name = json_text(&param["name"]);
+                let file_name = json_text(&param["fileName"]);
+                let is_file = mode == BodyModes::Multipart && !file_name.is_empty();
+                if is_file {
+                    unmapped.push(format!("{}: contents of file field {}", path, name));
+                }
+                parts.push(BodyPartSpec {
+                    value: match is_file {
+                        true => file_name,
+                        false => json_text(&param["value"]),
+                    },
+                    name,
+                    is_file,
+                    enabled: true,
+                });
+            }
+            BodySpec {
+                // The boundary is made up again when sending.
+                content_type: match mode {
+                    BodyModes::Multipart => String::new(),
+                    _ => content_type,
+                },
+                mode,
+                payload: String::new(),
+                parts,
+            }
+        }
+        (BodyModes::FormUrlEncoded, None) if text.contains('=') => BodySpec {
+            mode: BodyModes::FormUrlEncoded,
+            content_type,
+            payload: String::new(),
+            parts: text
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    BodyPartSpec {
+                        name: decode_component(name),
+                        value: decode_component(value),
+                        is_file: false,
+                        enabled: true,
+                    }
+                })
+                .collect(),
+        },
+        (BodyModes::FormUrlEncoded, None) => BodySpec {
+            mode: BodyModes::Raw,
+            content_type: mime_type,
+            payload: text,
+            parts: Vec::new(),
+        },
+        (BodyModes::Multipart, None) => {
+            unmapped.push(format!("{}: multipart body without its fields", path));
+            BodySpec {
+                mode: BodyModes::Multipart,
+                ..Default::default()
+            }
+        }
+        (mode, _) => BodySpec {
+            mode: match text.is_empty() && mime_type.is_empty() {
+                true => BodyModes::None,
+                false => mode,
+            },
+            content_type,
+            payload: text,
+            parts: Vec::new(),
+        },
+    }
+}
+
+fn parse_entry(entry: &Value) -> HarEntry {
+    let request = &entry["request"];
+    let url = json_text(&request["url"]);
+    let name = request_name(&url);
+    let mut unmapped = Vec::new();
+
+    let method_name = json_text(&request["method"]).to_uppercase();
+    let method = HTTPMethods::from_string(&method_name).unwrap_or_else(|| {
+        unmapped.push(format!("{}: method {}, sent as GET", name, method_name));
+        HTTPMethods::Get
+    });
+    let mut spec = RequestSpec::new(&name, method, &url);
+
+    let has_body = request["postData"].is_object();
+    for header in request["headers"].as_array().into_iter().flatten() {
+        let header_name = json_text(&header["name"]);
+        let lowercase = header_name.to_ascii_lowercase();
+        // HTTP/2 pseudo headers, and the content type the body keeps.
+        if header_name.starts_with(':')
+            || SKIPPED_HEADERS.contains(&lowercase.as_str())
+            || (has_body && lowercase == "content-type")
+        {
+            continue;
+        }
+        spec.headers.push(KeyValueSpec::new(
+            &header_name,
+            &json_text(&header["value"]),
+            true,
+        ));
+    }
+    if has_body {
+        spec.body = parse_body(&request["postData"], &name, &mut unmapped);
+    }
+
+    HarEntry {
+        domain: Url::parse(&url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(str::to_string))
+            .unwrap_or_default(),
+        request: spec,
+        status: entry["response"]["status"].as_i64().unwrap_or(0),
+        content_type: essence(&json_text(&entry["response"]["content"]["mimeType"])),
+        unmapped,
+    }
+}
+
+/// Read the entries of a HAR file, in the order they were recorded.
+pub fn parse_entries(contents: &str) -> Result<Vec<HarEntry>, Box<dyn Error>> {
+    let document: Value = serde_json::from_str(contents)?;
+    if !is_har_file(&document) {
+        return Err("Not a HAR file.".into());
+    }
+    Ok(document["log"]["entries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(parse_entry)
+        .collect())
+}
+
+/// A collection of the entries of a HAR file that match a filter.
+pub fn parse_har(
+    contents: &str,
+    name: &str,
+    filter: &HarFilter,
+) -> Result<ParsedCollection, Box<dyn Error>> {
+    let entries: Vec<HarEntry> = parse_entries(contents)?
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    let use_folders = filter_values(&entries).domains.len() > 1;
+
+    let mut collection = CollectionSpec::new(name);
+    let mut unmapped = Vec::new();
+    for mut entry in entries {
+        if use_folders {
+            entry.request.folder = entry.domain.clone();
+            for item in &mut entry.unmapped {
+                *item = item_path(&entry.domain, item);
+            }
+        }
+        unmapped.extend(entry.unmapped);
+        collection.requests.push(entry.request);
+    }
+    Ok(ParsedCollection {
+        collection,
+        unmapped,
+    })
+}
+
+/// Name for a collection imported from a HAR file with no other name.
+pub fn default_name(contents: &str) -> String {
+    let document: Value = serde_json::from_str(contents).unwrap_or_default();
+    match json_text(&document["log"]["pages"][0]["title"]) {
+        title if title.trim().is_empty() => "HAR import".to_string(),
+        title => title.trim().to_string(),
+    }
+}
+
+fn header_list(headers: &[(String, String)]) -> Value {
+    Value::Array(
+        headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect(),
+    )
+}
+
+fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+    headers
+        .iter()
+        .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+        .unwrap_or("")
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase().replace(['-', '_'], "");
+    name == "key" || SECRET_NAME_PARTS.iter().any(|part| name.contains(part))
+}
+
+/// Mask the values of credential pairs. `None` when there are none.
+fn redact_pairs(pairs: &[(String, String)]) -> Option<Vec<(String, String)>> {
+    if !pairs.iter().any(|(name, _)| is_secret_name(name)) {
+        return None;
+    }
+    Some(
+        pairs
+            .iter()
+            .map(|(name, value)| match is_secret_name(name) {
+                true => (name.clone(), REDACTED.to_string()),
+                false => (name.clone(), value.clone()),
+            })
+            .collect(),
+    )
+}
+
+/// Mask the credential fields of a JSON document, telling whether there
+/// were any.
+fn redact_json(value: &mut Value) -> bool {
+    match value {
+        Value::Object(fields) => {
+            let mut redacted = false;
+            for (name, field) in fields.iter_mut() {
+                if is_secret_name(name) && (field.is_string() || field.is_number()) {
+                    *field = json!(REDACTED);
+                    redacted = true;
+                } else {
+                    redacted |= redact_json(field);
+                }
+            }
+            redacted
+        }
+        Value::Array(items) => {
+            let mut redacted = false;
+            for item in items.iter_mut() {
+                redacted |= redact_json(item);
+            }
+            redacted
+        }
+        _ => false,
+    }
+}
+
+/// Mask the credentials of a JSON or form body, like OAuth token requests
+/// and responses. Other bodies, and ones without credentials, are kept as
+/// they are.
+fn redact_body(body: &[u8], content_type: &str) -> Vec<u8> {
+    let Ok(text) = std::str::from_utf8(body) else {
+        return body.to_vec();
+    };
+    let content_type = content_type.to_lowercase();
+    if content_type.contains("json") {
+        if let Ok(mut document) = serde_json::from_str::<Value>(text) {
+            if redact_json(&mut document) {
+                return document.to_string().into_bytes();
+            }
+        }
+    } else if content_type.contains("x-www-form-urlencoded") {
+        if let Some(pairs) = redact_pairs(&parse_query(&format!("?{}", text))) {
+            return build_query(&pairs).into_bytes();
+        }
+    }
+    body.to_vec()
+}
+
+/// A history entry with its credentials masked.
+fn redact_entry(entry: &HistoryEntryData) -> HistoryEntryData {
+    let mut redacted = entry.clone();
+    if let Some(params) = redact_pairs(&parse_query(&entry.url)) {
+        redacted.url = replace_query(&entry.url, &params);
+    }
+    if let Some(headers) = redact_pairs(&entry.request_headers) {
+        redacted.request_headers = headers;
+    }
+    if let Some(headers) = redact_pairs(&entry.response_headers) {
+        redacted.response_headers = headers;
+    }
+    redacted.request_body = redact_body(
+        &entry.request_body,
+        header_value(&entry.request_headers, "content-type"),
+    );
+    redacted.response_body = redact_body(
+        &entry.response_body,
+        header_value(&entry.response_headers, "content-type"),
+    );
+    redacted
+}
+
+/// History times are UTC, written the way SQLite writes them.
+fn started_date_time(created_at: &str) -> String {
+    match created_at.split_once(' ') {
+        Some((date, time)) => format!("{}T{}.000Z", date, time),
+        None => created_at.to_string(),
+    }
+}
+
+fn write_entry(entry: &HistoryEntryData) -> Value {
+    let mut truncated = Vec::new();
+
+    let mut request = json!({
+        "method": entry.http_method,
+        "url": entry.url,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": header_list(&entry.request_headers),
+        "queryString": parse_query(&entry.url)
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+        "headersSize": -1,
+        "bodySize": entry.request_body.len(),
+    });
+    if !entry.request_body.is_empty() {
+        request["postData"] = json!({
+            "mimeType": header_value(&entry.request_headers, "content-type"),
+            "text": String::from_utf8_lossy(&entry.request_body),
+        });
+    }
+    if entry.request_body_truncated {
+        truncated.push("request");
+    }
+
+    let mime_type = header_value(&entry.response_headers, "content-type");
+    let mut content = json!({
+        "size": entry.response_size,
+        "mimeType": mime_type,
+    });
+    match std::str::from_utf8(&entry.response_body) {
+        Ok(text) => content["text"] = json!(text),
+        Err(_) => {
+            content["text"] = json!(STANDARD.encode(&entry.response_body));
+            content["encoding"] = json!("base64");
+        }
+    }
+    if entry.response_body_truncated {
+        truncated.push("response");
+    }
+    let response = json!({
+        "status": entry.status.unwrap_or(0),
+        "statusText": entry.status_text,
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": header_list(&entry.response_headers),
+        "content": content,
+        "redirectURL": header_value(&entry.response_headers, "location"),
+        "headersSize": -1,
+        "bodySize": entry.response_size,
+    });
+
+    let mut written = json!({
+        "startedDateTime": started_date_time(&entry.created_at),
+        "time": entry.total_ms,
+        "request": request,
+        "response": response,
+        "cache": {},
+        "timings": {
+            "send": 0,
+            "wait": entry.headers_received_ms,
+            "receive": (entry.total_ms - entry.headers_received_ms).max(0),
+        },
+    });
+    if !truncated.is_empty() {
+        written["comment"] = json!(format!(
+            "The {} body was cut off when it was saved to the history.",
+            truncated.join(" and ")
+        ));
+    }
+    if !entry.error.is_empty() {
+        written["_error"] = json!(entry.error);
+    }
+    written
+}
+
+/// Write history entries as a HAR file. Credentials are masked unless
+/// `include_secrets` is set.
+pub fn write_har(
+    entries: &[HistoryEntryData],
+    include_secrets: bool,
+) -> Result<String, Box<dyn Error>> {
+    let written: Vec<Value> = entries
+        .iter()
+        .map(|entry| match include_secrets {
+            true => write_entry(entry),
+            false => write_entry(&redact_entry(entry)),
+        })
+        .collect();
+    let document = json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": "Querry", "version": env!("CARGO_PKG_VERSION") },
+            "entries": written,
+        }
+    });
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                bodies::get_body_parts,
+                history::{create_history_entry, get_history_entry, NewHistoryEntry},
+                requests::{get_collection_requests, get_request_headers},
+            },
+            interchange::import_har,
+        },
+    };
+
+    const HAR: &str = r#"{
+        "log": {
+            "version": "1.2",
+            "creator": {"name": "WebInspector", "version": "537.36"},
+            "pages": [{"title": "https://shop.example.com/"}],
+            "entries": [
+                {
+                    "request": {
+                        "method": "GET",
+                        "url": "https://shop.example.com/api/items?page=2",
+                        "headers": [
+                            {"name": ":authority", "value": "shop.example.com"},
+                            {"name": "Host", "value": "shop.example.com"},
+                            {"name": "Accept", "value": "application/json"}
+                        ]
+                    },
+                    "response": {"status": 200, "content": {"mimeType": "application/json; charset=utf-8"}}
+                },
+                {
+                    "request": {
+                        "method": "POST",
+                        "url": "https://shop.example.com/api/login",
+                        "headers": [{"name": "Content-Type", "value": "application/x-www-form-urlencoded"}],
+                        "postData": {"mimeType": "application/x-www-form-urlencoded", "text": "user=ann&pass=a%26b"}
+                    },
+                    "response": {"status": 302, "content": {"mimeType": "text/html"}}
+                },
+                {
+                    "request": {
+                        "method": "POST",
+                        "url": "https://cdn.example.com/upload",
+                        "headers": [],
+                        "postData": {
+                            "mimeType": "multipart/form-data; boundary=xyz",
+                            "params": [
+                                {"name": "title", "value": "Cat"},
+                                {"name": "photo", "fileName": "cat.png"}
+                            ]
+                        }
+                    },
+                    "response": {"status": 0, "content": {"mimeType": ""}}
+                },
+                {
+                    "request": {"method": "GET", "url": "https://cdn.example.com/logo.png", "headers": []},
+                    "response": {"status": 200, "content": {"mimeType": "image/png"}}
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_entries() {
+        let entries = parse_entries(HAR).unwrap();
+        assert!(entries.len() == 4);
+
+        let items = &entries[0];
+        assert!(items.domain == "shop.example.com" && items.status == 200);
+        assert!(items.content_type == "application/json");
+        assert!(items.request.name == "/api/items");
+        assert!(
+            items.request.headers == vec![KeyValueSpec::new("Accept", "application/json", true)]
+        );
+
+        let login = &entries[1].request;
+        assert!(login.headers.is_empty());
+        assert!(login.body.mode == BodyModes::FormUrlEncoded);
+        assert!(login.body.parts[1].name == "pass" && login.body.parts[1].value == "a&b");
+
+        let upload = &entries[2];
+        assert!(upload.request.body.mode == BodyModes::Multipart);
+        assert!(upload.request.body.content_type.is_empty());
+        assert!(upload.request.body.parts[1].is_file);
+        assert!(upload.unmapped == vec!["/upload: contents of file field photo"]);
+
+        assert!(parse_entries(r#"{"info": {}}"#).is_err());
+    }
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = parse_entries(HAR).unwrap();
+        let values = filter_values(&entries);
+        assert!(values.domains == vec!["cdn.example.com", "shop.example.com"]);
+        assert!(values.methods == vec!["GET", "POST"]);
+        assert!(values.content_types == vec!["application/json", "image/png", "text/html"]);
+
+        let filter = HarFilter {
+            domain: "cdn.example.com".to_string(),
+            ..Default::default()
+        };
+        let parsed = parse_har(HAR, "Shop", &filter).unwrap();
+        assert!(parsed.collection.requests.len() == 2);
+        // A single domain needs no folders.
+        assert!(parsed.collection.requests[0].folder.is_empty());
+
+        let filter = HarFilter {
+            method: "POST".to_string(),
+            ..Default::default()
+        };
+        let parsed = parse_har(HAR, "Shop", &filter).unwrap();
+        assert!(parsed.collection.requests[0].folder == "shop.example.com");
+        assert!(parsed.unmapped == vec!["cdn.example.com / /upload: contents of file field photo"]);
+        assert!(default_name(HAR) == "https://shop.example.com/");
+    }
+
+    fn history_entry() -> NewHistoryEntry {
+        NewHistoryEntry {
+            request_name: "Create item".to_string(),
+            http_method: "POST".to_string(),
+            url: "https://shop.example.com/api/items?draft=true".to_string(),
+            request_headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            request_body: b"{\"name\": \"Cup\"}".to_vec(),
+            status: Some(201),
+            status_text: "Created".to_string(),
+            response_headers: vec![("Content-Type".to_string(), "image/png".to_string())],
+            response_body: vec![0x89, 0x50, 0xff],
+            response_size: 3,
+            headers_received_ms: 40,
+            total_ms: 55,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_har() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let summary = create_history_entry(&history_entry(), &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+
+        let written = write_har(&[entry], false).unwrap();
+        let document: Value = serde_json::from_str(&written).unwrap();
+        let har_entry = &document["log"]["entries"][0];
+        assert!(document["log"]["version"] == "1.2");
+        assert!(har_entry["startedDateTime"]
+            .as_str()
+            .unwrap()
+            .ends_with(".000Z"));
+        assert!(
+            har_entry["request"]["queryString"][0] == json!({"name": "draft", "value": "true"})
+        );
+        assert!(har_entry["response"]["status"] == 201);
+        assert!(har_entry["response"]["content"]["encoding"] == "base64");
+        assert!(har_entry["timings"]["receive"] == 15);
+
+        // What is written reads back as the request that was sent.
+        let read = parse_entries(&written).unwrap();
+        assert!(read[0].request.method == HTTPMethods::Post);
+        assert!(read[0].request.body.mode == BodyModes::Json);
+        assert!(read[0].request.body.payload == "{\"name\": \"Cup\"}");
+        assert!(read[0].content_type == "image/png");
+    }
+
+    #[tokio::test]
+    async fn test_write_har_masks_secrets() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut new_entry = history_entry();
+        new_entry.url = "https://shop.example.com/api/token?api_key=k1&page=2".to_string();
+        new_entry
+            .request_headers
+            .push(("Authorization".to_string(), "Bearer abc123".to_string()));
+        new_entry.response_headers =
+            vec![("Content-Type".to_string(), "application/json".to_string())];
+        new_entry.response_body = b"{\"access_token\": \"t0k3n\", \"expires_in\": 3600}".to_vec();
+        let summary = create_history_entry(&new_entry, &db).await.unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+
+        let written = write_har(std::slice::from_ref(&entry), false).unwrap();
+        assert!(!written.contains("abc123") && !written.contains("k1"));
+        assert!(!written.contains("t0k3n"));
+        let document: Value = serde_json::from_str(&written).unwrap();
+        let request = &document["log"]["entries"][0]["request"];
+        assert!(request["headers"][1] == json!({"name": "Authorization", "value": REDACTED}));
+        assert!(
+            request["queryString"][0] == json!({"name": "api_key", "value": REDACTED})
+                && request["queryString"][1] == json!({"name": "page", "value": "2"})
+        );
+        // Bodies without credentials are written as they are.
+        assert!(request["postData"]["text"] == "{\"name\": \"Cup\"}");
+
+        let written = write_har(&[entry], true).unwrap();
+        assert!(written.contains("Bearer abc123") && written.contains("t0k3n"));
+    }
+
+    #[tokio::test]
+    async fn test_import_har() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let filter = HarFilter {
+            content_type: "image/png".to_string(),
+            ..Default::default()
+        };
+        let report = import_har(HAR, "Shop", &filter, &db).await.unwrap();
+        assert!(report.requests == 1 && report.collection.name == "Shop");
+        let requests = get_collection_requests(&db, &report.collection.id)
+            .await
+            .unwrap();
+        assert!(requests[0].url == Some("https://cdn.example.com/logo.png".to_string()));
+
+        let report = import_har(HAR, "Everything", &HarFilter::default(), &db)
+            .await
+            .unwrap();
+        let requests = get_collection_requests(&db, &report.collection.id)
+            .await
+            .unwrap();
+        assert!(requests.len() == 4 && requests[0].name == "/api/items");
+        let headers = get_request_headers(&requests[0].id, &db).await.unwrap();
+        assert!(headers.len() == 1);
+        let parts = get_body_parts(&requests[1].id, &db).await.unwrap();
+        assert!(parts.len() == 2 && parts[0].value == "ann");
+
+        let nothing = HarFilter {
+            method: "DELETE".to_string(),
+            ..Default::default()
+        };
+        assert!(import_har(HAR, "None", &nothing, &db).await.is_err());
+    }
+}
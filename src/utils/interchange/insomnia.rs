@@ -0,0 +1,876 @@
+//! Insomnia v4 exports.
+//!
+//! Each workspace is a collection and request groups are folders. The base
+//! environment of a workspace becomes its collection variables, the
+//! environments under it are created as regular environments. Insomnia
+//! writes variables as `{{ _.name }}`, which are turned into `{{name}}`
+//! and back.
+
+use std::error::Error;
+
+use serde_json::{json, Map, Value};
+
+use crate::utils::{
+    crud::{
+        auth::{ApiKeyPlacement, AuthTypes},
+        bodies::BodyModes,
+        oauth2::OAuth2GrantTypes,
+        requests::{HTTPMethods, ProtocolTypes},
+    },
+    interchange::{
+        body_mode_for, file_stem, item_path, json_text, AuthSpec, BodyPartSpec, BodySpec,
+        CollectionSpec, EnvironmentSpec, ExportedFile, KeyValueSpec, OAuth2Spec, ParsedCollection,
+        RequestSpec, FOLDER_SEPARATOR,
+    },
+    query_params::{parse_query, split_url, url_from_params, ParamRow},
+};
+
+const EXPORT_FORMAT: u64 = 4;
+
+/// Whether a JSON document is an Insomnia export of any version.
+pub fn is_insomnia_export(document: &Value) -> bool {
+    document["_type"] == "export" && document["__export_format"].is_number()
+}
+
+fn is_disabled(value: &Value) -> bool {
+    value["disabled"].as_bool().unwrap_or(false)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
+
+/// Rewrite every `{{ ... }}` in a text. `rewrite` gets the trimmed inside
+/// and returns the replacement, or `None` to leave it alone.
+fn rewrite_variables(text: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rewritten = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        let original = &rest[start..start + length + 2];
+        rewritten.push_str(&rest[..start]);
+        match rewrite(original[2..original.len() - 2].trim()) {
+            Some(replacement) => rewritten.push_str(&replacement),
+            None => rewritten.push_str(original),
+        }
+        rest = &rest[start + length + 2..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// `{{name}}` variables in the form Insomnia reads.
+fn to_insomnia_variables(text: &str) -> String {
+    rewrite_variables(text, |name| {
+        is_variable_name(name).then(|| format!("{{{{ _.{} }}}}", name))
+    })
+}
+
+/// Environment data, with nested objects flattened to dotted names.
+fn flatten_data(data: &Value, prefix: &str, variables: &mut Vec<KeyValueSpec>) {
+    let Some(object) = data.as_object() else {
+        return;
+    };
+    for (key, value) in object {
+        let name = match prefix.is_empty() {
+            true => key.clone(),
+            false => format!("{}.{}", prefix, key),
+        };
+        match value {
+            Value::Object(_) => flatten_data(value, &name, variables),
+            Value::Array(_) => variables.push(KeyValueSpec::new(&name, &value.to_string(), true)),
+            value => variables.push(KeyValueSpec::new(&name, &json_text(value), true)),
+        }
+    }
+}
+
+fn sort_key(resource: &Value) -> f64 {
+    resource["metaSortKey"].as_f64().unwrap_or(0.0)
+}
+
+struct Parser<'a> {
+    resources: &'a [Value],
+    unmapped: Vec<String>,
+    requests: Vec<RequestSpec>,
+}
+
+impl<'a> Parser<'a> {
+    fn report(&mut self, path: &str, what: &str) {
+        let line = format!("{}: {}", path, what);
+        if !self.unmapped.contains(&line) {
+            self.unmapped.push(line);
+        }
+    }
+
+    /// Resources under a parent, in the order Insomnia shows them.
+    fn children(&self, parent_id: &str) -> Vec<&'a Value> {
+        let mut children: Vec<&Value> = self
+            .resources
+            .iter()
+            .filter(|resource| resource["parentId"] == parent_id)
+            .collect();
+        children.sort_by(|a, b| sort_key(a).total_cmp(&sort_key(b)));
+        children
+    }
+
+    /// Text with its variables in the `{{name}}` form. Template tags and
+    /// expressions are kept as they are and reported.
+    fn text(&mut self, value: &Value, path: &str) -> String {
+        let text = json_text(value);
+        if text.contains("{%") {
+            self.report(path, "template tags");
+        }
+        let mut expressions = Vec::new();
+        let converted = rewrite_variables(&text, |inside| {
+            let name = inside.strip_prefix("_.").unwrap_or(inside).trim();
+            if is_variable_name(name) {
+                Some(format!("{{{{{}}}}}", name))
+            } else {
+                expressions.push(inside.to_string());
+                None
+            }
+        });
+        for expression in expressions {
+            self.report(path, &format!("template {{{{ {} }}}}", expression));
+        }
+        converted
+    }
+
+    fn key_values(&mut self, rows: &Value, path: &str) -> Vec<KeyValueSpec> {
+        let mut parsed = Vec::new();
+        for row in rows.as_array().into_iter().flatten() {
+            let name = self.text(&row["name"], path);
+            let value = self.text(&row["value"], path);
+            parsed.push(KeyValueSpec::new(&name, &value, !is_disabled(row)));
+        }
+        parsed
+    }
+
+    fn report_scripts(&mut self, resource: &Value, path: &str) {
+        if !json_text(&resource["preRequestScript"]).trim().is_empty() {
+            self.report(path, "pre-request script");
+        }
+        if !json_text(&resource["afterResponseScript"])
+            .trim()
+            .is_empty()
+        {
+            self.report(path, "after-response script");
+        }
+    }
+
+    /// Auth settings, `None` when they are inherited.
+    fn parse_auth(&mut self, auth: &Value, path: &str) -> Option<AuthSpec> {
+        let auth_type = auth["type"].as_str()?;
+        if is_disabled(auth) {
+            return Some(AuthSpec::new(AuthTypes::None));
+        }
+
+        let parsed = match auth_type {
+            "none" => AuthSpec::new(AuthTypes::None),
+            "basic" | "digest" => {
+                let mut parsed = AuthSpec::new(if auth_type == "basic" {
+                    AuthTypes::Basic
+                } else {
+                    AuthTypes::Digest
+                });
+                parsed.username = self.text(&auth["username"], path);
+                parsed.secret = self.text(&auth["password"], path);
+                parsed
+            }
+            "bearer" => {
+                let prefix = json_text(&auth["prefix"]);
+                if !prefix.is_empty() && prefix != "Bearer" {
+                    self.report(path, &format!("token prefix {}", prefix));
+                }
+                let mut parsed = AuthSpec::new(AuthTypes::Bearer);
+                parsed.secret = self.text(&auth["token"], path);
+                parsed
+            }
+            "apikey" => {
+                let mut parsed = AuthSpec::new(AuthTypes::ApiKey);
+                parsed.key_name = self.text(&auth["key"], path);
+                parsed.secret = self.text(&auth["value"], path);
+                match auth["addTo"].as_str().unwrap_or("header") {
+                    "queryParams" => parsed.placement = ApiKeyPlacement::Query,
+                    "header" => {}
+                    other => {
+                        self.report(path, &format!("API key sent as {}", other));
+                        return Some(AuthSpec::new(AuthTypes::None));
+                    }
+                }
+                parsed
+            }
+            "oauth2" => {
+                let grant_type = match auth["grantType"].as_str().unwrap_or("") {
+                    "client_credentials" => OAuth2GrantTypes::ClientCredentials,
+                    "password" => OAuth2GrantTypes::Password,
+                    "authorization_code" => OAuth2GrantTypes::AuthorizationCode,
+                    other => {
+                        self.report(path, &format!("OAuth 2.0 {} grant", other));
+                        return Some(AuthSpec::new(AuthTypes::None));
+                    }
+                };
+                let mut parsed = AuthSpec::new(AuthTypes::OAuth2);
+                parsed.username = self.text(&auth["username"], path);
+                parsed.secret = self.text(&auth["password"], path);
+                parsed.oauth2 = Some(OAuth2Spec {
+                    grant_type,
+                    auth_url: self.text(&auth["authorizationUrl"], path),
+                    token_url: self.text(&auth["accessTokenUrl"], path),
+                    client_id: self.text(&auth["clientId"], path),
+                    client_secret: self.text(&auth["clientSecret"], path),
+                    scope: self.text(&auth["scope"], path),
+                    redirect_uri: self.text(&auth["redirectUrl"], path),
+                });
+                parsed
+            }
+            other => {
+                self.report(path, &format!("{} auth", other));
+                AuthSpec::new(AuthTypes::None)
+            }
+        };
+        Some(parsed)
+    }
+
+    fn parse_body(&mut self, body: &Value, path: &str) -> BodySpec {
+        let mime_type = json_text(&body["mimeType"]);
+        let text = self.text(&body["text"], path);
+        if mime_type.is_empty() && text.is_empty() {
+            return BodySpec::default();
+        }
+
+        let (mode, content_type) = body_mode_for(&mime_type);
+        match mode {
+            BodyModes::FormUrlEncoded | BodyModes::Multipart => {
+                let mut parts = Vec::new();
+                for field in body["params"].as_array().into_iter().flatten() {
+                    let is_file = field["type"] == "file";
+                    let value = match is_file {
+                        true => json_text(&field["fileName"]),
+                        false => self.text(&field["value"], path),
+                    };
+                    parts.push(BodyPartSpec {
+                        name: self.text(&field["name"], path),
+                        value,
+                        is_file,
+                        enabled: !is_disabled(field),
+                    });
+                }
+                BodySpec {
+                    mode,
+                    content_type,
+                    payload: String::new(),
+                    parts,
+                }
+            }
+            _ if mime_type == "application/octet-stream" => BodySpec {
+                mode: BodyModes::Binary,
+                payload: json_text(&body["fileName"]),
+                ..Default::default()
+            },
+            // Already holds `{"query": ..., "variables": ...}`.
+            _ if mime_type == "application/graphql" => BodySpec {
+                mode: BodyModes::Json,
+                payload: text,
+                ..Default::default()
+            },
+            mode => BodySpec {
+                mode,
+                content_type,
+                payload: text,
+                parts: Vec::new(),
+            },
+        }
+    }
+
+    fn parse_request(
+        &mut self,
+        resource: &Value,
+        name: &str,
+        folder: &str,
+        auth: &Option<AuthSpec>,
+        headers: &[KeyValueSpec],
+        variables: &[KeyValueSpec],
+    ) {
+        let path = item_path(folder, name);
+
+        let method_name = json_text(&resource["method"]).to_uppercase();
+        let method = HTTPMethods::from_string(&method_name).unwrap_or_else(|| {
+            self.report(&path, &format!("method {}, sent as GET", method_name));
+            HTTPMethods::Get
+        });
+        let url = self.text(&resource["url"], &path);
+        let mut spec = RequestSpec::new(name, method, &url);
+        spec.folder = folder.to_string();
+
+        // Insomnia keeps params next to the URL rather than in it.
+        let parameters = self.key_values(&resource["parameters"], &path);
+        if !parameters.is_empty() {
+            let mut params: Vec<KeyValueSpec> = parse_query(&url)
+                .into_iter()
+                .map(|(name, value)| KeyValueSpec::new(&name, &value, true))
+                .collect();
+            params.extend(parameters);
+            let rows: Vec<ParamRow> = params
+                .iter()
+                .map(|param| ParamRow {
+                    name: param.name.clone(),
+                    value: param.value.clone(),
+                    enabled: param.enabled,
+                })
+                .collect();
+            spec.url = url_from_params(&url, &rows);
+            spec.params = params;
+        }
+
+        spec.headers = headers.to_vec();
+        spec.headers
+            .extend(self.key_values(&resource["headers"], &path));
+        spec.body = self.parse_body(&resource["body"], &path);
+        spec.auth = match self.parse_auth(&resource["authentication"], &path) {
+            Some(parsed) => Some(parsed),
+            None => auth.clone(),
+        };
+        spec.variables = variables.to_vec();
+        self.report_scripts(resource, &path);
+
+        self.requests.push(spec);
+    }
+
+    fn parse_children(
+        &mut self,
+        parent_id: &str,
+        folder: &str,
+        auth: &Option<AuthSpec>,
+        headers: &[KeyValueSpec],
+        variables: &[KeyValueSpec],
+    ) {
+        for resource in self.children(parent_id) {
+            let id = json_text(&resource["_id"]);
+            let name = match json_text(&resource["name"]) {
+                name if name.trim().is_empty() => "Untitled".to_string(),
+                name => name,
+            };
+            let path = item_path(folder, &name);
+
+            match resource["_type"].as_str().unwrap_or("") {
+                "request_group" => {
+                    let folder_auth = match self.parse_auth(&resource["authentication"], &path) {
+                        Some(parsed) => Some(parsed),
+                        None => auth.clone(),
+                    };
+                    let mut folder_headers = headers.to_vec();
+                    folder_headers.extend(self.key_values(&resource["headers"], &path));
+                    let mut folder_variables = variables.to_vec();
+                    flatten_data(&resource["environment"], "", &mut folder_variables);
+                    self.report_scripts(resource, &path);
+                    self.parse_children(
+                        &id,
+                        &path,
+                        &folder_auth,
+                        &folder_headers,
+                        &folder_variables,
+                    );
+                }
+                "request" => self.parse_request(resource, &name, folder, auth, headers, variables),
+                // Handled with the workspace.
+                "environment" => {}
+                "cookie_jar" => {
+                    if resource["cookies"]
+                        .as_array()
+                        .is_some_and(|cookies| !cookies.is_empty())
+                    {
+                        self.report(&path, "cookies");
+                    }
+                }
+                "api_spec" => {
+                    if !json_text(&resource["contents"]).trim().is_empty() {
+                        self.report(&path, "API spec");
+                    }
+                }
+                "grpc_request" => self.report(&path, "gRPC request"),
+                "websocket_request" => self.report(&path, "WebSocket request"),
+                other => self.report(&path, &other.replace('_', " ")),
+            }
+        }
+    }
+
+    fn parse_workspace(mut self, workspace: &Value) -> ParsedCollection {
+        let id = json_text(&workspace["_id"]);
+        let name = match json_text(&workspace["name"]) {
+            name if name.trim().is_empty() => "Imported workspace".to_string(),
+            name => name,
+        };
+        let mut collection = CollectionSpec::new(&name);
+
+        for base in self.children(&id) {
+            if base["_type"] != "environment" {
+                continue;
+            }
+            flatten_data(&base["data"], "", &mut collection.variables);
+            let base_id = json_text(&base["_id"]);
+            for environment in self.children(&base_id) {
+                if environment["_type"] != "environment" {
+                    continue;
+                }
+                let mut variables = Vec::new();
+                flatten_data(&environment["data"], "", &mut variables);
+                collection.environments.push(EnvironmentSpec {
+                    name: json_text(&environment["name"]),
+                    variables,
+                });
+            }
+        }
+
+        self.parse_children(&id, "", &None, &[], &[]);
+        collection.requests = self.requests;
+        ParsedCollection {
+            collection,
+            unmapped: self.unmapped,
+        }
+    }
+}
+
+/// Read every workspace of an Insomnia v4 export.
+pub fn parse_collections(contents: &str) -> Result<Vec<ParsedCollection>, Box<dyn Error>> {
+    let document: Value = serde_json::from_str(contents)?;
+    if !is_insomnia_export(&document) {
+        return Err("Not an Insomnia export.".into());
+    }
+    if document["__export_format"].as_u64() != Some(EXPORT_FORMAT) {
+        return Err("Only Insomnia v4 exports can be imported.".into());
+    }
+
+    let resources = document["resources"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let collections: Vec<ParsedCollection> = resources
+        .iter()
+        .filter(|resource| resource["_type"] == "workspace")
+        .map(|workspace| {
+            Parser {
+                resources: &resources,
+                unmapped: Vec::new(),
+                requests: Vec::new(),
+            }
+            .parse_workspace(workspace)
+        })
+        .collect();
+    if collections.is_empty() {
+        return Err("The export has no workspaces.".into());
+    }
+    Ok(collections)
+}
+
+fn key_value_list(rows: &[KeyValueSpec]) -> Value {
+    rows.iter()
+        .map(|row| {
+            json!({
+                "name": to_insomnia_variables(&row.name),
+                "value": to_insomnia_variables(&row.value),
+                "disabled": !row.enabled,
+            })
+        })
+        .collect()
+}
+
+fn write_auth(auth: &Option<AuthSpec>) -> Value {
+    let Some(auth) = auth else {
+        return json!({});
+    };
+    let text = |value: &str| to_insomnia_variables(value);
+
+    match auth.auth_type {
+        AuthTypes::Inherit => json!({}),
+        AuthTypes::None => json!({ "type": "none" }),
+        AuthTypes::Basic | AuthTypes::Digest => json!({
+            "type": auth.auth_type.to_string(),
+            "username": text(&auth.username),
+            "password": text(&auth.secret),
+        }),
+        AuthTypes::Bearer => json!({ "type": "bearer", "token": text(&auth.secret), "prefix": "" }),
+        AuthTypes::ApiKey => json!({
+            "type": "apikey",
+            "key": text(&auth.key_name),
+            "value": text(&auth.secret),
+            "addTo": match auth.placement {
+                ApiKeyPlacement::Header => "header",
+                ApiKeyPlacement::Query => "queryParams",
+            },
+        }),
+        AuthTypes::OAuth2 => {
+            let mut written = json!({
+                "type": "oauth2",
+                "username": text(&auth.username),
+                "password": text(&auth.secret),
+            });
+            if let Some(oauth2) = &auth.oauth2 {
+                written["grantType"] = json!(match oauth2.grant_type {
+                    OAuth2GrantTypes::ClientCredentials => "client_credentials",
+                    OAuth2GrantTypes::Password => "password",
+                    OAuth2GrantTypes::AuthorizationCode => "authorization_code",
+                });
+                written["usePkce"] =
+                    json!(oauth2.grant_type == OAuth2GrantTypes::AuthorizationCode);
+                written["authorizationUrl"] = json!(text(&oauth2.auth_url));
+                written["accessTokenUrl"] = json!(text(&oauth2.token_url));
+                written["clientId"] = json!(text(&oauth2.client_id));
+                written["clientSecret"] = json!(text(&oauth2.client_secret));
+                written["scope"] = json!(text(&oauth2.scope));
+                written["redirectUrl"] = json!(text(&oauth2.redirect_uri));
+            }
+            written
+        }
+    }
+}
+
+fn write_body(body: &BodySpec) -> Value {
+    let text = |default: &str| {
+        let mime_type = match body.content_type.is_empty() {
+            true => default.to_string(),
+            false => body.content_type.clone(),
+        };
+        json!({ "mimeType": mime_type, "text": to_insomnia_variables(&body.payload) })
+    };
+    let form = |mime_type: &str| {
+        let params: Vec<Value> = body
+            .parts
+            .iter()
+            .map(|part| match part.is_file {
+                true => json!({
+                    "name": to_insomnia_variables(&part.name),
+                    "type": "file",
+                    "fileName": part.value,
+                    "disabled": !part.enabled,
+                }),
+                false => json!({
+                    "name": to_insomnia_variables(&part.name),
+                    "value": to_insomnia_variables(&part.value),
+                    "disabled": !part.enabled,
+                }),
+            })
+            .collect();
+        json!({ "mimeType": mime_type, "params": params })
+    };
+
+    match body.mode {
+        BodyModes::None => json!({}),
+        BodyModes::Raw => text("text/plain"),
+        BodyModes::Json => text("application/json"),
+        BodyModes::Xml => text("application/xml"),
+        BodyModes::FormUrlEncoded => form("application/x-www-form-urlencoded"),
+        BodyModes::Multipart => form("multipart/form-data"),
+        BodyModes::Binary => {
+            json!({ "mimeType": "application/octet-stream", "fileName": body.payload })
+        }
+    }
+}
+
+/// Hands out resource ids and sort keys, in the order resources are written.
+struct Resources {
+    written: Vec<Value>,
+}
+
+impl Resources {
+    fn next_id(&self, prefix: &str) -> String {
+        format!("{}_{}", prefix, self.written.len() + 1)
+    }
+
+    fn push(&mut self, resource: Value) {
+        self.written.push(resource);
+    }
+}
+
+fn write_collection(spec: &CollectionSpec, resources: &mut Resources, unmapped: &mut Vec<String>) {
+    let workspace_id = resources.next_id("wrk");
+    resources.push(json!({
+        "_id": workspace_id,
+        "_type": "workspace",
+        "parentId": null,
+        "name": spec.name,
+        "description": "",
+        "scope": "collection",
+    }));
+
+    let mut data = Map::new();
+    for variable in &spec.variables {
+        match variable.enabled {
+            true => {
+                data.insert(
+                    variable.name.clone(),
+                    json!(to_insomnia_variables(&variable.value)),
+                );
+            }
+            false => unmapped.push(format!(
+                "{}: disabled variable {}",
+                spec.name, variable.name
+            )),
+        }
+    }
+    let order: Vec<String> = data.keys().cloned().collect();
+    let base_id = resources.next_id("env");
+    resources.push(json!({
+        "_id": base_id,
+        "_type": "environment",
+        "parentId": workspace_id,
+        "name": "Base Environment",
+        "data": data,
+        "dataPropertyOrder": { "&": order },
+    }));
+
+    // Insomnia has neither, so requests get a copy.
+    let collection_headers = &spec.headers;
+    let collection_auth = &spec.auth;
+
+    let mut folder_ids: Vec<(String, String)> = Vec::new();
+    for request in &spec.requests {
+        let path = item_path(&request.folder, &request.name);
+        if request.protocol != ProtocolTypes::Http {
+            unmapped.push(format!(
+                "{}: {} request, written as HTTP",
+                path, request.protocol
+            ));
+        }
+        if !request.variables.is_empty() {
+            unmapped.push(format!("{}: request variables", path));
+        }
+
+        let mut parent_id = workspace_id.clone();
+        let mut folder_path = String::new();
+        if !request.folder.is_empty() {
+            for folder in request.folder.split(FOLDER_SEPARATOR) {
+                folder_path = item_path(&folder_path, folder);
+                parent_id = match folder_ids.iter().find(|(known, _)| *known == folder_path) {
+                    Some((_, id)) => id.clone(),
+                    None => {
+                        let id = resources.next_id("fld");
+                        resources.push(json!({
+                            "_id": id,
+                            "_type": "request_group",
+                            "parentId": parent_id,
+                            "name": folder,
+                            "metaSortKey": resources.written.len(),
+                        }));
+                        folder_ids.push((folder_path.clone(), id.clone()));
+                        id
+                    }
+                };
+            }
+        }
+
+        let mut headers = collection_headers.clone();
+        headers.extend(request.headers.iter().cloned());
+        let (base, _, _) = split_url(&request.url);
+        let params = match request.params.is_empty() {
+            true => parse_query(&request.url)
+                .into_iter()
+                .map(|(name, value)| KeyValueSpec::new(&name, &value, true))
+                .collect(),
+            false => request.params.clone(),
+        };
+        let auth = match &request.auth {
+            Some(auth) => Some(auth.clone()),
+            None => collection_auth.clone(),
+        };
+
+        let id = resources.next_id("req");
+        resources.push(json!({
+            "_id": id,
+            "_type": "request",
+            "parentId": parent_id,
+            "name": request.name,
+            "method": request.method.to_string(),
+            "url": to_insomnia_variables(base),
+            "parameters": key_value_list(&params),
+            "headers": key_value_list(&headers),
+            "body": write_body(&request.body),
+            "authentication": write_auth(&auth),
+            "metaSortKey": resources.written.len(),
+        }));
+    }
+}
+
+/// Write collections as workspaces of a single Insomnia v4 export.
+pub fn write_collections(specs: &[CollectionSpec]) -> Result<ExportedFile, Box<dyn Error>> {
+    let mut resources = Resources {
+        written: Vec::new(),
+    };
+    let mut unmapped = Vec::new();
+    for spec in specs {
+        write_collection(spec, &mut resources, &mut unmapped);
+    }
+
+    let document = json!({
+        "_type": "export",
+        "__export_format": EXPORT_FORMAT,
+        "__export_source": "querry",
+        "resources": resources.written,
+    });
+    let file_name = match specs {
+        [spec] => format!("{}.insomnia.json", file_stem(&spec.name)),
+        _ => "collections.insomnia.json".to_string(),
+    };
+    Ok(ExportedFile {
+        file_name,
+        contents: serde_json::to_string_pretty(&document)?,
+        unmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                environments::{get_environment_variables, get_environments},
+                requests::get_collection_requests,
+            },
+            interchange::import_file,
+        },
+    };
+
+    const EXPORT: &str = r#"{
+        "_type": "export",
+        "__export_format": 4,
+        "__export_source": "insomnia.desktop.app:v2023.5.8",
+        "resources": [
+            {"_id": "req_2", "_type": "request", "parentId": "fld_1", "name": "Create pet",
+             "method": "POST", "url": "{{ _.base }}/pets", "metaSortKey": 2,
+             "body": {"mimeType": "application/json", "text": "{\"name\": \"{{ _.pet }}\"}"},
+             "headers": [{"name": "X-Trace", "value": "{% uuid 'v4' %}", "disabled": true}],
+             "authentication": {}},
+            {"_id": "wrk_1", "_type": "workspace", "parentId": null, "name": "Pet store"},
+            {"_id": "env_1", "_type": "environment", "parentId": "wrk_1", "name": "Base Environment",
+             "data": {"base": "https://pets.example.com", "limits": {"page": 20}}},
+            {"_id": "env_2", "_type": "environment", "parentId": "env_1", "name": "Staging",
+             "data": {"base": "https://staging.pets.example.com"}},
+            {"_id": "fld_1", "_type": "request_group", "parentId": "wrk_1", "name": "Pets",
+             "metaSortKey": 1, "environment": {"pet": "Rex"},
+             "authentication": {"type": "bearer", "token": "{{ _.token }}"}},
+            {"_id": "req_1", "_type": "request", "parentId": "fld_1", "name": "List pets",
+             "method": "GET", "url": "{{ _.base }}/pets?sort=name", "metaSortKey": 1,
+             "parameters": [{"name": "limit", "value": "{{ _.limits.page }}"},
+                            {"name": "debug", "value": "1", "disabled": true}],
+             "authentication": {"type": "apikey", "key": "api_key", "value": "k", "addTo": "queryParams"},
+             "afterResponseScript": "insomnia.test('ok', () => {});"},
+            {"_id": "req_3", "_type": "request", "parentId": "wrk_1", "name": "Upload",
+             "method": "PUT", "url": "{{base}}/upload", "metaSortKey": 5,
+             "body": {"mimeType": "multipart/form-data", "params": [
+                {"name": "photo", "type": "file", "fileName": "/tmp/rex.png"},
+                {"name": "note", "value": "hi", "disabled": true}]},
+             "authentication": {"type": "ntlm", "username": "a", "password": "b"}},
+            {"_id": "ws_1", "_type": "websocket_request", "parentId": "wrk_1", "name": "Feed", "metaSortKey": 6}
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_collections() {
+        let parsed = parse_collections(EXPORT).unwrap();
+        assert!(parsed.len() == 1);
+        let collection = &parsed[0].collection;
+
+        assert!(collection.name == "Pet store");
+        assert!(
+            collection.variables
+                == vec![
+                    KeyValueSpec::new("base", "https://pets.example.com", true),
+                    KeyValueSpec::new("limits.page", "20", true),
+                ]
+        );
+        assert!(collection.environments.len() == 1);
+        assert!(collection.environments[0].name == "Staging");
+        assert!(collection.requests.len() == 3);
+
+        let list = &collection.requests[0];
+        assert!(list.name == "List pets" && list.folder == "Pets");
+        assert!(list.url == "{{base}}/pets?sort=name&limit={{limits.page}}");
+        assert!(list.params.len() == 3 && !list.params[2].enabled);
+        let auth = list.auth.clone().unwrap();
+        assert!(auth.auth_type == AuthTypes::ApiKey && auth.placement == ApiKeyPlacement::Query);
+        assert!(list.variables == vec![KeyValueSpec::new("pet", "Rex", true)]);
+
+        let create = &collection.requests[1];
+        assert!(create.body.mode == BodyModes::Json);
+        assert!(create.body.payload == "{\"name\": \"{{pet}}\"}");
+        assert!(!create.headers[0].enabled);
+        // Inherited from the request group.
+        let auth = create.auth.clone().unwrap();
+        assert!(auth.auth_type == AuthTypes::Bearer && auth.secret == "{{token}}");
+
+        let upload = &collection.requests[2];
+        assert!(upload.folder.is_empty());
+        assert!(upload.body.mode == BodyModes::Multipart);
+        assert!(upload.body.parts[0].is_file && upload.body.parts[0].value == "/tmp/rex.png");
+        assert!(!upload.body.parts[1].enabled);
+        assert!(upload.auth.clone().unwrap().auth_type == AuthTypes::None);
+
+        assert!(
+            parsed[0].unmapped
+                == vec![
+                    "Pets / List pets: after-response script",
+                    "Pets / Create pet: template tags",
+                    "Upload: ntlm auth",
+                    "Feed: WebSocket request",
+                ]
+        );
+    }
+
+    #[test]
+    fn test_parse_collections_rejects_other_versions() {
+        let v3 = r#"{"_type": "export", "__export_format": 3, "resources": []}"#;
+        assert!(parse_collections(v3).is_err());
+        let empty = r#"{"_type": "export", "__export_format": 4, "resources": []}"#;
+        assert!(parse_collections(empty).is_err());
+    }
+
+    #[test]
+    fn test_variables_round_trip() {
+        let written = to_insomnia_variables("{{base}}/users/{{ id }}?q={{$guid}}");
+        assert!(written == "{{ _.base }}/users/{{ _.id }}?q={{$guid}}");
+    }
+
+    #[test]
+    fn test_write_collections_round_trip() {
+        let mut collection = parse_collections(EXPORT).unwrap().remove(0).collection;
+        collection.environments.clear();
+        for request in collection.requests.iter_mut() {
+            request.variables.clear();
+        }
+        collection.requests[0].folder = "Pets / Dogs".to_string();
+
+        let exported = write_collections(&[collection.clone()]).unwrap();
+        assert!(exported.file_name == "Pet store.insomnia.json");
+        assert!(exported.unmapped.is_empty());
+
+        let read = parse_collections(&exported.contents).unwrap().remove(0);
+        assert!(read.unmapped == vec!["Pets / Create pet: template tags"]);
+        assert!(read.collection == collection);
+    }
+
+    #[tokio::test]
+    async fn test_import_insomnia_export() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let reports = import_file(EXPORT, &db).await.unwrap();
+        assert!(reports.len() == 1 && reports[0].requests == 3);
+
+        let requests = get_collection_requests(&db, &reports[0].collection.id)
+            .await
+            .unwrap();
+        assert!(requests[0].name == "List pets" && requests[2].name == "Upload");
+        let environments = get_environments(&db).await.unwrap();
+        assert!(environments.len() == 1 && environments[0].name == "Staging");
+        let variables = get_environment_variables(&environments[0].id, &db)
+            .await
+            .unwrap();
+        assert!(variables[0].value == "https://staging.pets.example.com");
+    }
+}
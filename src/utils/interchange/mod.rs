@@ -0,0 +1,1535 @@
+//! Moving collections in and out of other tools.
+//!
+//! Importers turn a file into a [`CollectionSpec`], which [`save_collection`]
+//! writes through the regular crud functions in a single transaction, so a
+//! file that fails half way leaves nothing behind.
+
+use std::{error::Error, fmt};
+
+use serde_json::Value;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::utils::{
+    crud::{
+        auth::{
+            get_auth_secret, get_collection_auth, get_request_auth, update_auth_secret,
+            update_collection_auth, update_request_auth, ApiKeyPlacement, AuthData, AuthTypes,
+        },
+        bodies::{
+            create_body_part, delete_body_part, get_body_parts, get_request_body, update_body_part,
+            update_request_body, BodyModes,
+        },
+        collections::{
+            create_collection, create_collection_header, create_collection_variable,
+            get_all_collections, get_collection_headers, get_collection_variables,
+            get_single_collection, update_collection_header, update_collection_item,
+            update_collection_variable, CollectionData,
+        },
+        environments::{
+            create_environment, create_environment_variable, update_environment_variable,
+        },
+        graphql::{
+            get_graphql_query, get_graphql_schema, save_graphql_schema, update_graphql_query,
+            GraphQLQueryData,
+        },
+        grpc::{
+            get_grpc_call, get_grpc_descriptors, save_grpc_descriptors, update_grpc_call,
+            GrpcCallData,
+        },
+        mqtt::{get_mqtt_config, update_mqtt_config, MqttConfigData},
+        oauth2::{
+            get_oauth2_client_secret, get_oauth2_config, update_oauth2_client_secret,
+            update_oauth2_config, OAuth2GrantTypes,
+        },
+        params::{get_request_params, replace_request_params},
+        requests::{
+            create_request, create_request_header, create_request_variable,
+            get_collection_requests, get_request_headers, get_request_variables,
+            get_single_request, stored_method, update_request_folder, update_request_header,
+            update_request_item, update_request_variable, HTTPMethods, ProtocolTypes, RequestData,
+        },
+        socketio::{get_socketio_config, update_socketio_config, SocketIoConfigData},
+        sse::{get_sse_config, update_sse_config, SseConfigData},
+        websocket::{
+            create_websocket_template, get_websocket_subprotocols, get_websocket_templates,
+            update_websocket_subprotocols, MessageFormats,
+        },
+    },
+    query_params::{parse_query, split_url, url_from_params, ParamRow},
+    snippets::resolved_request,
+};
+
+pub mod curl;
+pub mod har;
+pub mod insomnia;
+pub mod native;
+pub mod openapi;
+pub mod postman;
+
+/// Separates nested folder names in a request's folder.
+pub const FOLDER_SEPARATOR: &str = " / ";
+
+/// A row of a headers, params, variables or form table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyValueSpec {
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+}
+
+impl KeyValueSpec {
+    pub fn new(name: &str, value: &str, enabled: bool) -> KeyValueSpec {
+        KeyValueSpec {
+            name: name.to_string(),
+            value: value.to_string(),
+            enabled,
+        }
+    }
+}
+
+/// A field of a form body. For file parts the value holds the file path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodyPartSpec {
+    pub name: String,
+    pub value: String,
+    pub is_file: bool,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BodySpec {
+    pub mode: BodyModes,
+    pub content_type: String,
+    /// Text of raw, JSON and XML bodies, the file path of binary ones.
+    pub payload: String,
+    pub parts: Vec<BodyPartSpec>,
+}
+
+impl Default for BodySpec {
+    fn default() -> Self {
+        BodySpec {
+            mode: BodyModes::None,
+            content_type: String::new(),
+            payload: String::new(),
+            parts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OAuth2Spec {
+    pub grant_type: OAuth2GrantTypes,
+    pub auth_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    pub redirect_uri: String,
+}
+
+/// Auth settings with their secret, see [`crate::utils::crud::auth::AuthData`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthSpec {
+    pub auth_type: AuthTypes,
+    pub username: String,
+    pub key_name: String,
+    pub placement: ApiKeyPlacement,
+    pub secret: String,
+    pub oauth2: Option<OAuth2Spec>,
+}
+
+impl AuthSpec {
+    pub fn new(auth_type: AuthTypes) -> AuthSpec {
+        AuthSpec {
+            auth_type,
+            username: String::new(),
+            key_name: String::new(),
+            placement: ApiKeyPlacement::Header,
+            secret: String::new(),
+            oauth2: None,
+        }
+    }
+}
+
+/// A message saved with a WebSocket request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebSocketTemplateSpec {
+    pub name: String,
+    pub format: MessageFormats,
+    pub payload: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WebSocketSpec {
+    /// Comma separated, as typed.
+    pub subprotocols: String,
+    pub templates: Vec<WebSocketTemplateSpec>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GraphQLSpec {
+    pub operation: GraphQLQueryData,
+    /// The introspected `__schema`, so the editor can complete fields
+    /// without fetching it again.
+    pub schema: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GrpcSpec {
+    pub call: GrpcCallData,
+    /// The services loaded last, an encoded `FileDescriptorSet`.
+    pub descriptors: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RequestSpec {
+    pub name: String,
+    pub protocol: ProtocolTypes,
+    /// Nested folder names joined with [`FOLDER_SEPARATOR`], empty outside of folders.
+    pub folder: String,
+    pub method: HTTPMethods,
+    /// The URL without the disabled params, which only live in `params`.
+    pub url: String,
+    /// Every query param, including disabled ones. Left empty, the params
+    /// table is filled from the URL.
+    pub params: Vec<KeyValueSpec>,
+    pub headers: Vec<KeyValueSpec>,
+    pub body: BodySpec,
+    /// `None` inherits the auth of the collection.
+    pub auth: Option<AuthSpec>,
+    pub variables: Vec<KeyValueSpec>,
+    /// Settings kept for other protocols are exported too, so switching a
+    /// request back finds them again. `None` when nothing was set.
+    pub websocket: Option<WebSocketSpec>,
+    pub graphql: Option<GraphQLSpec>,
+    pub grpc: Option<GrpcSpec>,
+    pub sse: Option<SseConfigData>,
+    pub socketio: Option<SocketIoConfigData>,
+    pub mqtt: Option<MqttConfigData>,
+}
+
+impl RequestSpec {
+    pub fn new(name: &str, method: HTTPMethods, url: &str) -> RequestSpec {
+        RequestSpec {
+            name: name.to_string(),
+            protocol: ProtocolTypes::Http,
+            folder: String::new(),
+            method,
+            url: url.to_string(),
+            params: Vec::new(),
+            headers: Vec::new(),
+            body: BodySpec::default(),
+            auth: None,
+            variables: Vec::new(),
+            websocket: None,
+            graphql: None,
+            grpc: None,
+            sse: None,
+            socketio: None,
+            mqtt: None,
+        }
+    }
+}
+
+/// An environment that came with a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvironmentSpec {
+    pub name: String,
+    pub variables: Vec<KeyValueSpec>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectionSpec {
+    pub name: String,
+    /// `None` picks a random icon, like new collections get.
+    pub icon: Option<String>,
+    pub headers: Vec<KeyValueSpec>,
+    pub variables: Vec<KeyValueSpec>,
+    pub auth: Option<AuthSpec>,
+    /// In the order they are listed in the sidebar.
+    pub requests: Vec<RequestSpec>,
+    /// Environments are shared by all collections, so these are created
+    /// next to the existing ones. Exports leave them out.
+    pub environments: Vec<EnvironmentSpec>,
+}
+
+impl CollectionSpec {
+    pub fn new(name: &str) -> CollectionSpec {
+        CollectionSpec {
+            name: name.to_string(),
+            icon: None,
+            headers: Vec::new(),
+            variables: Vec::new(),
+            auth: None,
+            requests: Vec::new(),
+            environments: Vec::new(),
+        }
+    }
+}
+
+/// A collection read from a file, and whatever in it had no equivalent here.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedCollection {
+    pub collection: CollectionSpec,
+    /// One line per item that was left out or changed, e.g.
+    /// `"Users / Create user: test script"`.
+    pub unmapped: Vec<String>,
+}
+
+/// What an import created.
+#[derive(Clone, Debug)]
+pub struct ImportReport {
+    pub collection: CollectionData,
+    /// Requests created.
+    pub requests: usize,
+    /// Saved requests brought up to date, when updating from a spec.
+    pub updated: usize,
+    pub unmapped: Vec<String>,
+}
+
+/// Formats collections can be exported to.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExportFormats {
+    /// Lossless, and the one to share collections through git with.
+    Querry,
+    Postman,
+    Insomnia,
+}
+
+impl fmt::Display for ExportFormats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportFormats::Querry => write!(f, "querry"),
+            ExportFormats::Postman => write!(f, "postman"),
+            ExportFormats::Insomnia => write!(f, "insomnia"),
+        }
+    }
+}
+
+impl ExportFormats {
+    pub fn from_string(s: &str) -> Option<ExportFormats> {
+        match s {
+            "querry" => Some(ExportFormats::Querry),
+            "postman" => Some(ExportFormats::Postman),
+            "insomnia" => Some(ExportFormats::Insomnia),
+            _ => None,
+        }
+    }
+}
+
+/// An exported file, and whatever the format had no room for.
+#[derive(Clone, Debug)]
+pub struct ExportedFile {
+    pub file_name: String,
+    pub contents: String,
+    pub unmapped: Vec<String>,
+}
+
+/// Text of a JSON string, number or boolean. Anything else is empty.
+pub fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => number.to_string(),
+        Value::Bool(flag) => flag.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// The body mode for a content type, and the content type to keep when it
+/// differs from the one the mode sends by default.
+pub fn body_mode_for(content_type: &str) -> (BodyModes, String) {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    let mode = if essence == "application/json" || essence.ends_with("+json") {
+        BodyModes::Json
+    } else if essence.ends_with("/xml") || essence.ends_with("+xml") {
+        BodyModes::Xml
+    } else if essence == "application/x-www-form-urlencoded" {
+        BodyModes::FormUrlEncoded
+    } else if essence == "multipart/form-data" {
+        BodyModes::Multipart
+    } else {
+        BodyModes::Raw
+    };
+
+    let kept = match mode.default_content_type() {
+        _ if essence.is_empty() => String::new(),
+        Some(default) if default == content_type.trim() => String::new(),
+        _ => content_type.trim().to_string(),
+    };
+    (mode, kept)
+}
+
+/// Where an unmapped item was found, for the import report.
+pub fn item_path(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", folder, FOLDER_SEPARATOR, name)
+    }
+}
+
+/// A name for the request, taken from the path of its URL.
+pub fn request_name(url: &str) -> String {
+    let (base, _, _) = split_url(url);
+    let without_scheme = base.split_once("://").map(|(_, rest)| rest).unwrap_or(base);
+    match without_scheme.split_once('/') {
+        Some((_, path)) if !path.trim_end_matches('/').is_empty() => {
+            format!("/{}", path.trim_end_matches('/'))
+        }
+        _ => without_scheme.to_string(),
+    }
+}
+
+/// What saved auth settings belong to.
+enum AuthOwner<'a> {
+    Request(&'a str),
+    Collection(&'a str),
+}
+
+async fn save_auth(
+    auth: &AuthSpec,
+    owner: AuthOwner<'_>,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let saved = match owner {
+        AuthOwner::Request(request_id) => {
+            update_request_auth(
+                request_id,
+                auth.auth_type.clone(),
+                &auth.username,
+                &auth.key_name,
+                auth.placement.clone(),
+                &mut *conn,
+            )
+            .await?
+        }
+        AuthOwner::Collection(collection_id) => {
+            update_collection_auth(
+                collection_id,
+                auth.auth_type.clone(),
+                &auth.username,
+                &auth.key_name,
+                auth.placement.clone(),
+                &mut *conn,
+            )
+            .await?
+        }
+    };
+    if !auth.secret.is_empty() {
+        update_auth_secret(&saved.id, &auth.secret, &mut *conn).await?;
+    }
+
+    if let Some(oauth2) = &auth.oauth2 {
+        update_oauth2_config(
+            &saved.id,
+            oauth2.grant_type.clone(),
+            &oauth2.auth_url,
+            &oauth2.token_url,
+            &oauth2.client_id,
+            &oauth2.scope,
+            &oauth2.redirect_uri,
+            &mut *conn,
+        )
+        .await?;
+        if !oauth2.client_secret.is_empty() {
+            update_oauth2_client_secret(&saved.id, &oauth2.client_secret, &mut *conn).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Add rows to a request's headers.
+async fn save_headers(
+    request_id: &str,
+    headers: &[KeyValueSpec],
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    for header in headers {
+        let saved =
+            create_request_header(request_id, &header.name, &header.value, &mut *conn).await?;
+        if !header.enabled {
+            update_request_header(&saved.id, &header.name, &header.value, false, &mut *conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Add rows to a request's variables.
+async fn save_variables(
+    request_id: &str,
+    variables: &[KeyValueSpec],
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    for variable in variables {
+        let saved =
+            create_request_variable(request_id, &variable.name, &variable.value, &mut *conn)
+                .await?;
+        if !variable.enabled {
+            update_request_variable(
+                &saved.id,
+                &variable.name,
+                &variable.value,
+                false,
+                &mut *conn,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Set a request's body and add its parts.
+async fn save_body(
+    request_id: &str,
+    body: &BodySpec,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    if body.mode != BodyModes::None || !body.payload.is_empty() {
+        update_request_body(
+            request_id,
+            body.mode.clone(),
+            &body.content_type,
+            &body.payload,
+            &mut *conn,
+        )
+        .await?;
+    }
+    for part in &body.parts {
+        let saved = create_body_part(
+            request_id,
+            &part.name,
+            &part.value,
+            part.is_file,
+            &mut *conn,
+        )
+        .await?;
+        if !part.enabled {
+            update_body_part(
+                &saved.id,
+                &part.name,
+                &part.value,
+                part.is_file,
+                false,
+                &mut *conn,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a request with everything in it, returning its id.
+async fn save_request(
+    request: &RequestSpec,
+    collection_id: &str,
+    conn: &mut SqliteConnection,
+) -> Result<String, Box<dyn Error>> {
+    let created = create_request(request.protocol.clone(), collection_id, &mut *conn).await?;
+
+    let params: Vec<ParamRow> = if request.params.is_empty() {
+        parse_query(&request.url)
+            .into_iter()
+            .map(|(name, value)| ParamRow {
+                name,
+                value,
+                enabled: true,
+            })
+            .collect()
+    } else {
+        request
+            .params
+            .iter()
+            .map(|param| ParamRow {
+                name: param.name.clone(),
+                value: param.value.clone(),
+                enabled: param.enabled,
+            })
+            .collect()
+    };
+    let url = if request.params.is_empty() {
+        request.url.clone()
+    } else {
+        url_from_params(&request.url, &params)
+    };
+    update_request_item(
+        &created.id,
+        &request.name,
+        request.protocol.clone(),
+        request.method.clone(),
+        &url,
+        &mut *conn,
+    )
+    .await?;
+    if !request.folder.is_empty() {
+        update_request_folder(&created.id, &request.folder, &mut *conn).await?;
+    }
+    replace_request_params(&created.id, &params, &mut *conn).await?;
+
+    save_headers(&created.id, &request.headers, &mut *conn).await?;
+    save_variables(&created.id, &request.variables, &mut *conn).await?;
+    save_body(&created.id, &request.body, &mut *conn).await?;
+
+    if let Some(auth) = &request.auth {
+        save_auth(auth, AuthOwner::Request(&created.id), &mut *conn).await?;
+    }
+    if let Some(websocket) = &request.websocket {
+        update_websocket_subprotocols(&created.id, &websocket.subprotocols, &mut *conn).await?;
+        for template in &websocket.templates {
+            create_websocket_template(
+                &created.id,
+                &template.name,
+                template.format.clone(),
+                &template.payload,
+                &mut *conn,
+            )
+            .await?;
+        }
+    }
+    if let Some(graphql) = &request.graphql {
+        update_graphql_query(&created.id, &graphql.operation, &mut *conn).await?;
+        if let Some(schema) = &graphql.schema {
+            save_graphql_schema(&created.id, schema, &mut *conn).await?;
+        }
+    }
+    if let Some(grpc) = &request.grpc {
+        update_grpc_call(&created.id, &grpc.call, &mut *conn).await?;
+        if let Some(descriptors) = &grpc.descriptors {
+            save_grpc_descriptors(&created.id, descriptors, &mut *conn).await?;
+        }
+    }
+    if let Some(sse) = &request.sse {
+        update_sse_config(&created.id, sse, &mut *conn).await?;
+    }
+    if let Some(socketio) = &request.socketio {
+        update_socketio_config(&created.id, socketio, &mut *conn).await?;
+    }
+    if let Some(mqtt) = &request.mqtt {
+        update_mqtt_config(&created.id, mqtt, &mut *conn).await?;
+    }
+
+    Ok(created.id)
+}
+
+/// Write a collection with everything in it, returning its id.
+async fn insert_collection(
+    spec: &CollectionSpec,
+    conn: &mut SqliteConnection,
+) -> Result<String, Box<dyn Error>> {
+    let collection = create_collection(spec.name.clone(), &mut *conn).await?;
+    for header in &spec.headers {
+        let saved =
+            create_collection_header(&collection.id, &header.name, &header.value, &mut *conn)
+                .await?;
+        if !header.enabled {
+            update_collection_header(&saved.id, &header.name, &header.value, false, &mut *conn)
+                .await?;
+        }
+    }
+    for variable in &spec.variables {
+        let saved =
+            create_collection_variable(&collection.id, &variable.name, &variable.value, &mut *conn)
+                .await?;
+        if !variable.enabled {
+            update_collection_variable(
+                &saved.id,
+                &variable.name,
+                &variable.value,
+                false,
+                &mut *conn,
+            )
+            .await?;
+        }
+    }
+    if let Some(auth) = &spec.auth {
+        save_auth(auth, AuthOwner::Collection(&collection.id), &mut *conn).await?;
+    }
+
+    // The sidebar lists the newest request first, so the last one is created first.
+    for request in spec.requests.iter().rev() {
+        save_request(request, &collection.id, &mut *conn).await?;
+    }
+
+    for environment in &spec.environments {
+        let created = create_environment(&environment.name, &mut *conn).await?;
+        for variable in &environment.variables {
+            let saved = create_environment_variable(
+                &created.id,
+                &variable.name,
+                &variable.value,
+                &mut *conn,
+            )
+            .await?;
+            if !variable.enabled {
+                update_environment_variable(
+                    &saved.id,
+                    &variable.name,
+                    &variable.value,
+                    false,
+                    &mut *conn,
+                )
+                .await?;
+            }
+        }
+    }
+
+    if let Some(icon) = &spec.icon {
+        let saved = get_single_collection(&collection.id, &mut *conn).await?;
+        update_collection_item(
+            &saved.id,
+            &saved.name,
+            icon,
+            saved.requests_count,
+            &mut *conn,
+        )
+        .await?;
+    }
+
+    Ok(collection.id)
+}
+
+/// Create collections with everything in them, or nothing at all when any
+/// part of them fails to save. They are listed in the sidebar in the order given.
+pub async fn save_collections(
+    specs: &[CollectionSpec],
+    pool: &SqlitePool,
+) -> Result<Vec<CollectionData>, Box<dyn Error>> {
+    let mut transaction = pool.begin().await?;
+
+    let mut ids = Vec::new();
+    for spec in specs.iter().rev() {
+        ids.push(insert_collection(spec, &mut transaction).await?);
+    }
+
+    transaction.commit().await?;
+    let mut collections = Vec::new();
+    for id in ids.iter().rev() {
+        collections.push(get_single_collection(id, pool).await?);
+    }
+    Ok(collections)
+}
+
+/// Create a single collection, see [`save_collections`].
+pub async fn save_collection(
+    spec: &CollectionSpec,
+    pool: &SqlitePool,
+) -> Result<CollectionData, Box<dyn Error>> {
+    let mut collections = save_collections(std::slice::from_ref(spec), pool).await?;
+    Ok(collections.remove(0))
+}
+
+/// Read a file in any of the formats there is an importer for.
+pub fn parse_file(contents: &str) -> Result<Vec<ParsedCollection>, Box<dyn Error>> {
+    let document = openapi::read_document(contents)?;
+    if native::is_native_file(&document) {
+        return native::parse_collections(contents);
+    }
+    if postman::is_postman_collection(&document) {
+        return Ok(vec![postman::parse_collection(contents)?]);
+    }
+    if insomnia::is_insomnia_export(&document) {
+        return insomnia::parse_collections(contents);
+    }
+    if har::is_har_file(&document) {
+        let name = har::default_name(contents);
+        return Ok(vec![har::parse_har(
+            contents,
+            &name,
+            &har::HarFilter::default(),
+        )?]);
+    }
+    if openapi::is_openapi_spec(&document) {
+        return Ok(vec![openapi::parse_spec(contents)?]);
+    }
+    Err("Not a Querry, Postman, Insomnia or HAR file, or an OpenAPI spec.".into())
+}
+
+/// Import every collection in a file.
+pub async fn import_file(
+    contents: &str,
+    pool: &SqlitePool,
+) -> Result<Vec<ImportReport>, Box<dyn Error>> {
+    let parsed = parse_file(contents)?;
+    let specs: Vec<CollectionSpec> = parsed.iter().map(|item| item.collection.clone()).collect();
+    let collections = save_collections(&specs, pool).await?;
+
+    Ok(collections
+        .into_iter()
+        .zip(parsed)
+        .map(|(collection, parsed)| ImportReport {
+            collection,
+            requests: parsed.collection.requests.len(),
+            updated: 0,
+            unmapped: parsed.unmapped,
+        })
+        .collect())
+}
+
+/// Import the entries of a HAR file that match a filter into a new collection.
+pub async fn import_har(
+    contents: &str,
+    name: &str,
+    filter: &har::HarFilter,
+    pool: &SqlitePool,
+) -> Result<ImportReport, Box<dyn Error>> {
+    let parsed = har::parse_har(contents, name, filter)?;
+    if parsed.collection.requests.is_empty() {
+        return Err("No entries match the filters.".into());
+    }
+    let collection = save_collection(&parsed.collection, pool).await?;
+
+    Ok(ImportReport {
+        collection,
+        requests: parsed.collection.requests.len(),
+        updated: 0,
+        unmapped: parsed.unmapped,
+    })
+}
+
+/// The collection a spec was imported into before, found by the title of
+/// the spec. `None` for files other than OpenAPI specs.
+pub async fn find_spec_collection(
+    contents: &str,
+    pool: &SqlitePool,
+) -> Result<Option<CollectionData>, Box<dyn Error>> {
+    if !openapi::is_openapi_spec(&openapi::read_document(contents)?) {
+        return Ok(None);
+    }
+    let name = openapi::parse_spec(contents)?.collection.name;
+    Ok(get_all_collections(pool)
+        .await?
+        .into_iter()
+        .find(|collection| collection.name == name))
+}
+
+/// What a saved request is matched to the operations of a spec by.
+fn operation_key(method: &HTTPMethods, url: &str) -> (String, String) {
+    (method.to_string(), split_url(url).0.to_string())
+}
+
+/// Bring a saved request in line with a newer version of its spec. Values
+/// filled in since, rows added by hand and edited bodies are kept.
+async fn update_request(
+    request: &RequestData,
+    spec: &RequestSpec,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error>> {
+    let saved_params = get_request_params(&request.id, &mut *conn).await?;
+    let params: Vec<ParamRow> = spec
+        .params
+        .iter()
+        .map(
+            |param| match saved_params.iter().find(|saved| saved.name == param.name) {
+                Some(saved) => saved.to_row(),
+                None => ParamRow {
+                    name: param.name.clone(),
+                    value: param.value.clone(),
+                    enabled: param.enabled,
+                },
+            },
+        )
+        .collect();
+    update_request_item(
+        &request.id,
+        &spec.name,
+        spec.protocol.clone(),
+        spec.method.clone(),
+        &url_from_params(&spec.url, &params),
+        &mut *conn,
+    )
+    .await?;
+    update_request_folder(&request.id, &spec.folder, &mut *conn).await?;
+    replace_request_params(&request.id, &params, &mut *conn).await?;
+
+    let saved_headers = get_request_headers(&request.id, &mut *conn).await?;
+    let headers: Vec<KeyValueSpec> = spec
+        .headers
+        .iter()
+        .filter(|header| {
+            !saved_headers
+                .iter()
+                .any(|saved| saved.name.eq_ignore_ascii_case(&header.name))
+        })
+        .cloned()
+        .collect();
+    save_headers(&request.id, &headers, &mut *conn).await?;
+    let saved_variables = get_request_variables(&request.id, &mut *conn).await?;
+    let variables: Vec<KeyValueSpec> = spec
+        .variables
+        .iter()
+        .filter(|variable| {
+            !saved_variables
+                .iter()
+                .any(|saved| saved.name == variable.name)
+        })
+        .cloned()
+        .collect();
+    save_variables(&request.id, &variables, &mut *conn).await?;
+
+    // The body is only replaced when the spec now sends another kind of body.
+    let saved_mode = get_request_body(&request.id, &mut *conn)
+        .await?
+        .and_then(|body| BodyModes::from_string(&body.mode))
+        .unwrap_or(BodyModes::None);
+    if saved_mode != spec.body.mode {
+        for part in get_body_parts(&request.id, &mut *conn).await? {
+            delete_body_part(&part.id, &mut *conn).await?;
+        }
+        save_body(&request.id, &spec.body, &mut *conn).await?;
+    }
+
+    // Same for auth, so usernames and secrets stay.
+    if let Some(auth) = &spec.auth {
+        let saved = get_request_auth(&request.id, &mut *conn).await?;
+        if saved.map(|saved| saved.auth_type) != Some(auth.auth_type.to_string()) {
+            save_auth(auth, AuthOwner::Request(&request.id), &mut *conn).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Update a collection imported from an OpenAPI spec from a newer version
+/// of the spec. Requests are matched to operations by method and path and
+/// updated in place, so their history stays with them. Operations new to
+/// the spec are added, requests of operations that are gone are left alone.
+pub async fn update_from_spec(
+    contents: &str,
+    collection_id: &str,
+    pool: &SqlitePool,
+) -> Result<ImportReport, Box<dyn Error>> {
+    let parsed = openapi::parse_spec(contents)?;
+    let spec = &parsed.collection;
+    let saved_requests = get_collection_requests(pool, collection_id).await?;
+    let saved_keys = saved_requests
+        .iter()
+        .map(|saved| {
+            let method = stored_method(saved.http_method.as_deref())?;
+            Ok(operation_key(&method, saved.url.as_deref().unwrap_or("")))
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let mut transaction = pool.begin().await?;
+
+    let saved_variables = get_collection_variables(collection_id, &mut *transaction).await?;
+    for variable in &spec.variables {
+        if !saved_variables
+            .iter()
+            .any(|saved| saved.name == variable.name)
+        {
+            create_collection_variable(
+                collection_id,
+                &variable.name,
+                &variable.value,
+                &mut *transaction,
+            )
+            .await?;
+        }
+    }
+    if let Some(auth) = &spec.auth {
+        let saved = get_collection_auth(collection_id, &mut *transaction).await?;
+        if saved.map(|saved| saved.auth_type) != Some(auth.auth_type.to_string()) {
+            save_auth(auth, AuthOwner::Collection(collection_id), &mut transaction).await?;
+        }
+    }
+
+    let mut updated = 0;
+    let mut added = Vec::new();
+    for request in &spec.requests {
+        let key = operation_key(&request.method, &request.url);
+        let saved = saved_requests
+            .iter()
+            .zip(&saved_keys)
+            .find(|(_, saved_key)| **saved_key == key)
+            .map(|(saved, _)| saved);
+        match saved {
+            Some(saved) => {
+                update_request(saved, request, &mut transaction).await?;
+                updated += 1;
+            }
+            None => added.push(request),
+        }
+    }
+    // The sidebar lists the newest request first, so the last one is created first.
+    for request in added.iter().rev() {
+        save_request(request, collection_id, &mut transaction).await?;
+    }
+
+    transaction.commit().await?;
+    Ok(ImportReport {
+        collection: get_single_collection(collection_id, pool).await?,
+        requests: added.len(),
+        updated,
+        unmapped: parsed.unmapped,
+    })
+}
+
+/// Create a request from a cURL command, returning it together with the
+/// options that could not be mapped.
+pub async fn import_curl(
+    command: &str,
+    collection_id: &str,
+    pool: &SqlitePool,
+) -> Result<(RequestData, Vec<String>), Box<dyn Error>> {
+    let parsed = curl::parse_command(command)?;
+
+    let mut transaction = pool.begin().await?;
+    let request_id = save_request(&parsed.request, collection_id, &mut transaction).await?;
+    transaction.commit().await?;
+
+    let request = get_single_request(&request_id, pool).await?;
+    Ok((request, parsed.unmapped))
+}
+
+/// The cURL command for a request, sent the way the send button would.
+pub async fn curl_command(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<String, Box<dyn Error>> {
+    let prepared = resolved_request(request, pool).await?;
+    Ok(curl::write_command(&prepared))
+}
+
+async fn load_auth(
+    auth: AuthData,
+    include_secrets: bool,
+    pool: &SqlitePool,
+) -> Result<Option<AuthSpec>, Box<dyn Error>> {
+    let auth_type = AuthTypes::from_string(&auth.auth_type).unwrap_or(AuthTypes::Inherit);
+    if auth_type == AuthTypes::Inherit {
+        return Ok(None);
+    }
+
+    let mut spec = AuthSpec::new(auth_type);
+    spec.username = auth.username;
+    spec.key_name = auth.key_name;
+    spec.placement =
+        ApiKeyPlacement::from_string(&auth.placement).unwrap_or(ApiKeyPlacement::Header);
+    if include_secrets {
+        spec.secret = get_auth_secret(&auth.id, pool).await?;
+    }
+    if let Some(config) = get_oauth2_config(&auth.id, pool).await? {
+        spec.oauth2 = Some(OAuth2Spec {
+            grant_type: OAuth2GrantTypes::from_string(&config.grant_type)
+                .unwrap_or(OAuth2GrantTypes::ClientCredentials),
+            auth_url: config.auth_url,
+            token_url: config.token_url,
+            client_id: config.client_id,
+            client_secret: match include_secrets {
+                true => get_oauth2_client_secret(&auth.id, pool).await?,
+                false => String::new(),
+            },
+            scope: config.scope,
+            redirect_uri: config.redirect_uri,
+        });
+    }
+    Ok(Some(spec))
+}
+
+async fn load_request(
+    request: RequestData,
+    include_secrets: bool,
+    pool: &SqlitePool,
+) -> Result<RequestSpec, Box<dyn Error>> {
+    let method = stored_method(request.http_method.as_deref())?;
+    let mut spec = RequestSpec::new(&request.name, method, &request.url.unwrap_or_default());
+    spec.protocol = ProtocolTypes::from_string(&request.protocol).unwrap_or(ProtocolTypes::Http);
+    spec.folder = request.folder;
+    spec.params = get_request_params(&request.id, pool)
+        .await?
+        .into_iter()
+        .map(|param| KeyValueSpec::new(&param.name, &param.value, param.enabled))
+        .collect();
+    spec.headers = get_request_headers(&request.id, pool)
+        .await?
+        .into_iter()
+        .map(|header| KeyValueSpec::new(&header.name, &header.value, header.enabled))
+        .collect();
+    spec.variables = get_request_variables(&request.id, pool)
+        .await?
+        .into_iter()
+        .map(|variable| KeyValueSpec::new(&variable.name, &variable.value, variable.enabled))
+        .collect();
+
+    if let Some(body) = get_request_body(&request.id, pool).await? {
+        spec.body.mode = BodyModes::from_string(&body.mode).unwrap_or(BodyModes::None);
+        spec.body.content_type = body.content_type;
+        spec.body.payload = body.payload;
+    }
+    spec.body.parts = get_body_parts(&request.id, pool)
+        .await?
+        .into_iter()
+        .map(|part| BodyPartSpec {
+            name: part.name,
+            value: part.value,
+            is_file: part.is_file,
+            enabled: part.enabled,
+        })
+        .collect();
+
+    if let Some(auth) = get_request_auth(&request.id, pool).await? {
+        spec.auth = load_auth(auth, include_secrets, pool).await?;
+    }
+
+    let websocket = WebSocketSpec {
+        subprotocols: get_websocket_subprotocols(&request.id, pool).await?,
+        templates: get_websocket_templates(&request.id, pool)
+            .await?
+            .into_iter()
+            .map(|template| WebSocketTemplateSpec {
+                name: template.name,
+                format: MessageFormats::from_string(&template.format)
+                    .unwrap_or(MessageFormats::Text),
+                payload: template.payload,
+            })
+            .collect(),
+    };
+    spec.websocket = Some(websocket).filter(|websocket| *websocket != WebSocketSpec::default());
+
+    let graphql = GraphQLSpec {
+        operation: get_graphql_query(&request.id, pool).await?,
+        schema: get_graphql_schema(&request.id, pool)
+            .await?
+            .map(|schema| schema.schema),
+    };
+    spec.graphql = Some(graphql).filter(|graphql| *graphql != GraphQLSpec::default());
+
+    let grpc = GrpcSpec {
+        call: get_grpc_call(&request.id, pool).await?,
+        descriptors: get_grpc_descriptors(&request.id, pool)
+            .await?
+            .map(|descriptors| descriptors.descriptors),
+    };
+    spec.grpc = Some(grpc).filter(|grpc| *grpc != GrpcSpec::default());
+    spec.sse = Some(get_sse_config(&request.id, pool).await?)
+        .filter(|sse| *sse != SseConfigData::default());
+    spec.socketio = Some(get_socketio_config(&request.id, pool).await?)
+        .filter(|socketio| *socketio != SocketIoConfigData::default());
+    spec.mqtt = Some(get_mqtt_config(&request.id, pool).await?)
+        .filter(|mqtt| *mqtt != MqttConfigData::default());
+    Ok(spec)
+}
+
+/// Read a saved collection back into a spec for exporting. Secrets are left
+/// empty unless `include_secrets` is set.
+pub async fn load_collection(
+    collection_id: &str,
+    include_secrets: bool,
+    pool: &SqlitePool,
+) -> Result<CollectionSpec, Box<dyn Error>> {
+    let collection = get_single_collection(collection_id, pool).await?;
+
+    let mut spec = CollectionSpec::new(&collection.name);
+    spec.icon = Some(collection.icon);
+    spec.headers = get_collection_headers(collection_id, pool)
+        .await?
+        .into_iter()
+        .map(|header| KeyValueSpec::new(&header.name, &header.value, header.enabled))
+        .collect();
+    spec.variables = get_collection_variables(collection_id, pool)
+        .await?
+        .into_iter()
+        .map(|variable| KeyValueSpec::new(&variable.name, &variable.value, variable.enabled))
+        .collect();
+    if let Some(auth) = get_collection_auth(collection_id, pool).await? {
+        spec.auth = load_auth(auth, include_secrets, pool).await?;
+    }
+    for request in get_collection_requests(pool, collection_id).await? {
+        spec.requests
+            .push(load_request(request, include_secrets, pool).await?);
+    }
+
+    Ok(spec)
+}
+
+/// Every collection, in sidebar order, see [`load_collection`].
+pub async fn load_all_collections(
+    include_secrets: bool,
+    pool: &SqlitePool,
+) -> Result<Vec<CollectionSpec>, Box<dyn Error>> {
+    let mut specs = Vec::new();
+    for collection in get_all_collections(pool).await? {
+        specs.push(load_collection(&collection.id, include_secrets, pool).await?);
+    }
+    Ok(specs)
+}
+
+/// A file name for a collection, without characters file systems object to.
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| match c.is_alphanumeric() || " -_.".contains(c) {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    match stem.trim_matches('.').is_empty() {
+        true => "collection".to_string(),
+        false => stem,
+    }
+}
+
+/// Write collections in the given format. Querry and Insomnia files hold any
+/// number of collections, Postman ones are written one file per collection.
+pub fn export_collections(
+    specs: &[CollectionSpec],
+    format: ExportFormats,
+) -> Result<Vec<ExportedFile>, Box<dyn Error>> {
+    match format {
+        ExportFormats::Querry => Ok(vec![native::write_collections(specs)?]),
+        ExportFormats::Postman => specs.iter().map(postman::write_collection).collect(),
+        ExportFormats::Insomnia => Ok(vec![insomnia::write_collections(specs)?]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::crud::{
+            auth::{get_auth_secret, get_collection_auth, get_request_auth},
+            bodies::{get_body_parts, get_request_body},
+            collections::{get_all_collections, get_collection_headers, get_collection_variables},
+            params::get_request_params,
+            requests::{get_collection_requests, get_request_headers},
+        },
+    };
+
+    fn sample_collection() -> CollectionSpec {
+        let mut collection = CollectionSpec::new("Imported");
+        collection
+            .variables
+            .push(KeyValueSpec::new("base", "https://example.com", true));
+        collection
+            .headers
+            .push(KeyValueSpec::new("X-Debug", "on", false));
+        let mut auth = AuthSpec::new(AuthTypes::Bearer);
+        auth.secret = "token".to_string();
+        collection.auth = Some(auth);
+
+        let mut first = RequestSpec::new("List users", HTTPMethods::Get, "{{base}}/users?page=1");
+        first.folder = "Users".to_string();
+        first.params = vec![
+            KeyValueSpec::new("page", "1", true),
+            KeyValueSpec::new("debug", "true", false),
+        ];
+        let mut second = RequestSpec::new("Create user", HTTPMethods::Post, "{{base}}/users");
+        second.body = BodySpec {
+            mode: BodyModes::Multipart,
+            parts: vec![BodyPartSpec {
+                name: "avatar".to_string(),
+                value: "/tmp/avatar.png".to_string(),
+                is_file: true,
+                enabled: false,
+            }],
+            ..Default::default()
+        };
+        let mut basic = AuthSpec::new(AuthTypes::Basic);
+        basic.username = "admin".to_string();
+        basic.secret = "secret".to_string();
+        second.auth = Some(basic);
+        collection.requests = vec![first, second];
+        collection
+    }
+
+    #[tokio::test]
+    async fn test_save_collection() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let collection = save_collection(&sample_collection(), &db).await.unwrap();
+        assert!(collection.name == "Imported");
+        assert!(collection.requests_count == 2);
+
+        let variables = get_collection_variables(&collection.id, &db).await.unwrap();
+        assert!(variables.len() == 1 && variables[0].value == "https://example.com");
+        let headers = get_collection_headers(&collection.id, &db).await.unwrap();
+        assert!(headers.len() == 1 && !headers[0].enabled);
+        let auth = get_collection_auth(&collection.id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(auth.auth_type == "bearer");
+        assert!(get_auth_secret(&auth.id, &db).await.unwrap() == "token");
+
+        // Listed newest first, which keeps the order of the file.
+        let requests = get_collection_requests(&db, &collection.id).await.unwrap();
+        assert!(requests[0].name == "List users" && requests[1].name == "Create user");
+        assert!(requests[0].folder == "Users" && requests[1].folder.is_empty());
+        assert!(requests[0].url == Some("{{base}}/users?page=1".to_string()));
+        let params = get_request_params(&requests[0].id, &db).await.unwrap();
+        assert!(params.len() == 2 && !params[1].enabled);
+        assert!(get_request_auth(&requests[0].id, &db)
+            .await
+            .unwrap()
+            .is_none());
+
+        let body = get_request_body(&requests[1].id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(body.mode == "form-data");
+        let parts = get_body_parts(&requests[1].id, &db).await.unwrap();
+        assert!(parts.len() == 1 && parts[0].is_file && !parts[0].enabled);
+        let auth = get_request_auth(&requests[1].id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(auth.auth_type == "basic" && auth.username == "admin");
+        assert!(get_request_headers(&requests[1].id, &db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_collection_is_all_or_nothing() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut collection = sample_collection();
+        // Fails on the last request, after everything else was written.
+        let mut oauth2 = AuthSpec::new(AuthTypes::OAuth2);
+        oauth2.oauth2 = Some(OAuth2Spec {
+            grant_type: OAuth2GrantTypes::ClientCredentials,
+            auth_url: String::new(),
+            token_url: String::new(),
+            client_id: String::new(),
+            client_secret: String::new(),
+            scope: String::new(),
+            redirect_uri: String::new(),
+        });
+        collection.requests[0].auth = Some(oauth2);
+        collection.requests[0]
+            .headers
+            .push(KeyValueSpec::new("X-Test", "1", true));
+        sqlx::query("CREATE TRIGGER fail_oauth2 BEFORE INSERT ON oauth2config BEGIN SELECT RAISE(ABORT, 'no'); END")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert!(save_collection(&collection, &db).await.is_err());
+        assert!(get_all_collections(&db).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_load_collection() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+
+        let saved = save_collection(&spec, &db).await.unwrap();
+        assert!(saved.icon == "E009.svg");
+        assert!(load_collection(&saved.id, true, &db).await.unwrap() == spec);
+
+        let without_secrets = load_collection(&saved.id, false, &db).await.unwrap();
+        assert!(without_secrets.auth.unwrap().secret.is_empty());
+        let request_auth = without_secrets.requests[1].auth.clone().unwrap();
+        assert!(request_auth.username == "admin" && request_auth.secret.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_all_collections() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let specs = vec![sample_collection(), CollectionSpec::new("Second")];
+        save_collections(&specs, &db).await.unwrap();
+
+        let loaded = load_all_collections(true, &db).await.unwrap();
+        assert!(loaded[0].name == "Imported" && loaded[1].name == "Second");
+        let files = export_collections(&loaded, ExportFormats::Querry).unwrap();
+        assert!(files.len() == 1);
+        let reports = import_file(&files[0].contents, &db).await.unwrap();
+        assert!(reports.len() == 2 && reports[0].unmapped.is_empty());
+
+        let collections = get_all_collections(&db).await.unwrap();
+        assert!(collections.len() == 4);
+        let reimported = load_collection(&reports[0].collection.id, true, &db)
+            .await
+            .unwrap();
+        assert!(reimported == loaded[0]);
+
+        let files = export_collections(&loaded, ExportFormats::Postman).unwrap();
+        assert!(files.len() == 2 && files[1].file_name == "Second.postman_collection.json");
+    }
+
+    /// Save a collection, export it to a Querry file, import that and load
+    /// the result back.
+    async fn native_round_trip(spec: &CollectionSpec, pool: &SqlitePool) -> CollectionSpec {
+        let saved = save_collection(spec, pool).await.unwrap();
+        let loaded = load_collection(&saved.id, true, pool).await.unwrap();
+        let files = export_collections(&[loaded], ExportFormats::Querry).unwrap();
+        let reports = import_file(&files[0].contents, pool).await.unwrap();
+        load_collection(&reports[0].collection.id, true, pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_websocket_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut chat = RequestSpec::new("Chat", HTTPMethods::Get, "wss://example.com/chat");
+        chat.protocol = ProtocolTypes::Websocket;
+        chat.websocket = Some(WebSocketSpec {
+            subprotocols: "chat, superchat".to_string(),
+            templates: vec![
+                WebSocketTemplateSpec {
+                    name: "Join".to_string(),
+                    format: MessageFormats::Json,
+                    payload: "{\"room\": \"{{room}}\"}".to_string(),
+                },
+                WebSocketTemplateSpec {
+                    name: "Ping".to_string(),
+                    format: MessageFormats::Binary,
+                    payload: "89 00".to_string(),
+                },
+            ],
+        });
+        spec.requests.push(chat);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut users = RequestSpec::new("Users", HTTPMethods::Post, "{{base}}/graphql");
+        users.protocol = ProtocolTypes::GraphQL;
+        users.graphql = Some(GraphQLSpec {
+            operation: GraphQLQueryData {
+                query: "query Users($first: Int) { users(first: $first) { id } }".to_string(),
+                variables: "{\"first\": {{page_size}}}".to_string(),
+                operation_name: "Users".to_string(),
+            },
+            schema: Some("{\"queryType\": {\"name\": \"Query\"}}".to_string()),
+        });
+        spec.requests.push(users);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut echo = RequestSpec::new("Echo", HTTPMethods::Post, "http://localhost:50051");
+        echo.protocol = ProtocolTypes::Grpc;
+        echo.grpc = Some(GrpcSpec {
+            call: GrpcCallData {
+                source: "proto".to_string(),
+                proto_files: "/protos/echo.proto".to_string(),
+                import_paths: "/protos".to_string(),
+                method: "echo.Echo/Say".to_string(),
+                message: "{\"text\": \"{{greeting}}\"}".to_string(),
+            },
+            descriptors: Some(vec![0x0a, 0x00, 0xff]),
+        });
+        spec.requests.push(echo);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_sse_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut feed = RequestSpec::new("Feed", HTTPMethods::Get, "{{base}}/events");
+        feed.protocol = ProtocolTypes::Sse;
+        feed.sse = Some(SseConfigData {
+            last_event_id: "42".to_string(),
+            reconnect: false,
+        });
+        spec.requests.push(feed);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_socketio_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut chat = RequestSpec::new("Chat", HTTPMethods::Get, "{{base}}/chat");
+        chat.protocol = ProtocolTypes::SocketIo;
+        chat.socketio = Some(SocketIoConfigData {
+            path: "/live/".to_string(),
+            listeners: "message\ntyping".to_string(),
+            event: "message".to_string(),
+            payload: "{\"text\": \"hi\"}".to_string(),
+            ack: true,
+        });
+        spec.requests.push(chat);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_mqtt_round_trip() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let mut spec = sample_collection();
+        spec.icon = Some("E009.svg".to_string());
+        let mut sensors =
+            RequestSpec::new("Sensors", HTTPMethods::Get, "wss://broker.example.com/mqtt");
+        sensors.protocol = ProtocolTypes::Mqtt;
+        sensors.mqtt = Some(MqttConfigData {
+            client_id: "querry-test".to_string(),
+            clean_session: false,
+            keep_alive: 30,
+            filters: "sensors/#".to_string(),
+            subscribe_qos: 1,
+            topic: "sensors/kitchen".to_string(),
+            payload: "21.5".to_string(),
+            publish_qos: 2,
+            retain: true,
+        });
+        spec.requests.push(sensors);
+
+        assert!(native_round_trip(&spec, &db).await == spec);
+    }
+
+    #[tokio::test]
+    async fn test_import_curl_and_copy_it_back() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = save_collection(&sample_collection(), &db).await.unwrap();
+
+        let command =
+            "curl -X POST '{{base}}/users?role=admin' -H 'X-Trace: 1' -d 'name=Ann' -u ann:pw";
+        let (request, unmapped) = import_curl(command, &collection.id, &db).await.unwrap();
+        assert!(unmapped.is_empty());
+        assert!(request.name == "/users" && request.collection_id == collection.id);
+        let params = get_request_params(&request.id, &db).await.unwrap();
+        assert!(params.len() == 1 && params[0].name == "role");
+        let parts = get_body_parts(&request.id, &db).await.unwrap();
+        assert!(parts.len() == 1 && parts[0].value == "Ann");
+
+        let copied = curl_command(&request, &db).await.unwrap();
+        assert!(copied.starts_with("curl -X POST 'https://example.com/users?role=admin'"));
+        let read = curl::parse_command(&copied).unwrap().request;
+        assert!(read.headers[0] == KeyValueSpec::new("X-Trace", "1", true));
+        assert!(read.headers[1].name == "Authorization");
+        assert!(read.body.mode == BodyModes::FormUrlEncoded);
+        assert!(read.body.parts[0].name == "name" && read.body.parts[0].value == "Ann");
+    }
+
+    #[test]
+    fn test_parse_file_rejects_unknown_formats() {
+        assert!(parse_file(r#"{"openapi": "2.0"}"#).is_err());
+        assert!(parse_file("name: not json").is_err());
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert!(file_stem("Users / Admin") == "Users _ Admin");
+        assert!(file_stem("..") == "collection");
+    }
+}
@@ -0,0 +1,758 @@
+//! Querry's own collection files.
+//!
+//! They hold everything a collection is made of, so exporting and importing
+//! one gives back the same collection. Fields are only ever added; anything
+//! that changes the meaning of existing ones bumps [`FORMAT_VERSION`].
+
+use std::error::Error;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::{
+    crud::{
+        auth::{ApiKeyPlacement, AuthTypes},
+        bodies::BodyModes,
+        graphql::GraphQLQueryData,
+        grpc::GrpcCallData,
+        mqtt::MqttConfigData,
+        oauth2::OAuth2GrantTypes,
+        requests::{HTTPMethods, ProtocolTypes},
+        socketio::SocketIoConfigData,
+        sse::SseConfigData,
+        websocket::MessageFormats,
+    },
+    interchange::{
+        file_stem, AuthSpec, BodyPartSpec, BodySpec, CollectionSpec, ExportedFile, GraphQLSpec,
+        GrpcSpec, KeyValueSpec, OAuth2Spec, ParsedCollection, RequestSpec, WebSocketSpec,
+        WebSocketTemplateSpec,
+    },
+};
+
+const FORMAT_NAME: &str = "querry-collection";
+/// Version written to new files. Files of newer versions are refused.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct CollectionFile {
+    format: String,
+    version: u32,
+    collections: Vec<CollectionEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CollectionEntry {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default)]
+    headers: Vec<KeyValueEntry>,
+    #[serde(default)]
+    variables: Vec<KeyValueEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth: Option<AuthEntry>,
+    #[serde(default)]
+    requests: Vec<RequestEntry>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyValueEntry {
+    name: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RequestEntry {
+    name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    folder: String,
+    protocol: String,
+    method: String,
+    url: String,
+    #[serde(default)]
+    params: Vec<KeyValueEntry>,
+    #[serde(default)]
+    headers: Vec<KeyValueEntry>,
+    #[serde(default)]
+    variables: Vec<KeyValueEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<BodyEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth: Option<AuthEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    websocket: Option<WebSocketEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    graphql: Option<GraphQLEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    grpc: Option<GrpcEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sse: Option<SseEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    socketio: Option<SocketIoEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mqtt: Option<MqttEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BodyEntry {
+    mode: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    content_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    payload: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    parts: Vec<BodyPartEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BodyPartEntry {
+    name: String,
+    #[serde(default)]
+    value: String,
+    #[serde(default)]
+    is_file: bool,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AuthEntry {
+    #[serde(rename = "type")]
+    auth_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    username: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    key_name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    placement: String,
+    /// Only written when secrets are exported.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    secret: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    oauth2: Option<OAuth2Entry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OAuth2Entry {
+    grant_type: String,
+    #[serde(default)]
+    auth_url: String,
+    #[serde(default)]
+    token_url: String,
+    #[serde(default)]
+    client_id: String,
+    /// Only written when secrets are exported.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    client_secret: String,
+    #[serde(default)]
+    scope: String,
+    #[serde(default)]
+    redirect_uri: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WebSocketEntry {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    subprotocols: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    templates: Vec<WebSocketTemplateEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct WebSocketTemplateEntry {
+    name: String,
+    format: String,
+    #[serde(default)]
+    payload: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphQLEntry {
+    #[serde(default)]
+    query: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    variables: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    operation_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schema: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GrpcEntry {
+    source: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    proto_files: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    import_paths: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    method: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    message: String,
+    /// Base64 of the encoded `FileDescriptorSet`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    descriptors: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SseEntry {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    last_event_id: String,
+    #[serde(default = "enabled")]
+    reconnect: bool,
+}
+
+/// Fields left out take the defaults of a new Socket.IO request.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct SocketIoEntry {
+    path: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    listeners: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    event: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    payload: String,
+    ack: bool,
+}
+
+impl Default for SocketIoEntry {
+    fn default() -> Self {
+        socketio_entry(&SocketIoConfigData::default())
+    }
+}
+
+/// Fields left out take the defaults of a new MQTT request.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct MqttEntry {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    client_id: String,
+    clean_session: bool,
+    keep_alive: i64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    filters: String,
+    subscribe_qos: i64,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    topic: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    payload: String,
+    publish_qos: i64,
+    retain: bool,
+}
+
+impl Default for MqttEntry {
+    fn default() -> Self {
+        mqtt_entry(&MqttConfigData::default())
+    }
+}
+
+/// Whether a JSON document is a Querry collection file.
+pub fn is_native_file(document: &Value) -> bool {
+    document["format"] == FORMAT_NAME
+}
+
+fn key_value_entries(rows: &[KeyValueSpec]) -> Vec<KeyValueEntry> {
+    rows.iter()
+        .map(|row| KeyValueEntry {
+            name: row.name.clone(),
+            value: row.value.clone(),
+            enabled: row.enabled,
+        })
+        .collect()
+}
+
+fn key_value_specs(entries: Vec<KeyValueEntry>) -> Vec<KeyValueSpec> {
+    entries
+        .into_iter()
+        .map(|entry| KeyValueSpec {
+            name: entry.name,
+            value: entry.value,
+            enabled: entry.enabled,
+        })
+        .collect()
+}
+
+fn auth_entry(auth: &AuthSpec) -> AuthEntry {
+    AuthEntry {
+        auth_type: auth.auth_type.to_string(),
+        username: auth.username.clone(),
+        key_name: auth.key_name.clone(),
+        placement: match auth.auth_type {
+            AuthTypes::ApiKey => auth.placement.to_string(),
+            _ => String::new(),
+        },
+        secret: auth.secret.clone(),
+        oauth2: auth.oauth2.as_ref().map(|oauth2| OAuth2Entry {
+            grant_type: oauth2.grant_type.to_string(),
+            auth_url: oauth2.auth_url.clone(),
+            token_url: oauth2.token_url.clone(),
+            client_id: oauth2.client_id.clone(),
+            client_secret: oauth2.client_secret.clone(),
+            scope: oauth2.scope.clone(),
+            redirect_uri: oauth2.redirect_uri.clone(),
+        }),
+    }
+}
+
+fn auth_spec(entry: AuthEntry) -> Result<AuthSpec, Box<dyn Error>> {
+    let auth_type = AuthTypes::from_string(&entry.auth_type)
+        .ok_or_else(|| format!("Unknown auth type {}.", entry.auth_type))?;
+    let mut auth = AuthSpec::new(auth_type);
+    auth.username = entry.username;
+    auth.key_name = entry.key_name;
+    auth.placement =
+        ApiKeyPlacement::from_string(&entry.placement).unwrap_or(ApiKeyPlacement::Header);
+    auth.secret = entry.secret;
+    auth.oauth2 = match entry.oauth2 {
+        Some(oauth2) => Some(OAuth2Spec {
+            grant_type: OAuth2GrantTypes::from_string(&oauth2.grant_type)
+                .ok_or_else(|| format!("Unknown OAuth 2.0 grant type {}.", oauth2.grant_type))?,
+            auth_url: oauth2.auth_url,
+            token_url: oauth2.token_url,
+            client_id: oauth2.client_id,
+            client_secret: oauth2.client_secret,
+            scope: oauth2.scope,
+            redirect_uri: oauth2.redirect_uri,
+        }),
+        None => None,
+    };
+    Ok(auth)
+}
+
+fn socketio_entry(config: &SocketIoConfigData) -> SocketIoEntry {
+    SocketIoEntry {
+        path: config.path.clone(),
+        listeners: config.listeners.clone(),
+        event: config.event.clone(),
+        payload: config.payload.clone(),
+        ack: config.ack,
+    }
+}
+
+fn mqtt_entry(config: &MqttConfigData) -> MqttEntry {
+    MqttEntry {
+        client_id: config.client_id.clone(),
+        clean_session: config.clean_session,
+        keep_alive: config.keep_alive,
+        filters: config.filters.clone(),
+        subscribe_qos: config.subscribe_qos,
+        topic: config.topic.clone(),
+        payload: config.payload.clone(),
+        publish_qos: config.publish_qos,
+        retain: config.retain,
+    }
+}
+
+fn request_entry(request: &RequestSpec) -> RequestEntry {
+    let body = &request.body;
+    RequestEntry {
+        name: request.name.clone(),
+        folder: request.folder.clone(),
+        protocol: request.protocol.to_string(),
+        method: request.method.to_string(),
+        url: request.url.clone(),
+        params: key_value_entries(&request.params),
+        headers: key_value_entries(&request.headers),
+        variables: key_value_entries(&request.variables),
+        body: match *body == BodySpec::default() {
+            true => None,
+            false => Some(BodyEntry {
+                mode: body.mode.to_string(),
+                content_type: body.content_type.clone(),
+                payload: body.payload.clone(),
+                parts: body
+                    .parts
+                    .iter()
+                    .map(|part| BodyPartEntry {
+                        name: part.name.clone(),
+                        value: part.value.clone(),
+                        is_file: part.is_file,
+                        enabled: part.enabled,
+                    })
+                    .collect(),
+            }),
+        },
+        auth: request.auth.as_ref().map(auth_entry),
+        websocket: request.websocket.as_ref().map(|websocket| WebSocketEntry {
+            subprotocols: websocket.subprotocols.clone(),
+            templates: websocket
+                .templates
+                .iter()
+                .map(|template| WebSocketTemplateEntry {
+                    name: template.name.clone(),
+                    format: template.format.to_string(),
+                    payload: template.payload.clone(),
+                })
+                .collect(),
+        }),
+        graphql: request.graphql.as_ref().map(|graphql| GraphQLEntry {
+            query: graphql.operation.query.clone(),
+            variables: graphql.operation.variables.clone(),
+            operation_name: graphql.operation.operation_name.clone(),
+            schema: graphql.schema.clone(),
+        }),
+        grpc: request.grpc.as_ref().map(|grpc| GrpcEntry {
+            source: grpc.call.source.clone(),
+            proto_files: grpc.call.proto_files.clone(),
+            import_paths: grpc.call.import_paths.clone(),
+            method: grpc.call.method.clone(),
+            message: grpc.call.message.clone(),
+            descriptors: grpc
+                .descriptors
+                .as_ref()
+                .map(|descriptors| STANDARD.encode(descriptors)),
+        }),
+        sse: request.sse.as_ref().map(|sse| SseEntry {
+            last_event_id: sse.last_event_id.clone(),
+            reconnect: sse.reconnect,
+        }),
+        socketio: request.socketio.as_ref().map(socketio_entry),
+        mqtt: request.mqtt.as_ref().map(mqtt_entry),
+    }
+}
+
+fn request_spec(entry: RequestEntry) -> Result<RequestSpec, Box<dyn Error>> {
+    let method = HTTPMethods::from_string(&entry.method)
+        .ok_or_else(|| format!("{}: invalid method {}.", entry.name, entry.method))?;
+    let mut request = RequestSpec::new(&entry.name, method, &entry.url);
+    request.protocol = ProtocolTypes::from_string(&entry.protocol)
+        .ok_or_else(|| format!("{}: unknown protocol {}.", entry.name, entry.protocol))?;
+    request.folder = entry.folder;
+    request.params = key_value_specs(entry.params);
+    request.headers = key_value_specs(entry.headers);
+    request.variables = key_value_specs(entry.variables);
+    if let Some(body) = entry.body {
+        request.body = BodySpec {
+            mode: BodyModes::from_string(&body.mode)
+                .ok_or_else(|| format!("{}: unknown body mode {}.", entry.name, body.mode))?,
+            content_type: body.content_type,
+            payload: body.payload,
+            parts: body
+                .parts
+                .into_iter()
+                .map(|part| BodyPartSpec {
+                    name: part.name,
+                    value: part.value,
+                    is_file: part.is_file,
+                    enabled: part.enabled,
+                })
+                .collect(),
+        };
+    }
+    request.auth = entry.auth.map(auth_spec).transpose()?;
+    if let Some(websocket) = entry.websocket {
+        let mut templates = Vec::new();
+        for template in websocket.templates {
+            templates.push(WebSocketTemplateSpec {
+                format: MessageFormats::from_string(&template.format).ok_or_else(|| {
+                    format!(
+                        "{}: unknown message format {}.",
+                        entry.name, template.format
+                    )
+                })?,
+                name: template.name,
+                payload: template.payload,
+            });
+        }
+        request.websocket = Some(WebSocketSpec {
+            subprotocols: websocket.subprotocols,
+            templates,
+        });
+    }
+    request.graphql = entry.graphql.map(|graphql| GraphQLSpec {
+        operation: GraphQLQueryData {
+            query: graphql.query,
+            variables: graphql.variables,
+            operation_name: graphql.operation_name,
+        },
+        schema: graphql.schema,
+    });
+    if let Some(grpc) = entry.grpc {
+        let descriptors = match grpc.descriptors {
+            Some(encoded) => Some(
+                STANDARD
+                    .decode(encoded)
+                    .map_err(|_| format!("{}: the gRPC descriptors aren't base64.", entry.name))?,
+            ),
+            None => None,
+        };
+        request.grpc = Some(GrpcSpec {
+            call: GrpcCallData {
+                source: grpc.source,
+                proto_files: grpc.proto_files,
+                import_paths: grpc.import_paths,
+                method: grpc.method,
+                message: grpc.message,
+            },
+            descriptors,
+        });
+    }
+    request.sse = entry.sse.map(|sse| SseConfigData {
+        last_event_id: sse.last_event_id,
+        reconnect: sse.reconnect,
+    });
+    request.socketio = entry.socketio.map(|socketio| SocketIoConfigData {
+        path: socketio.path,
+        listeners: socketio.listeners,
+        event: socketio.event,
+        payload: socketio.payload,
+        ack: socketio.ack,
+    });
+    request.mqtt = entry.mqtt.map(|mqtt| MqttConfigData {
+        client_id: mqtt.client_id,
+        clean_session: mqtt.clean_session,
+        keep_alive: mqtt.keep_alive,
+        filters: mqtt.filters,
+        subscribe_qos: mqtt.subscribe_qos,
+        topic: mqtt.topic,
+        payload: mqtt.payload,
+        publish_qos: mqtt.publish_qos,
+        retain: mqtt.retain,
+    });
+    Ok(request)
+}
+
+/// Write collections to a single Querry file.
+pub fn write_collections(specs: &[CollectionSpec]) -> Result<ExportedFile, Box<dyn Error>> {
+    let file = CollectionFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        collections: specs
+            .iter()
+            .map(|spec| CollectionEntry {
+                name: spec.name.clone(),
+                icon: spec.icon.clone(),
+                headers: key_value_entries(&spec.headers),
+                variables: key_value_entries(&spec.variables),
+                auth: spec.auth.as_ref().map(auth_entry),
+                requests: spec.requests.iter().map(request_entry).collect(),
+            })
+            .collect(),
+    };
+
+    let file_name = match specs {
+        [spec] => format!("{}.querry.json", file_stem(&spec.name)),
+        _ => "collections.querry.json".to_string(),
+    };
+    Ok(ExportedFile {
+        file_name,
+        contents: serde_json::to_string_pretty(&file)?,
+        unmapped: Vec::new(),
+    })
+}
+
+/// Read every collection in a Querry file.
+pub fn parse_collections(contents: &str) -> Result<Vec<ParsedCollection>, Box<dyn Error>> {
+    let file: CollectionFile = serde_json::from_str(contents)?;
+    if file.format != FORMAT_NAME {
+        return Err("Not a Querry collection file.".into());
+    }
+    if file.version > FORMAT_VERSION {
+        return Err(format!(
+            "This file uses version {} of the collection format, update Querry to import it.",
+            file.version
+        )
+        .into());
+    }
+
+    let mut parsed = Vec::new();
+    for entry in file.collections {
+        let mut collection = CollectionSpec::new(&entry.name);
+        collection.icon = entry.icon;
+        collection.headers = key_value_specs(entry.headers);
+        collection.variables = key_value_specs(entry.variables);
+        collection.auth = entry.auth.map(auth_spec).transpose()?;
+        collection.requests = entry
+            .requests
+            .into_iter()
+            .map(request_spec)
+            .collect::<Result<_, _>>()?;
+        parsed.push(ParsedCollection {
+            collection,
+            unmapped: Vec::new(),
+        });
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_collection() -> CollectionSpec {
+        let mut collection = CollectionSpec::new("Billing");
+        collection.icon = Some("E009.svg".to_string());
+        collection.headers = vec![KeyValueSpec::new("X-Debug", "on", false)];
+        collection.variables = vec![KeyValueSpec::new(
+            "base",
+            "https://billing.example.com",
+            true,
+        )];
+        let mut oauth2 = AuthSpec::new(AuthTypes::OAuth2);
+        oauth2.oauth2 = Some(OAuth2Spec {
+            grant_type: OAuth2GrantTypes::AuthorizationCode,
+            auth_url: "https://id.example.com/authorize".to_string(),
+            token_url: "https://id.example.com/token".to_string(),
+            client_id: "billing".to_string(),
+            client_secret: "s3cret".to_string(),
+            scope: "invoices".to_string(),
+            redirect_uri: String::new(),
+        });
+        collection.auth = Some(oauth2);
+
+        let mut invoices = RequestSpec::new(
+            "List invoices",
+            HTTPMethods::Custom("PURGE".to_string()),
+            "{{base}}/invoices?page=1",
+        );
+        invoices.folder = "Invoices / Drafts".to_string();
+        invoices.params = vec![
+            KeyValueSpec::new("page", "1", true),
+            KeyValueSpec::new("debug", "", false),
+        ];
+        let mut upload = RequestSpec::new("Upload", HTTPMethods::Post, "{{base}}/upload");
+        upload.body = BodySpec {
+            mode: BodyModes::Multipart,
+            content_type: String::new(),
+            payload: String::new(),
+            parts: vec![BodyPartSpec {
+                name: "file".to_string(),
+                value: "/tmp/invoice.pdf".to_string(),
+                is_file: true,
+                enabled: true,
+            }],
+        };
+        let mut api_key = AuthSpec::new(AuthTypes::ApiKey);
+        api_key.key_name = "api_key".to_string();
+        api_key.placement = ApiKeyPlacement::Query;
+        api_key.secret = "k".to_string();
+        upload.auth = Some(api_key);
+        let mut events = RequestSpec::new("Events", HTTPMethods::Get, "wss://billing.example.com");
+        events.protocol = ProtocolTypes::Websocket;
+        events.websocket = Some(WebSocketSpec {
+            subprotocols: "v2.billing".to_string(),
+            templates: vec![WebSocketTemplateSpec {
+                name: "Subscribe".to_string(),
+                format: MessageFormats::Json,
+                payload: "{\"topic\": \"invoices\"}".to_string(),
+            }],
+        });
+        let mut search = RequestSpec::new("Search", HTTPMethods::Post, "{{base}}/graphql");
+        search.protocol = ProtocolTypes::GraphQL;
+        search.graphql = Some(GraphQLSpec {
+            operation: GraphQLQueryData {
+                query: "{ invoices { id } }".to_string(),
+                ..Default::default()
+            },
+            schema: None,
+        });
+        let mut lookup = RequestSpec::new("Lookup", HTTPMethods::Post, "{{base}}:50051");
+        lookup.protocol = ProtocolTypes::Grpc;
+        lookup.grpc = Some(GrpcSpec {
+            call: GrpcCallData {
+                method: "billing.Invoices/Get".to_string(),
+                message: "{\"id\": 1}".to_string(),
+                ..Default::default()
+            },
+            descriptors: Some(vec![0x0a, 0x03, 0x66, 0x6f, 0x6f]),
+        });
+        let mut feed = RequestSpec::new("Feed", HTTPMethods::Get, "{{base}}/feed");
+        feed.protocol = ProtocolTypes::Sse;
+        feed.sse = Some(SseConfigData {
+            last_event_id: "evt-7".to_string(),
+            reconnect: true,
+        });
+        let mut live = RequestSpec::new("Live", HTTPMethods::Get, "{{base}}/live");
+        live.protocol = ProtocolTypes::SocketIo;
+        live.socketio = Some(SocketIoConfigData {
+            event: "invoice:paid".to_string(),
+            ack: true,
+            ..Default::default()
+        });
+        let mut meters = RequestSpec::new("Meters", HTTPMethods::Get, "wss://mqtt.example.com");
+        meters.protocol = ProtocolTypes::Mqtt;
+        meters.mqtt = Some(MqttConfigData {
+            filters: "meters/+".to_string(),
+            subscribe_qos: 1,
+            ..Default::default()
+        });
+        collection.requests = vec![invoices, upload, events, search, lookup, feed, live, meters];
+        collection
+    }
+
+    #[test]
+    fn test_native_round_trip() {
+        let collections = vec![sample_collection(), CollectionSpec::new("Empty")];
+        let exported = write_collections(&collections).unwrap();
+        assert!(exported.file_name == "collections.querry.json");
+
+        let parsed = parse_collections(&exported.contents).unwrap();
+        let read: Vec<CollectionSpec> = parsed.into_iter().map(|item| item.collection).collect();
+        assert!(read == collections);
+    }
+
+    #[test]
+    fn test_native_leaves_out_empty_secrets() {
+        let mut collection = sample_collection();
+        collection
+            .auth
+            .as_mut()
+            .unwrap()
+            .oauth2
+            .as_mut()
+            .unwrap()
+            .client_secret = String::new();
+        collection.requests[1].auth.as_mut().unwrap().secret = String::new();
+
+        let exported = write_collections(&[collection]).unwrap();
+        assert!(exported.file_name == "Billing.querry.json");
+        assert!(!exported.contents.contains("secret\""));
+    }
+
+    #[test]
+    fn test_native_refuses_newer_versions() {
+        let newer = format!(
+            r#"{{"format": "{}", "version": {}, "collections": []}}"#,
+            FORMAT_NAME,
+            FORMAT_VERSION + 1
+        );
+        assert!(parse_collections(&newer).is_err());
+        let current = format!(
+            r#"{{"format": "{}", "version": {}, "collections": [{{"name": "Bare"}}]}}"#,
+            FORMAT_NAME, FORMAT_VERSION
+        );
+        assert!(parse_collections(&current).unwrap()[0].collection == CollectionSpec::new("Bare"));
+    }
+
+    #[test]
+    fn test_native_fills_in_left_out_settings() {
+        let file = format!(
+            r#"{{"format": "{}", "version": {}, "collections": [{{"name": "Bare", "requests": [
+                {{"name": "Live", "protocol": "SIO", "method": "GET", "url": "", "socketio": {{"event": "ping"}}}},
+                {{"name": "Meters", "protocol": "MQTT", "method": "GET", "url": "", "mqtt": {{}}}}
+            ]}}]}}"#,
+            FORMAT_NAME, FORMAT_VERSION
+        );
+        let requests = parse_collections(&file).unwrap()[0]
+            .collection
+            .requests
+            .clone();
+        assert!(
+            requests[0].socketio
+                == Some(SocketIoConfigData {
+                    event: "ping".to_string(),
+                    ..Default::default()
+                })
+        );
+        assert!(requests[1].mqtt == Some(MqttConfigData::default()));
+    }
+}
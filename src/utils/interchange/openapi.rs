@@ -0,0 +1,999 @@
+//! OpenAPI 3.0 and 3.1 and Swagger 2.0 specs, written in JSON or YAML.
+//!
+//! Every operation becomes a request in the folder of its first tag. The
+//! server URL goes into a `baseUrl` collection variable and path params
+//! become request variables, so request URLs read like the paths of the spec.
+//! Bodies are filled with the examples of the spec, or with an example
+//! written from the schema when it has none.
+
+use std::error::Error;
+
+use serde_json::{Map, Value};
+
+use crate::utils::{
+    crud::{
+        auth::{ApiKeyPlacement, AuthTypes},
+        bodies::BodyModes,
+        oauth2::OAuth2GrantTypes,
+        requests::HTTPMethods,
+    },
+    interchange::{
+        body_mode_for, item_path, json_text, AuthSpec, BodyPartSpec, BodySpec, CollectionSpec,
+        KeyValueSpec, OAuth2Spec, ParsedCollection, RequestSpec,
+    },
+};
+
+/// The collection variable holding the server URL.
+pub const BASE_URL: &str = "baseUrl";
+
+/// Keys of a path item that hold operations, in the order they are read.
+const OPERATIONS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// How deep example bodies are written, which also ends recursive schemas.
+const MAX_DEPTH: usize = 8;
+
+/// Whether a document is an OpenAPI 3 or Swagger 2.0 spec.
+pub fn is_openapi_spec(document: &Value) -> bool {
+    document["openapi"]
+        .as_str()
+        .is_some_and(|version| version.starts_with("3."))
+        || document["swagger"] == "2.0"
+}
+
+/// Read a JSON or YAML document.
+pub fn read_document(contents: &str) -> Result<Value, Box<dyn Error>> {
+    match serde_json::from_str(contents) {
+        Ok(document) => Ok(document),
+        Err(json_error) => match serde_yaml::from_str(contents) {
+            Ok(document) => Ok(document),
+            // A file that starts like JSON gets the JSON error, which is the useful one.
+            Err(_) if contents.trim_start().starts_with(['{', '[']) => Err(json_error.into()),
+            Err(yaml_error) => Err(yaml_error.into()),
+        },
+    }
+}
+
+/// Turn `{name}` templates into `{{name}}` variables.
+fn template_variables(text: &str) -> String {
+    let mut replaced = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        replaced.push_str(&rest[..start]);
+        replaced.push_str(&format!("{{{{{}}}}}", &rest[start + 1..start + end]));
+        rest = &rest[start + end + 1..];
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// Text for a param or form field example.
+fn example_text(example: &Value) -> String {
+    match example {
+        Value::Null => String::new(),
+        Value::Array(items) => items.iter().map(json_text).collect::<Vec<_>>().join(","),
+        Value::Object(_) => example.to_string(),
+        value => json_text(value),
+    }
+}
+
+/// The type of a schema. 3.1 allows a list of types, of which the first
+/// one other than null is taken.
+fn schema_type(schema: &Value) -> &str {
+    match &schema["type"] {
+        Value::String(schema_type) => schema_type,
+        Value::Array(types) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|schema_type| *schema_type != "null")
+            .unwrap_or(""),
+        _ if schema["properties"].is_object() => "object",
+        _ if !schema["items"].is_null() => "array",
+        _ => "",
+    }
+}
+
+/// Whether a schema describes the contents of a file.
+fn is_binary(schema: &Value) -> bool {
+    schema["format"] == "binary"
+        || schema_type(schema) == "file"
+        || schema["contentMediaType"].is_string()
+}
+
+fn string_example(format: &str) -> &'static str {
+    match format {
+        "date-time" => "2024-01-01T00:00:00Z",
+        "date" => "2024-01-01",
+        "time" => "00:00:00",
+        "email" => "user@example.com",
+        "uuid" => "00000000-0000-0000-0000-000000000000",
+        "uri" | "url" => "https://example.com",
+        "hostname" => "example.com",
+        "ipv4" => "127.0.0.1",
+        "ipv6" => "::1",
+        "binary" | "byte" => "",
+        _ => "string",
+    }
+}
+
+struct Parser<'a> {
+    document: &'a Value,
+    unmapped: Vec<String>,
+}
+
+impl<'a> Parser<'a> {
+    fn report(&mut self, path: &str, what: &str) {
+        self.unmapped.push(format!("{}: {}", path, what));
+    }
+
+    fn is_swagger(&self) -> bool {
+        self.document["swagger"] == "2.0"
+    }
+
+    /// Follow `$ref`s within the document. References to other files are
+    /// left as they are.
+    fn resolve(&self, mut value: &'a Value) -> &'a Value {
+        let document = self.document;
+        // A limit, as references may point at each other.
+        for _ in 0..16 {
+            let Some(reference) = value["$ref"].as_str() else {
+                break;
+            };
+            let Some(pointer) = reference.strip_prefix('#') else {
+                break;
+            };
+            match document.pointer(pointer) {
+                Some(target) => value = target,
+                None => break,
+            }
+        }
+        value
+    }
+
+    /// The server URL, and the variables it uses.
+    fn base_url(&mut self, name: &str) -> (String, Vec<KeyValueSpec>) {
+        let document = self.document;
+        if self.is_swagger() {
+            let host = json_text(&document["host"]);
+            let base_path = json_text(&document["basePath"]);
+            if host.is_empty() {
+                self.report(name, "no host, fill in the baseUrl variable");
+                return (base_path.trim_end_matches('/').to_string(), Vec::new());
+            }
+            let scheme = document["schemes"]
+                .as_array()
+                .and_then(|schemes| {
+                    schemes
+                        .iter()
+                        .find(|scheme| *scheme == "https")
+                        .or_else(|| schemes.first())
+                })
+                .map(json_text)
+                .unwrap_or_else(|| "https".to_string());
+            let url = format!("{}://{}{}", scheme, host, base_path);
+            return (url.trim_end_matches('/').to_string(), Vec::new());
+        }
+
+        let Some(server) = document["servers"]
+            .as_array()
+            .and_then(|servers| servers.first())
+        else {
+            self.report(name, "no server URL, fill in the baseUrl variable");
+            return (String::new(), Vec::new());
+        };
+        let url = json_text(&server["url"]);
+        if !url.contains("://") {
+            self.report(name, "relative server URL, complete the baseUrl variable");
+        }
+        let variables = server["variables"]
+            .as_object()
+            .map(|variables| {
+                variables
+                    .iter()
+                    .map(|(name, variable)| {
+                        KeyValueSpec::new(name, &json_text(&variable["default"]), true)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        (template_variables(url.trim_end_matches('/')), variables)
+    }
+
+    /// An example value for a schema.
+    fn example(&self, schema: &'a Value, depth: usize) -> Value {
+        let schema = self.resolve(schema);
+        if depth > MAX_DEPTH {
+            return Value::Null;
+        }
+        for key in ["example", "default", "const"] {
+            if let Some(example) = schema.get(key) {
+                return example.clone();
+            }
+        }
+        for key in ["examples", "enum"] {
+            if let Some(example) = schema[key].as_array().and_then(|values| values.first()) {
+                return example.clone();
+            }
+        }
+        if let Some(schemas) = schema["allOf"].as_array() {
+            let mut merged = Map::new();
+            for part in schemas {
+                match self.example(part, depth + 1) {
+                    Value::Object(fields) => merged.extend(fields),
+                    other if schemas.len() == 1 => return other,
+                    _ => {}
+                }
+            }
+            return Value::Object(merged);
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(first) = schema[key].as_array().and_then(|schemas| schemas.first()) {
+                return self.example(first, depth + 1);
+            }
+        }
+
+        match schema_type(schema) {
+            "object" => Value::Object(
+                schema["properties"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter(|(_, property)| {
+                        !self.resolve(property)["readOnly"]
+                            .as_bool()
+                            .unwrap_or(false)
+                    })
+                    .map(|(name, property)| (name.clone(), self.example(property, depth + 1)))
+                    .collect(),
+            ),
+            "array" => match schema["items"].is_null() {
+                true => Value::Array(Vec::new()),
+                false => Value::Array(vec![self.example(&schema["items"], depth + 1)]),
+            },
+            "string" => {
+                Value::String(string_example(schema["format"].as_str().unwrap_or("")).to_string())
+            }
+            "integer" | "number" => Value::from(0),
+            "boolean" => Value::Bool(false),
+            _ => Value::Null,
+        }
+    }
+
+    /// The example of a param, or of its schema. Strings without an example
+    /// are left empty, which is easier to fill in than the word "string".
+    fn param_example(&self, param: &'a Value) -> String {
+        if let Some(example) = param.get("example") {
+            return example_text(example);
+        }
+        if let Some(example) = param["examples"]
+            .as_object()
+            .and_then(|examples| examples.values().next())
+        {
+            return example_text(&self.resolve(example)["value"]);
+        }
+        // Swagger 2.0 describes the value on the param itself.
+        let schema = match param["schema"].is_null() {
+            true => param,
+            false => self.resolve(&param["schema"]),
+        };
+        let has_example = ["example", "default", "const", "examples", "enum"]
+            .iter()
+            .any(|key| schema.get(key).is_some());
+        if !has_example && schema_type(schema) == "string" && schema["format"].is_null() {
+            return String::new();
+        }
+        example_text(&self.example(schema, 0))
+    }
+
+    /// Params of the path and the operation, the operation's replacing
+    /// those of the path with the same name and location.
+    fn parameters(&self, path_item: &'a Value, operation: &'a Value) -> Vec<&'a Value> {
+        let mut params: Vec<&'a Value> = Vec::new();
+        for list in [&path_item["parameters"], &operation["parameters"]] {
+            for param in list.as_array().into_iter().flatten() {
+                let param = self.resolve(param);
+                params.retain(|known| {
+                    !(known["name"] == param["name"] && known["in"] == param["in"])
+                });
+                params.push(param);
+            }
+        }
+        params
+    }
+
+    /// Form fields from the properties of an object schema.
+    fn form_parts(&self, schema: &'a Value, multipart: bool) -> Vec<BodyPartSpec> {
+        let schema = self.resolve(schema);
+        let required: Vec<&Value> = schema["required"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .collect();
+        let example = self.example(schema, 0);
+        schema["properties"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .map(|(name, property)| {
+                let is_file = multipart && is_binary(self.resolve(property));
+                BodyPartSpec {
+                    name: name.clone(),
+                    value: match is_file {
+                        true => String::new(),
+                        false => example_text(&example[name]),
+                    },
+                    is_file,
+                    enabled: required.iter().any(|required| *required == name)
+                        || required.is_empty(),
+                }
+            })
+            .collect()
+    }
+
+    /// Body of an OpenAPI 3 request body, using the first content type
+    /// in the order JSON, form, multipart, XML, anything else.
+    fn parse_request_body(&mut self, request_body: &'a Value, path: &str) -> BodySpec {
+        let request_body = self.resolve(request_body);
+        let Some(content) = request_body["content"].as_object() else {
+            return BodySpec::default();
+        };
+        let preference = |content_type: &str| match body_mode_for(content_type).0 {
+            BodyModes::Json => 0,
+            BodyModes::FormUrlEncoded => 1,
+            BodyModes::Multipart => 2,
+            BodyModes::Xml => 3,
+            _ => 4,
+        };
+        let Some((content_type, media)) = content
+            .iter()
+            .min_by_key(|(content_type, _)| preference(content_type))
+        else {
+            return BodySpec::default();
+        };
+        if content.len() > 1 {
+            let others: Vec<&str> = content
+                .keys()
+                .map(String::as_str)
+                .filter(|other| *other != content_type.as_str())
+                .collect();
+            self.report(path, &format!("other content types {}", others.join(", ")));
+        }
+
+        let (mode, kept_content_type) = body_mode_for(content_type);
+        let schema = &media["schema"];
+        let example = match media.get("example") {
+            Some(example) => example.clone(),
+            None => match media["examples"]
+                .as_object()
+                .and_then(|examples| examples.values().next())
+            {
+                Some(example) => self.resolve(example)["value"].clone(),
+                None => self.example(schema, 0),
+            },
+        };
+
+        match mode {
+            BodyModes::Json => BodySpec {
+                mode,
+                content_type: kept_content_type,
+                payload: serde_json::to_string_pretty(&example).unwrap_or_default(),
+                parts: Vec::new(),
+            },
+            BodyModes::FormUrlEncoded | BodyModes::Multipart => BodySpec {
+                parts: self.form_parts(schema, mode == BodyModes::Multipart),
+                mode,
+                content_type: kept_content_type,
+                payload: String::new(),
+            },
+            _ if is_binary(self.resolve(schema)) || content_type == "application/octet-stream" => {
+                BodySpec {
+                    mode: BodyModes::Binary,
+                    content_type: content_type.clone(),
+                    ..Default::default()
+                }
+            }
+            _ => {
+                let payload = match &example {
+                    Value::String(text) => text.clone(),
+                    _ => {
+                        if !example.is_null() {
+                            self.report(path, &format!("example {} body", content_type));
+                        }
+                        String::new()
+                    }
+                };
+                BodySpec {
+                    mode,
+                    content_type: kept_content_type,
+                    payload,
+                    parts: Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// Body of a Swagger 2.0 operation, from its `body` or `formData` params.
+    fn parse_swagger_body(&self, params: &[&'a Value], consumes: &[String]) -> BodySpec {
+        if let Some(body) = params.iter().find(|param| param["in"] == "body") {
+            let content_type = consumes
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "application/json".to_string());
+            let (mode, kept_content_type) = body_mode_for(&content_type);
+            let example = self.example(&body["schema"], 0);
+            let payload = match (&mode, &example) {
+                (BodyModes::Json, _) => serde_json::to_string_pretty(&example).unwrap_or_default(),
+                (_, Value::String(text)) => text.clone(),
+                _ => String::new(),
+            };
+            return BodySpec {
+                mode,
+                content_type: kept_content_type,
+                payload,
+                parts: Vec::new(),
+            };
+        }
+
+        let fields: Vec<&'a Value> = params
+            .iter()
+            .copied()
+            .filter(|param| param["in"] == "formData")
+            .collect();
+        if fields.is_empty() {
+            return BodySpec::default();
+        }
+        let multipart = consumes
+            .iter()
+            .any(|content_type| content_type.starts_with("multipart/form-data"))
+            || fields.iter().any(|field| field["type"] == "file");
+        BodySpec {
+            mode: match multipart {
+                true => BodyModes::Multipart,
+                false => BodyModes::FormUrlEncoded,
+            },
+            parts: fields
+                .iter()
+                .map(|&field| {
+                    let is_file = field["type"] == "file";
+                    BodyPartSpec {
+                        name: json_text(&field["name"]),
+                        value: match is_file {
+                            true => String::new(),
+                            false => self.param_example(field),
+                        },
+                        is_file,
+                        enabled: field["required"].as_bool().unwrap_or(false),
+                    }
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Auth for a list of security requirements. The first requirement
+    /// with a scheme that maps is used, `None` means nothing could be.
+    fn parse_security(&mut self, requirements: &'a Value, path: &str) -> Option<AuthSpec> {
+        let requirements = requirements.as_array()?;
+        if requirements.is_empty() {
+            return Some(AuthSpec::new(AuthTypes::None));
+        }
+        // `{}` makes auth optional.
+        if requirements.iter().any(|requirement| {
+            requirement
+                .as_object()
+                .is_some_and(|schemes| schemes.is_empty())
+        }) {
+            return Some(AuthSpec::new(AuthTypes::None));
+        }
+
+        let document = self.document;
+        let definitions = match self.is_swagger() {
+            true => &document["securityDefinitions"],
+            false => &document["components"]["securitySchemes"],
+        };
+        let mut skipped = Vec::new();
+        for requirement in requirements {
+            for (name, scopes) in requirement.as_object().into_iter().flatten() {
+                let scheme = self.resolve(&definitions[name]);
+                let scopes = scopes
+                    .as_array()
+                    .map(|scopes| scopes.iter().map(json_text).collect::<Vec<_>>().join(" "))
+                    .unwrap_or_default();
+                match self.parse_scheme(scheme, scopes) {
+                    Some(auth) => {
+                        if !skipped.is_empty() {
+                            self.report(path, &format!("security schemes {}", skipped.join(", ")));
+                        }
+                        return Some(auth);
+                    }
+                    None => skipped.push(name.clone()),
+                }
+            }
+        }
+        self.report(path, &format!("security schemes {}", skipped.join(", ")));
+        None
+    }
+
+    fn parse_scheme(&self, scheme: &Value, scopes: String) -> Option<AuthSpec> {
+        let oauth2 = |grant_type: OAuth2GrantTypes, flow: &Value| {
+            let mut auth = AuthSpec::new(AuthTypes::OAuth2);
+            auth.oauth2 = Some(OAuth2Spec {
+                grant_type,
+                auth_url: json_text(&flow["authorizationUrl"]),
+                token_url: json_text(&flow["tokenUrl"]),
+                client_id: String::new(),
+                client_secret: String::new(),
+                scope: scopes.clone(),
+                redirect_uri: String::new(),
+            });
+            auth
+        };
+
+        match scheme["type"].as_str()? {
+            "basic" => Some(AuthSpec::new(AuthTypes::Basic)),
+            "http" => match scheme["scheme"].as_str()?.to_ascii_lowercase().as_str() {
+                "basic" => Some(AuthSpec::new(AuthTypes::Basic)),
+                "bearer" => Some(AuthSpec::new(AuthTypes::Bearer)),
+                "digest" => Some(AuthSpec::new(AuthTypes::Digest)),
+                _ => None,
+            },
+            "apiKey" => {
+                let mut auth = AuthSpec::new(AuthTypes::ApiKey);
+                auth.key_name = json_text(&scheme["name"]);
+                auth.placement = match scheme["in"].as_str()? {
+                    "header" => ApiKeyPlacement::Header,
+                    "query" => ApiKeyPlacement::Query,
+                    _ => return None,
+                };
+                Some(auth)
+            }
+            "oauth2" if self.is_swagger() => match scheme["flow"].as_str()? {
+                "accessCode" => Some(oauth2(OAuth2GrantTypes::AuthorizationCode, scheme)),
+                "application" => Some(oauth2(OAuth2GrantTypes::ClientCredentials, scheme)),
+                "password" => Some(oauth2(OAuth2GrantTypes::Password, scheme)),
+                _ => None,
+            },
+            "oauth2" => {
+                let flows = &scheme["flows"];
+                [
+                    ("authorizationCode", OAuth2GrantTypes::AuthorizationCode),
+                    ("clientCredentials", OAuth2GrantTypes::ClientCredentials),
+                    ("password", OAuth2GrantTypes::Password),
+                ]
+                .into_iter()
+                .find(|(flow, _)| flows[*flow].is_object())
+                .map(|(flow, grant_type)| oauth2(grant_type, &flows[flow]))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_operation(
+        &mut self,
+        path: &str,
+        method_name: &str,
+        path_item: &'a Value,
+        operation: &'a Value,
+    ) -> RequestSpec {
+        let method =
+            HTTPMethods::from_string(&method_name.to_uppercase()).unwrap_or(HTTPMethods::Get);
+        let name = [&operation["summary"], &operation["operationId"]]
+            .into_iter()
+            .map(json_text)
+            .map(|name| name.trim().to_string())
+            .find(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("{} {}", method_name.to_uppercase(), path));
+        let folder = operation["tags"]
+            .as_array()
+            .and_then(|tags| tags.first())
+            .map(json_text)
+            .unwrap_or_default();
+        let report_path = item_path(&folder, &name);
+
+        let url = format!("{{{{{}}}}}{}", BASE_URL, template_variables(path));
+        let mut spec = RequestSpec::new(&name, method, &url);
+        spec.folder = folder;
+
+        let params = self.parameters(path_item, operation);
+        for &param in &params {
+            let param_name = json_text(&param["name"]);
+            let required = param["required"].as_bool().unwrap_or(false);
+            match param["in"].as_str().unwrap_or("") {
+                "path" => spec.variables.push(KeyValueSpec::new(
+                    &param_name,
+                    &self.param_example(param),
+                    true,
+                )),
+                "query" => spec.params.push(KeyValueSpec::new(
+                    &param_name,
+                    &self.param_example(param),
+                    required,
+                )),
+                // These are described by the body and security of the operation instead.
+                "header"
+                    if ["accept", "content-type", "authorization"]
+                        .contains(&param_name.to_ascii_lowercase().as_str()) => {}
+                "header" => spec.headers.push(KeyValueSpec::new(
+                    &param_name,
+                    &self.param_example(param),
+                    required,
+                )),
+                "cookie" => self.report(&report_path, &format!("cookie {}", param_name)),
+                _ => {}
+            }
+        }
+        // Variables the path uses without describing them.
+        let mut rest = path;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            let variable = &rest[start + 1..start + end];
+            if !spec.variables.iter().any(|known| known.name == variable) {
+                spec.variables.push(KeyValueSpec::new(variable, "", true));
+            }
+            rest = &rest[start + end + 1..];
+        }
+
+        spec.body = match self.is_swagger() {
+            true => {
+                let consumes: Vec<String> = operation["consumes"]
+                    .as_array()
+                    .or(self.document["consumes"].as_array())
+                    .into_iter()
+                    .flatten()
+                    .map(json_text)
+                    .collect();
+                self.parse_swagger_body(&params, &consumes)
+            }
+            false => self.parse_request_body(&operation["requestBody"], &report_path),
+        };
+
+        // Operations declaring the same security as the whole API inherit it.
+        if operation["security"].is_array() && operation["security"] != self.document["security"] {
+            spec.auth = Some(
+                self.parse_security(&operation["security"], &report_path)
+                    .unwrap_or_else(|| AuthSpec::new(AuthTypes::None)),
+            );
+        }
+
+        if operation["servers"].is_array() || path_item["servers"].is_array() {
+            self.report(&report_path, "own server URL, sent to baseUrl instead");
+        }
+        if !operation["callbacks"].is_null() {
+            self.report(&report_path, "callbacks");
+        }
+        spec
+    }
+}
+
+/// Read an OpenAPI 3.x or Swagger 2.0 spec.
+pub fn parse_spec(contents: &str) -> Result<ParsedCollection, Box<dyn Error>> {
+    let document = read_document(contents)?;
+    if !is_openapi_spec(&document) {
+        return Err("Not an OpenAPI 3 or Swagger 2.0 spec.".into());
+    }
+
+    let name = match json_text(&document["info"]["title"]) {
+        name if name.trim().is_empty() => "Imported API".to_string(),
+        name => name.trim().to_string(),
+    };
+    let mut parser = Parser {
+        document: &document,
+        unmapped: Vec::new(),
+    };
+    let mut collection = CollectionSpec::new(&name);
+    let (base_url, server_variables) = parser.base_url(&name);
+    collection
+        .variables
+        .push(KeyValueSpec::new(BASE_URL, &base_url, true));
+    collection.variables.extend(server_variables);
+    if document["security"].is_array() {
+        collection.auth = parser.parse_security(&document["security"], &name);
+    }
+
+    for (path, path_item) in document["paths"].as_object().into_iter().flatten() {
+        let path_item = parser.resolve(path_item);
+        for (method_name, operation) in path_item.as_object().into_iter().flatten() {
+            if !OPERATIONS.contains(&method_name.as_str()) {
+                continue;
+            }
+            let request = parser.parse_operation(path, method_name, path_item, operation);
+            collection.requests.push(request);
+        }
+    }
+    if document["webhooks"].is_object() {
+        parser.report(&name, "webhooks");
+    }
+
+    Ok(ParsedCollection {
+        collection,
+        unmapped: parser.unmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                auth::get_request_auth,
+                bodies::{get_body_parts, get_request_body},
+                collections::get_collection_variables,
+                requests::{get_collection_requests, get_request_variables},
+            },
+            interchange::{find_spec_collection, import_file, update_from_spec},
+        },
+    };
+
+    const SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Petstore
+  version: 1.0.0
+servers:
+  - url: https://{region}.example.com/v1/
+    variables:
+      region:
+        default: eu
+security:
+  - token: []
+paths:
+  /pets:
+    get:
+      summary: List pets
+      tags: [Pets]
+      parameters:
+        - name: limit
+          in: query
+          required: true
+          schema: {type: integer, example: 10}
+        - name: sort
+          in: query
+          schema: {type: string, enum: [name, age]}
+        - name: session
+          in: cookie
+          schema: {type: string}
+    post:
+      operationId: createPet
+      tags: [Pets]
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: '#/components/schemas/Pet'
+          application/xml:
+            schema:
+              $ref: '#/components/schemas/Pet'
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema: {type: string, format: uuid}
+    delete:
+      tags: [Pets]
+      security: []
+    put:
+      summary: Upload photo
+      security:
+        - key: []
+      requestBody:
+        content:
+          multipart/form-data:
+            schema:
+              type: object
+              required: [photo]
+              properties:
+                photo: {type: string, format: binary}
+                caption: {type: string, example: Rex}
+components:
+  securitySchemes:
+    token:
+      type: http
+      scheme: bearer
+    key:
+      type: apiKey
+      in: header
+      name: X-Api-Key
+  schemas:
+    Pet:
+      type: object
+      properties:
+        id: {type: integer, readOnly: true}
+        name: {type: string, example: Rex}
+        born: {type: string, format: date}
+        tags:
+          type: array
+          items: {type: string}
+        owner:
+          $ref: '#/components/schemas/Owner'
+    Owner:
+      type: object
+      properties:
+        email: {type: string, format: email}
+        pets:
+          type: array
+          items:
+            $ref: '#/components/schemas/Pet'
+"#;
+
+    const SWAGGER: &str = r#"{
+        "swagger": "2.0",
+        "info": {"title": "Legacy", "version": "1"},
+        "host": "api.example.com",
+        "basePath": "/v2",
+        "schemes": ["http", "https"],
+        "securityDefinitions": {
+            "oauth": {"type": "oauth2", "flow": "application", "tokenUrl": "https://auth.example.com/token"}
+        },
+        "security": [{"oauth": ["read", "write"]}],
+        "paths": {
+            "/users/{id}": {
+                "post": {
+                    "consumes": ["application/x-www-form-urlencoded"],
+                    "parameters": [
+                        {"name": "id", "in": "path", "required": true, "type": "integer"},
+                        {"name": "X-Trace", "in": "header", "type": "string", "default": "on"},
+                        {"name": "name", "in": "formData", "required": true, "type": "string"}
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_openapi_spec() {
+        let parsed = parse_spec(SPEC).unwrap();
+        let collection = parsed.collection;
+
+        assert!(collection.name == "Petstore");
+        assert!(
+            collection.variables
+                == vec![
+                    KeyValueSpec::new(BASE_URL, "https://{{region}}.example.com/v1", true),
+                    KeyValueSpec::new("region", "eu", true),
+                ]
+        );
+        assert!(collection.auth.clone().unwrap().auth_type == AuthTypes::Bearer);
+        assert!(collection.requests.len() == 4);
+
+        let list = &collection.requests[0];
+        assert!(list.name == "List pets" && list.folder == "Pets");
+        assert!(list.url == "{{baseUrl}}/pets" && list.auth.is_none());
+        assert!(list.params[0] == KeyValueSpec::new("limit", "10", true));
+        assert!(list.params[1] == KeyValueSpec::new("sort", "name", false));
+
+        let create = &collection.requests[1];
+        assert!(create.name == "createPet" && create.method == HTTPMethods::Post);
+        assert!(create.body.mode == BodyModes::Json);
+        let body: Value = serde_json::from_str(&create.body.payload).unwrap();
+        assert!(body["name"] == "Rex" && body["born"] == "2024-01-01");
+        assert!(body.get("id").is_none() && body["tags"][0] == "string");
+        // The recursion between pets and owners ends somewhere.
+        assert!(body["owner"]["pets"][0]["owner"]["email"] == "user@example.com");
+
+        let delete = &collection.requests[2];
+        assert!(delete.name == "DELETE /pets/{petId}");
+        assert!(delete.url == "{{baseUrl}}/pets/{{petId}}");
+        assert!(delete.variables[0].name == "petId");
+        assert!(delete.auth.clone().unwrap().auth_type == AuthTypes::None);
+
+        let upload = &collection.requests[3];
+        assert!(upload.folder.is_empty());
+        let auth = upload.auth.clone().unwrap();
+        assert!(auth.auth_type == AuthTypes::ApiKey && auth.key_name == "X-Api-Key");
+        assert!(upload.body.mode == BodyModes::Multipart);
+        assert!(upload.body.parts[0].is_file && upload.body.parts[0].enabled);
+        assert!(upload.body.parts[1].value == "Rex" && !upload.body.parts[1].enabled);
+
+        assert!(
+            parsed.unmapped
+                == vec![
+                    "Pets / List pets: cookie session",
+                    "Pets / createPet: other content types application/xml",
+                ]
+        );
+    }
+
+    #[test]
+    fn test_parse_swagger_spec() {
+        let collection = parse_spec(SWAGGER).unwrap().collection;
+        assert!(collection.variables[0].value == "https://api.example.com/v2");
+        let oauth2 = collection.auth.unwrap().oauth2.unwrap();
+        assert!(oauth2.grant_type == OAuth2GrantTypes::ClientCredentials);
+        assert!(oauth2.scope == "read write");
+
+        let request = &collection.requests[0];
+        assert!(request.name == "POST /users/{id}");
+        assert!(request.variables == vec![KeyValueSpec::new("id", "0", true)]);
+        assert!(request.headers == vec![KeyValueSpec::new("X-Trace", "on", false)]);
+        assert!(request.body.mode == BodyModes::FormUrlEncoded);
+        assert!(request.body.parts[0].name == "name" && request.body.parts[0].enabled);
+    }
+
+    #[test]
+    fn test_parse_spec_rejects_other_files() {
+        assert!(parse_spec(r#"{"swagger": "1.2"}"#).is_err());
+        assert!(parse_spec("openapi: [unclosed").is_err());
+        assert!(template_variables("/a/{b}/{c}") == "/a/{{b}}/{{c}}");
+    }
+
+    #[tokio::test]
+    async fn test_import_and_update_spec() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let reports = import_file(SPEC, &db).await.unwrap();
+        let collection = &reports[0].collection;
+        assert!(reports[0].requests == 4 && collection.name == "Petstore");
+        let requests = get_collection_requests(&db, &collection.id).await.unwrap();
+        let parts = get_body_parts(&requests[3].id, &db).await.unwrap();
+        assert!(parts.len() == 2 && parts[0].is_file);
+
+        // The user points the collection at staging and edits a body.
+        let variables = get_collection_variables(&collection.id, &db).await.unwrap();
+        crate::utils::crud::collections::update_collection_variable(
+            &variables[0].id,
+            BASE_URL,
+            "http://localhost:8080",
+            true,
+            &db,
+        )
+        .await
+        .unwrap();
+        crate::utils::crud::bodies::update_request_body(
+            &requests[1].id,
+            BodyModes::Json,
+            "",
+            "{\"name\": \"Mine\"}",
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let updated_spec = SPEC
+            .replace("summary: List pets", "summary: List all pets")
+            .replace(
+                "  /pets/{petId}:",
+                "  /owners:\n    get:\n      summary: List owners\n  /pets/{petId}:",
+            );
+        let found = find_spec_collection(&updated_spec, &db).await.unwrap();
+        assert!(found.unwrap().id == collection.id);
+
+        let report = update_from_spec(&updated_spec, &collection.id, &db)
+            .await
+            .unwrap();
+        assert!(report.requests == 1 && report.updated == 4);
+        let updated = get_collection_requests(&db, &collection.id).await.unwrap();
+        assert!(updated.len() == 5);
+        let list = updated
+            .iter()
+            .find(|request| request.id == requests[0].id)
+            .unwrap();
+        assert!(list.name == "List all pets");
+        assert!(updated.iter().any(|request| request.name == "List owners"));
+
+        let variables = get_collection_variables(&collection.id, &db).await.unwrap();
+        assert!(variables.len() == 2 && variables[0].value == "http://localhost:8080");
+        let body = get_request_body(&requests[1].id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(body.payload == "{\"name\": \"Mine\"}");
+        let parts = get_body_parts(&requests[3].id, &db).await.unwrap();
+        assert!(parts.len() == 2);
+        let auth = get_request_auth(&requests[3].id, &db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(auth.auth_type == "apikey");
+        let variables = get_request_variables(&requests[2].id, &db).await.unwrap();
+        assert!(variables.len() == 1);
+    }
+}
@@ -0,0 +1,1003 @@
+//! Postman Collection v2.1 files.
+//!
+//! Folders become the folder of their requests, and the auth and variables
+//! of a folder are copied onto the requests in it that don't set their own.
+//! Scripts, saved example responses and anything else without an equivalent
+//! are listed in the report instead.
+
+use std::error::Error;
+
+use serde_json::{json, Value};
+
+use crate::utils::{
+    crud::{
+        auth::{ApiKeyPlacement, AuthTypes},
+        bodies::BodyModes,
+        oauth2::OAuth2GrantTypes,
+        requests::{HTTPMethods, ProtocolTypes},
+    },
+    interchange::{
+        file_stem, item_path, json_text, AuthSpec, BodyPartSpec, BodySpec, CollectionSpec,
+        ExportedFile, KeyValueSpec, OAuth2Spec, ParsedCollection, RequestSpec, FOLDER_SEPARATOR,
+    },
+    query_params::{build_query, split_url},
+};
+
+/// The `info.schema` of v2.1 collections contains this.
+const SCHEMA_VERSION: &str = "/collection/v2.1";
+/// Written to exported collections.
+const SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+/// Whether a JSON document looks like a Postman collection of any version.
+pub fn is_postman_collection(document: &Value) -> bool {
+    document["info"]["schema"]
+        .as_str()
+        .is_some_and(|schema| schema.contains("/json/collection/"))
+}
+
+fn is_disabled(value: &Value) -> bool {
+    value["disabled"].as_bool().unwrap_or(false)
+}
+
+/// Rows of a `[{"key", "value", "disabled"}]` list.
+fn key_values(value: &Value) -> Vec<KeyValueSpec> {
+    value
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .filter(|row| row.is_object())
+                .map(|row| {
+                    KeyValueSpec::new(
+                        &json_text(&row["key"]),
+                        &json_text(&row["value"]),
+                        !is_disabled(row),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Headers are usually a list, but may also be written as a raw header block.
+fn headers(value: &Value) -> Vec<KeyValueSpec> {
+    match value {
+        Value::String(block) => block
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| KeyValueSpec::new(name.trim(), value.trim(), true))
+            .collect(),
+        _ => key_values(value),
+    }
+}
+
+/// Lines of a script, empty when it does nothing.
+fn script_source(event: &Value) -> String {
+    match &event["script"]["exec"] {
+        Value::Array(lines) => lines.iter().map(json_text).collect::<Vec<_>>().join("\n"),
+        exec => json_text(exec),
+    }
+}
+
+/// `{{$guid}}` style variables, which Postman generates on the fly.
+fn dynamic_variables(texts: &[&str]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for source in texts {
+        let mut rest = *source;
+        while let Some(start) = rest.find("{{$") {
+            rest = &rest[start..];
+            let Some(end) = rest.find("}}") else {
+                break;
+            };
+            let name = rest[..end + 2].to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+            rest = &rest[end + 2..];
+        }
+    }
+    names
+}
+
+/// Turn `:name` path segments into `{{name}}` variables.
+fn replace_path_variables(url: &str) -> String {
+    let (base, query, fragment) = split_url(url);
+    let mut replaced = base
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) if !name.is_empty() => format!("{{{{{}}}}}", name),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    if let Some(query) = query {
+        replaced = format!("{}?{}", replaced, query);
+    }
+    if let Some(fragment) = fragment {
+        replaced = format!("{}#{}", replaced, fragment);
+    }
+    replaced
+}
+
+/// A URL object without `raw`, put back together from its parts.
+fn url_from_parts(url: &Value) -> String {
+    let join = |value: &Value, separator: &str| match value {
+        Value::Array(parts) => parts
+            .iter()
+            .map(json_text)
+            .collect::<Vec<_>>()
+            .join(separator),
+        value => json_text(value),
+    };
+
+    let mut built = String::new();
+    let protocol = json_text(&url["protocol"]);
+    if !protocol.is_empty() {
+        built.push_str(&protocol);
+        built.push_str("://");
+    }
+    built.push_str(&join(&url["host"], "."));
+    let port = json_text(&url["port"]);
+    if !port.is_empty() {
+        built.push(':');
+        built.push_str(&port);
+    }
+    let path = join(&url["path"], "/");
+    if !path.is_empty() {
+        built.push('/');
+        built.push_str(&path);
+    }
+    let query: Vec<(String, String)> = key_values(&url["query"])
+        .into_iter()
+        .filter(|param| param.enabled)
+        .map(|param| (param.name, param.value))
+        .collect();
+    if !query.is_empty() {
+        built.push('?');
+        built.push_str(&build_query(&query));
+    }
+    built
+}
+
+/// The pieces of a request that come from its URL: the URL itself, the
+/// query params and the values of path variables.
+struct ParsedUrl {
+    url: String,
+    params: Vec<KeyValueSpec>,
+    path_variables: Vec<KeyValueSpec>,
+}
+
+fn parse_url(url: &Value) -> ParsedUrl {
+    match url {
+        Value::Object(_) => {
+            let raw = url["raw"]
+                .as_str()
+                .map(|raw| raw.to_string())
+                .unwrap_or_else(|| url_from_parts(url));
+            ParsedUrl {
+                url: replace_path_variables(&raw),
+                params: key_values(&url["query"]),
+                path_variables: key_values(&url["variable"]),
+            }
+        }
+        url => ParsedUrl {
+            url: replace_path_variables(&json_text(url)),
+            params: Vec::new(),
+            path_variables: Vec::new(),
+        },
+    }
+}
+
+fn grant_type(grant_type: &str) -> Option<OAuth2GrantTypes> {
+    match grant_type {
+        "client_credentials" => Some(OAuth2GrantTypes::ClientCredentials),
+        "password_credentials" => Some(OAuth2GrantTypes::Password),
+        "authorization_code" | "authorization_code_with_pkce" => {
+            Some(OAuth2GrantTypes::AuthorizationCode)
+        }
+        _ => None,
+    }
+}
+
+struct Parser {
+    unmapped: Vec<String>,
+    requests: Vec<RequestSpec>,
+}
+
+impl Parser {
+    fn report(&mut self, path: &str, what: &str) {
+        self.unmapped.push(format!("{}: {}", path, what));
+    }
+
+    fn report_events(&mut self, item: &Value, path: &str) {
+        for event in item["event"].as_array().into_iter().flatten() {
+            if script_source(event).trim().is_empty() {
+                continue;
+            }
+            match event["listen"].as_str() {
+                Some("prerequest") => self.report(path, "pre-request script"),
+                Some("test") => self.report(path, "test script"),
+                _ => self.report(path, "script"),
+            }
+        }
+    }
+
+    /// Auth settings, `None` when they are inherited.
+    fn parse_auth(&mut self, auth: &Value, path: &str) -> Option<AuthSpec> {
+        let auth_type = auth["type"].as_str()?;
+        let param = |name: &str| {
+            auth[auth_type]
+                .as_array()
+                .and_then(|params| params.iter().find(|param| param["key"] == name))
+                .map(|param| json_text(&param["value"]))
+                .unwrap_or_default()
+        };
+
+        let parsed = match auth_type {
+            "inherit" => return None,
+            "noauth" => AuthSpec::new(AuthTypes::None),
+            "basic" | "digest" => {
+                let mut parsed = AuthSpec::new(if auth_type == "basic" {
+                    AuthTypes::Basic
+                } else {
+                    AuthTypes::Digest
+                });
+                parsed.username = param("username");
+                parsed.secret = param("password");
+                parsed
+            }
+            "bearer" => {
+                let mut parsed = AuthSpec::new(AuthTypes::Bearer);
+                parsed.secret = param("token");
+                parsed
+            }
+            "apikey" => {
+                let mut parsed = AuthSpec::new(AuthTypes::ApiKey);
+                parsed.key_name = param("key");
+                parsed.secret = param("value");
+                if param("in") == "query" {
+                    parsed.placement = ApiKeyPlacement::Query;
+                }
+                parsed
+            }
+            "oauth2" => {
+                let Some(grant_type) = grant_type(&param("grant_type")) else {
+                    self.report(path, &format!("OAuth 2.0 {} grant", param("grant_type")));
+                    return Some(AuthSpec::new(AuthTypes::None));
+                };
+                let mut parsed = AuthSpec::new(AuthTypes::OAuth2);
+                parsed.username = param("username");
+                parsed.secret = param("password");
+                parsed.oauth2 = Some(OAuth2Spec {
+                    grant_type,
+                    auth_url: param("authUrl"),
+                    token_url: param("accessTokenUrl"),
+                    client_id: param("clientId"),
+                    client_secret: param("clientSecret"),
+                    scope: param("scope"),
+                    redirect_uri: param("redirect_uri"),
+                });
+                parsed
+            }
+            other => {
+                self.report(path, &format!("{} auth", other));
+                AuthSpec::new(AuthTypes::None)
+            }
+        };
+        Some(parsed)
+    }
+
+    fn parse_body(&mut self, body: &Value, path: &str) -> BodySpec {
+        if body.is_null() || is_disabled(body) {
+            return BodySpec::default();
+        }
+
+        match body["mode"].as_str().unwrap_or("") {
+            "raw" => {
+                let payload = json_text(&body["raw"]);
+                let (mode, content_type) =
+                    match body["options"]["raw"]["language"].as_str().unwrap_or("") {
+                        "json" => (BodyModes::Json, ""),
+                        "xml" => (BodyModes::Xml, ""),
+                        "html" => (BodyModes::Raw, "text/html"),
+                        "javascript" => (BodyModes::Raw, "application/javascript"),
+                        _ => (BodyModes::Raw, ""),
+                    };
+                BodySpec {
+                    mode,
+                    content_type: content_type.to_string(),
+                    payload,
+                    parts: Vec::new(),
+                }
+            }
+            "urlencoded" => BodySpec {
+                mode: BodyModes::FormUrlEncoded,
+                parts: key_values(&body["urlencoded"])
+                    .into_iter()
+                    .map(|field| BodyPartSpec {
+                        name: field.name,
+                        value: field.value,
+                        is_file: false,
+                        enabled: field.enabled,
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            "formdata" => {
+                let mut parts = Vec::new();
+                for field in body["formdata"].as_array().into_iter().flatten() {
+                    let name = json_text(&field["key"]);
+                    let is_file = field["type"] == "file";
+                    let value = if is_file {
+                        match &field["src"] {
+                            Value::Array(files) => {
+                                if files.len() > 1 {
+                                    self.report(
+                                        path,
+                                        &format!("all but the first file of form field {}", name),
+                                    );
+                                }
+                                files.first().map(json_text).unwrap_or_default()
+                            }
+                            src => json_text(src),
+                        }
+                    } else {
+                        json_text(&field["value"])
+                    };
+                    if !json_text(&field["contentType"]).is_empty() {
+                        self.report(path, &format!("content type of form field {}", name));
+                    }
+                    parts.push(BodyPartSpec {
+                        name,
+                        value,
+                        is_file,
+                        enabled: !is_disabled(field),
+                    });
+                }
+                BodySpec {
+                    mode: BodyModes::Multipart,
+                    parts,
+                    ..Default::default()
+                }
+            }
+            "file" => match body["file"]["src"].as_str() {
+                Some(src) => BodySpec {
+                    mode: BodyModes::Binary,
+                    payload: src.to_string(),
+                    ..Default::default()
+                },
+                None => BodySpec {
+                    mode: BodyModes::Raw,
+                    payload: json_text(&body["file"]["content"]),
+                    ..Default::default()
+                },
+            },
+            // Sent the way GraphQL servers expect it over HTTP.
+            "graphql" => {
+                let graphql = &body["graphql"];
+                let variables = match &graphql["variables"] {
+                    Value::String(variables) if !variables.trim().is_empty() => {
+                        serde_json::from_str(variables).unwrap_or(Value::Null)
+                    }
+                    Value::String(_) => Value::Null,
+                    variables => variables.clone(),
+                };
+                let payload =
+                    json!({ "query": json_text(&graphql["query"]), "variables": variables });
+                BodySpec {
+                    mode: BodyModes::Json,
+                    payload: serde_json::to_string_pretty(&payload).unwrap_or_default(),
+                    ..Default::default()
+                }
+            }
+            "" => BodySpec::default(),
+            other => {
+                self.report(path, &format!("{} body", other));
+                BodySpec::default()
+            }
+        }
+    }
+
+    fn parse_request(
+        &mut self,
+        item: &Value,
+        name: &str,
+        folder: &str,
+        auth: &Option<AuthSpec>,
+        variables: &[KeyValueSpec],
+    ) {
+        let path = item_path(folder, name);
+        let request = &item["request"];
+
+        let method_name = request["method"].as_str().unwrap_or("GET").to_uppercase();
+        let method = HTTPMethods::from_string(&method_name).unwrap_or_else(|| {
+            self.report(&path, &format!("method {}, sent as GET", method_name));
+            HTTPMethods::Get
+        });
+        let parsed_url = match request {
+            Value::String(_) => parse_url(request),
+            _ => parse_url(&request["url"]),
+        };
+
+        let mut spec = RequestSpec::new(name, method, &parsed_url.url);
+        spec.folder = folder.to_string();
+        spec.params = parsed_url.params;
+        spec.headers = headers(&request["header"]);
+        spec.body = self.parse_body(&request["body"], &path);
+        spec.auth = match request["auth"].is_object() {
+            true => self.parse_auth(&request["auth"], &path),
+            false => auth.clone(),
+        };
+        spec.variables = variables.to_vec();
+        spec.variables.extend(key_values(&item["variable"]));
+        spec.variables.extend(parsed_url.path_variables);
+
+        self.report_events(item, &path);
+        let examples = item["response"].as_array().map(|responses| responses.len());
+        match examples.unwrap_or(0) {
+            0 => {}
+            1 => self.report(&path, "saved example response"),
+            count => self.report(&path, &format!("{} saved example responses", count)),
+        }
+        let mut texts = vec![spec.url.as_str(), spec.body.payload.as_str()];
+        texts.extend(spec.headers.iter().map(|header| header.value.as_str()));
+        let dynamic = dynamic_variables(&texts);
+        if !dynamic.is_empty() {
+            self.report(&path, &format!("dynamic variables {}", dynamic.join(", ")));
+        }
+
+        self.requests.push(spec);
+    }
+
+    fn parse_items(
+        &mut self,
+        items: &Value,
+        folder: &str,
+        auth: &Option<AuthSpec>,
+        variables: &[KeyValueSpec],
+    ) {
+        for item in items.as_array().into_iter().flatten() {
+            let name = match json_text(&item["name"]) {
+                name if name.trim().is_empty() => "Untitled".to_string(),
+                name => name,
+            };
+
+            if item["item"].is_array() {
+                let path = item_path(folder, &name);
+                let folder_auth = match item["auth"].is_object() {
+                    true => self.parse_auth(&item["auth"], &path),
+                    false => auth.clone(),
+                };
+                let mut folder_variables = variables.to_vec();
+                folder_variables.extend(key_values(&item["variable"]));
+                self.report_events(item, &path);
+                self.parse_items(&item["item"], &path, &folder_auth, &folder_variables);
+            } else if !item["request"].is_null() {
+                self.parse_request(item, &name, folder, auth, variables);
+            } else {
+                self.report(&item_path(folder, &name), "neither a request nor a folder");
+            }
+        }
+    }
+}
+
+/// Read a Postman v2.1 collection.
+pub fn parse_collection(contents: &str) -> Result<ParsedCollection, Box<dyn Error>> {
+    let document: Value = serde_json::from_str(contents)?;
+    if !is_postman_collection(&document) {
+        return Err("Not a Postman collection.".into());
+    }
+    if !document["info"]["schema"]
+        .as_str()
+        .unwrap_or("")
+        .contains(SCHEMA_VERSION)
+    {
+        return Err("Only Postman Collection v2.1 files can be imported, export the collection as v2.1 first.".into());
+    }
+
+    let name = match json_text(&document["info"]["name"]) {
+        name if name.trim().is_empty() => "Imported collection".to_string(),
+        name => name,
+    };
+    let mut parser = Parser {
+        unmapped: Vec::new(),
+        requests: Vec::new(),
+    };
+    let mut collection = CollectionSpec::new(&name);
+    collection.variables = key_values(&document["variable"]);
+    collection.auth = match document["auth"].is_object() {
+        true => parser.parse_auth(&document["auth"], &name),
+        false => None,
+    };
+    parser.report_events(&document, &name);
+    parser.parse_items(&document["item"], "", &None, &[]);
+
+    collection.requests = parser.requests;
+    Ok(ParsedCollection {
+        collection,
+        unmapped: parser.unmapped,
+    })
+}
+
+/// Rows as a `[{"key", "value", "disabled"}]` list.
+fn key_value_list(rows: &[KeyValueSpec]) -> Value {
+    rows.iter()
+        .map(|row| {
+            let mut item = json!({ "key": row.name, "value": row.value });
+            if !row.enabled {
+                item["disabled"] = Value::Bool(true);
+            }
+            item
+        })
+        .collect()
+}
+
+/// A URL object, split up the way Postman keeps it.
+fn write_url(url: &str, params: &[KeyValueSpec]) -> Value {
+    let (base, _, _) = split_url(url);
+    let mut written = json!({ "raw": url });
+
+    let rest = match base.split_once("://") {
+        Some((protocol, rest)) => {
+            written["protocol"] = json!(protocol);
+            rest
+        }
+        None => base,
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => {
+            written["port"] = json!(port);
+            host
+        }
+        _ => authority,
+    };
+    written["host"] = json!(host.split('.').collect::<Vec<_>>());
+    if !path.is_empty() {
+        written["path"] = json!(path.split('/').collect::<Vec<_>>());
+    }
+    if !params.is_empty() {
+        written["query"] = key_value_list(params);
+    }
+    written
+}
+
+fn write_auth(auth: &AuthSpec) -> Value {
+    let params = |pairs: &[(&str, &str)]| -> Value {
+        pairs
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value, "type": "string" }))
+            .collect()
+    };
+
+    let auth_type = match auth.auth_type {
+        AuthTypes::Inherit => "inherit",
+        AuthTypes::None => "noauth",
+        AuthTypes::Basic => "basic",
+        AuthTypes::Bearer => "bearer",
+        AuthTypes::ApiKey => "apikey",
+        AuthTypes::Digest => "digest",
+        AuthTypes::OAuth2 => "oauth2",
+    };
+    let mut written = json!({ "type": auth_type });
+    match auth.auth_type {
+        AuthTypes::Inherit | AuthTypes::None => {}
+        AuthTypes::Basic | AuthTypes::Digest => {
+            written[auth_type] =
+                params(&[("username", &auth.username), ("password", &auth.secret)]);
+        }
+        AuthTypes::Bearer => {
+            written[auth_type] = params(&[("token", &auth.secret)]);
+        }
+        AuthTypes::ApiKey => {
+            written[auth_type] = params(&[
+                ("key", &auth.key_name),
+                ("value", &auth.secret),
+                ("in", &auth.placement.to_string()),
+            ]);
+        }
+        AuthTypes::OAuth2 => {
+            let mut pairs = vec![("addTokenTo", "header")];
+            if let Some(oauth2) = &auth.oauth2 {
+                pairs.extend([
+                    (
+                        "grant_type",
+                        match oauth2.grant_type {
+                            OAuth2GrantTypes::ClientCredentials => "client_credentials",
+                            OAuth2GrantTypes::Password => "password_credentials",
+                            OAuth2GrantTypes::AuthorizationCode => "authorization_code_with_pkce",
+                        },
+                    ),
+                    ("authUrl", oauth2.auth_url.as_str()),
+                    ("accessTokenUrl", oauth2.token_url.as_str()),
+                    ("clientId", oauth2.client_id.as_str()),
+                    ("clientSecret", oauth2.client_secret.as_str()),
+                    ("scope", oauth2.scope.as_str()),
+                    ("redirect_uri", oauth2.redirect_uri.as_str()),
+                ]);
+            }
+            if !auth.username.is_empty() || !auth.secret.is_empty() {
+                pairs.extend([
+                    ("username", auth.username.as_str()),
+                    ("password", auth.secret.as_str()),
+                ]);
+            }
+            written[auth_type] = params(&pairs);
+        }
+    }
+    written
+}
+
+fn write_body(body: &BodySpec, path: &str, unmapped: &mut Vec<String>) -> Option<Value> {
+    let raw = |language: &str| {
+        json!({
+            "mode": "raw",
+            "raw": body.payload,
+            "options": { "raw": { "language": language } }
+        })
+    };
+    let mut content_type_mapped = body.content_type.is_empty();
+
+    let written = match body.mode {
+        BodyModes::None => return None,
+        BodyModes::Raw => match body.content_type.as_str() {
+            "text/html" => {
+                content_type_mapped = true;
+                raw("html")
+            }
+            "application/javascript" => {
+                content_type_mapped = true;
+                raw("javascript")
+            }
+            _ => raw("text"),
+        },
+        BodyModes::Json => raw("json"),
+        BodyModes::Xml => raw("xml"),
+        BodyModes::FormUrlEncoded => {
+            let fields: Vec<KeyValueSpec> = body
+                .parts
+                .iter()
+                .map(|part| KeyValueSpec::new(&part.name, &part.value, part.enabled))
+                .collect();
+            json!({ "mode": "urlencoded", "urlencoded": key_value_list(&fields) })
+        }
+        BodyModes::Multipart => {
+            let fields: Vec<Value> = body
+                .parts
+                .iter()
+                .map(|part| {
+                    let mut field = match part.is_file {
+                        true => json!({ "key": part.name, "type": "file", "src": part.value }),
+                        false => json!({ "key": part.name, "value": part.value, "type": "text" }),
+                    };
+                    if !part.enabled {
+                        field["disabled"] = Value::Bool(true);
+                    }
+                    field
+                })
+                .collect();
+            json!({ "mode": "formdata", "formdata": fields })
+        }
+        BodyModes::Binary => json!({ "mode": "file", "file": { "src": body.payload } }),
+    };
+
+    if !content_type_mapped {
+        unmapped.push(format!("{}: body content type {}", path, body.content_type));
+    }
+    Some(written)
+}
+
+fn write_request(request: &RequestSpec, unmapped: &mut Vec<String>) -> Value {
+    let path = item_path(&request.folder, &request.name);
+    if request.protocol != ProtocolTypes::Http {
+        unmapped.push(format!(
+            "{}: {} request, written as HTTP",
+            path, request.protocol
+        ));
+    }
+
+    let mut written = json!({
+        "method": request.method.to_string(),
+        "header": key_value_list(&request.headers),
+        "url": write_url(&request.url, &request.params),
+    });
+    if let Some(body) = write_body(&request.body, &path, unmapped) {
+        written["body"] = body;
+    }
+    if let Some(auth) = &request.auth {
+        written["auth"] = write_auth(auth);
+    }
+
+    let mut item = json!({ "name": request.name, "request": written });
+    if !request.variables.is_empty() {
+        item["variable"] = key_value_list(&request.variables);
+    }
+    item
+}
+
+/// Items of one folder level. Each folder goes where its first request is.
+fn write_items<'a>(
+    requests: &[(Vec<&'a str>, &'a RequestSpec)],
+    unmapped: &mut Vec<String>,
+) -> Vec<Value> {
+    let mut items = Vec::new();
+    let mut written_folders: Vec<&str> = Vec::new();
+
+    for (folders, request) in requests {
+        let Some(folder) = folders.first() else {
+            items.push(write_request(request, unmapped));
+            continue;
+        };
+        if written_folders.contains(folder) {
+            continue;
+        }
+        written_folders.push(*folder);
+
+        let children: Vec<(Vec<&str>, &RequestSpec)> = requests
+            .iter()
+            .filter(|(other, _)| other.first() == Some(folder))
+            .map(|(other, child)| (other[1..].to_vec(), *child))
+            .collect();
+        items.push(json!({ "name": folder, "item": write_items(&children, unmapped) }));
+    }
+    items
+}
+
+/// Write a collection as a Postman v2.1 collection.
+pub fn write_collection(spec: &CollectionSpec) -> Result<ExportedFile, Box<dyn Error>> {
+    let mut unmapped = Vec::new();
+    if !spec.headers.is_empty() {
+        unmapped.push(format!("{}: collection headers", spec.name));
+    }
+
+    let requests: Vec<(Vec<&str>, &RequestSpec)> = spec
+        .requests
+        .iter()
+        .map(|request| match request.folder.is_empty() {
+            true => (Vec::new(), request),
+            false => (request.folder.split(FOLDER_SEPARATOR).collect(), request),
+        })
+        .collect();
+    let mut document = json!({
+        "info": { "name": spec.name, "schema": SCHEMA },
+        "item": write_items(&requests, &mut unmapped),
+    });
+    if let Some(auth) = &spec.auth {
+        document["auth"] = write_auth(auth);
+    }
+    if !spec.variables.is_empty() {
+        document["variable"] = key_value_list(&spec.variables);
+    }
+
+    Ok(ExportedFile {
+        file_name: format!("{}.postman_collection.json", file_stem(&spec.name)),
+        contents: serde_json::to_string_pretty(&document)?,
+        unmapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::requests::{get_collection_requests, get_request_variables},
+            interchange::import_file,
+        },
+    };
+
+    const COLLECTION: &str = r#"{
+        "info": {
+            "name": "Shop",
+            "schema": "https://schema.getpostman.com/json/collection/v2.1.0/collection.json"
+        },
+        "variable": [
+            {"key": "base", "value": "https://shop.example.com"},
+            {"key": "retries", "value": 3, "disabled": true}
+        ],
+        "auth": {"type": "bearer", "bearer": [{"key": "token", "value": "{{token}}", "type": "string"}]},
+        "event": [{"listen": "prerequest", "script": {"exec": ["pm.environment.set('a', 1);"]}}],
+        "item": [
+            {
+                "name": "Orders",
+                "auth": {"type": "basic", "basic": [
+                    {"key": "username", "value": "admin"},
+                    {"key": "password", "value": "secret"}
+                ]},
+                "item": [
+                    {
+                        "name": "Get order",
+                        "request": {
+                            "method": "GET",
+                            "header": [{"key": "Accept", "value": "application/json"}],
+                            "url": {
+                                "raw": "{{base}}/orders/:id?expand=items",
+                                "host": ["{{base}}"],
+                                "path": ["orders", ":id"],
+                                "query": [
+                                    {"key": "expand", "value": "items"},
+                                    {"key": "debug", "value": "1", "disabled": true}
+                                ],
+                                "variable": [{"key": "id", "value": "42"}]
+                            }
+                        },
+                        "event": [
+                            {"listen": "test", "script": {"exec": ["pm.test('ok', () => {});"]}},
+                            {"listen": "prerequest", "script": {"exec": [""]}}
+                        ],
+                        "response": [{"name": "Found"}]
+                    },
+                    {
+                        "name": "Create order",
+                        "request": {
+                            "method": "POST",
+                            "auth": {"type": "noauth"},
+                            "url": "{{base}}/orders",
+                            "body": {
+                                "mode": "raw",
+                                "raw": "{\"id\": \"{{$guid}}\"}",
+                                "options": {"raw": {"language": "json"}}
+                            }
+                        }
+                    }
+                ]
+            },
+            {
+                "name": "Upload",
+                "request": {
+                    "method": "PUT",
+                    "url": "{{base}}/upload",
+                    "auth": {"type": "awsv4", "awsv4": []},
+                    "body": {
+                        "mode": "formdata",
+                        "formdata": [
+                            {"key": "note", "value": "hi", "type": "text"},
+                            {"key": "file", "src": ["/tmp/a.png", "/tmp/b.png"], "type": "file"},
+                            {"key": "old", "value": "x", "type": "text", "disabled": true}
+                        ]
+                    }
+                }
+            },
+            {
+                "name": "Search",
+                "request": {
+                    "method": "POST",
+                    "url": "{{base}}/graphql",
+                    "body": {
+                        "mode": "graphql",
+                        "graphql": {"query": "{ orders { id } }", "variables": "{\"first\": 2}"}
+                    }
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_collection() {
+        let parsed = parse_collection(COLLECTION).unwrap();
+        let collection = parsed.collection;
+
+        assert!(collection.name == "Shop");
+        assert!(collection.variables.len() == 2);
+        assert!(collection.variables[1].value == "3" && !collection.variables[1].enabled);
+        let auth = collection.auth.unwrap();
+        assert!(auth.auth_type == AuthTypes::Bearer && auth.secret == "{{token}}");
+        assert!(collection.requests.len() == 4);
+
+        let get_order = &collection.requests[0];
+        assert!(get_order.folder == "Orders");
+        assert!(get_order.url == "{{base}}/orders/{{id}}?expand=items");
+        assert!(get_order.params.len() == 2 && !get_order.params[1].enabled);
+        assert!(get_order.variables == vec![KeyValueSpec::new("id", "42", true)]);
+        assert!(get_order.headers[0].name == "Accept");
+        // Inherited from the folder.
+        let auth = get_order.auth.clone().unwrap();
+        assert!(auth.auth_type == AuthTypes::Basic && auth.username == "admin");
+
+        let create_order = &collection.requests[1];
+        assert!(create_order.method == HTTPMethods::Post);
+        assert!(create_order.body.mode == BodyModes::Json);
+        assert!(create_order.auth.clone().unwrap().auth_type == AuthTypes::None);
+
+        let upload = &collection.requests[2];
+        assert!(upload.folder.is_empty());
+        assert!(upload.body.mode == BodyModes::Multipart);
+        assert!(upload.body.parts[1].is_file && upload.body.parts[1].value == "/tmp/a.png");
+        assert!(!upload.body.parts[2].enabled);
+
+        let search = &collection.requests[3];
+        let payload: Value = serde_json::from_str(&search.body.payload).unwrap();
+        assert!(payload == json!({"query": "{ orders { id } }", "variables": {"first": 2}}));
+
+        assert!(
+            parsed.unmapped
+                == vec![
+                    "Shop: pre-request script",
+                    "Orders / Get order: test script",
+                    "Orders / Get order: saved example response",
+                    "Orders / Create order: dynamic variables {{$guid}}",
+                    "Upload: all but the first file of form field file",
+                    "Upload: awsv4 auth",
+                ]
+        );
+    }
+
+    #[test]
+    fn test_parse_collection_rejects_other_files() {
+        assert!(parse_collection("not json").is_err());
+        assert!(parse_collection(r#"{"openapi": "3.0.0"}"#).is_err());
+        let older = r#"{"info": {"name": "Old", "schema": "https://schema.getpostman.com/json/collection/v2.0.0/collection.json"}, "item": []}"#;
+        assert!(parse_collection(older).is_err());
+    }
+
+    #[test]
+    fn test_url_from_parts() {
+        let url = json!({
+            "protocol": "https",
+            "host": ["api", "example", "com"],
+            "port": "8443",
+            "path": ["v1", "users"],
+            "query": [{"key": "q", "value": "a b"}, {"key": "x", "value": "1", "disabled": true}]
+        });
+        assert!(url_from_parts(&url) == "https://api.example.com:8443/v1/users?q=a%20b");
+    }
+
+    #[test]
+    fn test_write_url() {
+        let params = vec![
+            KeyValueSpec::new("q", "a b", true),
+            KeyValueSpec::new("x", "1", false),
+        ];
+        let written = write_url("https://api.example.com:8443/v1/users?q=a%20b", &params);
+        assert!(written["host"] == json!(["api", "example", "com"]));
+        assert!(written["port"] == "8443");
+        assert!(written["query"][1]["disabled"] == true);
+        assert!(url_from_parts(&written) == "https://api.example.com:8443/v1/users?q=a%20b");
+    }
+
+    #[test]
+    fn test_write_collection_round_trip() {
+        let parsed = parse_collection(COLLECTION).unwrap();
+        let mut collection = parsed.collection;
+        collection.headers = vec![KeyValueSpec::new("X-Debug", "on", true)];
+        collection.requests[0].folder = "Orders / Open".to_string();
+
+        let exported = write_collection(&collection).unwrap();
+        assert!(exported.file_name == "Shop.postman_collection.json");
+        assert!(exported.unmapped == vec!["Shop: collection headers"]);
+
+        let document: Value = serde_json::from_str(&exported.contents).unwrap();
+        // Folders are nested, each placed where its first request was.
+        assert!(document["item"][0]["name"] == "Orders");
+        assert!(document["item"][0]["item"][0]["name"] == "Open");
+        assert!(document["item"][0]["item"][1]["name"] == "Create order");
+        assert!(document["item"][1]["name"] == "Upload");
+
+        let mut read = parse_collection(&exported.contents).unwrap().collection;
+        read.headers = collection.headers.clone();
+        // Folder auth was copied onto the requests on import and stays there.
+        assert!(read == collection);
+    }
+
+    #[tokio::test]
+    async fn test_import_postman_collection() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+
+        let reports = import_file(COLLECTION, &db).await.unwrap();
+        assert!(reports.len() == 1);
+        let report = &reports[0];
+        assert!(report.collection.name == "Shop");
+        assert!(report.requests == 4);
+        assert!(report.unmapped.len() == 6);
+
+        let requests = get_collection_requests(&db, &report.collection.id)
+            .await
+            .unwrap();
+        assert!(requests.len() == 4);
+        assert!(requests[0].name == "Get order");
+        // Disabled params are kept out of the URL.
+        assert!(requests[0].url == Some("{{base}}/orders/{{id}}?expand=items".to_string()));
+        let variables = get_request_variables(&requests[0].id, &db).await.unwrap();
+        assert!(variables.len() == 1 && variables[0].value == "42");
+    }
+}
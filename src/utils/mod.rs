@@ -1,9 +1,25 @@
 use std::error::Error;
 
+pub mod compare;
 pub mod crud;
+pub mod errors;
+pub mod executor;
+pub mod graphql;
+pub mod grpc;
+pub mod interchange;
 pub mod messaging;
+pub mod mqtt;
+pub mod query_params;
+pub mod response_view;
+pub mod snippets;
+pub mod socketio;
+pub mod sse;
 pub mod sys_dir;
+#[cfg(test)]
+pub mod test_server;
 pub mod tokio_runtime;
+pub mod variables;
+pub mod websocket;
 
 pub fn get_icon_pack_names() -> Result<Vec<String>, Box<dyn Error>> {
     let additional_icons = [
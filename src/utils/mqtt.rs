@@ -0,0 +1,1141 @@
+//! MQTT 3.1.1 over WebSocket: the packets, topic filters and client sessions
+//! with the log they leave behind.
+
+use std::{collections::HashSet, error::Error, fmt, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use sqlx::SqlitePool;
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::{interval_at, timeout, Instant},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+use crate::utils::{
+    crud::{mqtt::MqttConfigData, requests::RequestData},
+    errors::error_chain,
+    executor::{apply_oauth2_token, auth::PreparedAuth, prepare_request},
+    tokio_runtime::runtime,
+    variables::{UnresolvedVariables, VariableResolver},
+    websocket::{hex_text, shorten, Directions, Handshake, LogEntry},
+};
+
+/// The WebSocket subprotocol brokers expect MQTT to be carried in.
+pub const MQTT_PROTOCOL: &str = "mqtt";
+
+/// How long a broker gets to answer the connect packet.
+const CONNACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A subscription the broker refused, in place of the granted QoS.
+const SUBSCRIPTION_FAILED: u8 = 0x80;
+
+/// The longest string a packet carries, its length taking two bytes.
+const MAX_STRING: usize = u16::MAX as usize;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connect {
+    pub client_id: String,
+    pub clean_session: bool,
+    /// Seconds the broker waits for a packet before dropping the client.
+    pub keep_alive: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Publish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: u8,
+    pub retain: bool,
+    /// Set when the packet is sent again.
+    pub dup: bool,
+    /// Only QoS 1 and 2 messages have one.
+    pub packet_id: Option<u16>,
+}
+
+/// The control packets of MQTT 3.1.1.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Packet {
+    Connect(Connect),
+    ConnAck {
+        session_present: bool,
+        code: u8,
+    },
+    Publish(Publish),
+    PubAck(u16),
+    PubRec(u16),
+    PubRel(u16),
+    PubComp(u16),
+    Subscribe {
+        packet_id: u16,
+        filters: Vec<(String, u8)>,
+    },
+    SubAck {
+        packet_id: u16,
+        codes: Vec<u8>,
+    },
+    Unsubscribe {
+        packet_id: u16,
+        filters: Vec<String>,
+    },
+    UnsubAck(u16),
+    PingReq,
+    PingResp,
+    Disconnect,
+}
+
+fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_be_bytes());
+}
+
+/// A string with its length in front. Longer ones than that length holds
+/// are refused rather than cut short.
+fn put_bytes(bytes: &mut Vec<u8>, name: &str, value: &[u8]) -> Result<(), String> {
+    if value.len() > MAX_STRING {
+        return Err(format!("The {} is at most 65535 bytes long.", name));
+    }
+    put_u16(bytes, value.len() as u16);
+    bytes.extend_from_slice(value);
+    Ok(())
+}
+
+impl Packet {
+    /// The packet as it goes on the wire.
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut body = Vec::new();
+        let header = match self {
+            Packet::Connect(connect) => {
+                put_bytes(&mut body, "protocol name", b"MQTT")?;
+                body.push(4);
+                let mut flags = 0;
+                if connect.username.is_some() {
+                    flags |= 0x80;
+                }
+                if connect.password.is_some() {
+                    flags |= 0x40;
+                }
+                if connect.clean_session {
+                    flags |= 0x02;
+                }
+                body.push(flags);
+                put_u16(&mut body, connect.keep_alive);
+                put_bytes(&mut body, "client ID", connect.client_id.as_bytes())?;
+                for (name, value) in [
+                    ("user name", &connect.username),
+                    ("password", &connect.password),
+                ] {
+                    if let Some(value) = value {
+                        put_bytes(&mut body, name, value.as_bytes())?;
+                    }
+                }
+                0x10
+            }
+            Packet::ConnAck {
+                session_present,
+                code,
+            } => {
+                body.push(u8::from(*session_present));
+                body.push(*code);
+                0x20
+            }
+            Packet::Publish(publish) => {
+                put_bytes(&mut body, "topic", publish.topic.as_bytes())?;
+                if let Some(packet_id) = publish.packet_id {
+                    put_u16(&mut body, packet_id);
+                }
+                body.extend_from_slice(&publish.payload);
+                0x30 | (u8::from(publish.dup) << 3) | (publish.qos << 1) | u8::from(publish.retain)
+            }
+            Packet::PubAck(packet_id) => {
+                put_u16(&mut body, *packet_id);
+                0x40
+            }
+            Packet::PubRec(packet_id) => {
+                put_u16(&mut body, *packet_id);
+                0x50
+            }
+            Packet::PubRel(packet_id) => {
+                put_u16(&mut body, *packet_id);
+                0x62
+            }
+            Packet::PubComp(packet_id) => {
+                put_u16(&mut body, *packet_id);
+                0x70
+            }
+            Packet::Subscribe { packet_id, filters } => {
+                put_u16(&mut body, *packet_id);
+                for (filter, qos) in filters {
+                    put_bytes(&mut body, "topic filter", filter.as_bytes())?;
+                    body.push(*qos);
+                }
+                0x82
+            }
+            Packet::SubAck { packet_id, codes } => {
+                put_u16(&mut body, *packet_id);
+                body.extend_from_slice(codes);
+                0x90
+            }
+            Packet::Unsubscribe { packet_id, filters } => {
+                put_u16(&mut body, *packet_id);
+                for filter in filters {
+                    put_bytes(&mut body, "topic filter", filter.as_bytes())?;
+                }
+                0xa2
+            }
+            Packet::UnsubAck(packet_id) => {
+                put_u16(&mut body, *packet_id);
+                0xb0
+            }
+            Packet::PingReq => 0xc0,
+            Packet::PingResp => 0xd0,
+            Packet::Disconnect => 0xe0,
+        };
+
+        let mut bytes = vec![header];
+        let mut length = body.len();
+        loop {
+            let mut byte = (length % 128) as u8;
+            length /= 128;
+            if length > 0 {
+                byte |= 0x80;
+            }
+            bytes.push(byte);
+            if length == 0 {
+                break;
+            }
+        }
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+}
+
+/// Reads the fields of a packet body.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], Box<dyn Error + Send + Sync>> {
+        if self.bytes.len() < count {
+            return Err("The packet ends too early.".into());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Box<dyn Error + Send + Sync>> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Box<dyn Error + Send + Sync>> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn string(&mut self) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8(self.take(length)?.to_vec())?)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Read the packet at the start of `bytes`, along with how many bytes it
+/// took. Nothing is read while the packet is still incomplete.
+pub fn decode_packet(
+    bytes: &[u8],
+) -> Result<Option<(Packet, usize)>, Box<dyn Error + Send + Sync>> {
+    let Some(&header) = bytes.first() else {
+        return Ok(None);
+    };
+    let mut length = 0;
+    let mut offset = 1;
+    loop {
+        let Some(&byte) = bytes.get(offset) else {
+            return Ok(None);
+        };
+        length += ((byte & 0x7f) as usize) << (7 * (offset - 1));
+        offset += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if offset > 4 {
+            return Err("The packet length is malformed.".into());
+        }
+    }
+    if bytes.len() < offset + length {
+        return Ok(None);
+    }
+
+    let mut body = Reader {
+        bytes: &bytes[offset..offset + length],
+    };
+    let packet = match header >> 4 {
+        1 => {
+            if body.string()? != "MQTT" || body.u8()? != 4 {
+                return Err("Only MQTT 3.1.1 is spoken.".into());
+            }
+            let flags = body.u8()?;
+            let keep_alive = body.u16()?;
+            let client_id = body.string()?;
+            let username = match flags & 0x80 != 0 {
+                true => Some(body.string()?),
+                false => None,
+            };
+            let password = match flags & 0x40 != 0 {
+                true => Some(body.string()?),
+                false => None,
+            };
+            Packet::Connect(Connect {
+                client_id,
+                clean_session: flags & 0x02 != 0,
+                keep_alive,
+                username,
+                password,
+            })
+        }
+        2 => Packet::ConnAck {
+            session_present: body.u8()? & 0x01 != 0,
+            code: body.u8()?,
+        },
+        3 => {
+            let qos = (header >> 1) & 0x03;
+            if qos > 2 {
+                return Err("The packet has an unknown QoS.".into());
+            }
+            let topic = body.string()?;
+            let packet_id = match qos {
+                0 => None,
+                _ => Some(body.u16()?),
+            };
+            Packet::Publish(Publish {
+                topic,
+                payload: body.bytes.to_vec(),
+                qos,
+                retain: header & 0x01 != 0,
+                dup: header & 0x08 != 0,
+                packet_id,
+            })
+        }
+        4 => Packet::PubAck(body.u16()?),
+        5 => Packet::PubRec(body.u16()?),
+        6 => Packet::PubRel(body.u16()?),
+        7 => Packet::PubComp(body.u16()?),
+        8 => {
+            let packet_id = body.u16()?;
+            let mut filters = Vec::new();
+            while !body.is_empty() {
+                filters.push((body.string()?, body.u8()?));
+            }
+            Packet::Subscribe { packet_id, filters }
+        }
+        9 => Packet::SubAck {
+            packet_id: body.u16()?,
+            codes: body.bytes.to_vec(),
+        },
+        10 => {
+            let packet_id = body.u16()?;
+            let mut filters = Vec::new();
+            while !body.is_empty() {
+                filters.push(body.string()?);
+            }
+            Packet::Unsubscribe { packet_id, filters }
+        }
+        11 => Packet::UnsubAck(body.u16()?),
+        12 => Packet::PingReq,
+        13 => Packet::PingResp,
+        14 => Packet::Disconnect,
+        kind => return Err(format!("Unknown packet type {}.", kind).into()),
+    };
+
+    Ok(Some((packet, offset + length)))
+}
+
+/// Whether a topic filter, wildcards and all, takes in a topic. Topics
+/// starting with `$` are only matched by filters spelling the `$` out.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(filter_level), Some(topic_level)) if filter_level == topic_level => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// Check a topic messages are published to. Those have no wildcards.
+pub fn validate_topic(topic: &str) -> Result<(), Box<dyn Error>> {
+    if topic.is_empty() {
+        return Err("Enter a topic to publish to.".into());
+    }
+    if topic.len() > MAX_STRING {
+        return Err("Topics are at most 65535 bytes long.".into());
+    }
+    if topic.contains(['+', '#']) {
+        return Err(format!(
+            "\"{}\" has wildcards, messages go to a single topic.",
+            topic
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Check a topic filter. `+` takes a whole level and `#` the last one.
+pub fn validate_filter(filter: &str) -> Result<(), Box<dyn Error>> {
+    if filter.is_empty() {
+        return Err("Topic filters can't be empty.".into());
+    }
+    if filter.len() > MAX_STRING {
+        return Err("Topic filters are at most 65535 bytes long.".into());
+    }
+    let levels: Vec<&str> = filter.split('/').collect();
+    for (index, level) in levels.iter().enumerate() {
+        let wildcard_misplaced = (level.contains('+') && *level != "+")
+            || (level.contains('#') && (*level != "#" || index + 1 != levels.len()));
+        if wildcard_misplaced {
+            return Err(format!("\"{}\" is not a valid topic filter.", filter).into());
+        }
+    }
+    Ok(())
+}
+
+/// Topic filters typed one per line.
+pub fn parse_filters(text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let filters: Vec<String> = text
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if filters.is_empty() {
+        return Err("Enter a topic filter, one per line.".into());
+    }
+    for filter in &filters {
+        validate_filter(filter)?;
+    }
+    Ok(filters)
+}
+
+/// Why a broker turned the client away.
+fn connect_refusal(code: u8) -> String {
+    let reason = match code {
+        1 => "unacceptable protocol version",
+        2 => "client identifier rejected",
+        3 => "server unavailable",
+        4 => "bad user name or password",
+        5 => "not authorized",
+        _ => "unknown reason",
+    };
+    format!("Connection refused: {} ({})", reason, code)
+}
+
+/// A client id for requests that don't set one.
+pub fn generated_client_id() -> String {
+    format!("querry-{}", &Uuid::new_v4().simple().to_string()[..8])
+}
+
+/// Payloads as text, or as hex when they are not text.
+fn payload_text(payload: &[u8]) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(text) => shorten(text.to_string()),
+        Err(_) => shorten(hex_text(payload)),
+    }
+}
+
+fn describe_publish(publish: &Publish) -> String {
+    let mut flags = Vec::new();
+    if publish.qos > 0 {
+        flags.push(format!("QoS {}", publish.qos));
+    }
+    if publish.retain {
+        flags.push("retained".to_string());
+    }
+    if publish.dup {
+        flags.push("again".to_string());
+    }
+    let topic = match flags.is_empty() {
+        true => publish.topic.clone(),
+        false => format!("{} ({})", publish.topic, flags.join(", ")),
+    };
+    format!("{}\n{}", topic, payload_text(&publish.payload))
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKinds {
+    Connected,
+    Publish,
+    Subscribe,
+    Unsubscribe,
+    /// The acknowledgements of publishes and subscriptions.
+    Ack,
+    Disconnected,
+    Error,
+}
+
+impl fmt::Display for EntryKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryKinds::Connected => write!(f, "connected"),
+            EntryKinds::Publish => write!(f, "publish"),
+            EntryKinds::Subscribe => write!(f, "subscribe"),
+            EntryKinds::Unsubscribe => write!(f, "unsubscribe"),
+            EntryKinds::Ack => write!(f, "ack"),
+            EntryKinds::Disconnected => write!(f, "disconnected"),
+            EntryKinds::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A line of the session log. Publishes are logged as their topic on the
+/// first line and payload below.
+pub type MqttEntry = LogEntry<EntryKinds>;
+
+impl MqttEntry {
+    /// The entry for a packet, none for the packets only keeping the
+    /// connection alive.
+    fn for_packet(direction: Directions, packet: &Packet) -> Option<MqttEntry> {
+        let (kind, data) = match packet {
+            Packet::Publish(publish) => (EntryKinds::Publish, describe_publish(publish)),
+            Packet::PubAck(packet_id) => (EntryKinds::Ack, format!("PUBACK {}", packet_id)),
+            Packet::PubRec(packet_id) => (EntryKinds::Ack, format!("PUBREC {}", packet_id)),
+            Packet::PubRel(packet_id) => (EntryKinds::Ack, format!("PUBREL {}", packet_id)),
+            Packet::PubComp(packet_id) => (EntryKinds::Ack, format!("PUBCOMP {}", packet_id)),
+            Packet::Subscribe { filters, .. } => (
+                EntryKinds::Subscribe,
+                filters
+                    .iter()
+                    .map(|(filter, qos)| format!("{} (QoS {})", filter, qos))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            Packet::SubAck { codes, .. } => {
+                let granted: Vec<String> = codes
+                    .iter()
+                    .map(|code| match *code {
+                        SUBSCRIPTION_FAILED => "refused".to_string(),
+                        qos => format!("QoS {}", qos),
+                    })
+                    .collect();
+                (EntryKinds::Ack, format!("SUBACK {}", granted.join(", ")))
+            }
+            Packet::Unsubscribe { filters, .. } => (EntryKinds::Unsubscribe, filters.join("\n")),
+            Packet::UnsubAck(_) => (EntryKinds::Ack, "UNSUBACK".to_string()),
+            _ => return None,
+        };
+        Some(MqttEntry::new(direction, kind, &data))
+    }
+}
+
+/// What a client session is asked to do.
+enum Commands {
+    Publish(Publish),
+    Subscribe(Vec<(String, u8)>),
+    Unsubscribe(Vec<String>),
+    Disconnect,
+}
+
+fn check_qos(qos: u8) -> Result<(), Box<dyn Error>> {
+    match qos {
+        0..=2 => Ok(()),
+        _ => Err("QoS is 0, 1 or 2.".into()),
+    }
+}
+
+/// A connected client. Dropping it disconnects.
+pub struct MqttSession {
+    commands: UnboundedSender<Commands>,
+}
+
+impl MqttSession {
+    fn send(&self, command: Commands) -> Result<(), Box<dyn Error>> {
+        self.commands
+            .send(command)
+            .map_err(|_| "The client is disconnected.".into())
+    }
+
+    /// Queue a message. What happens to it shows up in the log.
+    pub fn publish(
+        &self,
+        topic: &str,
+        payload: &str,
+        qos: u8,
+        retain: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        validate_topic(topic)?;
+        check_qos(qos)?;
+        self.send(Commands::Publish(Publish {
+            topic: topic.to_string(),
+            payload: payload.as_bytes().to_vec(),
+            qos,
+            retain,
+            dup: false,
+            packet_id: None,
+        }))
+    }
+
+    pub fn subscribe(&self, filters: &[String], qos: u8) -> Result<(), Box<dyn Error>> {
+        check_qos(qos)?;
+        for filter in filters {
+            validate_filter(filter)?;
+        }
+        self.send(Commands::Subscribe(
+            filters.iter().map(|filter| (filter.clone(), qos)).collect(),
+        ))
+    }
+
+    pub fn unsubscribe(&self, filters: &[String]) -> Result<(), Box<dyn Error>> {
+        self.send(Commands::Unsubscribe(filters.to_vec()))
+    }
+
+    /// Tell the broker the client is leaving, then close the connection.
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(Commands::Disconnect);
+    }
+}
+
+/// How a client connects to its broker.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MqttOptions {
+    pub handshake: Handshake,
+    pub connect: Connect,
+}
+
+/// The options of a stored request, with its variables substituted.
+///
+/// The handshake gets the request's headers, like any WebSocket request.
+/// Basic auth is sent as the user name and password of the connect packet
+/// rather than as a header.
+pub async fn prepare_mqtt(
+    request: &RequestData,
+    config: &MqttConfigData,
+    pool: &SqlitePool,
+) -> Result<MqttOptions, Box<dyn Error>> {
+    let mut prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let mut unresolved = Vec::new();
+    let auth = PreparedAuth::for_request(request, pool)
+        .await?
+        .substitute_variables(&variables, &mut unresolved);
+    let client_id = variables.substitute(config.client_id.trim(), &mut unresolved);
+    if !unresolved.is_empty() {
+        return Err(UnresolvedVariables(unresolved).into());
+    }
+
+    let (username, password) = match auth {
+        PreparedAuth::Basic { username, password } => {
+            prepared
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("authorization"));
+            (Some(username), Some(password).filter(|p| !p.is_empty()))
+        }
+        _ => (None, None),
+    };
+    let keep_alive = u16::try_from(config.keep_alive.max(0))
+        .map_err(|_| "Keep alive is at most 65535 seconds.")?;
+    let too_long = [
+        ("client ID", Some(&client_id)),
+        ("user name", username.as_ref()),
+        ("password", password.as_ref()),
+    ]
+    .into_iter()
+    .find(|(_, value)| value.is_some_and(|value| value.len() > MAX_STRING));
+    if let Some((name, _)) = too_long {
+        return Err(format!("The {} is at most 65535 bytes long.", name).into());
+    }
+
+    Ok(MqttOptions {
+        handshake: Handshake::for_request(&prepared, vec![MQTT_PROTOCOL.to_string()])?,
+        connect: Connect {
+            client_id: match client_id.is_empty() {
+                true => generated_client_id(),
+                false => client_id,
+            },
+            clean_session: config.clean_session,
+            keep_alive,
+            username,
+            password,
+        },
+    })
+}
+
+/// The topic and payload of the next publish, with the request's variables
+/// substituted.
+pub async fn prepare_publish(
+    request: &RequestData,
+    config: &MqttConfigData,
+    pool: &SqlitePool,
+) -> Result<(String, String), Box<dyn Error>> {
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let mut unresolved = Vec::new();
+    let topic = variables.substitute(config.topic.trim(), &mut unresolved);
+    let payload = variables.substitute(&config.payload, &mut unresolved);
+    if !unresolved.is_empty() {
+        return Err(UnresolvedVariables(unresolved).into());
+    }
+    Ok((topic, payload))
+}
+
+/// A client and the log entries it produces, starting with the broker
+/// accepting it. The log ends once the client is disconnected.
+pub struct MqttConnection {
+    pub session: MqttSession,
+    pub log: UnboundedReceiver<MqttEntry>,
+}
+
+/// Connect a client, returning once the broker accepted it.
+///
+/// The connection lives on the runtime, so only the log entries are
+/// awaited on the UI side.
+pub async fn connect_mqtt(options: MqttOptions) -> Result<MqttConnection, Box<dyn Error>> {
+    let connection = runtime()
+        .spawn(async move { open_session(options).await })
+        .await?
+        .map_err(|error| error_chain(error.as_ref()))?;
+
+    Ok(connection)
+}
+
+/// Wait for the broker to accept the client, telling whether it kept the
+/// session of an earlier connection.
+async fn await_connack(
+    socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    buffer: &mut Vec<u8>,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    loop {
+        if let Some((packet, length)) = decode_packet(buffer)? {
+            buffer.drain(..length);
+            return match packet {
+                Packet::ConnAck {
+                    code: 0,
+                    session_present,
+                } => Ok(session_present),
+                Packet::ConnAck { code, .. } => Err(connect_refusal(code).into()),
+                _ => Err("The broker didn't acknowledge the connection.".into()),
+            };
+        }
+        match socket.next().await {
+            Some(Ok(Message::Binary(bytes))) => buffer.extend_from_slice(&bytes),
+            Some(Ok(Message::Close(_))) | None => {
+                return Err("The broker closed the connection.".into())
+            }
+            Some(Ok(_)) => {}
+            Some(Err(error)) => return Err(error.into()),
+        }
+    }
+}
+
+async fn open_session(
+    options: MqttOptions,
+) -> Result<MqttConnection, Box<dyn Error + Send + Sync>> {
+    let (mut socket, _) = connect_async(options.handshake.to_request()?).await?;
+    socket
+        .send(Message::binary(
+            Packet::Connect(options.connect.clone()).encode()?,
+        ))
+        .await?;
+
+    let mut buffer = Vec::new();
+    let session_present = timeout(CONNACK_TIMEOUT, await_connack(&mut socket, &mut buffer))
+        .await
+        .map_err(|_| "The broker didn't answer in time.")??;
+
+    let (commands, mut queued) = unbounded_channel::<Commands>();
+    let (log, entries) = unbounded_channel();
+    let mut connected = format!("{} as {}", options.handshake.url, options.connect.client_id);
+    if session_present {
+        connected.push_str(", resuming the session");
+    }
+    let _ = log.send(MqttEntry::new(
+        Directions::Info,
+        EntryKinds::Connected,
+        &connected,
+    ));
+
+    let keep_alive = Duration::from_secs(options.connect.keep_alive.into());
+    tokio::spawn(async move {
+        let (mut sink, mut stream) = socket.split();
+        // Pinging at the keep alive itself can be too late for the broker.
+        let period = (keep_alive * 3 / 4).max(Duration::from_secs(1));
+        let mut pings = interval_at(Instant::now() + period, period);
+        let mut last_packet_id: u16 = 0;
+        // QoS 2 messages received but not released yet, so one that
+        // arrives again isn't logged twice.
+        let mut unreleased = HashSet::new();
+        let mut outgoing: Vec<Packet> = Vec::new();
+
+        let error = 'session: loop {
+            for packet in outgoing.drain(..) {
+                let bytes = match packet.encode() {
+                    Ok(bytes) => bytes,
+                    Err(error) => {
+                        let _ =
+                            log.send(MqttEntry::new(Directions::Info, EntryKinds::Error, &error));
+                        continue;
+                    }
+                };
+                let entry = MqttEntry::for_packet(Directions::Sent, &packet);
+                if let Err(error) = sink.send(Message::binary(bytes)).await {
+                    break 'session Some(error_chain(&error));
+                }
+                if let Some(entry) = entry {
+                    let _ = log.send(entry);
+                }
+            }
+
+            tokio::select! {
+                command = queued.recv() => {
+                    let mut next_id = || {
+                        last_packet_id = last_packet_id.checked_add(1).unwrap_or(1);
+                        last_packet_id
+                    };
+                    match command {
+                        Some(Commands::Publish(mut publish)) => {
+                            if publish.qos > 0 {
+                                publish.packet_id = Some(next_id());
+                            }
+                            outgoing.push(Packet::Publish(publish));
+                        }
+                        Some(Commands::Subscribe(filters)) => outgoing.push(Packet::Subscribe {
+                            packet_id: next_id(),
+                            filters,
+                        }),
+                        Some(Commands::Unsubscribe(filters)) => outgoing.push(Packet::Unsubscribe {
+                            packet_id: next_id(),
+                            filters,
+                        }),
+                        // A dropped session disconnects too.
+                        Some(Commands::Disconnect) | None => {
+                            if let Ok(bytes) = Packet::Disconnect.encode() {
+                                let _ = sink.send(Message::binary(bytes)).await;
+                            }
+                            let _ = sink.close().await;
+                            break 'session None;
+                        }
+                    }
+                }
+                _ = pings.tick(), if !keep_alive.is_zero() => {
+                    outgoing.push(Packet::PingReq);
+                }
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Binary(bytes))) => {
+                        buffer.extend_from_slice(&bytes);
+                        loop {
+                            match decode_packet(&buffer) {
+                                Ok(Some((packet, length))) => {
+                                    buffer.drain(..length);
+                                    let logged = match &packet {
+                                        Packet::Publish(Publish { qos: 1, packet_id: Some(packet_id), .. }) => {
+                                            outgoing.push(Packet::PubAck(*packet_id));
+                                            true
+                                        }
+                                        Packet::Publish(Publish { qos: 2, packet_id: Some(packet_id), .. }) => {
+                                            outgoing.push(Packet::PubRec(*packet_id));
+                                            unreleased.insert(*packet_id)
+                                        }
+                                        Packet::PubRel(packet_id) => {
+                                            unreleased.remove(packet_id);
+                                            outgoing.push(Packet::PubComp(*packet_id));
+                                            true
+                                        }
+                                        Packet::PubRec(packet_id) => {
+                                            outgoing.push(Packet::PubRel(*packet_id));
+                                            true
+                                        }
+                                        _ => true,
+                                    };
+                                    if logged {
+                                        if let Some(entry) = MqttEntry::for_packet(Directions::Received, &packet) {
+                                            let _ = log.send(entry);
+                                        }
+                                    }
+                                }
+                                Ok(None) => break,
+                                Err(error) => break 'session Some(error.to_string()),
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Text(_))) => {
+                        break 'session Some("The broker sent text, MQTT is carried in binary frames.".to_string());
+                    }
+                    Some(Ok(Message::Close(_))) | None => break 'session None,
+                    Some(Ok(_)) => {}
+                    Some(Err(error)) => break 'session Some(error_chain(&error)),
+                },
+            }
+        };
+        if let Some(error) = error {
+            let _ = log.send(MqttEntry::new(Directions::Info, EntryKinds::Error, &error));
+        }
+        let _ = log.send(MqttEntry::new(
+            Directions::Info,
+            EntryKinds::Disconnected,
+            "",
+        ));
+    });
+
+    Ok(MqttConnection {
+        session: MqttSession { commands },
+        log: entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                auth::{update_auth_secret, update_request_auth, ApiKeyPlacement, AuthTypes},
+                collections::create_collection,
+                requests::{create_request, update_request_item, HTTPMethods, ProtocolTypes},
+            },
+            test_server::spawn_mqtt_broker,
+        },
+    };
+
+    async fn next_entry(connection: &mut MqttConnection) -> MqttEntry {
+        connection.log.recv().await.expect("The log ended early.")
+    }
+
+    #[test]
+    fn test_packets() {
+        let packets = vec![
+            Packet::Connect(Connect {
+                client_id: "client".to_string(),
+                clean_session: true,
+                keep_alive: 60,
+                username: Some("user".to_string()),
+                password: Some("secret".to_string()),
+            }),
+            Packet::ConnAck {
+                session_present: true,
+                code: 0,
+            },
+            Packet::Publish(Publish {
+                topic: "a/b".to_string(),
+                payload: vec![7; 300],
+                qos: 2,
+                retain: true,
+                dup: false,
+                packet_id: Some(9),
+            }),
+            Packet::PubRel(9),
+            Packet::Subscribe {
+                packet_id: 1,
+                filters: vec![("a/+".to_string(), 1), ("#".to_string(), 0)],
+            },
+            Packet::SubAck {
+                packet_id: 1,
+                codes: vec![1, SUBSCRIPTION_FAILED],
+            },
+            Packet::Unsubscribe {
+                packet_id: 2,
+                filters: vec!["a/+".to_string()],
+            },
+            Packet::PingReq,
+            Packet::Disconnect,
+        ];
+
+        let mut bytes = Vec::new();
+        for packet in &packets {
+            bytes.extend(packet.encode().unwrap());
+        }
+        // Payloads over 127 bytes take a second length byte.
+        assert!(Packet::PingReq.encode().unwrap() == vec![0xc0, 0x00]);
+        assert!(decode_packet(&bytes[..3]).unwrap().is_none());
+
+        let mut decoded = Vec::new();
+        let mut offset = 0;
+        while let Some((packet, length)) = decode_packet(&bytes[offset..]).unwrap() {
+            decoded.push(packet);
+            offset += length;
+        }
+        assert!(decoded == packets && offset == bytes.len());
+        assert!(decode_packet(&[0x40, 0x01, 0x00]).is_err());
+        assert!(decode_packet(&[0xf0, 0x00]).is_err());
+
+        // Longer strings than their two length bytes hold aren't cut short.
+        let long = "a".repeat(MAX_STRING + 1);
+        let publish = Packet::Publish(Publish {
+            topic: long.clone(),
+            payload: Vec::new(),
+            qos: 0,
+            retain: false,
+            dup: false,
+            packet_id: None,
+        });
+        assert!(publish.encode().is_err());
+        assert!(Packet::Unsubscribe {
+            packet_id: 3,
+            filters: vec![long.clone()],
+        }
+        .encode()
+        .is_err());
+        assert!(validate_topic(&long).is_err());
+        assert!(validate_filter(&long).is_err());
+    }
+
+    #[test]
+    fn test_topic_filters() {
+        assert!(topic_matches("sport/tennis/+", "sport/tennis/player1"));
+        assert!(!topic_matches(
+            "sport/tennis/+",
+            "sport/tennis/player1/ranking"
+        ));
+        assert!(topic_matches("sport/#", "sport"));
+        assert!(topic_matches("sport/#", "sport/tennis/player1"));
+        assert!(topic_matches("+/+", "/finance"));
+        assert!(!topic_matches("sport/+", "sport"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+
+        assert!(validate_filter("sport/+/player/#").is_ok());
+        assert!(validate_filter("sport/#/ranking").is_err());
+        assert!(validate_filter("sport+").is_err());
+        assert!(validate_topic("sport/tennis").is_ok());
+        assert!(validate_topic("sport/+").is_err());
+        assert!(parse_filters(" a/b \n\n c/# ").unwrap() == vec!["a/b", "c/#"]);
+        assert!(parse_filters("\n").is_err());
+        assert!(generated_client_id().len() == 15);
+    }
+
+    #[tokio::test]
+    async fn test_mqtt_session() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let url = spawn_mqtt_broker().await;
+        let collection = create_collection("Brokers".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Mqtt, &collection.id, &db)
+            .await
+            .unwrap();
+        let request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::Mqtt,
+            HTTPMethods::Get,
+            &url,
+            &db,
+        )
+        .await
+        .unwrap();
+        let auth = update_request_auth(
+            &request.id,
+            AuthTypes::Basic,
+            "user",
+            "",
+            ApiKeyPlacement::Header,
+            &db,
+        )
+        .await
+        .unwrap();
+        update_auth_secret(&auth.id, "secret", &db).await.unwrap();
+
+        let config = MqttConfigData {
+            client_id: "sensor".to_string(),
+            ..Default::default()
+        };
+        let options = prepare_mqtt(&request, &config, &db).await.unwrap();
+        assert!(options.handshake.subprotocols == vec![MQTT_PROTOCOL]);
+        assert!(!options
+            .handshake
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("authorization")));
+        assert!(options.connect.username.as_deref() == Some("user"));
+        let long_id = MqttConfigData {
+            client_id: "a".repeat(MAX_STRING + 1),
+            ..Default::default()
+        };
+        assert!(prepare_mqtt(&request, &long_id, &db).await.is_err());
+
+        let mut connection = connect_mqtt(options.clone()).await.unwrap();
+        let connected = next_entry(&mut connection).await;
+        assert!(connected.kind == EntryKinds::Connected && connected.data.ends_with("as sensor"));
+
+        let session = &connection.session;
+        session
+            .subscribe(&["sensors/+".to_string(), "refused/#".to_string()], 2)
+            .unwrap();
+        session.publish("sensors/kitchen", "21.5", 1, true).unwrap();
+        session.publish("sensors/hall", "19", 2, false).unwrap();
+        assert!(session.publish("sensors/#", "", 0, false).is_err());
+
+        // Both QoS 2 flows are done once the last completions went by.
+        let mut entries = Vec::new();
+        let mut completions = 0;
+        while completions < 2 {
+            let entry = next_entry(&mut connection).await;
+            if entry.data.starts_with("PUBCOMP") {
+                completions += 1;
+            }
+            entries.push((entry.direction, entry.kind, entry.data));
+        }
+        connection.session.disconnect();
+        while let Some(entry) = connection.log.recv().await {
+            entries.push((entry.direction, entry.kind, entry.data));
+        }
+        let sent = |kind, data: &str| (Directions::Sent, kind, data.to_string());
+        let received = |kind, data: &str| (Directions::Received, kind, data.to_string());
+        assert!(entries.contains(&sent(
+            EntryKinds::Subscribe,
+            "sensors/+ (QoS 2)\nrefused/# (QoS 2)"
+        )));
+        assert!(entries.contains(&received(EntryKinds::Ack, "SUBACK QoS 2, refused")));
+        assert!(entries.contains(&sent(
+            EntryKinds::Publish,
+            "sensors/kitchen (QoS 1, retained)\n21.5"
+        )));
+        assert!(entries.contains(&received(EntryKinds::Ack, "PUBACK 2")));
+        assert!(entries.contains(&received(
+            EntryKinds::Publish,
+            "sensors/kitchen (QoS 1)\n21.5"
+        )));
+        assert!(entries.contains(&sent(EntryKinds::Ack, "PUBACK 1")));
+        assert!(entries.contains(&received(EntryKinds::Ack, "PUBREC 3")));
+        assert!(entries.contains(&sent(EntryKinds::Ack, "PUBREL 3")));
+        assert!(entries.contains(&received(EntryKinds::Ack, "PUBCOMP 3")));
+        assert!(entries.contains(&received(EntryKinds::Publish, "sensors/hall (QoS 2)\n19")));
+        assert!(entries.contains(&sent(EntryKinds::Ack, "PUBCOMP 2")));
+        assert!(entries.last().unwrap().1 == EntryKinds::Disconnected);
+
+        // The retained message is waiting for the next subscriber.
+        let mut connection = connect_mqtt(options.clone()).await.unwrap();
+        next_entry(&mut connection).await;
+        connection
+            .session
+            .subscribe(&["sensors/#".to_string()], 0)
+            .unwrap();
+        loop {
+            let entry = next_entry(&mut connection).await;
+            if entry.kind == EntryKinds::Publish && entry.direction == Directions::Received {
+                assert!(entry.data == "sensors/kitchen (retained)\n21.5");
+                break;
+            }
+        }
+        connection.session.disconnect();
+        while connection.log.recv().await.is_some() {}
+
+        let refused = MqttOptions {
+            connect: Connect {
+                password: Some("wrong".to_string()),
+                ..options.connect
+            },
+            ..options
+        };
+        let error = connect_mqtt(refused).await.err().unwrap();
+        assert!(error.to_string().contains("bad user name or password"));
+    }
+}
@@ -0,0 +1,264 @@
+//! Keeping the query string of a request URL and its params table in sync.
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+
+/// Characters escaped in query keys and values. Braces are left alone so
+/// `{{variable}}` placeholders survive a round trip.
+const QUERY_COMPONENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'`');
+
+/// A query parameter row of the params table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamRow {
+    pub name: String,
+    pub value: String,
+    pub enabled: bool,
+}
+
+/// Split a URL into the part before the query, the query and the fragment.
+pub fn split_url(url: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (without_fragment, fragment) = match url.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (url, None),
+    };
+    match without_fragment.split_once('?') {
+        Some((base, query)) => (base, Some(query), fragment),
+        None => (without_fragment, None, fragment),
+    }
+}
+
+pub fn encode_component(value: &str) -> String {
+    utf8_percent_encode(value, QUERY_COMPONENT).to_string()
+}
+
+pub fn decode_component(value: &str) -> String {
+    percent_decode_str(&value.replace('+', " "))
+        .decode_utf8_lossy()
+        .to_string()
+}
+
+/// Decoded query parameters of a URL, in order.
+pub fn parse_query(url: &str) -> Vec<(String, String)> {
+    let (_, query, _) = split_url(url);
+    query
+        .unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode_component(name), decode_component(value))
+        })
+        .collect()
+}
+
+/// Encode parameters into a query string, without the leading `?`.
+pub fn build_query(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode_component(name), encode_component(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+/// Replace the query string of a URL, keeping its fragment.
+pub fn replace_query(url: &str, params: &[(String, String)]) -> String {
+    let (base, _, fragment) = split_url(url);
+    let mut new_url = base.to_string();
+    if !params.is_empty() {
+        new_url.push('?');
+        new_url.push_str(&build_query(params));
+    }
+    if let Some(fragment) = fragment {
+        new_url.push('#');
+        new_url.push_str(fragment);
+    }
+    new_url
+}
+
+/// Add one parameter to the end of a URL's query, leaving the rest untouched.
+pub fn append_query_param(url: &str, name: &str, value: &str) -> String {
+    let (base, query, fragment) = split_url(url);
+    let mut new_url = base.to_string();
+    new_url.push('?');
+    if let Some(query) = query.filter(|query| !query.is_empty()) {
+        new_url.push_str(query);
+        new_url.push('&');
+    }
+    new_url.push_str(&format!(
+        "{}={}",
+        encode_component(name),
+        encode_component(value)
+    ));
+    if let Some(fragment) = fragment {
+        new_url.push('#');
+        new_url.push_str(fragment);
+    }
+    new_url
+}
+
+/// Whether a row is carried by the URL. Blank rows, like a param that was
+/// just added, are left out until something is typed in them.
+fn in_url(row: &ParamRow) -> bool {
+    row.enabled && !(row.name.is_empty() && row.value.is_empty())
+}
+
+/// Rebuild the params table after the URL changed.
+///
+/// The URL only carries enabled params, so enabled rows are replaced by what
+/// was parsed from it, in order, while disabled and blank rows stay where
+/// they are.
+pub fn merge_url_params(existing: &[ParamRow], parsed: Vec<(String, String)>) -> Vec<ParamRow> {
+    let mut parsed = parsed
+        .into_iter()
+        .map(|(name, value)| ParamRow {
+            name,
+            value,
+            enabled: true,
+        })
+        .filter(in_url);
+    let mut rows: Vec<ParamRow> = Vec::new();
+
+    for row in existing {
+        if !in_url(row) {
+            rows.push(row.clone());
+        } else if let Some(parsed_row) = parsed.next() {
+            rows.push(parsed_row);
+        }
+    }
+    rows.extend(parsed);
+
+    rows
+}
+
+/// Build the URL for the enabled rows of the params table.
+pub fn url_from_params(url: &str, rows: &[ParamRow]) -> String {
+    let params: Vec<(String, String)> = rows
+        .iter()
+        .filter(|row| in_url(row))
+        .map(|row| (row.name.clone(), row.value.clone()))
+        .collect();
+    replace_query(url, &params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, value: &str, enabled: bool) -> ParamRow {
+        ParamRow {
+            name: name.to_string(),
+            value: value.to_string(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn test_split_url() {
+        assert!(
+            split_url("https://a.com/x?q=1#top") == ("https://a.com/x", Some("q=1"), Some("top"))
+        );
+        assert!(split_url("https://a.com/x") == ("https://a.com/x", None, None));
+        assert!(split_url("https://a.com/x#a?b") == ("https://a.com/x", None, Some("a?b")));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let params = parse_query("https://a.com/?q=rust+lang&page=2%263&flag&=empty");
+        assert!(
+            params
+                == vec![
+                    ("q".to_string(), "rust lang".to_string()),
+                    ("page".to_string(), "2&3".to_string()),
+                    ("flag".to_string(), "".to_string()),
+                    ("".to_string(), "empty".to_string()),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_replace_query() {
+        let params = vec![
+            ("q".to_string(), "rust lang".to_string()),
+            ("redirect".to_string(), "a=b&c".to_string()),
+            ("id".to_string(), "{{user_id}}".to_string()),
+        ];
+        let url = replace_query("https://a.com/search?old=1#results", &params);
+        assert!(
+            url == "https://a.com/search?q=rust%20lang&redirect=a%3Db%26c&id={{user_id}}#results"
+        );
+        assert!(parse_query(&url) == params);
+
+        assert!(replace_query("https://a.com/search?old=1", &[]) == "https://a.com/search");
+    }
+
+    #[test]
+    fn test_append_query_param() {
+        assert!(append_query_param("https://a.io/x", "key", "a b") == "https://a.io/x?key=a%20b");
+        assert!(
+            append_query_param("https://a.io/x?q=%2F#top", "key", "1")
+                == "https://a.io/x?q=%2F&key=1#top"
+        );
+    }
+
+    #[test]
+    fn test_merge_url_params() {
+        let existing = vec![
+            row("a", "1", true),
+            row("debug", "true", false),
+            row("b", "2", true),
+        ];
+
+        let merged = merge_url_params(
+            &existing,
+            vec![
+                ("a".to_string(), "10".to_string()),
+                ("b".to_string(), "20".to_string()),
+                ("c".to_string(), "30".to_string()),
+            ],
+        );
+        assert!(
+            merged
+                == vec![
+                    row("a", "10", true),
+                    row("debug", "true", false),
+                    row("b", "20", true),
+                    row("c", "30", true),
+                ]
+        );
+
+        let merged = merge_url_params(&existing, Vec::new());
+        assert!(merged == vec![row("debug", "true", false)]);
+    }
+
+    #[test]
+    fn test_url_from_params() {
+        let rows = vec![
+            row("a", "1", true),
+            row("debug", "true", false),
+            row("", "", true),
+        ];
+        assert!(url_from_params("https://a.com/?x=1", &rows) == "https://a.com/?a=1");
+    }
+
+    #[test]
+    fn test_empty_param_names() {
+        let url = "https://a.com/?=x&a=1";
+        let blank = row("", "", true);
+
+        let rows = merge_url_params(std::slice::from_ref(&blank), parse_query(url));
+        assert!(rows == vec![blank, row("", "x", true), row("a", "1", true)]);
+        assert!(url_from_params(url, &rows) == url);
+
+        // Syncing the same URL again leaves the table as it is.
+        assert!(merge_url_params(&rows, parse_query(url)) == rows);
+    }
+}
@@ -0,0 +1,740 @@
+//! Turning a response body into the lines shown by the response viewer.
+//!
+//! Bodies are formatted once, off the UI thread, and split into lines. The
+//! viewer then only highlights the lines it shows, so large bodies do not
+//! block the event loop.
+
+use std::{error::Error, fmt};
+
+use crate::utils::{executor::ResponseData, tokio_runtime::runtime};
+
+/// Bodies larger than this are shown as they are instead of pretty-printed.
+pub const PRETTY_PRINT_LIMIT: usize = 20 * 1024 * 1024;
+
+/// Longer lines are split over several rows, so minified bodies stay scrollable.
+pub const MAX_LINE_LENGTH: usize = 500;
+
+/// Bytes shown per row of the hex view.
+pub const HEX_ROW_LENGTH: usize = 16;
+
+/// HTML elements that never have a closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// HTML elements whose content is not markup.
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BodyKind {
+    Json,
+    Xml,
+    Html,
+    Image,
+    Text,
+    Binary,
+}
+
+impl fmt::Display for BodyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BodyKind::Json => write!(f, "json"),
+            BodyKind::Xml => write!(f, "xml"),
+            BodyKind::Html => write!(f, "html"),
+            BodyKind::Image => write!(f, "image"),
+            BodyKind::Text => write!(f, "text"),
+            BodyKind::Binary => write!(f, "binary"),
+        }
+    }
+}
+
+impl BodyKind {
+    /// Kind of a body from its `Content-Type`, or from its first bytes when
+    /// the header is missing or too generic.
+    pub fn detect(content_type: Option<&str>, body: &[u8]) -> BodyKind {
+        let mime = content_type
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+
+        if mime == "image/svg+xml" {
+            return BodyKind::Xml;
+        }
+        if mime.starts_with("image/") {
+            return BodyKind::Image;
+        }
+        if mime.ends_with("json") || mime.ends_with("+json") {
+            return BodyKind::Json;
+        }
+        if mime.contains("html") {
+            return BodyKind::Html;
+        }
+        if mime.ends_with("xml") {
+            return BodyKind::Xml;
+        }
+        if mime.starts_with("text/") {
+            return BodyKind::Text;
+        }
+        BodyKind::sniff(body)
+    }
+
+    fn sniff(body: &[u8]) -> BodyKind {
+        const IMAGE_SIGNATURES: [&[u8]; 5] = [
+            b"\x89PNG\r\n\x1a\n",
+            b"\xff\xd8\xff",
+            b"GIF87a",
+            b"GIF89a",
+            b"BM",
+        ];
+        if IMAGE_SIGNATURES
+            .iter()
+            .any(|signature| body.starts_with(signature))
+            || (body.starts_with(b"RIFF") && body.get(8..12) == Some(b"WEBP"))
+        {
+            return BodyKind::Image;
+        }
+
+        let start = &body[..body.len().min(8192)];
+        let text = match std::str::from_utf8(start) {
+            Ok(text) => text,
+            // The sample may end in the middle of a character.
+            Err(error) if error.error_len().is_none() => {
+                std::str::from_utf8(&start[..error.valid_up_to()]).unwrap_or_default()
+            }
+            Err(_) => return BodyKind::Binary,
+        };
+        if text.contains('\0') {
+            return BodyKind::Binary;
+        }
+
+        let trimmed = text.trim_start().to_ascii_lowercase();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            BodyKind::Json
+        } else if trimmed.starts_with("<!doctype html") || trimmed.starts_with("<html") {
+            BodyKind::Html
+        } else if trimmed.starts_with('<') {
+            BodyKind::Xml
+        } else {
+            BodyKind::Text
+        }
+    }
+}
+
+/// Pretty-print JSON, keeping the order of object keys.
+pub fn pretty_json(text: &str) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+#[derive(Debug, PartialEq)]
+enum MarkupToken<'a> {
+    Open(&'a str),
+    Close(&'a str),
+    /// Self-closing tags, comments, declarations and CDATA.
+    Standalone(&'a str),
+    Text(&'a str),
+}
+
+/// Name of the element a tag opens or closes, lowercased.
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Split markup into tags and the text between them.
+fn markup_tokens(text: &str, html: bool) -> Vec<MarkupToken<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if !rest.starts_with('<') {
+            let end = rest.find('<').unwrap_or(rest.len());
+            tokens.push(MarkupToken::Text(&rest[..end]));
+            rest = &rest[end..];
+            continue;
+        }
+
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|index| index + 3)
+        } else if rest.starts_with("<![CDATA[") {
+            rest.find("]]>").map(|index| index + 3)
+        } else {
+            // `>` inside quoted attribute values does not end the tag.
+            let mut quote = None;
+            rest.char_indices().skip(1).find_map(|(index, c)| {
+                match (quote, c) {
+                    (None, '"' | '\'') => quote = Some(c),
+                    (Some(open), _) if open == c => quote = None,
+                    (None, '>') => return Some(index + 1),
+                    _ => {}
+                }
+                None
+            })
+        }
+        .unwrap_or(rest.len());
+        let tag = &rest[..end];
+        rest = &rest[end..];
+
+        let name = tag_name(tag);
+        if tag.starts_with("</") {
+            tokens.push(MarkupToken::Close(tag));
+        } else if tag.starts_with("<!")
+            || tag.starts_with("<?")
+            || tag.ends_with("/>")
+            || (html && VOID_ELEMENTS.contains(&name.as_str()))
+        {
+            tokens.push(MarkupToken::Standalone(tag));
+        } else if html && RAW_TEXT_ELEMENTS.contains(&name.as_str()) {
+            tokens.push(MarkupToken::Open(tag));
+            let closing = format!("</{}", name);
+            let content_end = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .unwrap_or(rest.len());
+            tokens.push(MarkupToken::Text(&rest[..content_end]));
+            rest = &rest[content_end..];
+        } else {
+            tokens.push(MarkupToken::Open(tag));
+        }
+    }
+    tokens
+}
+
+/// Indent XML or HTML, one element per line.
+///
+/// Elements that only hold a short text stay on one line. Text is trimmed
+/// but otherwise kept as it is.
+pub fn pretty_markup(text: &str, html: bool) -> String {
+    let tokens = markup_tokens(text, html);
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth: usize = 0;
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let indent = "  ".repeat(depth);
+        match &tokens[index] {
+            MarkupToken::Open(tag) => {
+                if let (Some(MarkupToken::Text(content)), Some(MarkupToken::Close(close))) =
+                    (tokens.get(index + 1), tokens.get(index + 2))
+                {
+                    if !content.trim().contains('\n') && tag_name(tag) == tag_name(close) {
+                        lines.push(format!("{}{}{}{}", indent, tag, content.trim(), close));
+                        index += 3;
+                        continue;
+                    }
+                }
+                lines.push(format!("{}{}", indent, tag));
+                depth += 1;
+            }
+            MarkupToken::Close(tag) => {
+                depth = depth.saturating_sub(1);
+                lines.push(format!("{}{}", "  ".repeat(depth), tag));
+            }
+            MarkupToken::Standalone(tag) => lines.push(format!("{}{}", indent, tag)),
+            MarkupToken::Text(content) => {
+                for line in content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                {
+                    lines.push(format!("{}{}", indent, line));
+                }
+            }
+        }
+        index += 1;
+    }
+    lines.join("\n")
+}
+
+/// Formatted version of a body, when its kind has one.
+pub fn pretty_print(kind: BodyKind, text: &str) -> Option<String> {
+    if text.len() > PRETTY_PRINT_LIMIT {
+        return None;
+    }
+    match kind {
+        BodyKind::Json => pretty_json(text),
+        BodyKind::Xml => Some(pretty_markup(text, false)),
+        BodyKind::Html => Some(pretty_markup(text, true)),
+        _ => None,
+    }
+}
+
+/// A text split into the rows of the viewer.
+#[derive(Clone, Debug, Default)]
+pub struct TextLines {
+    text: String,
+    /// Byte range and line number of every row. Rows continuing a split
+    /// line have no number.
+    rows: Vec<(usize, usize, Option<usize>)>,
+}
+
+impl TextLines {
+    pub fn new(text: String) -> TextLines {
+        let mut rows = Vec::new();
+        let mut start = 0;
+
+        for (number, line) in text.split('\n').enumerate() {
+            let end = start + line.trim_end_matches('\r').len();
+            let mut row_start = start;
+            let mut first = true;
+            loop {
+                let mut row_end = (row_start + MAX_LINE_LENGTH).min(end);
+                while !text.is_char_boundary(row_end) {
+                    row_end -= 1;
+                }
+                rows.push((row_start, row_end, first.then_some(number + 1)));
+                first = false;
+                row_start = row_end;
+                if row_start >= end {
+                    break;
+                }
+            }
+            start += line.len() + 1;
+        }
+
+        TextLines { text, rows }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Text and line number of a row.
+    pub fn row(&self, index: usize) -> Option<(&str, Option<usize>)> {
+        self.rows
+            .get(index)
+            .map(|(start, end, number)| (&self.text[*start..*end], *number))
+    }
+}
+
+/// Number of rows of the hex view of a body.
+pub fn hex_row_count(length: usize) -> usize {
+    length.div_ceil(HEX_ROW_LENGTH)
+}
+
+/// Offset, hex bytes and printable characters of a row of the hex view.
+pub fn hex_row(bytes: &[u8], index: usize) -> Option<(String, String, String)> {
+    let start = index * HEX_ROW_LENGTH;
+    if start >= bytes.len() {
+        return None;
+    }
+    let chunk = &bytes[start..(start + HEX_ROW_LENGTH).min(bytes.len())];
+
+    let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let ascii: String = chunk
+        .iter()
+        .map(|byte| {
+            if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    Some((
+        format!("{:08x}", start),
+        format!("{:<47}", hex.join(" ")),
+        ascii,
+    ))
+}
+
+/// Size in bytes, in the largest unit that keeps it above one.
+pub fn format_size(size: usize) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TokenKind {
+    Plain,
+    Key,
+    String,
+    Number,
+    Keyword,
+    Punctuation,
+    Tag,
+    Attribute,
+    Comment,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenKind::Plain => write!(f, "plain"),
+            TokenKind::Key => write!(f, "key"),
+            TokenKind::String => write!(f, "string"),
+            TokenKind::Number => write!(f, "number"),
+            TokenKind::Keyword => write!(f, "keyword"),
+            TokenKind::Punctuation => write!(f, "punctuation"),
+            TokenKind::Tag => write!(f, "tag"),
+            TokenKind::Attribute => write!(f, "attribute"),
+            TokenKind::Comment => write!(f, "comment"),
+        }
+    }
+}
+
+/// Appends to the last token when it has the same kind.
+fn push_token(tokens: &mut Vec<(TokenKind, String)>, kind: TokenKind, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match tokens.last_mut() {
+        Some((last_kind, last_text)) if *last_kind == kind => last_text.push_str(text),
+        _ => tokens.push((kind, text.to_string())),
+    }
+}
+
+/// Length of a quoted string starting at the beginning of `text`, quotes included.
+fn quoted_length(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c if c == quote && !escaped => return index + 1,
+            _ => escaped = false,
+        }
+    }
+    text.len()
+}
+
+fn highlight_json(line: &str) -> Vec<(TokenKind, String)> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+
+    while let Some(c) = rest.chars().next() {
+        let (kind, length) = match c {
+            '"' => {
+                let length = quoted_length(rest, '"');
+                let is_key = rest[length..].trim_start().starts_with(':');
+                (
+                    if is_key {
+                        TokenKind::Key
+                    } else {
+                        TokenKind::String
+                    },
+                    length,
+                )
+            }
+            '-' | '0'..='9' => (
+                TokenKind::Number,
+                rest.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+                    .unwrap_or(rest.len()),
+            ),
+            '{' | '}' | '[' | ']' | ',' | ':' => (TokenKind::Punctuation, 1),
+            c if c.is_alphabetic() => {
+                let length = rest
+                    .find(|c: char| !c.is_alphanumeric())
+                    .unwrap_or(rest.len());
+                let kind = match &rest[..length] {
+                    "true" | "false" | "null" => TokenKind::Keyword,
+                    _ => TokenKind::Plain,
+                };
+                (kind, length)
+            }
+            c => (TokenKind::Plain, c.len_utf8()),
+        };
+        push_token(&mut tokens, kind, &rest[..length]);
+        rest = &rest[length..];
+    }
+    tokens
+}
+
+fn highlight_markup(line: &str) -> Vec<(TokenKind, String)> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    let mut in_tag = false;
+
+    while let Some(c) = rest.chars().next() {
+        let (kind, length) = if rest.starts_with("<!--") {
+            (
+                TokenKind::Comment,
+                rest.find("-->")
+                    .map(|index| index + 3)
+                    .unwrap_or(rest.len()),
+            )
+        } else if !in_tag && c == '<' {
+            in_tag = true;
+            let name_start = rest
+                .find(|c: char| c != '<' && c != '/' && c != '?' && c != '!')
+                .unwrap_or(rest.len());
+            push_token(&mut tokens, TokenKind::Punctuation, &rest[..name_start]);
+            rest = &rest[name_start..];
+            let name_length = rest
+                .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+                .unwrap_or(rest.len());
+            (TokenKind::Tag, name_length)
+        } else if !in_tag {
+            (TokenKind::Plain, rest.find('<').unwrap_or(rest.len()))
+        } else {
+            match c {
+                '>' => {
+                    in_tag = false;
+                    (TokenKind::Punctuation, 1)
+                }
+                '/' | '?' | '=' => (TokenKind::Punctuation, 1),
+                '"' | '\'' => (TokenKind::String, quoted_length(rest, c)),
+                c if c.is_whitespace() => (TokenKind::Plain, c.len_utf8()),
+                _ => (
+                    TokenKind::Attribute,
+                    rest.find(|c: char| c.is_whitespace() || "=>/".contains(c))
+                        .unwrap_or(rest.len())
+                        .max(1),
+                ),
+            }
+        };
+        push_token(&mut tokens, kind, &rest[..length]);
+        rest = &rest[length..];
+    }
+    tokens
+}
+
+/// Split a line of a body into highlighted tokens.
+///
+/// Lines are highlighted on their own, so constructs spanning several
+/// lines, like long comments, are only highlighted on their first line.
+pub fn highlight_line(kind: BodyKind, line: &str) -> Vec<(TokenKind, String)> {
+    match kind {
+        BodyKind::Json => highlight_json(line),
+        BodyKind::Xml | BodyKind::Html => highlight_markup(line),
+        _ => vec![(TokenKind::Plain, line.to_string())],
+    }
+}
+
+/// A decoded image, ready to be turned into a Slint image on the UI thread.
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Everything the viewer shows for a body, computed ahead of time.
+#[derive(Clone, Debug)]
+pub struct ResponseView {
+    pub kind: BodyKind,
+    pub content_type: String,
+    pub pretty: Option<TextLines>,
+    pub raw: TextLines,
+    pub image: Option<DecodedImage>,
+}
+
+impl ResponseView {
+    pub fn new(content_type: Option<&str>, body: &[u8]) -> ResponseView {
+        let kind = BodyKind::detect(content_type, body);
+        let raw = String::from_utf8_lossy(body).to_string();
+        let pretty = pretty_print(kind, &raw).map(TextLines::new);
+        let image = match kind {
+            BodyKind::Image => image::load_from_memory(body).ok().map(|image| {
+                let image = image.to_rgba8();
+                DecodedImage {
+                    width: image.width(),
+                    height: image.height(),
+                    rgba: image.into_raw(),
+                }
+            }),
+            _ => None,
+        };
+
+        ResponseView {
+            kind,
+            content_type: content_type.unwrap_or_default().to_string(),
+            pretty,
+            raw: TextLines::new(raw),
+            image,
+        }
+    }
+}
+
+/// Build the view of a response on the shared runtime, away from the UI thread.
+pub async fn prepare_response_view(
+    response: ResponseData,
+) -> Result<(ResponseData, ResponseView), Box<dyn Error>> {
+    let prepared = runtime()
+        .spawn_blocking(move || {
+            let view = ResponseView::new(response.header("content-type"), &response.body);
+            (response, view)
+        })
+        .await?;
+
+    Ok(prepared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_body_kind() {
+        assert!(BodyKind::detect(Some("application/json; charset=utf-8"), b"") == BodyKind::Json);
+        assert!(BodyKind::detect(Some("application/problem+json"), b"") == BodyKind::Json);
+        assert!(BodyKind::detect(Some("text/html"), b"") == BodyKind::Html);
+        assert!(BodyKind::detect(Some("application/atom+xml"), b"") == BodyKind::Xml);
+        assert!(BodyKind::detect(Some("image/svg+xml"), b"") == BodyKind::Xml);
+        assert!(BodyKind::detect(Some("image/png"), b"") == BodyKind::Image);
+        assert!(BodyKind::detect(Some("text/plain"), b"{}") == BodyKind::Text);
+
+        // Without a usable content type the body is sniffed.
+        assert!(BodyKind::detect(None, b"  [1, 2]") == BodyKind::Json);
+        assert!(
+            BodyKind::detect(Some("application/octet-stream"), b"<!DOCTYPE html>")
+                == BodyKind::Html
+        );
+        assert!(BodyKind::detect(None, b"<?xml version=\"1.0\"?>") == BodyKind::Xml);
+        assert!(BodyKind::detect(None, b"\x89PNG\r\n\x1a\n....") == BodyKind::Image);
+        assert!(BodyKind::detect(None, b"\x00\x01\x02") == BodyKind::Binary);
+        assert!(BodyKind::detect(None, b"hello") == BodyKind::Text);
+    }
+
+    #[test]
+    fn test_pretty_json_keeps_key_order() {
+        let pretty = pretty_json("{\"b\":1,\"a\":[true,null]}").unwrap();
+        assert!(pretty == "{\n  \"b\": 1,\n  \"a\": [\n    true,\n    null\n  ]\n}");
+        assert!(pretty_json("{broken").is_none());
+    }
+
+    #[test]
+    fn test_pretty_markup() {
+        let xml = pretty_markup(
+            "<?xml version=\"1.0\"?><users><user id=\"1\"><name>Ada</name><!-- note --><admin/></user></users>",
+            false,
+        );
+        assert!(
+            xml == "<?xml version=\"1.0\"?>\n<users>\n  <user id=\"1\">\n    <name>Ada</name>\n    <!-- note -->\n    <admin/>\n  </user>\n</users>"
+        );
+
+        let html = pretty_markup(
+            "<html><head><meta charset=\"utf-8\"><script>if (a < b) { go(); }</script></head><body><p>Hi<br>there</p></body></html>",
+            true,
+        );
+        assert!(
+            html == "<html>\n  <head>\n    <meta charset=\"utf-8\">\n    <script>if (a < b) { go(); }</script>\n  </head>\n  <body>\n    <p>\n      Hi\n      <br>\n      there\n    </p>\n  </body>\n</html>"
+        );
+    }
+
+    #[test]
+    fn test_text_lines_split_long_lines() {
+        let long_line = "é".repeat(MAX_LINE_LENGTH);
+        let lines = TextLines::new(format!("first\r\n{}\nlast", long_line));
+
+        assert!(lines.len() == 4);
+        assert!(lines.row(0) == Some(("first", Some(1))));
+        let (second, number) = lines.row(1).unwrap();
+        assert!(number == Some(2));
+        assert!(second.len() <= MAX_LINE_LENGTH);
+        let (continued, number) = lines.row(2).unwrap();
+        assert!(number.is_none());
+        assert!(second.len() + continued.len() == long_line.len());
+        assert!(lines.row(3) == Some(("last", Some(3))));
+        assert!(lines.row(4).is_none());
+    }
+
+    #[test]
+    fn test_hex_row() {
+        let bytes = b"Hello, world!\n\x00\x01\xff";
+        assert!(hex_row_count(bytes.len()) == 2);
+
+        let (offset, hex, ascii) = hex_row(bytes, 0).unwrap();
+        assert!(offset == "00000000");
+        assert!(hex == "48 65 6c 6c 6f 2c 20 77 6f 72 6c 64 21 0a 00 01");
+        assert!(ascii == "Hello, world!...");
+
+        let (offset, hex, ascii) = hex_row(bytes, 1).unwrap();
+        assert!(offset == "00000010");
+        assert!(hex.trim_end() == "ff");
+        assert!(hex.len() == 47);
+        assert!(ascii == ".");
+        assert!(hex_row(bytes, 2).is_none());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert!(format_size(512) == "512 B");
+        assert!(format_size(1536) == "1.5 KB");
+        assert!(format_size(50 * 1024 * 1024) == "50.0 MB");
+    }
+
+    #[test]
+    fn test_highlight_json_line() {
+        let tokens = highlight_line(
+            BodyKind::Json,
+            "  \"id\": 42, \"ok\": true, \"name\": \"a\\\"b\"",
+        );
+        assert!(
+            tokens
+                == vec![
+                    (TokenKind::Plain, "  ".to_string()),
+                    (TokenKind::Key, "\"id\"".to_string()),
+                    (TokenKind::Punctuation, ":".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Number, "42".to_string()),
+                    (TokenKind::Punctuation, ",".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Key, "\"ok\"".to_string()),
+                    (TokenKind::Punctuation, ":".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Keyword, "true".to_string()),
+                    (TokenKind::Punctuation, ",".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Key, "\"name\"".to_string()),
+                    (TokenKind::Punctuation, ":".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::String, "\"a\\\"b\"".to_string()),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_highlight_markup_line() {
+        let tokens = highlight_line(BodyKind::Xml, "<user id=\"1\">Ada</user> <!-- x -->");
+        assert!(
+            tokens
+                == vec![
+                    (TokenKind::Punctuation, "<".to_string()),
+                    (TokenKind::Tag, "user".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Attribute, "id".to_string()),
+                    (TokenKind::Punctuation, "=".to_string()),
+                    (TokenKind::String, "\"1\"".to_string()),
+                    (TokenKind::Punctuation, ">".to_string()),
+                    (TokenKind::Plain, "Ada".to_string()),
+                    (TokenKind::Punctuation, "</".to_string()),
+                    (TokenKind::Tag, "user".to_string()),
+                    (TokenKind::Punctuation, ">".to_string()),
+                    (TokenKind::Plain, " ".to_string()),
+                    (TokenKind::Comment, "<!-- x -->".to_string()),
+                ]
+        );
+    }
+
+    #[test]
+    fn test_response_view() {
+        let view = ResponseView::new(Some("application/json"), b"{\"a\":1}");
+        assert!(view.kind == BodyKind::Json);
+        assert!(view.pretty.as_ref().unwrap().len() == 3);
+        assert!(view.raw.row(0) == Some(("{\"a\":1}", Some(1))));
+        assert!(view.image.is_none());
+
+        // A body that is not valid JSON is still shown raw.
+        let view = ResponseView::new(Some("application/json"), b"{oops");
+        assert!(view.pretty.is_none());
+        assert!(view.raw.len() == 1);
+    }
+}
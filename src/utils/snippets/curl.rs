@@ -0,0 +1,19 @@
+use crate::utils::{
+    executor::PreparedRequest, interchange::curl::write_command, snippets::SnippetTarget,
+};
+
+pub struct Curl;
+
+impl SnippetTarget for Curl {
+    fn id(&self) -> &'static str {
+        "curl"
+    }
+
+    fn label(&self) -> &'static str {
+        "cURL"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        write_command(request)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::utils::{
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    snippets::{file_name, quoted, snippet_headers, SnippetTarget},
+};
+
+/// Go, with the standard library's net/http.
+pub struct GoNetHttp;
+
+const PANIC_ON_ERROR: [&str; 3] = ["\tif err != nil {", "\t\tpanic(err)", "\t}"];
+
+impl SnippetTarget for GoNetHttp {
+    fn id(&self) -> &'static str {
+        "go"
+    }
+
+    fn label(&self) -> &'static str {
+        "Go (net/http)"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut imports = vec!["fmt", "io", "net/http"];
+        let mut body = Vec::new();
+        let mut content_type = None;
+
+        match &request.body {
+            PreparedBody::Empty => {}
+            PreparedBody::Text(text) => {
+                imports.push("strings");
+                body.push(format!("\tbody := strings.NewReader({})", quoted(text)));
+            }
+            PreparedBody::Bytes(bytes) => {
+                imports.push("strings");
+                body.push(format!(
+                    "\tbody := strings.NewReader({})",
+                    quoted(&String::from_utf8_lossy(bytes))
+                ));
+            }
+            PreparedBody::Form(fields) => {
+                imports.extend(["net/url", "strings"]);
+                body.push("\tform := url.Values{}".to_string());
+                for (name, value) in fields {
+                    body.push(format!("\tform.Add({}, {})", quoted(name), quoted(value)));
+                }
+                body.push("\tbody := strings.NewReader(form.Encode())".to_string());
+            }
+            PreparedBody::Multipart { fields, .. } => {
+                imports.extend(["bytes", "mime/multipart"]);
+                body.push("\tbody := &bytes.Buffer{}".to_string());
+                body.push("\twriter := multipart.NewWriter(body)".to_string());
+                let mut files = 0;
+                for field in fields {
+                    if !field.is_file {
+                        body.push(format!(
+                            "\twriter.WriteField({}, {})",
+                            quoted(&field.name),
+                            quoted(&field.value)
+                        ));
+                        continue;
+                    }
+                    if !imports.contains(&"os") {
+                        imports.push("os");
+                    }
+                    files += 1;
+                    body.push(format!(
+                        "\tfile{}, err := os.Open({})",
+                        files,
+                        quoted(&field.value)
+                    ));
+                    body.extend(PANIC_ON_ERROR.map(String::from));
+                    body.push(format!("\tdefer file{}.Close()", files));
+                    body.push(format!(
+                        "\tpart{}, err := writer.CreateFormFile({}, {})",
+                        files,
+                        quoted(&field.name),
+                        quoted(&file_name(&field.value))
+                    ));
+                    body.extend(PANIC_ON_ERROR.map(String::from));
+                    body.push(format!("\tio.Copy(part{}, file{})", files, files));
+                }
+                body.push("\twriter.Close()".to_string());
+                content_type = Some("writer.FormDataContentType()".to_string());
+            }
+            PreparedBody::File(path) => {
+                imports.push("os");
+                body.push(format!("\tbody, err := os.Open({})", quoted(path)));
+                body.extend(PANIC_ON_ERROR.map(String::from));
+                body.push("\tdefer body.Close()".to_string());
+            }
+        }
+        imports.sort();
+
+        let mut lines = vec![
+            "package main".to_string(),
+            String::new(),
+            "import (".to_string(),
+        ];
+        lines.extend(imports.iter().map(|import| format!("\t{}", quoted(import))));
+        lines.push(")".to_string());
+        lines.push(String::new());
+        lines.push("func main() {".to_string());
+
+        let body_argument = match body.is_empty() {
+            true => "nil",
+            false => "body",
+        };
+        lines.extend(body);
+        if let PreparedAuth::Digest { .. } = request.auth {
+            lines.push("\t// net/http does not answer digest challenges.".to_string());
+        }
+        lines.push(format!(
+            "\treq, err := http.NewRequest({}, {}, {})",
+            quoted(&request.method.to_string()),
+            quoted(&request.url),
+            body_argument
+        ));
+        lines.extend(PANIC_ON_ERROR.map(String::from));
+        for (name, value) in snippet_headers(request) {
+            lines.push(format!(
+                "\treq.Header.Add({}, {})",
+                quoted(name),
+                quoted(value)
+            ));
+        }
+        if let Some(content_type) = content_type {
+            lines.push(format!(
+                "\treq.Header.Set(\"Content-Type\", {})",
+                content_type
+            ));
+        }
+
+        lines.push(String::new());
+        lines.push("\tres, err := http.DefaultClient.Do(req)".to_string());
+        lines.extend(PANIC_ON_ERROR.map(String::from));
+        lines.push("\tdefer res.Body.Close()".to_string());
+        lines.push(String::new());
+        lines.push("\tdata, err := io.ReadAll(res.Body)".to_string());
+        lines.extend(PANIC_ON_ERROR.map(String::from));
+        lines.push("\tfmt.Println(string(data))".to_string());
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,70 @@
+use crate::utils::{
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    interchange::curl::shell_quote,
+    snippets::{snippet_headers, SnippetTarget},
+};
+
+/// The HTTPie command line client.
+pub struct Httpie;
+
+impl SnippetTarget for Httpie {
+    fn id(&self) -> &'static str {
+        "httpie"
+    }
+
+    fn label(&self) -> &'static str {
+        "HTTPie"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut options = Vec::new();
+        let mut items = Vec::new();
+
+        if let PreparedAuth::Digest { username, password } = &request.auth {
+            options.push(format!(
+                "-A digest -a {}",
+                shell_quote(&format!("{}:{}", username, password))
+            ));
+        }
+        for (name, value) in snippet_headers(request) {
+            // `Name;` sends a header without a value.
+            items.push(match value.is_empty() {
+                true => shell_quote(&format!("{};", name)),
+                false => shell_quote(&format!("{}:{}", name, value)),
+            });
+        }
+        match &request.body {
+            PreparedBody::Empty => {}
+            PreparedBody::Text(text) => options.push(format!("--raw {}", shell_quote(text))),
+            PreparedBody::Bytes(bytes) => options.push(format!(
+                "--raw {}",
+                shell_quote(&String::from_utf8_lossy(bytes))
+            )),
+            PreparedBody::Form(fields) => {
+                options.push("--form".to_string());
+                for (name, value) in fields {
+                    items.push(shell_quote(&format!("{}={}", name, value)));
+                }
+            }
+            PreparedBody::Multipart { fields, .. } => {
+                options.push("--multipart".to_string());
+                for field in fields {
+                    items.push(match field.is_file {
+                        true => shell_quote(&format!("{}@{}", field.name, field.value)),
+                        false => shell_quote(&format!("{}={}", field.name, field.value)),
+                    });
+                }
+            }
+            PreparedBody::File(path) => items.push(format!("< {}", shell_quote(path))),
+        }
+
+        let mut first_line = vec!["http".to_string()];
+        first_line.extend(options);
+        first_line.push(request.method.to_string());
+        first_line.push(shell_quote(&request.url));
+
+        let mut lines = vec![first_line.join(" ")];
+        lines.extend(items);
+        lines.join(" \\\n  ")
+    }
+}
@@ -0,0 +1,103 @@
+use crate::utils::{
+    crud::requests::HTTPMethods,
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    snippets::{file_name, quoted, snippet_headers, SnippetTarget},
+};
+
+/// JavaScript, with `fetch` as found in browsers and Node.js. Files are
+/// read with Node.js.
+pub struct Fetch;
+
+impl SnippetTarget for Fetch {
+    fn id(&self) -> &'static str {
+        "javascript"
+    }
+
+    fn label(&self) -> &'static str {
+        "JavaScript (fetch)"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut lines = Vec::new();
+
+        let reads_files = match &request.body {
+            PreparedBody::File(_) => true,
+            PreparedBody::Multipart { fields, .. } => fields.iter().any(|field| field.is_file),
+            _ => false,
+        };
+        if reads_files {
+            lines.push("import { readFile } from \"node:fs/promises\";".to_string());
+            lines.push(String::new());
+        }
+        if let PreparedBody::Multipart { fields, .. } = &request.body {
+            lines.push("const form = new FormData();".to_string());
+            for field in fields {
+                lines.push(match field.is_file {
+                    true => format!(
+                        "form.append({}, new Blob([await readFile({})]), {});",
+                        quoted(&field.name),
+                        quoted(&field.value),
+                        quoted(&file_name(&field.value))
+                    ),
+                    false => format!(
+                        "form.append({}, {});",
+                        quoted(&field.name),
+                        quoted(&field.value)
+                    ),
+                });
+            }
+            lines.push(String::new());
+        }
+        if let PreparedAuth::Digest { .. } = request.auth {
+            lines.push("// fetch does not answer digest challenges.".to_string());
+        }
+
+        let mut options = Vec::new();
+        if request.method != HTTPMethods::Get {
+            options.push(format!(
+                "  method: {},",
+                quoted(&request.method.to_string())
+            ));
+        }
+        let headers = snippet_headers(request);
+        if !headers.is_empty() {
+            options.push("  headers: {".to_string());
+            for (name, value) in headers {
+                options.push(format!("    {}: {},", quoted(name), quoted(value)));
+            }
+            options.push("  },".to_string());
+        }
+        match &request.body {
+            PreparedBody::Empty => {}
+            PreparedBody::Text(text) => options.push(format!("  body: {},", quoted(text))),
+            PreparedBody::Bytes(bytes) => options.push(format!(
+                "  body: {},",
+                quoted(&String::from_utf8_lossy(bytes))
+            )),
+            PreparedBody::Form(fields) => {
+                options.push("  body: new URLSearchParams([".to_string());
+                for (name, value) in fields {
+                    options.push(format!("    [{}, {}],", quoted(name), quoted(value)));
+                }
+                options.push("  ]),".to_string());
+            }
+            PreparedBody::Multipart { .. } => options.push("  body: form,".to_string()),
+            PreparedBody::File(path) => {
+                options.push(format!("  body: await readFile({}),", quoted(path)))
+            }
+        }
+
+        let url = quoted(&request.url);
+        match options.is_empty() {
+            true => lines.push(format!("const response = await fetch({});", url)),
+            false => {
+                lines.push(format!("const response = await fetch({}, {{", url));
+                lines.extend(options);
+                lines.push("});".to_string());
+            }
+        }
+        lines.push(String::new());
+        lines.push("console.log(await response.text());".to_string());
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,194 @@
+//! Code that sends a request, for handing it to other teams.
+//!
+//! Every target implements [`SnippetTarget`] and is listed in [`targets`].
+//! Snippets are written from a [`PreparedRequest`], so variables are
+//! resolved and auth is already turned into headers.
+
+use std::{error::Error, path::Path};
+
+use sqlx::SqlitePool;
+
+use crate::utils::{
+    crud::requests::{ProtocolTypes, RequestData},
+    executor::{apply_oauth2_token, body::PreparedBody, prepare_request, PreparedRequest},
+};
+
+pub mod curl;
+pub mod go;
+pub mod httpie;
+pub mod javascript;
+pub mod python;
+pub mod reqwest;
+pub mod wget;
+
+/// A language or tool snippets can be written for.
+pub trait SnippetTarget {
+    /// Identifies the target in the UI.
+    fn id(&self) -> &'static str;
+
+    /// Name shown in the target picker.
+    fn label(&self) -> &'static str;
+
+    fn generate(&self, request: &PreparedRequest) -> String;
+}
+
+/// Every target, in the order they are offered.
+pub fn targets() -> Vec<Box<dyn SnippetTarget>> {
+    vec![
+        Box::new(curl::Curl),
+        Box::new(reqwest::Reqwest),
+        Box::new(python::PythonRequests),
+        Box::new(javascript::Fetch),
+        Box::new(go::GoNetHttp),
+        Box::new(httpie::Httpie),
+        Box::new(wget::Wget),
+    ]
+}
+
+pub fn find_target(id: &str) -> Option<Box<dyn SnippetTarget>> {
+    targets().into_iter().find(|target| target.id() == id)
+}
+
+/// A request resolved the way the send button would send it.
+///
+/// An OAuth 2.0 token may be obtained first.
+pub async fn resolved_request(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    if ProtocolTypes::from_string(&request.protocol) != Some(ProtocolTypes::Http) {
+        return Err("Only HTTP requests can be written as code.".into());
+    }
+    apply_oauth2_token(prepare_request(request, pool).await?, pool).await
+}
+
+/// A double-quoted string literal, valid in Python, JavaScript and Go.
+pub fn quoted(text: &str) -> String {
+    serde_json::Value::String(text.to_string()).to_string()
+}
+
+/// Name a file is uploaded under.
+pub fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Headers to write out. The content type of multipart bodies carries a
+/// boundary only the sending library knows, so it is left to the library.
+pub fn snippet_headers(request: &PreparedRequest) -> Vec<&(String, String)> {
+    let is_multipart = matches!(request.body, PreparedBody::Multipart { .. });
+    request
+        .headers
+        .iter()
+        .filter(|(name, _)| !(is_multipart && name.eq_ignore_ascii_case("content-type")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{
+        crud::requests::HTTPMethods,
+        executor::{auth::PreparedAuth, body::MultipartField},
+    };
+
+    fn sample_request() -> PreparedRequest {
+        PreparedRequest {
+            method: HTTPMethods::Post,
+            url: "https://api.example.com/pets?limit=10".to_string(),
+            headers: vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("Authorization".to_string(), "Bearer abc".to_string()),
+            ],
+            body: PreparedBody::Text("{\"name\": \"Rex\"}".to_string()),
+            auth: PreparedAuth::None,
+        }
+    }
+
+    fn assert_snapshot(target: &dyn SnippetTarget, snapshot: &str) {
+        let generated = target.generate(&sample_request());
+        assert!(
+            generated == snapshot.trim_end(),
+            "{} snippet changed:\n{}",
+            target.id(),
+            generated
+        );
+    }
+
+    #[test]
+    fn test_curl_snapshot() {
+        assert_snapshot(&curl::Curl, include_str!("snapshots/curl.snap"));
+    }
+
+    #[test]
+    fn test_reqwest_snapshot() {
+        assert_snapshot(&reqwest::Reqwest, include_str!("snapshots/reqwest.snap"));
+    }
+
+    #[test]
+    fn test_python_snapshot() {
+        assert_snapshot(
+            &python::PythonRequests,
+            include_str!("snapshots/python.snap"),
+        );
+    }
+
+    #[test]
+    fn test_javascript_snapshot() {
+        assert_snapshot(
+            &javascript::Fetch,
+            include_str!("snapshots/javascript.snap"),
+        );
+    }
+
+    #[test]
+    fn test_go_snapshot() {
+        assert_snapshot(&go::GoNetHttp, include_str!("snapshots/go.snap"));
+    }
+
+    #[test]
+    fn test_httpie_snapshot() {
+        assert_snapshot(&httpie::Httpie, include_str!("snapshots/httpie.snap"));
+    }
+
+    #[test]
+    fn test_wget_snapshot() {
+        assert_snapshot(&wget::Wget, include_str!("snapshots/wget.snap"));
+    }
+
+    #[test]
+    fn test_every_target_writes_every_body() {
+        let bodies = vec![
+            PreparedBody::Empty,
+            PreparedBody::Form(vec![("name".to_string(), "Rex".to_string())]),
+            PreparedBody::Multipart {
+                boundary: "x".to_string(),
+                fields: vec![MultipartField {
+                    name: "photo".to_string(),
+                    value: "/tmp/rex.png".to_string(),
+                    is_file: true,
+                }],
+            },
+            PreparedBody::File("/tmp/pets.json".to_string()),
+        ];
+
+        for target in targets() {
+            for body in &bodies {
+                let mut request = sample_request();
+                request.body = body.clone();
+                let generated = target.generate(&request);
+                assert!(generated.contains("https://api.example.com/pets?limit=10"));
+                match body {
+                    PreparedBody::Form(_) => assert!(generated.contains("Rex"), "{}", target.id()),
+                    PreparedBody::File(path) => {
+                        assert!(generated.contains(path.as_str()), "{}", target.id())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        assert!(find_target("python").is_some() && find_target("cobol").is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::utils::{
+    crud::requests::HTTPMethods,
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    snippets::{quoted, snippet_headers, SnippetTarget},
+};
+
+/// Python, with the requests library.
+pub struct PythonRequests;
+
+impl SnippetTarget for PythonRequests {
+    fn id(&self) -> &'static str {
+        "python"
+    }
+
+    fn label(&self) -> &'static str {
+        "Python (requests)"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut lines = vec!["import requests".to_string()];
+        if let PreparedAuth::Digest { .. } = request.auth {
+            lines.push("from requests.auth import HTTPDigestAuth".to_string());
+        }
+        lines.push(String::new());
+
+        lines.push(format!("url = {}", quoted(&request.url)));
+        let mut arguments = vec!["url".to_string()];
+
+        let headers = snippet_headers(request);
+        if !headers.is_empty() {
+            lines.push("headers = {".to_string());
+            for (name, value) in headers {
+                lines.push(format!("    {}: {},", quoted(name), quoted(value)));
+            }
+            lines.push("}".to_string());
+            arguments.push("headers=headers".to_string());
+        }
+
+        match &request.body {
+            PreparedBody::Empty => {}
+            PreparedBody::Text(text) => {
+                lines.push(format!("data = {}", quoted(text)));
+                arguments.push("data=data".to_string());
+            }
+            PreparedBody::Bytes(bytes) => {
+                lines.push(format!(
+                    "data = {}",
+                    quoted(&String::from_utf8_lossy(bytes))
+                ));
+                arguments.push("data=data".to_string());
+            }
+            PreparedBody::Form(fields) => {
+                lines.push("data = [".to_string());
+                for (name, value) in fields {
+                    lines.push(format!("    ({}, {}),", quoted(name), quoted(value)));
+                }
+                lines.push("]".to_string());
+                arguments.push("data=data".to_string());
+            }
+            PreparedBody::Multipart { fields, .. } => {
+                // Fields without a file name are sent as plain form fields.
+                lines.push("files = [".to_string());
+                for field in fields {
+                    lines.push(match field.is_file {
+                        true => format!(
+                            "    ({}, open({}, \"rb\")),",
+                            quoted(&field.name),
+                            quoted(&field.value)
+                        ),
+                        false => format!(
+                            "    ({}, (None, {})),",
+                            quoted(&field.name),
+                            quoted(&field.value)
+                        ),
+                    });
+                }
+                lines.push("]".to_string());
+                arguments.push("files=files".to_string());
+            }
+            PreparedBody::File(path) => {
+                lines.push(format!("data = open({}, \"rb\")", quoted(path)));
+                arguments.push("data=data".to_string());
+            }
+        }
+        if let PreparedAuth::Digest { username, password } = &request.auth {
+            arguments.push(format!(
+                "auth=HTTPDigestAuth({}, {})",
+                quoted(username),
+                quoted(password)
+            ));
+        }
+
+        let call = match &request.method {
+            HTTPMethods::Get => "requests.get(".to_string(),
+            HTTPMethods::Post => "requests.post(".to_string(),
+            HTTPMethods::Put => "requests.put(".to_string(),
+            HTTPMethods::Patch => "requests.patch(".to_string(),
+            HTTPMethods::Delete => "requests.delete(".to_string(),
+            HTTPMethods::Head => "requests.head(".to_string(),
+            HTTPMethods::Options => "requests.options(".to_string(),
+            method => format!("requests.request({}, ", quoted(&method.to_string())),
+        };
+        lines.push(String::new());
+        lines.push(format!("response = {}{})", call, arguments.join(", ")));
+        lines.push("print(response.text)".to_string());
+        lines.join("\n")
+    }
+}
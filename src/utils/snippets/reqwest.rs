@@ -0,0 +1,112 @@
+use crate::utils::{
+    crud::requests::HTTPMethods,
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    snippets::{file_name, snippet_headers, SnippetTarget},
+};
+
+/// Rust, with reqwest on tokio.
+pub struct Reqwest;
+
+/// A Rust string literal.
+fn literal(text: &str) -> String {
+    format!("{:?}", text)
+}
+
+impl SnippetTarget for Reqwest {
+    fn id(&self) -> &'static str {
+        "reqwest"
+    }
+
+    fn label(&self) -> &'static str {
+        "Rust (reqwest)"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut lines = vec![
+            "use std::error::Error;".to_string(),
+            String::new(),
+            "#[tokio::main]".to_string(),
+            "async fn main() -> Result<(), Box<dyn Error>> {".to_string(),
+            "    let client = reqwest::Client::new();".to_string(),
+        ];
+
+        if let PreparedBody::Multipart { fields, .. } = &request.body {
+            lines.push("    let form = reqwest::multipart::Form::new()".to_string());
+            for field in fields {
+                lines.push(match field.is_file {
+                    true => format!(
+                        "        .part({}, reqwest::multipart::Part::bytes(std::fs::read({})?).file_name({}))",
+                        literal(&field.name),
+                        literal(&field.value),
+                        literal(&file_name(&field.value))
+                    ),
+                    false => format!(
+                        "        .text({}, {})",
+                        literal(&field.name),
+                        literal(&field.value)
+                    ),
+                });
+            }
+            if let Some(last) = lines.last_mut() {
+                last.push(';');
+            }
+        }
+        if let PreparedAuth::Digest { .. } = request.auth {
+            lines.push("    // reqwest does not answer digest challenges.".to_string());
+        }
+
+        lines.push("    let response = client".to_string());
+        let url = literal(&request.url);
+        lines.push(match &request.method {
+            HTTPMethods::Get => format!("        .get({})", url),
+            HTTPMethods::Post => format!("        .post({})", url),
+            HTTPMethods::Put => format!("        .put({})", url),
+            HTTPMethods::Patch => format!("        .patch({})", url),
+            HTTPMethods::Delete => format!("        .delete({})", url),
+            HTTPMethods::Head => format!("        .head({})", url),
+            HTTPMethods::Options | HTTPMethods::Trace | HTTPMethods::Connect => format!(
+                "        .request(reqwest::Method::{}, {})",
+                request.method, url
+            ),
+            HTTPMethods::Custom(method) => format!(
+                "        .request(reqwest::Method::from_bytes(b{})?, {})",
+                literal(method),
+                url
+            ),
+        });
+        for (name, value) in snippet_headers(request) {
+            lines.push(format!(
+                "        .header({}, {})",
+                literal(name),
+                literal(value)
+            ));
+        }
+        match &request.body {
+            PreparedBody::Empty => {}
+            PreparedBody::Text(text) => lines.push(format!("        .body({})", literal(text))),
+            PreparedBody::Form(fields) => {
+                let pairs: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("({}, {})", literal(name), literal(value)))
+                    .collect();
+                lines.push(format!("        .form(&[{}])", pairs.join(", ")));
+            }
+            PreparedBody::Multipart { .. } => lines.push("        .multipart(form)".to_string()),
+            PreparedBody::File(path) => {
+                lines.push(format!("        .body(std::fs::read({})?)", literal(path)))
+            }
+            PreparedBody::Bytes(bytes) => lines.push(format!(
+                "        .body({})",
+                literal(&String::from_utf8_lossy(bytes))
+            )),
+        }
+        lines.push("        .send()".to_string());
+        lines.push("        .await?;".to_string());
+
+        lines.push(String::new());
+        lines.push("    println!(\"{}\", response.text().await?);".to_string());
+        lines.push("    Ok(())".to_string());
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
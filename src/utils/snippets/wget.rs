@@ -0,0 +1,66 @@
+use url::form_urlencoded;
+
+use crate::utils::{
+    crud::requests::HTTPMethods,
+    executor::{auth::PreparedAuth, body::PreparedBody, PreparedRequest},
+    interchange::curl::shell_quote,
+    snippets::{snippet_headers, SnippetTarget},
+};
+
+/// GNU Wget, printing the response to the terminal.
+pub struct Wget;
+
+impl SnippetTarget for Wget {
+    fn id(&self) -> &'static str {
+        "wget"
+    }
+
+    fn label(&self) -> &'static str {
+        "wget"
+    }
+
+    fn generate(&self, request: &PreparedRequest) -> String {
+        let mut lines = Vec::new();
+        if let PreparedBody::Multipart { .. } = request.body {
+            lines.push("# wget cannot send multipart bodies, the form is left out.".to_string());
+        }
+
+        let mut options = vec!["wget --quiet --output-document -".to_string()];
+        if request.method != HTTPMethods::Get {
+            options.push(format!(
+                "--method {}",
+                shell_quote(&request.method.to_string())
+            ));
+        }
+        for (name, value) in snippet_headers(request) {
+            options.push(format!(
+                "--header {}",
+                shell_quote(&format!("{}: {}", name, value))
+            ));
+        }
+        // wget picks basic or digest itself, depending on the challenge.
+        if let PreparedAuth::Digest { username, password } = &request.auth {
+            options.push(format!("--user {}", shell_quote(username)));
+            options.push(format!("--password {}", shell_quote(password)));
+        }
+        match &request.body {
+            PreparedBody::Empty | PreparedBody::Multipart { .. } => {}
+            PreparedBody::Text(text) => options.push(format!("--body-data {}", shell_quote(text))),
+            PreparedBody::Bytes(bytes) => options.push(format!(
+                "--body-data {}",
+                shell_quote(&String::from_utf8_lossy(bytes))
+            )),
+            PreparedBody::Form(fields) => {
+                let encoded = form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(fields)
+                    .finish();
+                options.push(format!("--body-data {}", shell_quote(&encoded)));
+            }
+            PreparedBody::File(path) => options.push(format!("--body-file {}", shell_quote(path))),
+        }
+        options.push(shell_quote(&request.url));
+
+        lines.push(options.join(" \\\n  "));
+        lines.join("\n")
+    }
+}
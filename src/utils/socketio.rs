@@ -0,0 +1,699 @@
+//! Socket.IO clients: Engine.IO 4 over a WebSocket, the packets of Socket.IO
+//! 5 on top of it and the log a session leaves behind.
+
+use std::{error::Error, fmt, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tokio::{
+    net::TcpStream,
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::timeout,
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+use crate::utils::{
+    crud::{requests::RequestData, socketio::SocketIoConfigData},
+    errors::error_chain,
+    executor::{apply_oauth2_token, prepare_request},
+    tokio_runtime::runtime,
+    variables::{UnresolvedVariables, VariableResolver},
+    websocket::{shorten, websocket_url, Directions, Handshake, LogEntry},
+};
+
+/// How long a server gets to open the session and let the client in.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Event names Socket.IO keeps for itself. Clients can't emit them.
+const RESERVED_EVENTS: [&str; 6] = [
+    "connect",
+    "connect_error",
+    "disconnect",
+    "disconnecting",
+    "newListener",
+    "removeListener",
+];
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PacketKinds {
+    Connect,
+    Disconnect,
+    Event,
+    Ack,
+    ConnectError,
+    BinaryEvent,
+    BinaryAck,
+}
+
+impl PacketKinds {
+    fn code(&self) -> char {
+        match self {
+            PacketKinds::Connect => '0',
+            PacketKinds::Disconnect => '1',
+            PacketKinds::Event => '2',
+            PacketKinds::Ack => '3',
+            PacketKinds::ConnectError => '4',
+            PacketKinds::BinaryEvent => '5',
+            PacketKinds::BinaryAck => '6',
+        }
+    }
+
+    fn from_code(code: char) -> Option<PacketKinds> {
+        match code {
+            '0' => Some(PacketKinds::Connect),
+            '1' => Some(PacketKinds::Disconnect),
+            '2' => Some(PacketKinds::Event),
+            '3' => Some(PacketKinds::Ack),
+            '4' => Some(PacketKinds::ConnectError),
+            '5' => Some(PacketKinds::BinaryEvent),
+            '6' => Some(PacketKinds::BinaryAck),
+            _ => None,
+        }
+    }
+}
+
+/// A Socket.IO packet, as carried by an Engine.IO message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub kind: PacketKinds,
+    /// `/` for the main namespace.
+    pub namespace: String,
+    pub ack_id: Option<u64>,
+    pub data: Option<Value>,
+}
+
+impl Packet {
+    pub fn new(kind: PacketKinds, namespace: &str) -> Packet {
+        Packet {
+            kind,
+            namespace: namespace.to_string(),
+            ack_id: None,
+            data: None,
+        }
+    }
+
+    /// The text frame carrying the packet, Engine.IO message type included.
+    pub fn encode(&self) -> String {
+        let mut text = format!("4{}", self.kind.code());
+        if self.namespace != "/" {
+            text.push_str(&self.namespace);
+            text.push(',');
+        }
+        if let Some(ack_id) = self.ack_id {
+            text.push_str(&ack_id.to_string());
+        }
+        if let Some(data) = &self.data {
+            text.push_str(&data.to_string());
+        }
+        text
+    }
+
+    /// Read the packet of an Engine.IO message, its type already taken off.
+    pub fn parse(text: &str) -> Result<Packet, Box<dyn Error + Send + Sync>> {
+        let mut chars = text.chars();
+        let kind = chars
+            .next()
+            .and_then(PacketKinds::from_code)
+            .ok_or_else(|| format!("\"{}\" is not a Socket.IO packet.", text))?;
+        let mut rest = chars.as_str();
+
+        if matches!(kind, PacketKinds::BinaryEvent | PacketKinds::BinaryAck) {
+            // The number of attachments comes first, they arrive separately.
+            let (_, after) = rest
+                .split_once('-')
+                .ok_or("The binary packet is malformed.")?;
+            rest = after;
+        }
+        let mut namespace = "/".to_string();
+        if rest.starts_with('/') {
+            let (name, after) = rest.split_once(',').unwrap_or((rest, ""));
+            namespace = name.to_string();
+            rest = after;
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let ack_id = match digits {
+            0 => None,
+            _ => Some(rest[..digits].parse()?),
+        };
+        rest = &rest[digits..];
+        let data = match rest.is_empty() {
+            true => None,
+            false => Some(serde_json::from_str(rest)?),
+        };
+
+        Ok(Packet {
+            kind,
+            namespace,
+            ack_id,
+            data,
+        })
+    }
+}
+
+/// The WebSocket URL of the Engine.IO endpoint a request reaches, and the
+/// namespace it connects to.
+///
+/// As with the Socket.IO client, the path of the URL names the namespace and
+/// the endpoint is found at `path` instead.
+pub fn socketio_url(url: &str, path: &str) -> Result<(String, String), Box<dyn Error>> {
+    let mut url = Url::parse(&websocket_url(url)?)?;
+    let namespace = match url.path().trim_end_matches('/') {
+        "" => "/".to_string(),
+        namespace => namespace.to_string(),
+    };
+    let path = path.trim().trim_matches('/');
+    match path.is_empty() {
+        true => url.set_path("/"),
+        false => url.set_path(&format!("/{}/", path)),
+    }
+    url.query_pairs_mut()
+        .append_pair("EIO", "4")
+        .append_pair("transport", "websocket");
+
+    Ok((url.to_string(), namespace))
+}
+
+/// Event names typed one per line.
+pub fn parse_listeners(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// The arguments of an event or ack as shown in the log, none when empty.
+fn describe_arguments(arguments: &[Value]) -> Option<String> {
+    match arguments.is_empty() {
+        true => None,
+        false => Some(shorten(Value::from(arguments.to_vec()).to_string())),
+    }
+}
+
+fn describe_event(name: &str, ack_id: Option<u64>, arguments: &[Value]) -> String {
+    let mut text = match ack_id {
+        Some(ack_id) => format!("{} (ack {})", name, ack_id),
+        None => name.to_string(),
+    };
+    if let Some(arguments) = describe_arguments(arguments) {
+        text = format!("{}\n{}", text, arguments);
+    }
+    text
+}
+
+fn describe_ack(ack_id: u64, arguments: &[Value]) -> String {
+    match describe_arguments(arguments) {
+        Some(arguments) => format!("{}\n{}", ack_id, arguments),
+        None => ack_id.to_string(),
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKinds {
+    Connected,
+    Event,
+    Ack,
+    Disconnected,
+    Error,
+}
+
+impl fmt::Display for EntryKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryKinds::Connected => write!(f, "connected"),
+            EntryKinds::Event => write!(f, "event"),
+            EntryKinds::Ack => write!(f, "ack"),
+            EntryKinds::Disconnected => write!(f, "disconnected"),
+            EntryKinds::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A line of the session log. Events are logged as their name on the first
+/// line and arguments below, acks as their id and arguments.
+pub type SocketIoEntry = LogEntry<EntryKinds>;
+
+/// What a session is asked to do.
+enum Commands {
+    Emit {
+        event: String,
+        arguments: Vec<Value>,
+        ack: bool,
+    },
+    Listen(Vec<String>),
+    Disconnect,
+}
+
+/// A socket connected to a namespace. Dropping it disconnects.
+pub struct SocketIoSession {
+    commands: UnboundedSender<Commands>,
+}
+
+impl SocketIoSession {
+    fn send(&self, command: Commands) -> Result<(), Box<dyn Error>> {
+        self.commands
+            .send(command)
+            .map_err(|_| "The socket is disconnected.".into())
+    }
+
+    /// Queue an event with a single JSON argument, or none when `payload` is
+    /// empty. With `ack`, the server's answer shows up in the log.
+    pub fn emit(&self, event: &str, payload: &str, ack: bool) -> Result<(), Box<dyn Error>> {
+        let event = event.trim();
+        if event.is_empty() {
+            return Err("Enter the name of the event to emit.".into());
+        }
+        if RESERVED_EVENTS.contains(&event) {
+            return Err(format!("\"{}\" is reserved by Socket.IO.", event).into());
+        }
+        let arguments = match payload.trim().is_empty() {
+            true => vec![],
+            false => vec![serde_json::from_str(payload)
+                .map_err(|error| format!("Not valid JSON: {}", error))?],
+        };
+        self.send(Commands::Emit {
+            event: event.to_string(),
+            arguments,
+            ack,
+        })
+    }
+
+    /// Log only the events named, or all of them when none are.
+    pub fn listen(&self, events: Vec<String>) -> Result<(), Box<dyn Error>> {
+        self.send(Commands::Listen(events))
+    }
+
+    /// Leave the namespace and close the connection.
+    pub fn disconnect(&self) {
+        let _ = self.commands.send(Commands::Disconnect);
+    }
+}
+
+/// How a socket connects to its server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocketIoOptions {
+    pub handshake: Handshake,
+    pub namespace: String,
+    /// Event names to log, all of them when empty.
+    pub listeners: Vec<String>,
+}
+
+/// The options of a stored request, with its variables substituted.
+///
+/// The request's headers and auth go into the WebSocket handshake, just as
+/// for WebSocket requests.
+pub async fn prepare_socketio(
+    request: &RequestData,
+    config: &SocketIoConfigData,
+    pool: &SqlitePool,
+) -> Result<SocketIoOptions, Box<dyn Error>> {
+    let prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    let (url, namespace) = socketio_url(&prepared.url, &config.path)?;
+    let mut handshake = Handshake::for_request(&prepared, vec![])?;
+    handshake.url = url;
+
+    Ok(SocketIoOptions {
+        handshake,
+        namespace,
+        listeners: parse_listeners(&config.listeners),
+    })
+}
+
+/// The event and payload of the next emit, with the request's variables
+/// substituted.
+pub async fn prepare_emit(
+    request: &RequestData,
+    config: &SocketIoConfigData,
+    pool: &SqlitePool,
+) -> Result<(String, String), Box<dyn Error>> {
+    let variables = VariableResolver::for_request(request, pool).await?;
+    let mut unresolved = Vec::new();
+    let event = variables.substitute(config.event.trim(), &mut unresolved);
+    let payload = variables.substitute(&config.payload, &mut unresolved);
+    if !unresolved.is_empty() {
+        return Err(UnresolvedVariables(unresolved).into());
+    }
+    Ok((event, payload))
+}
+
+/// A socket and the log entries it produces, starting with the namespace
+/// letting it in. The log ends once the socket is disconnected.
+pub struct SocketIoConnection {
+    pub session: SocketIoSession,
+    pub log: UnboundedReceiver<SocketIoEntry>,
+}
+
+/// Connect a socket, returning once its namespace let it in.
+///
+/// The connection lives on the runtime, so only the log entries are
+/// awaited on the UI side.
+pub async fn connect_socketio(
+    options: SocketIoOptions,
+) -> Result<SocketIoConnection, Box<dyn Error>> {
+    let connection = runtime()
+        .spawn(async move { open_session(options).await })
+        .await?
+        .map_err(|error| error_chain(error.as_ref()))?;
+
+    Ok(connection)
+}
+
+/// Open the Engine.IO session and join the namespace, answering pings on the
+/// way. Returns the id the namespace gave the socket.
+async fn join_namespace(
+    socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    namespace: &str,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut opened = false;
+    loop {
+        let text = match socket.next().await {
+            Some(Ok(Message::Text(text))) => text.to_string(),
+            Some(Ok(Message::Close(_))) | None => {
+                return Err("The server closed the connection.".into())
+            }
+            Some(Ok(_)) => continue,
+            Some(Err(error)) => return Err(error.into()),
+        };
+        match text.split_at_checked(1) {
+            Some(("0", _)) if !opened => {
+                opened = true;
+                let connect = Packet::new(PacketKinds::Connect, namespace);
+                socket.send(Message::text(connect.encode())).await?;
+            }
+            Some(("2", payload)) => {
+                socket.send(Message::text(format!("3{}", payload))).await?;
+            }
+            Some(("4", packet)) if opened => {
+                let packet = Packet::parse(packet)?;
+                if packet.namespace != namespace {
+                    continue;
+                }
+                let data = packet.data.unwrap_or_default();
+                match packet.kind {
+                    PacketKinds::Connect => {
+                        return Ok(data["sid"].as_str().unwrap_or_default().to_string())
+                    }
+                    PacketKinds::ConnectError => {
+                        let message = data["message"]
+                            .as_str()
+                            .map(|message| message.to_string())
+                            .unwrap_or_else(|| data.to_string());
+                        return Err(format!("The namespace refused the socket: {}", message).into());
+                    }
+                    _ => {}
+                }
+            }
+            Some(("1", _)) => return Err("The server closed the session.".into()),
+            _ => {}
+        }
+    }
+}
+
+async fn open_session(
+    options: SocketIoOptions,
+) -> Result<SocketIoConnection, Box<dyn Error + Send + Sync>> {
+    let (mut socket, _) = connect_async(options.handshake.to_request()?).await?;
+    let namespace = options.namespace;
+    let sid = timeout(CONNECT_TIMEOUT, join_namespace(&mut socket, &namespace))
+        .await
+        .map_err(|_| "The server didn't answer in time.")??;
+
+    let (commands, mut queued) = unbounded_channel::<Commands>();
+    let (log, entries) = unbounded_channel();
+    let _ = log.send(SocketIoEntry::new(
+        Directions::Info,
+        EntryKinds::Connected,
+        &format!("{} as {}", namespace, sid),
+    ));
+
+    let mut listeners = options.listeners;
+    tokio::spawn(async move {
+        let (mut sink, mut stream) = socket.split();
+        let mut last_ack_id: u64 = 0;
+        let mut outgoing: Vec<(String, Option<SocketIoEntry>)> = Vec::new();
+
+        let (error, reason) = 'session: loop {
+            for (text, entry) in outgoing.drain(..) {
+                if let Err(error) = sink.send(Message::text(text)).await {
+                    break 'session (Some(error_chain(&error)), "");
+                }
+                if let Some(entry) = entry {
+                    let _ = log.send(entry);
+                }
+            }
+
+            tokio::select! {
+                command = queued.recv() => match command {
+                    Some(Commands::Emit { event, arguments, ack }) => {
+                        let ack_id = ack.then(|| {
+                            last_ack_id += 1;
+                            last_ack_id
+                        });
+                        let entry = SocketIoEntry::new(
+                            Directions::Sent,
+                            EntryKinds::Event,
+                            &describe_event(&event, ack_id, &arguments),
+                        );
+                        let mut data = vec![Value::from(event)];
+                        data.extend(arguments);
+                        let packet = Packet {
+                            ack_id,
+                            data: Some(Value::from(data)),
+                            ..Packet::new(PacketKinds::Event, &namespace)
+                        };
+                        outgoing.push((packet.encode(), Some(entry)));
+                    }
+                    Some(Commands::Listen(events)) => listeners = events,
+                    // A dropped session disconnects too.
+                    Some(Commands::Disconnect) | None => {
+                        let leave = Packet::new(PacketKinds::Disconnect, &namespace);
+                        let _ = sink.send(Message::text(leave.encode())).await;
+                        let _ = sink.close().await;
+                        break 'session (None, "");
+                    }
+                },
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => match text.split_at_checked(1) {
+                        // The server checks the connection, clients only answer.
+                        Some(("2", payload)) => outgoing.push((format!("3{}", payload), None)),
+                        Some(("1", _)) => break 'session (None, "The server closed the session."),
+                        Some(("4", packet)) => {
+                            let packet = match Packet::parse(packet) {
+                                Ok(packet) => packet,
+                                Err(error) => break 'session (Some(error.to_string()), ""),
+                            };
+                            if packet.namespace != namespace {
+                                continue;
+                            }
+                            let arguments = match packet.data {
+                                Some(Value::Array(arguments)) => arguments,
+                                Some(data) => vec![data],
+                                None => vec![],
+                            };
+                            match packet.kind {
+                                PacketKinds::Event => {
+                                    let (name, arguments) = match arguments.split_first() {
+                                        Some((Value::String(name), rest)) => (name.clone(), rest.to_vec()),
+                                        _ => continue,
+                                    };
+                                    if listeners.is_empty() || listeners.contains(&name) {
+                                        let _ = log.send(SocketIoEntry::new(
+                                            Directions::Received,
+                                            EntryKinds::Event,
+                                            &describe_event(&name, packet.ack_id, &arguments),
+                                        ));
+                                    }
+                                    // Servers waiting on an answer get an empty one.
+                                    if let Some(ack_id) = packet.ack_id {
+                                        let ack = Packet {
+                                            ack_id: Some(ack_id),
+                                            data: Some(Value::Array(vec![])),
+                                            ..Packet::new(PacketKinds::Ack, &namespace)
+                                        };
+                                        let entry = SocketIoEntry::new(
+                                            Directions::Sent,
+                                            EntryKinds::Ack,
+                                            &describe_ack(ack_id, &[]),
+                                        );
+                                        outgoing.push((ack.encode(), Some(entry)));
+                                    }
+                                }
+                                PacketKinds::Ack => {
+                                    let _ = log.send(SocketIoEntry::new(
+                                        Directions::Received,
+                                        EntryKinds::Ack,
+                                        &describe_ack(packet.ack_id.unwrap_or_default(), &arguments),
+                                    ));
+                                }
+                                PacketKinds::Disconnect => {
+                                    break 'session (None, "The server disconnected the socket.")
+                                }
+                                PacketKinds::BinaryEvent | PacketKinds::BinaryAck => {
+                                    let _ = log.send(SocketIoEntry::new(
+                                        Directions::Received,
+                                        EntryKinds::Error,
+                                        "Events with binary arguments can't be shown.",
+                                    ));
+                                }
+                                PacketKinds::Connect | PacketKinds::ConnectError => {}
+                            }
+                        }
+                        _ => {}
+                    },
+                    Some(Ok(Message::Close(_))) | None => break 'session (None, ""),
+                    // Binary frames only carry attachments of binary events.
+                    Some(Ok(_)) => {}
+                    Some(Err(error)) => break 'session (Some(error_chain(&error)), ""),
+                },
+            }
+        };
+        if let Some(error) = error {
+            let _ = log.send(SocketIoEntry::new(
+                Directions::Info,
+                EntryKinds::Error,
+                &error,
+            ));
+        }
+        let _ = log.send(SocketIoEntry::new(
+            Directions::Info,
+            EntryKinds::Disconnected,
+            reason,
+        ));
+    });
+
+    Ok(SocketIoConnection {
+        session: SocketIoSession { commands },
+        log: entries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                collections::create_collection,
+                requests::{create_request, update_request_item, HTTPMethods, ProtocolTypes},
+            },
+            test_server::spawn_socketio_server,
+        },
+    };
+    use serde_json::json;
+
+    async fn next_entry(connection: &mut SocketIoConnection) -> SocketIoEntry {
+        connection.log.recv().await.expect("The log ended early.")
+    }
+
+    #[test]
+    fn test_packets() {
+        let packet = Packet::parse("2/chat,12[\"hello\",{\"a\":1}]").unwrap();
+        assert!(packet.kind == PacketKinds::Event);
+        assert!(packet.namespace == "/chat" && packet.ack_id == Some(12));
+        assert!(packet.data == Some(json!(["hello", {"a": 1}])));
+        assert!(packet.encode() == "42/chat,12[\"hello\",{\"a\":1}]");
+
+        let connect = Packet::parse("0{\"sid\":\"x\"}").unwrap();
+        assert!(connect.kind == PacketKinds::Connect && connect.namespace == "/");
+        assert!(Packet::new(PacketKinds::Connect, "/").encode() == "40");
+        assert!(Packet::new(PacketKinds::Disconnect, "/chat").encode() == "41/chat,");
+
+        let binary =
+            Packet::parse("51-/chat,[\"file\",{\"_placeholder\":true,\"num\":0}]").unwrap();
+        assert!(binary.kind == PacketKinds::BinaryEvent && binary.namespace == "/chat");
+        assert!(Packet::parse("9").is_err());
+        assert!(Packet::parse("2[").is_err());
+    }
+
+    #[test]
+    fn test_socketio_url() {
+        let (url, namespace) =
+            socketio_url("https://example.com/admin?token=1", "/socket.io/").unwrap();
+        assert!(url == "wss://example.com/socket.io/?token=1&EIO=4&transport=websocket");
+        assert!(namespace == "/admin");
+        let (url, namespace) = socketio_url("ws://localhost:3000", "realtime").unwrap();
+        assert!(url == "ws://localhost:3000/realtime/?EIO=4&transport=websocket");
+        assert!(namespace == "/");
+        assert!(socketio_url("localhost:3000", "/socket.io/").is_err());
+        assert!(parse_listeners(" chat \n\nnotice") == vec!["chat", "notice"]);
+    }
+
+    #[tokio::test]
+    async fn test_socketio_session() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let url = spawn_socketio_server().await;
+        let collection = create_collection("Sockets".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::SocketIo, &collection.id, &db)
+            .await
+            .unwrap();
+        let request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::SocketIo,
+            HTTPMethods::Get,
+            &url,
+            &db,
+        )
+        .await
+        .unwrap();
+        let config = SocketIoConfigData {
+            listeners: "welcome\nquestion\nanswered\necho".to_string(),
+            ..Default::default()
+        };
+
+        let options = prepare_socketio(&request, &config, &db).await.unwrap();
+        assert!(options.namespace == "/chat");
+        let mut connection = connect_socketio(options.clone()).await.unwrap();
+        let connected = next_entry(&mut connection).await;
+        assert!(connected.kind == EntryKinds::Connected && connected.data == "/chat as socket");
+
+        let session = &connection.session;
+        session.emit("echo", r#"{"a": 1}"#, true).unwrap();
+        session.emit("echo", "", false).unwrap();
+        assert!(session.emit("disconnect", "", false).is_err());
+        assert!(session.emit("echo", "{", false).is_err());
+
+        let mut entries = Vec::new();
+        while entries.len() < 8 {
+            let entry = next_entry(&mut connection).await;
+            entries.push((entry.direction, entry.kind, entry.data));
+        }
+        connection.session.disconnect();
+        while let Some(entry) = connection.log.recv().await {
+            entries.push((entry.direction, entry.kind, entry.data));
+        }
+
+        let sent = |kind, data: &str| (Directions::Sent, kind, data.to_string());
+        let received = |kind, data: &str| (Directions::Received, kind, data.to_string());
+        assert!(entries.contains(&received(
+            EntryKinds::Event,
+            "welcome\n[{\"user\":\"querry\"}]"
+        )));
+        assert!(entries.contains(&received(
+            EntryKinds::Event,
+            "question (ack 7)\n[\"ready?\"]"
+        )));
+        assert!(entries.contains(&sent(EntryKinds::Ack, "7")));
+        assert!(entries.contains(&received(EntryKinds::Event, "answered\n[7]")));
+        assert!(entries.contains(&sent(EntryKinds::Event, "echo (ack 1)\n[{\"a\":1}]")));
+        assert!(entries.contains(&received(EntryKinds::Ack, "1\n[{\"a\":1}]")));
+        assert!(entries.contains(&sent(EntryKinds::Event, "echo")));
+        assert!(entries.contains(&received(EntryKinds::Event, "echo")));
+        // Events nobody listens to are left out.
+        assert!(!entries
+            .iter()
+            .any(|(_, _, data)| data.starts_with("notice")));
+        assert!(entries.last().unwrap().1 == EntryKinds::Disconnected);
+
+        let refused = SocketIoOptions {
+            namespace: "/".to_string(),
+            ..options
+        };
+        let error = connect_socketio(refused).await.err().unwrap();
+        assert!(error.to_string().contains("Invalid namespace"));
+    }
+}
@@ -0,0 +1,684 @@
+//! Server-Sent Events: a `text/event-stream` response read as it arrives,
+//! opened again where it stopped when the server ends it.
+
+use std::{error::Error, fmt, time::Duration};
+
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use sqlx::SqlitePool;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
+
+use crate::utils::{
+    crud::{
+        environments::get_active_environment,
+        history::{create_history_entry, HistorySummaryData, NewHistoryEntry},
+        requests::RequestData,
+    },
+    errors::error_chain,
+    executor::{open_response, resolve_request, response_headers, HistoryOrigin, PreparedRequest},
+    tokio_runtime::runtime,
+    websocket::log_time,
+};
+
+/// How long to wait before opening the stream again, until the server asks
+/// for something else with a `retry` field.
+const DEFAULT_RETRY: Duration = Duration::from_millis(3000);
+
+/// An event as the server dispatched it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerEvent {
+    /// The last event id seen when the event arrived, as `EventSource` has it.
+    pub id: String,
+    /// `message` unless the server named it.
+    pub event: String,
+    pub data: String,
+}
+
+/// Reads the event stream format as its bytes arrive, by the rules
+/// browsers follow for `EventSource`.
+#[derive(Debug, Default)]
+pub struct EventParser {
+    /// The line read so far.
+    line: Vec<u8>,
+    /// The last chunk ended on a carriage return, so a line feed starting
+    /// the next one ends no line.
+    after_cr: bool,
+    started: bool,
+    data: String,
+    event: String,
+    last_event_id: String,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    /// A parser for a stream picking up after the given event id.
+    pub fn new(last_event_id: &str) -> EventParser {
+        EventParser {
+            last_event_id: last_event_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Read more of the stream, returning the events it completed.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<ServerEvent> {
+        let mut events = Vec::new();
+        for &byte in bytes {
+            let after_cr = std::mem::replace(&mut self.after_cr, byte == b'\r');
+            match byte {
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.read_line(&line) {
+                        events.push(event);
+                    }
+                }
+                byte => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    /// The id the next connection resumes from.
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    /// The reconnection time the server asked for since the last call.
+    pub fn take_retry(&mut self) -> Option<Duration> {
+        self.retry.take()
+    }
+
+    fn read_line(&mut self, line: &[u8]) -> Option<ServerEvent> {
+        let mut line = String::from_utf8_lossy(line).to_string();
+        if !self.started {
+            self.started = true;
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                line = rest.to_string();
+            }
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_string(),
+            "retry" if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                if let Ok(millis) = value.parse() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<ServerEvent> {
+        let mut data = std::mem::take(&mut self.data);
+        let event = std::mem::take(&mut self.event);
+        if data.is_empty() {
+            return None;
+        }
+        data.pop();
+
+        Some(ServerEvent {
+            id: self.last_event_id.clone(),
+            event: match event.is_empty() {
+                true => "message".to_string(),
+                false => event,
+            },
+            data,
+        })
+    }
+}
+
+/// Events written back in the event stream format.
+pub fn event_stream_text(events: &[ServerEvent]) -> String {
+    let mut text = String::new();
+    let mut last_event_id = "";
+    for event in events {
+        if event.id != last_event_id {
+            text.push_str(&format!("id: {}\n", event.id));
+            last_event_id = &event.id;
+        }
+        if event.event != "message" {
+            text.push_str(&format!("event: {}\n", event.event));
+        }
+        for line in event.data.split('\n') {
+            text.push_str(&format!("data: {}\n", line));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKinds {
+    Event,
+    Connected,
+    Disconnected,
+    Reconnecting,
+    Error,
+}
+
+impl fmt::Display for EntryKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntryKinds::Event => write!(f, "event"),
+            EntryKinds::Connected => write!(f, "connected"),
+            EntryKinds::Disconnected => write!(f, "disconnected"),
+            EntryKinds::Reconnecting => write!(f, "reconnecting"),
+            EntryKinds::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// How the server answered when the stream was opened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: Vec<(String, String)>,
+}
+
+/// A line of the stream log.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamEntry {
+    /// Local time, down to the millisecond.
+    pub time: String,
+    pub kind: EntryKinds,
+    /// The event, on event entries.
+    pub event: Option<ServerEvent>,
+    /// The answer of the server, on connected entries.
+    pub response: Option<StreamResponse>,
+    /// What happened, on the other entries.
+    pub data: String,
+}
+
+impl StreamEntry {
+    pub fn new(kind: EntryKinds, data: &str) -> StreamEntry {
+        StreamEntry {
+            time: log_time(),
+            kind,
+            event: None,
+            response: None,
+            data: data.to_string(),
+        }
+    }
+
+    fn for_event(event: ServerEvent) -> StreamEntry {
+        StreamEntry {
+            event: Some(event),
+            ..StreamEntry::new(EntryKinds::Event, "")
+        }
+    }
+
+    fn for_response(response: StreamResponse) -> StreamEntry {
+        let data = format!("{} {}", response.status, response.status_text);
+        StreamEntry {
+            response: Some(response),
+            ..StreamEntry::new(EntryKinds::Connected, data.trim())
+        }
+    }
+}
+
+/// A stream being read. Dropping it stops the stream.
+pub struct SseSession {
+    stop: Option<oneshot::Sender<()>>,
+}
+
+impl SseSession {
+    /// Stop reading and don't reconnect.
+    pub fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+}
+
+/// A session and the log it produces, from the first connection on. The log
+/// ends once the stream is stopped or the server refuses it.
+pub struct EventStream {
+    pub session: SseSession,
+    pub log: UnboundedReceiver<StreamEntry>,
+}
+
+/// The request of a stored SSE request, with its variables substituted.
+///
+/// Its auth is not applied yet, so the request can be recorded in the
+/// history as it is. See [`crate::utils::executor::authenticate`] for
+/// sending it.
+pub async fn prepare_stream(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<PreparedRequest, Box<dyn Error>> {
+    let mut prepared = resolve_request(request, pool).await?;
+    prepared.set_default_header("Accept", "text/event-stream".to_string());
+    prepared.set_default_header("Cache-Control", "no-cache".to_string());
+
+    Ok(prepared)
+}
+
+/// Start reading a stream, resuming after `last_event_id` when there is one.
+///
+/// A stream the server ends, or that breaks, is opened again with the last
+/// event id seen when `reconnect` is set. A server answering with anything
+/// but a `200` event stream is never asked again. The stream is read on the
+/// runtime, so only the log is awaited on the UI side.
+pub fn open_stream(request: PreparedRequest, last_event_id: &str, reconnect: bool) -> EventStream {
+    let (log, entries) = unbounded_channel();
+    let (stop, stopped) = oneshot::channel();
+    let last_event_id = last_event_id.to_string();
+    runtime().spawn(async move {
+        tokio::select! {
+            _ = run_stream(request, last_event_id, reconnect, &log) => {}
+            // A dropped session stops the stream too.
+            _ = stopped => {
+                let _ = log.send(StreamEntry::new(EntryKinds::Disconnected, "Stopped"));
+            }
+        }
+    });
+
+    EventStream {
+        session: SseSession { stop: Some(stop) },
+        log: entries,
+    }
+}
+
+/// How a connection ended.
+enum StreamEnd {
+    /// The stream may be opened again.
+    Retry(StreamEntry),
+    /// The server doesn't want it opened again.
+    Final(StreamEntry),
+}
+
+async fn run_stream(
+    request: PreparedRequest,
+    last_event_id: String,
+    reconnect: bool,
+    log: &UnboundedSender<StreamEntry>,
+) {
+    let body = match request.body.to_bytes().await {
+        Ok(body) => body,
+        Err(error) => {
+            let _ = log.send(StreamEntry::new(EntryKinds::Error, &error.to_string()));
+            return;
+        }
+    };
+
+    let mut parser = EventParser::new(&last_event_id);
+    let mut retry = DEFAULT_RETRY;
+    loop {
+        let mut attempt = request.clone();
+        if !parser.last_event_id().is_empty() {
+            attempt
+                .headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("last-event-id"));
+            attempt.headers.push((
+                "Last-Event-ID".to_string(),
+                parser.last_event_id().to_string(),
+            ));
+        }
+
+        let end = read_stream(&attempt, body.clone(), &mut parser, log).await;
+        if let Some(asked) = parser.take_retry() {
+            retry = asked;
+        }
+        match end {
+            StreamEnd::Final(entry) => {
+                let _ = log.send(entry);
+                return;
+            }
+            StreamEnd::Retry(entry) => {
+                let _ = log.send(entry);
+                if !reconnect {
+                    return;
+                }
+            }
+        }
+
+        let resume = match parser.last_event_id() {
+            "" => String::new(),
+            id => format!(" after event {}", id),
+        };
+        let _ = log.send(StreamEntry::new(
+            EntryKinds::Reconnecting,
+            &format!("In {} ms{}", retry.as_millis(), resume),
+        ));
+        tokio::time::sleep(retry).await;
+    }
+}
+
+/// Open the stream once and read it until it ends.
+async fn read_stream(
+    request: &PreparedRequest,
+    body: Vec<u8>,
+    parser: &mut EventParser,
+    log: &UnboundedSender<StreamEntry>,
+) -> StreamEnd {
+    let mut response = match open_response(request, body).await {
+        Ok(response) => response,
+        Err(error) => {
+            return StreamEnd::Retry(StreamEntry::new(
+                EntryKinds::Error,
+                &error_chain(error.as_ref()),
+            ))
+        }
+    };
+
+    let status = response.status();
+    let answer = format!(
+        "{} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    );
+    if status == StatusCode::NO_CONTENT {
+        return StreamEnd::Final(StreamEntry::new(
+            EntryKinds::Disconnected,
+            &format!("The server has no more events ({})", answer.trim()),
+        ));
+    }
+    if status != StatusCode::OK {
+        return StreamEnd::Final(StreamEntry::new(
+            EntryKinds::Error,
+            &format!("The server answered {}", answer.trim()),
+        ));
+    }
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    if !content_type
+        .to_ascii_lowercase()
+        .starts_with("text/event-stream")
+    {
+        return StreamEnd::Final(StreamEntry::new(
+            EntryKinds::Error,
+            &format!(
+                "Not an event stream: the content type is \"{}\"",
+                content_type
+            ),
+        ));
+    }
+
+    let _ = log.send(StreamEntry::for_response(StreamResponse {
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or("").to_string(),
+        headers: response_headers(&response),
+    }));
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                for event in parser.feed(&chunk) {
+                    let _ = log.send(StreamEntry::for_event(event));
+                }
+            }
+            Ok(None) => {
+                return StreamEnd::Retry(StreamEntry::new(
+                    EntryKinds::Disconnected,
+                    "The server closed the stream",
+                ))
+            }
+            Err(error) => {
+                return StreamEnd::Retry(StreamEntry::new(EntryKinds::Error, &error_chain(&error)))
+            }
+        }
+    }
+}
+
+/// Record a stream in the history, with the events of its log as the body
+/// of the response.
+///
+/// The response is the last answer the server gave to opening the stream.
+/// A stream that never opened is recorded with its last error.
+pub async fn record_stream(
+    request: &PreparedRequest,
+    origin: HistoryOrigin,
+    log: &[StreamEntry],
+    total_ms: i64,
+    pool: &SqlitePool,
+) -> Result<HistorySummaryData, Box<dyn Error>> {
+    let environment = get_active_environment(pool).await?;
+    let body = request
+        .body
+        .to_bytes()
+        .await
+        .map_err(|error| error.to_string())?;
+    let events: Vec<ServerEvent> = log.iter().filter_map(|entry| entry.event.clone()).collect();
+    let events_text = event_stream_text(&events);
+
+    let mut entry = NewHistoryEntry {
+        request_id: origin.request_id,
+        request_name: origin.request_name,
+        collection_id: origin.collection_id,
+        environment_id: environment
+            .as_ref()
+            .map(|environment| environment.id.clone()),
+        environment_name: environment
+            .map(|environment| environment.name)
+            .unwrap_or_default(),
+        http_method: request.method.to_string(),
+        url: request.url.clone(),
+        request_headers: request.headers.clone(),
+        request_body: body,
+        total_ms,
+        ..Default::default()
+    };
+    match log.iter().rev().find_map(|entry| entry.response.as_ref()) {
+        Some(response) => {
+            entry.status = Some(response.status);
+            entry.status_text = response.status_text.clone();
+            entry.response_headers = response.headers.clone();
+            entry.response_size = events_text.len();
+            entry.response_body = events_text.into_bytes();
+        }
+        None => {
+            entry.error = log
+                .iter()
+                .rev()
+                .find(|entry| entry.kind == EntryKinds::Error)
+                .map(|entry| entry.data.clone())
+                .unwrap_or_else(|| "The stream never opened".to_string())
+        }
+    }
+
+    create_history_entry(&entry, pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                collections::create_collection,
+                history::get_history_entry,
+                requests::{create_request, update_request_item, HTTPMethods, ProtocolTypes},
+            },
+            executor::{auth::PreparedAuth, body::PreparedBody},
+            test_server::spawn_test_server_sequence,
+        },
+    };
+
+    fn event(id: &str, event: &str, data: &str) -> ServerEvent {
+        ServerEvent {
+            id: id.to_string(),
+            event: event.to_string(),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_parser() {
+        let mut parser = EventParser::new("");
+        let stream = "\u{feff}: a comment\r\nretry: 50\r\nid: 1\r\nevent: greeting\r\ndata: hello\r\ndata:world\r\n\r\ndata: second\n\nid\nevent: empty\n\ndata\n\nid: bad\0\rdata: third\r\r";
+        let mut events = Vec::new();
+        // Lines and line endings are split between chunks.
+        for chunk in stream.as_bytes().chunks(3) {
+            events.extend(parser.feed(chunk));
+        }
+        assert!(
+            events
+                == vec![
+                    event("1", "greeting", "hello\nworld"),
+                    event("1", "message", "second"),
+                    event("", "message", ""),
+                    event("", "message", "third"),
+                ]
+        );
+        assert!(parser.take_retry() == Some(Duration::from_millis(50)));
+        assert!(parser.take_retry().is_none());
+        assert!(parser.last_event_id().is_empty());
+
+        // An event cut off by the end of the stream is never dispatched.
+        let mut parser = EventParser::new("7");
+        assert!(parser.feed(b"data: cut").is_empty());
+        assert!(parser.feed(b" off\n\n") == vec![event("7", "message", "cut off")]);
+    }
+
+    #[test]
+    fn test_event_stream_text() {
+        let events = vec![
+            event("", "message", "first"),
+            event("2", "update", "{\"a\": 1}\n{\"b\": 2}"),
+            event("2", "message", ""),
+        ];
+        let text = event_stream_text(&events);
+        assert!(text.starts_with("data: first\n\nid: 2\nevent: update\n"));
+        assert!(EventParser::new("").feed(text.as_bytes()) == events);
+    }
+
+    fn stream_response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n{}",
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_open_stream() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, requests) = spawn_test_server_sequence(vec![
+            stream_response(
+                "retry: 10\nid: 1\nevent: greeting\ndata: hello\n\nid: 2\ndata: two\n\n",
+            ),
+            stream_response("id: 3\ndata: resumed\n\n"),
+        ])
+        .await;
+        let collection = create_collection("Streams".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Sse, &collection.id, &db)
+            .await
+            .unwrap();
+        let request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::Sse,
+            HTTPMethods::Get,
+            &url,
+            &db,
+        )
+        .await
+        .unwrap();
+
+        let prepared = prepare_stream(&request, &db).await.unwrap();
+        let mut stream = open_stream(prepared.clone(), "", true);
+        let mut log = Vec::new();
+        while let Some(entry) = stream.log.recv().await {
+            log.push(entry);
+            if log.iter().filter(|entry| entry.event.is_some()).count() == 3 {
+                break;
+            }
+        }
+        stream.session.stop();
+        while let Some(entry) = stream.log.recv().await {
+            log.push(entry);
+        }
+
+        let events: Vec<ServerEvent> = log.iter().filter_map(|entry| entry.event.clone()).collect();
+        assert!(
+            events
+                == vec![
+                    event("1", "greeting", "hello"),
+                    event("2", "message", "two"),
+                    event("3", "message", "resumed"),
+                ]
+        );
+        let kinds: Vec<EntryKinds> = log.iter().map(|entry| entry.kind).collect();
+        assert!(kinds.starts_with(&[
+            EntryKinds::Connected,
+            EntryKinds::Event,
+            EntryKinds::Event,
+            EntryKinds::Disconnected,
+            EntryKinds::Reconnecting,
+            EntryKinds::Connected,
+        ]));
+        assert!(log.last().unwrap().data == "Stopped");
+        assert!(log[4].data == "In 10 ms after event 2");
+
+        let requests = requests.await.unwrap();
+        assert!(requests[0].contains("accept: text/event-stream"));
+        assert!(!requests[0].to_lowercase().contains("last-event-id"));
+        assert!(requests[1].contains("last-event-id: 2"));
+
+        let summary = record_stream(
+            &prepared,
+            HistoryOrigin::for_request(&request),
+            &log,
+            120,
+            &db,
+        )
+        .await
+        .unwrap();
+        let entry = get_history_entry(&summary.id, &db).await.unwrap();
+        assert!(entry.status == Some(200) && entry.total_ms == 120);
+        assert!(entry.request_id == Some(request.id.clone()));
+        assert!(entry.response_body == event_stream_text(&events).into_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_refused_stream() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, _) = spawn_test_server_sequence(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+        ])
+        .await;
+        let request = PreparedRequest {
+            method: HTTPMethods::Get,
+            url,
+            headers: vec![],
+            body: PreparedBody::Empty,
+            auth: PreparedAuth::None,
+        };
+
+        let mut stream = open_stream(request.clone(), "5", true);
+        let mut log = Vec::new();
+        while let Some(entry) = stream.log.recv().await {
+            log.push(entry);
+        }
+        // Servers refusing the stream are never asked again.
+        assert!(log.len() == 1 && log[0].kind == EntryKinds::Error);
+        assert!(log[0].data == "The server answered 404 Not Found");
+
+        let summary = record_stream(&request, HistoryOrigin::default(), &log, 0, &db)
+            .await
+            .unwrap();
+        assert!(summary.status.is_none() && summary.error == log[0].data);
+    }
+}
@@ -0,0 +1,712 @@
+//! Minimal HTTP server used as a stand-in endpoint in tests.
+
+use std::{convert::Infallible, fs, pin::Pin};
+
+use futures_util::{stream, SinkExt, Stream, StreamExt};
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor, Value as ProtoValue};
+use prost_types::FileDescriptorSet;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    sync::oneshot,
+};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        handshake::server::{Request, Response},
+        http::header::{HeaderValue, SEC_WEBSOCKET_PROTOCOL},
+        Message,
+    },
+};
+use tonic::{
+    body::Body,
+    codegen::{http, BoxFuture, Context, Poll, Service},
+    metadata::MetadataMap,
+    server::{Grpc, NamedService},
+    transport::{server::TcpIncoming, Server},
+    Code, Status, Streaming,
+};
+use uuid::Uuid;
+
+use crate::utils::{
+    grpc::{codec::DynamicCodec, compile_protos, find_method},
+    mqtt::{decode_packet, topic_matches, Packet, Publish},
+};
+
+/// Read a full HTTP request (headers plus `Content-Length` body) from a stream.
+pub async fn read_http_request<S: AsyncRead + Unpin>(stream: &mut S) -> String {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    loop {
+        let read = stream.read(&mut chunk).await.unwrap_or(0);
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let text = String::from_utf8_lossy(&buffer).to_string();
+        if let Some(headers_end) = text.find("\r\n\r\n") {
+            let content_length = text[..headers_end]
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    if name.trim().eq_ignore_ascii_case("content-length") {
+                        value.trim().parse::<usize>().ok()
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(0);
+            if buffer.len() >= headers_end + 4 + content_length {
+                break;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&buffer).to_string()
+}
+
+/// Serve `responses` to consecutive connections and report the raw requests.
+pub async fn spawn_test_server_sequence(
+    responses: Vec<String>,
+) -> (String, oneshot::Receiver<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for response in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            requests.push(read_http_request(&mut stream).await);
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+        let _ = sender.send(requests);
+    });
+
+    (format!("http://{}/", address), receiver)
+}
+
+/// Serve a single canned response and report the raw request that was received.
+pub async fn spawn_test_server(response: &str) -> (String, oneshot::Receiver<String>) {
+    let (url, requests) = spawn_test_server_sequence(vec![response.to_string()]).await;
+    let (sender, receiver) = oneshot::channel();
+
+    tokio::spawn(async move {
+        if let Ok(mut requests) = requests.await {
+            let _ = sender.send(requests.remove(0));
+        }
+    });
+
+    (url, receiver)
+}
+
+/// Accept a single WebSocket connection and send every text and binary
+/// message back. Reports the handshake headers, with lowercase names, and
+/// picks the "echo" subprotocol when the client offers it.
+pub async fn spawn_websocket_echo_server() -> (String, oneshot::Receiver<Vec<(String, String)>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // The error type of the callback is set by tungstenite.
+        #[allow(clippy::result_large_err)]
+        let handshake = |request: &Request, mut response: Response| {
+            let headers: Vec<(String, String)> = request
+                .headers()
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        String::from_utf8_lossy(value.as_bytes()).to_string(),
+                    )
+                })
+                .collect();
+            let offers_echo = headers.iter().any(|(name, value)| {
+                name == "sec-websocket-protocol" && value.split(',').any(|p| p.trim() == "echo")
+            });
+            if offers_echo {
+                response
+                    .headers_mut()
+                    .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("echo"));
+            }
+            let _ = sender.send(headers);
+            Ok(response)
+        };
+        let mut socket = accept_hdr_async(stream, handshake).await.unwrap();
+
+        while let Some(Ok(message)) = socket.next().await {
+            if message.is_text() || message.is_binary() {
+                let _ = socket.send(message).await;
+            }
+        }
+    });
+
+    (format!("ws://{}/socket", address), receiver)
+}
+
+/// The `__schema` object of the schema the GraphQL stand-ins serve.
+pub fn graphql_introspection() -> Value {
+    let named = |kind: &str, name: &str| json!({"kind": kind, "name": name, "ofType": null});
+    let non_null = |inner: Value| json!({"kind": "NON_NULL", "name": null, "ofType": inner});
+    let list = |inner: Value| json!({"kind": "LIST", "name": null, "ofType": inner});
+    let field = |name: &str, field_type: Value, args: Value| json!({"name": name, "description": null, "args": args, "type": field_type, "isDeprecated": false});
+    let arg = |name: &str, value_type: Value| json!({"name": name, "description": null, "type": value_type, "defaultValue": null});
+
+    json!({
+        "queryType": {"name": "Query"},
+        "mutationType": {"name": "Mutation"},
+        "subscriptionType": {"name": "Subscription"},
+        "types": [
+            {
+                "kind": "OBJECT", "name": "Query", "description": "The root.",
+                "fields": [
+                    field("user", named("OBJECT", "User"), json!([arg("id", non_null(named("SCALAR", "ID")))])),
+                    field("users", non_null(list(non_null(named("OBJECT", "User")))), json!([arg("role", named("ENUM", "Role"))])),
+                    field("search", list(named("UNION", "SearchResult")), json!([arg("text", non_null(named("SCALAR", "String")))])),
+                ],
+                "inputFields": null, "interfaces": [], "enumValues": null, "possibleTypes": null
+            },
+            {
+                "kind": "OBJECT", "name": "Mutation", "description": null,
+                "fields": [
+                    field("createUser", non_null(named("OBJECT", "User")), json!([arg("input", non_null(named("INPUT_OBJECT", "UserInput")))])),
+                ],
+                "inputFields": null, "interfaces": [], "enumValues": null, "possibleTypes": null
+            },
+            {
+                "kind": "OBJECT", "name": "Subscription", "description": null,
+                "fields": [
+                    field("userAdded", non_null(named("OBJECT", "User")), json!([])),
+                ],
+                "inputFields": null, "interfaces": [], "enumValues": null, "possibleTypes": null
+            },
+            {
+                "kind": "OBJECT", "name": "User", "description": "Someone who signed up.",
+                "fields": [
+                    field("id", non_null(named("SCALAR", "ID")), json!([])),
+                    field("name", named("SCALAR", "String"), json!([])),
+                    field("role", named("ENUM", "Role"), json!([])),
+                    field("friends", list(named("OBJECT", "User")), json!([arg("first", named("SCALAR", "Int"))])),
+                ],
+                "inputFields": null, "interfaces": [named("INTERFACE", "Node")], "enumValues": null, "possibleTypes": null
+            },
+            {
+                "kind": "OBJECT", "name": "Post", "description": null,
+                "fields": [
+                    field("id", non_null(named("SCALAR", "ID")), json!([])),
+                    field("title", named("SCALAR", "String"), json!([])),
+                ],
+                "inputFields": null, "interfaces": [named("INTERFACE", "Node")], "enumValues": null, "possibleTypes": null
+            },
+            {
+                "kind": "INTERFACE", "name": "Node", "description": null,
+                "fields": [field("id", non_null(named("SCALAR", "ID")), json!([]))],
+                "inputFields": null, "interfaces": [], "enumValues": null,
+                "possibleTypes": [named("OBJECT", "User"), named("OBJECT", "Post")]
+            },
+            {
+                "kind": "UNION", "name": "SearchResult", "description": null,
+                "fields": null, "inputFields": null, "interfaces": null, "enumValues": null,
+                "possibleTypes": [named("OBJECT", "User"), named("OBJECT", "Post")]
+            },
+            {
+                "kind": "ENUM", "name": "Role", "description": null,
+                "fields": null, "inputFields": null, "interfaces": null,
+                "enumValues": [{"name": "ADMIN"}, {"name": "MEMBER"}], "possibleTypes": null
+            },
+            {
+                "kind": "INPUT_OBJECT", "name": "UserInput", "description": null,
+                "fields": null, "interfaces": null, "enumValues": null, "possibleTypes": null,
+                "inputFields": [arg("name", non_null(named("SCALAR", "String")))]
+            },
+            {"kind": "SCALAR", "name": "ID", "description": null, "fields": null, "inputFields": null, "interfaces": null, "enumValues": null, "possibleTypes": null},
+            {"kind": "SCALAR", "name": "String", "description": null, "fields": null, "inputFields": null, "interfaces": null, "enumValues": null, "possibleTypes": null},
+            {"kind": "SCALAR", "name": "Int", "description": null, "fields": null, "inputFields": null, "interfaces": null, "enumValues": null, "possibleTypes": null},
+            {"kind": "OBJECT", "name": "__Schema", "description": null, "fields": [], "inputFields": null, "interfaces": [], "enumValues": null, "possibleTypes": null}
+        ]
+    })
+}
+
+/// Answer GraphQL requests over HTTP until the test ends.
+///
+/// Introspection queries get [`graphql_introspection`]; any other operation
+/// gets the request body back as `data.received`. Only POST is answered.
+pub async fn spawn_graphql_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let request = read_http_request(&mut stream).await;
+            let body = request
+                .split_once("\r\n\r\n")
+                .map(|(_, body)| body)
+                .unwrap_or_default();
+            let operation: Value = serde_json::from_str(body).unwrap_or(Value::Null);
+            let query = operation["query"].as_str().unwrap_or_default();
+            let result = match query.contains("__schema") {
+                true => json!({"data": {"__schema": graphql_introspection()}}),
+                false => json!({"data": {"received": operation}}),
+            }
+            .to_string();
+            let response = match request.starts_with("POST ") {
+                true => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    result.len(),
+                    result
+                ),
+                false => "HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+
+    format!("http://{}/graphql", address)
+}
+
+/// Accept a single graphql-transport-ws connection. Every subscription gets
+/// two `next` messages, numbered in `data.count`, and is then completed.
+pub async fn spawn_graphql_ws_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // The error type of the callback is set by tungstenite.
+        #[allow(clippy::result_large_err)]
+        let handshake = |_: &Request, mut response: Response| {
+            response.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_static("graphql-transport-ws"),
+            );
+            Ok(response)
+        };
+        let mut socket = accept_hdr_async(stream, handshake).await.unwrap();
+
+        while let Some(Ok(Message::Text(text))) = socket.next().await {
+            let message: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+            match message["type"].as_str() {
+                Some("connection_init") => {
+                    let ack = json!({"type": "connection_ack"}).to_string();
+                    let _ = socket.send(Message::text(ack)).await;
+                }
+                Some("subscribe") => {
+                    let id = message["id"].clone();
+                    for count in 1..=2 {
+                        let next = json!({"id": id, "type": "next", "payload": {"data": {"count": count}}});
+                        let _ = socket.send(Message::text(next.to_string())).await;
+                    }
+                    let complete = json!({"id": id, "type": "complete"}).to_string();
+                    let _ = socket.send(Message::text(complete)).await;
+                }
+                _ => {}
+            }
+        }
+    });
+
+    format!("ws://{}/graphql", address)
+}
+
+/// A Socket.IO server with a `/chat` namespace, found at `/socket.io/`.
+///
+/// Sockets joining get a `welcome` and a `notice` event, then a `question`
+/// the server wants an answer to, which it follows with `answered`. Events
+/// asking for an ack get their arguments back in it, others are emitted
+/// back as they came.
+pub async fn spawn_socketio_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let mut endpoint = String::new();
+            // The error type of the callback is set by tungstenite.
+            #[allow(clippy::result_large_err)]
+            let handshake = |request: &Request, response: Response| {
+                endpoint = request.uri().to_string();
+                Ok(response)
+            };
+            let Ok(mut socket) = accept_hdr_async(stream, handshake).await else {
+                continue;
+            };
+            if !endpoint.starts_with("/socket.io/?")
+                || !endpoint.contains("EIO=4&transport=websocket")
+            {
+                let _ = socket.close(None).await;
+                continue;
+            }
+
+            let open = json!({"sid": "engine", "upgrades": [], "pingInterval": 25000, "pingTimeout": 20000, "maxPayload": 1000000});
+            let _ = socket.send(Message::text(format!("0{}", open))).await;
+            let _ = socket.send(Message::text("2")).await;
+
+            while let Some(Ok(Message::Text(text))) = socket.next().await {
+                let replies: Vec<String> = match text.as_str() {
+                    "40/chat," => vec![
+                        format!("40/chat,{}", json!({"sid": "socket"})),
+                        format!("42/chat,{}", json!(["welcome", {"user": "querry"}])),
+                        format!("42/chat,{}", json!(["notice", "hidden"])),
+                        format!("42/chat,7{}", json!(["question", "ready?"])),
+                    ],
+                    "40" => vec![format!("44{}", json!({"message": "Invalid namespace"}))],
+                    "41/chat," => break,
+                    "43/chat,7[]" => vec![format!("42/chat,{}", json!(["answered", 7]))],
+                    text => match text.strip_prefix("42/chat,") {
+                        Some(event) => {
+                            let digits = event.find('[').unwrap_or_default();
+                            match digits {
+                                0 => vec![text.to_string()],
+                                _ => {
+                                    let arguments: Vec<Value> =
+                                        serde_json::from_str(&event[digits..]).unwrap();
+                                    vec![format!(
+                                        "43/chat,{}{}",
+                                        &event[..digits],
+                                        Value::from(arguments[1..].to_vec())
+                                    )]
+                                }
+                            }
+                        }
+                        None => vec![],
+                    },
+                };
+                for reply in replies {
+                    let _ = socket.send(Message::text(reply)).await;
+                }
+            }
+        }
+    });
+
+    format!("http://{}/chat", address)
+}
+
+/// An MQTT-over-WebSocket broker for a single client at a time.
+///
+/// Clients with a user name must log in as `user` with `secret`. Retained
+/// messages are kept across clients, and subscriptions to `refused/#` are
+/// turned down.
+pub async fn spawn_mqtt_broker() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut retained: Vec<Publish> = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            // The error type of the callback is set by tungstenite.
+            #[allow(clippy::result_large_err)]
+            let handshake = |_: &Request, mut response: Response| {
+                response
+                    .headers_mut()
+                    .insert(SEC_WEBSOCKET_PROTOCOL, HeaderValue::from_static("mqtt"));
+                Ok(response)
+            };
+            let Ok(mut socket) = accept_hdr_async(stream, handshake).await else {
+                continue;
+            };
+
+            let mut buffer = Vec::new();
+            let mut subscriptions: Vec<(String, u8)> = Vec::new();
+            let mut last_packet_id = 0;
+            'client: while let Some(Ok(message)) = socket.next().await {
+                buffer.extend_from_slice(&message.into_data());
+                while let Ok(Some((packet, length))) = decode_packet(&buffer) {
+                    buffer.drain(..length);
+                    let mut replies = Vec::new();
+                    match packet {
+                        Packet::Connect(connect) => {
+                            let refused = connect.username.is_some()
+                                && (connect.username.as_deref() != Some("user")
+                                    || connect.password.as_deref() != Some("secret"));
+                            replies.push(Packet::ConnAck {
+                                session_present: false,
+                                code: if refused { 4 } else { 0 },
+                            });
+                        }
+                        Packet::Subscribe { packet_id, filters } => {
+                            let codes = filters
+                                .iter()
+                                .map(|(filter, qos)| match filter.starts_with("refused/") {
+                                    true => 0x80,
+                                    false => *qos,
+                                })
+                                .collect();
+                            replies.push(Packet::SubAck { packet_id, codes });
+                            for (filter, qos) in filters {
+                                if filter.starts_with("refused/") {
+                                    continue;
+                                }
+                                for publish in &retained {
+                                    if topic_matches(&filter, &publish.topic) {
+                                        replies.push(Packet::Publish(Publish {
+                                            qos: 0,
+                                            packet_id: None,
+                                            ..publish.clone()
+                                        }));
+                                    }
+                                }
+                                subscriptions.push((filter, qos));
+                            }
+                        }
+                        Packet::Unsubscribe { packet_id, filters } => {
+                            subscriptions.retain(|(filter, _)| !filters.contains(filter));
+                            replies.push(Packet::UnsubAck(packet_id));
+                        }
+                        Packet::Publish(publish) => {
+                            match (publish.qos, publish.packet_id) {
+                                (1, Some(packet_id)) => replies.push(Packet::PubAck(packet_id)),
+                                (2, Some(packet_id)) => replies.push(Packet::PubRec(packet_id)),
+                                _ => {}
+                            }
+                            if publish.retain {
+                                retained.retain(|kept| kept.topic != publish.topic);
+                                retained.push(publish.clone());
+                            }
+                            let granted = subscriptions
+                                .iter()
+                                .filter(|(filter, _)| topic_matches(filter, &publish.topic))
+                                .map(|(_, qos)| *qos)
+                                .max();
+                            if let Some(granted) = granted {
+                                let qos = publish.qos.min(granted);
+                                let packet_id = match qos {
+                                    0 => None,
+                                    _ => {
+                                        last_packet_id += 1;
+                                        Some(last_packet_id)
+                                    }
+                                };
+                                replies.push(Packet::Publish(Publish {
+                                    qos,
+                                    retain: false,
+                                    packet_id,
+                                    ..publish
+                                }));
+                            }
+                        }
+                        Packet::PubRec(packet_id) => replies.push(Packet::PubRel(packet_id)),
+                        Packet::PubRel(packet_id) => replies.push(Packet::PubComp(packet_id)),
+                        Packet::PingReq => replies.push(Packet::PingResp),
+                        Packet::Disconnect => break 'client,
+                        _ => {}
+                    }
+                    for reply in replies {
+                        let refused = matches!(reply, Packet::ConnAck { code: 4, .. });
+                        let _ = socket.send(Message::binary(reply.encode().unwrap())).await;
+                        if refused {
+                            let _ = socket.close(None).await;
+                            break 'client;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    format!("ws://{}/mqtt", address)
+}
+
+/// The service of the gRPC echo server.
+const ECHO_PROTO: &str = r#"syntax = "proto3";
+
+package echo;
+
+import "echo/types.proto";
+
+service Echo {
+  rpc Say(Shout) returns (Shout);
+  rpc Repeat(Shout) returns (stream Shout);
+  rpc Collect(stream Shout) returns (Shout);
+  rpc Chat(stream Shout) returns (stream Shout);
+}
+"#;
+
+/// The messages of the echo service, imported from a folder of their own.
+const ECHO_TYPES_PROTO: &str = r#"syntax = "proto3";
+
+package echo;
+
+import "google/protobuf/timestamp.proto";
+
+enum Tone {
+  TONE_CALM = 0;
+  TONE_LOUD = 1;
+}
+
+message Shout {
+  string text = 1;
+  int32 times = 2;
+  Tone tone = 3;
+  repeated string tags = 4;
+  map<string, int32> counts = 5;
+  google.protobuf.Timestamp at = 6;
+  int64 total = 7;
+  oneof target {
+    string person = 8;
+    Shout reply_to = 9;
+  }
+}
+"#;
+
+/// Write the .proto files of the echo server into a new temporary folder.
+///
+/// Returns the path of `echo.proto` and the import path its types are
+/// found in.
+pub fn write_echo_protos() -> (String, String) {
+    let root = std::env::temp_dir().join(format!("querry_grpc_{}", Uuid::new_v4()));
+    let protos = root.join("protos");
+    let include = root.join("include");
+    fs::create_dir_all(&protos).unwrap();
+    fs::create_dir_all(include.join("echo")).unwrap();
+    fs::write(protos.join("echo.proto"), ECHO_PROTO).unwrap();
+    fs::write(include.join("echo").join("types.proto"), ECHO_TYPES_PROTO).unwrap();
+
+    (
+        protos.join("echo.proto").to_string_lossy().to_string(),
+        include.to_string_lossy().to_string(),
+    )
+}
+
+type EchoStream = Pin<Box<dyn Stream<Item = Result<DynamicMessage, Status>> + Send>>;
+
+/// The `echo.Echo` service, answering with dynamic messages.
+#[derive(Clone)]
+struct EchoServer {
+    services: DescriptorPool,
+}
+
+impl NamedService for EchoServer {
+    const NAME: &'static str = "echo.Echo";
+}
+
+impl Service<http::Request<Body>> for EchoServer {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let method = find_method(&self.services, request.uri().path()).ok();
+        Box::pin(async move {
+            let Some(method) = method else {
+                return Ok(Status::unimplemented("").into_http());
+            };
+            let mut grpc = Grpc::new(DynamicCodec::new(method.input()));
+            Ok(grpc.streaming(EchoMethod(method), request).await)
+        })
+    }
+}
+
+struct EchoMethod(MethodDescriptor);
+
+fn shout_text(message: &DynamicMessage) -> String {
+    message
+        .get_field_by_name("text")
+        .and_then(|text| text.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl Service<tonic::Request<Streaming<DynamicMessage>>> for EchoMethod {
+    type Response = tonic::Response<EchoStream>;
+    type Error = Status;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: tonic::Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.0.name().to_string();
+        Box::pin(async move {
+            let echo = request.metadata().get("x-echo").cloned();
+            let mut messages = request.into_inner();
+            let replies: EchoStream = match method.as_str() {
+                "Say" | "Repeat" => {
+                    let message = messages
+                        .message()
+                        .await?
+                        .ok_or_else(|| Status::invalid_argument("No message"))?;
+                    if shout_text(&message) == "fail" {
+                        let mut trailers = MetadataMap::new();
+                        trailers.insert("reason", "asked".parse().unwrap());
+                        return Err(Status::with_metadata(
+                            Code::InvalidArgument,
+                            "Asked to fail",
+                            trailers,
+                        ));
+                    }
+                    let times = match method.as_str() {
+                        "Repeat" => message
+                            .get_field_by_name("times")
+                            .and_then(|times| times.as_i32())
+                            .unwrap_or_default(),
+                        _ => 1,
+                    };
+                    Box::pin(stream::iter((0..times).map(move |_| Ok(message.clone()))))
+                }
+                "Collect" => {
+                    let mut texts = Vec::new();
+                    let mut reply = None;
+                    while let Some(message) = messages.message().await? {
+                        texts.push(shout_text(&message));
+                        reply = Some(message);
+                    }
+                    let mut reply = reply.ok_or_else(|| Status::invalid_argument("No messages"))?;
+                    reply.set_field_by_name("text", ProtoValue::String(texts.join(" ")));
+                    reply.set_field_by_name("times", ProtoValue::I32(texts.len() as i32));
+                    Box::pin(stream::iter([Ok(reply)]))
+                }
+                _ => Box::pin(stream::unfold(messages, |mut messages| async move {
+                    let message = messages.message().await.transpose()?;
+                    Some((message, messages))
+                })),
+            };
+
+            let mut response = tonic::Response::new(replies);
+            if let Some(echo) = echo {
+                response.metadata_mut().insert("x-echo", echo);
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Serve the echo service over plain text gRPC until the test ends, along
+/// with server reflection.
+///
+/// `Say` answers with its message and the `x-echo` metadata it got, and
+/// fails with INVALID_ARGUMENT and a `reason` trailer when the text is
+/// "fail". `Repeat` sends the message back `times` times, `Collect` joins
+/// the texts it got, and `Chat` answers every message with itself.
+pub async fn spawn_grpc_server() -> String {
+    let (file, include) = write_echo_protos();
+    let services = compile_protos(&[file], &[include]).unwrap();
+    let descriptors = FileDescriptorSet {
+        file: services.file_descriptor_protos().cloned().collect(),
+    };
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_file_descriptor_set(descriptors)
+        .build_v1()
+        .unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let _ = Server::builder()
+            .add_service(EchoServer { services })
+            .add_service(reflection)
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await;
+    });
+
+    format!("http://{}", address)
+}
@@ -0,0 +1,260 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use sqlx::SqlitePool;
+
+use crate::utils::crud::{
+    collections::get_collection_variables,
+    environments::{get_active_environment_variables, get_global_variables},
+    requests::{get_request_variables, RequestData},
+};
+
+/// Variables referenced with `{{name}}` that have no value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnresolvedVariables(pub Vec<String>);
+
+impl fmt::Display for UnresolvedVariables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self
+            .0
+            .iter()
+            .map(|name| format!("{{{{{}}}}}", name))
+            .collect();
+        write!(f, "Unresolved variables: {}", names.join(", "))
+    }
+}
+
+impl Error for UnresolvedVariables {}
+
+/// Position and trimmed name of every `{{name}}` placeholder in a text.
+fn placeholders(text: &str) -> Vec<(usize, usize, String)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = text[offset..].find("{{").map(|index| index + offset) {
+        let end = match text[start + 2..].find("}}") {
+            Some(index) => start + 2 + index,
+            None => break,
+        };
+        let name = text[start + 2..end].trim();
+        // `{{ }}` and nested braces are left as they are.
+        if name.is_empty() || name.contains('{') {
+            offset = start + 2;
+            continue;
+        }
+        found.push((start, end + 2, name.to_string()));
+        offset = end + 2;
+    }
+    found
+}
+
+/// Names of the variables a text references, without duplicates.
+pub fn find_variables(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (_, _, name) in placeholders(text) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Scopes a variable can be defined in, from the highest precedence to the lowest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VariableScope {
+    Request,
+    Collection,
+    Environment,
+    Global,
+}
+
+/// Looks variables up in every scope they can be defined in.
+///
+/// A name resolves to the value of the first scope that defines it, in this
+/// order:
+///
+/// 1. variables of the request itself,
+/// 2. variables of the request's collection,
+/// 3. variables of the active environment,
+/// 4. global variables.
+///
+/// Disabled and nameless variables are left out of every scope.
+#[derive(Clone, Debug, Default)]
+pub struct VariableResolver {
+    pub request: HashMap<String, String>,
+    pub collection: HashMap<String, String>,
+    pub environment: HashMap<String, String>,
+    pub global: HashMap<String, String>,
+}
+
+/// Enabled variables by trimmed name, later rows winning over earlier ones.
+fn to_scope(
+    variables: impl IntoIterator<Item = (String, String, bool)>,
+) -> HashMap<String, String> {
+    variables
+        .into_iter()
+        .filter(|(name, _, enabled)| *enabled && !name.trim().is_empty())
+        .map(|(name, value, _)| (name.trim().to_string(), value))
+        .collect()
+}
+
+impl VariableResolver {
+    /// Load every scope visible to a stored request.
+    pub async fn for_request(
+        request: &RequestData,
+        pool: &SqlitePool,
+    ) -> Result<VariableResolver, Box<dyn Error>> {
+        let request_variables = get_request_variables(&request.id, pool)
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value, variable.enabled));
+
+        Ok(VariableResolver {
+            request: to_scope(request_variables),
+            ..VariableResolver::for_collection(&request.collection_id, pool).await?
+        })
+    }
+
+    /// Load every scope visible to a collection, leaving the request scope empty.
+    pub async fn for_collection(
+        collection_id: &str,
+        pool: &SqlitePool,
+    ) -> Result<VariableResolver, Box<dyn Error>> {
+        let collection_variables = get_collection_variables(collection_id, pool)
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value, variable.enabled));
+        let global_variables = get_global_variables(pool)
+            .await?
+            .into_iter()
+            .map(|variable| (variable.name, variable.value, variable.enabled));
+
+        Ok(VariableResolver {
+            request: HashMap::new(),
+            collection: to_scope(collection_variables),
+            environment: get_active_environment_variables(pool).await?,
+            global: to_scope(global_variables),
+        })
+    }
+
+    /// Value of a variable together with the scope it was found in.
+    pub fn lookup(&self, name: &str) -> Option<(VariableScope, &str)> {
+        [
+            (VariableScope::Request, &self.request),
+            (VariableScope::Collection, &self.collection),
+            (VariableScope::Environment, &self.environment),
+            (VariableScope::Global, &self.global),
+        ]
+        .into_iter()
+        .find_map(|(scope, variables)| variables.get(name).map(|value| (scope, value.as_str())))
+    }
+
+    pub fn resolve(&self, name: &str) -> Option<&str> {
+        self.lookup(name).map(|(_, value)| value)
+    }
+
+    /// Replace `{{name}}` placeholders with their values.
+    ///
+    /// Placeholders without a value are kept as they are and their names are
+    /// added to `unresolved`, so every field of a request can be checked
+    /// before reporting.
+    pub fn substitute(&self, text: &str, unresolved: &mut Vec<String>) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for (start, end, name) in placeholders(text) {
+            result.push_str(&text[last..start]);
+            match self.resolve(&name) {
+                Some(value) => result.push_str(value),
+                None => {
+                    result.push_str(&text[start..end]);
+                    if !unresolved.contains(&name) {
+                        unresolved.push(name);
+                    }
+                }
+            }
+            last = end;
+        }
+        result.push_str(&text[last..]);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> VariableResolver {
+        VariableResolver {
+            environment: HashMap::from([
+                ("host".to_string(), "localhost:8080".to_string()),
+                ("token".to_string(), "abc".to_string()),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_substitute_variables() {
+        let mut unresolved = Vec::new();
+        let text =
+            variables().substitute("http://{{host}}/users?token={{ token }}", &mut unresolved);
+
+        assert!(text == "http://localhost:8080/users?token=abc");
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_unresolved_variables_are_kept_and_reported() {
+        let mut unresolved = Vec::new();
+        let text = variables().substitute(
+            "{{host}}/{{version}}/{{version}}/{{missing}}",
+            &mut unresolved,
+        );
+
+        assert!(text == "localhost:8080/{{version}}/{{version}}/{{missing}}");
+        assert!(unresolved == vec!["version".to_string(), "missing".to_string()]);
+        assert!(
+            UnresolvedVariables(unresolved).to_string()
+                == "Unresolved variables: {{version}}, {{missing}}"
+        );
+    }
+
+    #[test]
+    fn test_variable_precedence() {
+        let scope = |value: &str| HashMap::from([("baseUrl".to_string(), value.to_string())]);
+        let mut resolver = VariableResolver {
+            request: scope("request"),
+            collection: scope("collection"),
+            environment: scope("environment"),
+            global: scope("global"),
+        };
+
+        assert!(resolver.lookup("baseUrl") == Some((VariableScope::Request, "request")));
+        resolver.request.clear();
+        assert!(resolver.lookup("baseUrl") == Some((VariableScope::Collection, "collection")));
+        resolver.collection.clear();
+        assert!(resolver.lookup("baseUrl") == Some((VariableScope::Environment, "environment")));
+        resolver.environment.clear();
+        assert!(resolver.resolve("baseUrl") == Some("global"));
+        resolver.global.clear();
+        assert!(resolver.resolve("baseUrl").is_none());
+    }
+
+    #[test]
+    fn test_disabled_variables_are_not_in_scope() {
+        let scope = to_scope(vec![
+            ("host".to_string(), "a".to_string(), true),
+            (" host ".to_string(), "b".to_string(), true),
+            ("token".to_string(), "c".to_string(), false),
+            ("".to_string(), "d".to_string(), true),
+        ]);
+
+        assert!(scope == HashMap::from([("host".to_string(), "b".to_string())]));
+    }
+
+    #[test]
+    fn test_find_variables_ignores_incomplete_placeholders() {
+        assert!(find_variables("{{a}} {{ }} {{b {{c}} {{a}} d}}") == vec!["a", "c"]);
+        assert!(find_variables("{\"json\": {\"nested\": 1}}").is_empty());
+    }
+}
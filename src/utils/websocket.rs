@@ -0,0 +1,608 @@
+//! WebSocket sessions: the handshake, the frames going both ways and the
+//! log they leave behind.
+
+use std::{error::Error, fmt};
+
+use chrono::Local;
+use futures_util::{SinkExt, StreamExt};
+use sqlx::SqlitePool;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{
+            header::{HeaderName, HeaderValue, SEC_WEBSOCKET_PROTOCOL},
+            Request,
+        },
+        protocol::CloseFrame,
+        Bytes, Message,
+    },
+};
+
+use crate::utils::{
+    crud::{
+        requests::RequestData,
+        websocket::{get_websocket_subprotocols, MessageFormats},
+    },
+    executor::{apply_oauth2_token, prepare_request, PreparedRequest},
+    tokio_runtime::runtime,
+};
+
+/// Longest payload written to the log in full. Longer ones are cut short.
+const MAX_LOGGED_CHARS: usize = 16 * 1024;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Directions {
+    Sent,
+    Received,
+    /// Something that happened to the connection rather than a frame.
+    Info,
+}
+
+impl fmt::Display for Directions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Directions::Sent => write!(f, "sent"),
+            Directions::Received => write!(f, "received"),
+            Directions::Info => write!(f, "info"),
+        }
+    }
+}
+
+impl Directions {
+    pub fn from_string(s: &str) -> Option<Directions> {
+        match s {
+            "sent" => Some(Directions::Sent),
+            "received" => Some(Directions::Received),
+            "info" => Some(Directions::Info),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrameKinds {
+    Text,
+    Binary,
+    Ping,
+    Pong,
+    Close,
+    Connected,
+    Disconnected,
+    Error,
+}
+
+impl fmt::Display for FrameKinds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameKinds::Text => write!(f, "text"),
+            FrameKinds::Binary => write!(f, "binary"),
+            FrameKinds::Ping => write!(f, "ping"),
+            FrameKinds::Pong => write!(f, "pong"),
+            FrameKinds::Close => write!(f, "close"),
+            FrameKinds::Connected => write!(f, "connected"),
+            FrameKinds::Disconnected => write!(f, "disconnected"),
+            FrameKinds::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A line of a session log. Protocols carried over WebSocket log their own
+/// kinds of lines, so `K` is theirs; the WebSocket log itself logs frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogEntry<K = FrameKinds> {
+    /// Local time, down to the millisecond.
+    pub time: String,
+    pub direction: Directions,
+    pub kind: K,
+    pub data: String,
+}
+
+/// The current local time as log entries show it, down to the millisecond.
+pub fn log_time() -> String {
+    Local::now().format("%H:%M:%S%.3f").to_string()
+}
+
+impl<K> LogEntry<K> {
+    pub fn new(direction: Directions, kind: K, data: &str) -> LogEntry<K> {
+        LogEntry {
+            time: log_time(),
+            direction,
+            kind,
+            data: data.to_string(),
+        }
+    }
+}
+
+impl LogEntry {
+    fn for_message(direction: Directions, message: &Message) -> Option<LogEntry> {
+        let (kind, data) = describe_message(message)?;
+        Some(LogEntry::new(direction, kind, &data))
+    }
+}
+
+/// Which log entries to show. An empty text and no direction show them all.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LogFilter {
+    /// Matched case-insensitively against the data and the frame kind.
+    pub text: String,
+    pub direction: Option<Directions>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        let text = self.text.trim().to_lowercase();
+        self.direction
+            .is_none_or(|direction| direction == entry.direction)
+            && (text.is_empty()
+                || entry.data.to_lowercase().contains(&text)
+                || entry.kind.to_string() == text)
+    }
+}
+
+/// Bytes written as space separated hex pairs.
+pub fn hex_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Read hex typed by the user. Whitespace between the digits is ignored.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digits: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("Binary messages are written as pairs of hex digits.".into());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("\"{}\" is not hex.", String::from_utf8_lossy(pair)).into())
+        })
+        .collect()
+}
+
+/// Turn what the user typed into the frame it is sent as.
+pub fn encode_message(format: &MessageFormats, payload: &str) -> Result<Message, Box<dyn Error>> {
+    match format {
+        MessageFormats::Text => Ok(Message::text(payload)),
+        MessageFormats::Json => {
+            serde_json::from_str::<serde_json::Value>(payload)
+                .map_err(|error| format!("Not valid JSON: {}", error))?;
+            Ok(Message::text(payload))
+        }
+        MessageFormats::Binary => Ok(Message::binary(parse_hex(payload)?)),
+    }
+}
+
+/// Text cut down to what the log keeps.
+pub fn shorten(mut text: String) -> String {
+    if let Some((index, _)) = text.char_indices().nth(MAX_LOGGED_CHARS) {
+        let length = text.len();
+        text.truncate(index);
+        text.push_str(&format!("… ({} bytes in total)", length));
+    }
+    text
+}
+
+/// Payload of a control frame, as text when it is text.
+fn control_payload(payload: &Bytes) -> String {
+    match std::str::from_utf8(payload) {
+        Ok(text) => text.to_string(),
+        Err(_) => hex_text(payload),
+    }
+}
+
+/// What a frame is and what it carries, as shown in the log. Raw frames
+/// are never read or written by a session and have no description.
+pub fn describe_message(message: &Message) -> Option<(FrameKinds, String)> {
+    let description = match message {
+        Message::Text(text) => (FrameKinds::Text, shorten(text.as_str().to_string())),
+        Message::Binary(bytes) => (FrameKinds::Binary, shorten(hex_text(bytes))),
+        Message::Ping(payload) => (FrameKinds::Ping, control_payload(payload)),
+        Message::Pong(payload) => (FrameKinds::Pong, control_payload(payload)),
+        Message::Close(Some(CloseFrame { code, reason })) => match reason.is_empty() {
+            true => (FrameKinds::Close, u16::from(*code).to_string()),
+            false => (
+                FrameKinds::Close,
+                format!("{} {}", u16::from(*code), reason),
+            ),
+        },
+        Message::Close(None) => (FrameKinds::Close, String::new()),
+        Message::Frame(_) => return None,
+    };
+    Some(description)
+}
+
+/// Subprotocols typed comma separated, in the order they are offered.
+pub fn parse_subprotocols(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|protocol| protocol.trim().to_string())
+        .filter(|protocol| !protocol.is_empty())
+        .collect()
+}
+
+/// The `ws://` or `wss://` URL of a request. HTTP URLs are switched over,
+/// as servers often document their sockets with them.
+pub fn websocket_url(url: &str) -> Result<String, Box<dyn Error>> {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let scheme = match scheme.to_ascii_lowercase().as_str() {
+        "ws" | "http" => "ws",
+        "wss" | "https" => "wss",
+        _ => return Err("WebSocket URLs start with ws:// or wss://".into()),
+    };
+
+    Ok(format!("{}://{}", scheme, rest))
+}
+
+/// What the opening handshake of a session is made of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Handshake {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub subprotocols: Vec<String>,
+}
+
+impl Handshake {
+    /// The handshake for a prepared request, sent to its URL with its headers.
+    pub fn for_request(
+        request: &PreparedRequest,
+        subprotocols: Vec<String>,
+    ) -> Result<Handshake, Box<dyn Error>> {
+        Ok(Handshake {
+            url: websocket_url(&request.url)?,
+            headers: request
+                .headers
+                .iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+                .cloned()
+                .collect(),
+            subprotocols,
+        })
+    }
+
+    pub fn to_request(&self) -> Result<Request<()>, Box<dyn Error + Send + Sync>> {
+        let mut request = self.url.as_str().into_client_request()?;
+        for (name, value) in &self.headers {
+            request.headers_mut().append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        if !self.subprotocols.is_empty() {
+            request.headers_mut().insert(
+                SEC_WEBSOCKET_PROTOCOL,
+                HeaderValue::from_str(&self.subprotocols.join(", "))?,
+            );
+        }
+        Ok(request)
+    }
+}
+
+/// The handshake of a stored request, with its variables substituted.
+///
+/// The request's headers and auth go into the handshake just as they would
+/// go into an HTTP request. Its body is never sent.
+pub async fn prepare_handshake(
+    request: &RequestData,
+    pool: &SqlitePool,
+) -> Result<Handshake, Box<dyn Error>> {
+    let prepared = apply_oauth2_token(prepare_request(request, pool).await?, pool).await?;
+    let subprotocols = get_websocket_subprotocols(&request.id, pool).await?;
+
+    Handshake::for_request(&prepared, parse_subprotocols(&subprotocols))
+}
+
+/// An open connection. Dropping it closes the connection.
+pub struct WebSocketSession {
+    commands: UnboundedSender<Message>,
+}
+
+impl WebSocketSession {
+    /// Queue a frame. What happens to it shows up in the log.
+    pub fn send(&self, message: Message) -> Result<(), Box<dyn Error>> {
+        self.commands
+            .send(message)
+            .map_err(|_| "The connection is closed.".into())
+    }
+
+    /// Start the closing handshake.
+    pub fn close(&self) {
+        let _ = self.commands.send(Message::Close(None));
+    }
+}
+
+/// A session and the log entries it produces, starting with the
+/// connection being opened. The log ends when the connection is gone.
+pub struct Connection {
+    pub session: WebSocketSession,
+    pub log: UnboundedReceiver<LogEntry>,
+    /// The subprotocol the server picked, empty when it picked none.
+    pub protocol: String,
+}
+
+/// Open a session.
+///
+/// Slint callbacks run on the UI thread, so the connection lives on the
+/// runtime and only the log entries are awaited on the UI side.
+pub async fn connect(handshake: Handshake) -> Result<Connection, Box<dyn Error>> {
+    let connection = runtime()
+        .spawn(async move { open_session(handshake).await })
+        .await?
+        .map_err(|error| error.to_string())?;
+
+    Ok(connection)
+}
+
+async fn open_session(handshake: Handshake) -> Result<Connection, Box<dyn Error + Send + Sync>> {
+    let (stream, response) = connect_async(handshake.to_request()?).await?;
+    let protocol = response
+        .headers()
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    let (commands, mut queued) = unbounded_channel::<Message>();
+    let (log, entries) = unbounded_channel();
+    let connected = match protocol.is_empty() {
+        true => handshake.url.clone(),
+        false => format!("{} ({})", handshake.url, protocol),
+    };
+    let _ = log.send(LogEntry::new(
+        Directions::Info,
+        FrameKinds::Connected,
+        &connected,
+    ));
+
+    tokio::spawn(async move {
+        let (mut sink, mut stream) = stream.split();
+        // Once closing, only the answer of the server is waited for.
+        let mut closing = false;
+        loop {
+            tokio::select! {
+                command = queued.recv(), if !closing => {
+                    // A dropped session closes the connection.
+                    let message = command.unwrap_or(Message::Close(None));
+                    closing = matches!(message, Message::Close(_));
+                    let entry = LogEntry::for_message(Directions::Sent, &message);
+                    match sink.send(message).await {
+                        Ok(()) => {
+                            if let Some(entry) = entry {
+                                let _ = log.send(entry);
+                            }
+                        }
+                        Err(error) => {
+                            let _ = log.send(LogEntry::new(
+                                Directions::Info,
+                                FrameKinds::Error,
+                                &error.to_string(),
+                            ));
+                            break;
+                        }
+                    }
+                }
+                incoming = stream.next() => match incoming {
+                    Some(Ok(message)) => {
+                        if let Some(entry) = LogEntry::for_message(Directions::Received, &message) {
+                            let _ = log.send(entry);
+                        }
+                        // Pings are answered by the connection itself.
+                        if let Message::Ping(payload) = message {
+                            let _ = log.send(LogEntry::new(
+                                Directions::Sent,
+                                FrameKinds::Pong,
+                                &control_payload(&payload),
+                            ));
+                        }
+                    }
+                    Some(Err(error)) => {
+                        let _ = log.send(LogEntry::new(
+                            Directions::Info,
+                            FrameKinds::Error,
+                            &error.to_string(),
+                        ));
+                        break;
+                    }
+                    None => break,
+                },
+            }
+        }
+        let _ = log.send(LogEntry::new(
+            Directions::Info,
+            FrameKinds::Disconnected,
+            "",
+        ));
+    });
+
+    Ok(Connection {
+        session: WebSocketSession { commands },
+        log: entries,
+        protocol,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::setup_test_db,
+        utils::{
+            crud::{
+                collections::create_collection,
+                requests::{
+                    create_request, create_request_header, update_request_item, HTTPMethods,
+                    ProtocolTypes,
+                },
+                websocket::update_websocket_subprotocols,
+            },
+            test_server::spawn_websocket_echo_server,
+        },
+    };
+
+    async fn next_entry(connection: &mut Connection) -> LogEntry {
+        connection.log.recv().await.expect("The log ended early.")
+    }
+
+    #[test]
+    fn test_encode_message() {
+        assert!(encode_message(&MessageFormats::Text, "hello").unwrap() == Message::text("hello"));
+        assert!(
+            encode_message(&MessageFormats::Json, "{\"a\": 1}").unwrap()
+                == Message::text("{\"a\": 1}")
+        );
+        assert!(encode_message(&MessageFormats::Json, "{\"a\":").is_err());
+        assert!(
+            encode_message(&MessageFormats::Binary, "01 ff\n0A").unwrap()
+                == Message::binary(vec![0x01, 0xff, 0x0a])
+        );
+        assert!(encode_message(&MessageFormats::Binary, "0").is_err());
+        assert!(encode_message(&MessageFormats::Binary, "zz").is_err());
+
+        let (kind, data) = describe_message(&Message::binary(vec![0x01, 0xff])).unwrap();
+        assert!(kind == FrameKinds::Binary && data == "01 ff");
+        let long = "a".repeat(MAX_LOGGED_CHARS + 10);
+        let (_, data) = describe_message(&Message::text(long)).unwrap();
+        assert!(data.ends_with(&format!("… ({} bytes in total)", MAX_LOGGED_CHARS + 10)));
+    }
+
+    #[test]
+    fn test_websocket_url() {
+        assert!(websocket_url("ws://localhost:8080/chat").unwrap() == "ws://localhost:8080/chat");
+        assert!(websocket_url("https://example.com/socket").unwrap() == "wss://example.com/socket");
+        assert!(websocket_url("HTTP://example.com").unwrap() == "ws://example.com");
+        assert!(websocket_url("ftp://example.com").is_err());
+        assert!(websocket_url("example.com").is_err());
+        assert!(parse_subprotocols(" graphql-ws, ,mqtt ") == vec!["graphql-ws", "mqtt"]);
+    }
+
+    #[test]
+    fn test_log_filter() {
+        let sent = LogEntry::new(Directions::Sent, FrameKinds::Text, "Hello there");
+        let received = LogEntry::new(Directions::Received, FrameKinds::Pong, "");
+
+        assert!(LogFilter::default().matches(&sent) && LogFilter::default().matches(&received));
+        let hello = LogFilter {
+            text: "hello".to_string(),
+            direction: None,
+        };
+        assert!(hello.matches(&sent) && !hello.matches(&received));
+        let pongs = LogFilter {
+            text: "pong".to_string(),
+            direction: Some(Directions::Received),
+        };
+        assert!(pongs.matches(&received) && !pongs.matches(&sent));
+        let only_sent = LogFilter {
+            text: String::new(),
+            direction: Some(Directions::Sent),
+        };
+        assert!(only_sent.matches(&sent) && !only_sent.matches(&received));
+        assert!(
+            LogEntry::new(Directions::Info, FrameKinds::Close, "")
+                .time
+                .len()
+                == 12
+        );
+    }
+
+    #[tokio::test]
+    async fn test_echo_session() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let (url, handshake_headers) = spawn_websocket_echo_server().await;
+        let collection = create_collection("Sockets".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Websocket, &collection.id, &db)
+            .await
+            .unwrap();
+        let request = update_request_item(
+            &request.id,
+            &request.name,
+            ProtocolTypes::Websocket,
+            HTTPMethods::Get,
+            &url,
+            &db,
+        )
+        .await
+        .unwrap();
+        create_request_header(&request.id, "X-Token", "secret", &db)
+            .await
+            .unwrap();
+        update_websocket_subprotocols(&request.id, "chat, echo", &db)
+            .await
+            .unwrap();
+
+        let handshake = prepare_handshake(&request, &db).await.unwrap();
+        let mut connection = connect(handshake).await.unwrap();
+        assert!(connection.protocol == "echo");
+        let connected = next_entry(&mut connection).await;
+        assert!(connected.kind == FrameKinds::Connected && connected.data.ends_with("(echo)"));
+        let headers = handshake_headers.await.unwrap();
+        assert!(headers.contains(&("x-token".to_string(), "secret".to_string())));
+        assert!(headers.contains(&(
+            "sec-websocket-protocol".to_string(),
+            "chat, echo".to_string()
+        )));
+
+        let session = &connection.session;
+        session
+            .send(encode_message(&MessageFormats::Json, "{\"hello\":1}").unwrap())
+            .unwrap();
+        session
+            .send(encode_message(&MessageFormats::Binary, "de ad").unwrap())
+            .unwrap();
+        session.send(Message::Ping(Bytes::from("check"))).unwrap();
+
+        let mut entries = Vec::new();
+        for _ in 0..6 {
+            let entry = next_entry(&mut connection).await;
+            entries.push((entry.direction, entry.kind, entry.data));
+        }
+        for expected in [
+            (Directions::Sent, FrameKinds::Text, "{\"hello\":1}"),
+            (Directions::Received, FrameKinds::Text, "{\"hello\":1}"),
+            (Directions::Sent, FrameKinds::Binary, "de ad"),
+            (Directions::Received, FrameKinds::Binary, "de ad"),
+            (Directions::Sent, FrameKinds::Ping, "check"),
+            (Directions::Received, FrameKinds::Pong, "check"),
+        ] {
+            assert!(entries.contains(&(expected.0, expected.1, expected.2.to_string())));
+        }
+
+        connection.session.close();
+        let mut kinds = Vec::new();
+        while let Some(entry) = connection.log.recv().await {
+            kinds.push((entry.direction, entry.kind));
+        }
+        assert!(kinds.contains(&(Directions::Sent, FrameKinds::Close)));
+        assert!(kinds.last() == Some(&(Directions::Info, FrameKinds::Disconnected)));
+        assert!(connection.session.send(Message::text("late")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_handshake() {
+        let db = setup_test_db().await.expect("Cant setup db.");
+        let collection = create_collection("Sockets".to_string(), &db).await.unwrap();
+        let request = create_request(ProtocolTypes::Websocket, &collection.id, &db)
+            .await
+            .unwrap();
+        assert!(prepare_handshake(&request, &db).await.is_err());
+
+        // Nothing listens on the port of a server that was just dropped.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        let handshake = Handshake {
+            url,
+            headers: Vec::new(),
+            subprotocols: Vec::new(),
+        };
+        assert!(connect(handshake).await.is_err());
+    }
+}